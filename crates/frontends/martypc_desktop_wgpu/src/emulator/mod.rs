@@ -131,9 +131,8 @@ impl Emulator {
             self.config.machine.cpu.service_interrupt.unwrap_or(false),
         ));
 
-        // TODO: Re-enable these
-        //gui.set_option(GuiBoolean::EnableSnow, config.machine.cga_snow.unwrap_or(false));
-        //machine.set_video_option(VideoOption::EnableSnow(config.machine.cga_snow.unwrap_or(false)));
+        // CGA snow is configured per video card, and applied when the card is created.
+        // TODO: Re-enable this
         //gui.set_option(GuiBoolean::CorrectAspect, config.emulator.scaler_aspect_correction);
 
         //if config.emulator.scaler_aspect_correction {
@@ -209,6 +208,8 @@ impl Emulator {
             .set_cpu_option(CpuOption::TraceLoggingEnabled(self.config.machine.cpu.trace_on));
 
         self.gui.set_option(GuiBoolean::TurboButton, self.config.machine.turbo);
+        // The machine may have refused fast boot, so show its state rather than the config's.
+        self.gui.set_option(GuiBoolean::FastBoot, self.machine.get_fast_boot());
 
         self.gui.set_scaler_presets(&self.config.emulator.scaler_preset);
 
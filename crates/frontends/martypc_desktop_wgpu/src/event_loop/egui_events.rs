@@ -35,7 +35,12 @@ use fluxfox::{DiskImage, LoadingStatus};
 use marty_core::{
     breakpoints::BreakPointType,
     cpu_common,
-    cpu_common::{Cpu, CpuOption, Register16},
+    cpu_common::{
+        symbols::{parse_symbol_file, to_symbol_file, SymbolTable},
+        Cpu,
+        CpuOption,
+        Register16,
+    },
     device_traits::videocard::ClockingMode,
     device_types::fdc::FloppyImageType,
     machine::{MachineOption, MachineState},
@@ -51,10 +56,12 @@ use marty_egui::{
     GuiEvent,
     GuiVariable,
     GuiVariableContext,
+    GuiWindow,
     InputFieldChangeSource,
 };
 use marty_frontend_common::{
     constants::{LONG_NOTIFICATION_TIME, NORMAL_NOTIFICATION_TIME, SHORT_NOTIFICATION_TIME},
+    savestate_manager,
     types::floppy::FloppyImageSource,
 };
 use marty_videocard_renderer::AspectCorrectionMode;
@@ -80,6 +87,44 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
             // User wants to crash the computer. Sure, why not.
             emu.machine.set_nmi(*state);
         }
+        GuiEvent::FillMemory { start, len, value } => {
+            let (written, skipped) = emu.machine.fill_memory(*start, *len, *value);
+            log::debug!(
+                "Filled {} bytes at {:05X} with {:02X}, skipped {}",
+                written,
+                start,
+                value,
+                skipped
+            );
+            if skipped > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!(
+                        "Filled {} bytes. Skipped {} bytes of read-only or unmapped memory.",
+                        written, skipped
+                    ))
+                    .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            else {
+                emu.gui
+                    .toasts()
+                    .info(format!("Filled {} bytes at {:05X}", written, start))
+                    .set_duration(Some(SHORT_NOTIFICATION_TIME));
+            }
+        }
+        GuiEvent::InjectInterrupt(irq) => {
+            // Only inject while paused, so the request is serviced at a deterministic point.
+            if !emu.exec_control.borrow().get_state().can_step() {
+                emu.gui
+                    .toasts()
+                    .error("IRQs can only be injected while the machine is paused.".to_string())
+                    .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+                return;
+            }
+            if emu.machine.inject_interrupt(*irq) {
+                log::debug!("Injected IRQ {}", irq);
+            }
+        }
         // Gui variables have a context, which is sort of like a namespace so that multiple versions
         // of a single GuiEnum can be stored - for example we have a Context per configured Display
         // target. A Global context is used if only a single instance of any GuiEnum is required.
@@ -91,12 +136,21 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                 (GuiBoolean::CpuInstructionHistory, state) => {
                     emu.machine.set_cpu_option(CpuOption::InstructionHistory(state));
                 }
+                (GuiBoolean::CpuInstructionCoverage, state) => {
+                    emu.machine.set_cpu_option(CpuOption::InstructionCoverage(state));
+                }
                 (GuiBoolean::CpuTraceLoggingEnabled, state) => {
                     emu.machine.set_cpu_option(CpuOption::TraceLoggingEnabled(state));
                 }
                 (GuiBoolean::TurboButton, state) => {
                     emu.machine.set_turbo_mode(state);
                 }
+                (GuiBoolean::FastBoot, state) => {
+                    emu.machine.set_fast_boot(state);
+                }
+                (GuiBoolean::BreakOnMemoryEdit, state) => {
+                    emu.machine.set_option(MachineOption::BreakOnDebugWrite(state));
+                }
                 _ => {}
             },
             GuiVariable::Enum(op) => match ctx {
@@ -149,6 +203,11 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                             renderer.set_composite(*state);
                         }
                     }
+                    GuiEnum::DisplayPhosphor(phosphor) => {
+                        if let Some(renderer) = emu.dm.renderer_mut(*d_idx) {
+                            renderer.set_mono_phosphor(*phosphor);
+                        }
+                    }
                     GuiEnum::DisplayAspectCorrect(state) => {
                         if let Err(_e) = emu.dm.set_aspect_correction(*d_idx, *state) {
                             log::error!("Failed to set aspect correction state for display target!");
@@ -162,7 +221,12 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                     }
                     _ => {}
                 },
-                GuiVariableContext::Global => {}
+                GuiVariableContext::Global => match op {
+                    GuiEnum::CpuHistoryDepth(depth) => {
+                        emu.machine.set_cpu_option(CpuOption::InstructionHistoryDepth(*depth));
+                    }
+                    _ => {}
+                },
             },
         },
         GuiEvent::LoadVHD(drive_idx, image_idx) => {
@@ -298,8 +362,8 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                                     .info(format!("Cartridge inserted: {:?}", name.clone()))
                                     .set_duration(Some(NORMAL_NOTIFICATION_TIME));
 
-                                // Inserting a cartridge reboots the machine due to a switch in the cartridge slot.
-                                reboot = true;
+                                // Inserting a cartridge reboots the machine due to a switch in the cartridge slot, if enabled.
+                                reboot = emu.config.emulator.media.cartridge_reset;
                             }
                             Err(err) => {
                                 log::error!("Cart image failed to load into slot {}: {}", slot_select, err);
@@ -327,6 +391,15 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
         GuiEvent::RemoveCartridge(slot_select) => {
             log::info!("Removing cartridge from slot: {}", slot_select);
 
+            // Without a hot-swap reset, a cartridge can only be removed while the machine is off.
+            if emu.machine.get_state().is_on() && !emu.config.emulator.media.cartridge_reset {
+                emu.gui
+                    .toasts()
+                    .error("Cartridge can only be removed while the machine is off.".to_string())
+                    .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+                return;
+            }
+
             let mut reboot = false;
             if let Some(cart_slot) = emu.machine.cart_slot() {
                 cart_slot.remove_cart(*slot_select);
@@ -336,7 +409,7 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                     .info("Cartridge removed!".to_string())
                     .set_duration(Some(SHORT_NOTIFICATION_TIME));
 
-                reboot = true;
+                reboot = emu.config.emulator.media.cartridge_reset;
             }
             if reboot {
                 emu.machine.change_state(MachineState::Rebooting);
@@ -653,6 +726,175 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                     None
                 });
         }
+        GuiEvent::SaveState(slot) => match savestate_manager::save_state(&mut emu.machine, &mut emu.rm, *slot) {
+            Ok(path) => {
+                emu.gui
+                    .toasts()
+                    .info(format!("Machine state saved: {:?}", path))
+                    .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            Err(e) => {
+                log::error!("Failed to save machine state: {}", e);
+                emu.gui
+                    .toasts()
+                    .error(format!("Failed to save machine state: {e}"))
+                    .set_duration(Some(LONG_NOTIFICATION_TIME));
+            }
+        },
+        GuiEvent::LoadState(slot) => match savestate_manager::load_state(&mut emu.machine, &emu.rm, *slot) {
+            Ok(path) => {
+                emu.gui
+                    .toasts()
+                    .info(format!("Machine state restored: {:?}", path))
+                    .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            Err(e) => {
+                log::error!("Failed to restore machine state: {}", e);
+                emu.gui
+                    .toasts()
+                    .error(format!("Failed to restore machine state: {e}"))
+                    .set_duration(Some(LONG_NOTIFICATION_TIME));
+            }
+        },
+        GuiEvent::DumpCoverage => {
+            let report = emu.machine.cpu().coverage_report();
+            log::info!(
+                "Instruction coverage: {} distinct shapes, {} untouched primary opcodes",
+                report.shapes.len(),
+                report.untouched_opcodes.len()
+            );
+            match emu
+                .rm
+                .get_available_filename("dump", "coverage", Some("csv"))
+                .map_err(|e| e.to_string())
+                .and_then(|path| {
+                    std::fs::write(&path, report.to_csv())
+                        .map(|_| path)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!(
+                            "Coverage report saved: {:?} ({} untouched opcodes)",
+                            path,
+                            report.untouched_opcodes.len()
+                        ))
+                        .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to save coverage report: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to save coverage report: {e}"))
+                        .set_duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::DumpCoverageBitmap => {
+            let summary = emu.machine.bus().exec_coverage_summary();
+            for (segment, covered) in summary.iter().enumerate().filter(|(_, covered)| **covered > 0) {
+                log::info!("Executed bytes in segment {:X}000: {}", segment, covered);
+            }
+            let covered: usize = summary.iter().sum();
+            match emu
+                .rm
+                .get_available_filename("dump", "coverage", Some("bin"))
+                .map_err(|e| e.to_string())
+                .and_then(|path| {
+                    std::fs::write(&path, emu.machine.bus().exec_coverage())
+                        .map(|_| path)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!(
+                            "Coverage bitmap saved: {:?} ({} bytes executed)",
+                            path, covered
+                        ))
+                        .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to save coverage bitmap: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to save coverage bitmap: {e}"))
+                        .set_duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ExportSymbols => {
+            let symbols = emu.machine.cpu().export_discovered_symbols();
+            match emu
+                .rm
+                .get_available_filename("dump", "symbols", Some("map"))
+                .map_err(|e| e.to_string())
+                .and_then(|path| {
+                    std::fs::write(&path, to_symbol_file(&symbols))
+                        .map(|_| path)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!("Exported {} symbols: {:?}", symbols.len(), path))
+                        .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to export symbols: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to export symbols: {e}"))
+                        .set_duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ImportSymbols => {
+            // Import the most recently modified symbol file, which is the one the user just edited.
+            let latest = emu
+                .rm
+                .resource_path("dump")
+                .ok_or_else(|| "Resource path not found: dump".to_string())
+                .and_then(|dir| std::fs::read_dir(dir).map_err(|e| e.to_string()))
+                .and_then(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("map")))
+                        .max_by_key(|path| {
+                            std::fs::metadata(path)
+                                .and_then(|m| m.modified())
+                                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+                        })
+                        .ok_or_else(|| "No symbol files found".to_string())
+                });
+            match latest.and_then(|path| {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| parse_symbol_file(&text))
+                    .map(|symbols| (path, symbols))
+            }) {
+                Ok((path, symbols)) => {
+                    let count = symbols.len();
+                    emu.machine.cpu_mut().set_symbols(SymbolTable::from(symbols));
+                    emu.gui
+                        .toasts()
+                        .info(format!("Imported {} symbols: {:?}", count, path))
+                        .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to import symbols: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to import symbols: {e}"))
+                        .set_duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ClearCoverage => {
+            emu.machine.cpu_mut().clear_coverage();
+        }
         GuiEvent::EditBreakpoint => {
             // Get breakpoints from GUI
             let bp_set = emu.gui.get_breakpoints();
@@ -678,7 +920,11 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
             // Push int breakpoint to list
             if let Ok(iv) = u32::from_str_radix(bp_set.int_breakpoint, 10) {
                 if iv < 256 {
-                    breakpoints.push(BreakPointType::Interrupt(iv as u8));
+                    // Filter on AH if a valid function number is given.
+                    match u8::from_str_radix(bp_set.int_ah_filter, 16) {
+                        Ok(ah) => breakpoints.push(BreakPointType::InterruptFunc { int: iv as u8, ah }),
+                        Err(_) => breakpoints.push(BreakPointType::Interrupt(iv as u8)),
+                    }
                 }
             }
 
@@ -689,6 +935,12 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                 breakpoints.push(BreakPointType::IoAccess(port));
             }
 
+            // Push raster line breakpoint to list
+            if let Ok(line) = u16::from_str_radix(bp_set.raster_breakpoint, 10) {
+                log::debug!("Adding raster line breakpoint: {}", line);
+                breakpoints.push(BreakPointType::RasterLine(line));
+            }
+
             // Push stopwatches to list
             if let Some(addr) = emu.machine.cpu().eval_address(bp_set.sw_start) {
                 let start_flat_addr = u32::from(addr);
@@ -727,6 +979,25 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
                 emu.gui.memory_viewer.set_address(mem_dump_addr as usize);
             }
         }
+        GuiEvent::MemoryEdit(addr, data) => {
+            // The user has changed memory in the memory viewer.
+            let force = emu.gui.memory_viewer.force_rom_writes;
+            let (_written, rejected) = emu.machine.edit_memory(*addr as u32, data, force);
+            if rejected > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!("{} byte(s) not written: ROM or unpopulated memory", rejected))
+                    .set_duration(Some(SHORT_NOTIFICATION_TIME));
+            }
+        }
+        GuiEvent::UndoMemoryEdit => {
+            if !emu.machine.undo_memory_change() {
+                emu.gui
+                    .toasts()
+                    .warning("No memory change to undo.".to_string())
+                    .set_duration(Some(SHORT_NOTIFICATION_TIME));
+            }
+        }
         GuiEvent::TokenHover(addr) => {
             // Hovered over a token in a TokenListView.
             let cpu_type = emu.machine.cpu().get_type();
@@ -806,6 +1077,18 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
         GuiEvent::CtrlAltDel => {
             emu.machine.emit_ctrl_alt_del();
         }
+        GuiEvent::TypeText(text) => {
+            let skipped = emu.machine.inject_text(text);
+            if skipped > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!("{} character(s) could not be typed", skipped))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+        }
+        GuiEvent::ClearPostCodes => {
+            emu.machine.clear_post_codes();
+        }
         GuiEvent::CompositeAdjust(dt_idx, params) => {
             //log::warn!("got composite params: {:?}", params);
             emu.dm.with_renderer(*dt_idx, |renderer| {
@@ -832,6 +1115,16 @@ pub fn handle_egui_event(emu: &mut Emulator, elwt: &EventLoopWindowTarget<()>, g
         GuiEvent::StopRecordingDisassembly => {
             emu.machine.set_option(MachineOption::RecordListing(false));
         }
+        GuiEvent::ShowDisassembly(address) => {
+            emu.gui.disassembly_viewer.set_address(address.clone());
+            emu.gui.set_window_open(GuiWindow::DisassemblyViewer, true);
+        }
+        GuiEvent::NavigateDisassembly(address) => {
+            emu.gui.disassembly_viewer.navigate(*address);
+        }
+        GuiEvent::AssembleInstruction(address, text) => {
+            assemble_instruction(emu, address, text);
+        }
         _ => {
             log::warn!("Unhandled GUI event: {:?}", discriminant(gui_event));
         }
@@ -964,3 +1257,44 @@ pub fn handle_load_floppy(emu: &mut Emulator, drive_select: usize, context: File
         }
     }
 }
+
+/// Assemble an instruction entered in the disassembly viewer and write it to memory.
+fn assemble_instruction(emu: &mut Emulator, address_str: &str, text: &str) {
+    let Some(address) = emu.machine.cpu().eval_address(address_str)
+    else {
+        emu.gui
+            .toasts()
+            .error(format!("Invalid address: {}", address_str))
+            .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+        return;
+    };
+
+    match cpu_common::assemble(text, address.offset()) {
+        Ok(bytes) => {
+            let skipped = emu.machine.bus_mut().poke_slice(address.into(), &bytes);
+            if skipped > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!(
+                        "Skipped {} of {} bytes at {}: memory is read-only or unmapped.",
+                        skipped,
+                        bytes.len(),
+                        address
+                    ))
+                    .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            else {
+                log::debug!("Assembled '{}' at {}: {:02X?}", text, address, bytes);
+                emu.gui
+                    .disassembly_viewer
+                    .set_assembled(address.offset_by(bytes.len() as u16));
+            }
+        }
+        Err(e) => {
+            emu.gui
+                .toasts()
+                .error(format!("Couldn't assemble '{}': {}", text, e))
+                .set_duration(Some(NORMAL_NOTIFICATION_TIME));
+        }
+    }
+}
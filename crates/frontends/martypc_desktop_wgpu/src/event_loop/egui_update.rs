@@ -36,8 +36,9 @@ use marty_core::{
     cpu_808x::Cpu,
     cpu_common,
     cpu_common::CpuOption,
+    device_traits::realtimeclock::RtcDispatch,
     machine,
-    syntax_token::SyntaxToken,
+    syntax_token::{HighlightType, SyntaxFormatType, SyntaxToken},
     util,
 };
 use marty_egui::GuiWindow;
@@ -116,15 +117,19 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
         emu.gui.perf_viewer.update(dti, sound_stats, &emu.perf, frame_history)
     }
 
+    // The memory viewer's undo count also gates the fill menu's undo button, so update it regardless.
+    emu.gui.memory_viewer.set_undo_len(emu.machine.memory_undo_len());
+
     // -- Update memory viewer window if open
     if emu.gui.is_window_open(GuiWindow::MemoryViewer) {
+        let layout = emu.gui.memory_viewer.layout();
         let (mem_dump_addr_str, _source) = emu.gui.memory_viewer.get_address();
 
         let (addr, mem_dump_addr) = match emu.machine.cpu().eval_address(&mem_dump_addr_str) {
             Some(i) => {
                 let addr: u32 = i.into();
-                // Dump at 16 byte block boundaries
-                (addr, addr & !0x0F)
+                // Dump at row boundaries
+                (addr, addr & !(layout.row_len as u32 - 1))
             }
             None => {
                 // Show address 0 if expression eval fails
@@ -135,12 +140,40 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
         let mem_dump_vec = emu
             .machine
             .bus()
-            .dump_flat_tokens_ex(mem_dump_addr as usize, addr as usize, 256);
+            .dump_flat_tokens_layout(mem_dump_addr as usize, addr as usize, 256, &layout);
 
         //framework.gui.memory_viewer.set_row(mem_dump_addr as usize);
 
         emu.gui.memory_viewer.set_address(addr as usize);
-        emu.gui.memory_viewer.set_memory(mem_dump_vec);
+        emu.gui.memory_viewer.set_memory(mem_dump_vec, emu.machine.cpu_cycles());
+        let bus = emu.machine.bus();
+        let executed = (0..vewport_len)
+            .map(|i| bus.is_executed(mem_dump_addr as usize + i))
+            .collect();
+        emu.gui.memory_viewer.set_executed(mem_dump_addr as usize, executed);
+        let rom = (0..vewport_len)
+            .map(|i| bus.is_rom(mem_dump_addr as usize + i))
+            .collect();
+        emu.gui.memory_viewer.set_rom(mem_dump_addr as usize, rom);
+        if emu.gui.memory_viewer.show_heatmap {
+            emu.gui.memory_viewer.set_page_stats(emu.machine.bus().get_page_stats());
+        }
+    }
+
+    // Page access counts are only collected while the memory viewer is showing its heat map.
+    let heatmap = emu.gui.is_window_open(GuiWindow::MemoryViewer) && emu.gui.memory_viewer.show_heatmap;
+    if emu.machine.bus().page_stats_enabled() != heatmap {
+        emu.machine.bus_mut().set_page_stats_enabled(heatmap);
+    }
+
+    // -- Update memory access profile window
+    // The profiler only runs while its window is open, so it costs nothing otherwise.
+    let profiling = emu.gui.is_window_open(GuiWindow::AccessProfileViewer);
+    if emu.machine.bus().access_profiler_enabled() != profiling {
+        emu.machine.bus_mut().set_access_profiler_enabled(profiling);
+    }
+    if let Some(profile) = emu.machine.bus().access_profile() {
+        emu.gui.access_profile_viewer.set_profile(profile);
     }
 
     // Update data visualizer
@@ -177,8 +210,8 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
 
     // -- Update IVR viewer window if open
     if emu.gui.is_window_open(GuiWindow::IvtViewer) {
-        let vec = emu.machine.bus_mut().dump_ivt_tokens();
-        emu.gui.ivt_viewer.set_content(vec);
+        let ivt = emu.machine.bus().dump_ivt();
+        emu.gui.ivt_viewer.set_content(ivt, emu.machine.cpu().get_int_counts());
     }
 
     // -- Update IO stats viewer window if open
@@ -187,6 +220,27 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
         emu.gui.io_stats_viewer.set_content(vec);
     }
 
+    // -- Update POST code viewer window
+    if emu.gui.is_window_open(GuiWindow::PostCodeViewer) {
+        let log = emu.machine.post_code_log();
+        emu.gui.post_code_viewer.set_content(log);
+    }
+
+    // -- Update CMOS viewer window
+    if emu.gui.is_window_open(GuiWindow::CmosViewer) {
+        // Only the MC146818 has CMOS RAM.
+        let cmos = match emu.machine.bus().rtc() {
+            Some(RtcDispatch::Rtc(rtc)) => Some(rtc.cmos()),
+            _ => None,
+        };
+        emu.gui.cmos_viewer.set_content(cmos);
+    }
+
+    // -- Update ROM info window
+    if emu.gui.is_window_open(GuiWindow::RomInfoViewer) {
+        emu.gui.rom_info_viewer.set_content(emu.machine.rom_info());
+    }
+
     // -- Update register viewer window
     if emu.gui.is_window_open(GuiWindow::CpuStateViewer) {
         let cpu_state = emu.machine.cpu().get_string_state();
@@ -261,18 +315,37 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
         if let Some(videocard_state) = emu.machine.videocard_state() {
             emu.gui.update_videocard_state(videocard_state);
         }
+        if let Some(registers) = emu.machine.videocard_registers() {
+            emu.gui.update_videocard_registers(registers);
+        }
     }
 
     // -- Update Instruction Trace window
     if emu.gui.is_window_open(GuiWindow::InstructionHistoryViewer) {
-        let trace = emu.machine.cpu().dump_instruction_history_tokens();
+        let trace = match emu.gui.trace_viewer.query() {
+            Some(query) => emu.machine.cpu().query_instruction_history(query),
+            None => emu.machine.cpu().dump_instruction_history_tokens(),
+        };
         emu.gui.trace_viewer.set_content(trace);
     }
 
     // -- Update Call Stack window
     if emu.gui.is_window_open(GuiWindow::CallStack) {
         let stack = emu.machine.cpu().dump_call_stack();
-        emu.gui.call_stack_viewer.set_content(stack);
+        let overflow = emu.machine.cpu().call_stack_overflow();
+        emu.gui.call_stack_viewer.set_content(stack, overflow);
+    }
+
+    // -- Update prefetch queue window
+    if emu.gui.is_window_open(GuiWindow::PrefetchViewer) {
+        let prefetch_state = emu.machine.cpu().prefetch_state();
+        emu.gui.prefetch_viewer.set_state(prefetch_state);
+    }
+
+    // -- Update watch window
+    if emu.gui.is_window_open(GuiWindow::WatchViewer) {
+        let cpu = emu.machine.cpu();
+        emu.gui.watch_viewer.update(|expr| cpu.eval_expression(expr));
     }
 
     // -- Update cycle trace viewer window
@@ -290,6 +363,17 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
                 _ => {}
             }
         }
+
+        if !emu.machine.get_cpu_option(CpuOption::CycleTraceRecords(true)) {
+            emu.machine.set_cpu_option(CpuOption::CycleTraceRecords(true));
+        }
+        if let Some(records) = emu.machine.cpu().get_cycle_trace_records() {
+            emu.gui.cycle_trace_viewer.update_records(records);
+        }
+    }
+    else if emu.machine.get_cpu_option(CpuOption::CycleTraceRecords(true)) {
+        // Stop recording cycles when nobody is looking at them.
+        emu.machine.set_cpu_option(CpuOption::CycleTraceRecords(false));
     }
 
     /*
@@ -321,9 +405,12 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
         };
 
         let cpu_type = emu.machine.cpu().get_type();
+        // The bus borrows the CPU mutably, so take a copy of any imported symbols to name branch targets.
+        let symbols = emu.machine.cpu().symbols().clone();
         let bus = emu.machine.bus_mut();
 
         let mut listview_vec = Vec::new();
+        let mut instr_ranges = Vec::new();
 
         //let mut disassembly_string = String::new();
         let mut disassembly_addr_flat = start_addr_flat as usize;
@@ -334,9 +421,11 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
                 bus.seek(disassembly_addr_flat);
 
                 let mut decode_vec = Vec::new();
+                let mut instr_range = (disassembly_addr_flat, 0);
 
                 match cpu_type.decode(bus, true) {
                     Ok(i) => {
+                        instr_range.1 = i.size as usize;
                         let instr_vec = bus.get_vec_at_ex(disassembly_addr_flat, i.size as usize);
                         let instr_bytes_str = util::fmt_byte_array(&instr_vec);
 
@@ -345,7 +434,7 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
                             format!("{:05X}", disassembly_addr_flat),
                         ));
 
-                        let mut instr_vec = cpu_type.tokenize_instruction(&i);
+                        let mut instr_vec = cpu_type.tokenize_instruction(&i, &symbols);
 
                         //let decode_str = format!("{:05X} {:012} {}\n", disassembly_addr, instr_bytes_str, i);
 
@@ -360,6 +449,15 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
                                 format!("{:04X}:{:04X}{}", segment, offset, " ",),
                             ));
 
+                            // Resolve relative branch targets within the code segment.
+                            if let Some(target) = i.branch_target(Some((segment, offset))) {
+                                for token in instr_vec.iter_mut() {
+                                    if let SyntaxToken::BranchTarget(address, _) = token {
+                                        *address = target;
+                                    }
+                                }
+                            }
+
                             let new_offset = offset.wrapping_add(i.size as u16);
                             if new_offset < offset {
                                 // A wrap of the code segment occurred. Update the linear address to match.
@@ -379,6 +477,21 @@ pub fn update_egui(emu: &mut Emulator, tm: &TimestepManager, elwt: &EventLoopWin
 
                 //disassembly_string.push_str(&decode_str);
                 listview_vec.push(decode_vec);
+                instr_ranges.push(instr_range);
+            }
+        }
+
+        // Flag instructions that were rewritten after the CPU had already fetched them, and tint
+        // instructions that have been executed while coverage recording was enabled.
+        for (decode_vec, (addr, size)) in listview_vec.iter_mut().zip(instr_ranges) {
+            if (addr..addr + size).any(|a| emu.machine.code_write(a as u32).is_some()) {
+                decode_vec.push(SyntaxToken::ErrorString("; SMC".to_string()));
+            }
+            if size > 0 && emu.machine.bus().is_executed(addr) {
+                decode_vec.insert(
+                    0,
+                    SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(HighlightType::Info)),
+                );
             }
         }
 
@@ -38,7 +38,13 @@ use winit::{
 
 use display_manager_wgpu::DisplayManager;
 use marty_core::machine::{ExecutionOperation, MachineState};
-use marty_frontend_common::{constants::LONG_NOTIFICATION_TIME, types::joykeys::JoyKeyInput, HotkeyEvent};
+use marty_egui::GuiBoolean;
+use marty_frontend_common::{
+    constants::{LONG_NOTIFICATION_TIME, SHORT_NOTIFICATION_TIME},
+    savestate_manager,
+    types::joykeys::JoyKeyInput,
+    HotkeyEvent,
+};
 
 use crate::{input::TranslateKey, Emulator};
 
@@ -260,6 +266,36 @@ pub fn process_hotkeys(emu: &mut Emulator, keycode: KeyCode, pressed: bool, wind
             HotkeyEvent::DebugStepOver => {
                 emu.exec_control.borrow_mut().set_op(ExecutionOperation::StepOver);
             }
+            HotkeyEvent::QuickSave => {
+                log::debug!("QuickSave hotkey triggered.");
+                if let Err(err) = savestate_manager::save_state(&mut emu.machine, &mut emu.rm, Some(1)) {
+                    log::error!("Quick save failed: {}", err);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Quick save failed: {}", err))
+                        .set_duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+            HotkeyEvent::QuickLoad => {
+                log::debug!("QuickLoad hotkey triggered.");
+                if let Err(err) = savestate_manager::load_state(&mut emu.machine, &emu.rm, Some(1)) {
+                    log::error!("Quick load failed: {}", err);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Quick load failed: {}", err))
+                        .set_duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+            HotkeyEvent::ToggleTurbo => {
+                let state = !emu.machine.get_turbo_mode();
+                log::debug!("ToggleTurbo hotkey triggered. Setting turbo mode: {}", state);
+                emu.machine.set_turbo_mode(state);
+                emu.gui.set_option(GuiBoolean::TurboButton, state);
+                emu.gui
+                    .toasts()
+                    .info(format!("Turbo mode {}!", if state { "enabled" } else { "disabled" }))
+                    .set_duration(Some(SHORT_NOTIFICATION_TIME));
+            }
             HotkeyEvent::JoyToggle => {
                 log::debug!("JoyToggle hotkey triggered. Toggling joystick keyboard emulation.");
                 emu.joy_data.enabled = !emu.joy_data.enabled;
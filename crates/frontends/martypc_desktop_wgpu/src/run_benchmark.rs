@@ -32,7 +32,6 @@ use std::{cell::RefCell, rc::Rc, time::Instant};
 
 use marty_config::ConfigFileParams;
 use marty_core::{
-    bus::ClockFactor,
     cpu_common::Cpu,
     machine::{ExecutionControl, ExecutionState, MachineBuilder, MachineRomManifest},
 };
@@ -110,10 +109,8 @@ pub fn run_benchmark(
     let (cycle_total, halt_cycles) = machine.cpu().get_cycle_ct();
 
     let cpu_factor = machine.get_cpu_factor();
-    let sys_ticks = match cpu_factor {
-        ClockFactor::Divisor(d) => cycle_total * d as u64,
-        ClockFactor::Multiplier(m) => cycle_total / m as u64,
-    };
+    let (ticks, cycles) = cpu_factor.ratio();
+    let sys_ticks = cycle_total * ticks as u64 / cycles as u64;
 
     println!(
         "Benchmark complete.\nRan {} cycles and {} instructions in {:?} seconds.",
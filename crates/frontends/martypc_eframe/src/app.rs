@@ -27,7 +27,11 @@
 use crate::{
     emulator::Emulator,
     emulator_builder::EmulatorBuilder,
-    event_loop::thread_events::handle_thread_event,
+    event_loop::{
+        file_drop::handle_dropped_file,
+        mouse_capture::{capture_mouse, handle_captured_input, release_mouse},
+        thread_events::handle_thread_event,
+    },
     timestep_update::process_update,
     MARTY_ICON,
 };
@@ -39,11 +43,12 @@ use display_manager_eframe::{
     EFrameDisplayManager,
     TextureDimensions,
 };
+use marty_egui::{GuiEnum, GuiVariableContext};
 use marty_egui_eframe::{context::GuiRenderContext, EGUI_MENU_BAR_HEIGHT};
 use marty_frontend_common::{
     display_manager::{DisplayManager, DmGuiOptions},
     thread_events::*,
-    timestep_manager::TimestepManager,
+    timestep_manager::{frame_profiler::ProfilePhase, TimestepManager},
 };
 use marty_web_helpers::FetchResult;
 
@@ -56,6 +61,8 @@ use eframe::egui_wgpu;
 #[cfg(not(feature = "use_winit"))]
 use crate::event_loop::web_keyboard::handle_web_key_event;
 
+use std::cell::Cell;
+
 use crossbeam_channel::{Receiver, Sender};
 
 use egui::{Context, RawInput, Sense, ViewportCommand, ViewportId};
@@ -63,6 +70,8 @@ use egui::{Context, RawInput, Sense, ViewportCommand, ViewportId};
 use crate::emulator_builder::builder::EmuBuilderError;
 #[cfg(target_arch = "wasm32")]
 use crate::wasm::*;
+#[cfg(not(target_arch = "wasm32"))]
+use marty_frontend_common::constants::LONG_NOTIFICATION_TIME;
 use marty_frontend_common::{
     color::MartyColor,
     display_manager::{DisplayTargetType, DtHandle},
@@ -73,6 +82,9 @@ use marty_web_helpers::console_writer::ConsoleWriter;
 #[cfg(target_arch = "wasm32")]
 use url::Url;
 
+/// Storage key for the GUI workspace (window state and watch list).
+const WORKSPACE_KEY: &str = "workspace";
+
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -436,6 +448,17 @@ impl MartyApp {
         emu.gui.set_card_list(card_strs);
         emu.gui.init_display_info(dti);
 
+        // Select the POST code table for this machine profile.
+        emu.gui.post_code_viewer.set_table(emu.machine.post_code_table());
+
+        // Set the initial snow state for each card that was configured with it.
+        for vid in vid_list.iter() {
+            if let Some(true) = emu.machine.config().video.get(vid.idx).and_then(|card| card.snow) {
+                emu.gui
+                    .set_option_enum(GuiEnum::VideoCardSnow(true), Some(GuiVariableContext::VideoCard(*vid)));
+            }
+        }
+
         // Populate the list of display apertures for each display.
         display_manager.for_each_target(|dtc, dt_idx| {
             if let Some(card_id) = &dtc.get_card_id() {
@@ -490,6 +513,14 @@ impl MartyApp {
 
         // Create our GUI rendering context.
         let gui = GuiRenderContext::new(cc.egui_ctx.clone(), 0, 640, 480, 1.0, &gui_options);
+        emu.gui.set_syntax_colors(gui.syntax_colors());
+
+        // Restore the GUI workspace, if one was saved.
+        if let Some(workspace) = cc.storage.and_then(|storage| storage.get_string(WORKSPACE_KEY)) {
+            if let Err(e) = emu.gui.set_workspace_config_string(&workspace) {
+                log::warn!("Failed to restore workspace: {}", e);
+            }
+        }
 
         Self {
             gui,
@@ -554,6 +585,11 @@ impl eframe::App for MartyApp {
                 self.last_size = self.current_size; // Update tracked size
             }
 
+            // Don't hold on to the mouse while another application has focus.
+            if !self.focused && emu.mouse_data.is_captured {
+                release_mouse(emu, ctx);
+            }
+
             // Receive hooked Winit events.
             #[cfg(feature = "use_winit")]
             if let Some(receiver) = &self.winit_receiver {
@@ -581,6 +617,7 @@ impl eframe::App for MartyApp {
                     handle_web_key_event(
                         emu,
                         self.dm.as_mut().unwrap(),
+                        ctx,
                         event,
                         ctx.memory(|mem| mem.focused()).is_some(),
                     );
@@ -592,6 +629,31 @@ impl eframe::App for MartyApp {
             process_update(emu, dm, &mut self.tm);
             handle_thread_event(emu, ctx);
 
+            // Mount any files dropped onto the window.
+            for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+                handle_dropped_file(emu, file);
+            }
+
+            // Service debug server requests.
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(debug_server) = &mut emu.debug_server {
+                debug_server.poll(&mut emu.machine, &mut emu.exec_control.borrow_mut());
+            }
+
+            // Check whether the machine configuration file has been edited.
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(result) = emu.config_watcher.as_ref().and_then(|watcher| watcher.poll()) {
+                match result {
+                    Ok(_) => emu.gui.set_config_changed(true),
+                    Err(e) => {
+                        emu.gui
+                            .toasts()
+                            .error(format!("Error parsing machine configuration: {}", e))
+                            .duration(Some(LONG_NOTIFICATION_TIME));
+                    }
+                }
+            }
+
             let fill_color = dm
                 .main_display_target()
                 .read()
@@ -603,7 +665,13 @@ impl eframe::App for MartyApp {
 
             let show_bezel = emu.gui.primary_video_has_bezel();
 
+            // A click that closes a menu shouldn't also capture the mouse, so use the menu state
+            // from before this frame's GUI is drawn.
+            let menu_was_open = emu.gui.menu_open();
+            let display_clicked = Cell::new(false);
+
             // Draw the emulator GUI.
+            let profile_start = emu.frame_profiler.start();
             self.gui.show(
                 &mut emu.gui,
                 !self.hide_menu,
@@ -619,7 +687,8 @@ impl eframe::App for MartyApp {
                             // Draw the main display in a window.
                             egui::Window::new(display_name).resizable(true).show(ctx, |ui| {
                                 let ui_size = egui::Vec2::new(scaler_geom.target_w as f32, scaler_geom.target_h as f32);
-                                let (rect, _) = ui.allocate_exact_size(ui_size, Sense::hover());
+                                let (rect, response) = ui.allocate_exact_size(ui_size, Sense::click());
+                                display_clicked.set(response.clicked());
 
                                 #[cfg(feature = "use_wgpu")]
                                 {
@@ -665,6 +734,8 @@ impl eframe::App for MartyApp {
                     if let Some(DisplayTargetType::WindowBackground) = dm.display_type(DtHandle::MAIN) {
                         ui.allocate_ui(ui.available_size(), |ui| {
                             let rect = ui.max_rect();
+                            let response = ui.interact(rect, ui.id().with("main_display"), Sense::click());
+                            display_clicked.set(response.clicked());
 
                             //log::debug!("in allocate_ui with response rect: {:?}", rect);
 
@@ -678,6 +749,11 @@ impl eframe::App for MartyApp {
                     }
                 },
             );
+            emu.frame_profiler.end(ProfilePhase::Gui, profile_start);
+
+            if display_clicked.get() && !menu_was_open && !ctx.memory(|mem| mem.any_popup_open()) {
+                capture_mouse(emu, ctx);
+            }
         }
 
         // if let Some(dm) = &mut self.dm {
@@ -694,9 +770,21 @@ impl eframe::App for MartyApp {
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        if let Some(emu) = &mut self.emu {
+            match emu.gui.get_workspace_config_string() {
+                Ok(workspace) => storage.set_string(WORKSPACE_KEY, workspace),
+                Err(e) => log::error!("Failed to save workspace: {}", e),
+            }
+        }
     }
 
     fn raw_input_hook(&mut self, ctx: &Context, raw_input: &mut RawInput) {
+        if let Some(emu) = &mut self.emu {
+            if !handle_captured_input(emu, raw_input) {
+                release_mouse(emu, ctx);
+            }
+        }
+
         let gui_has_focus = ctx.wants_keyboard_input();
 
         //let gui_has_focus = ctx.memory(|mem| mem.focused()).is_some();
@@ -55,21 +55,28 @@ use crate::{
 use marty_core::{
     cpu_common::{Cpu, CpuOption},
     machine::{ExecutionControl, Machine, MachineEvent, MachineState},
-    vhd::{VhdIO, VirtualHardDisk},
+    vhd::{VhdIO, VhdMountMode, VhdOverlay, VirtualHardDisk},
 };
-use marty_egui::{state::GuiState, GuiBoolean, GuiWindow};
+use marty_egui::{command_script::CommandScript, state::GuiState, GuiBoolean, GuiWindow};
 use marty_frontend_common::{
     cartridge_manager::CartridgeManager,
+    constants::LONG_NOTIFICATION_TIME,
     display_scaler::SCALER_MODES,
+    file_drop::DroppedFile,
     floppy_manager::FloppyManager,
     resource_manager::ResourceManager,
     rom_manager::RomManager,
     thread_events::{FileOpenContext, FileSelectionContext, FrontendThreadEvent},
-    timestep_manager::PerfSnapshot,
+    timestep_manager::{frame_profiler::FrameProfiler, PerfSnapshot},
     types::floppy::FloppyImageSource,
     vhd_manager::VhdManager,
 };
 
+#[cfg(feature = "lua_scripting")]
+use marty_frontend_common::script_engine::LuaScriptEngine;
+#[cfg(not(target_arch = "wasm32"))]
+use marty_frontend_common::{debug_server::DebugServer, machine_manager::config_watcher::ConfigWatcher};
+
 /// Define flags to be used by emulator.
 pub struct EmuFlags {
     pub render_gui: bool,
@@ -98,8 +105,19 @@ pub struct Emulator {
     pub cart_manager: CartridgeManager,
     pub flags: EmuFlags,
     pub perf: PerfSnapshot,
+    pub frame_profiler: FrameProfiler,
     pub hkm: HotkeyManager,
     pub si: Option<SoundInterface>,
+    #[cfg(feature = "lua_scripting")]
+    pub script_engine: LuaScriptEngine,
+    pub command_script: Option<CommandScript>,
+    pub speaker_recording: Vec<i16>,
+    pub speaker_recording_rate: u32,
+    pub dropped_file: Option<DroppedFile>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub debug_server: Option<DebugServer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub config_watcher: Option<ConfigWatcher>,
     pub receiver: crossbeam_channel::Receiver<FrontendThreadEvent<Arc<DiskImage>>>,
     pub sender: crossbeam_channel::Sender<FrontendThreadEvent<Arc<DiskImage>>>,
 }
@@ -139,9 +157,8 @@ impl Emulator {
             self.config.machine.cpu.service_interrupt.unwrap_or(false),
         ));
 
-        // TODO: Re-enable these
-        //gui.set_option(GuiBoolean::EnableSnow, config.machine.cga_snow.unwrap_or(false));
-        //machine.set_video_option(VideoOption::EnableSnow(config.machine.cga_snow.unwrap_or(false)));
+        // CGA snow is configured per video card, and applied when the card is created.
+        // TODO: Re-enable this
         //gui.set_option(GuiBoolean::CorrectAspect, config.emulator.scaler_aspect_correction);
 
         //if config.emulator.scaler_aspect_correction {
@@ -194,6 +211,16 @@ impl Emulator {
             }
         }
 
+        // Load the boot script if one was specified in config options
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(script_path) = self.config.emulator.boot_script.clone() {
+            let source = self.rm.read_resource_from_path_blocking(&script_path)?;
+            let script = CommandScript::parse(&String::from_utf8_lossy(&source))
+                .map_err(|e| anyhow::anyhow!("Error in boot script {}: {}", script_path.display(), e))?;
+            log::debug!("Loaded boot script: {}", script_path.display());
+            self.command_script = Some(script);
+        }
+
         self.gui.set_option(
             GuiBoolean::CpuEnableWaitStates,
             self.config.machine.cpu.wait_states.unwrap_or(true),
@@ -217,6 +244,8 @@ impl Emulator {
             .set_cpu_option(CpuOption::TraceLoggingEnabled(self.config.machine.cpu.trace_on));
 
         self.gui.set_option(GuiBoolean::TurboButton, self.config.machine.turbo);
+        // The machine may have refused fast boot, so show its state rather than the config's.
+        self.gui.set_option(GuiBoolean::FastBoot, self.machine.get_fast_boot());
 
         self.gui.set_scaler_presets(&self.config.emulator.scaler_preset);
 
@@ -380,6 +409,7 @@ impl Emulator {
         // First, retrieve the list of VHD images specified in the machine configuration.
         let mut vhd_names: Vec<Option<String>> = self.get_vhds_from_machine();
         let machine_max = vhd_names.len();
+        let mut vhd_modes = vec![VhdMountMode::default(); machine_max];
 
         for (drive_i, vhd) in self
             .config
@@ -394,21 +424,27 @@ impl Emulator {
             if drive_i >= machine_max {
                 // Add new drive
                 vhd_names.push(Some(vhd.filename.clone()));
+                vhd_modes.push(vhd.mode);
             }
             else {
                 // Replace existing drive
                 vhd_names[drive_i] = Some(vhd.filename.clone());
+                vhd_modes[drive_i] = vhd.mode;
             }
         }
 
         let mut drive_idx: usize = 0;
-        for vhd_name in vhd_names.into_iter().filter_map(|x| x) {
+        for (vhd_name, mode) in vhd_names
+            .into_iter()
+            .zip(vhd_modes)
+            .filter_map(|(name, mode)| name.map(|name| (name, mode)))
+        {
             let vhd_os_name: OsString = vhd_name.into();
 
             #[cfg(not(target_arch = "wasm32"))]
             match self.vhd_manager.load_vhd_file_by_name(drive_idx, &vhd_os_name) {
                 Ok((vhd_file, vhd_idx)) => {
-                    self.load_vhd(Box::new(vhd_file), drive_idx, &vhd_os_name, Some(vhd_idx))?;
+                    self.load_vhd(Box::new(vhd_file), drive_idx, &vhd_os_name, Some(vhd_idx), mode)?;
                 }
                 Err(err) => {
                     log::error!("Failed to load VHD image {:?}: {}", vhd_os_name, err);
@@ -420,7 +456,13 @@ impl Emulator {
                 .load_vhd_file_by_name(&mut self.rm, drive_idx, &vhd_os_name)
             {
                 Ok(vhd_data) => {
-                    self.load_vhd(Box::new(std::io::Cursor::new(vhd_data)), drive_idx, &vhd_os_name, None)?;
+                    self.load_vhd(
+                        Box::new(std::io::Cursor::new(vhd_data)),
+                        drive_idx,
+                        &vhd_os_name,
+                        None,
+                        mode,
+                    )?;
                 }
                 Err(err) => {
                     log::error!("Failed to load VHD image {:?}: {}", vhd_os_name, err);
@@ -437,8 +479,10 @@ impl Emulator {
         drive_idx: usize,
         vhd_os_name: &OsStr,
         vhd_idx: Option<usize>,
+        mode: VhdMountMode,
     ) -> Result<(), Error> {
-        match VirtualHardDisk::parse(Box::new(vhd_file), false) {
+        self.gui.set_hdd_mount_mode(drive_idx, mode);
+        match self.open_vhd(vhd_file, drive_idx, mode) {
             Ok(vhd) => {
                 if let Some(hdc) = self.machine.hdc_mut() {
                     match hdc.set_vhd(drive_idx, vhd) {
@@ -491,6 +535,61 @@ impl Emulator {
         Ok(())
     }
 
+    /// Parse a VHD image and prepare it for mounting in the specified mode.
+    /// For copy-on-write mounts, the image's existing overlay file is reapplied if it was created
+    /// against the current contents of the image. A stale overlay is moved aside, not applied.
+    pub fn open_vhd(
+        &mut self,
+        vhd_file: Box<dyn VhdIO>,
+        drive_idx: usize,
+        mode: VhdMountMode,
+    ) -> Result<VirtualHardDisk, Error> {
+        let mut vhd = VirtualHardDisk::parse(vhd_file, mode == VhdMountMode::ReadOnly)?;
+        if mode != VhdMountMode::CopyOnWrite {
+            return Ok(vhd);
+        }
+
+        let base_hash = vhd.content_hash()?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let overlay_file = self.vhd_manager.open_overlay_file(drive_idx, false)?;
+            if overlay_file.metadata()?.len() > 0 {
+                match VhdOverlay::open(Box::new(overlay_file), base_hash) {
+                    Ok(overlay) => {
+                        log::info!("Reapplied VHD overlay with {} sector(s)", overlay.len());
+                        vhd.attach_overlay(overlay);
+                        return Ok(vhd);
+                    }
+                    Err(err) => {
+                        let retired_path = self.vhd_manager.retire_overlay_file(drive_idx)?;
+                        log::warn!("Refusing VHD overlay: {}. Moved to {:?}", err, retired_path);
+                        self.gui
+                            .toasts()
+                            .warning(format!("VHD overlay not applied: {}\nMoved to {:?}", err, retired_path))
+                            .duration(Some(LONG_NOTIFICATION_TIME));
+                    }
+                }
+            }
+        }
+
+        vhd.attach_overlay(self.new_vhd_overlay(drive_idx, base_hash)?);
+        Ok(vhd)
+    }
+
+    /// Create a new, empty copy-on-write overlay for the VHD in the specified drive. On the web,
+    /// images and their overlays only live in memory.
+    pub fn new_vhd_overlay(&mut self, drive_idx: usize, base_hash: [u8; 16]) -> Result<VhdOverlay, Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let overlay_file: Box<dyn VhdIO> = Box::new(self.vhd_manager.open_overlay_file(drive_idx, true)?);
+        #[cfg(target_arch = "wasm32")]
+        let overlay_file: Box<dyn VhdIO> = {
+            let _ = drive_idx;
+            Box::new(std::io::Cursor::new(Vec::new()))
+        };
+        VhdOverlay::create(overlay_file, base_hash)
+    }
+
     pub fn post_dm_build_init(&mut self) {
         // // Set all DisplayTargets to hardware aspect correction
         // self.dm.for_each_target(|dtc, _idx| {
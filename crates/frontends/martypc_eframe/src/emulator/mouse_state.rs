@@ -15,6 +15,8 @@ pub struct MouseData {
     pub r_button_is_pressed: bool,
     pub frame_delta_x: f64,
     pub frame_delta_y: f64,
+    /// Last pointer position, used to derive motion where raw motion events are unavailable.
+    pub last_pointer_pos: Option<egui::Pos2>,
 }
 
 impl MouseData {
@@ -33,6 +35,7 @@ impl MouseData {
             r_button_is_pressed: false,
             frame_delta_x: 0.0,
             frame_delta_y: 0.0,
+            last_pointer_pos: None,
         }
     }
     pub fn reset(&mut self) {
@@ -50,4 +53,52 @@ impl MouseData {
         self.frame_delta_y = 0.0;
         self.have_update = false;
     }
+    /// Accumulate relative motion until the next mouse update.
+    pub fn add_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.frame_delta_x += delta_x;
+        self.frame_delta_y += delta_y;
+        self.have_update = true;
+    }
+
+    /// Record a button press or release. A click could be faster than one frame, so presses are
+    /// 'sticky' and are sent to the guest on the next mouse update even if already released.
+    pub fn set_button(&mut self, left: bool, pressed: bool) {
+        let left = left != self.reverse_buttons;
+        let (was_pressed, was_released, is_pressed) = if left {
+            (
+                &mut self.l_button_was_pressed,
+                &mut self.l_button_was_released,
+                &mut self.l_button_is_pressed,
+            )
+        }
+        else {
+            (
+                &mut self.r_button_was_pressed,
+                &mut self.r_button_was_released,
+                &mut self.r_button_is_pressed,
+            )
+        };
+
+        if pressed {
+            *was_pressed = true;
+            *is_pressed = true;
+        }
+        else if *is_pressed {
+            *is_pressed = false;
+            *was_released = true;
+        }
+        else {
+            return;
+        }
+        self.have_update = true;
+    }
+
+    /// Clear all button and motion state when the mouse is released.
+    pub fn release(&mut self) {
+        self.l_button_is_pressed = false;
+        self.r_button_is_pressed = false;
+        self.reset();
+        self.last_pointer_pos = None;
+        self.is_captured = false;
+    }
 }
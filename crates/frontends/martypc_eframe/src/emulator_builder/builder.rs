@@ -58,11 +58,14 @@ use crate::sound::SoundInterface;
 use marty_core::cpu_validator::ValidatorType;
 use marty_core::{
     machine::{ExecutionControl, ExecutionState, MachineBuilder},
+    machine_config::VideoCardConfig,
+    machine_preset::{MachinePreset, PresetOverrides},
     supported_floppy_extensions,
 };
 use marty_egui::state::GuiState;
 use marty_frontend_common::{
     cartridge_manager::CartridgeManager,
+    constants::LONG_NOTIFICATION_TIME,
     floppy_manager::FloppyManager,
     machine_manager::MachineManager,
     resource_manager::ResourceManager,
@@ -70,6 +73,12 @@ use marty_frontend_common::{
     types::resource_location::ResourceLocation,
     vhd_manager::VhdManager,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use marty_frontend_common::{
+    cmos_manager,
+    debug_server::{DebugServer, DEFAULT_DEBUG_SERVER_PORT},
+    machine_manager::MachineConfigFile,
+};
 
 use anyhow::{anyhow, Error};
 use url::Url;
@@ -417,9 +426,25 @@ impl EmulatorBuilder {
             )?;
         }
 
+        // A machine template, if specified, is built in place of the named machine configuration.
+        // Benchmarks always use their static configuration.
+        let template = match config.machine.template.as_deref() {
+            Some(name) if !config.emulator.benchmark_mode => match name.parse::<MachinePreset>() {
+                Ok(preset) => Some(preset),
+                Err(e) => {
+                    writeln!(stderr, "{e}")?;
+                    return Err(BadMachineConfig(name.to_string()));
+                }
+            },
+            _ => None,
+        };
+        if let Some(preset) = template {
+            init_config_name = preset.config_name().to_string();
+        }
+
         // Get a list of machine configuration names
         let machine_names = machine_manager.get_config_names();
-        let have_machine_config = machine_names.contains(&init_config_name);
+        let have_machine_config = template.is_some() || machine_names.contains(&init_config_name);
 
         // Do --machinescan commandline argument. We print machine info (and ROM info if --romscan
         // was also specified), then quit.
@@ -463,14 +488,39 @@ impl EmulatorBuilder {
         // Load ROM definitions
         rom_manager.load_defs(&mut resource_manager).await?;
 
+        // A machine built from a template has no configuration file to watch.
+        #[cfg(not(target_arch = "wasm32"))]
+        let machine_config_path = match template {
+            Some(_) => None,
+            None => machine_manager.get_config_path(&init_config_name).cloned(),
+        };
+
         // Get the ROM requirements for the requested machine type
-        let machine_config_file = {
-            for overlay in init_config_overlays.iter() {
-                log::debug!("Have machine config overlay from global config: {}", overlay);
+        let machine_config_file = match template {
+            Some(preset) => {
+                let overrides = PresetOverrides {
+                    conventional_memory: config.machine.template_memory.map(|kb| kb * 1024),
+                    video: config.machine.template_video.map(|video_type| {
+                        vec![VideoCardConfig {
+                            video_type,
+                            video_subtype: None,
+                            dip_switch: None,
+                            snow: None,
+                        }]
+                    }),
+                    ..Default::default()
+                };
+                writeln!(stdout, "Building machine from template: {}", preset)?;
+                machine_manager.get_config_from_template(preset, &overrides)
             }
-            let overlay_vec = init_config_overlays.clone();
+            None => {
+                for overlay in init_config_overlays.iter() {
+                    log::debug!("Have machine config overlay from global config: {}", overlay);
+                }
+                let overlay_vec = init_config_overlays.clone();
 
-            machine_manager.get_config_with_overlays(&init_config_name, &overlay_vec)?
+                machine_manager.get_config_with_overlays(&init_config_name, &overlay_vec)?
+            }
         };
 
         // Collect the ROM requirements for the machine configuration
@@ -524,7 +574,7 @@ impl EmulatorBuilder {
         }
 
         // Create the ROM manifest to pass to the emulator core
-        let rom_manifest = rom_manager
+        let mut rom_manifest = rom_manager
             .create_manifest_async(rom_sets_resolved.clone(), &mut resource_manager)
             .await?;
 
@@ -593,7 +643,7 @@ impl EmulatorBuilder {
         // I'm not sure how to implement this yet, so for now we'll just print an error and exit.
         // A true headless mode is probably best implemented as a separate front-end.
         if config.emulator.headless {
-            let err_str = "Headless mode requested, but not implemented".to_string();
+            let err_str = "Headless mode is not supported by this frontend. Use martypc_headless instead.".to_string();
             return Err(UnsupportedPlatform(err_str));
         }
 
@@ -632,6 +682,16 @@ impl EmulatorBuilder {
         // Create a MachineConfiguration for core initialization
         let machine_config = machine_config_file.to_machine_config();
 
+        // Load any option ROMs specified by the machine configuration. Bad option ROMs are skipped and
+        // reported once the GUI is up, rather than preventing the machine from starting.
+        let option_rom_errors = rom_manager
+            .load_option_roms(&machine_config.option_roms, &mut rom_manifest, &mut resource_manager)
+            .await;
+        for err in option_rom_errors.iter() {
+            log::error!("{}", err);
+            writeln!(stdout, "{}", err)?;
+        }
+
         let trace_file_base = resource_manager.resource_path("trace").unwrap_or_default();
         let mut trace_file_path = None;
         if let Some(trace_file) = &config.machine.cpu.trace_file {
@@ -695,7 +755,12 @@ impl EmulatorBuilder {
 
         // Build the Machine instance
         log::debug!("Building Machine...");
-        let machine = machine_builder.build()?;
+        #[allow(unused_mut)]
+        let mut machine = machine_builder.build()?;
+
+        // Restore the RTC's CMOS RAM, if the machine has one and it was saved previously.
+        #[cfg(not(target_arch = "wasm32"))]
+        let cmos_error = cmos_manager::load_cmos(&mut machine, &resource_manager, &config.machine.config_name).err();
 
         // Now that we have a Machine, we can query it for sound sources (devices that produce sound)
         // For each sound source we will create a source in the SoundInterface, to give it
@@ -729,6 +794,20 @@ impl EmulatorBuilder {
         // Create a GUI state object
         let mut gui = GuiState::new(exec_control.clone(), sender.clone());
 
+        // Report any option ROMs that failed to load
+        for err in option_rom_errors.iter() {
+            gui.toasts()
+                .error(err.to_string())
+                .duration(Some(LONG_NOTIFICATION_TIME));
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(err) = cmos_error {
+            gui.toasts()
+                .error(format!("Failed to load CMOS: {}", err))
+                .duration(Some(LONG_NOTIFICATION_TIME));
+        }
+
         // Set list of virtual serial ports
         gui.set_serial_ports(machine.bus().enumerate_serial_ports());
 
@@ -765,6 +844,46 @@ impl EmulatorBuilder {
         // TODO: This should probably be converted into a channel
         let machine_events = Vec::new();
 
+        // Start the debug server, if enabled.
+        #[cfg(not(target_arch = "wasm32"))]
+        let debug_server = if config.emulator.debugger.server_enabled {
+            let port = config
+                .emulator
+                .debugger
+                .server_port
+                .unwrap_or(DEFAULT_DEBUG_SERVER_PORT);
+            match DebugServer::start(("127.0.0.1", port)) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    log::error!("Failed to start debug server on port {}: {}", port, e);
+                    None
+                }
+            }
+        }
+        else {
+            None
+        };
+
+        // Watch the file that defines the machine configuration, so the user can be told when it changes.
+        #[cfg(not(target_arch = "wasm32"))]
+        let config_watcher = machine_config_path.and_then(|path| match MachineConfigFile::from_file_watched(&path) {
+            Ok((config_file, watcher)) => {
+                log::debug!("Watching machine configuration file: {:?}", watcher.path());
+                if !config_file.defines_machine(&init_config_name) {
+                    log::warn!(
+                        "Machine configuration file {:?} does not define '{}'",
+                        watcher.path(),
+                        init_config_name
+                    );
+                }
+                Some(watcher)
+            }
+            Err(e) => {
+                log::warn!("Failed to watch machine configuration file {:?}: {}", path, e);
+                None
+            }
+        });
+
         Ok(Emulator {
             rm: resource_manager,
             romm: rom_manager,
@@ -782,12 +901,23 @@ impl EmulatorBuilder {
             vhd_manager,
             cart_manager,
             perf: Default::default(),
+            frame_profiler: Default::default(),
             flags: EmuFlags {
                 render_gui: self.enable_gui,
                 debug_keyboard: false,
             },
             hkm: hotkey_manager,
             si: sound_player,
+            #[cfg(feature = "lua_scripting")]
+            script_engine: Default::default(),
+            command_script: None,
+            speaker_recording: Vec::new(),
+            speaker_recording_rate: 0,
+            dropped_file: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            debug_server,
+            #[cfg(not(target_arch = "wasm32"))]
+            config_watcher,
             sender,
             receiver,
         })
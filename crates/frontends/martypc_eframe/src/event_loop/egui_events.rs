@@ -29,7 +29,7 @@
 //! Typically, the GUI is implemented by the `marty_egui` crate.
 
 use std::{
-    ffi::OsString,
+    ffi::{OsStr, OsString},
     io::Cursor,
     mem::discriminant,
     path::{Path, PathBuf},
@@ -37,25 +37,45 @@ use std::{
     time::Duration,
 };
 
-use crate::{emulator, emulator::Emulator, floppy::load_floppy::handle_load_floppy};
+use crate::{
+    emulator,
+    emulator::Emulator,
+    event_loop::{file_drop, text_screen},
+    floppy::load_floppy::handle_load_floppy,
+};
 use display_manager_eframe::EFrameDisplayManager;
 
 use marty_frontend_common::{
-    constants::{LONG_NOTIFICATION_TIME, NORMAL_NOTIFICATION_TIME, SHORT_NOTIFICATION_TIME},
+    constants::{
+        LONG_NOTIFICATION_TIME,
+        NORMAL_NOTIFICATION_TIME,
+        SHORT_NOTIFICATION_TIME,
+        SPEAKER_RECORDING_DEFAULT_RATE,
+    },
     floppy_manager::FloppyError,
     thread_events::{FileSelectionContext, FrontendThreadEvent},
     types::floppy::FloppyImageSource,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use marty_frontend_common::{savestate_manager, wav_writer::write_wav};
 
 use marty_core::{
     breakpoints::BreakPointType,
     cpu_common,
-    cpu_common::{Cpu, CpuOption, Register16},
-    device_traits::videocard::ClockingMode,
+    cpu_common::{
+        symbols::{parse_symbol_file, to_symbol_file, SymbolTable},
+        Cpu,
+        CpuOption,
+        Register16,
+    },
+    device_traits::videocard::{ClockingMode, VideoOption},
     device_types::fdc::FloppyImageType,
-    machine::{MachineOption, MachineState},
+    devices::{floppy_drive::BadSector, serial::SERIAL_LOG_EXTENSION},
+    machine::{ExecutionOperation, MachineOption, MachineState},
+    machine_config::get_machine_descriptor,
+    machine_preset::{MachinePreset, PresetOverrides},
     vhd,
-    vhd::VirtualHardDisk,
+    vhd::VhdIO,
 };
 use marty_egui::{
     modal::ModalContext,
@@ -67,11 +87,12 @@ use marty_egui::{
     GuiFloat,
     GuiVariable,
     GuiVariableContext,
+    GuiWindow,
     InputFieldChangeSource,
 };
 use marty_videocard_renderer::AspectCorrectionMode;
 
-use fluxfox::{DiskImage, LoadingStatus};
+use fluxfox::{DiskImage, DiskImageFileFormat, LoadingStatus};
 
 use anyhow::Error;
 use winit::event_loop::ActiveEventLoop;
@@ -105,6 +126,48 @@ pub fn handle_egui_event(
             // User wants to crash the computer. Sure, why not.
             emu.machine.set_nmi(*state);
         }
+        GuiEvent::MountDroppedFile(target) => match target {
+            Some(target) => file_drop::mount_dropped_file(emu, *target),
+            None => emu.dropped_file = None,
+        },
+        GuiEvent::FillMemory { start, len, value } => {
+            let (written, skipped) = emu.machine.fill_memory(*start, *len, *value);
+            log::debug!(
+                "Filled {} bytes at {:05X} with {:02X}, skipped {}",
+                written,
+                start,
+                value,
+                skipped
+            );
+            if skipped > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!(
+                        "Filled {} bytes. Skipped {} bytes of read-only or unmapped memory.",
+                        written, skipped
+                    ))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            else {
+                emu.gui
+                    .toasts()
+                    .info(format!("Filled {} bytes at {:05X}", written, start))
+                    .duration(Some(SHORT_NOTIFICATION_TIME));
+            }
+        }
+        GuiEvent::InjectInterrupt(irq) => {
+            // Only inject while paused, so the request is serviced at a deterministic point.
+            if !emu.exec_control.borrow().get_state().can_step() {
+                emu.gui
+                    .toasts()
+                    .error("IRQs can only be injected while the machine is paused.".to_string())
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+                return;
+            }
+            if emu.machine.inject_interrupt(*irq) {
+                log::debug!("Injected IRQ {}", irq);
+            }
+        }
         // Gui variables have a context, which is sort of like a namespace so that multiple versions
         // of a single GuiEnum can be stored - for example we have a Context per configured Display
         // target. A Global context is used if only a single instance of any GuiEnum is required.
@@ -116,12 +179,21 @@ pub fn handle_egui_event(
                 (GuiBoolean::CpuInstructionHistory, state) => {
                     emu.machine.set_cpu_option(CpuOption::InstructionHistory(state));
                 }
+                (GuiBoolean::CpuInstructionCoverage, state) => {
+                    emu.machine.set_cpu_option(CpuOption::InstructionCoverage(state));
+                }
                 (GuiBoolean::CpuTraceLoggingEnabled, state) => {
                     emu.machine.set_cpu_option(CpuOption::TraceLoggingEnabled(state));
                 }
                 (GuiBoolean::TurboButton, state) => {
                     emu.machine.set_turbo_mode(state);
                 }
+                (GuiBoolean::FastBoot, state) => {
+                    emu.machine.set_fast_boot(state);
+                }
+                (GuiBoolean::BreakOnMemoryEdit, state) => {
+                    emu.machine.set_option(MachineOption::BreakOnDebugWrite(state));
+                }
                 _ => {}
             },
             GuiVariable::Float(op, val) => match op {
@@ -146,6 +218,11 @@ pub fn handle_egui_event(
                             si.set_volume(*s_idx, Some(*vol), None);
                         }
                     }
+                    GuiEnum::AudioLatency(latency_ms) => {
+                        if let Some(si) = &mut emu.si {
+                            si.set_target_latency(*s_idx, *latency_ms);
+                        }
+                    }
                     _ => {}
                 },
                 GuiVariableContext::Display(dth) => match op {
@@ -190,6 +267,12 @@ pub fn handle_egui_event(
                                 Some(GuiVariableContext::Display(*dth)),
                             );
 
+                            // Update monochrome phosphor selection
+                            emu.gui.set_option_enum(
+                                GuiEnum::DisplayPhosphor(renderer.get_mono_phosphor()),
+                                Some(GuiVariableContext::Display(*dth)),
+                            );
+
                             // Update aspect correction checkbox state
                             let aspect_correct = renderer.get_params().aspect_correction;
                             let aspect_correct_on = !matches!(aspect_correct, AspectCorrectionMode::None);
@@ -206,6 +289,12 @@ pub fn handle_egui_event(
                             renderer.set_composite(*state);
                         });
                     }
+                    GuiEnum::DisplayPhosphor(phosphor) => {
+                        log::debug!("Got monochrome phosphor update event: {:?}", phosphor);
+                        dm.with_renderer_mut(*dth, |renderer| {
+                            renderer.set_mono_phosphor(*phosphor);
+                        });
+                    }
                     GuiEnum::DisplayAspectCorrect(state) => {
                         if let Err(_e) = dm.set_aspect_correction(*dth, *state) {
                             log::error!("Failed to set aspect correction state for display target!");
@@ -236,78 +325,84 @@ pub fn handle_egui_event(
                     }
                     _ => {}
                 },
-                GuiVariableContext::Global => {}
+                GuiVariableContext::VideoCard(vid) => match op {
+                    GuiEnum::VideoCardSnow(state) => {
+                        log::debug!("Got snow state update event for card {:?}: {}", vid, state);
+                        emu.machine.set_card_video_option(vid, VideoOption::EnableSnow(*state));
+                    }
+                    _ => {}
+                },
+                GuiVariableContext::Global => match op {
+                    GuiEnum::CpuHistoryDepth(depth) => {
+                        emu.machine.set_cpu_option(CpuOption::InstructionHistoryDepth(*depth));
+                    }
+                    _ => {}
+                },
                 _ => {
                     log::warn!("Unhandled enum context: {:?}", ctx);
                 }
             },
         },
         GuiEvent::LoadVHD(drive_idx, image_idx) => {
-            log::debug!("Releasing VHD slot: {}", drive_idx);
-            emu.vhd_manager.release_vhd(*drive_idx);
-
-            let mut error_str = None;
-
-            match emu.vhd_manager.load_vhd_file(*drive_idx, *image_idx) {
-                Ok(vhd_file) => match VirtualHardDisk::parse(Box::new(vhd_file), false) {
-                    Ok(vhd) => {
-                        if let Some(hdc) = emu.machine.hdc_mut() {
-                            match hdc.set_vhd(*drive_idx, vhd) {
-                                Ok(_) => {
-                                    let vhd_name = emu.vhd_manager.get_vhd_name(*image_idx).unwrap();
-                                    log::info!(
-                                        "VHD image {:?} successfully loaded into virtual drive: {}",
-                                        vhd_name,
-                                        *drive_idx
-                                    );
-
-                                    emu.gui
-                                        .toasts()
-                                        .info(format!("VHD loaded: {:?}", vhd_name))
-                                        .duration(Some(NORMAL_NOTIFICATION_TIME));
-                                }
-                                Err(err) => {
-                                    error_str = Some(format!("Error mounting VHD: {}", err));
-                                }
-                            }
-                        }
-                        else if let Some(hdc) = emu.machine.xtide_mut() {
-                            match hdc.set_vhd(*drive_idx, vhd) {
-                                Ok(_) => {
-                                    let vhd_name = emu.vhd_manager.get_vhd_name(*image_idx).unwrap();
-                                    log::info!(
-                                        "VHD image {:?} successfully loaded into virtual drive: {}",
-                                        vhd_name,
-                                        *drive_idx
-                                    );
-
-                                    emu.gui
-                                        .toasts()
-                                        .info(format!("VHD loaded: {:?}", vhd_name))
-                                        .duration(Some(NORMAL_NOTIFICATION_TIME));
-                                }
-                                Err(err) => {
-                                    error_str = Some(format!("Error mounting VHD: {}", err));
-                                }
-                            }
-                        }
-                        else {
-                            error_str = Some("No Hard Disk Controller present!".to_string());
-                        }
-                    }
-                    Err(err) => {
-                        error_str = Some(format!("Error loading VHD: {}", err));
-                    }
-                },
+            mount_vhd_image(emu, *drive_idx, *image_idx);
+        }
+        GuiEvent::SetVhdMountMode(drive_idx, mode) => {
+            // Remount the loaded image, if any, so the new mode takes effect.
+            log::debug!("Setting VHD mount mode for drive {}: {}", drive_idx, mode);
+            if let Some(image_idx) = emu.vhd_manager.get_drive_image_idx(*drive_idx) {
+                mount_vhd_image(emu, *drive_idx, image_idx);
+            }
+        }
+        GuiEvent::CommitVhdOverlay(drive_idx) | GuiEvent::DiscardVhdOverlay(drive_idx) => {
+            let commit = matches!(gui_event, GuiEvent::CommitVhdOverlay(_));
+            match reset_vhd_overlay(emu, *drive_idx, commit) {
+                Ok(sectors) => {
+                    let action = if commit { "committed to image" } else { "discarded" };
+                    emu.gui
+                        .toasts()
+                        .info(format!("VHD overlay {}: {} sector(s)", action, sectors))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
                 Err(err) => {
-                    error_str = Some(format!("Failed to load VHD image index {}: {}", *image_idx, err));
+                    log::error!("Failed to reset VHD overlay: {}", err);
+                    emu.gui
+                        .toasts()
+                        .error(format!("VHD overlay error: {}", err))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
                 }
             }
-
-            // Handle errors.
-            if let Some(err_str) = error_str {
-                log::error!("{}", err_str);
-                emu.gui.toasts().error(err_str).duration(Some(LONG_NOTIFICATION_TIME));
+        }
+        GuiEvent::VerifyVHD(drive_idx) => {
+            let result = emu
+                .machine
+                .vhd_mut(*drive_idx)
+                .map(|vhd| vhd.verify_bat().map(|_| vhd.is_differencing()));
+            match result {
+                Some(Ok(differencing)) => {
+                    let kind = if differencing {
+                        "differencing VHD and its parent"
+                    }
+                    else {
+                        "VHD"
+                    };
+                    emu.gui.modal.open(ModalContext::Notice(format!(
+                        "Verification complete. The {} in drive {} is consistent.",
+                        kind, drive_idx
+                    )));
+                }
+                Some(Err(err)) => {
+                    log::error!("VHD verification failed: {}", err);
+                    emu.gui.modal.open(ModalContext::Notice(format!(
+                        "Verification of the VHD in drive {} failed:\n{}",
+                        drive_idx, err
+                    )));
+                }
+                None => {
+                    emu.gui
+                        .toasts()
+                        .error(format!("No VHD mounted in drive {}", drive_idx))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
             }
         }
         GuiEvent::CreateVHD(filename, fmt) => {
@@ -413,8 +508,8 @@ pub fn handle_egui_event(
                                         .info(format!("Cartridge inserted: {:?}", name.clone()))
                                         .duration(Some(NORMAL_NOTIFICATION_TIME));
 
-                                    // Inserting a cartridge reboots the machine due to a switch in the cartridge slot.
-                                    reboot = true;
+                                    // Inserting a cartridge reboots the machine due to a switch in the cartridge slot, if enabled.
+                                    reboot = emu.config.emulator.media.cartridge_reset;
                                 }
                                 Err(err) => {
                                     log::error!("Cart image failed to load into slot {}: {}", slot_select, err);
@@ -447,9 +542,19 @@ pub fn handle_egui_event(
             }
         }
         GuiEvent::RemoveCartridge(slot_select) => {
-            // User requested to remove a PCjr cartridge from the indicated slot. This will reboot the machine.
+            // User requested to remove a PCjr cartridge from the indicated slot. This will reboot the machine if
+            // cartridge_reset is enabled.
             log::info!("Removing cartridge from slot: {}", slot_select);
 
+            // Without a hot-swap reset, a cartridge can only be removed while the machine is off.
+            if emu.machine.get_state().is_on() && !emu.config.emulator.media.cartridge_reset {
+                emu.gui
+                    .toasts()
+                    .error("Cartridge can only be removed while the machine is off.".to_string())
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+                return;
+            }
+
             let mut reboot = false;
             if let Some(cart_slot) = emu.machine.cart_slot() {
                 cart_slot.remove_cart(*slot_select);
@@ -459,7 +564,7 @@ pub fn handle_egui_event(
                     .info("Cartridge removed!".to_string())
                     .duration(Some(SHORT_NOTIFICATION_TIME));
 
-                reboot = true;
+                reboot = emu.config.emulator.media.cartridge_reset;
             }
             if reboot {
                 emu.machine.change_state(MachineState::Rebooting);
@@ -657,61 +762,51 @@ pub fn handle_egui_event(
                 filepath,
             );
 
-            if let Some(fdc) = emu.machine.fdc() {
-                let (disk_image_opt, _) = fdc.get_image(*drive_select);
-                if let Some(floppy_image) = disk_image_opt {
-                    let mut image = floppy_image.write().unwrap();
-                    match fluxfox::ImageWriter::new(&mut image)
-                        .with_format(*format)
-                        .with_path(filepath.clone())
-                        .write()
-                    {
-                        Ok(_) => {
-                            log::info!("Floppy image successfully saved: {:?}", filepath);
-
-                            emu.gui.set_floppy_selection(
-                                *drive_select,
-                                None,
-                                FloppyDriveSelection::Image(filepath.clone()),
-                                Some(*format),
-                                image.compatible_formats(true),
-                                None,
-                            );
-
-                            emu.gui
-                                .toasts()
-                                .info(format!("Floppy saved: {:?}", filepath.file_name().unwrap_or_default()))
-                                .duration(Some(NORMAL_NOTIFICATION_TIME));
-                        }
-                        Err(err) => {
-                            log::error!("Floppy image failed to save: {}", err);
-
-                            emu.gui
-                                .toasts()
-                                .error(format!("Failed to save: {}", err))
-                                .duration(Some(NORMAL_NOTIFICATION_TIME));
-                        }
-                    }
-                }
-            }
+            save_floppy(emu, *drive_select, *format, filepath);
         }
         GuiEvent::EjectFloppy(drive_select) => {
-            // User ejected the floppy from the drive slot 'drive_select'
-            log::info!("Ejecting floppy in drive: {}", drive_select);
-            if let Some(fdc) = emu.machine.fdc() {
-                fdc.unload_image(*drive_select);
-                emu.gui.set_floppy_selection(
-                    *drive_select,
-                    None,
-                    FloppyDriveSelection::None,
-                    None,
-                    Vec::new(),
-                    Some(false),
-                );
+            // User ejected the floppy from the drive slot 'drive_select'. If the image has been modified,
+            // ask whether to save it first.
+            if emu
+                .machine
+                .fdc()
+                .as_ref()
+                .is_some_and(|fdc| fdc.is_dirty(*drive_select))
+            {
+                emu.gui.modal.open(ModalContext::ConfirmFloppyEject(*drive_select));
+                return;
+            }
+            eject_floppy(emu, *drive_select);
+        }
+        GuiEvent::ForceEjectFloppy(drive_select) => {
+            // User chose to discard changes to the floppy in the drive slot 'drive_select'.
+            eject_floppy(emu, *drive_select);
+        }
+        GuiEvent::SaveAndEjectFloppy(drive_select) => {
+            // User chose to save changes to the floppy in the drive slot 'drive_select' before ejecting.
+            // TODO: Implement save floppy image on web, see RequestSaveFloppyDialog
+            #[cfg(target_arch = "wasm32")]
+            {
                 emu.gui
                     .toasts()
-                    .info("Floppy ejected!".to_string())
-                    .duration(Some(SHORT_NOTIFICATION_TIME));
+                    .error("Saving floppy images is not yet supported in the browser.".to_string())
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                match emu.gui.floppy_writeback_target(*drive_select) {
+                    Some((path, format)) => {
+                        if save_floppy(emu, *drive_select, format, &path) {
+                            eject_floppy(emu, *drive_select);
+                        }
+                    }
+                    None => {
+                        emu.gui
+                            .toasts()
+                            .error("This image can't be written back. Use Save As instead.".to_string())
+                            .duration(Some(NORMAL_NOTIFICATION_TIME));
+                    }
+                }
             }
         }
         GuiEvent::CreateNewFloppy(drive_select, format, formatted) => {
@@ -775,6 +870,45 @@ pub fn handle_egui_event(
                 fdc.write_protect(*drive_select, *state);
             }
         }
+        GuiEvent::VerifyFloppyImage(drive_select) => {
+            let bad_sectors = emu
+                .machine
+                .fdc()
+                .as_ref()
+                .and_then(|fdc| fdc.verify_image(*drive_select));
+            match bad_sectors {
+                Some(bad_sectors) => {
+                    emu.gui
+                        .modal
+                        .open(ModalContext::Notice(floppy_verify_report(&bad_sectors)));
+                }
+                None => {
+                    emu.gui
+                        .toasts()
+                        .error(format!("No image in floppy drive {}", drive_select))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::RepairFloppyImage(drive_select, image_idx) => {
+            match repair_floppy_image(emu, *drive_select, *image_idx) {
+                Ok((repaired, damaged)) => {
+                    emu.gui.modal.open(ModalContext::Notice(format!(
+                        "Repaired {} of {} damaged sector(s) in drive {}.\n\n\
+                    Sectors with damaged IDs, or that are also damaged in the reference image, can't be repaired.",
+                        repaired, damaged, drive_select
+                    )));
+                }
+                Err(err) => {
+                    log::error!("Floppy repair failed: {}", err);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Floppy repair failed: {}", err))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
         #[cfg(feature = "use_serialport")]
         GuiEvent::BridgeSerialPort(guest_port_id, host_port_name, host_port_id) => {
             log::info!("Bridging serial port: {}, id: {}", host_port_name, host_port_id);
@@ -805,6 +939,64 @@ pub fn handle_egui_event(
                 );
             }
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::StartSerialRecording(port_id) => {
+            let base_name = format!("serial{}", port_id);
+            let result = emu
+                .rm
+                .get_available_filename("recording", &base_name, Some(SERIAL_LOG_EXTENSION))
+                .and_then(|path| emu.machine.start_serial_recording(*port_id, &path).map(|_| path));
+
+            match result {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!("Recording serial port {} to {:?}", port_id, path))
+                        .duration(Some(SHORT_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to start serial recording: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to start serial recording: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::PlaySerialRecording(port_id) => {
+            let result = emu
+                .rm
+                .resource_path("recording")
+                .ok_or_else(|| anyhow::anyhow!("Resource path not found: recording"))
+                .and_then(|dir| latest_serial_recording(&dir))
+                .and_then(|path| emu.machine.start_serial_playback(*port_id, &path).map(|_| path));
+
+            match result {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!("Playing back {:?} into serial port {}", path, port_id))
+                        .duration(Some(SHORT_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to start serial playback: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to start serial playback: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::StopSerialCapture(port_id) => {
+            if let Err(e) = emu.machine.stop_serial_capture(*port_id) {
+                log::error!("Failed to stop serial recording: {}", e);
+                emu.gui
+                    .toasts()
+                    .error(format!("Failed to stop serial recording: {e}"))
+                    .duration(Some(LONG_NOTIFICATION_TIME));
+            }
+        }
         GuiEvent::DumpVRAM => {
             if let Some(video_card) = emu.machine.primary_videocard() {
                 let dump_path = emu.rm.resource_path("dump").unwrap();
@@ -869,6 +1061,177 @@ pub fn handle_egui_event(
                     None
                 });
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::SaveState(slot) => match savestate_manager::save_state(&mut emu.machine, &mut emu.rm, *slot) {
+            Ok(path) => {
+                emu.gui
+                    .toasts()
+                    .info(format!("Machine state saved: {:?}", path))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            Err(e) => {
+                log::error!("Failed to save machine state: {}", e);
+                emu.gui
+                    .toasts()
+                    .error(format!("Failed to save machine state: {e}"))
+                    .duration(Some(LONG_NOTIFICATION_TIME));
+            }
+        },
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::LoadState(slot) => match savestate_manager::load_state(&mut emu.machine, &emu.rm, *slot) {
+            Ok(path) => {
+                emu.gui
+                    .toasts()
+                    .info(format!("Machine state restored: {:?}", path))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            Err(e) => {
+                log::error!("Failed to restore machine state: {}", e);
+                emu.gui
+                    .toasts()
+                    .error(format!("Failed to restore machine state: {e}"))
+                    .duration(Some(LONG_NOTIFICATION_TIME));
+            }
+        },
+        GuiEvent::DumpCoverage => {
+            let report = emu.machine.cpu().coverage_report();
+            log::info!(
+                "Instruction coverage: {} distinct shapes, {} untouched primary opcodes",
+                report.shapes.len(),
+                report.untouched_opcodes.len()
+            );
+            match emu
+                .rm
+                .get_available_filename("dump", "coverage", Some("csv"))
+                .map_err(|e| e.to_string())
+                .and_then(|path| {
+                    std::fs::write(&path, report.to_csv())
+                        .map(|_| path)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!(
+                            "Coverage report saved: {:?} ({} untouched opcodes)",
+                            path,
+                            report.untouched_opcodes.len()
+                        ))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to save coverage report: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to save coverage report: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::DumpCoverageBitmap => {
+            let summary = emu.machine.bus().exec_coverage_summary();
+            for (segment, covered) in summary.iter().enumerate().filter(|(_, covered)| **covered > 0) {
+                log::info!("Executed bytes in segment {:X}000: {}", segment, covered);
+            }
+            let covered: usize = summary.iter().sum();
+            match emu
+                .rm
+                .get_available_filename("dump", "coverage", Some("bin"))
+                .map_err(|e| e.to_string())
+                .and_then(|path| {
+                    std::fs::write(&path, emu.machine.bus().exec_coverage())
+                        .map(|_| path)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!(
+                            "Coverage bitmap saved: {:?} ({} bytes executed)",
+                            path, covered
+                        ))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to save coverage bitmap: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to save coverage bitmap: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ExportSymbols => {
+            let symbols = emu.machine.cpu().export_discovered_symbols();
+            match emu
+                .rm
+                .get_available_filename("dump", "symbols", Some("map"))
+                .map_err(|e| e.to_string())
+                .and_then(|path| {
+                    std::fs::write(&path, to_symbol_file(&symbols))
+                        .map(|_| path)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!("Exported {} symbols: {:?}", symbols.len(), path))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to export symbols: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to export symbols: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ImportSymbols => {
+            // Import the most recently modified symbol file, which is the one the user just edited.
+            let latest = emu
+                .rm
+                .resource_path("dump")
+                .ok_or_else(|| "Resource path not found: dump".to_string())
+                .and_then(|dir| std::fs::read_dir(dir).map_err(|e| e.to_string()))
+                .and_then(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("map")))
+                        .max_by_key(|path| {
+                            std::fs::metadata(path)
+                                .and_then(|m| m.modified())
+                                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+                        })
+                        .ok_or_else(|| "No symbol files found".to_string())
+                });
+            match latest.and_then(|path| {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| parse_symbol_file(&text))
+                    .map(|symbols| (path, symbols))
+            }) {
+                Ok((path, symbols)) => {
+                    let count = symbols.len();
+                    emu.machine.cpu_mut().set_symbols(SymbolTable::from(symbols));
+                    emu.gui
+                        .toasts()
+                        .info(format!("Imported {} symbols: {:?}", count, path))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to import symbols: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to import symbols: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ClearCoverage => {
+            emu.machine.cpu_mut().clear_coverage();
+        }
         GuiEvent::EditBreakpoint => {
             // Get breakpoints from GUI
             let bp_set = emu.gui.get_breakpoints();
@@ -894,7 +1257,11 @@ pub fn handle_egui_event(
             // Push int breakpoint to list
             if let Ok(iv) = u32::from_str_radix(bp_set.int_breakpoint, 10) {
                 if iv < 256 {
-                    breakpoints.push(BreakPointType::Interrupt(iv as u8));
+                    // Filter on AH if a valid function number is given.
+                    match u8::from_str_radix(bp_set.int_ah_filter, 16) {
+                        Ok(ah) => breakpoints.push(BreakPointType::InterruptFunc { int: iv as u8, ah }),
+                        Err(_) => breakpoints.push(BreakPointType::Interrupt(iv as u8)),
+                    }
                 }
             }
 
@@ -905,6 +1272,12 @@ pub fn handle_egui_event(
                 breakpoints.push(BreakPointType::IoAccess(port));
             }
 
+            // Push raster line breakpoint to list
+            if let Ok(line) = u16::from_str_radix(bp_set.raster_breakpoint, 10) {
+                log::debug!("Adding raster line breakpoint: {}", line);
+                breakpoints.push(BreakPointType::RasterLine(line));
+            }
+
             // Push stopwatches to list
             if let Some(addr) = emu.machine.cpu().eval_address(bp_set.sw_start) {
                 let start_flat_addr = u32::from(addr);
@@ -943,25 +1316,45 @@ pub fn handle_egui_event(
                 emu.gui.memory_viewer.set_address(mem_dump_addr as usize);
             }
         }
-        GuiEvent::MemoryByteUpdate(addr, val) => {
-            // The user has changed a memory value in the memory viewer.
-            // We need to update the memory contents in the emulator.
-            _ = emu.machine.bus_mut().write_u8(*addr, *val, 0);
+        GuiEvent::MemoryEdit(addr, data) => {
+            // The user has changed memory in the memory viewer.
+            let force = emu.gui.memory_viewer.force_rom_writes;
+            let (_written, rejected) = emu.machine.edit_memory(*addr as u32, data, force);
+            if rejected > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!("{} byte(s) not written: ROM or unpopulated memory", rejected))
+                    .duration(Some(SHORT_NOTIFICATION_TIME));
+            }
+        }
+        GuiEvent::UndoMemoryEdit => {
+            if !emu.machine.undo_memory_change() {
+                emu.gui
+                    .toasts()
+                    .warning("No memory change to undo.".to_string())
+                    .duration(Some(SHORT_NOTIFICATION_TIME));
+            }
         }
-        GuiEvent::Register16Update(reg, val) => {
+        GuiEvent::SetRegister(reg, val) => {
             // The user has changed a 16-bit register value in the register viewer.
             // We need to update the register contents in the emulator.
+            if let Register16::PC = reg {
+                // Flush the instruction queue so that execution resumes at the new IP.
+                emu.machine.cpu_mut().flush_piq();
+            }
             emu.machine.cpu_mut().set_register16(*reg, *val);
         }
-        GuiEvent::CpuFlagsUpdate(flags) => {
-            // The user has changed the CPU flags in the register viewer.
-            // We need to update the flags in the emulator.
-            emu.machine.cpu_mut().set_flags(*flags);
+        GuiEvent::SetFlag(flag, state) => {
+            // The user has toggled a CPU flag in the register viewer.
+            emu.machine.cpu_mut().set_flag_state(*flag, *state);
         }
         GuiEvent::CpuFlushQueue => {
             // The user has requested to clear the CPU instruction queue.
             emu.machine.cpu_mut().flush_piq();
         }
+        GuiEvent::StepOut => {
+            emu.exec_control.borrow_mut().set_op(ExecutionOperation::StepOut);
+        }
         GuiEvent::TokenHover(addr) => {
             // Hovered over a token in a TokenListView.
             let cpu_type = emu.machine.cpu().get_type();
@@ -1031,6 +1424,13 @@ pub fn handle_egui_event(
                     .duration(Some(LONG_NOTIFICATION_TIME));
             }
         }
+        GuiEvent::CopyTextScreen(dt_idx) => {
+            text_screen::copy_text_screen(emu, *dt_idx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::SaveTextScreen(dt_idx) => {
+            text_screen::save_text_screen(emu, *dt_idx);
+        }
         GuiEvent::ToggleFullscreen(_dt_idx) => {
             // User requested to toggle fullscreen mode
             let _ = emu.sender.send(FrontendThreadEvent::ToggleFullscreen);
@@ -1039,6 +1439,141 @@ pub fn handle_egui_event(
             // User requested to send CTRL + ALT + DEL keyboard combination
             emu.machine.emit_ctrl_alt_del();
         }
+        GuiEvent::TypeText(text) => {
+            // User requested to type text into the machine
+            let skipped = emu.machine.inject_text(text);
+            if skipped > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!("{} character(s) could not be typed", skipped))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::PasteClipboard => {
+            // User requested to type the contents of the host clipboard into the machine
+            let mut clipboard = egui_winit::clipboard::Clipboard::new(None);
+            match clipboard.get() {
+                Some(text) if !text.is_empty() => {
+                    // Don't type an extra Enter for each line of text copied on Windows
+                    let skipped = emu.machine.inject_text(&text.replace("\r\n", "\n"));
+                    if skipped > 0 {
+                        emu.gui
+                            .toasts()
+                            .warning(format!("{} character(s) could not be typed", skipped))
+                            .duration(Some(NORMAL_NOTIFICATION_TIME));
+                    }
+                }
+                _ => {
+                    emu.gui
+                        .toasts()
+                        .info("Clipboard is empty".to_string())
+                        .duration(Some(SHORT_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::NewMachineFromTemplate(preset, overrides) => {
+            let config = preset.to_machine_config_with(overrides);
+            let valid = get_machine_descriptor(config.machine_type)
+                .map(|desc| config.validate(desc).map_err(|e| e.to_string()))
+                .unwrap_or_else(|| Err(format!("No descriptor for machine type {:?}", config.machine_type)));
+
+            if let Err(err) = valid {
+                log::error!("Machine template {} is invalid: {}", preset, err);
+                emu.gui
+                    .toasts()
+                    .error(format!("Machine template {} is invalid: {}", preset, err))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+                return;
+            }
+
+            // The machine is built once at startup, so build the new machine by starting a new instance
+            // of MartyPC with the template selected, then shutting down this one.
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let restart = std::env::current_exe().and_then(|exe| {
+                    std::process::Command::new(exe)
+                        .args(template_args(*preset, overrides))
+                        .spawn()
+                });
+                match restart {
+                    Ok(_) => {
+                        log::info!("Restarting to build a new {} from template...", preset);
+                        let _ = emu.sender.send(FrontendThreadEvent::QuitRequested);
+                    }
+                    Err(e) => {
+                        log::error!("Failed to restart MartyPC: {}", e);
+                        emu.gui
+                            .toasts()
+                            .error(format!("Failed to restart MartyPC: {}", e))
+                            .duration(Some(NORMAL_NOTIFICATION_TIME));
+                    }
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            {
+                emu.gui
+                    .toasts()
+                    .info(format!(
+                        "Reload with ?machine_template={} to use a {}",
+                        preset.config_name(),
+                        preset
+                    ))
+                    .duration(Some(LONG_NOTIFICATION_TIME));
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::ConfigurationChanged => {
+            // The machine can only be configured at startup, so apply the new configuration by starting
+            // a new instance of MartyPC with the same arguments, then shutting down this one.
+            let restart = std::env::current_exe().and_then(|exe| {
+                std::process::Command::new(exe)
+                    .args(std::env::args_os().skip(1))
+                    .spawn()
+            });
+            match restart {
+                Ok(_) => {
+                    log::info!("Restarting to apply changed machine configuration...");
+                    let _ = emu.sender.send(FrontendThreadEvent::QuitRequested);
+                }
+                Err(e) => {
+                    log::error!("Failed to restart MartyPC: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to restart MartyPC: {}", e))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::WaitFrames(_) => {
+            // Waits are handled by the command script itself.
+        }
+        GuiEvent::AssertMemory(address, expected) => {
+            // A command script is checking memory contents
+            let actual = (0..expected.len())
+                .map(|i| emu.machine.bus().peek_u8(*address as usize + i).unwrap_or(0xFF))
+                .collect::<Vec<u8>>();
+            if actual != *expected {
+                log::error!(
+                    "Memory assertion failed at {:05X}: expected {:02X?}, found {:02X?}. Stopping script.",
+                    address,
+                    expected,
+                    actual
+                );
+                emu.gui
+                    .toasts()
+                    .error(format!("Memory assertion failed at {:05X}", address))
+                    .duration(Some(LONG_NOTIFICATION_TIME));
+                emu.command_script = None;
+            }
+            else {
+                log::debug!("Memory assertion passed at {:05X}", address);
+            }
+        }
+        GuiEvent::ClearPostCodes => {
+            // User requested to clear the POST code log
+            emu.machine.clear_post_codes();
+        }
         GuiEvent::CompositeAdjust(dt, params) => {
             // User adjusted the composite video parameters
             dm.with_renderer(*dt, |renderer| {
@@ -1070,8 +1605,412 @@ pub fn handle_egui_event(
             // User stopped recording disassembly
             emu.machine.set_option(MachineOption::RecordListing(false));
         }
+        GuiEvent::StartSpeakerRecording => {
+            // Record at the output sample rate if we have one, so recordings match what was heard.
+            let sample_rate = emu
+                .si
+                .as_ref()
+                .map(|si| si.config().sample_rate)
+                .unwrap_or(SPEAKER_RECORDING_DEFAULT_RATE);
+
+            if emu.machine.start_speaker_recording(sample_rate) {
+                emu.speaker_recording.clear();
+                emu.speaker_recording_rate = sample_rate;
+                emu.gui
+                    .toasts()
+                    .info("Recording PC speaker audio...".to_string())
+                    .duration(Some(SHORT_NOTIFICATION_TIME));
+            }
+            else {
+                emu.gui
+                    .toasts()
+                    .error("This machine has no PC speaker to record.".to_string())
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+        }
+        GuiEvent::StopSpeakerRecording => {
+            if let Some(samples) = emu.machine.stop_speaker_recording() {
+                emu.speaker_recording.extend(samples);
+            }
+            let seconds = emu.speaker_recording.len() as f64 / emu.speaker_recording_rate.max(1) as f64;
+            emu.gui
+                .toasts()
+                .info(format!("Speaker recording stopped ({:.1}s).", seconds))
+                .duration(Some(SHORT_NOTIFICATION_TIME));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        GuiEvent::SaveSpeakerRecording => {
+            // Older configurations may not define a 'recording' resource.
+            let result = emu
+                .rm
+                .get_available_filename("recording", "speaker", Some("wav"))
+                .or_else(|_| emu.rm.get_available_filename("dump", "speaker", Some("wav")))
+                .and_then(|path| {
+                    let mut writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                    write_wav(&mut writer, emu.speaker_recording_rate, 1, &emu.speaker_recording)?;
+                    Ok(path)
+                });
+
+            match result {
+                Ok(path) => {
+                    emu.gui
+                        .toasts()
+                        .info(format!("Speaker recording saved: {:?}", path))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(e) => {
+                    log::error!("Failed to save speaker recording: {}", e);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Failed to save speaker recording: {e}"))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+        }
+        GuiEvent::ShowDisassembly(address) => {
+            // User clicked on an address to view it in the disassembly viewer
+            emu.gui.disassembly_viewer.set_address(address.clone());
+            emu.gui.set_window_open(GuiWindow::DisassemblyViewer, true);
+        }
+        GuiEvent::NavigateDisassembly(address) => {
+            // User double-clicked a jump or call target in the disassembly viewer
+            emu.gui.disassembly_viewer.navigate(*address);
+        }
+        GuiEvent::AssembleInstruction(address, text) => {
+            // User entered an instruction to patch into memory in the disassembly viewer
+            assemble_instruction(emu, address, text);
+        }
+        #[cfg(feature = "lua_scripting")]
+        GuiEvent::RunScript(source) => {
+            // User ran a script from the script console.
+            match emu.script_engine.run(&mut emu.machine, source) {
+                Ok(output) => {
+                    for msg in output.messages {
+                        emu.gui.script_console.push_output(&msg);
+                        emu.gui.toasts().info(msg).duration(Some(NORMAL_NOTIFICATION_TIME));
+                    }
+                    if let Some(op) = output.exec_op {
+                        emu.exec_control.borrow_mut().set_op(op);
+                    }
+                }
+                Err(err) => {
+                    log::error!("{}", err);
+                    emu.gui.script_console.push_output(&err.to_string());
+                }
+            }
+        }
         _ => {
             log::warn!("Unhandled GUI event: {:?}", discriminant(gui_event));
         }
     }
 }
+
+/// Build the command line for a new instance of MartyPC using the specified machine template. Any
+/// template arguments we were started with are replaced; all other arguments are kept.
+#[cfg(not(target_arch = "wasm32"))]
+fn template_args(preset: MachinePreset, overrides: &PresetOverrides) -> Vec<OsString> {
+    const TEMPLATE_ARGS: [&str; 3] = ["--machine_template", "--template_memory", "--template_video"];
+
+    let mut args = Vec::new();
+    let mut old_args = std::env::args_os().skip(1);
+    while let Some(arg) = old_args.next() {
+        let arg_str = arg.to_string_lossy();
+        if TEMPLATE_ARGS.contains(&arg_str.as_ref()) {
+            // Skip the argument's value, too.
+            old_args.next();
+        }
+        else if !TEMPLATE_ARGS.iter().any(|t| arg_str.starts_with(&format!("{}=", t))) {
+            args.push(arg);
+        }
+    }
+
+    args.push("--machine_template".into());
+    args.push(preset.config_name().into());
+    if let Some(size) = overrides.conventional_memory {
+        args.push("--template_memory".into());
+        args.push((size / 1024).to_string().into());
+    }
+    if let Some(card) = overrides.video.as_ref().and_then(|video| video.first()) {
+        args.push("--template_video".into());
+        args.push(format!("{:?}", card.video_type).into());
+    }
+    args
+}
+
+/// Write the floppy image in the specified drive to `filepath` in the given format. Returns true on success.
+fn save_floppy(emu: &mut Emulator, drive_select: usize, format: DiskImageFileFormat, filepath: &Path) -> bool {
+    let Some(fdc) = emu.machine.fdc()
+    else {
+        return false;
+    };
+    let (disk_image_opt, _) = fdc.get_image(drive_select);
+    let Some(floppy_image) = disk_image_opt
+    else {
+        return false;
+    };
+
+    let mut image = floppy_image.write().unwrap();
+    match fluxfox::ImageWriter::new(&mut image)
+        .with_format(format)
+        .with_path(filepath.to_path_buf())
+        .write()
+    {
+        Ok(_) => {
+            log::info!("Floppy image successfully saved: {:?}", filepath);
+            fdc.clear_dirty(drive_select);
+
+            emu.gui.set_floppy_selection(
+                drive_select,
+                None,
+                FloppyDriveSelection::Image(filepath.to_path_buf()),
+                Some(format),
+                image.compatible_formats(true),
+                None,
+            );
+
+            emu.gui
+                .toasts()
+                .info(format!("Floppy saved: {:?}", filepath.file_name().unwrap_or_default()))
+                .duration(Some(NORMAL_NOTIFICATION_TIME));
+            true
+        }
+        Err(err) => {
+            log::error!("Floppy image failed to save: {}", err);
+
+            emu.gui
+                .toasts()
+                .error(format!("Failed to save: {}", err))
+                .duration(Some(NORMAL_NOTIFICATION_TIME));
+            false
+        }
+    }
+}
+
+/// Eject the floppy in the specified drive, discarding any unsaved changes.
+fn eject_floppy(emu: &mut Emulator, drive_select: usize) {
+    log::info!("Ejecting floppy in drive: {}", drive_select);
+    if let Some(fdc) = emu.machine.fdc() {
+        fdc.unload_image(drive_select);
+        emu.gui.set_floppy_selection(
+            drive_select,
+            None,
+            FloppyDriveSelection::None,
+            None,
+            Vec::new(),
+            Some(false),
+        );
+        emu.gui
+            .toasts()
+            .info("Floppy ejected!".to_string())
+            .duration(Some(SHORT_NOTIFICATION_TIME));
+    }
+}
+
+/// Load the specified VHD image into a hard disk drive, using the mount mode selected in the GUI.
+fn mount_vhd_image(emu: &mut Emulator, drive_idx: usize, image_idx: usize) {
+    log::debug!("Releasing VHD slot: {}", drive_idx);
+    emu.vhd_manager.release_vhd(drive_idx);
+
+    match emu.vhd_manager.load_vhd_file(drive_idx, image_idx) {
+        Ok(vhd_file) => {
+            let vhd_name = emu.vhd_manager.get_vhd_name(image_idx).unwrap_or_default();
+            attach_vhd_image(emu, Box::new(vhd_file), drive_idx, &vhd_name);
+        }
+        Err(err) => {
+            let err_str = format!("Failed to load VHD image index {}: {}", image_idx, err);
+            log::error!("{}", err_str);
+            emu.gui.toasts().error(err_str).duration(Some(LONG_NOTIFICATION_TIME));
+        }
+    }
+}
+
+/// Open a VHD image in the current mount mode and attach it to the specified drive of the
+/// machine's hard disk controller. Returns true if the image was attached.
+pub fn attach_vhd_image(emu: &mut Emulator, vhd_file: Box<dyn VhdIO>, drive_idx: usize, vhd_name: &OsStr) -> bool {
+    let mut error_str = None;
+
+    let mode = emu.gui.hdd_mount_mode(drive_idx);
+    match emu.open_vhd(vhd_file, drive_idx, mode) {
+        Ok(vhd) => {
+            let result = if let Some(hdc) = emu.machine.hdc_mut() {
+                hdc.set_vhd(drive_idx, vhd).map_err(|err| err.to_string())
+            }
+            else if let Some(hdc) = emu.machine.xtide_mut() {
+                hdc.set_vhd(drive_idx, vhd).map_err(|err| err.to_string())
+            }
+            else {
+                Err("No Hard Disk Controller present!".to_string())
+            };
+
+            match result {
+                Ok(_) => {
+                    log::info!(
+                        "VHD image {:?} successfully loaded into virtual drive: {}",
+                        vhd_name,
+                        drive_idx
+                    );
+
+                    emu.gui
+                        .toasts()
+                        .info(format!("VHD loaded: {:?} ({})", vhd_name, mode))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+                }
+                Err(err) => {
+                    error_str = Some(format!("Error mounting VHD: {}", err));
+                }
+            }
+        }
+        Err(err) => {
+            error_str = Some(format!("Error loading VHD: {}", err));
+        }
+    }
+
+    // Handle errors.
+    if let Some(err_str) = error_str {
+        log::error!("{}", err_str);
+        emu.gui.toasts().error(err_str).duration(Some(LONG_NOTIFICATION_TIME));
+        return false;
+    }
+    true
+}
+
+/// Commit or discard the copy-on-write overlay of the VHD in the specified drive, starting a new,
+/// empty overlay against the resulting base image. Returns the number of sectors affected.
+fn reset_vhd_overlay(emu: &mut Emulator, drive_idx: usize, commit: bool) -> Result<usize, Error> {
+    let vhd = emu
+        .machine
+        .vhd_mut(drive_idx)
+        .ok_or_else(|| anyhow::anyhow!("No VHD mounted in drive {}", drive_idx))?;
+
+    let sectors = match commit {
+        true => vhd.commit_overlay()?,
+        false => vhd.overlay().map(|overlay| overlay.len()).unwrap_or(0),
+    };
+    let base_hash = vhd.content_hash()?;
+
+    let overlay = emu.new_vhd_overlay(drive_idx, base_hash)?;
+    if let Some(vhd) = emu.machine.vhd_mut(drive_idx) {
+        vhd.attach_overlay(overlay);
+    }
+    Ok(sectors)
+}
+
+/// Summarize the damaged sectors found by verifying a floppy image.
+fn floppy_verify_report(bad_sectors: &[BadSector]) -> String {
+    const MAX_REPORTED_SECTORS: usize = 20;
+
+    if bad_sectors.is_empty() {
+        return "Verification complete. No damaged sectors were found.".to_string();
+    }
+
+    let mut report = format!("Verification found {} damaged sector(s):\n", bad_sectors.len());
+    for bad_sector in bad_sectors.iter().take(MAX_REPORTED_SECTORS) {
+        let error = match (bad_sector.address_crc_error, bad_sector.data_crc_error) {
+            (true, true) => "address and data CRC errors",
+            (true, false) => "address CRC error",
+            _ => "data CRC error",
+        };
+        report.push_str(&format!(
+            "\nc:{} h:{} sector {}: {}",
+            bad_sector.phys_ch.c(),
+            bad_sector.phys_ch.h(),
+            bad_sector.chsn,
+            error
+        ));
+    }
+    if bad_sectors.len() > MAX_REPORTED_SECTORS {
+        report.push_str(&format!("\n...and {} more.", bad_sectors.len() - MAX_REPORTED_SECTORS));
+    }
+    report
+}
+
+/// Repair the damaged sectors of the image in the specified drive from the floppy image with the
+/// specified index in the floppy manager. Returns the number of sectors repaired, and the number
+/// of damaged sectors found.
+#[cfg(not(target_arch = "wasm32"))]
+fn repair_floppy_image(emu: &mut Emulator, drive_select: usize, image_idx: usize) -> Result<(usize, usize), Error> {
+    let path = emu
+        .floppy_manager
+        .get_floppy_path(image_idx)
+        .ok_or_else(|| anyhow::anyhow!("Failed to resolve index to floppy path"))?;
+
+    let (image_vec, path) = match emu.floppy_manager.load_floppy_by_path(path, &mut emu.rm)? {
+        FloppyImageSource::DiskImage(image_vec, path) | FloppyImageSource::KryoFluxSet(image_vec, path) => {
+            (image_vec, path)
+        }
+        FloppyImageSource::ZipArchive(..) => {
+            return Err(anyhow::anyhow!("A zip archive can't be used as a reference image"));
+        }
+    };
+    let mut reference = DiskImage::load(&mut Cursor::new(image_vec), Some(&path), None, None)?;
+
+    let fdc = emu
+        .machine
+        .fdc()
+        .as_mut()
+        .ok_or_else(|| anyhow::anyhow!("No floppy controller present"))?;
+    let bad_sectors = fdc
+        .verify_image(drive_select)
+        .ok_or_else(|| anyhow::anyhow!("No image in floppy drive {}", drive_select))?;
+    let repaired = fdc.repair_image(drive_select, &mut reference, &bad_sectors)?;
+    Ok((repaired, bad_sectors.len()))
+}
+
+/// Find the most recently written serial recording in the given directory.
+#[cfg(not(target_arch = "wasm32"))]
+fn latest_serial_recording(dir: &Path) -> anyhow::Result<PathBuf> {
+    std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(SERIAL_LOG_EXTENSION))
+        })
+        .max_by_key(|path| {
+            std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+        })
+        .ok_or_else(|| anyhow::anyhow!("No serial recordings found"))
+}
+
+/// Assemble an instruction entered in the disassembly viewer and write it to memory.
+fn assemble_instruction(emu: &mut Emulator, address_str: &str, text: &str) {
+    let Some(address) = emu.machine.cpu().eval_address(address_str)
+    else {
+        emu.gui
+            .toasts()
+            .error(format!("Invalid address: {}", address_str))
+            .duration(Some(NORMAL_NOTIFICATION_TIME));
+        return;
+    };
+
+    match cpu_common::assemble(text, address.offset()) {
+        Ok(bytes) => {
+            let skipped = emu.machine.bus_mut().poke_slice(address.into(), &bytes);
+            if skipped > 0 {
+                emu.gui
+                    .toasts()
+                    .warning(format!(
+                        "Skipped {} of {} bytes at {}: memory is read-only or unmapped.",
+                        skipped,
+                        bytes.len(),
+                        address
+                    ))
+                    .duration(Some(NORMAL_NOTIFICATION_TIME));
+            }
+            else {
+                log::debug!("Assembled '{}' at {}: {:02X?}", text, address, bytes);
+                emu.gui
+                    .disassembly_viewer
+                    .set_assembled(address.offset_by(bytes.len() as u16));
+            }
+        }
+        Err(e) => {
+            emu.gui
+                .toasts()
+                .error(format!("Couldn't assemble '{}': {}", text, e))
+                .duration(Some(NORMAL_NOTIFICATION_TIME));
+        }
+    }
+}
@@ -37,8 +37,9 @@ use marty_core::{
     cpu_808x::Cpu,
     cpu_common,
     cpu_common::{CpuAddress, CpuOption, TraceMode},
+    device_traits::realtimeclock::RtcDispatch,
     machine,
-    syntax_token::SyntaxToken,
+    syntax_token::{HighlightType, SyntaxFormatType, SyntaxToken},
     util,
 };
 use marty_egui::GuiWindow;
@@ -66,9 +67,49 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
         }
     }
 
+    // Run any commands due this frame from the boot script
+    if let Some(script) = &mut emu.command_script {
+        let events = script.frame_events();
+        for event in events {
+            handle_egui_event(emu, dm, tm, tmu, &event);
+            if emu.command_script.is_none() {
+                // A failed assertion stopped the script.
+                break;
+            }
+        }
+        if emu.command_script.as_ref().is_some_and(|script| script.is_finished()) {
+            emu.command_script = None;
+        }
+    }
+
     // -- Update machine state
     emu.gui.set_machine_state(emu.machine.get_state());
 
+    // -- Update POST code status display
+    emu.gui.set_post_code(emu.machine.last_post_code());
+
+    // -- Update keyboard lock indicators
+    emu.gui.set_keyboard_lock_state(Some(emu.machine.keyboard_lock_state()));
+
+    // -- Update text mode status of each video card, for the text screen actions
+    dm.for_each_card(|vid| {
+        let text_mode = emu
+            .machine
+            .bus()
+            .video(vid)
+            .is_some_and(|card| card.get_text_buffer().is_some());
+        emu.gui.set_card_text_mode(*vid, text_mode);
+    });
+
+    // -- Collect recorded PC speaker samples
+    let recording = emu.machine.is_recording_speaker();
+    if recording {
+        let samples = emu.machine.get_speaker_samples();
+        emu.speaker_recording.extend(samples);
+    }
+    emu.gui
+        .set_speaker_recording_state(recording, !emu.speaker_recording.is_empty());
+
     // -- Update sound sources
     if let Some(si) = emu.si.as_ref() {
         emu.gui.set_sound_state(si.info());
@@ -126,18 +167,23 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
         let (_, frame_history) = tm.get_perf_stats();
 
         //emu.gui.perf_viewer.update_video_data(*video.params());
-        emu.gui.perf_viewer.update(dti, sound_stats, &emu.perf, frame_history)
+        emu.gui.perf_viewer.update(dti, sound_stats, &emu.perf, frame_history);
+        emu.gui.perf_viewer.update_frame_profile(emu.frame_profiler.snapshot());
     }
 
+    // The memory viewer's undo count also gates the fill menu's undo button, so update it regardless.
+    emu.gui.memory_viewer.set_undo_len(emu.machine.memory_undo_len());
+
     // -- Update memory viewer window if open
     if emu.gui.is_window_open(GuiWindow::MemoryViewer) {
         let vewport_len = emu.gui.memory_viewer.viewport_len();
+        let layout = emu.gui.memory_viewer.layout();
         let (mem_dump_addr_str, _source) = emu.gui.memory_viewer.get_address();
         let (addr, mem_dump_addr) = match emu.machine.cpu().eval_address(&mem_dump_addr_str) {
             Some(i) => {
                 let addr: u32 = i.into();
-                // Dump at 16 byte block boundaries
-                (addr, addr & !0x0F)
+                // Dump at row boundaries
+                (addr, addr & !(layout.row_len as u32 - 1))
             }
             None => {
                 // Show address 0 if expression eval fails
@@ -148,12 +194,40 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
         let mem_dump_vec = emu
             .machine
             .bus()
-            .dump_flat_tokens_ex(mem_dump_addr as usize, addr as usize, vewport_len);
+            .dump_flat_tokens_layout(mem_dump_addr as usize, addr as usize, vewport_len, &layout);
 
         //framework.gui.memory_viewer.set_row(mem_dump_addr as usize);
 
         emu.gui.memory_viewer.set_address(addr as usize);
-        emu.gui.memory_viewer.set_memory(mem_dump_vec);
+        emu.gui.memory_viewer.set_memory(mem_dump_vec, emu.machine.cpu_cycles());
+        let bus = emu.machine.bus();
+        let executed = (0..vewport_len)
+            .map(|i| bus.is_executed(mem_dump_addr as usize + i))
+            .collect();
+        emu.gui.memory_viewer.set_executed(mem_dump_addr as usize, executed);
+        let rom = (0..vewport_len)
+            .map(|i| bus.is_rom(mem_dump_addr as usize + i))
+            .collect();
+        emu.gui.memory_viewer.set_rom(mem_dump_addr as usize, rom);
+        if emu.gui.memory_viewer.show_heatmap {
+            emu.gui.memory_viewer.set_page_stats(emu.machine.bus().get_page_stats());
+        }
+    }
+
+    // Page access counts are only collected while the memory viewer is showing its heat map.
+    let heatmap = emu.gui.is_window_open(GuiWindow::MemoryViewer) && emu.gui.memory_viewer.show_heatmap;
+    if emu.machine.bus().page_stats_enabled() != heatmap {
+        emu.machine.bus_mut().set_page_stats_enabled(heatmap);
+    }
+
+    // -- Update memory access profile window
+    // The profiler only runs while its window is open, so it costs nothing otherwise.
+    let profiling = emu.gui.is_window_open(GuiWindow::AccessProfileViewer);
+    if emu.machine.bus().access_profiler_enabled() != profiling {
+        emu.machine.bus_mut().set_access_profiler_enabled(profiling);
+    }
+    if let Some(profile) = emu.machine.bus().access_profile() {
+        emu.gui.access_profile_viewer.set_profile(profile);
     }
 
     // Update data visualizer
@@ -190,8 +264,8 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
 
     // -- Update IVR viewer window if open
     if emu.gui.is_window_open(GuiWindow::IvtViewer) {
-        let vec = emu.machine.bus_mut().dump_ivt_tokens();
-        emu.gui.ivt_viewer.set_content(vec);
+        let ivt = emu.machine.bus().dump_ivt();
+        emu.gui.ivt_viewer.set_content(ivt, emu.machine.cpu().get_int_counts());
     }
 
     // -- Update IO stats viewer window if open
@@ -200,6 +274,32 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
         emu.gui.io_stats_viewer.set_content(vec);
     }
 
+    // -- Update POST code viewer window
+    if emu.gui.is_window_open(GuiWindow::PostCodeViewer) {
+        let log = emu.machine.post_code_log();
+        emu.gui.post_code_viewer.set_content(log);
+    }
+
+    // -- Update CMOS viewer window
+    if emu.gui.is_window_open(GuiWindow::CmosViewer) {
+        // Only the MC146818 has CMOS RAM.
+        let cmos = match emu.machine.bus().rtc() {
+            Some(RtcDispatch::Rtc(rtc)) => Some(rtc.cmos()),
+            _ => None,
+        };
+        emu.gui.cmos_viewer.set_content(cmos);
+    }
+
+    // -- Update option ROM viewer window
+    if emu.gui.is_window_open(GuiWindow::OptionRomViewer) {
+        emu.gui.option_rom_viewer.set_content(emu.machine.option_roms());
+    }
+
+    // -- Update ROM info window
+    if emu.gui.is_window_open(GuiWindow::RomInfoViewer) {
+        emu.gui.rom_info_viewer.set_content(emu.machine.rom_info());
+    }
+
     // -- Update PIT viewer window
     if emu.gui.is_window_open(GuiWindow::PitViewer) {
         let pit_state = emu.machine.pit_state();
@@ -268,18 +368,37 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
         if let Some(videocard_state) = emu.machine.videocard_state() {
             emu.gui.update_videocard_state(videocard_state);
         }
+        if let Some(registers) = emu.machine.videocard_registers() {
+            emu.gui.update_videocard_registers(registers);
+        }
     }
 
     // -- Update Instruction Trace window
     if emu.gui.is_window_open(GuiWindow::InstructionHistoryViewer) {
-        let trace = emu.machine.cpu().dump_instruction_history_tokens();
+        let trace = match emu.gui.trace_viewer.query() {
+            Some(query) => emu.machine.cpu().query_instruction_history(query),
+            None => emu.machine.cpu().dump_instruction_history_tokens(),
+        };
         emu.gui.trace_viewer.set_content(trace);
     }
 
     // -- Update Call Stack window
     if emu.gui.is_window_open(GuiWindow::CallStack) {
         let stack = emu.machine.cpu().dump_call_stack();
-        emu.gui.call_stack_viewer.set_content(stack);
+        let overflow = emu.machine.cpu().call_stack_overflow();
+        emu.gui.call_stack_viewer.set_content(stack, overflow);
+    }
+
+    // -- Update prefetch queue window
+    if emu.gui.is_window_open(GuiWindow::PrefetchViewer) {
+        let prefetch_state = emu.machine.cpu().prefetch_state();
+        emu.gui.prefetch_viewer.set_state(prefetch_state);
+    }
+
+    // -- Update watch window
+    if emu.gui.is_window_open(GuiWindow::WatchViewer) {
+        let cpu = emu.machine.cpu();
+        emu.gui.watch_viewer.update(|expr| cpu.eval_expression(expr));
     }
 
     // -- Update cycle trace viewer window
@@ -297,6 +416,17 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
                 _ => {}
             }
         }
+
+        if !emu.machine.get_cpu_option(CpuOption::CycleTraceRecords(true)) {
+            emu.machine.set_cpu_option(CpuOption::CycleTraceRecords(true));
+        }
+        if let Some(records) = emu.machine.cpu().get_cycle_trace_records() {
+            emu.gui.cycle_trace_viewer.update_records(records);
+        }
+    }
+    else if emu.machine.get_cpu_option(CpuOption::CycleTraceRecords(true)) {
+        // Stop recording cycles when nobody is looking at them.
+        emu.machine.set_cpu_option(CpuOption::CycleTraceRecords(false));
     }
 
     /*
@@ -328,9 +458,12 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
         };
 
         let cpu_type = emu.machine.cpu().get_type();
+        // The bus borrows the CPU mutably, so take a copy of any imported symbols to name branch targets.
+        let symbols = emu.machine.cpu().symbols().clone();
         let bus = emu.machine.bus_mut();
 
         let mut listview_vec = Vec::new();
+        let mut instr_ranges = Vec::new();
 
         //let mut disassembly_string = String::new();
         let mut disassembly_addr_flat = start_addr_flat as usize;
@@ -341,9 +474,11 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
                 bus.seek(disassembly_addr_flat);
 
                 let mut decode_vec = Vec::new();
+                let mut instr_range = (disassembly_addr_flat, 0);
 
                 match cpu_type.decode(bus, true) {
                     Ok(i) => {
+                        instr_range.1 = i.size as usize;
                         let instr_vec = bus.get_vec_at_ex(disassembly_addr_flat, i.size as usize);
                         let instr_bytes_str = util::fmt_byte_array(&instr_vec);
 
@@ -352,7 +487,7 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
                             format!("{:05X}", disassembly_addr_flat),
                         ));
 
-                        let mut instr_vec = cpu_type.tokenize_instruction(&i);
+                        let mut instr_vec = cpu_type.tokenize_instruction(&i, &symbols);
 
                         //let decode_str = format!("{:05X} {:012} {}\n", disassembly_addr, instr_bytes_str, i);
 
@@ -367,6 +502,15 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
                                 format!("{:04X}:{:04X}{}", segment, offset, " ",),
                             ));
 
+                            // Resolve relative branch targets within the code segment.
+                            if let Some(target) = i.branch_target(Some((segment, offset))) {
+                                for token in instr_vec.iter_mut() {
+                                    if let SyntaxToken::BranchTarget(address, _) = token {
+                                        *address = target;
+                                    }
+                                }
+                            }
+
                             let new_offset = offset.wrapping_add(i.size as u16);
                             if new_offset < offset {
                                 // A wrap of the code segment occurred. Update the linear address to match.
@@ -386,6 +530,21 @@ pub fn update_egui(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &Times
 
                 //disassembly_string.push_str(&decode_str);
                 listview_vec.push(decode_vec);
+                instr_ranges.push(instr_range);
+            }
+        }
+
+        // Flag instructions that were rewritten after the CPU had already fetched them, and tint
+        // instructions that have been executed while coverage recording was enabled.
+        for (decode_vec, (addr, size)) in listview_vec.iter_mut().zip(instr_ranges) {
+            if (addr..addr + size).any(|a| emu.machine.code_write(a as u32).is_some()) {
+                decode_vec.push(SyntaxToken::ErrorString("; SMC".to_string()));
+            }
+            if size > 0 && emu.machine.bus().is_executed(addr) {
+                decode_vec.insert(
+                    0,
+                    SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(HighlightType::Info)),
+                );
             }
         }
 
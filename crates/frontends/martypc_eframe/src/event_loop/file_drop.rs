@@ -0,0 +1,232 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    event_loop/file_drop.rs
+
+    Mount floppy images, VHDs and cartridges dropped onto the emulator window.
+*/
+
+use std::path::PathBuf;
+
+use crate::{emulator::Emulator, event_loop::egui_events::attach_vhd_image, floppy::load_floppy::load_floppy_image};
+use marty_core::{machine::MachineState, vhd::VhdIO};
+use marty_egui::modal::ModalContext;
+use marty_frontend_common::{
+    cartridge_manager::read_cart_image,
+    constants::{LONG_NOTIFICATION_TIME, NORMAL_NOTIFICATION_TIME},
+    file_drop::{DropTarget, DroppedFile, DroppedFileKind, DroppedFileSource},
+    thread_events::FileSelectionContext,
+};
+
+/// Identify a file dropped onto the emulator window and mount it, or prompt the user for where
+/// to mount it.
+pub fn handle_dropped_file(emu: &mut Emulator, file: egui::DroppedFile) {
+    // Native platforms give us a path, while the browser gives us the file's contents.
+    let source = match (file.path, file.bytes) {
+        (Some(path), _) => DroppedFileSource::Path(path),
+        (None, Some(bytes)) => DroppedFileSource::Bytes(bytes.to_vec()),
+        (None, None) => {
+            log::warn!("Dropped file {} has no path or contents", file.name);
+            return;
+        }
+    };
+
+    let dropped = match DroppedFile::identify(file.name.clone(), source) {
+        Ok(Some(dropped)) => dropped,
+        Ok(None) => {
+            emu.gui
+                .toasts()
+                .error(format!("Unsupported file type: {}", file.name))
+                .duration(Some(NORMAL_NOTIFICATION_TIME));
+            return;
+        }
+        Err(err) => {
+            emu.gui
+                .toasts()
+                .error(format!("Couldn't read dropped file {}: {}", file.name, err))
+                .duration(Some(LONG_NOTIFICATION_TIME));
+            return;
+        }
+    };
+    log::debug!("{} dropped: {:?}", dropped.kind, dropped.name);
+
+    let kind = dropped.kind;
+    let name = dropped.name.to_string_lossy().to_string();
+    emu.dropped_file = Some(dropped);
+
+    match kind {
+        DroppedFileKind::FloppyImage => {
+            let Some(fdc) = emu.machine.fdc()
+            else {
+                drop_error(emu, "This machine has no floppy drives.");
+                return;
+            };
+
+            // Mount into the first empty drive, or let the user pick a drive to replace.
+            let drive_ct = fdc.drive_ct();
+            match (0..drive_ct).find(|&drive| fdc.get_image(drive).0.is_none()) {
+                Some(drive) => mount_dropped_file(emu, DropTarget::FloppyDrive(drive)),
+                None => emu.gui.modal.open(ModalContext::SelectDropTarget(
+                    format!("All floppy drives are occupied. Replace which disk with {}?", name),
+                    (0..drive_ct).map(DropTarget::FloppyDrive).collect(),
+                )),
+            }
+        }
+        DroppedFileKind::Vhd => {
+            // Only allow VHD loading if machine is off to prevent corruption to VHD.
+            if emu.machine.get_state().is_on() {
+                drop_error(emu, "Machine must be off to attach a VHD.");
+                return;
+            }
+            let drive_ct = if let Some(hdc) = emu.machine.hdc_mut() {
+                hdc.drive_ct()
+            }
+            else if let Some(hdc) = emu.machine.xtide_mut() {
+                hdc.drive_ct()
+            }
+            else {
+                drop_error(emu, "This machine has no hard disk controller.");
+                return;
+            };
+            emu.gui.modal.open(ModalContext::SelectDropTarget(
+                format!("Attach VHD {}?", name),
+                (0..drive_ct).map(DropTarget::HardDisk).collect(),
+            ));
+        }
+        DroppedFileKind::Cartridge => mount_dropped_file(emu, DropTarget::CartridgeSlot(0)),
+    }
+}
+
+/// Mount the most recently dropped file into the specified drive or slot.
+pub fn mount_dropped_file(emu: &mut Emulator, target: DropTarget) {
+    let Some(dropped) = emu.dropped_file.take()
+    else {
+        log::warn!("mount_dropped_file(): No dropped file pending");
+        return;
+    };
+
+    let contents = match dropped.source {
+        // VHDs are opened in place on native platforms, rather than read into memory.
+        DroppedFileSource::Path(_) if matches!(target, DropTarget::HardDisk(_)) => Vec::new(),
+        _ => match dropped.read() {
+            Ok(contents) => contents,
+            Err(err) => {
+                drop_error(emu, &format!("Couldn't read {:?}: {}", dropped.name, err));
+                return;
+            }
+        },
+    };
+
+    match target {
+        DropTarget::FloppyDrive(drive_select) => {
+            log::info!(
+                "Loading dropped floppy image {:?} into drive: {}",
+                dropped.name,
+                drive_select
+            );
+            let fsc = FileSelectionContext::Path(
+                dropped
+                    .path()
+                    .map(|path| path.to_path_buf())
+                    .unwrap_or_else(|| PathBuf::from(&dropped.name)),
+            );
+
+            emu.gui
+                .toasts()
+                .info("Loading disk image...")
+                .duration(Some(NORMAL_NOTIFICATION_TIME));
+
+            load_floppy_image(emu, drive_select, fsc, contents, dropped.path());
+        }
+        DropTarget::HardDisk(drive_idx) => {
+            if emu.machine.get_state().is_on() {
+                drop_error(emu, "Machine must be off to attach a VHD.");
+                return;
+            }
+
+            let vhd_file: Box<dyn VhdIO> = match dropped.source {
+                #[cfg(not(target_arch = "wasm32"))]
+                DroppedFileSource::Path(ref path) => {
+                    let read_only = emu.gui.hdd_mount_mode(drive_idx) == marty_core::vhd::VhdMountMode::ReadOnly;
+                    match std::fs::OpenOptions::new().read(true).write(!read_only).open(path) {
+                        Ok(file) => Box::new(file),
+                        Err(err) => {
+                            drop_error(emu, &format!("Couldn't open {:?}: {}", path, err));
+                            return;
+                        }
+                    }
+                }
+                _ => Box::new(std::io::Cursor::new(contents)),
+            };
+
+            emu.vhd_manager.release_vhd(drive_idx);
+            if attach_vhd_image(emu, vhd_file, drive_idx, &dropped.name) {
+                emu.gui
+                    .set_hdd_selection(drive_idx, None, Some(PathBuf::from(&dropped.name)));
+            }
+        }
+        DropTarget::CartridgeSlot(slot_select) => {
+            let reboot = emu.config.emulator.media.cartridge_reset;
+            let Some(cart_slot) = emu.machine.cart_slot()
+            else {
+                drop_error(emu, "This machine has no cartridge slots.");
+                return;
+            };
+
+            match read_cart_image(&contents, &dropped.name).and_then(|cart| cart_slot.insert_cart(slot_select, cart)) {
+                Ok(()) => {
+                    log::info!(
+                        "Dropped cart image {:?} loaded into slot: {}",
+                        dropped.name,
+                        slot_select
+                    );
+                    emu.gui
+                        .set_cart_selection(slot_select, None, Some(PathBuf::from(&dropped.name)));
+                    emu.gui
+                        .toasts()
+                        .info(format!("Cartridge inserted: {:?}", dropped.name))
+                        .duration(Some(NORMAL_NOTIFICATION_TIME));
+
+                    // Inserting a cartridge reboots the machine due to a switch in the cartridge slot, if enabled.
+                    if reboot {
+                        emu.machine.change_state(MachineState::Rebooting);
+                    }
+                }
+                Err(err) => drop_error(emu, &format!("Cartridge load failed: {}", err)),
+            }
+        }
+    }
+}
+
+/// Report a dropped file that can't be mounted, discarding it.
+fn drop_error(emu: &mut Emulator, msg: &str) {
+    log::error!("{}", msg);
+    emu.dropped_file = None;
+    emu.gui
+        .toasts()
+        .error(msg.to_string())
+        .duration(Some(NORMAL_NOTIFICATION_TIME));
+}
@@ -1,6 +1,9 @@
 pub mod egui_events;
 pub mod egui_update;
+pub mod file_drop;
+pub mod mouse_capture;
 pub mod render_frame;
+pub mod text_screen;
 pub mod thread_events;
 #[cfg(not(feature = "use_winit"))]
 pub mod web_keyboard;
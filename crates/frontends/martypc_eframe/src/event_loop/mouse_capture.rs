@@ -0,0 +1,174 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    --------------------------------------------------------------------------
+
+    event_loop/mouse_capture.rs
+
+    Capture the host mouse for the emulated mouse.
+
+*/
+
+//! While the mouse is captured, the cursor is hidden and confined to the window, and relative
+//! motion and button presses are routed to the emulated mouse instead of egui. No mouse data
+//! reaches the guest while the mouse is not captured.
+//!
+//! eframe cannot lock the pointer on web targets, so there the cursor is only hidden, motion is
+//! derived from pointer positions, and capture is released when the pointer leaves the canvas.
+
+use crate::emulator::Emulator;
+use egui::{PointerButton, RawInput, ViewportCommand};
+use marty_frontend_common::HotkeyEvent;
+
+/// The window title to restore when the mouse is released.
+const WINDOW_TITLE: &str = "MartyPC";
+
+/// Capture the mouse, if the machine has a mouse to receive input.
+pub fn capture_mouse(emu: &mut Emulator, ctx: &egui::Context) {
+    if emu.mouse_data.is_captured {
+        return;
+    }
+    if emu.machine.mouse_mut().is_none() {
+        log::debug!("Not capturing mouse: machine has no mouse.");
+        return;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // macOS can only lock the cursor, while Windows and X11 can only confine it.
+        let grab = if cfg!(target_os = "macos") {
+            egui::CursorGrab::Locked
+        }
+        else {
+            egui::CursorGrab::Confined
+        };
+        ctx.send_viewport_cmd(ViewportCommand::CursorGrab(grab));
+    }
+    ctx.send_viewport_cmd(ViewportCommand::CursorVisible(false));
+    ctx.send_viewport_cmd(ViewportCommand::Title(format!(
+        "{} - Mouse captured ({} to release)",
+        WINDOW_TITLE,
+        release_hint(emu)
+    )));
+
+    emu.mouse_data.release();
+    emu.mouse_data.is_captured = true;
+    log::debug!("Mouse captured.");
+}
+
+/// Release the mouse, restoring the cursor.
+pub fn release_mouse(emu: &mut Emulator, ctx: &egui::Context) {
+    if !emu.mouse_data.is_captured {
+        return;
+    }
+
+    // Don't leave the guest with a button held down.
+    if emu.mouse_data.l_button_is_pressed || emu.mouse_data.r_button_is_pressed {
+        if let Some(mouse) = emu.machine.mouse_mut() {
+            mouse.update(false, false, 0.0, 0.0);
+        }
+    }
+    emu.mouse_data.release();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    ctx.send_viewport_cmd(ViewportCommand::CursorGrab(egui::CursorGrab::None));
+    ctx.send_viewport_cmd(ViewportCommand::CursorVisible(true));
+    ctx.send_viewport_cmd(ViewportCommand::Title(WINDOW_TITLE.to_string()));
+    log::debug!("Mouse released.");
+}
+
+pub fn toggle_mouse_capture(emu: &mut Emulator, ctx: &egui::Context) {
+    if emu.mouse_data.is_captured {
+        release_mouse(emu, ctx);
+    }
+    else {
+        capture_mouse(emu, ctx);
+    }
+}
+
+/// Route pointer events to the emulated mouse while the mouse is captured. Routed events are
+/// removed from `raw_input` so that egui does not also act on them. Returns false if the pointer
+/// left the window, in which case the caller should release the mouse.
+pub fn handle_captured_input(emu: &mut Emulator, raw_input: &mut RawInput) -> bool {
+    if !emu.mouse_data.is_captured {
+        return true;
+    }
+
+    let mouse = &mut emu.mouse_data;
+    let mut pointer_present = true;
+    raw_input.events.retain(|event| match event {
+        // Raw relative motion is unaffected by the cursor being confined or locked.
+        #[cfg(not(target_arch = "wasm32"))]
+        egui::Event::MouseMoved(delta) => {
+            mouse.add_motion(delta.x as f64, delta.y as f64);
+            false
+        }
+        #[cfg(target_arch = "wasm32")]
+        egui::Event::MouseMoved(_) => false,
+        egui::Event::PointerMoved(pos) => {
+            #[cfg(target_arch = "wasm32")]
+            if let Some(last) = mouse.last_pointer_pos {
+                mouse.add_motion((pos.x - last.x) as f64, (pos.y - last.y) as f64);
+            }
+            mouse.last_pointer_pos = Some(*pos);
+            false
+        }
+        egui::Event::PointerGone => {
+            pointer_present = cfg!(not(target_arch = "wasm32"));
+            false
+        }
+        egui::Event::PointerButton { button, pressed, .. } => {
+            match button {
+                PointerButton::Primary => mouse.set_button(true, *pressed),
+                PointerButton::Secondary => mouse.set_button(false, *pressed),
+                _ => {}
+            }
+            false
+        }
+        egui::Event::MouseWheel { .. } => false,
+        _ => true,
+    });
+
+    pointer_present
+}
+
+/// Describe the hotkey that releases the mouse.
+fn release_hint(emu: &Emulator) -> String {
+    emu.config
+        .emulator
+        .input
+        .hotkeys
+        .iter()
+        .find(|hotkey| hotkey.event == HotkeyEvent::CaptureMouse)
+        .map(|hotkey| {
+            hotkey
+                .keys
+                .iter()
+                .map(|key| format!("{:?}", key))
+                .collect::<Vec<_>>()
+                .join("+")
+        })
+        .unwrap_or_else(|| "switch windows".to_string())
+}
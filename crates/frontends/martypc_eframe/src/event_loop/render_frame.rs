@@ -33,10 +33,12 @@ use crate::emulator::Emulator;
 use display_manager_eframe::{DisplayBackend, DisplayManager, EFrameDisplayManager};
 use marty_core::{device_traits::videocard::BufferSelect, machine::ExecutionState};
 use marty_egui::GuiBoolean;
+use marty_frontend_common::timestep_manager::frame_profiler::ProfilePhase;
 
 pub fn render_frame(emu: &mut Emulator, dm: &mut EFrameDisplayManager) {
     // First, run each renderer to resolve all videocard views.
     // Every renderer will have an associated card and backend.
+    let profile_start = emu.frame_profiler.start();
     dm.for_each_renderer(|renderer, vid, backend_buf| {
         if let Some(videocard) = emu.machine.bus_mut().video_mut(&vid) {
             // Check if the emulator is paused - if paused, optionally select the back buffer
@@ -78,6 +80,7 @@ pub fn render_frame(emu: &mut Emulator, dm: &mut EFrameDisplayManager) {
             )
         }
     });
+    emu.frame_profiler.end(ProfilePhase::Composite, profile_start);
 
     // Don't need this as eframe does not host guis ...
     // Prepare guis for rendering.
@@ -91,7 +94,7 @@ pub fn render_frame(emu: &mut Emulator, dm: &mut EFrameDisplayManager) {
     // });
 
     // Finally, render each surface
-
+    let profile_start = emu.frame_profiler.start();
     dm.for_each_surface(None, |backend, surface, _scaler, _gui_opt| {
         // log::debug!(
         //     "Rendering surface. Scaler? {} Gui? {}",
@@ -107,4 +110,5 @@ pub fn render_frame(emu: &mut Emulator, dm: &mut EFrameDisplayManager) {
 
         _ = surface.write().unwrap().update_backing(device, queue);
     });
+    emu.frame_profiler.end(ProfilePhase::Render, profile_start);
 }
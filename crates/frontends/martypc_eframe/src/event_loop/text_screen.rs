@@ -0,0 +1,104 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    event_loop/text_screen.rs
+
+    Copy or save the text screen of a display's video card.
+*/
+
+use crate::emulator::Emulator;
+use anyhow::{anyhow, Error};
+use marty_frontend_common::{
+    constants::{LONG_NOTIFICATION_TIME, SHORT_NOTIFICATION_TIME},
+    text_screen::text_buffer_to_string,
+};
+
+/// Read the text screen of the video card shown on the specified display target.
+fn read_text_screen(emu: &Emulator, dt_idx: usize) -> Result<String, Error> {
+    let vid = emu
+        .gui
+        .display_info
+        .get(dt_idx)
+        .and_then(|info| info.vid)
+        .ok_or_else(|| anyhow!("Display {} has no video card", dt_idx))?;
+    let buffer = emu
+        .machine
+        .bus()
+        .video(&vid)
+        .and_then(|card| card.get_text_buffer())
+        .ok_or_else(|| anyhow!("The video card is not in a text mode"))?;
+    Ok(text_buffer_to_string(&buffer))
+}
+
+/// Copy the text screen of the specified display target to the host clipboard.
+pub fn copy_text_screen(emu: &mut Emulator, dt_idx: usize) {
+    match read_text_screen(emu, dt_idx) {
+        Ok(text) => {
+            emu.gui.copy_to_clipboard(text);
+            emu.gui
+                .toasts()
+                .info("Text screen copied to clipboard")
+                .duration(Some(SHORT_NOTIFICATION_TIME));
+        }
+        Err(err) => {
+            log::error!("Failed to copy text screen: {}", err);
+            emu.gui
+                .toasts()
+                .error(format!("Couldn't copy text screen: {}", err))
+                .duration(Some(LONG_NOTIFICATION_TIME));
+        }
+    }
+}
+
+/// Save the text screen of the specified display target to a timestamped file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_text_screen(emu: &mut Emulator, dt_idx: usize) {
+    use marty_frontend_common::text_screen::{save_text_screen, TEXT_SCREEN_RESOURCE};
+
+    let result = read_text_screen(emu, dt_idx).and_then(|text| {
+        let dir = emu
+            .rm
+            .resource_path(TEXT_SCREEN_RESOURCE)
+            .ok_or_else(|| anyhow!("Resource path not found: {}", TEXT_SCREEN_RESOURCE))?;
+        save_text_screen(&dir, &text)
+    });
+
+    match result {
+        Ok(path) => {
+            emu.gui
+                .toasts()
+                .info(format!("Text screen saved to {:?}", path))
+                .duration(Some(SHORT_NOTIFICATION_TIME));
+        }
+        Err(err) => {
+            log::error!("Failed to save text screen: {}", err);
+            emu.gui
+                .toasts()
+                .error(format!("Couldn't save text screen: {}", err))
+                .duration(Some(LONG_NOTIFICATION_TIME));
+        }
+    }
+}
@@ -125,6 +125,7 @@ pub fn handle_thread_event(emu: &mut Emulator, ctx: &egui::Context) {
                         {
                             Ok(_) => {
                                 log::info!("Floppy image successfully saved: {:?}", path_buf);
+                                fdc.clear_dirty(drive_select);
 
                                 // emu.gui.set_floppy_selection(
                                 //     *drive_select,
@@ -32,18 +32,31 @@
 
 use std::str::FromStr;
 
-use crate::emulator::Emulator;
+use crate::{emulator::Emulator, event_loop::mouse_capture::toggle_mouse_capture};
 use display_manager_eframe::EFrameDisplayManager;
 use eframe::WebKeyboardEvent;
 use marty_core::keys::MartyKey;
+use marty_frontend_common::HotkeyEvent;
 
 pub fn handle_web_key_event(
     emu: &mut Emulator,
     _dm: &mut EFrameDisplayManager,
+    ctx: &egui::Context,
     event: WebKeyboardEvent,
     gui_focus: bool,
 ) {
     if let Ok(marty_key) = MartyKey::from_str(&event.key) {
+        // Only the mouse capture hotkey is handled on the web.
+        if event.pressed {
+            let hotkeys = emu.hkm.keydown(marty_key, gui_focus, emu.mouse_data.is_captured);
+            if hotkeys.unwrap_or_default().contains(&HotkeyEvent::CaptureMouse) {
+                toggle_mouse_capture(emu, ctx);
+            }
+        }
+        else {
+            emu.hkm.keyup(marty_key);
+        }
+
         if !gui_focus {
             if event.pressed {
                 emu.machine.key_press(marty_key, emu.kb_data.modifiers);
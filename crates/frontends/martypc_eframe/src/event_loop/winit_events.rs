@@ -32,6 +32,7 @@
 use crate::{
     emulator::Emulator,
     event_loop::winit_keyboard::{handle_modifiers, handle_winit_key_event},
+    input::host_lock_state,
 };
 use display_manager_eframe::EFrameDisplayManager;
 use marty_frontend_common::timestep_manager::TimestepManager;
@@ -118,6 +119,14 @@ pub fn handle_window_event(
         WindowEvent::Focused(state) => match state {
             true => {
                 //log::debug!("Window {:?} gained focus", window_id);
+                // Lock keys may have been toggled while we were in the background.
+                if emu.config.emulator.input.sync_lock_keys {
+                    if let Some(host_state) = host_lock_state() {
+                        if emu.machine.sync_keyboard_lock_state(host_state) {
+                            log::debug!("Synchronized guest lock key state to host: {:?}", host_state);
+                        }
+                    }
+                }
                 // dm.for_each_target(|dtc, _| {
                 //     if dtc.window_opts.as_ref().is_some_and(|opts| opts.always_on_top) {
                 //         dtc.window.as_ref().map(|window| {
@@ -29,13 +29,20 @@
     Handle keyboard events.
 
 */
-use crate::{emulator::Emulator, input::TranslateKey};
+use crate::{
+    emulator::Emulator,
+    event_loop::{mouse_capture::toggle_mouse_capture, text_screen::copy_text_screen},
+    input::TranslateKey,
+};
 use egui::ViewportCommand;
 
 use display_manager_eframe::{DisplayManager, EFrameDisplayManager};
 use marty_core::machine::{ExecutionOperation, MachineState};
+use marty_egui::GuiBoolean;
+#[cfg(not(target_arch = "wasm32"))]
+use marty_frontend_common::savestate_manager;
 use marty_frontend_common::{
-    constants::LONG_NOTIFICATION_TIME,
+    constants::{LONG_NOTIFICATION_TIME, SHORT_NOTIFICATION_TIME},
     display_manager::DtHandle,
     types::joykeys::JoyKeyInput,
     HotkeyEvent,
@@ -190,48 +197,10 @@ pub fn process_hotkeys(
                 log::debug!("ToggleGui hotkey triggered. Toggling GUI visibility.");
                 emu.flags.render_gui = !emu.flags.render_gui;
             }
-            // HotkeyEvent::CaptureMouse => {
-            //     // Get the window for this event.
-            //     let event_window = dm
-            //         .viewport_by_id(window_id)
-            //         .expect(&format!("Couldn't resolve window id {:?} to window.", window_id));
-            //
-            //     log::debug!("CaptureMouse hotkey triggered. Capturing mouse cursor.");
-            //     if !emu.mouse_data.is_captured {
-            //         let mut grab_success = false;
-            //
-            //         match event_window.set_cursor_grab(winit::window::CursorGrabMode::Confined) {
-            //             Ok(_) => {
-            //                 emu.mouse_data.is_captured = true;
-            //                 grab_success = true;
-            //             }
-            //             Err(_) => {
-            //                 // Try alternate grab mode (Windows/Mac require opposite modes)
-            //                 match event_window.set_cursor_grab(winit::window::CursorGrabMode::Locked) {
-            //                     Ok(_) => {
-            //                         emu.mouse_data.is_captured = true;
-            //                         grab_success = true;
-            //                     }
-            //                     Err(e) => {
-            //                         log::error!("Couldn't set cursor grab mode: {:?}", e)
-            //                     }
-            //                 }
-            //             }
-            //         }
-            //         // Hide mouse cursor if grab successful
-            //         if grab_success {
-            //             event_window.set_cursor_visible(false);
-            //         }
-            //     }
-            //     else {
-            //         // Cursor is grabbed, ungrab
-            //         match event_window.set_cursor_grab(winit::window::CursorGrabMode::None) {
-            //             Ok(_) => emu.mouse_data.is_captured = false,
-            //             Err(e) => log::error!("Couldn't set cursor grab mode: {:?}", e),
-            //         }
-            //         event_window.set_cursor_visible(true);
-            //     }
-            // }
+            HotkeyEvent::CaptureMouse => {
+                log::debug!("CaptureMouse hotkey triggered. Toggling mouse capture.");
+                toggle_mouse_capture(emu, &ctx);
+            }
             HotkeyEvent::CtrlAltDel => {
                 log::debug!("CtrlAltDel hotkey triggered. Sending Ctrl-Alt-Del to machine.");
                 emu.machine.emit_ctrl_alt_del();
@@ -262,12 +231,48 @@ pub fn process_hotkeys(
                         .duration(Some(LONG_NOTIFICATION_TIME));
                 }
             }
+            HotkeyEvent::CopyTextScreen => {
+                log::debug!("CopyTextScreen hotkey triggered. Copying text screen of primary display.");
+                copy_text_screen(emu, DtHandle::default().into());
+            }
             HotkeyEvent::DebugStep => {
                 emu.exec_control.borrow_mut().set_op(ExecutionOperation::Step);
             }
             HotkeyEvent::DebugStepOver => {
                 emu.exec_control.borrow_mut().set_op(ExecutionOperation::StepOver);
             }
+            #[cfg(not(target_arch = "wasm32"))]
+            HotkeyEvent::QuickSave => {
+                log::debug!("QuickSave hotkey triggered.");
+                if let Err(err) = savestate_manager::save_state(&mut emu.machine, &mut emu.rm, Some(1)) {
+                    log::error!("Quick save failed: {}", err);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Quick save failed: {}", err))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            HotkeyEvent::QuickLoad => {
+                log::debug!("QuickLoad hotkey triggered.");
+                if let Err(err) = savestate_manager::load_state(&mut emu.machine, &emu.rm, Some(1)) {
+                    log::error!("Quick load failed: {}", err);
+                    emu.gui
+                        .toasts()
+                        .error(format!("Quick load failed: {}", err))
+                        .duration(Some(LONG_NOTIFICATION_TIME));
+                }
+            }
+            HotkeyEvent::ToggleTurbo => {
+                let state = !emu.machine.get_turbo_mode();
+                log::debug!("ToggleTurbo hotkey triggered. Setting turbo mode: {}", state);
+                emu.machine.set_turbo_mode(state);
+                emu.gui.set_option(GuiBoolean::TurboButton, state);
+                emu.gui
+                    .toasts()
+                    .info(format!("Turbo mode {}!", if state { "enabled" } else { "disabled" }))
+                    .duration(Some(SHORT_NOTIFICATION_TIME));
+            }
             HotkeyEvent::JoyToggle => {
                 log::debug!("JoyToggle hotkey triggered. Toggling joystick keyboard emulation.");
                 emu.joy_data.enabled = !emu.joy_data.enabled;
@@ -38,7 +38,7 @@ use std::{
     env::consts::OS,
 };
 
-use marty_core::keys::MartyKey;
+use marty_core::{devices::keyboard::KeyboardLockState, keys::MartyKey};
 use marty_frontend_common::{HotkeyConfigEntry, HotkeyEvent, HotkeyScope};
 
 use strum::IntoEnumIterator;
@@ -593,6 +593,25 @@ pub fn button_from_id(id: u32, reverse: bool) -> MouseButton {
     }
 }
 
+/// Read the state of the host's Caps, Num and Scroll lock toggles. winit does not expose this, so it is
+/// only available where we can query the OS directly.
+#[cfg(target_os = "windows")]
+pub fn host_lock_state() -> Option<KeyboardLockState> {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, VK_CAPITAL, VK_NUMLOCK, VK_SCROLL};
+    // The low bit of GetKeyState's result is the toggle state of the key.
+    let toggled = |vk: u16| unsafe { GetKeyState(vk as i32) } & 1 != 0;
+    Some(KeyboardLockState {
+        caps:   toggled(VK_CAPITAL),
+        num:    toggled(VK_NUMLOCK),
+        scroll: toggled(VK_SCROLL),
+    })
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn host_lock_state() -> Option<KeyboardLockState> {
+    None
+}
+
 /// Return the winit button id for
 pub fn get_mouse_buttons(reverse: bool) -> (u32, u32) {
     match (OS, reverse) {
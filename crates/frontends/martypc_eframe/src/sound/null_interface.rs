@@ -102,6 +102,8 @@ impl SoundInterface {
 
     pub fn set_volume(&mut self, s_idx: usize, volume: f32) {}
 
+    pub fn set_target_latency(&mut self, _s_idx: usize, _latency_ms: f32) {}
+
     pub fn config(&self) -> SoundOutputConfig {
         Default::default()
     }
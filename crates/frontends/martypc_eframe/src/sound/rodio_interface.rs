@@ -30,6 +30,10 @@
 
 */
 const MAX_BUFFER_SIZE: u32 = 100;
+// Output frames generated per lock of a source's resampler.
+const RESAMPLER_BLOCK_FRAMES: usize = 128;
+
+use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Error};
 use crossbeam_channel::Receiver;
@@ -37,14 +41,15 @@ use marty_core::{
     device_traits::sounddevice::AudioSample,
     sound::{SoundOutputConfig, SoundSourceDescriptor},
 };
-use marty_frontend_common::types::sound::SoundSourceInfo;
+use marty_frontend_common::{audio_resampler::AudioResampler, types::sound::SoundSourceInfo};
 use rodio::{
     cpal::{traits::HostTrait, SupportedBufferSize},
     DeviceTrait,
     Sink,
+    Source,
     SupportedStreamConfig,
 };
-use web_time::{Duration, Instant};
+use web_time::Duration;
 
 pub struct SoundSource {
     pub name: String,
@@ -52,24 +57,28 @@ pub struct SoundSource {
     pub channels: u16,
     pub receiver: Receiver<AudioSample>,
     pub sample_ct: u64,
-    pub latency_ms: f32,
-    pub buffer_ct: u64,
-    pub first_buffer: Option<Instant>,
     pub muted: bool,
     pub volume: f32,
     pub sink: Sink,
-    pub last_block_received: Instant,
-    pub controller: AudioLatencyController,
+    pub resampler: Arc<Mutex<AudioResampler>>,
 }
 
 impl SoundSource {
     pub fn info(&self) -> SoundSourceInfo {
+        let (stats, target_latency_ms) = match self.resampler.lock() {
+            Ok(resampler) => (resampler.stats(), resampler.target_latency()),
+            Err(_) => (Default::default(), 0.0),
+        };
         SoundSourceInfo {
             name: self.name.clone(),
             sample_rate: self.sample_rate,
             channels: self.channels,
             sample_ct: self.sample_ct,
-            latency_ms: self.latency_ms,
+            latency_ms: stats.latency_ms,
+            target_latency_ms,
+            rate_adjust: stats.rate_adjust,
+            underruns: stats.underruns,
+            overruns: stats.overruns,
             muted: self.muted,
             volume: self.volume,
             len: self.sink.len(),
@@ -77,74 +86,47 @@ impl SoundSource {
     }
 }
 
-struct AudioLatencyController {
-    target_latency: f32, // Target latency in milliseconds
-    tolerance: f32,      // Tolerance in milliseconds
-    playback_speed: f32, // Current playback speed (1.0 = normal)
-    kp: f32,             // Proportional gain
-    ki: f32,             // Integral gain (optional)
-    integral: f32,       // Accumulated integral term
-    min_speed: f32,      // Lower bound for playback speed
-    max_speed: f32,      // Upper bound for playback speed
+/// An endless rodio source that plays a sound source through its resampler, at the output device's sample rate.
+struct ResampledSource {
+    resampler: Arc<Mutex<AudioResampler>>,
+    channels: u16,
+    sample_rate: u32,
+    block: Vec<f32>,
+    pos: usize,
 }
 
-impl Default for AudioLatencyController {
-    fn default() -> Self {
-        AudioLatencyController::new(
-            50.0,   // Target latency in ms
-            20.0,   // Tolerance in ms
-            0.001,  // Proportional gain
-            0.0001, // Integral gain
-            0.90,   // Min playback speed
-            1.1,    // Max playback speed
-        )
-    }
-}
+impl Iterator for ResampledSource {
+    type Item = f32;
 
-impl AudioLatencyController {
-    fn new(target_latency: f32, tolerance: f32, kp: f32, ki: f32, min_speed: f32, max_speed: f32) -> Self {
-        Self {
-            target_latency,
-            tolerance,
-            playback_speed: 1.0,
-            kp,
-            ki,
-            integral: 0.0,
-            min_speed,
-            max_speed,
+    fn next(&mut self) -> Option<f32> {
+        if self.pos >= self.block.len() {
+            match self.resampler.lock() {
+                Ok(mut resampler) => resampler.fill(&mut self.block),
+                Err(_) => self.block.fill(0.0),
+            }
+            self.pos = 0;
         }
+        let sample = self.block[self.pos];
+        self.pos += 1;
+        Some(sample)
     }
+}
 
-    fn speed(&self) -> f32 {
-        self.playback_speed
+impl Source for ResampledSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
     }
 
-    fn update(&mut self, measured_latency: f32, _dt: f32) -> f32 {
-        let error = measured_latency - self.target_latency;
-
-        let lower_bound = self.target_latency - self.tolerance;
-        let upper_bound = self.target_latency + self.tolerance;
-        if measured_latency < lower_bound || measured_latency > upper_bound {
-            // Proportional term
-            let p_term = self.kp * error;
-            //log::trace!("Error: {:.2} P-term: {}", error, p_term);
-
-            // Integral term (accumulates over time)
-            //self.integral += error * dt;
-            // let i_term = self.ki * self.integral;
-            let i_term = 0.0;
-
-            // Compute new playback speed
-            self.playback_speed += p_term + i_term;
+    fn channels(&self) -> u16 {
+        self.channels
+    }
 
-            // Clamp playback speed within safe bounds
-            self.playback_speed = self.playback_speed.clamp(self.min_speed, self.max_speed);
-        }
-        else {
-            self.playback_speed = 1.0;
-        }
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
 
-        self.playback_speed
+    fn total_duration(&self) -> Option<Duration> {
+        None
     }
 }
 
@@ -247,7 +229,9 @@ impl SoundInterface {
         self.master_speed = speed;
 
         for source in self.sources.iter_mut() {
-            source.sink.set_speed(speed);
+            if let Ok(mut resampler) = source.resampler.lock() {
+                resampler.set_speed(speed as f64);
+            }
         }
     }
 
@@ -255,72 +239,44 @@ impl SoundInterface {
         let stream_handle = self.stream_handle.as_ref().unwrap();
         let sink = Sink::try_new(stream_handle)?;
 
+        let channels = source.channels as u16;
+        let mut resampler = AudioResampler::new(source.sample_rate, self.sample_rate, source.channels);
+        resampler.set_speed(self.master_speed as f64);
+        let resampler = Arc::new(Mutex::new(resampler));
+
+        sink.append(ResampledSource {
+            resampler: resampler.clone(),
+            channels,
+            sample_rate: self.sample_rate,
+            block: vec![0.0; RESAMPLER_BLOCK_FRAMES * source.channels.max(1)],
+            pos: usize::MAX,
+        });
+
         self.sources.push(SoundSource {
             name: source.name.clone(),
             sample_rate: source.sample_rate,
-            channels: source.channels as u16,
+            channels,
             receiver: source.receiver.clone(),
             sample_ct: 0,
-            latency_ms: 0.0,
-            buffer_ct: 0,
-            first_buffer: None,
             sink,
             muted: false,
             volume: 1.0,
-            last_block_received: Instant::now(),
-            controller: Default::default(),
+            resampler,
         });
 
         Ok(())
     }
 
-    pub fn run(&mut self, duration: Duration) {
+    pub fn run(&mut self, _duration: Duration) {
         for source in self.sources.iter_mut() {
             let samples_in = source.receiver.try_iter().collect::<Vec<f32>>();
-            //log::debug!("received {} samples from channel {}", samples_in.len(), source.name);
-
-            // Do not append an empty buffer.
-            if samples_in.len() > 0 {
-                let now = Instant::now();
-                if source.first_buffer.is_none() {
-                    source.first_buffer = Some(now);
-                }
-                let last_block_duration = now - source.last_block_received;
-                source.last_block_received = now;
-                let block_len = samples_in.len() / source.channels as usize;
-
-                let block_duration = Duration::from_secs_f64(block_len as f64 / source.sample_rate as f64);
-                // How far along is the current block?
-                let mut sink_pos = source.sink.get_pos();
 
-                if sink_pos > block_duration {
-                    sink_pos = block_duration;
+            // Do not lock the resampler for an empty buffer.
+            if !samples_in.is_empty() {
+                source.sample_ct += (samples_in.len() / source.channels as usize) as u64;
+                if let Ok(mut resampler) = source.resampler.lock() {
+                    resampler.push(&samples_in);
                 }
-
-                // Calculate the latency of the audio queue, by combining the current source position with the
-                // number of buffers in the queue
-                let latency = (block_duration - sink_pos)
-                    + Duration::from_secs_f64(source.sink.len() as f64 * block_duration.as_secs_f64());
-                let dt = last_block_duration.as_secs_f32();
-                let new_speed = source.controller.update(latency.as_millis_f32(), dt);
-
-                //let effective_sample_rate = block_len as f32 / block_duration.as_secs_f32();
-                let average_sample_rate =
-                    source.sample_ct as f64 / source.first_buffer.unwrap().elapsed().as_secs_f64();
-
-                source.latency_ms = latency.as_millis() as f32;
-                // log::debug!(
-                //     "{}: Average sample rate: {} Latency: {}ms Speed: {:.2}",
-                //     source.name,
-                //     average_sample_rate,
-                //     latency.as_millis(),
-                //     new_speed,
-                // );
-
-                source.sample_ct += block_len as u64;
-                let sink_buffer = rodio::buffer::SamplesBuffer::new(source.channels, source.sample_rate, samples_in);
-                source.sink.append(sink_buffer);
-                source.sink.set_speed(new_speed * self.master_speed);
             }
         }
     }
@@ -358,6 +314,15 @@ impl SoundInterface {
         }
     }
 
+    /// Set the amount of audio to keep buffered for the specified source, in milliseconds.
+    pub fn set_target_latency(&mut self, s_idx: usize, latency_ms: f32) {
+        if let Some(source) = self.sources.get(s_idx) {
+            if let Ok(mut resampler) = source.resampler.lock() {
+                resampler.set_target_latency(latency_ms);
+            }
+        }
+    }
+
     pub fn config(&self) -> SoundOutputConfig {
         SoundOutputConfig {
             enabled: self.enabled,
@@ -35,10 +35,13 @@ use web_time::{Duration, Instant};
 use crate::{emulator::Emulator, event_loop::render_frame::render_frame};
 use display_manager_eframe::{DisplayManager, EFrameDisplayManager};
 use marty_core::{bus::DeviceEvent, cpu_common::ServiceEvent, machine::MachineEvent};
+use marty_egui::GuiWindow;
+#[cfg(not(target_arch = "wasm32"))]
+use marty_frontend_common::cmos_manager;
 use marty_frontend_common::{
     constants::{LONG_NOTIFICATION_TIME, NORMAL_NOTIFICATION_TIME, SHORT_NOTIFICATION_TIME},
     thread_events::FrontendThreadEvent,
-    timestep_manager::{MachinePerfStats, TimestepManager},
+    timestep_manager::{frame_profiler::ProfilePhase, MachinePerfStats, TimestepManager},
 };
 use marty_videocard_renderer::RendererEvent;
 /*use crate::{
@@ -64,6 +67,12 @@ pub fn process_update(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &mu
             // );
 
             // Per second freq
+            // Persist CMOS RAM if the guest has changed it, so that BIOS settings survive restarts.
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Err(err) = cmos_manager::save_cmos(&mut emuc.machine, &emuc.rm, &emuc.config.machine.config_name) {
+                log::error!("Failed to save CMOS: {}", err);
+            }
+
             MachinePerfStats {
                 cpu_mhz: emuc.machine.get_cpu_mhz(),
                 cpu_cycles: emuc.machine.cpu_cycles(),
@@ -74,7 +83,9 @@ pub fn process_update(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &mu
         },
         |emuc, cycles| {
             // Per emu update freq
+            let profile_start = emuc.frame_profiler.start();
             emuc.machine.run(cycles, &mut emuc.exec_control.borrow_mut());
+            emuc.frame_profiler.end(ProfilePhase::Emulation, profile_start);
         },
         |emuc, dmc, tmc, &perf, duration, tmu| {
             emuc.perf = perf;
@@ -257,6 +268,16 @@ pub fn process_update(emu: &mut Emulator, dm: &mut EFrameDisplayManager, tm: &mu
                     }
                 }
             });
+
+            // Complete the profiled frame. We only pay for profiling while the performance viewer is open.
+            let devices = emuc.machine.bus_mut().take_device_profile();
+            emuc.frame_profiler
+                .end_frame(devices, emuc.machine.cpu_cycles(), emuc.machine.get_cpu_mhz());
+            let profiling = emuc.gui.is_window_open(GuiWindow::PerfViewer);
+            if profiling != emuc.frame_profiler.enabled() {
+                emuc.frame_profiler.set_enabled(profiling);
+                emuc.machine.bus_mut().set_device_profiling(profiling);
+            }
         },
     );
 }
@@ -161,6 +161,11 @@ impl Emulator {
             self.config.machine.cpu.instruction_history.unwrap_or(false),
         ));
 
+        if let Some(seed) = self.config.emulator.deterministic_seed {
+            log::info!("Deterministic mode enabled with seed: {}", seed);
+            self.machine.set_cpu_option(CpuOption::Deterministic(seed));
+        }
+
         // Debug mode on?
         if self.config.emulator.debug_mode {
             self.machine.set_cpu_option(CpuOption::InstructionHistory(true));
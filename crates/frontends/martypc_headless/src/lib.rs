@@ -48,7 +48,7 @@ use std::{
 
 use pollster::FutureExt as _;
 
-use crate::run_benchmark::run_benchmark;
+use crate::{run_benchmark::run_benchmark, run_headless::run_headless};
 
 #[cfg(feature = "arduino_validator")]
 use crate::{cpu_test::gen_tests::run_gentests, cpu_test::process_tests::run_processtests, run_fuzzer::run_fuzzer};
@@ -274,7 +274,7 @@ pub fn run() {
     // First we resolve the emulator configuration by parsing the configuration toml and merging it with
     // command line arguments. For the desktop frontend, this is handled by the config_toml_bpaf front end
     // library.
-    let config = match marty_config::read_local_config("./martypc.toml") {
+    let config = match marty_config::read_config_file("./martypc.toml") {
         Ok(config) => config,
         Err(e) => match e.downcast_ref::<std::io::Error>() {
             Some(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -733,9 +733,13 @@ pub fn run() {
     // Start emulator
     emu.start();
 
-    // TODO: We don't have any backend to run an event loop. If we want to actually run the
-    //       emulator now we need some way of controlling / stopping it.
-    log::debug!("Headless operation not implemented. Exiting.");
+    if !emu.config.emulator.headless {
+        // TODO: We don't have any backend to run an event loop, so without --headless there is
+        //       nothing to control or stop the emulator with.
+        log::debug!("Interactive operation not implemented. Run with --headless. Exiting.");
+        std::process::exit(0);
+    }
 
-    std::process::exit(0);
+    let exit_code = run_headless(&mut emu);
+    std::process::exit(exit_code);
 }
@@ -28,110 +28,125 @@
 
 */
 
-use marty_config::ConfigFileParams;
-use marty_frontend_common::floppy_manager::FloppyManager;
-
-#[allow(dead_code)]
-pub fn run_headless(_config: &ConfigFileParams, _floppy_manager: FloppyManager) {
-
-    /*
-
-    // Init sound
-    // The cpal sound library uses generics to initialize depending on the SampleFormat type.
-    // On Windows at least a sample type of f32 is typical, but just in case...
-    let sample_fmt = SoundPlayer::get_sample_format();
-    let sp = match sample_fmt {
-        cpal::SampleFormat::F32 => SoundPlayer::new::<f32>(),
-        cpal::SampleFormat::I16 => SoundPlayer::new::<i16>(),
-        cpal::SampleFormat::U16 => SoundPlayer::new::<u16>(),
-    };
-
-    // Look up the machine description given the machine type in the configuration file
-    let machine_desc_opt = MACHINE_DESCS.get(&config.machine.model);
-    if let Some(machine_desc) = machine_desc_opt {
-        log::debug!(
-            "Given machine type {:?} got machine description: {:?}",
-            config.machine.model,
-            machine_desc
-        );
-    }
-    else {
-        log::error!("Couldn't get machine description for {:?}", config.machine.model);
-
-        eprintln!(
-            "Couldn't get machine description for machine type {:?}. \
-             Check that you have a valid machine type specified in configuration file.",
-            config.machine.model
-        );
-        std::process::exit(1);
-    }
+use std::time::Instant;
 
-    let (video_type, _clock_mode, _video_debug) = {
-        let mut video_type: Option<VideoType> = None;
-        let mut clock_mode: Option<ClockingMode> = None;
-        let video_cards = config.get_video_cards();
-        if video_cards.len() > 0 {
-            clock_mode = video_cards[0].clocking_mode;
-            video_type = Some(video_cards[0].video_type); // Videotype is not optional
-        }
-        (
-            video_type.unwrap_or(VideoType::CGA),
-            clock_mode.unwrap_or_default(),
-            video_cards[0].debug.unwrap_or(false),
-        )
-    };
-
-    // Instantiate the main Machine data struct
-    // Machine coordinates all the parts of the emulated computer
-    let mut machine = Machine::new(
-        config,
-        config.machine.model,
-        *machine_desc_opt.unwrap(),
-        config.machine.cpu.trace_mode.unwrap_or_default(),
-        video_type,
-        sp,
-        rom_manager,
+use marty_core::{
+    bus::ProfiledDevice,
+    cpu_common::Cpu,
+    machine::{ExecutionControl, ExecutionState},
+};
+
+use crate::emulator::Emulator;
+
+const HEADLESS_CYCLE_BATCH: u64 = 100_000;
+const HEADLESS_DEFAULT_SECONDS: u32 = 30;
+
+/// Exit code when the requested number of emulated seconds ran to completion.
+pub const EXIT_OK: i32 = 0;
+/// Exit code when the CPU halted (not a HLT instruction - the CPU entered an unrecoverable state).
+pub const EXIT_HALTED: i32 = 2;
+/// Exit code when a breakpoint was hit.
+pub const EXIT_BREAKPOINT: i32 = 3;
+
+/// Run the configured machine with no video or sound output for a fixed number of emulated
+/// seconds, then print emulation speed statistics. Returns the process exit code.
+pub fn run_headless(emu: &mut Emulator) -> i32 {
+    let seconds = emu.config.emulator.headless_seconds.unwrap_or(HEADLESS_DEFAULT_SECONDS);
+    let cycle_total = (emu.machine.get_cpu_mhz() * 1_000_000.0 * seconds as f64) as u64;
+
+    emu.machine
+        .bus_mut()
+        .set_device_profiling(emu.config.emulator.headless_profile_devices);
+
+    let mut exec_control = ExecutionControl::new();
+    exec_control.set_state(ExecutionState::Running);
+
+    println!(
+        "Running headless for {} emulated seconds; {} cycles at {:.4} MHz",
+        seconds,
+        cycle_total,
+        emu.machine.get_cpu_mhz()
     );
 
-    // Load program binary if one was specified in config options
-    if let Some(prog_bin) = &config.emulator.run_bin {
-        if let Some(prog_seg) = config.emulator.run_bin_seg {
-            if let Some(prog_ofs) = config.emulator.run_bin_ofs {
-                let prog_vec = match std::fs::read(prog_bin.clone()) {
-                    Ok(vec) => vec,
-                    Err(e) => {
-                        eprintln!("Error opening filename {:?}: {}", prog_bin, e);
-                        std::process::exit(1);
-                    }
-                };
-
-                if let Err(_) = machine.load_program(&prog_vec, prog_seg, prog_ofs) {
-                    eprintln!(
-                        "Error loading program into memory at {:04X}:{:04X}.",
-                        prog_seg, prog_ofs
-                    );
-                    std::process::exit(1);
-                };
+    let mut exit_code = EXIT_OK;
+    let mut cycles_left = cycle_total;
+    let run_start = Instant::now();
+    while cycles_left > 0 {
+        let cycle_batch = std::cmp::min(cycles_left, HEADLESS_CYCLE_BATCH);
+        emu.machine.run(cycle_batch as u32, &mut exec_control);
+        cycles_left = cycles_left.saturating_sub(cycle_batch);
+
+        match exec_control.get_state() {
+            ExecutionState::Halted => {
+                eprintln!("CPU halted at {:05X}.", emu.machine.cpu().flat_ip());
+                exit_code = EXIT_HALTED;
+                break;
             }
-            else {
-                eprintln!("Must specifiy program load offset.");
-                std::process::exit(1);
+            ExecutionState::BreakpointHit => {
+                eprintln!("Breakpoint hit at {:05X}.", emu.machine.cpu().flat_ip());
+                exit_code = EXIT_BREAKPOINT;
+                break;
             }
-        }
-        else {
-            eprintln!("Must specifiy program load segment.");
-            std::process::exit(1);
+            _ => {}
         }
     }
+    let run_duration = run_start.elapsed();
 
-    let mut exec_control = ExecutionControl::new();
-    exec_control.set_state(ExecutionState::Running);
+    print_stats(emu, run_duration.as_secs_f64());
 
-    loop {
-        // This should really return a Result
-        machine.run(1000, &mut exec_control);
+    if emu.config.emulator.headless_dump_screen {
+        dump_screen(emu);
     }
-    */
 
-    //std::process::exit(0);
+    exit_code
+}
+
+fn print_stats(emu: &mut Emulator, host_secs: f64) {
+    let instruction_ct = emu.machine.cpu_instructions();
+    let (cycle_ct, halt_cycles) = emu.machine.cpu().get_cycle_ct();
+    let emulated_secs = cycle_ct as f64 / (emu.machine.get_cpu_mhz() * 1_000_000.0);
+
+    println!(
+        "Ran {} cycles and {} instructions ({:.4} emulated seconds) in {:.4} host seconds.",
+        cycle_ct, instruction_ct, emulated_secs, host_secs
+    );
+    println!("Speed: {:.2}% of real time", emulated_secs / host_secs * 100.0);
+    println!(
+        "Cycles spent in halt state: {} ({:.4}%)",
+        halt_cycles,
+        (halt_cycles as f64 / cycle_ct as f64) * 100.0
+    );
+    println!(
+        "Effective CPU speed: {:.4} MHz",
+        cycle_ct as f64 / host_secs / 1_000_000.0
+    );
+    println!("MIPS: {:.4}", instruction_ct as f64 / host_secs / 1_000_000.0);
+
+    if let Some(profile) = emu.machine.bus().device_profile() {
+        println!("Device tick time:");
+        for device in ProfiledDevice::ALL {
+            let device_secs = profile.get(device).as_secs_f64();
+            println!(
+                "  {:<14} {:>10.4}s ({:>6.2}%)",
+                device.to_string(),
+                device_secs,
+                device_secs / host_secs * 100.0
+            );
+        }
+        println!("  {:<14} {:>10.4}s", "Total", profile.total().as_secs_f64());
+    }
+}
+
+fn dump_screen(emu: &mut Emulator) {
+    match emu.machine.primary_videocard() {
+        Some(card) => {
+            println!("Screen contents:");
+            for line in card.get_text_mode_strings() {
+                println!("{}", line);
+            }
+        }
+        None => {
+            eprintln!("No video card to dump screen from.");
+        }
+    }
 }
@@ -343,6 +343,22 @@ pub struct DisplayTargetCallback {
 
 #[cfg(feature = "use_wgpu")]
 impl egui_wgpu::CallbackTrait for DisplayTargetCallback {
+    fn prepare(
+        &self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _screen_descriptor: &egui_wgpu::ScreenDescriptor,
+        egui_encoder: &mut wgpu::CommandEncoder,
+        _callback_resources: &mut egui_wgpu::CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        // Run any scaler pre-passes (such as phosphor persistence) before egui's render pass begins.
+        let dtc = self.lock.read().unwrap();
+        if let (Some(_surface), Some(scaler)) = (&dtc.surface, &dtc.scaler) {
+            scaler.prepare(egui_encoder);
+        }
+        Vec::new()
+    }
+
     // Required method
     fn paint(
         &self,
@@ -599,7 +615,8 @@ impl DisplayTargetContext {
             scaler_update.push(ScalerOption::Scanlines {
                 enabled: Some(params.crt_scanlines),
                 lines: Some(lines),
-                intensity: Some(0.3),
+                intensity: Some(params.crt_scanline_intensity),
+                persistence: Some(params.crt_persistence),
             });
         }
         else {
@@ -608,6 +625,7 @@ impl DisplayTargetContext {
                 enabled: Some(false),
                 lines: Some(0),
                 intensity: Some(0.0),
+                persistence: Some(params.crt_persistence),
             });
         }
 
@@ -1382,6 +1400,7 @@ impl<'p> DisplayManager<EFrameBackend, GuiRenderContext, ViewportId, ViewportId,
                                     enabled: None,
                                     lines: Some(scanlines),
                                     intensity: None,
+                                    persistence: None,
                                 },
                                 true,
                             );
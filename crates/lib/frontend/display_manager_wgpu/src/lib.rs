@@ -534,7 +534,8 @@ impl DisplayTargetContext<WgpuBackend<'static>> {
             scaler_update.push(ScalerOption::Scanlines {
                 enabled: Some(params.crt_scanlines),
                 lines: Some(lines),
-                intensity: Some(0.3),
+                intensity: Some(params.crt_scanline_intensity),
+                persistence: Some(params.crt_persistence),
             });
         }
         else {
@@ -543,6 +544,7 @@ impl DisplayTargetContext<WgpuBackend<'static>> {
                 enabled: Some(false),
                 lines: Some(0),
                 intensity: Some(0.0),
+                persistence: Some(params.crt_persistence),
             });
         }
 
@@ -1171,6 +1173,7 @@ impl<'p> DisplayManager<WgpuBackend<'p>, GuiRenderContext, WindowId, Window, Act
                                     enabled: None,
                                     lines: Some(scanlines),
                                     intensity: None,
+                                    persistence: None,
                                 },
                                 true,
                             );
@@ -1503,6 +1506,7 @@ impl<'p> DisplayManager<WgpuBackend<'p>, GuiRenderContext, WindowId, Window, Act
                             enabled: None,
                             lines: Some(scanlines),
                             intensity: None,
+                            persistence: None,
                         },
                         true,
                     );
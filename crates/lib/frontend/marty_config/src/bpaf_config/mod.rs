@@ -24,7 +24,7 @@
 
     --------------------------------------------------------------------------
 */
-use marty_core::{cpu_common::CpuType, cpu_validator::ValidatorType};
+use marty_core::{cpu_common::CpuType, cpu_validator::ValidatorType, device_traits::videocard::VideoType};
 use std::path::PathBuf;
 
 use bpaf::Bpaf;
@@ -55,6 +55,15 @@ pub struct CmdLineArgs {
     #[bpaf(long, switch)]
     pub headless: bool,
 
+    #[bpaf(long("headless_seconds"), long("seconds"))]
+    pub headless_seconds: Option<u32>,
+
+    #[bpaf(long("dump_screen"), switch)]
+    pub dump_screen: bool,
+
+    #[bpaf(long("profile_devices"), switch)]
+    pub profile_devices: bool,
+
     #[bpaf(long, switch)]
     pub fuzzer: bool,
 
@@ -74,6 +83,12 @@ pub struct CmdLineArgs {
     #[bpaf(long, switch)]
     pub title_hacks: bool,
 
+    #[bpaf(long("deterministic_seed"), long("deterministic"))]
+    pub deterministic_seed: Option<u64>,
+
+    #[bpaf(long("boot_script"))]
+    pub boot_script: Option<PathBuf>,
+
     #[bpaf(long, switch)]
     pub off_rails_detection: bool,
 
@@ -82,10 +97,16 @@ pub struct CmdLineArgs {
     #[bpaf(long, switch)]
     pub reverse_mouse_buttons: bool,
 
-    #[bpaf(long)]
+    #[bpaf(long("machine_config_name"), long("config"))]
     pub machine_config_name: Option<String>,
     #[bpaf(long)]
     pub machine_config_overlays: Option<String>,
+    #[bpaf(long)]
+    pub machine_template: Option<String>,
+    #[bpaf(long)]
+    pub template_memory: Option<u32>,
+    #[bpaf(long)]
+    pub template_video: Option<VideoType>,
 
     #[bpaf(long)]
     pub turbo: bool,
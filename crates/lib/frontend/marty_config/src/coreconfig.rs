@@ -40,6 +40,7 @@ use marty_core::{
     coreconfig::CoreConfig,
     cpu_common::TraceMode,
     cpu_validator::ValidatorType,
+    devices::post_code::POST_CODE_DEFAULT_PORT,
     machine_types::{MachineType, OnHaltBehavior},
 };
 
@@ -71,6 +72,9 @@ impl CoreConfig for ConfigFileParams {
     fn get_machine_turbo(&self) -> bool {
         self.machine.turbo
     }
+    fn get_machine_fast_boot(&self) -> bool {
+        self.machine.fast_boot
+    }
     //fn get_keyboard_type(&self) -> Option<KeyboardType> { self.machine.keyboard_type }
     fn get_keyboard_layout(&self) -> Option<String> {
         self.machine.input.keyboard_layout.clone()
@@ -114,4 +118,10 @@ impl CoreConfig for ConfigFileParams {
     fn get_terminal_port(&self) -> Option<u16> {
         self.machine.terminal_port
     }
+    fn get_post_code_ports(&self) -> Vec<u16> {
+        self.machine
+            .post_code_ports
+            .clone()
+            .unwrap_or_else(|| vec![POST_CODE_DEFAULT_PORT])
+    }
 }
@@ -58,7 +58,9 @@ use marty_frontend_common::{
 use marty_core::{
     cpu_common::{CpuSubType, CpuType, TraceMode},
     cpu_validator::ValidatorType,
+    device_traits::videocard::VideoType,
     machine_types::OnHaltBehavior,
+    vhd::VhdMountMode,
 };
 
 #[cfg(feature = "use_bpaf")]
@@ -113,8 +115,10 @@ impl FromStr for TestMode {
 
 #[derive(Debug, Deserialize)]
 pub struct VhdConfigEntry {
-    pub drive:    usize,
+    pub drive: usize,
     pub filename: String,
+    #[serde(default)]
+    pub mode: VhdMountMode,
 }
 
 #[derive(Debug, Deserialize)]
@@ -128,6 +132,8 @@ pub struct Media {
     pub raw_sector_image_extensions: Option<Vec<String>>,
     #[serde(default)]
     pub write_protect_default: bool,
+    #[serde(default = "_default_true")]
+    pub cartridge_reset: bool,
     pub floppy: Option<Vec<FloppyConfigEntry>>,
     pub vhd: Option<Vec<VhdConfigEntry>>,
 }
@@ -143,6 +149,9 @@ pub struct Debugger {
     pub checkpoint_notify_level: Option<u32>,
     #[serde(default)]
     pub breakpoint_notify: bool,
+    #[serde(default)]
+    pub server_enabled: bool,
+    pub server_port: Option<u16>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -170,6 +179,12 @@ pub struct Emulator {
     #[serde(default)]
     pub headless: bool,
     #[serde(default)]
+    pub headless_seconds: Option<u32>,
+    #[serde(default)]
+    pub headless_dump_screen: bool,
+    #[serde(default)]
+    pub headless_profile_devices: bool,
+    #[serde(default)]
     pub romscan: bool,
     #[serde(default)]
     pub machinescan: bool,
@@ -180,6 +195,10 @@ pub struct Emulator {
     #[serde(default)]
     pub title_hacks: bool,
     #[serde(default)]
+    pub deterministic_seed: Option<u64>,
+    #[serde(default)]
+    pub boot_script: Option<PathBuf>,
+    #[serde(default)]
     pub debug_mode: bool,
     #[serde(default = "_default_true")]
     pub debug_warn: bool,
@@ -291,6 +310,9 @@ pub struct MachineInput {
 pub struct Machine {
     pub config_name: String,
     pub config_overlays: Option<Vec<String>>,
+    pub template: Option<String>,
+    pub template_memory: Option<u32>,
+    pub template_video: Option<VideoType>,
     #[serde(default = "_default_true")]
     pub prefer_oem: bool,
     //pub model: MachineType,
@@ -304,12 +326,15 @@ pub struct Machine {
     pub raw_rom: bool,
     #[serde(default)]
     pub turbo: bool,
+    #[serde(default)]
+    pub fast_boot: bool,
     pub cpu: Cpu,
     pub pit_phase: Option<u32>,
     pub input: MachineInput,
     pub disassembly_recording: Option<bool>,
     pub disassembly_file: Option<PathBuf>,
     pub terminal_port: Option<u16>,
+    pub post_code_ports: Option<Vec<u16>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -322,6 +347,8 @@ pub struct EmulatorInput {
     pub keyboard_joystick: bool,
     #[serde(default)]
     pub debug_keyboard: bool,
+    #[serde(default)]
+    pub sync_lock_keys: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -343,6 +370,15 @@ impl ConfigFileParams {
             let config_overlays: Vec<String> = config_overlay_string.split(',').map(|s| s.trim().to_string()).collect();
             self.machine.config_overlays = Some(config_overlays);
         }
+        if let Some(template) = shell_args.machine_template {
+            self.machine.template = Some(template);
+        }
+        if let Some(memory) = shell_args.template_memory {
+            self.machine.template_memory = Some(memory);
+        }
+        if let Some(video) = shell_args.template_video {
+            self.machine.template_video = Some(video);
+        }
 
         // Apply 'fullscreen' parameter to the first window definition
         if let Some(window) = self.emulator.window.first_mut() {
@@ -360,10 +396,21 @@ impl ConfigFileParams {
         self.emulator.demo_mode |= shell_args.demo_mode;
         self.emulator.benchmark_mode |= shell_args.benchmark_mode;
         self.emulator.headless |= shell_args.headless;
+        if let Some(seconds) = shell_args.headless_seconds {
+            self.emulator.headless_seconds = Some(seconds);
+        }
+        self.emulator.headless_dump_screen |= shell_args.dump_screen;
+        self.emulator.headless_profile_devices |= shell_args.profile_devices;
         self.emulator.fuzzer |= shell_args.fuzzer;
         self.emulator.auto_poweron |= shell_args.auto_poweron;
         self.emulator.warpspeed |= shell_args.warpspeed;
         self.emulator.title_hacks |= shell_args.title_hacks;
+        if let Some(seed) = shell_args.deterministic_seed {
+            self.emulator.deterministic_seed = Some(seed);
+        }
+        if let Some(boot_script) = shell_args.boot_script {
+            self.emulator.boot_script = Some(boot_script);
+        }
         self.emulator.audio.enabled &= !shell_args.no_sound;
 
         //self.emulator.scaler_aspect_correction |= shell_args.scaler_aspect_correction;
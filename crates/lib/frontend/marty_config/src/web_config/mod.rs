@@ -30,7 +30,7 @@
 
 use std::path::PathBuf;
 
-use marty_core::{cpu_common::CpuType, cpu_validator::ValidatorType};
+use marty_core::{cpu_common::CpuType, cpu_validator::ValidatorType, device_traits::videocard::VideoType};
 
 use url::Url;
 use wasm_bindgen::prelude::*;
@@ -50,6 +50,12 @@ pub struct CmdLineArgs {
     // Ignored on wasm
     pub headless: bool,
     // Ignored on wasm
+    pub headless_seconds: Option<u32>,
+    // Ignored on wasm
+    pub dump_screen: bool,
+    // Ignored on wasm
+    pub profile_devices: bool,
+    // Ignored on wasm
     pub fuzzer: bool,
     // Ignored on wasm
     pub romscan: bool,
@@ -60,10 +66,16 @@ pub struct CmdLineArgs {
     // Ignored on wasm
     pub warpspeed: bool,
     pub title_hacks: bool,
+    pub deterministic_seed: Option<u64>,
+    // Ignored on wasm
+    pub boot_script: Option<PathBuf>,
     pub off_rails_detection: bool,
     pub reverse_mouse_buttons: bool,
     pub machine_config_name: Option<String>,
     pub machine_config_overlays: Option<String>,
+    pub machine_template: Option<String>,
+    pub template_memory: Option<u32>,
+    pub template_video: Option<VideoType>,
     pub turbo: bool,
     // Ignored on wasm
     pub validator: Option<ValidatorType>,
@@ -99,8 +111,10 @@ pub fn parse_query_params() -> CmdLineArgs {
                     "no_sound" => args.no_sound = true,
                     "machine_config_name" => args.machine_config_name = Some(String::from(value.into_owned())),
                     "machine_config_overlays" => args.machine_config_name = Some(String::from(value.into_owned())),
+                    "machine_template" => args.machine_template = Some(String::from(value.into_owned())),
                     "no_roms" => args.no_roms = true,
                     "turbo" => args.turbo = true,
+                    "deterministic_seed" => args.deterministic_seed = value.parse().ok(),
                     _ => {} // Ignore unknown parameters
                 }
             }
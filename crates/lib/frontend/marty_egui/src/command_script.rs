@@ -0,0 +1,283 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    --------------------------------------------------------------------------
+
+    egui::src::command_script.rs
+
+    A simple script of emulator commands, read from a text file and replayed
+    at startup. Each command maps to a GuiEvent, so a script does exactly what
+    a user clicking through the GUI would do. This allows a bug reproduction
+    or demo to be committed as a file rather than described as a sequence of
+    clicks.
+
+    One command per line. Blank lines and lines beginning with '#' are ignored.
+
+        load_floppy <drive> <path>    Load a floppy image into a drive
+        eject_floppy <drive>          Eject a floppy image
+        power_on                      Turn the machine on
+        power_off                     Turn the machine off
+        reboot                        Reboot the machine (cold boot, clears RAM)
+        warm_reboot                   Reboot the machine preserving RAM (warm boot)
+        ctrl_alt_del                  Send Ctrl-Alt-Del
+        wait <frames>                 Wait for the specified number of frames
+        type "<text>"                 Type text. Supports \n, \t, \" and \\ escapes
+        screenshot [display]          Take a screenshot of a display (default 0)
+        assert_mem <addr> <bytes..>   Check memory contents against hex bytes
+        save_state <slot>             Save state to a quick save slot
+        load_state <slot>             Load state from a quick save slot
+        quit                          Exit the emulator
+
+    Numbers may be given in decimal, or in hex with a '0x' prefix or 'h' suffix.
+*/
+
+use std::{collections::VecDeque, error::Error, fmt, fmt::Display, path::PathBuf};
+
+use crate::GuiEvent;
+use marty_core::machine::MachineState;
+
+#[derive(Debug)]
+pub enum CommandScriptError {
+    UnknownCommand(usize, String),
+    MissingArgument(usize, &'static str),
+    BadArgument(usize, String),
+}
+impl Error for CommandScriptError {}
+impl Display for CommandScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandScriptError::UnknownCommand(line, cmd) => {
+                write!(f, "Line {}: Unknown command '{}'", line, cmd)
+            }
+            CommandScriptError::MissingArgument(line, arg) => {
+                write!(f, "Line {}: Missing argument: {}", line, arg)
+            }
+            CommandScriptError::BadArgument(line, arg) => {
+                write!(f, "Line {}: Invalid argument '{}'", line, arg)
+            }
+        }
+    }
+}
+
+/// A parsed command script. The frontend should call [CommandScript::frame_events] once per frame and handle the
+/// returned events as if they came from the GUI.
+pub struct CommandScript {
+    events: VecDeque<GuiEvent>,
+    wait_frames: u32,
+}
+
+impl CommandScript {
+    pub fn parse(source: &str) -> Result<Self, CommandScriptError> {
+        let mut events = VecDeque::new();
+
+        for (idx, line) in source.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (cmd, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let args = args.trim();
+            let mut arg_iter = args.split_whitespace();
+            let mut next_num = |name: &'static str| -> Result<u32, CommandScriptError> {
+                let arg = arg_iter
+                    .next()
+                    .ok_or(CommandScriptError::MissingArgument(line_no, name))?;
+                parse_number(arg).ok_or_else(|| CommandScriptError::BadArgument(line_no, arg.to_string()))
+            };
+
+            let event = match cmd.to_ascii_lowercase().as_str() {
+                "load_floppy" => {
+                    let drive = next_num("drive")? as usize;
+                    let path = args
+                        .split_once(char::is_whitespace)
+                        .map(|(_, path)| path.trim().trim_matches('"'))
+                        .filter(|path| !path.is_empty())
+                        .ok_or(CommandScriptError::MissingArgument(line_no, "path"))?;
+                    GuiEvent::LoadFloppyAs(drive, PathBuf::from(path))
+                }
+                "eject_floppy" => GuiEvent::EjectFloppy(next_num("drive")? as usize),
+                "power_on" => GuiEvent::MachineStateChange(MachineState::On),
+                "power_off" => GuiEvent::MachineStateChange(MachineState::Off),
+                "reboot" => GuiEvent::MachineStateChange(MachineState::Rebooting),
+                "warm_reboot" => GuiEvent::MachineStateChange(MachineState::WarmRebooting),
+                "ctrl_alt_del" => GuiEvent::CtrlAltDel,
+                "wait" => GuiEvent::WaitFrames(next_num("frames")?),
+                "type" => GuiEvent::TypeText(parse_string(line_no, args)?),
+                "screenshot" => {
+                    // The display defaults to 0 only when omitted; a malformed display number is an error.
+                    let display = match next_num("display") {
+                        Err(CommandScriptError::MissingArgument(..)) => 0,
+                        result => result?,
+                    };
+                    GuiEvent::TakeScreenshot(display as usize)
+                }
+                "assert_mem" => {
+                    let address = next_num("address")?;
+                    let bytes = arg_iter
+                        .map(|b| {
+                            u8::from_str_radix(b, 16)
+                                .map_err(|_| CommandScriptError::BadArgument(line_no, b.to_string()))
+                        })
+                        .collect::<Result<Vec<u8>, _>>()?;
+                    if bytes.is_empty() {
+                        return Err(CommandScriptError::MissingArgument(line_no, "bytes"));
+                    }
+                    GuiEvent::AssertMemory(address, bytes)
+                }
+                "save_state" => GuiEvent::SaveState(Some(next_num("slot")? as usize)),
+                "load_state" => GuiEvent::LoadState(Some(next_num("slot")? as usize)),
+                "quit" => GuiEvent::Exit,
+                _ => return Err(CommandScriptError::UnknownCommand(line_no, cmd.to_string())),
+            };
+            events.push_back(event);
+        }
+
+        Ok(Self { events, wait_frames: 0 })
+    }
+
+    /// Return the events to be handled this frame. Events are returned up to the next `wait` command, which
+    /// then suspends the script for the specified number of frames.
+    pub fn frame_events(&mut self) -> Vec<GuiEvent> {
+        let mut events = Vec::new();
+        if self.wait_frames > 0 {
+            self.wait_frames -= 1;
+            return events;
+        }
+        while let Some(event) = self.events.pop_front() {
+            if let GuiEvent::WaitFrames(frames) = event {
+                self.wait_frames = frames.saturating_sub(1);
+                break;
+            }
+            events.push(event);
+        }
+        events
+    }
+
+    /// Returns true once all commands have been returned.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty() && self.wait_frames == 0
+    }
+}
+
+fn parse_number(arg: &str) -> Option<u32> {
+    if let Some(hex) = arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    }
+    else if let Some(hex) = arg.strip_suffix('h').or_else(|| arg.strip_suffix('H')) {
+        u32::from_str_radix(hex, 16).ok()
+    }
+    else {
+        arg.parse().ok()
+    }
+}
+
+fn parse_string(line_no: usize, arg: &str) -> Result<String, CommandScriptError> {
+    let inner = arg
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| CommandScriptError::BadArgument(line_no, arg.to_string()))?;
+
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('"') => text.push('"'),
+            Some('\\') => text.push('\\'),
+            _ => return Err(CommandScriptError::BadArgument(line_no, arg.to_string())),
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_replay() {
+        let source = r#"
+            # Boot a disk and check the boot sector made it into memory
+            load_floppy 0 "media/floppies/boot disk.img"
+            power_on
+            wait 3
+            type "dir\n"
+            assert_mem 0x7C00 EB 3C 90
+            quit
+        "#;
+        let mut script = CommandScript::parse(source).unwrap();
+
+        let events = script.frame_events();
+        assert_eq!(events.len(), 2);
+        assert!(
+            matches!(&events[0], GuiEvent::LoadFloppyAs(0, path) if path.to_str() == Some("media/floppies/boot disk.img"))
+        );
+
+        // Waiting for three frames, including the one the wait started on.
+        assert!(script.frame_events().is_empty());
+        assert!(script.frame_events().is_empty());
+
+        let events = script.frame_events();
+        assert!(matches!(&events[0], GuiEvent::TypeText(text) if text == "dir\n"));
+        assert!(matches!(&events[1], GuiEvent::AssertMemory(0x7C00, bytes) if bytes == &[0xEB, 0x3C, 0x90]));
+        assert!(matches!(events[2], GuiEvent::Exit));
+        assert!(script.is_finished());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            CommandScript::parse("power_on\nfrobnicate"),
+            Err(CommandScriptError::UnknownCommand(2, _))
+        ));
+        assert!(matches!(
+            CommandScript::parse("wait"),
+            Err(CommandScriptError::MissingArgument(1, "frames"))
+        ));
+        assert!(matches!(
+            CommandScript::parse("type unquoted"),
+            Err(CommandScriptError::BadArgument(1, _))
+        ));
+        assert!(matches!(
+            CommandScript::parse("screenshot one"),
+            Err(CommandScriptError::BadArgument(1, arg)) if arg == "one"
+        ));
+    }
+
+    #[test]
+    fn screenshot_display_defaults_to_zero() {
+        let mut script = CommandScript::parse("screenshot\nscreenshot 1").unwrap();
+        let events = script.frame_events();
+        assert!(matches!(events[0], GuiEvent::TakeScreenshot(0)));
+        assert!(matches!(events[1], GuiEvent::TakeScreenshot(1)));
+    }
+}
@@ -34,6 +34,7 @@ use egui::Color32;
 // Window geometries
 pub const PIT_VIEWER_WIDTH: f32 = 350.0;
 pub const DMA_VIEWER_WIDTH: f32 = 350.0;
+pub const DMA_VIEWER_HISTORY_HEIGHT: f32 = 200.0;
 
 // Color definitions
 pub const COLOR32_CYAN: Color32 = Color32::from_rgb(0, 255, 255);
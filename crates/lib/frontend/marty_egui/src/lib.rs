@@ -44,11 +44,14 @@ use std::{
 };
 
 use marty_frontend_common::{
+    audio_resampler::DEFAULT_TARGET_LATENCY_MS,
     display_manager::DisplayTargetInfo,
     display_scaler::{ScalerMode, ScalerParams},
+    file_drop::DropTarget,
 };
 
 mod color;
+pub mod command_script;
 mod constants;
 mod image;
 
@@ -66,15 +69,17 @@ mod windows;
 mod workspace;
 
 use marty_core::{
-    device_traits::videocard::DisplayApertureType,
+    device_traits::videocard::{DisplayApertureType, VideoCardId},
     device_types::hdc::HardDiskFormat,
     devices::pic::PicStringState,
     machine::MachineState,
+    machine_preset::{MachinePreset, PresetOverrides},
+    vhd::VhdMountMode,
 };
 
-use marty_core::cpu_common::Register16;
+use marty_core::cpu_common::{CpuAddress, Flag, Register16, DEFAULT_HISTORY_DEPTH};
 use marty_frontend_common::display_manager::{DisplayTargetType, DtHandle};
-use marty_videocard_renderer::CompositeParams;
+use marty_videocard_renderer::{CompositeParams, PhosphorType};
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
@@ -100,12 +105,18 @@ pub enum GuiWindow {
     CpuControl,
     PerfViewer,
     MemoryViewer,
+    AccessProfileViewer,
     CompositeAdjust,
     ScalerAdjust,
     CpuStateViewer,
+    PrefetchViewer,
     InstructionHistoryViewer,
     IvtViewer,
     IoStatsViewer,
+    PostCodeViewer,
+    CmosViewer,
+    OptionRomViewer,
+    RomInfoViewer,
     DelayAdjust,
     DeviceControl,
     DisassemblyViewer,
@@ -117,11 +128,15 @@ pub enum GuiWindow {
     VideoCardViewer,
     DataVisualizer,
     CallStack,
+    WatchViewer,
     VHDCreator,
     CycleTraceViewer,
     TextModeViewer,
     FdcViewer,
     FloppyViewer,
+    ScriptConsole,
+    TypeText,
+    MachineTemplates,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -142,10 +157,13 @@ pub enum GuiBoolean {
     // Boolean options
     CpuEnableWaitStates,
     CpuInstructionHistory,
+    CpuInstructionCoverage,
     CpuTraceLoggingEnabled,
     TurboButton,
+    FastBoot,
     ShowBackBuffer,
     ShowRasterPosition,
+    BreakOnMemoryEdit,
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -163,6 +181,7 @@ pub enum GuiVariableContext {
     Display(DtHandle),
     SoundSource(usize),
     SerialPort(usize),
+    VideoCard(VideoCardId),
 }
 impl Default for GuiVariableContext {
     fn default() -> Self {
@@ -178,10 +197,14 @@ pub enum GuiEnum {
     DisplayScalerMode(ScalerMode),
     DisplayScalerPreset(String),
     DisplayComposite(bool),
+    DisplayPhosphor(PhosphorType),
+    VideoCardSnow(bool),
     WindowBezel(bool),
     SerialPortBridge(usize),
     AudioMuted(bool),
     AudioVolume(f32),
+    AudioLatency(f32),
+    CpuHistoryDepth(usize),
 }
 
 fn create_default_variant(ge: GuiEnum) -> GuiEnum {
@@ -192,10 +215,14 @@ fn create_default_variant(ge: GuiEnum) -> GuiEnum {
         GuiEnum::DisplayScalerMode(_) => GuiEnum::DisplayAperture(Default::default()),
         GuiEnum::DisplayScalerPreset(_) => GuiEnum::DisplayScalerPreset(String::new()),
         GuiEnum::DisplayComposite(_) => GuiEnum::DisplayComposite(Default::default()),
+        GuiEnum::DisplayPhosphor(_) => GuiEnum::DisplayPhosphor(Default::default()),
+        GuiEnum::VideoCardSnow(_) => GuiEnum::VideoCardSnow(false),
         GuiEnum::WindowBezel(_) => GuiEnum::WindowBezel(Default::default()),
         GuiEnum::SerialPortBridge(_) => GuiEnum::SerialPortBridge(Default::default()),
         GuiEnum::AudioMuted(_) => GuiEnum::AudioMuted(false),
         GuiEnum::AudioVolume(_) => GuiEnum::AudioVolume(0.5),
+        GuiEnum::AudioLatency(_) => GuiEnum::AudioLatency(DEFAULT_TARGET_LATENCY_MS),
+        GuiEnum::CpuHistoryDepth(_) => GuiEnum::CpuHistoryDepth(DEFAULT_HISTORY_DEPTH),
     }
 }
 
@@ -205,6 +232,10 @@ type GuiEnumMap = HashMap<(GuiVariableContext, Discriminant<GuiEnum>), GuiEnum>;
 pub enum GuiEvent {
     LoadVHD(usize, usize),
     DetachVHD(usize),
+    SetVhdMountMode(usize, VhdMountMode),
+    CommitVhdOverlay(usize),
+    DiscardVhdOverlay(usize),
+    VerifyVHD(usize),
     CreateVHD(OsString, HardDiskFormat),
     LoadQuickFloppy(usize, usize),
     RequestLoadFloppyDialog(usize),
@@ -214,19 +245,35 @@ pub enum GuiEvent {
     SaveFloppy(usize, usize),                          // Drive index, disk index
     SaveFloppyAs(usize, DiskImageFileFormat, PathBuf), // Drive image, format, requested path
     EjectFloppy(usize),
+    ForceEjectFloppy(usize),   // Eject without checking for unsaved changes
+    SaveAndEjectFloppy(usize), // Write the image back to its source file, then eject
     CreateNewFloppy(usize, StandardFormat, bool),
     QueryCompatibleFloppyFormats(usize),
     SetFloppyWriteProtect(usize, bool),
+    VerifyFloppyImage(usize),
+    RepairFloppyImage(usize, usize), // Drive index, index of the reference image
     BridgeSerialPort(usize, String, usize),
+    StartSerialRecording(usize), // Record the bytes received on a serial port
+    PlaySerialRecording(usize),  // Play back the most recent serial recording into a serial port
+    StopSerialCapture(usize),
     DumpVRAM,
     DumpSegment(Register16),
     DumpAllMem,
+    DumpCoverage,
+    DumpCoverageBitmap,
+    ClearCoverage,
+    ExportSymbols, // Save the routine entry points discovered in the instruction history as a symbol file
+    ImportSymbols, // Load the most recently modified symbol file in the dump directory
+    SaveState(Option<usize>), // Quick save slot, or None to create a new save state file
+    LoadState(Option<usize>), // Quick save slot, or None to load the most recent save state
     EditBreakpoint,
     MemoryUpdate,
-    MemoryByteUpdate(usize, u8),
-    CpuFlagsUpdate(u16),
+    MemoryEdit(usize, Vec<u8>), // Write bytes to memory from the memory viewer
+    UndoMemoryEdit,             // Undo the most recent memory edit or fill
+    SetFlag(Flag, bool),
     CpuFlushQueue,
-    Register16Update(Register16, u16),
+    StepOut, // Run until the current subroutine returns
+    SetRegister(Register16, u16),
     TokenHover(usize),
     VariableChanged(GuiVariableContext, GuiVariable),
     CompositeAdjust(DtHandle, CompositeParams),
@@ -236,9 +283,14 @@ pub enum GuiEvent {
     TickDevice(DeviceSelection, u32),
     MachineStateChange(MachineState),
     TakeScreenshot(usize),
+    CopyTextScreen(usize),
+    SaveTextScreen(usize),
     ToggleFullscreen(usize),
     Exit,
     SetNMI(bool),
+    InjectInterrupt(u8),
+    FillMemory { start: u32, len: u32, value: u8 },
+    MountDroppedFile(Option<DropTarget>),
     TriggerParity,
     RescanMediaFolders,
     CtrlAltDel,
@@ -246,8 +298,22 @@ pub enum GuiEvent {
     ResetIOStats,
     StartRecordingDisassembly,
     StopRecordingDisassembly,
+    StartSpeakerRecording,
+    StopSpeakerRecording,
+    SaveSpeakerRecording,
     InsertCartridge(usize, usize),
     RemoveCartridge(usize),
+    RunScript(String),
+    ShowDisassembly(String),
+    NavigateDisassembly(CpuAddress), // Follow a jump or call target in the disassembly viewer
+    AssembleInstruction(String, String), // Address expression, instruction text
+    TypeText(String),
+    PasteClipboard, // Type the contents of the host clipboard
+    NewMachineFromTemplate(MachinePreset, PresetOverrides),
+    ConfigurationChanged, // Apply a changed machine configuration file by restarting
+    ClearPostCodes,
+    WaitFrames(u32),            // Only meaningful in a command script
+    AssertMemory(u32, Vec<u8>), // Address, expected bytes
 }
 
 pub enum DeviceSelection {
@@ -347,6 +413,16 @@ lazy_static! {
                 resizable: false,
             },
         ),
+        (
+            GuiWindow::AccessProfileViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::AccessProfileViewer,
+                title: "Memory Access Profile",
+                menu: "Access Profile",
+                width: 600.0,
+                resizable: false,
+            },
+        ),
         (
             GuiWindow::CompositeAdjust,
             WorkspaceWindowDef {
@@ -397,6 +473,16 @@ lazy_static! {
                 resizable: true,
             },
         ),
+        (
+            GuiWindow::PrefetchViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::PrefetchViewer,
+                title: "Prefetch Queue Viewer",
+                menu: "Prefetch Queue",
+                width: 300.0,
+                resizable: false,
+            },
+        ),
         (
             GuiWindow::CallStack,
             WorkspaceWindowDef {
@@ -407,14 +493,24 @@ lazy_static! {
                 resizable: true,
             },
         ),
+        (
+            GuiWindow::WatchViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::WatchViewer,
+                title: "Watches",
+                menu: "Watches",
+                width: 400.0,
+                resizable: true,
+            },
+        ),
         (
             GuiWindow::IvtViewer,
             WorkspaceWindowDef {
                 id: GuiWindow::IvtViewer,
                 title: "IVT Viewer",
                 menu: "IVT",
-                width: 400.0,
-                resizable: false,
+                width: 520.0,
+                resizable: true,
             },
         ),
         (
@@ -427,6 +523,46 @@ lazy_static! {
                 resizable: false,
             },
         ),
+        (
+            GuiWindow::PostCodeViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::PostCodeViewer,
+                title: "POST Code Viewer",
+                menu: "POST Codes",
+                width: 400.0,
+                resizable: true,
+            },
+        ),
+        (
+            GuiWindow::CmosViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::CmosViewer,
+                title: "CMOS Viewer",
+                menu: "CMOS RAM",
+                width: 420.0,
+                resizable: false,
+            },
+        ),
+        (
+            GuiWindow::OptionRomViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::OptionRomViewer,
+                title: "Option ROM Viewer",
+                menu: "Option ROMs",
+                width: 400.0,
+                resizable: true,
+            },
+        ),
+        (
+            GuiWindow::RomInfoViewer,
+            WorkspaceWindowDef {
+                id: GuiWindow::RomInfoViewer,
+                title: "ROM Info",
+                menu: "ROM Info",
+                width: 720.0,
+                resizable: true,
+            },
+        ),
         (
             GuiWindow::DelayAdjust,
             WorkspaceWindowDef {
@@ -567,6 +703,36 @@ lazy_static! {
                 resizable: false,
             },
         ),
+        (
+            GuiWindow::ScriptConsole,
+            WorkspaceWindowDef {
+                id: GuiWindow::ScriptConsole,
+                title: "Script Console",
+                menu: "Script Console",
+                width: 540.0,
+                resizable: true,
+            },
+        ),
+        (
+            GuiWindow::TypeText,
+            WorkspaceWindowDef {
+                id: GuiWindow::TypeText,
+                title: "Type Text",
+                menu: "⌨ Type Text",
+                width: 400.0,
+                resizable: true,
+            },
+        ),
+        (
+            GuiWindow::MachineTemplates,
+            WorkspaceWindowDef {
+                id: GuiWindow::MachineTemplates,
+                title: "New Machine from Template",
+                menu: "🖥 New Machine from Template",
+                width: 400.0,
+                resizable: false,
+            },
+        ),
     ]
     .into();
 }
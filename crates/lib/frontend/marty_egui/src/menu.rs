@@ -32,10 +32,19 @@
 use crate::{state::GuiState, GuiBoolean, GuiEnum, GuiEvent, GuiFloat, GuiVariable, GuiVariableContext, GuiWindow};
 use std::path::{Path, PathBuf};
 
-use marty_frontend_common::display_manager::DtHandle;
+use marty_frontend_common::{
+    audio_resampler::{MAX_TARGET_LATENCY_MS, MIN_TARGET_LATENCY_MS},
+    constants::QUICKSAVE_SLOTS,
+    display_manager::DtHandle,
+};
 
 //use egui_file_dialog::FileDialog;
-use marty_core::{device_traits::videocard::VideoType, machine::MachineState};
+use marty_core::{
+    device_traits::videocard::VideoType,
+    devices::post_code::POST_CODE_TABLES,
+    machine::MachineState,
+    vhd::VhdMountMode,
+};
 
 #[cfg(feature = "scaler_ui")]
 use marty_frontend_common::display_manager::DisplayTargetType;
@@ -53,13 +62,15 @@ use crate::{
 };
 use egui::RichText;
 use fluxfox::ImageFormatParser;
-use marty_core::cpu_common::Register16;
+use marty_core::cpu_common::{Register16, MAX_HISTORY_DEPTH, MIN_HISTORY_DEPTH};
 use marty_frontend_common::thread_events::{FileOpenContext, FileSaveContext, FileSelectionContext};
+use marty_videocard_renderer::PhosphorType;
 
 impl GuiState {
     pub fn show_menu(&mut self, ui: &mut egui::Ui) {
+        let mut menu_open = false;
         egui::menu::bar(ui, |ui| {
-            ui.menu_button("Emulator", |ui| {
+            menu_open |= ui.menu_button("Emulator", |ui| {
                 ui.set_min_width(120.0);
 
                 if !self.modal.is_open() {
@@ -79,14 +90,16 @@ impl GuiState {
                     self.event_queue.send(GuiEvent::Exit);
                     ui.close_menu();
                 }
-            });
+            })
+            .inner
+            .is_some();
 
             // Only show the Emulator menu if a modal dialog is open.
             if self.modal.is_open() {
                 return;
             }
 
-            ui.menu_button("Machine", |ui| {
+            menu_open |= ui.menu_button("Machine", |ui| {
                 ui.menu_button("Emulation Speed", |ui| {
                     ui.horizontal(|ui| {
                         let mut speed = self.option_floats.get_mut(&GuiFloat::EmulationSpeed).unwrap();
@@ -166,6 +179,8 @@ impl GuiState {
                     _ => (false, false),
                 };
 
+                self.workspace_window_open_button(ui, GuiWindow::MachineTemplates, true, true);
+
                 ui.add_enabled_ui(!is_on, |ui| {
                     if ui.button("⚡ Power on").clicked() {
                         self.event_queue.send(GuiEvent::MachineStateChange(MachineState::On));
@@ -186,6 +201,20 @@ impl GuiState {
                     ui.close_menu();
                 }
 
+                if ui
+                    .checkbox(&mut self.get_option_mut(GuiBoolean::FastBoot), "Fast Boot")
+                    .on_hover_text("Skip the BIOS memory test on the next cold boot.")
+                    .clicked()
+                {
+                    let new_opt = self.get_option(GuiBoolean::FastBoot).unwrap();
+
+                    self.event_queue.send(GuiEvent::VariableChanged(
+                        GuiVariableContext::Global,
+                        GuiVariable::Bool(GuiBoolean::FastBoot, new_opt),
+                    ));
+                    ui.close_menu();
+                }
+
                 ui.add_enabled_ui(is_on && !is_paused, |ui| {
                     if ui.button("⏸ Pause").clicked() {
                         self.event_queue
@@ -203,13 +232,29 @@ impl GuiState {
                 });
 
                 ui.add_enabled_ui(is_on, |ui| {
-                    if ui.button("⟲ Reboot").clicked() {
+                    if ui
+                        .button("⟲ Reboot (Cold)")
+                        .on_hover_text("Power cycle the machine, clearing RAM.")
+                        .clicked()
+                    {
                         self.event_queue
                             .send(GuiEvent::MachineStateChange(MachineState::Rebooting));
                         ui.close_menu();
                     }
                 });
 
+                ui.add_enabled_ui(is_on, |ui| {
+                    if ui
+                        .button("⟲ Reboot (Warm)")
+                        .on_hover_text("Reset the CPU, preserving RAM and setting the BIOS warm boot flag.")
+                        .clicked()
+                    {
+                        self.event_queue
+                            .send(GuiEvent::MachineStateChange(MachineState::WarmRebooting));
+                        ui.close_menu();
+                    }
+                });
+
                 ui.add_enabled_ui(is_on, |ui| {
                     if ui.button("⟲ CTRL-ALT-DEL").clicked() {
                         self.event_queue.send(GuiEvent::CtrlAltDel);
@@ -217,15 +262,52 @@ impl GuiState {
                     }
                 });
 
+                self.workspace_window_open_button(ui, GuiWindow::TypeText, true, is_on);
+
+                ui.add_enabled_ui(is_on, |ui| {
+                    if ui.button("📋 Paste").clicked() {
+                        self.event_queue.send(GuiEvent::PasteClipboard);
+                        ui.close_menu();
+                    }
+                });
+
+                ui.add_enabled_ui(is_on, |ui| {
+                    ui.menu_button("💾 Save States", |ui| {
+                        if ui.button("Save State").clicked() {
+                            self.event_queue.send(GuiEvent::SaveState(None));
+                            ui.close_menu();
+                        }
+                        if ui.button("Load Latest State").clicked() {
+                            self.event_queue.send(GuiEvent::LoadState(None));
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        for slot in 1..=QUICKSAVE_SLOTS {
+                            ui.horizontal(|ui| {
+                                if ui.button(format!("Quick Save {}", slot)).clicked() {
+                                    self.event_queue.send(GuiEvent::SaveState(Some(slot)));
+                                    ui.close_menu();
+                                }
+                                if ui.button(format!("Quick Load {}", slot)).clicked() {
+                                    self.event_queue.send(GuiEvent::LoadState(Some(slot)));
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+                    });
+                });
+
                 ui.add_enabled_ui(is_on, |ui| {
                     if ui.button("🔌 Power off").clicked() {
                         self.event_queue.send(GuiEvent::MachineStateChange(MachineState::Off));
                         ui.close_menu();
                     }
                 });
-            });
+            })
+            .inner
+            .is_some();
 
-            let _media_response = ui.menu_button("Media", |ui| {
+            menu_open |= ui.menu_button("Media", |ui| {
                 //ui.set_min_size(egui::vec2(240.0, 0.0));
                 //ui.style_mut().spacing.item_spacing = egui::Vec2{ x: 6.0, y:6.0 };
                 ui.set_width_range(egui::Rangef { min: 100.0, max: 240.0 });
@@ -257,9 +339,11 @@ impl GuiState {
                         ui.close_menu();
                     };
                 }
-            });
+            })
+            .inner
+            .is_some();
 
-            ui.menu_button("Sound", |ui| {
+            menu_open |= ui.menu_button("Sound", |ui| {
                 ui.set_min_width(240.0);
                 if !self.sound_sources.is_empty() {
                     self.draw_sound_menu(ui);
@@ -267,9 +351,31 @@ impl GuiState {
                 else {
                     ui.label(RichText::new("No sound sources available.").italics());
                 }
-            });
 
-            ui.menu_button("Display", |ui| {
+                ui.separator();
+                if !self.speaker_recording {
+                    if ui.button("⏺ Record Speaker Audio").clicked() {
+                        self.event_queue.send(GuiEvent::StartSpeakerRecording);
+                        ui.close_menu();
+                    }
+                }
+                else if ui.button("⏹ Stop Recording").clicked() {
+                    self.event_queue.send(GuiEvent::StopSpeakerRecording);
+                    ui.close_menu();
+                }
+
+                if ui
+                    .add_enabled(self.speaker_recording_available, egui::Button::new("💾 Save Recording"))
+                    .clicked()
+                {
+                    self.event_queue.send(GuiEvent::SaveSpeakerRecording);
+                    ui.close_menu();
+                }
+            })
+            .inner
+            .is_some();
+
+            menu_open |= ui.menu_button("Display", |ui| {
                 ui.set_min_size(egui::vec2(240.0, 0.0));
 
                 // If there is only one display, emit the display menu directly.
@@ -285,9 +391,11 @@ impl GuiState {
                         });
                     }
                 }
-            });
+            })
+            .inner
+            .is_some();
 
-            ui.menu_button("Debug", |ui| {
+            menu_open |= ui.menu_button("Debug", |ui| {
                 ui.menu_button("CPU", |ui| {
                     self.workspace_window_open_button(ui, GuiWindow::CpuControl, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::CpuStateViewer, true, true);
@@ -322,6 +430,58 @@ impl GuiState {
                             ));
                             ui.close_menu();
                         }
+                        ui.horizontal(|ui| {
+                            ui.label("History Depth:");
+                            if let Some(GuiEnum::CpuHistoryDepth(depth)) =
+                                self.get_option_enum_mut(GuiEnum::CpuHistoryDepth(Default::default()), None)
+                            {
+                                if ui
+                                    .add(egui::DragValue::new(depth).clamp_range(MIN_HISTORY_DEPTH..=MAX_HISTORY_DEPTH))
+                                    .changed()
+                                {
+                                    let new_depth = *depth;
+                                    self.event_queue.send(GuiEvent::VariableChanged(
+                                        GuiVariableContext::Global,
+                                        GuiVariable::Enum(GuiEnum::CpuHistoryDepth(new_depth)),
+                                    ));
+                                }
+                            }
+                        });
+                        if ui.button("Export Discovered Symbols").clicked() {
+                            self.event_queue.send(GuiEvent::ExportSymbols);
+                            ui.close_menu();
+                        }
+                        if ui.button("Import Symbols").clicked() {
+                            self.event_queue.send(GuiEvent::ImportSymbols);
+                            ui.close_menu();
+                        }
+                        if ui
+                            .checkbox(
+                                &mut self.get_option_mut(GuiBoolean::CpuInstructionCoverage),
+                                "Instruction Coverage",
+                            )
+                            .clicked()
+                        {
+                            let new_opt = self.get_option(GuiBoolean::CpuInstructionCoverage).unwrap();
+
+                            self.event_queue.send(GuiEvent::VariableChanged(
+                                GuiVariableContext::Global,
+                                GuiVariable::Bool(GuiBoolean::CpuInstructionCoverage, new_opt),
+                            ));
+                            ui.close_menu();
+                        }
+                        if ui.button("Save Coverage Report").clicked() {
+                            self.event_queue.send(GuiEvent::DumpCoverage);
+                            ui.close_menu();
+                        }
+                        if ui.button("Save Coverage Bitmap").clicked() {
+                            self.event_queue.send(GuiEvent::DumpCoverageBitmap);
+                            ui.close_menu();
+                        }
+                        if ui.button("Clear Coverage").clicked() {
+                            self.event_queue.send(GuiEvent::ClearCoverage);
+                            ui.close_menu();
+                        }
                         if ui
                             .checkbox(
                                 &mut self.get_option_mut(GuiBoolean::CpuTraceLoggingEnabled),
@@ -352,11 +512,22 @@ impl GuiState {
                             self.event_queue.send(GuiEvent::SetNMI(false));
                             ui.close_menu();
                         }
+
+                        ui.menu_button("Inject IRQ", |ui| {
+                            for irq in 0..8 {
+                                if ui.button(format!("IRQ {}", irq)).clicked() {
+                                    self.event_queue.send(GuiEvent::InjectInterrupt(irq));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
                     });
 
                     self.workspace_window_open_button(ui, GuiWindow::InstructionHistoryViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::CycleTraceViewer, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::PrefetchViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::CallStack, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::WatchViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::DisassemblyViewer, true, true);
 
                     // Don't show disassembly listing recording options on web.
@@ -378,6 +549,7 @@ impl GuiState {
 
                 ui.menu_button("Memory", |ui| {
                     self.workspace_window_open_button(ui, GuiWindow::MemoryViewer, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::AccessProfileViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::DataVisualizer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::IvtViewer, true, true);
 
@@ -407,6 +579,33 @@ impl GuiState {
                             ui.close_menu();
                         }
                     });
+
+                    ui.menu_button("Fill Memory Region", |ui| {
+                        egui::Grid::new("fill_memory_grid").num_columns(2).show(ui, |ui| {
+                            ui.label("Address:");
+                            ui.add(egui::TextEdit::singleline(&mut self.fill_memory.address).desired_width(60.0));
+                            ui.end_row();
+                            ui.label("Length:");
+                            ui.add(egui::TextEdit::singleline(&mut self.fill_memory.length).desired_width(60.0));
+                            ui.end_row();
+                            ui.label("Value:");
+                            ui.add(egui::TextEdit::singleline(&mut self.fill_memory.value).desired_width(60.0));
+                            ui.end_row();
+                        });
+
+                        let params = self.fill_memory.parse();
+                        if ui.add_enabled(params.is_some(), egui::Button::new("Fill")).clicked() {
+                            if let Some((start, len, value)) = params {
+                                self.event_queue.send(GuiEvent::FillMemory { start, len, value });
+                            }
+                            ui.close_menu();
+                        }
+                        let can_undo = self.memory_viewer.undo_len() > 0;
+                        if ui.add_enabled(can_undo, egui::Button::new("Undo Last Fill")).clicked() {
+                            self.event_queue.send(GuiEvent::UndoMemoryEdit);
+                            ui.close_menu();
+                        }
+                    });
                 });
 
                 ui.menu_button("Devices", |ui| {
@@ -416,6 +615,10 @@ impl GuiState {
                         ui.close_menu();
                     }
                     self.workspace_window_open_button(ui, GuiWindow::IoStatsViewer, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::PostCodeViewer, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::CmosViewer, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::OptionRomViewer, true, true);
+                    self.workspace_window_open_button(ui, GuiWindow::RomInfoViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::PicViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::PitViewer, true, true);
                     self.workspace_window_open_button(ui, GuiWindow::PpiViewer, true, true);
@@ -443,6 +646,9 @@ impl GuiState {
                      */
                 });
 
+                #[cfg(feature = "lua_scripting")]
+                self.workspace_window_open_button(ui, GuiWindow::ScriptConsole, true, true);
+
                 if ui
                     .checkbox(&mut self.get_option_mut(GuiBoolean::ShowBackBuffer), "Show Back Buffer")
                     .clicked()
@@ -474,20 +680,26 @@ impl GuiState {
                     self.event_queue.send(GuiEvent::FlushLogs);
                     ui.close_menu();
                 }
-            });
+            })
+            .inner
+            .is_some();
 
             // Draw drive indicators, etc.
             self.draw_status_widgets(ui);
         });
+        self.menu_open = menu_open;
     }
 
     pub fn draw_floppy_menu(&mut self, ui: &mut egui::Ui, drive_idx: usize) {
+        let drive_type = self.floppy_drives[drive_idx].drive_type;
         let floppy_name = match drive_idx {
-            0 => format!("💾 Floppy Drive 0 - {} (A:)", self.floppy_drives[drive_idx].drive_type),
-            1 => format!("💾 Floppy Drive 1 - {} (B:)", self.floppy_drives[drive_idx].drive_type),
+            0 => format!("💾 Floppy Drive 0 - {} {} (A:)", drive_type.form_factor(), drive_type),
+            1 => format!("💾 Floppy Drive 1 - {} {} (B:)", drive_type.form_factor(), drive_type),
             _ => format!(
-                "💾 Floppy Drive {} - {}",
-                drive_idx, self.floppy_drives[drive_idx].drive_type
+                "💾 Floppy Drive {} - {} {}",
+                drive_idx,
+                drive_type.form_factor(),
+                drive_type
             ),
         };
 
@@ -650,6 +862,21 @@ impl GuiState {
                     }
                 }
 
+                let have_image = self.floppy_drives[drive_idx].filename().is_some()
+                    || self.floppy_drives[drive_idx].is_new().is_some();
+                ui.add_enabled_ui(have_image, |ui| {
+                    if ui.button("🔍 Verify Image").clicked() {
+                        self.event_queue.send(GuiEvent::VerifyFloppyImage(drive_idx));
+                        ui.close_menu();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.menu_button("🔧 Repair from Reference Image", |ui| {
+                        self.floppy_tree_menu.draw(ui, drive_idx, true, &mut |image_idx| {
+                            self.event_queue.send(GuiEvent::RepairFloppyImage(drive_idx, image_idx));
+                        });
+                    });
+                });
+
                 if ui
                     .checkbox(&mut self.floppy_drives[drive_idx].write_protected, "Write Protect")
                     .changed()
@@ -695,6 +922,42 @@ impl GuiState {
                         self.event_queue.send(GuiEvent::DetachVHD(drive_idx));
                     }
                 });
+
+                ui.separator();
+                ui.label("Mount mode:");
+                for mode in [
+                    VhdMountMode::ReadWrite,
+                    VhdMountMode::ReadOnly,
+                    VhdMountMode::CopyOnWrite,
+                ] {
+                    if ui
+                        .radio_value(&mut self.hdds[drive_idx].mount_mode, mode, mode.to_string())
+                        .changed()
+                    {
+                        self.event_queue.send(GuiEvent::SetVhdMountMode(drive_idx, mode));
+                    }
+                }
+
+                let have_overlay = have_vhd && self.hdds[drive_idx].mount_mode == VhdMountMode::CopyOnWrite;
+                ui.add_enabled_ui(have_overlay, |ui| {
+                    if ui.button("Commit overlay to image").clicked() {
+                        self.event_queue.send(GuiEvent::CommitVhdOverlay(drive_idx));
+                        ui.close_menu();
+                    }
+                    if ui.button("Discard overlay").clicked() {
+                        self.event_queue.send(GuiEvent::DiscardVhdOverlay(drive_idx));
+                        ui.close_menu();
+                    }
+                });
+            });
+
+            // Verification only reads the image, so it is allowed while the machine is running.
+            ui.separator();
+            ui.add_enabled_ui(self.hdds[drive_idx].filename().is_some(), |ui| {
+                if ui.button("🔍 Verify Image").clicked() {
+                    self.event_queue.send(GuiEvent::VerifyVHD(drive_idx));
+                    ui.close_menu();
+                }
             });
         });
     }
@@ -884,21 +1147,26 @@ impl GuiState {
                 ));
             }
 
-            /* TODO: Snow should be set per-adapter, not per-display
-            if ui
-                .checkbox(&mut self.get_option_mut(GuiBoolean::EnableSnow), "Enable Snow")
-                .clicked()
-            {
-                let new_opt = self.get_option(GuiBoolean::EnableSnow).unwrap();
-
-                self.event_queue.send(GuiEvent::OptionChanged(GuiOption::Bool(
-                    GuiBoolean::EnableSnow,
-                    new_opt,
-                )));
-
-                ui.close_menu();
+            // Snow is a property of the adapter, not the display, so it uses the card context.
+            if let Some(vid) = self.display_info[usize::from(display)].vid {
+                let mut snow_changed = false;
+                let mut new_snow = false;
+                if let Some(GuiEnum::VideoCardSnow(state)) =
+                    self.get_option_enum_mut(GuiEnum::VideoCardSnow(false), Some(GuiVariableContext::VideoCard(vid)))
+                {
+                    if ui.checkbox(state, "Enable Snow").clicked() {
+                        snow_changed = true;
+                        new_snow = *state;
+                        ui.close_menu();
+                    }
+                }
+                if snow_changed {
+                    self.event_queue.send(GuiEvent::VariableChanged(
+                        GuiVariableContext::VideoCard(vid),
+                        GuiVariable::Enum(GuiEnum::VideoCardSnow(new_snow)),
+                    ));
+                }
             }
-             */
 
             if ui.button("Composite Adjustments...").clicked() {
                 *self.window_flag(GuiWindow::CompositeAdjust) = true;
@@ -907,6 +1175,35 @@ impl GuiState {
             }
         }
 
+        // MDA and Hercules options.
+        if matches!(self.display_info[usize::from(display)].vtype, Some(VideoType::MDA)) {
+            ui.menu_button("Monitor Phosphor", |ui| {
+                for (phosphor, name) in [
+                    (PhosphorType::White, "White"),
+                    (PhosphorType::Green, "Green"),
+                    (PhosphorType::Amber, "Amber"),
+                ] {
+                    if let Some(enum_mut) =
+                        self.get_option_enum_mut(GuiEnum::DisplayPhosphor(Default::default()), Some(vctx))
+                    {
+                        // The default 'Color' phosphor renders monochrome output as white.
+                        let checked = match *enum_mut {
+                            GuiEnum::DisplayPhosphor(PhosphorType::Color) => phosphor == PhosphorType::White,
+                            _ => *enum_mut == GuiEnum::DisplayPhosphor(phosphor),
+                        };
+
+                        if ui.add(egui::RadioButton::new(checked, name)).clicked() {
+                            *enum_mut = GuiEnum::DisplayPhosphor(phosphor);
+                            self.event_queue.send(GuiEvent::VariableChanged(
+                                GuiVariableContext::Display(display),
+                                GuiVariable::Enum(GuiEnum::DisplayPhosphor(phosphor)),
+                            ));
+                        }
+                    }
+                }
+            });
+        }
+
         self.workspace_window_open_button_with(ui, GuiWindow::TextModeViewer, true, |state| {
             state.text_mode_viewer.select_card(display.into());
         });
@@ -925,6 +1222,30 @@ impl GuiState {
             self.event_queue.send(GuiEvent::TakeScreenshot(display.into()));
             ui.close_menu();
         };
+
+        let text_mode = self.display_info[usize::from(display)]
+            .vid
+            .is_some_and(|vid| self.card_text_mode.get(&vid).copied().unwrap_or(false));
+        ui.add_enabled_ui(text_mode, |ui| {
+            let graphics_mode_tip = "The video card is in a graphics mode, so there is no text to copy.";
+            if ui
+                .button("📋 Copy Text Screen")
+                .on_disabled_hover_text(graphics_mode_tip)
+                .clicked()
+            {
+                self.event_queue.send(GuiEvent::CopyTextScreen(display.into()));
+                ui.close_menu();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("💾 Save Text Screen")
+                .on_disabled_hover_text(graphics_mode_tip)
+                .clicked()
+            {
+                self.event_queue.send(GuiEvent::SaveTextScreen(display.into()));
+                ui.close_menu();
+            }
+        });
     }
 
     pub fn draw_sound_menu(&mut self, ui: &mut egui::Ui) {
@@ -980,8 +1301,34 @@ impl GuiState {
                             }
                         }
                     });
+                    if ui
+                        .add(
+                            egui::Slider::new(
+                                &mut source.target_latency_ms,
+                                MIN_TARGET_LATENCY_MS..=MAX_TARGET_LATENCY_MS,
+                            )
+                            .suffix("ms")
+                            .text("Target Latency"),
+                        )
+                        .changed()
+                    {
+                        if let Some(GuiEnum::AudioLatency(latency_ms)) =
+                            self.get_option_enum_mut(GuiEnum::AudioLatency(Default::default()), Some(sctx))
+                        {
+                            *latency_ms = source.target_latency_ms;
+                            self.event_queue.send(GuiEvent::VariableChanged(
+                                GuiVariableContext::SoundSource(snd_idx),
+                                GuiVariable::Enum(GuiEnum::AudioLatency(source.target_latency_ms)),
+                            ));
+                        }
+                    }
                     ui.label(format!("Sample Rate: {}Hz", source.sample_rate));
-                    ui.label(format!("Latency: {:.0}ms", source.latency_ms));
+                    ui.label(format!(
+                        "Latency: {:.0}ms (rate {:+.2}%)",
+                        source.latency_ms,
+                        source.rate_adjust * 100.0
+                    ));
+                    ui.label(format!("Underruns: {} Overruns: {}", source.underruns, source.overruns));
                     // ui.label(format!("Samples: {}", source.sample_ct));
                     // ui.label(format!("Buffers: {}", source.len));
                 });
@@ -989,14 +1336,58 @@ impl GuiState {
         }
     }
 
-    pub fn draw_status_widgets(&mut self, _ui: &mut egui::Ui) {
-        // Can we put stuff on the right hand side of the menu bar?
-        // ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-        //     ui.label("💾");
-        // });
-        //
-        // ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-        //     ui.label("🐢");
-        // });
+    /// Show a notification bar under the menu when the machine configuration file has been modified.
+    pub fn show_config_changed_bar(&mut self, ui: &mut egui::Ui) {
+        if !self.config_changed {
+            return;
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui
+                .add(egui::Button::new("⚠ Configuration changed — click to apply (requires restart)").frame(false))
+                .clicked()
+            {
+                self.event_queue.send(GuiEvent::ConfigurationChanged);
+                self.config_changed = false;
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                    self.config_changed = false;
+                }
+            });
+        });
+    }
+
+    pub fn draw_status_widgets(&mut self, ui: &mut egui::Ui) {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            // Show the last POST code written, like a POST card would. Clicking it opens the history.
+            if let Some(code) = self.post_code {
+                let table = &POST_CODE_TABLES[self.post_code_viewer.table()];
+                let hover_text = match table.lookup(code) {
+                    Some(meaning) => format!("{}: {}", table.name, meaning),
+                    None => "Click to show the POST code log".to_string(),
+                };
+
+                if ui
+                    .add(egui::Button::new(RichText::new(format!("POST {:02X}h", code)).monospace()).frame(false))
+                    .on_hover_text(hover_text)
+                    .clicked()
+                {
+                    *self.window_flag(GuiWindow::PostCodeViewer) = true;
+                }
+            }
+
+            // Show the lock key state as the guest BIOS sees it. Laid out right to left.
+            if let Some(lock_state) = self.lock_state {
+                for (label, on) in [
+                    ("SCRL", lock_state.scroll),
+                    ("NUM", lock_state.num),
+                    ("CAPS", lock_state.caps),
+                ] {
+                    let text = RichText::new(label).monospace();
+                    ui.label(if on { text.strong() } else { text.weak() });
+                }
+            }
+        });
     }
 }
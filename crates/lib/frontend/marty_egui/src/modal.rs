@@ -32,12 +32,15 @@
 use crate::{GuiEvent, GuiEventQueue, PathBuf};
 
 use fluxfox::DiskImageFileFormat;
+use marty_frontend_common::file_drop::DropTarget;
 
 pub enum ModalContext {
     Notice(String),                                           // Non-interactive dialog with message
     SaveFloppyImage(usize, DiskImageFileFormat, Vec<String>), // Index of the floppy drive, list of extensions
     OpenFloppyImage(usize, Vec<String>),                      // Index of the floppy drive, list of extensions
     ProgressBar(String, f32),                                 // Progress bar with message and progress
+    ConfirmFloppyEject(usize),                                // Index of the floppy drive with unsaved changes
+    SelectDropTarget(String, Vec<DropTarget>),                // Prompt, and where a dropped file may be mounted
 }
 
 pub struct ProgressWindow {
@@ -50,6 +53,8 @@ pub enum ModalDialog {
     // Save(FileDialog),
     // Open(FileDialog),
     ProgressBar(ProgressWindow),
+    ConfirmFloppyEject(usize),
+    SelectDropTarget(String, Vec<DropTarget>),
 }
 
 #[derive(Default)]
@@ -87,6 +92,12 @@ impl ModalState {
                     progress: *progress,
                 }));
             }
+            ModalContext::ConfirmFloppyEject(drive_idx) => {
+                self.dialog = Some(ModalDialog::ConfirmFloppyEject(*drive_idx));
+            }
+            ModalContext::SelectDropTarget(prompt, targets) => {
+                self.dialog = Some(ModalDialog::SelectDropTarget(prompt.clone(), targets.clone()));
+            }
         }
         self.context = Some(context);
     }
@@ -147,8 +158,59 @@ impl ModalState {
                         );
                     });
             }
+            Some(ModalDialog::ConfirmFloppyEject(drive_idx)) => {
+                let drive_idx = *drive_idx;
+                let id = egui::Id::new("modal_confirm_floppy_eject");
+                let modal = egui::Modal::new(id);
+
+                modal.show(ctx, |ui| {
+                    ui.label(format!(
+                        "The disk in drive {} has been modified. Save changes before ejecting?",
+                        drive_idx
+                    ));
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("💾 Save").clicked() {
+                            events.send(GuiEvent::SaveAndEjectFloppy(drive_idx));
+                            dialog_resolved = true;
+                        }
+                        if ui.button("Discard").clicked() {
+                            events.send(GuiEvent::ForceEjectFloppy(drive_idx));
+                            dialog_resolved = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            dialog_resolved = true;
+                        }
+                    });
+                });
+            }
+            Some(ModalDialog::SelectDropTarget(prompt, targets)) => {
+                let id = egui::Id::new("modal_select_drop_target");
+                let modal = egui::Modal::new(id);
+
+                modal.show(ctx, |ui| {
+                    ui.label(prompt.as_str());
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        for target in targets.iter() {
+                            if ui.button(target.to_string()).clicked() {
+                                events.send(GuiEvent::MountDroppedFile(Some(*target)));
+                                dialog_resolved = true;
+                            }
+                        }
+                        if ui.button("Cancel").clicked() {
+                            events.send(GuiEvent::MountDroppedFile(None));
+                            dialog_resolved = true;
+                        }
+                    });
+                });
+            }
             None => {}
         }
+
+        if dialog_resolved {
+            self.close();
+        }
     }
 
     fn resolve(&mut self, event_queue: &mut GuiEventQueue) {
@@ -176,6 +238,9 @@ impl ModalState {
                 ModalContext::ProgressBar(_, _) => {
                     // Nothing to do to resolve a ProgressBar
                 }
+                ModalContext::ConfirmFloppyEject(_) | ModalContext::SelectDropTarget(..) => {
+                    // Resolved by the dialog buttons
+                }
             }
         }
 
@@ -41,10 +41,13 @@ use std::{
 
 use crate::{
     modal::ModalState,
+    themes::SyntaxColors,
     widgets::file_tree_menu::FileTreeMenu,
     windows::{
         about::AboutDialog,
+        access_profile_viewer::AccessProfileViewerControl,
         call_stack_viewer::CallStackViewer,
+        cmos_viewer::CmosViewerControl,
         composite_adjust::CompositeAdjustControl,
         cpu_control::{BreakpointSet, CpuControl},
         cpu_state_viewer::CpuViewerControl,
@@ -59,15 +62,24 @@ use crate::{
         instruction_history_viewer::InstructionHistoryControl,
         io_stats_viewer::IoStatsViewerControl,
         ivt_viewer::IvtViewerControl,
+        machine_templates::MachineTemplateDialog,
         memory_viewer::MemoryViewerControl,
+        option_rom_viewer::OptionRomViewerControl,
         performance_viewer::PerformanceViewerControl,
         pic_viewer::PicViewerControl,
         pit_viewer::PitViewerControl,
+        post_code_viewer::PostCodeViewerControl,
         ppi_viewer::PpiViewerControl,
+        prefetch_viewer::PrefetchViewerControl,
+        rom_info_viewer::RomInfoViewerControl,
         scaler_adjust::ScalerAdjustControl,
+        script_console::ScriptConsole,
         serial_viewer::SerialViewerControl,
         text_mode_viewer::TextModeViewer,
+        type_text::TypeTextDialog,
         vhd_creator::VhdCreator,
+        videocard_viewer::VideoCardRegisterTable,
+        watch_viewer::WatchViewerControl,
     },
     DialogProvider,
     GuiBoolean,
@@ -87,10 +99,12 @@ use crate::{
 use crate::windows::info_viewer::InfoViewer;
 
 use marty_core::{
-    device_traits::videocard::{DisplayApertureDesc, VideoCardState, VideoCardStateEntry},
-    devices::{pit::PitDisplayState, serial::SerialPortDescriptor},
+    cpu_common::DEFAULT_HISTORY_DEPTH,
+    device_traits::videocard::{DisplayApertureDesc, VideoCardId, VideoCardState, VideoCardStateEntry, VideoType},
+    devices::{keyboard::KeyboardLockState, pit::PitDisplayState, serial::SerialPortDescriptor},
     machine::{ExecutionControl, MachineState},
     machine_types::FloppyDriveType,
+    vhd::VhdMountMode,
 };
 use marty_frontend_common::{
     display_manager::{DisplayTargetInfo, DtHandle},
@@ -187,6 +201,7 @@ pub struct GuiHddInfo {
     pub(crate) selected_idx: Option<usize>,
     pub(crate) selected_path: Option<PathBuf>,
     pub(crate) write_protected: bool,
+    pub(crate) mount_mode: VhdMountMode,
 }
 
 impl GuiHddInfo {
@@ -242,6 +257,29 @@ impl Default for WorkspaceWindowState {
     }
 }
 
+/// Text inputs for the Fill Memory Region menu. Values are hexadecimal.
+#[derive(Default)]
+pub(crate) struct FillMemoryInput {
+    pub(crate) address: String,
+    pub(crate) length:  String,
+    pub(crate) value:   String,
+}
+
+impl FillMemoryInput {
+    /// Parse the inputs into a start address, length and value, if valid.
+    pub(crate) fn parse(&self) -> Option<(u32, u32, u8)> {
+        let hex = |s: &str| {
+            let s = s.trim();
+            let s = s.strip_prefix("0x").or_else(|| s.strip_suffix('h')).unwrap_or(s);
+            u32::from_str_radix(s, 16).ok()
+        };
+        let address = hex(&self.address).filter(|a| *a <= 0xFFFFF)?;
+        let length = hex(&self.length).filter(|l| *l > 0 && *l <= 0x100000)?;
+        let value = hex(&self.value).and_then(|v| u8::try_from(v).ok())?;
+        Some((address, length, value))
+    }
+}
+
 pub struct GuiState {
     pub(crate) event_queue: GuiEventQueue,
     pub(crate) thread_sender: crossbeam_channel::Sender<FrontendThreadEvent<Arc<DiskImage>>>,
@@ -255,6 +293,7 @@ pub struct GuiState {
     /// Only show the associated window when true.
     pub(crate) window_open_flags: HashMap<GuiWindow, bool>,
     pub(crate) window_state: BTreeMap<GuiWindow, WorkspaceWindowState>,
+    pub(crate) menu_open: bool,
     pub(crate) error_dialog_open: bool,
     pub(crate) warning_dialog_open: bool,
 
@@ -269,11 +308,19 @@ pub struct GuiState {
 
     // Audio stuff
     pub(crate) sound_sources: Vec<SoundSourceInfo>,
+    pub(crate) speaker_recording: bool,
+    pub(crate) speaker_recording_available: bool,
+
+    pub(crate) post_code: Option<u8>,
+    pub(crate) lock_state: Option<KeyboardLockState>,
+    pub(crate) config_changed: bool,
 
     // Display stuff
     pub(crate) display_apertures: HashMap<usize, Vec<DisplayApertureDesc>>,
     pub(crate) scaler_modes: Vec<ScalerMode>,
     pub(crate) scaler_presets: Vec<String>,
+    pub(crate) card_text_mode: HashMap<VideoCardId, bool>,
+    pub(crate) clipboard_text: Option<String>,
 
     // Media Images
     pub(crate) floppy_drives: Vec<GuiFloppyDriveInfo>,
@@ -290,6 +337,8 @@ pub struct GuiState {
     pub(crate) host_serial_ports: Vec<SerialPortInfo>,
     pub(crate) serial_port_name: String,
 
+    pub(crate) fill_memory: FillMemoryInput,
+
     pub(crate) exec_control: Rc<RefCell<ExecutionControl>>,
 
     pub(crate) error_string:   String,
@@ -300,6 +349,7 @@ pub struct GuiState {
     pub cpu_viewer: CpuViewerControl,
     pub cycle_trace_viewer: CycleTraceViewerControl,
     pub memory_viewer: MemoryViewerControl,
+    pub access_profile_viewer: AccessProfileViewerControl,
     pub data_visualizer: DataVisualizerControl,
 
     pub perf_viewer:  PerformanceViewerControl,
@@ -311,7 +361,8 @@ pub struct GuiState {
     pub ppi_viewer:    PpiViewerControl,
 
     pub videocard_state: VideoCardState,
-    pub display_info:    Vec<DisplayTargetInfo>,
+    pub videocard_registers: VideoCardRegisterTable,
+    pub display_info: Vec<DisplayTargetInfo>,
 
     pub disassembly_viewer: DisassemblyControl,
     pub dma_viewer: DmaViewerControl,
@@ -320,12 +371,21 @@ pub struct GuiState {
     pub scaler_adjust: ScalerAdjustControl,
     pub ivt_viewer: IvtViewerControl,
     pub io_stats_viewer: IoStatsViewerControl,
+    pub post_code_viewer: PostCodeViewerControl,
+    pub cmos_viewer: CmosViewerControl,
+    pub option_rom_viewer: OptionRomViewerControl,
+    pub rom_info_viewer: RomInfoViewerControl,
     pub device_control: DeviceControl,
     pub vhd_creator: VhdCreator,
     pub text_mode_viewer: TextModeViewer,
     pub fdc_viewer: FdcViewerControl,
     pub floppy_viewer: FloppyViewerControl,
     pub call_stack_viewer: CallStackViewer,
+    pub prefetch_viewer: PrefetchViewerControl,
+    pub watch_viewer: WatchViewerControl,
+    pub script_console: ScriptConsole,
+    pub type_text: TypeTextDialog,
+    pub machine_templates: MachineTemplateDialog,
     #[cfg(feature = "markdown")]
     pub info_viewer: InfoViewer,
 
@@ -360,8 +420,10 @@ impl GuiState {
             //(GuiBoolean::CorrectAspect, false),
             (GuiBoolean::CpuEnableWaitStates, true),
             (GuiBoolean::CpuInstructionHistory, false),
+            (GuiBoolean::CpuInstructionCoverage, false),
             (GuiBoolean::CpuTraceLoggingEnabled, false),
             (GuiBoolean::TurboButton, false),
+            (GuiBoolean::FastBoot, false),
             (GuiBoolean::ShowBackBuffer, false),
             (GuiBoolean::ShowRasterPosition, true),
             //(GuiBoolean::EnableSnow, true),
@@ -370,7 +432,12 @@ impl GuiState {
 
         let option_floats: HashMap<GuiFloat, f32> = [(GuiFloat::EmulationSpeed, 1.0f32)].into();
 
-        let option_enums = HashMap::new();
+        let mut option_enums: GuiEnumMap = HashMap::new();
+        let history_depth = GuiEnum::CpuHistoryDepth(DEFAULT_HISTORY_DEPTH);
+        option_enums.insert(
+            (GuiVariableContext::Global, discriminant(&history_depth)),
+            history_depth,
+        );
 
         Self {
             event_queue: GuiEventQueue::new(),
@@ -383,6 +450,7 @@ impl GuiState {
 
             window_open_flags,
             window_state,
+            menu_open: false,
             error_dialog_open: false,
             warning_dialog_open: false,
 
@@ -396,10 +464,18 @@ impl GuiState {
             perf_stats: Default::default(),
 
             sound_sources: Vec::new(),
+            speaker_recording: false,
+            speaker_recording_available: false,
+
+            post_code: None,
+            lock_state: None,
+            config_changed: false,
 
             display_apertures: Default::default(),
             scaler_modes: Vec::new(),
             scaler_presets: Vec::new(),
+            card_text_mode: HashMap::new(),
+            clipboard_text: None,
 
             floppy_drives: Vec::new(),
             hdds: Vec::new(),
@@ -412,6 +488,8 @@ impl GuiState {
             host_serial_ports: Vec::new(),
             serial_port_name: String::new(),
 
+            fill_memory: FillMemoryInput::default(),
+
             exec_control: exec_control.clone(),
 
             error_string: String::new(),
@@ -422,6 +500,7 @@ impl GuiState {
             cpu_viewer: CpuViewerControl::new(exec_control.clone()),
             cycle_trace_viewer: CycleTraceViewerControl::new(),
             memory_viewer: MemoryViewerControl::new(),
+            access_profile_viewer: AccessProfileViewerControl::new(),
             data_visualizer: DataVisualizerControl::new(),
 
             perf_viewer: PerformanceViewerControl::new(),
@@ -432,6 +511,7 @@ impl GuiState {
             ppi_viewer: PpiViewerControl::new(),
 
             videocard_state: Default::default(),
+            videocard_registers: Default::default(),
             display_info: Vec::new(),
             disassembly_viewer: DisassemblyControl::new(),
             dma_viewer: DmaViewerControl::new(),
@@ -440,12 +520,21 @@ impl GuiState {
             scaler_adjust: ScalerAdjustControl::new(),
             ivt_viewer: IvtViewerControl::new(),
             io_stats_viewer: IoStatsViewerControl::new(),
+            post_code_viewer: PostCodeViewerControl::new(),
+            cmos_viewer: CmosViewerControl::new(),
+            option_rom_viewer: OptionRomViewerControl::new(),
+            rom_info_viewer: RomInfoViewerControl::new(),
             device_control: DeviceControl::new(),
             vhd_creator: VhdCreator::new(),
             text_mode_viewer: TextModeViewer::new(),
             fdc_viewer: FdcViewerControl::new(),
             floppy_viewer: FloppyViewerControl::new(),
             call_stack_viewer: CallStackViewer::new(),
+            prefetch_viewer: PrefetchViewerControl::new(),
+            watch_viewer: WatchViewerControl::new(),
+            script_console: ScriptConsole::new(),
+            type_text: TypeTextDialog::new(),
+            machine_templates: MachineTemplateDialog::new(),
             #[cfg(feature = "markdown")]
             info_viewer: InfoViewer::new(),
 
@@ -623,6 +712,16 @@ impl GuiState {
         self.floppy_viewer.reset();
     }
 
+    /// Return the path and format to write the image in the specified drive back to, if the image was
+    /// loaded from a file in a format we can write.
+    pub fn floppy_writeback_target(&self, drive: usize) -> Option<(PathBuf, DiskImageFileFormat)> {
+        let info = self.floppy_drives.get(drive)?;
+        match (&info.selected_path, info.source_format) {
+            (FloppyDriveSelection::Image(path), Some(format)) if info.is_writeable() => Some((path.clone(), format)),
+            _ => None,
+        }
+    }
+
     pub fn set_floppy_supported_formats(
         &mut self,
         drive: usize,
@@ -639,6 +738,7 @@ impl GuiState {
                 selected_idx: None,
                 selected_path: None,
                 write_protected: true,
+                mount_mode: VhdMountMode::default(),
             });
         }
     }
@@ -652,6 +752,16 @@ impl GuiState {
         self.hdds[drive].selected_path = name;
     }
 
+    pub fn hdd_mount_mode(&self, drive: usize) -> VhdMountMode {
+        self.hdds.get(drive).map(|hdd| hdd.mount_mode).unwrap_or_default()
+    }
+
+    pub fn set_hdd_mount_mode(&mut self, drive: usize, mode: VhdMountMode) {
+        if let Some(hdd) = self.hdds.get_mut(drive) {
+            hdd.mount_mode = mode;
+        }
+    }
+
     pub fn set_cart_slots(&mut self, slotct: usize) {
         self.carts.clear();
         for idx in 0..slotct {
@@ -690,6 +800,12 @@ impl GuiState {
         self.text_mode_viewer.set_cards(cards.clone());
     }
 
+    /// Provide the current theme's syntax colors to all windows that show disassembly.
+    pub fn set_syntax_colors(&mut self, colors: SyntaxColors) {
+        self.disassembly_viewer.set_syntax_colors(colors);
+        self.trace_viewer.set_syntax_colors(colors);
+    }
+
     pub fn set_scaler_presets(&mut self, presets: &Vec<ScalerPreset>) {
         self.scaler_presets = presets.iter().map(|p| p.name.clone()).collect();
         log::debug!("installed scaler presets: {:?}", self.scaler_presets);
@@ -720,6 +836,10 @@ impl GuiState {
         self.videocard_state = state;
     }
 
+    pub fn update_videocard_registers(&mut self, registers: Vec<(String, u8)>) {
+        self.videocard_registers.update(registers);
+    }
+
     pub fn set_sound_state(&mut self, info: Vec<SoundSourceInfo>) {
         for (snd_idx, source) in info.iter().enumerate() {
             let sctx = GuiVariableContext::SoundSource(snd_idx);
@@ -734,10 +854,53 @@ impl GuiState {
             {
                 *vol = source.volume;
             }
+
+            if let Some(GuiEnum::AudioLatency(latency_ms)) =
+                self.get_option_enum_mut(GuiEnum::AudioLatency(Default::default()), Some(sctx))
+            {
+                *latency_ms = source.target_latency_ms;
+            }
         }
         self.sound_sources = info;
     }
 
+    /// Set whether the specified video card is currently in a text mode. Text screen actions are
+    /// only available for cards in a text mode.
+    pub fn set_card_text_mode(&mut self, vid: VideoCardId, state: bool) {
+        self.card_text_mode.insert(vid, state);
+    }
+
+    /// Place text on the host clipboard. This happens the next time the GUI is drawn.
+    pub fn copy_to_clipboard(&mut self, text: String) {
+        self.clipboard_text = Some(text);
+    }
+
+    /// Set the last POST code written, to be shown in the menu bar status area.
+    pub fn set_post_code(&mut self, code: Option<u8>) {
+        self.post_code = code;
+    }
+
+    /// Set the keyboard lock state to be shown in the menu bar status area.
+    pub fn set_keyboard_lock_state(&mut self, state: Option<KeyboardLockState>) {
+        self.lock_state = state;
+    }
+
+    /// Set whether the machine configuration file has changed since startup, showing a notification bar.
+    pub fn set_config_changed(&mut self, state: bool) {
+        self.config_changed = state;
+    }
+
+    /// Return whether a menu bar menu was open when the menu bar was last drawn.
+    pub fn menu_open(&self) -> bool {
+        self.menu_open
+    }
+
+    /// Set whether the PC speaker is being recorded, and whether a recording is available to save.
+    pub fn set_speaker_recording_state(&mut self, recording: bool, available: bool) {
+        self.speaker_recording = recording;
+        self.speaker_recording_available = available;
+    }
+
     /// Initialize the Sound enum state given a vector of SoundSourceInfo fields.
     pub fn init_sound_info(&mut self, info: Vec<SoundSourceInfo>) {
         self.sound_sources = info;
@@ -755,6 +918,11 @@ impl GuiState {
                 GuiEnum::AudioVolume(sound_source.volume),
                 Some(GuiVariableContext::SoundSource(idx)),
             ));
+
+            enum_vec.push((
+                GuiEnum::AudioLatency(sound_source.target_latency_ms),
+                Some(GuiVariableContext::SoundSource(idx)),
+            ));
         }
 
         // Set all enums.
@@ -800,6 +968,10 @@ impl GuiState {
                     GuiEnum::DisplayComposite(renderer.composite),
                     Some(GuiVariableContext::Display(display.handle)),
                 ));
+                enum_vec.push((
+                    GuiEnum::DisplayPhosphor(renderer.mono_phosphor),
+                    Some(GuiVariableContext::Display(display.handle)),
+                ));
             }
 
             // Create GuiEnums for each display scaler mode.
@@ -811,6 +983,13 @@ impl GuiState {
                 ));
             }
 
+            // Create GuiEnum for CGA snow. The frontend may override this from the machine config.
+            if let Some(vid) = display.vid {
+                if matches!(vid.vtype, VideoType::CGA) {
+                    enum_vec.push((GuiEnum::VideoCardSnow(false), Some(GuiVariableContext::VideoCard(vid))));
+                }
+            }
+
             // Create GuiEnum for Display Type (windowed or background)
             enum_vec.push((
                 GuiEnum::DisplayType(display.dtype),
@@ -36,7 +36,7 @@ mod lilac;
 mod purple;
 
 use crate::themes::{cobalt::CobaltTheme, hal::HalTheme, lilac::LilacTheme, purple::DarkTintedTheme};
-use egui::Visuals;
+use egui::{Color32, Visuals};
 use marty_frontend_common::MartyGuiTheme;
 use std::sync::Arc;
 
@@ -45,9 +45,45 @@ pub enum ThemeBase {
     Dark,
 }
 
+/// Colors used to syntax-highlight disassembly tokens. Token views draw on a black background
+/// regardless of theme, so the defaults are suitable for any theme.
+#[derive(Copy, Clone, Debug)]
+pub struct SyntaxColors {
+    pub instruction_bytes: Color32,
+    pub prefix: Color32,
+    pub mnemonic: Color32,
+    pub register: Color32,
+    pub segment: Color32,
+    pub immediate: Color32,
+    pub value: Color32,
+    pub symbol: Color32,
+    pub bracket: Color32,
+    pub comment: Color32,
+}
+
+impl Default for SyntaxColors {
+    fn default() -> Self {
+        Self {
+            instruction_bytes: Color32::from_rgb(6, 152, 255),
+            prefix: Color32::from_rgb(116, 228, 227),
+            mnemonic: Color32::from_rgb(128, 255, 158),
+            register: Color32::from_rgb(245, 138, 52),
+            segment: Color32::from_rgb(245, 138, 52),
+            immediate: Color32::from_rgb(255, 200, 120),
+            value: Color32::from_rgb(96, 200, 210),
+            symbol: Color32::from_rgb(200, 160, 255),
+            bracket: Color32::from_rgb(228, 214, 116),
+            comment: Color32::from_rgb(110, 140, 110),
+        }
+    }
+}
+
 pub trait GuiTheme: Send + Sync {
     fn visuals(&self) -> Visuals;
     fn base(&self) -> ThemeBase;
+    fn syntax_colors(&self) -> SyntaxColors {
+        SyntaxColors::default()
+    }
 }
 
 pub fn make_theme(theme: MartyGuiTheme) -> Arc<dyn GuiTheme> {
@@ -34,13 +34,15 @@
     to enable color syntax highlighting, hover tooltips and other features.
 
 */
-use std::mem::discriminant;
+use std::{mem::discriminant, ops::RangeInclusive};
 
-use crate::{color::*, constants::*, *};
+use crate::{color::*, constants::*, themes::SyntaxColors, *};
 use egui::*;
-use marty_core::syntax_token::*;
+use marty_core::{cpu_common::CpuAddress, syntax_token::*};
 
 pub const TOKEN_TAB_STOPS: u32 = 128;
+pub const TOKEN_AGE_STEP: u8 = 2;
+const ROM_BYTE_COLOR: Color32 = Color32::from_rgb(200, 140, 80);
 
 pub struct TokenListView {
     pub row: usize,
@@ -59,11 +61,17 @@ pub struct TokenListView {
     pub edit_cursor: usize,
     pub edit_buffer: Option<String>,
     pub edit_hint_buffer: Option<String>,
+    /// Whether the byte at the edit cursor is being edited in the ASCII column.
+    pub edit_ascii: bool,
+    selection: Option<usize>,
 
     pub l_margin: f32,
     pub t_margin: f32,
 
-    hover_text: String,
+    hover_text:   String,
+    tinted_bytes: (usize, Vec<bool>),
+    rom_bytes:    (usize, Vec<bool>),
+    colors:       SyntaxColors,
 }
 
 impl TokenListView {
@@ -84,14 +92,23 @@ impl TokenListView {
             edit_cursor: 0,
             edit_buffer: None,
             edit_hint_buffer: None,
+            edit_ascii: false,
+            selection: None,
 
             l_margin: 5.0,
             t_margin: 3.0,
 
-            hover_text: String::new(),
+            hover_text:   String::new(),
+            tinted_bytes: (0, Vec::new()),
+            rom_bytes:    (0, Vec::new()),
+            colors:       SyntaxColors::default(),
         }
     }
 
+    pub fn set_syntax_colors(&mut self, colors: SyntaxColors) {
+        self.colors = colors;
+    }
+
     pub fn set_visible(&mut self, size: usize) {
         self.visible_rows = size;
     }
@@ -109,7 +126,14 @@ impl TokenListView {
         self.row_offset = Some(pos);
     }
 
-    pub fn set_contents(&mut self, mut contents: Vec<Vec<SyntaxToken>>, scrolling: bool) {
+    pub fn set_contents(&mut self, contents: Vec<Vec<SyntaxToken>>, scrolling: bool) {
+        self.set_contents_aged(contents, scrolling, TOKEN_AGE_STEP);
+    }
+
+    /// Set the contents of the listview, aging unchanged tokens by `age_step`. Tokens that changed
+    /// since the last call start at age 0 and are drawn highlighted until they reach TOKEN_MAX_AGE.
+    /// An `age_step` of 0 holds all highlights at their current age.
+    pub fn set_contents_aged(&mut self, mut contents: Vec<Vec<SyntaxToken>>, scrolling: bool, age_step: u8) {
         if self.contents.len() != contents.len() {
             // Size of contents is changing. Assume these are all new bytes.
 
@@ -119,6 +143,7 @@ impl TokenListView {
                         SyntaxToken::MemoryByteHexValue(.., new_age) => {
                             *new_age = TOKEN_MAX_AGE;
                         }
+                        SyntaxToken::MemoryWordHexValue(.., new_age) => *new_age = TOKEN_MAX_AGE,
                         SyntaxToken::MemoryByteAsciiValue(.., new_age) => *new_age = TOKEN_MAX_AGE,
                         SyntaxToken::StateMemoryAddressSeg16(.., new_age) => *new_age = TOKEN_MAX_AGE,
                         _ => {}
@@ -146,7 +171,7 @@ impl TokenListView {
                                 // This is the same byte as before. Compare values.
                                 if old_val == new_val {
                                     // Byte hasn't changed, so increment age.
-                                    *new_age = old_age.saturating_add(2);
+                                    *new_age = old_age.saturating_add(age_step);
                                 }
                             }
                             else {
@@ -154,6 +179,19 @@ impl TokenListView {
                                 *new_age = 255;
                             }
                         }
+                        (
+                            SyntaxToken::MemoryWordHexValue(new_addr, new_val, _, new_age),
+                            SyntaxToken::MemoryWordHexValue(old_addr, old_val, _, old_age),
+                        ) => {
+                            if old_addr == new_addr {
+                                if old_val == new_val {
+                                    *new_age = old_age.saturating_add(age_step);
+                                }
+                            }
+                            else {
+                                *new_age = 255;
+                            }
+                        }
                         (
                             SyntaxToken::MemoryByteAsciiValue(new_addr, new_val, _, new_age),
                             SyntaxToken::MemoryByteAsciiValue(old_addr, old_val, _, old_age),
@@ -162,7 +200,7 @@ impl TokenListView {
                                 // This is the same byte as before. Compare values.
                                 if old_val == new_val {
                                     // Byte hasn't changed, so increment age.
-                                    *new_age = old_age.saturating_add(2);
+                                    *new_age = old_age.saturating_add(age_step);
                                 }
                             }
                             else {
@@ -176,7 +214,7 @@ impl TokenListView {
                         ) => {
                             if old_seg == new_seg && old_off == new_off {
                                 // This is the same address as before. Update age.
-                                *new_age = old_age.saturating_add(2);
+                                *new_age = old_age.saturating_add(age_step);
                             }
                             else {
                                 // Different address in this position. Reset age if not scrolling.
@@ -194,7 +232,7 @@ impl TokenListView {
                         ) => {
                             if old_s == new_s && old_dirty == new_dirty {
                                 // This is the same string as before. Update age.
-                                *new_age = old_age.saturating_add(2);
+                                *new_age = old_age.saturating_add(age_step);
                             }
                             else {
                                 // Different string in this position. Set age to maximum so it doesn't flash.
@@ -214,6 +252,68 @@ impl TokenListView {
         self.hover_text = text;
     }
 
+    /// Tint the background of memory byte tokens. `tinted[n]` sets whether the byte at `address + n` is tinted.
+    pub fn set_tinted_bytes(&mut self, address: usize, tinted: Vec<bool>) {
+        self.tinted_bytes = (address, tinted);
+    }
+
+    fn is_tinted(&self, address: usize) -> bool {
+        address
+            .checked_sub(self.tinted_bytes.0)
+            .and_then(|i| self.tinted_bytes.1.get(i))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Mark memory byte tokens as ROM. `rom[n]` sets whether the byte at `address + n` is ROM.
+    pub fn set_rom_bytes(&mut self, address: usize, rom: Vec<bool>) {
+        self.rom_bytes = (address, rom);
+    }
+
+    fn is_rom(&self, address: usize) -> bool {
+        address
+            .checked_sub(self.rom_bytes.0)
+            .and_then(|i| self.rom_bytes.1.get(i))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// The address of the byte most recently clicked for editing, if any.
+    pub fn selection(&self) -> Option<usize> {
+        self.selection
+    }
+
+    fn begin_edit(&mut self, address: usize, ascii: bool) {
+        self.edit_mode = true;
+        self.edit_ascii = ascii;
+        self.edit_cursor = address;
+        self.edit_len = 0;
+        self.edit_buffer = None;
+        self.edit_hint_buffer = None;
+        self.edit_requested_focus = false;
+        self.selection = Some(address);
+    }
+
+    fn end_edit(&mut self) {
+        self.edit_mode = false;
+        self.edit_ascii = false;
+        self.edit_len = 0;
+        self.edit_cursor = 0;
+        self.edit_buffer = None;
+        self.edit_hint_buffer = None;
+        self.edit_requested_focus = false;
+    }
+
+    /// Complete entry of the byte at the edit cursor and advance to the next byte.
+    fn advance_edit(&mut self) {
+        self.edit_cursor = self.edit_cursor.wrapping_add(1);
+        self.edit_len = 0;
+        self.edit_buffer = None;
+        self.edit_hint_buffer = None;
+        self.edit_requested_focus = false;
+        self.selection = Some(self.edit_cursor);
+    }
+
     pub fn measure_token(&self, ui: &mut Ui, token: &SyntaxToken, fontid: FontId) -> Rect {
         let old_clip_rect = ui.clip_rect();
         //let old_cursor = ui.cursor();
@@ -310,15 +410,18 @@ impl TokenListView {
             let plus = "+".to_string();
             let null = "[missing token!]".to_string();
 
-            for (i, row) in self.contents[0..show_rows].iter().enumerate() {
+            // Take the contents so that edit state can be updated while drawing them.
+            let contents = std::mem::take(&mut self.contents);
+            for (i, row) in contents[0..show_rows].iter().enumerate() {
                 let x = ui.min_rect().left() + self.l_margin;
                 //let width = ui.min_rect().right() - ui.min_rect().left();
                 let y = start_y + ((i as f32) * row_height) + self.t_margin;
 
                 let mut token_x = x;
 
-                let mut column_select = 32; // Initial value out of range to not highlight anything
-                for (j, token) in row.iter().enumerate() {
+                // Address range of the hovered hex value, to highlight the matching ASCII characters.
+                let mut hovered_range: Option<RangeInclusive<u32>> = None;
+                for token in row.iter() {
                     let mut text_rect;
 
                     let drawn;
@@ -370,7 +473,7 @@ impl TokenListView {
                         }
                         SyntaxToken::MemoryByteHexValue(addr, _, s, cursor, age) => {
                             let response = match self.edit_mode {
-                                true if *addr as usize == self.edit_cursor => {
+                                true if !self.edit_ascii && *addr as usize == self.edit_cursor => {
                                     // Initialize the edit buffer with the current byte value.
                                     // We need to do this to support moving from cell to cell.
                                     if self.edit_buffer.is_none() {
@@ -399,27 +502,16 @@ impl TokenListView {
 
                                     if edit_response.clicked_elsewhere() {
                                         // User can cancel the edit by clicking outside the text edit box.
-                                        self.edit_mode = false;
-                                        self.edit_len = 0;
-                                        self.edit_cursor = 0;
-                                        self.edit_buffer = None;
-                                        self.edit_hint_buffer = None;
-                                        self.edit_requested_focus = false;
+                                        self.end_edit();
                                     }
                                     else if edit_response.lost_focus() {
                                         // A TextEdit "loses focus" on tab or enter.
                                         // We will consider that a completion of a data entry, and update the current byte.
                                         // We will stay in edit mode, and advance the cursor to the next byte.
-                                        self.edit_mode = true;
-                                        self.edit_cursor = self.edit_cursor.wrapping_add(1);
-                                        self.edit_len = 0;
-                                        self.edit_hint_buffer = None;
-                                        self.edit_requested_focus = false;
-
-                                        if let Ok(val) = u8::from_str_radix(&self.edit_buffer.as_ref().unwrap(), 16) {
-                                            events.send(GuiEvent::MemoryByteUpdate(*addr as usize, val));
+                                        if let Ok(val) = u8::from_str_radix(self.edit_buffer.as_ref().unwrap(), 16) {
+                                            events.send(GuiEvent::MemoryEdit(*addr as usize, vec![val]));
                                         }
-                                        self.edit_buffer = None;
+                                        self.advance_edit();
                                     }
                                     else if edit_response.changed() {
                                         if let Some(edit_buffer) = &self.edit_buffer {
@@ -427,33 +519,41 @@ impl TokenListView {
 
                                             if self.edit_len == 1 && current_edit_len == 2 {
                                                 // User just completed a byte entry.
-                                                self.edit_mode = true;
-                                                self.edit_cursor = self.edit_cursor.wrapping_add(1);
-                                                self.edit_len = 0;
-                                                self.edit_hint_buffer = None;
-                                                self.edit_requested_focus = false;
-
-                                                if let Ok(val) =
-                                                    u8::from_str_radix(&self.edit_buffer.as_ref().unwrap(), 16)
-                                                {
-                                                    events.send(GuiEvent::MemoryByteUpdate(*addr as usize, val));
+                                                if let Ok(val) = u8::from_str_radix(edit_buffer, 16) {
+                                                    events.send(GuiEvent::MemoryEdit(*addr as usize, vec![val]));
                                                 }
-                                                self.edit_buffer = None;
+                                                self.advance_edit();
+                                            }
+                                            else {
+                                                self.edit_len = current_edit_len;
                                             }
-                                            self.edit_len = current_edit_len;
                                         }
                                     }
                                 }
                                 _ => {
+                                    let byte_rect = Rect {
+                                        min: pos2(token_x, y),
+                                        max: pos2(token_x + label_rect.max.x + 1.0, y + label_rect.max.y),
+                                    };
+                                    if self.is_tinted(*addr as usize) {
+                                        ui.painter().rect_filled(
+                                            byte_rect,
+                                            egui::Rounding::ZERO,
+                                            Color32::from_rgb(0, 64, 0),
+                                        );
+                                    }
+                                    // ROM is drawn in a different color, as it can only be edited with force.
+                                    let base_color = match self.is_rom(*addr as usize) {
+                                        true => ROM_BYTE_COLOR,
+                                        false => Color32::GRAY,
+                                    };
                                     let label_response = ui
                                         .put(
-                                            Rect {
-                                                min: pos2(token_x, y),
-                                                max: pos2(token_x + label_rect.max.x + 1.0, y + label_rect.max.y),
-                                            },
+                                            byte_rect,
                                             Label::new(RichText::new(s).text_style(TextStyle::Monospace).color(
-                                                fade_c32(Color32::GRAY, Color32::from_rgb(0, 255, 255), 255 - *age),
-                                            )),
+                                                fade_c32(base_color, Color32::from_rgb(0, 255, 255), 255 - *age),
+                                            ))
+                                            .sense(Sense::click()),
                                         )
                                         .on_hover_ui(|ui| {
                                             ui.add(Label::new(
@@ -462,18 +562,11 @@ impl TokenListView {
                                         });
 
                                     if label_response.hovered() {
-                                        column_select = j;
+                                        hovered_range = Some(*addr..=*addr);
                                         events.send(GuiEvent::TokenHover(*addr as usize));
                                     }
-                                    if label_response.double_clicked() {
-                                        log::warn!("Double clicked on token: {} at addr: {:05X}", s, addr);
-                                        self.edit_mode = true;
-                                        self.edit_cursor = *addr as usize;
-                                        if self.edit_buffer.is_none() {
-                                            self.edit_len = 0;
-                                            self.edit_buffer = None;
-                                            self.edit_hint_buffer = None;
-                                        }
+                                    if label_response.clicked() {
+                                        self.begin_edit(*addr as usize, false);
                                     }
                                 }
                             };
@@ -504,7 +597,100 @@ impl TokenListView {
                             used_rect = used_rect.union(text_rect);
                             */
                         }
-                        SyntaxToken::MemoryByteAsciiValue(_addr, _, s, age) => {
+                        SyntaxToken::MemoryWordHexValue(addr, _, s, age) => {
+                            // A word is as wide as two byte tokens, without the gap between them.
+                            let word_rect = Rect {
+                                min: pos2(token_x, y),
+                                max: pos2(token_x + label_rect.max.x * 2.0 + 1.0, y + label_rect.max.y),
+                            };
+                            if self.is_tinted(*addr as usize) || self.is_tinted(*addr as usize + 1) {
+                                ui.painter()
+                                    .rect_filled(word_rect, egui::Rounding::ZERO, Color32::from_rgb(0, 64, 0));
+                            }
+                            text_rect = ui.painter().text(
+                                word_rect.min,
+                                egui::Align2::LEFT_TOP,
+                                s,
+                                font_id.clone(),
+                                fade_c32(Color32::GRAY, Color32::from_rgb(0, 255, 255), 255 - *age),
+                            );
+
+                            let word_response = ui
+                                .interact(word_rect, ui.id().with(("word", *addr)), Sense::hover())
+                                .on_hover_ui(|ui| {
+                                    ui.add(Label::new(
+                                        RichText::new(&self.hover_text).text_style(TextStyle::Monospace),
+                                    ));
+                                });
+                            if word_response.hovered() {
+                                hovered_range = Some(*addr..=addr.wrapping_add(1));
+                                events.send(GuiEvent::TokenHover(*addr as usize));
+                            }
+
+                            token_x = word_rect.max.x + 6.0;
+                            used_rect = used_rect.union(text_rect);
+                            drawn = true;
+                        }
+                        SyntaxToken::MemoryByteUnpopulated(_addr, s) => {
+                            // Unpopulated memory is drawn dimmed and cannot be edited.
+                            text_rect = ui.painter().text(
+                                egui::pos2(token_x, y),
+                                egui::Align2::LEFT_TOP,
+                                s,
+                                font_id.clone(),
+                                Color32::DARK_GRAY,
+                            );
+                            token_x += label_rect.max.x.max(text_rect.width()) + 7.0;
+                            drawn = true;
+                        }
+                        SyntaxToken::MemoryByteAsciiValue(addr, _, s, _)
+                            if self.edit_mode && self.edit_ascii && *addr as usize == self.edit_cursor =>
+                        {
+                            let char_rect = ui.painter().text(
+                                egui::pos2(token_x, y),
+                                egui::Align2::LEFT_TOP,
+                                s,
+                                font_id.clone(),
+                                Color32::TRANSPARENT,
+                            );
+                            let edit_buffer = self.edit_buffer.get_or_insert_with(String::new);
+                            let edit_response = ui.put(
+                                char_rect.expand2(vec2(1.0, 0.0)),
+                                TextEdit::singleline(edit_buffer)
+                                    .font(TextStyle::Monospace)
+                                    .char_limit(1)
+                                    .hint_text(s)
+                                    .margin(0.0),
+                            );
+
+                            if !self.edit_requested_focus {
+                                ui.memory_mut(|mem| mem.request_focus(edit_response.id));
+                                self.edit_requested_focus = true;
+                            }
+
+                            if edit_response.clicked_elsewhere() {
+                                self.end_edit();
+                            }
+                            else if edit_response.lost_focus() {
+                                // Enter or tab skips over the current character.
+                                self.advance_edit();
+                            }
+                            else if edit_response.changed() {
+                                // Only printable ASCII characters can be typed.
+                                match self.edit_buffer.as_ref().and_then(|b| b.chars().next()) {
+                                    Some(c) if c.is_ascii() && !c.is_ascii_control() => {
+                                        events.send(GuiEvent::MemoryEdit(*addr as usize, vec![c as u8]));
+                                        self.advance_edit();
+                                    }
+                                    _ => self.edit_buffer = None,
+                                }
+                            }
+
+                            token_x = char_rect.max.x + 2.0;
+                            used_rect = used_rect.union(char_rect);
+                            drawn = true;
+                        }
+                        SyntaxToken::MemoryByteAsciiValue(addr, _, s, age) => {
                             text_rect = ui.painter().text(
                                 egui::pos2(token_x, y),
                                 egui::Align2::LEFT_TOP,
@@ -513,10 +699,15 @@ impl TokenListView {
                                 fade_c32(Color32::LIGHT_GRAY, Color32::from_rgb(0, 255, 255), 255 - *age),
                             );
 
-                            // If previous hex byte was hovered, show a rectangle around this ascii byte
-                            // TODO: Rather than rely on hex bytes directly preceding the ascii bytes,
-                            // use an 'index' field in the enum?
-                            if (j - 16) == column_select {
+                            if ui
+                                .interact(text_rect, ui.id().with(("ascii", *addr)), Sense::click())
+                                .clicked()
+                            {
+                                self.begin_edit(*addr as usize, true);
+                            }
+
+                            // If the hex value for this byte was hovered, show a rectangle around it.
+                            if hovered_range.as_ref().is_some_and(|range| range.contains(addr)) {
                                 ui.painter().rect(
                                     text_rect.expand(2.0),
                                     egui::Rounding::ZERO,
@@ -535,12 +726,51 @@ impl TokenListView {
                                 egui::Align2::LEFT_TOP,
                                 s,
                                 font_id.clone(),
-                                Color32::from_rgb(128, 255, 158),
+                                self.colors.mnemonic,
                             );
                             token_x = text_rect.min.x + 45.0;
                             used_rect = used_rect.union(text_rect);
                             drawn = true;
                         }
+                        SyntaxToken::BranchTarget(..) | SyntaxToken::Symbol(..) => {
+                            // Jump and call targets can be double-clicked to navigate to them.
+                            let (target, s, color) = match token {
+                                SyntaxToken::BranchTarget(target, s) => (*target, s, self.colors.value),
+                                SyntaxToken::Symbol(addr, s) => (CpuAddress::Flat(*addr), s, self.colors.symbol),
+                                _ => unreachable!(),
+                            };
+                            text_rect = ui.painter().text(
+                                egui::pos2(token_x, y),
+                                egui::Align2::LEFT_TOP,
+                                s,
+                                font_id.clone(),
+                                color,
+                            );
+
+                            let target_response = ui
+                                .interact(text_rect, ui.id().with(("branch_target", i)), Sense::click())
+                                .on_hover_cursor(CursorIcon::PointingHand)
+                                .on_hover_text(format!("Double-click to go to {}", target));
+                            if target_response.double_clicked() {
+                                events.send(GuiEvent::NavigateDisassembly(target));
+                            }
+
+                            token_x = text_rect.max.x + 2.0;
+                            used_rect = used_rect.union(text_rect);
+                            drawn = true;
+                        }
+                        SyntaxToken::Comment(s) => {
+                            text_rect = ui.painter().text(
+                                egui::pos2(token_x + 8.0, y),
+                                egui::Align2::LEFT_TOP,
+                                format!("; {}", s),
+                                font_id.clone(),
+                                self.colors.comment,
+                            );
+                            token_x = text_rect.max.x + 2.0;
+                            used_rect = used_rect.union(text_rect);
+                            drawn = true;
+                        }
                         SyntaxToken::StateMemoryAddressSeg16(_, _, s, age) => {
                             text_rect = ui.painter().text(
                                 egui::pos2(token_x, y),
@@ -573,18 +803,17 @@ impl TokenListView {
                     if !drawn {
                         let (token_color, token_text, token_padding) = match token {
                             SyntaxToken::MemoryAddressSeg16(_, _, s) => (Color32::LIGHT_GRAY, s, 10.0),
-                            SyntaxToken::InstructionBytes(s) => (Color32::from_rgb(6, 152, 255), s, 1.0),
-                            SyntaxToken::Prefix(s) => (Color32::from_rgb(116, 228, 227), s, 6.0),
-                            SyntaxToken::Register(s) => (Color32::from_rgb(245, 138, 52), s, 1.0),
-                            SyntaxToken::OpenBracket => (Color32::from_rgb(228, 214, 116), &l_bracket, 1.0),
-                            SyntaxToken::CloseBracket => (Color32::from_rgb(228, 214, 116), &r_bracket, 2.0),
+                            SyntaxToken::InstructionBytes(s) => (self.colors.instruction_bytes, s, 1.0),
+                            SyntaxToken::Prefix(s) => (self.colors.prefix, s, 6.0),
+                            SyntaxToken::Register(s) => (self.colors.register, s, 1.0),
+                            SyntaxToken::OpenBracket => (self.colors.bracket, &l_bracket, 1.0),
+                            SyntaxToken::CloseBracket => (self.colors.bracket, &r_bracket, 2.0),
                             SyntaxToken::Colon => (Color32::LIGHT_GRAY, &colon, 1.0),
                             SyntaxToken::Comma => (Color32::LIGHT_GRAY, &comma, 6.0),
                             SyntaxToken::PlusSign => (Color32::LIGHT_GRAY, &plus, 1.0),
-                            SyntaxToken::Displacement(s) | SyntaxToken::HexValue(s) => {
-                                (Color32::from_rgb(96, 200, 210), s, 2.0)
-                            }
-                            SyntaxToken::Segment(s) => (Color32::from_rgb(245, 138, 52), s, 1.0),
+                            SyntaxToken::Displacement(s) | SyntaxToken::HexValue(s) => (self.colors.value, s, 2.0),
+                            SyntaxToken::Immediate(_, s) => (self.colors.immediate, s, 2.0),
+                            SyntaxToken::Segment(s) => (self.colors.segment, s, 1.0),
                             SyntaxToken::Text(s) => (Color32::LIGHT_GRAY, s, 2.0),
                             SyntaxToken::ErrorString(s) => (Color32::RED, s, 2.0),
                             _ => (Color32::WHITE, &null, 2.0),
@@ -603,6 +832,8 @@ impl TokenListView {
                 }
             }
 
+            self.contents = contents;
+
             //egui::TextEdit::multiline(&mut format!("hi!"))
             //    .font(egui::TextStyle::Monospace);
 
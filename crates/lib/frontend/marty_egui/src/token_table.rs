@@ -440,9 +440,9 @@ impl TokenTable {
                             SyntaxToken::Colon => (Color32::LIGHT_GRAY, &colon, 1.0),
                             SyntaxToken::Comma => (Color32::LIGHT_GRAY, &comma, 6.0),
                             SyntaxToken::PlusSign => (Color32::LIGHT_GRAY, &plus, 1.0),
-                            SyntaxToken::Displacement(s) | SyntaxToken::HexValue(s) => {
-                                (Color32::from_rgb(96, 200, 210), s, 2.0)
-                            }
+                            SyntaxToken::Displacement(s)
+                            | SyntaxToken::HexValue(s)
+                            | SyntaxToken::BranchTarget(_, s) => (Color32::from_rgb(96, 200, 210), s, 2.0),
                             SyntaxToken::Segment(s) => (Color32::from_rgb(245, 138, 52), s, 1.0),
                             SyntaxToken::Text(s) => (Color32::LIGHT_GRAY, s, 2.0),
                             SyntaxToken::ErrorString(s) => (Color32::RED, s, 2.0),
@@ -36,6 +36,9 @@ impl GuiState {
     pub fn show_windows(&mut self, ctx: &Context) {
         // Init things that need the context
         self.toasts.show(ctx);
+        if let Some(text) = self.clipboard_text.take() {
+            ctx.copy_text(text);
+        }
         self.data_visualizer.init(ctx.clone());
         self.floppy_viewer.init(ctx.clone());
 
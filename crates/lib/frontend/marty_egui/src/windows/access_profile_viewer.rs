@@ -0,0 +1,110 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    ---------------------------------------------------------------------------
+
+    egui::access_profile_viewer.rs
+
+    Implements a viewer for the memory access profiler, showing how many
+    accesses and wait states each region of the address space accounted
+    for in the last frame.
+
+*/
+
+use crate::*;
+use marty_core::access_profiler::AccessProfileRegion;
+
+const BAR_WIDTH: f32 = 160.0;
+
+pub struct AccessProfileViewerControl {
+    profile: Vec<AccessProfileRegion>,
+}
+
+impl AccessProfileViewerControl {
+    pub fn new() -> Self {
+        Self { profile: Vec::new() }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        if self.profile.is_empty() {
+            ui.label("No profile data.");
+            return;
+        }
+
+        let total_accesses: u64 = self.profile.iter().map(|region| region.accesses).sum();
+        let total_waits: u64 = self.profile.iter().map(|region| region.wait_cycles).sum();
+
+        egui::Grid::new("access_profile_grid")
+            .striped(true)
+            .min_col_width(60.0)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new("Region").strong());
+                ui.label(egui::RichText::new("Range").strong());
+                ui.label(egui::RichText::new("Accesses").strong());
+                ui.label(egui::RichText::new("% Accesses").strong());
+                ui.label(egui::RichText::new("Wait cycles").strong());
+                ui.label(egui::RichText::new("% Waits").strong());
+                ui.end_row();
+
+                for region in self.profile.iter() {
+                    ui.label(&region.name);
+                    if region.end > region.start {
+                        ui.label(egui::RichText::new(format!("{:05X}-{:05X}", region.start, region.end)).monospace());
+                    }
+                    else {
+                        ui.label("");
+                    }
+                    ui.label(format!("{}", region.accesses));
+                    Self::percent_bar(ui, region.accesses, total_accesses);
+                    ui.label(format!("{}", region.wait_cycles));
+                    Self::percent_bar(ui, region.wait_cycles, total_waits);
+                    ui.end_row();
+                }
+
+                ui.label(egui::RichText::new("Total").strong());
+                ui.label("");
+                ui.label(format!("{}", total_accesses));
+                ui.label("");
+                ui.label(format!("{}", total_waits));
+                ui.label("");
+                ui.end_row();
+            });
+    }
+
+    fn percent_bar(ui: &mut egui::Ui, value: u64, total: u64) {
+        let fraction = if total > 0 { value as f32 / total as f32 } else { 0.0 };
+        ui.add(
+            egui::ProgressBar::new(fraction)
+                .desired_width(BAR_WIDTH)
+                .text(format!("{:.1}%", fraction * 100.0)),
+        );
+    }
+
+    /// Update the profile with the totals of the last completed frame.
+    pub fn set_profile(&mut self, profile: &[AccessProfileRegion]) {
+        self.profile.clear();
+        self.profile.extend_from_slice(profile);
+    }
+}
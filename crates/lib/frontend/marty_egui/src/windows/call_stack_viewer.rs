@@ -29,19 +29,32 @@
 */
 
 use crate::GuiEventQueue;
+use marty_core::cpu_common::CallStackOverflowPolicy;
 
 pub struct CallStackViewer {
-    content: String,
+    content:  String,
+    overflow: Option<CallStackOverflowPolicy>,
 }
 
 impl CallStackViewer {
     pub fn new() -> Self {
         Self {
-            content: Default::default(),
+            content:  Default::default(),
+            overflow: None,
         }
     }
 
     pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        if let Some(policy) = self.overflow {
+            let lost = match policy {
+                CallStackOverflowPolicy::DropOldest => "oldest",
+                CallStackOverflowPolicy::DropNewest => "newest",
+            };
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!("WARNING: Stack overflow - {} entries lost", lost),
+            );
+        }
         ui.horizontal(|ui| {
             ui.add_sized(
                 ui.available_size(),
@@ -51,7 +64,8 @@ impl CallStackViewer {
         });
     }
 
-    pub fn set_content(&mut self, content: String) {
+    pub fn set_content(&mut self, content: String, overflow: Option<CallStackOverflowPolicy>) {
         self.content = content;
+        self.overflow = overflow;
     }
 }
@@ -0,0 +1,122 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    ---------------------------------------------------------------------------
+
+    egui::cmos_viewer.rs
+
+    Implements a viewer for the CMOS RAM of the real time clock.
+
+*/
+
+use crate::*;
+use marty_core::devices::rtc::{
+    cmos_checksum,
+    CMOS_CHECKSUM_END,
+    CMOS_CHECKSUM_HI,
+    CMOS_CHECKSUM_LO,
+    CMOS_CHECKSUM_START,
+    CMOS_SIZE,
+};
+
+/// The clock and status registers at the start of CMOS RAM.
+const CLOCK_REGISTERS: usize = 0x0E;
+const COLUMNS: usize = 16;
+
+pub struct CmosViewerControl {
+    cmos: Option<[u8; CMOS_SIZE]>,
+}
+
+impl CmosViewerControl {
+    pub fn new() -> Self {
+        Self { cmos: None }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        let Some(cmos) = &self.cmos
+        else {
+            ui.label("This machine has no real time clock.");
+            return;
+        };
+
+        let stored = u16::from_be_bytes([cmos[CMOS_CHECKSUM_HI], cmos[CMOS_CHECKSUM_LO]]);
+        let calculated = cmos_checksum(cmos);
+        ui.horizontal(|ui| {
+            ui.label("Checksum:");
+            ui.monospace(format!("{:04X}", stored));
+            if stored == calculated {
+                ui.colored_label(egui::Color32::LIGHT_GREEN, "Valid");
+            }
+            else {
+                ui.colored_label(
+                    egui::Color32::LIGHT_RED,
+                    format!("Invalid (expected {:04X})", calculated),
+                );
+            }
+        });
+        ui.separator();
+
+        egui::Grid::new("cmos_viewer_grid")
+            .striped(true)
+            .spacing(egui::vec2(6.0, 2.0))
+            .show(ui, |ui| {
+                ui.label("");
+                for col in 0..COLUMNS {
+                    ui.monospace(egui::RichText::new(format!("{:X}", col)).weak());
+                }
+                ui.end_row();
+
+                for (row, bytes) in cmos.chunks(COLUMNS).enumerate() {
+                    ui.monospace(egui::RichText::new(format!("{:02X}", row * COLUMNS)).weak());
+                    for (col, byte) in bytes.iter().enumerate() {
+                        let addr = row * COLUMNS + col;
+                        let mut text = egui::RichText::new(format!("{:02X}", byte)).monospace();
+                        if addr < CLOCK_REGISTERS {
+                            text = text.color(egui::Color32::LIGHT_BLUE);
+                        }
+                        else if (CMOS_CHECKSUM_START..=CMOS_CHECKSUM_END).contains(&addr) {
+                            text = text.color(egui::Color32::from_rgb(0xC0, 0xC0, 0x80));
+                        }
+                        else if addr == CMOS_CHECKSUM_HI || addr == CMOS_CHECKSUM_LO {
+                            text = text.strong();
+                        }
+                        ui.label(text)
+                            .on_hover_text(format!("{:02X}h: {:02X}h ({})", addr, byte, byte));
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::LIGHT_BLUE, "Clock");
+            ui.colored_label(egui::Color32::from_rgb(0xC0, 0xC0, 0x80), "Checksummed");
+        });
+    }
+
+    pub fn set_content(&mut self, cmos: Option<[u8; CMOS_SIZE]>) {
+        self.cmos = cmos;
+    }
+}
@@ -42,7 +42,9 @@ pub struct BreakpointSet<'a> {
     pub breakpoint: &'a str,
     pub mem_breakpoint: &'a str,
     pub int_breakpoint: &'a str,
+    pub int_ah_filter: &'a str,
     pub io_breakpoint: &'a str,
+    pub raster_breakpoint: &'a str,
     pub sw_start: &'a str,
     pub sw_stop: &'a str,
 }
@@ -52,7 +54,9 @@ pub struct CpuControl {
     breakpoint: String,
     mem_breakpoint: String,
     int_breakpoint: String,
+    int_ah_filter: String,
     io_breakpoint: String,
+    raster_breakpoint: String,
     sw_start: String,
     sw_stop: String,
     sw_measurements: String,
@@ -70,7 +74,9 @@ impl CpuControl {
             breakpoint: String::new(),
             mem_breakpoint: String::new(),
             int_breakpoint: String::new(),
+            int_ah_filter: String::new(),
             io_breakpoint: String::new(),
+            raster_breakpoint: String::new(),
             sw_start: String::new(),
             sw_stop: String::new(),
             sw_measurements: String::new(),
@@ -86,7 +92,10 @@ impl CpuControl {
         let mut exec_control = self.exec_control.borrow_mut();
 
         let (pause_enabled, step_enabled, run_enabled) = match exec_control.state {
-            ExecutionState::Paused | ExecutionState::BreakpointHit | ExecutionState::StepOverHit => (false, true, true),
+            ExecutionState::Paused
+            | ExecutionState::BreakpointHit
+            | ExecutionState::StepOverHit
+            | ExecutionState::StepOutHit => (false, true, true),
             ExecutionState::Running => (true, false, false),
             ExecutionState::Halted => (false, false, false),
         };
@@ -137,6 +146,17 @@ impl CpuControl {
                 */
             });
 
+            ui.add_enabled_ui(step_enabled, |ui| {
+                if ui
+                    .button(egui::RichText::new("⤴").font(egui::FontId::proportional(20.0)))
+                    .on_hover_text("Step Out")
+                    .on_disabled_hover_text("Step Out")
+                    .clicked()
+                {
+                    events.send(GuiEvent::StepOut);
+                };
+            });
+
             ui.add_enabled_ui(run_enabled, |ui| {
                 if ui
                     .button(egui::RichText::new("▶").font(egui::FontId::proportional(20.0)))
@@ -250,11 +270,31 @@ impl CpuControl {
                 }
                 ui.end_row();
 
+                ui.label("Int AH Filter: ");
+                if ui
+                    .text_edit_singleline(&mut self.int_ah_filter)
+                    .on_hover_text("Only break on the interrupt when AH has this value (hex)")
+                    .changed()
+                {
+                    events.send(GuiEvent::EditBreakpoint);
+                }
+                ui.end_row();
+
                 ui.label("IO Breakpoint: ");
                 if ui.text_edit_singleline(&mut self.io_breakpoint).changed() {
                     events.send(GuiEvent::EditBreakpoint);
                 }
                 ui.end_row();
+
+                ui.label("Raster Line: ");
+                if ui
+                    .text_edit_singleline(&mut self.raster_breakpoint)
+                    .on_hover_text("Break when the video card's raster beam reaches this scanline (decimal)")
+                    .changed()
+                {
+                    events.send(GuiEvent::EditBreakpoint);
+                }
+                ui.end_row();
             });
 
         egui::CollapsingHeader::new("StopWatch")
@@ -317,7 +357,9 @@ impl CpuControl {
             breakpoint: &self.breakpoint,
             mem_breakpoint: &self.mem_breakpoint,
             int_breakpoint: &self.int_breakpoint,
+            int_ah_filter: &self.int_ah_filter,
             io_breakpoint: &self.io_breakpoint,
+            raster_breakpoint: &self.raster_breakpoint,
             sw_start: &self.sw_start,
             sw_stop: &self.sw_stop,
         }
@@ -35,7 +35,7 @@ use crate::layouts::MartyLayout;
 use crate::*;
 use egui::TextBuffer;
 use marty_core::{
-    cpu_common::CpuStringState,
+    cpu_common::{CpuStringState, Flag},
     machine::{ExecutionControl, ExecutionState},
 };
 use std::{cell::RefCell, rc::Rc};
@@ -44,7 +44,6 @@ pub struct CpuViewerControl {
     exec_control: Rc<RefCell<ExecutionControl>>,
     cpu_state: CpuStringState,
     reg_updated: bool,
-    flag_updated: bool,
     paused_updates: u32,
 }
 
@@ -54,7 +53,6 @@ impl CpuViewerControl {
             exec_control,
             cpu_state: Default::default(),
             reg_updated: false,
-            flag_updated: false,
             paused_updates: 0,
         }
     }
@@ -161,22 +159,20 @@ impl CpuViewerControl {
     ) {
         ui.horizontal(|ui| {
             ui.label(egui::RichText::new(label).text_style(egui::TextStyle::Monospace));
+            let parsed = parse_register_value(value.as_str());
             let response = ui.add(
                 egui::TextEdit::singleline(value)
                     .char_limit(4)
+                    .text_color_opt(parsed.is_none().then_some(egui::Color32::RED))
                     .font(egui::TextStyle::Monospace),
             );
 
             if response.lost_focus() {
                 // TextEdit loses focus on enter or tab. In any case, we'll apply the value if it is valid.
-                match u16::from_str_radix(value.as_str(), 16) {
-                    Ok(val) => {
-                        log::debug!("Register {:?} updated to 0x{:04X}", reg, val);
-                        events.send(GuiEvent::Register16Update(reg, val));
-                    }
-                    Err(_) => {
-                        // Invalid value - could change text color to red?
-                    }
+                // An invalid value is discarded when the register display is next refreshed.
+                if let Some(val) = parsed {
+                    log::debug!("Register {:?} updated to 0x{:04X}", reg, val);
+                    events.send(GuiEvent::SetRegister(reg, val));
                 }
                 *updated = true;
             }
@@ -185,7 +181,6 @@ impl CpuViewerControl {
 
     #[rustfmt::skip]
     fn show_mutable_regs(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
-        self.flag_updated = false;
         egui::Grid::new("reg_general_grid")
             .striped(true)
             .min_col_width(100.0)
@@ -258,7 +253,9 @@ impl CpuViewerControl {
                     Self::show_reg_mut(ui, "ES", &mut self.cpu_state.es, Register16::ES, &mut self.reg_updated, events);
                 });
                 ui.horizontal(|ui| {
-                    Self::show_reg_mut(ui, "PC", &mut self.cpu_state.pc, Register16::PC, &mut self.reg_updated, events);
+                    // PC is the prefetch address. Edit IP instead, which flushes the queue.
+                    ui.label(egui::RichText::new("PC:").text_style(egui::TextStyle::Monospace));
+                    ui.add(egui::TextEdit::singleline(&mut self.cpu_state.pc.as_str()).font(egui::TextStyle::Monospace));
                 });
                 ui.end_row();
 
@@ -269,9 +266,8 @@ impl CpuViewerControl {
                     Self::show_reg_mut(ui, "CS", &mut self.cpu_state.cs, Register16::CS, &mut self.reg_updated, events);
                 });
                 ui.horizontal(|ui| {
-                    // IP is not a real register - don't allow editing (?)
-                    ui.label(egui::RichText::new("IP:").text_style(egui::TextStyle::Monospace));
-                    ui.add(egui::TextEdit::singleline(&mut self.cpu_state.ip.as_str()).font(egui::TextStyle::Monospace));
+                    // IP is not a real register. Setting it sets PC, and the frontend flushes the queue.
+                    Self::show_reg_mut(ui, "IP", &mut self.cpu_state.ip, Register16::PC, &mut self.reg_updated, events);
                 });
                 ui.end_row();
 
@@ -298,15 +294,15 @@ impl CpuViewerControl {
             .striped(true)
             .max_col_width(10.0)
             .show(ui, |ui| {
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.o_fl, &mut self.flag_updated,  "O", "Overflow");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.d_fl, &mut self.flag_updated, "D", "Direction");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.i_fl, &mut self.flag_updated, "I", "Interrupt enable");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.t_fl, &mut self.flag_updated, "T", "Trap");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.s_fl, &mut self.flag_updated, "S", "Sign");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.z_fl, &mut self.flag_updated, "Z", "Zero");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.a_fl, &mut self.flag_updated, "A", "Auxiliary carry");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.p_fl, &mut self.flag_updated, "P", "Parity");
-                Self::show_flagbit_mut(ui, &mut self.cpu_state.c_fl, &mut self.flag_updated, "C", "Carry");
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.o_fl, Flag::Overflow, "O", "Overflow", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.d_fl, Flag::Direction, "D", "Direction", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.i_fl, Flag::Interrupt, "I", "Interrupt enable", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.t_fl, Flag::Trap, "T", "Trap", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.s_fl, Flag::Sign, "S", "Sign", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.z_fl, Flag::Zero, "Z", "Zero", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.a_fl, Flag::AuxCarry, "A", "Auxiliary carry", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.p_fl, Flag::Parity, "P", "Parity", events);
+                Self::show_flagbit_mut(ui, &mut self.cpu_state.c_fl, Flag::Carry, "C", "Carry", events);
                 ui.end_row();
             });
 
@@ -314,9 +310,6 @@ impl CpuViewerControl {
             log::trace!("Clearing paused update count on reg update...");
             self.paused_updates = 0;
         }
-        if self.flag_updated {
-            events.send(GuiEvent::CpuFlagsUpdate(self.calc_flag_value()));
-        }
     }
 
     #[rustfmt::skip]
@@ -491,29 +484,28 @@ impl CpuViewerControl {
         });
     }
 
-    /// Display a widget for an editable flag bit. It will show the provided tooltip text on hover.
-    fn show_flagbit_mut(ui: &mut egui::Ui, text: &mut dyn TextBuffer, updated: &mut bool, label: &str, tip: &str) {
+    /// Display a widget for an editable flag bit. Clicking the bit toggles the flag. It will show
+    /// the provided tooltip text on hover.
+    fn show_flagbit_mut(
+        ui: &mut egui::Ui,
+        text: &mut String,
+        flag: Flag,
+        label: &str,
+        tip: &str,
+        events: &mut GuiEventQueue,
+    ) {
         ui.vertical(|ui| {
-            let edit_response = ui.add(
-                egui::TextEdit::singleline(text)
-                    .char_limit(1)
-                    .horizontal_align(egui::Align::Center)
-                    .char_limit(1)
-                    .font(egui::TextStyle::Monospace),
-            );
-
-            if edit_response.lost_focus() {
-                // TextEdit loses focus on enter or tab. In any case, we'll apply the value if it is valid.
-                match u16::from_str_radix(text.as_str(), 16) {
-                    Ok(val) if val == 0 || val == 1 => {
-                        log::debug!("Flag {} updated to {}", label, val);
-                        //events.send(GuiEvent::Register16Update(reg, val));
-                    }
-                    _ => {
-                        // Invalid value - could change text color to red?
-                    }
-                }
-                *updated = true;
+            let set = text.as_str() == "1";
+            let response = ui.add(egui::SelectableLabel::new(
+                set,
+                egui::RichText::new(text.as_str()).text_style(egui::TextStyle::Monospace),
+            ));
+
+            if response.clicked() {
+                log::debug!("Flag {} updated to {}", label, !set);
+                events.send(GuiEvent::SetFlag(flag, !set));
+                // Update the displayed bit immediately, as we only accept one state update while paused.
+                *text = if set { "0" } else { "1" }.to_string();
             }
 
             ui.centered_and_justified(|ui| {
@@ -539,34 +531,6 @@ impl CpuViewerControl {
         });
     }
 
-    /// Calculate the flag value from the current string state.
-    /// Note we don't have to account for reserved fields, as the cpu's set_flags method will
-    /// enforce the correct bit values.
-    #[rustfmt::skip]
-    fn calc_flag_value(&self) -> u16 {
-        let mut flags = 0u16;
-        //const CPU_FLAG_CARRY: u16      = 0b0000_0000_0001;
-        //const CPU_FLAG_RESERVED1: u16  = 0b0000_0000_0010;
-        //const CPU_FLAG_PARITY: u16     = 0b0000_0000_0100;
-        //const CPU_FLAG_AUX_CARRY: u16  = 0b0000_0001_0000;
-        //const CPU_FLAG_ZERO: u16       = 0b0000_0100_0000;
-        //const CPU_FLAG_SIGN: u16       = 0b0000_1000_0000;
-        //const CPU_FLAG_TRAP: u16       = 0b0001_0000_0000;
-        //const CPU_FLAG_INT_ENABLE: u16 = 0b0010_0000_0000;
-        //const CPU_FLAG_DIRECTION: u16  = 0b0100_0000_0000;
-        //const CPU_FLAG_OVERFLOW: u16   = 0b1000_0000_0000;
-        flags |= if self.cpu_state.c_fl.as_str() == "1" { 0b0000_0000_0001 } else { 0 };
-        flags |= if self.cpu_state.p_fl.as_str() == "1" { 0b0000_0000_0100 } else { 0 };
-        flags |= if self.cpu_state.a_fl.as_str() == "1" { 0b0000_0001_0000 } else { 0 };
-        flags |= if self.cpu_state.z_fl.as_str() == "1" { 0b0000_0100_0000 } else { 0 };
-        flags |= if self.cpu_state.s_fl.as_str() == "1" { 0b0000_1000_0000 } else { 0 };
-        flags |= if self.cpu_state.t_fl.as_str() == "1" { 0b0001_0000_0000 } else { 0 };
-        flags |= if self.cpu_state.i_fl.as_str() == "1" { 0b0010_0000_0000 } else { 0 };
-        flags |= if self.cpu_state.d_fl.as_str() == "1" { 0b0100_0000_0000 } else { 0 };
-        flags |= if self.cpu_state.o_fl.as_str() == "1" { 0b1000_0000_0000 } else { 0 };
-        flags
-    }
-
     pub fn update_state(&mut self, cpu_state: CpuStringState) {
         let exec_state = self.exec_control.borrow_mut().get_state();
         match exec_state {
@@ -589,3 +553,30 @@ impl CpuViewerControl {
         }
     }
 }
+
+/// Parse a hexadecimal register value entered in the register viewer, rejecting values that do not
+/// fit in 16 bits.
+fn parse_register_value(text: &str) -> Option<u16> {
+    let text = text.trim();
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(text, 16)
+        .ok()
+        .and_then(|val| u16::try_from(val).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_values_must_be_16_bit_hex() {
+        assert_eq!(parse_register_value("1234"), Some(0x1234));
+        assert_eq!(parse_register_value(" ffff "), Some(0xFFFF));
+        assert_eq!(parse_register_value("10000"), None);
+        assert_eq!(parse_register_value("+12"), None);
+        assert_eq!(parse_register_value("12G4"), None);
+        assert_eq!(parse_register_value(""), None);
+    }
+}
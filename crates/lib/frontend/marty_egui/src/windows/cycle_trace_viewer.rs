@@ -30,10 +30,34 @@
     This is a simple edit control for now. Tokenizing with syntaxtokens
     may be interesting, but a bit complex for how niche this feature is.
 
+    If the CPU is recording structured cycle trace records, the viewer
+    instead shows the recorded cycles grouped by instruction.
+
 */
 use crate::*;
+use egui::Color32;
 use egui_extras::{Column, TableBuilder};
-use marty_core::{cpu_common::TraceMode, syntax_token::SyntaxToken};
+use marty_common::types::history_buffer::HistoryBuffer;
+use marty_core::{
+    cpu_common::{
+        cycle_trace::{bus_status_str, queue_op_char, BusTransfer},
+        CycleTraceRecord,
+        QueueOp,
+        Segment,
+        TraceMode,
+    },
+    syntax_token::SyntaxToken,
+};
+
+const READ_COLOR: Color32 = Color32::from_rgb(128, 255, 128);
+const WRITE_COLOR: Color32 = Color32::from_rgb(255, 255, 128);
+
+/// A run of cycle records belonging to one instruction.
+struct InstructionGroup {
+    start: usize,
+    len:   usize,
+    title: String,
+}
 
 pub struct CycleTraceViewerControl {
     pub mode: TraceMode,
@@ -45,6 +69,10 @@ pub struct CycleTraceViewerControl {
     pub content: Vec<Vec<SyntaxToken>>,
     pub col_sizes: Vec<u32>,
     pub col_states: Vec<bool>,
+
+    records:  Vec<CycleTraceRecord>,
+    groups:   Vec<InstructionGroup>,
+    show_raw: bool,
 }
 
 impl CycleTraceViewerControl {
@@ -58,6 +86,9 @@ impl CycleTraceViewerControl {
             content: vec![vec![]],
             col_sizes: Vec::new(),
             col_states: Vec::new(),
+            records: Vec::new(),
+            groups: Vec::new(),
+            show_raw: false,
         }
     }
 
@@ -88,6 +119,16 @@ impl CycleTraceViewerControl {
     pub fn col_select_menu(&mut self, _ui: &mut egui::Ui) {}
 
     pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        if !self.records.is_empty() {
+            if self.mode != TraceMode::None {
+                ui.checkbox(&mut self.show_raw, "Show trace log");
+            }
+            if !self.show_raw {
+                self.draw_records(ui);
+                return;
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.label(egui::RichText::new("Cycles:").text_style(egui::TextStyle::Monospace));
             ui.label(egui::RichText::new(format!("{}", self.instr_len)).text_style(egui::TextStyle::Monospace));
@@ -191,4 +232,168 @@ impl CycleTraceViewerControl {
 
         self.content = trace_vec.clone();
     }
+
+    /// Update the structured cycle records, regrouping them by instruction. The records are only
+    /// copied if new cycles have been recorded since the last update.
+    pub fn update_records(&mut self, records: &HistoryBuffer<CycleTraceRecord>) {
+        let last_cycle = records.iter().last().map(|r| r.cycle);
+        if records.len() == self.records.len() && last_cycle == self.records.last().map(|r| r.cycle) {
+            return;
+        }
+
+        self.records = records.as_vec();
+        self.groups.clear();
+        for (i, record) in self.records.iter().enumerate() {
+            if record.begins_instruction() || self.groups.is_empty() {
+                let title = record
+                    .instruction
+                    .clone()
+                    .unwrap_or_else(|| "(partial instruction)".to_string());
+                self.groups.push(InstructionGroup {
+                    start: i,
+                    len: 0,
+                    title,
+                });
+            }
+            if let Some(group) = self.groups.last_mut() {
+                group.len += 1;
+            }
+        }
+    }
+
+    fn draw_records(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Cycles:").text_style(egui::TextStyle::Monospace));
+            ui.label(egui::RichText::new(format!("{}", self.records.len())).text_style(egui::TextStyle::Monospace));
+            ui.label(egui::RichText::new("Instructions:").text_style(egui::TextStyle::Monospace));
+            ui.label(egui::RichText::new(format!("{}", self.groups.len())).text_style(egui::TextStyle::Monospace));
+        });
+
+        egui::ScrollArea::both()
+            .id_salt("cycle_trace_records_scroll")
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                let group_ct = self.groups.len();
+                for (i, group) in self.groups.iter().enumerate() {
+                    let first_cycle = self.records[group.start].cycle;
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new(format!("{} - {} cycles", group.title, group.len))
+                            .text_style(egui::TextStyle::Monospace),
+                    )
+                    .id_salt(first_cycle)
+                    // Expand the most recent instruction by default.
+                    .default_open(i + 1 == group_ct)
+                    .show(ui, |ui| {
+                        for record in &self.records[group.start..group.start + group.len] {
+                            Self::draw_record(ui, record);
+                        }
+                    });
+                }
+            });
+    }
+
+    fn draw_record(ui: &mut egui::Ui, r: &CycleTraceRecord) {
+        let flag = |state: bool, chr: char| if state { chr } else { '.' };
+        let seg_str = match r.segment {
+            Segment::None => "  ",
+            Segment::SS => "SS",
+            Segment::ES => "ES",
+            Segment::CS => "CS",
+            Segment::DS => "DS",
+        };
+
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            let mono = |ui: &mut egui::Ui, text: String, color: Option<Color32>| {
+                let mut text = egui::RichText::new(text).text_style(egui::TextStyle::Monospace);
+                if let Some(color) = color {
+                    text = text.color(color);
+                }
+                ui.add(egui::Label::new(text).extend());
+            };
+
+            mono(
+                ui,
+                format!(
+                    "{:08}:{:04} {}[{:05X}] {} {}{}{} M:{}{}{} I:{}{}{} |{:5}| ",
+                    r.cycle,
+                    r.instr_cycle,
+                    if r.ale { "A:" } else { "  " },
+                    r.address_latch,
+                    seg_str,
+                    if r.ready { 'R' } else { '.' },
+                    r.wait_states,
+                    if r.last_wait { 'x' } else { '.' },
+                    flag(r.mrdc, 'R'),
+                    flag(r.amwc, 'A'),
+                    flag(r.mwtc, 'W'),
+                    flag(r.iorc, 'R'),
+                    flag(r.aiowc, 'A'),
+                    flag(r.iowc, 'W'),
+                    r.dma,
+                ),
+                None,
+            );
+            mono(
+                ui,
+                bus_status_str(r.bus_status).to_string(),
+                Some(bus_status_color(r.bus_status)),
+            );
+            mono(
+                ui,
+                format!(" {:2} | {} {} | ", r.t_cycle, r.pl_slots[0], r.pl_slots[1]),
+                None,
+            );
+            match r.transfer {
+                BusTransfer::Read(byte) => mono(ui, format!("<-r {:02X}", byte), Some(READ_COLOR)),
+                BusTransfer::Write(byte) => mono(ui, format!("w-> {:02X}", byte), Some(WRITE_COLOR)),
+                BusTransfer::None => mono(ui, "      ".to_string(), None),
+            }
+            mono(ui, format!(" | {:<8}| {:<10} | ", r.bus_pending, r.fetch_state), None);
+            mono(ui, queue_op_char(r.queue_op).to_string(), queue_op_color(r.queue_op));
+            let q_read_str = match r.queue_op {
+                QueueOp::First | QueueOp::Subsequent => format!("<-q {:02X}", r.queue_byte),
+                _ => "      ".to_string(),
+            };
+            mono(
+                ui,
+                format!(
+                    "{}{}[{:08}] {}",
+                    r.queue_len,
+                    if r.queue_preload { '*' } else { ' ' },
+                    r.queue,
+                    q_read_str
+                ),
+                None,
+            );
+            if !r.microcode_line.is_empty() {
+                mono(ui, format!(" | {}: {}", r.microcode_line, r.microcode_src), None);
+            }
+            for comment in &r.comments {
+                mono(ui, format!("; {}", comment), None);
+            }
+        });
+    }
+}
+
+fn bus_status_color(status: u8) -> Color32 {
+    match status & 0x07 {
+        0 => Color32::from_rgb(255, 128, 255),    // INTA
+        1 | 2 => Color32::from_rgb(255, 160, 64), // IOR, IOW
+        3 => Color32::from_rgb(255, 96, 96),      // HALT
+        4 => Color32::from_rgb(128, 192, 255),    // CODE
+        5 => READ_COLOR,
+        6 => WRITE_COLOR,
+        _ => Color32::GRAY, // PASV
+    }
+}
+
+fn queue_op_color(op: QueueOp) -> Option<Color32> {
+    match op {
+        QueueOp::Idle => None,
+        QueueOp::First => Some(Color32::from_rgb(128, 255, 128)),
+        QueueOp::Subsequent => Some(Color32::from_rgb(128, 192, 255)),
+        QueueOp::Flush => Some(Color32::from_rgb(255, 96, 96)),
+    }
 }
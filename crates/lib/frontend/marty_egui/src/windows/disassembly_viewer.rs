@@ -32,13 +32,22 @@
     be an expression, such as 'cs:ip'
 
 */
-use crate::{token_listview::*, *};
-use marty_core::syntax_token::*;
+use std::collections::VecDeque;
+
+use crate::{themes::SyntaxColors, token_listview::*, *};
+use marty_core::{cpu_common::CpuAddress, syntax_token::*};
+
+/// Maximum number of addresses kept in the back and forward navigation history.
+pub const NAVIGATION_HISTORY_LEN: usize = 64;
 
 pub struct DisassemblyControl {
     pub address: String,
     pub row: usize,
     pub lastrow: usize,
+    back: VecDeque<String>,
+    forward: Vec<String>,
+    asm_address: String,
+    asm_text: String,
     tlv: TokenListView,
 }
 
@@ -48,6 +57,10 @@ impl DisassemblyControl {
             address: "cs:ip".to_string(),
             row: 0,
             lastrow: 0,
+            back: VecDeque::new(),
+            forward: Vec::new(),
+            asm_address: "cs:ip".to_string(),
+            asm_text: String::new(),
             tlv: TokenListView::new(),
         }
     }
@@ -58,6 +71,37 @@ impl DisassemblyControl {
             if ui.text_edit_singleline(&mut self.address).changed() {
                 //events.send(GuiEvent::MemoryUpdate);
             }
+            if ui
+                .add_enabled(!self.back.is_empty(), egui::Button::new("⏴"))
+                .on_hover_text("Back")
+                .clicked()
+            {
+                self.back();
+            }
+            if ui
+                .add_enabled(!self.forward.is_empty(), egui::Button::new("⏵"))
+                .on_hover_text("Forward")
+                .clicked()
+            {
+                self.forward();
+            }
+            if ui.button("Go to current").clicked() {
+                self.go_to("cs:ip".to_string());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Assemble at: ");
+            ui.add(egui::TextEdit::singleline(&mut self.asm_address).desired_width(80.0));
+            let response = ui.add(egui::TextEdit::singleline(&mut self.asm_text).hint_text("mov ax, 1234"));
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Assemble").clicked() || submitted) && !self.asm_text.trim().is_empty() {
+                events.send(GuiEvent::AssembleInstruction(
+                    self.asm_address.clone(),
+                    self.asm_text.clone(),
+                ));
+                // Keep focus in the instruction field so that several instructions can be entered in a row.
+                response.request_focus();
+            }
         });
         ui.separator();
 
@@ -76,11 +120,48 @@ impl DisassemblyControl {
         self.tlv.set_contents(mem, false);
     }
 
-    #[allow(dead_code)]
+    pub fn set_syntax_colors(&mut self, colors: SyntaxColors) {
+        self.tlv.set_syntax_colors(colors);
+    }
+
     pub fn set_address(&mut self, address: String) {
         self.address = address;
     }
 
+    /// Navigate to a jump or call target, recording the current address in the back history.
+    pub fn navigate(&mut self, address: CpuAddress) {
+        self.go_to(address.to_string());
+    }
+
+    fn go_to(&mut self, address: String) {
+        if address == self.address {
+            return;
+        }
+        if self.back.len() == NAVIGATION_HISTORY_LEN {
+            self.back.pop_front();
+        }
+        self.back.push_back(std::mem::replace(&mut self.address, address));
+        self.forward.clear();
+    }
+
+    fn back(&mut self) {
+        if let Some(address) = self.back.pop_back() {
+            self.forward.push(std::mem::replace(&mut self.address, address));
+        }
+    }
+
+    fn forward(&mut self) {
+        if let Some(address) = self.forward.pop() {
+            self.back.push_back(std::mem::replace(&mut self.address, address));
+        }
+    }
+
+    /// Advance the assembly address past an instruction that was successfully assembled.
+    pub fn set_assembled(&mut self, next: CpuAddress) {
+        self.asm_address = next.to_string();
+        self.asm_text.clear();
+    }
+
     pub fn get_address(&mut self) -> String {
         self.address.clone()
     }
@@ -26,7 +26,8 @@
 
     egui::dma_viewer.rs
 
-    Implements a viewer control for the DMA Controller.
+    Implements a viewer control for the DMA Controller, including a list
+    of recently completed transfers.
 
 */
 use crate::{constants::*, *};
@@ -166,8 +167,58 @@ impl DmaViewerControl {
                     );
                     ui.add(egui::TextEdit::singleline(&mut chan.masked).font(egui::TextStyle::Monospace));
                     ui.end_row();
+
+                    ui.label(
+                        egui::RichText::new(format!("#{} Request:     ", self.dma_channel_select))
+                            .text_style(egui::TextStyle::Monospace),
+                    );
+                    ui.add(egui::TextEdit::singleline(&mut chan.request).font(egui::TextStyle::Monospace));
+                    ui.end_row();
                 }
             });
+
+        ui.separator();
+        ui.label("Completed Transfers:");
+        egui::ScrollArea::vertical()
+            .id_salt("dma_history_scroll")
+            .max_height(DMA_VIEWER_HISTORY_HEIGHT)
+            .show(ui, |ui| {
+                egui::Grid::new("dma_history")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for heading in ["Ch", "Type", "Address", "Length", "Device"] {
+                            ui.label(
+                                egui::RichText::new(heading)
+                                    .text_style(egui::TextStyle::Monospace)
+                                    .strong(),
+                            );
+                        }
+                        ui.end_row();
+
+                        // Most recent transfer first
+                        for record in self.dma_state.transfer_history.iter().rev() {
+                            ui.label(
+                                egui::RichText::new(format!("{}", record.channel))
+                                    .text_style(egui::TextStyle::Monospace),
+                            );
+                            ui.label(
+                                egui::RichText::new(format!("{:?}", record.transfer_type))
+                                    .text_style(egui::TextStyle::Monospace),
+                            );
+                            ui.label(
+                                egui::RichText::new(format!("{:05X}", record.address))
+                                    .text_style(egui::TextStyle::Monospace),
+                            );
+                            ui.label(
+                                egui::RichText::new(format!("{}", record.length))
+                                    .text_style(egui::TextStyle::Monospace),
+                            );
+                            ui.label(egui::RichText::new(record.device).text_style(egui::TextStyle::Monospace));
+                            ui.end_row();
+                        }
+                    });
+            });
     }
 
     pub fn update_state(&mut self, state: DMAControllerStringState) {
@@ -293,6 +293,17 @@ impl FloppyViewerControl {
                                                     .monospace(),
                                             );
                                             sector_status(ui, &track[idx], true);
+                                            let chsn = &track[idx].chsn;
+                                            if let Some(error) =
+                                                state.sector_errors.get(&(chsn.c(), chsn.h(), chsn.s()))
+                                            {
+                                                ui.label(
+                                                    egui::RichText::new(format!("⚠{}", error.short_name()))
+                                                        .monospace()
+                                                        .color(egui::Color32::RED),
+                                                )
+                                                .on_hover_text(format!("Simulated read error: {:?}", error));
+                                            }
                                         });
                                     }
                                 }
@@ -31,39 +31,104 @@
     the last X executed instructions.
 
 */
-use crate::{token_listview::*, *};
-use marty_core::syntax_token::*;
+use crate::{themes::SyntaxColors, token_listview::*, *};
+use marty_core::{cpu_common::HistoryQuery, syntax_token::*};
+
+const HISTORY_ROWS: usize = 32;
 
 pub struct InstructionHistoryControl {
     pub address: String,
     pub row: usize,
     pub lastrow: usize,
+    filter: String,
+    query: Option<HistoryQuery>,
+    filter_error: bool,
+    content: Vec<Vec<SyntaxToken>>,
+    // Keep the newest entries in view as the history grows, until the user scrolls away.
+    follow: bool,
+    scrolling: bool,
     tlv: TokenListView,
 }
 
 impl InstructionHistoryControl {
     pub fn new() -> Self {
+        let mut tlv = TokenListView::new();
+        tlv.set_capacity(HISTORY_ROWS);
+        tlv.set_visible(HISTORY_ROWS);
+
         Self {
             address: "cs:ip".to_string(),
             row: 0,
             lastrow: 0,
-            tlv: TokenListView::new(),
+            filter: String::new(),
+            query: None,
+            filter_error: false,
+            content: Vec::new(),
+            follow: true,
+            scrolling: false,
+            tlv,
         }
     }
 
     pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
-        self.tlv.set_capacity(32);
-        self.tlv.set_visible(32);
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            let response = ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.filter)
+                        .hint_text("MOV or F000:E000-F000:EFFF")
+                        .desired_width(200.0),
+                )
+                .on_hover_text(
+                    "Show only entries with a mnemonic starting with the given text, or within an address range",
+                );
+            if response.changed() {
+                self.query = HistoryQuery::parse(&self.filter);
+                self.filter_error = self.query.is_none() && !self.filter.trim().is_empty();
+                self.follow = true;
+            }
+            if self.filter_error {
+                ui.colored_label(egui::Color32::LIGHT_RED, "Invalid filter");
+            }
+        });
 
         let mut new_row = self.row;
         ui.horizontal(|ui| {
             self.tlv
                 .draw(ui, events, &mut new_row, &mut |_scrolled_to, _sevents| {});
         });
+
+        // TLV viewport was scrolled, update row
+        if self.row != new_row {
+            self.row = new_row;
+            self.scrolling = true;
+            self.follow = new_row + HISTORY_ROWS >= self.content.len();
+        }
+    }
+
+    /// The query to filter the instruction history by, if any.
+    pub fn query(&self) -> Option<&HistoryQuery> {
+        self.query.as_ref()
+    }
+
+    pub fn set_content(&mut self, content: Vec<Vec<SyntaxToken>>) {
+        self.content = content;
+        self.tlv.set_capacity(self.content.len().max(HISTORY_ROWS));
+
+        let last_row = self.content.len().saturating_sub(HISTORY_ROWS);
+        if (self.follow || self.row > last_row) && self.row != last_row {
+            self.row = last_row;
+            self.tlv.set_scroll_pos(last_row);
+        }
+        self.tlv.set_contents(
+            self.content[self.row..usize::min(self.content.len(), self.row + HISTORY_ROWS)].to_vec(),
+            self.scrolling,
+        );
+        self.scrolling = false;
     }
 
-    pub fn set_content(&mut self, mem: Vec<Vec<SyntaxToken>>) {
-        self.tlv.set_contents(mem, false);
+    pub fn set_syntax_colors(&mut self, colors: SyntaxColors) {
+        self.tlv.set_syntax_colors(colors);
     }
 
     #[allow(dead_code)]
@@ -28,53 +28,204 @@
 
     Implements the a viewer for the IVT (Interrupt Vector Table)
 
+    Each vector is displayed with the memory region its handler resides in
+    and the number of times it has been invoked. Vectors that change are
+    highlighted, and vectors that differ from the baseline snapshot are
+    marked as hooked until the baseline is reset. The instruction that last
+    wrote each vector is shown, revealing which TSR or driver hooked it.
+    Since the control retains
+    its state while the window is closed, any vectors changed in the
+    meantime are detected when the window is reopened.
+
 */
 
-use crate::{token_listview::*, *};
-use marty_core::syntax_token::*;
+use crate::{color::*, *};
+use egui::Color32;
+use egui_extras::{Column, TableBuilder};
+use marty_core::bus::{IvtEntry, IvtTargetRegion};
 
-const DEFAULT_ROWS: usize = 24;
+const ROW_HEIGHT: f32 = 18.0;
+const HOOKED_COLOR: Color32 = Color32::from_rgb(255, 160, 64);
 
 pub struct IvtViewerControl {
-    tlv: TokenListView,
-    row: usize,
-    content: Vec<Vec<SyntaxToken>>,
-    scrolling: bool,
+    entries: Vec<IvtEntry>,
+    counts: Vec<u64>,
+    /// Age of the last change to each vector, 0 being most recent.
+    change_age: Vec<u8>,
+    /// Vector contents at the time the baseline was taken.
+    baseline: Option<Vec<(u16, u16)>>,
+    hooked_only: bool,
 }
 
 impl IvtViewerControl {
     pub fn new() -> Self {
-        let mut tlv = TokenListView::new();
-        tlv.set_capacity(256);
-        tlv.set_visible(DEFAULT_ROWS);
-
         Self {
-            tlv,
-            row: 0,
-            content: Vec::new(),
-            scrolling: false,
+            entries: Vec::new(),
+            counts: vec![0; 256],
+            change_age: vec![255; 256],
+            baseline: None,
+            hooked_only: false,
         }
     }
 
     pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
-        let mut new_row = self.row;
         ui.horizontal(|ui| {
-            self.tlv
-                .draw(ui, events, &mut new_row, &mut |_scrolled_to, _sevents| {});
+            ui.checkbox(&mut self.hooked_only, "Hooked vectors only");
+            if ui
+                .button("Reset Baseline")
+                .on_hover_text("Treat the current vectors as unhooked")
+                .clicked()
+            {
+                self.baseline = Some(self.entries.iter().map(|e| (e.segment, e.offset)).collect());
+            }
         });
+        ui.separator();
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(true)
+            .max_scroll_height(480.0)
+            .column(Column::exact(36.0))
+            .column(Column::exact(84.0))
+            .column(Column::auto().at_least(100.0).clip(true).resizable(true))
+            .column(Column::exact(72.0))
+            .column(Column::exact(84.0))
+            .column(Column::remainder().clip(true))
+            .header(ROW_HEIGHT, |mut header| {
+                for title in ["Vec", "Address", "Region", "Calls", "Written By", "Description"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for entry in self.entries.iter() {
+                    let v = entry.vector as usize;
+                    let hooked = self.is_hooked(entry);
+                    if self.hooked_only && !hooked {
+                        continue;
+                    }
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.monospace(format!("{:02X}h", v));
+                        });
+                        row.col(|ui| {
+                            let color = fade_c32(Color32::LIGHT_GRAY, STATUS_UPDATE_COLOR, 255 - self.change_age[v]);
+                            let address = format!("{:04X}:{:04X}", entry.segment, entry.offset);
+                            if ui
+                                .link(egui::RichText::new(&address).monospace().color(color))
+                                .on_hover_text("Open in Disassembly Viewer")
+                                .clicked()
+                            {
+                                events.send(GuiEvent::ShowDisassembly(address));
+                            }
+                        });
+                        row.col(|ui| {
+                            let text = egui::RichText::new(entry.region.to_string());
+                            ui.label(if hooked { text.color(HOOKED_COLOR) } else { text });
+                        });
+                        row.col(|ui| {
+                            ui.monospace(format!("{}", self.counts[v]));
+                        });
+                        row.col(|ui| {
+                            if let Some(writer) = &entry.written_by {
+                                let address = format!("{:04X}:{:04X}", writer.segment, writer.offset);
+                                if ui
+                                    .link(egui::RichText::new(&address).monospace())
+                                    .on_hover_text(format!(
+                                        "Hooked by [{}] at instruction {}",
+                                        address, writer.instruction
+                                    ))
+                                    .clicked()
+                                {
+                                    events.send(GuiEvent::ShowDisassembly(address));
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(IvtViewerControl::vector_description(entry.vector));
+                        });
+                    });
+                }
+            });
+    }
 
-        // TLV viewport was scrolled, update address
-        if self.row != new_row {
-            //log::debug!("update address to: {}", new_row);
-            self.row = new_row;
-            self.scrolling = true;
+    /// Update the viewer with the current IVT and per-vector invocation counts. Vectors that differ from the
+    /// previous update are highlighted.
+    pub fn set_content(&mut self, entries: Vec<IvtEntry>, counts: &[u64]) {
+        for age in self.change_age.iter_mut() {
+            *age = age.saturating_add(2);
+        }
+        for (new, old) in entries.iter().zip(self.entries.iter()) {
+            if (new.segment, new.offset) != (old.segment, old.offset) {
+                self.change_age[new.vector as usize] = 0;
+            }
+        }
+        if self.baseline.is_none() {
+            self.baseline = Some(entries.iter().map(|e| (e.segment, e.offset)).collect());
         }
+
+        self.entries = entries;
+        self.counts.clear();
+        self.counts.extend_from_slice(counts);
+        self.counts.resize(256, 0);
     }
 
-    pub fn set_content(&mut self, ivt: Vec<Vec<SyntaxToken>>) {
-        self.content = ivt;
-        self.tlv
-            .set_contents(self.content[self.row..self.row + DEFAULT_ROWS].to_vec(), self.scrolling);
-        self.scrolling = false;
+    /// A vector is considered hooked if it differs from the baseline, or if its handler resides in a program's
+    /// memory block.
+    fn is_hooked(&self, entry: &IvtEntry) -> bool {
+        let changed = self.baseline.as_ref().map_or(false, |baseline| {
+            baseline[entry.vector as usize] != (entry.segment, entry.offset)
+        });
+        changed || matches!(entry.region, IvtTargetRegion::Program(..))
+    }
+
+    fn vector_description(vector: u8) -> &'static str {
+        // TODO: The bus should eventually register IRQs, and then we would query the bus for the device identifier
+        //       for each IRQ.
+        match vector {
+            0x00 => "Divide Error",
+            0x01 => "Single Step",
+            0x02 => "NMI",
+            0x03 => "Breakpoint",
+            0x04 => "Overflow",
+            0x05 => "Print Screen",
+            0x08 => "Timer",
+            0x09 => "Keyboard",
+            0x0B => "Serial Port 2",
+            0x0C => "Serial Port 1",
+            0x0D => "HDC",
+            0x0E => "FDC",
+            0x0F => "Parallel Port 1",
+            0x10 => "Video Services",
+            0x11 => "Equipment List",
+            0x12 => "Memory Size",
+            0x13 => "Disk Services",
+            0x14 => "Serial Services",
+            0x15 => "Cassette/System Services",
+            0x16 => "Keyboard Services",
+            0x17 => "Printer Services",
+            0x18 => "ROM BASIC",
+            0x19 => "Bootstrap Loader",
+            0x1A => "Time of Day",
+            0x1B => "Ctrl-Break",
+            0x1C => "User Timer Tick",
+            0x1D => "Video Parameters",
+            0x1E => "Diskette Parameters",
+            0x1F => "Graphics Characters",
+            0x20 => "DOS Terminate",
+            0x21 => "DOS Services",
+            0x22 => "DOS Terminate Address",
+            0x23 => "DOS Ctrl-C Handler",
+            0x24 => "DOS Critical Error Handler",
+            0x25 => "DOS Absolute Disk Read",
+            0x26 => "DOS Absolute Disk Write",
+            0x27 => "DOS Terminate and Stay Resident",
+            0x28 => "DOS Idle",
+            0x2F => "DOS Multiplex",
+            0x33 => "Mouse Services",
+            0x67 => "EMS Services",
+            _ => "",
+        }
     }
 }
@@ -0,0 +1,137 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    -------------------------------------------------------------------------
+
+    egui::machine_templates
+
+    Implements a dialog for picking one of the built-in machine presets.
+
+*/
+
+use crate::*;
+use marty_core::{
+    device_traits::videocard::VideoType,
+    machine_config::VideoCardConfig,
+    machine_preset::{MachinePreset, PresetOverrides},
+};
+use strum::IntoEnumIterator;
+
+const MEMORY_SIZES_KB: [u32; 6] = [64, 128, 256, 384, 512, 640];
+const VIDEO_TYPES: [VideoType; 2] = [VideoType::MDA, VideoType::CGA];
+
+pub struct MachineTemplateDialog {
+    selected: MachinePreset,
+    memory_kb: Option<u32>,
+    video: Option<VideoType>,
+}
+
+impl MachineTemplateDialog {
+    pub fn new() -> Self {
+        Self {
+            selected: MachinePreset::IbmPcXt5160,
+            memory_kb: None,
+            video: None,
+        }
+    }
+
+    /// Whether the preset's video card can be swapped. The PCjr and Tandy 1000 have onboard video.
+    fn video_selectable(preset: MachinePreset) -> bool {
+        matches!(preset, MachinePreset::IbmPc5150 | MachinePreset::IbmPcXt5160)
+    }
+
+    fn overrides(&self) -> PresetOverrides {
+        PresetOverrides {
+            conventional_memory: self.memory_kb.map(|kb| kb * 1024),
+            video: self
+                .video
+                .filter(|_| Self::video_selectable(self.selected))
+                .map(|video_type| {
+                    vec![VideoCardConfig {
+                        video_type,
+                        video_subtype: None,
+                        dip_switch: None,
+                        snow: None,
+                    }]
+                }),
+            ..Default::default()
+        }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
+        ui.label("Select a machine template:");
+        ui.add_space(4.0);
+
+        for preset in MachinePreset::iter() {
+            ui.radio_value(&mut self.selected, preset, preset.name());
+            ui.indent(preset.name(), |ui| {
+                ui.label(egui::RichText::new(preset.description()).weak());
+            });
+        }
+
+        ui.separator();
+        egui::Grid::new("machine_template_overrides")
+            .num_columns(2)
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Memory:");
+                egui::ComboBox::from_id_source("machine_template_memory")
+                    .selected_text(match self.memory_kb {
+                        Some(kb) => format!("{}K", kb),
+                        None => "Default".to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.memory_kb, None, "Default");
+                        for kb in MEMORY_SIZES_KB {
+                            ui.selectable_value(&mut self.memory_kb, Some(kb), format!("{}K", kb));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Video card:");
+                ui.add_enabled_ui(Self::video_selectable(self.selected), |ui| {
+                    egui::ComboBox::from_id_source("machine_template_video")
+                        .selected_text(match self.video {
+                            Some(video_type) => format!("{:?}", video_type),
+                            None => "Default".to_string(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.video, None, "Default");
+                            for video_type in VIDEO_TYPES {
+                                ui.selectable_value(&mut self.video, Some(video_type), format!("{:?}", video_type));
+                            }
+                        });
+                });
+                ui.end_row();
+            });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("🖥 Create").clicked() {
+                events.send(GuiEvent::NewMachineFromTemplate(self.selected, self.overrides()));
+            }
+            ui.label("MartyPC will restart with the new machine.");
+        });
+    }
+}
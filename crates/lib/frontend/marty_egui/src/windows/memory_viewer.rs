@@ -35,9 +35,21 @@
 */
 
 use crate::{token_listview::*, *};
-use marty_core::syntax_token::*;
+use marty_core::{
+    bus::{MemoryDumpLayout, WordOrder},
+    syntax_token::*,
+};
 
 pub const DEFAULT_VIEWER_ROWS: usize = 25;
+pub const DEFAULT_FADE_REFRESHES: u8 = 4;
+
+const ROW_LENGTHS: [usize; 3] = [8, 16, 32];
+
+const HEATMAP_COLUMNS: usize = 16;
+const HEATMAP_CELL_SIZE: f32 = 12.0;
+const HEATMAP_PAGE_SIZE: usize = 0x1000;
+const HEATMAP_COLD: egui::Color32 = egui::Color32::from_rgb(0, 0, 255);
+const HEATMAP_HOT: egui::Color32 = egui::Color32::from_rgb(255, 0, 0);
 
 pub struct MemoryViewerControl {
     pub address_input: String,
@@ -49,6 +61,18 @@ pub struct MemoryViewerControl {
     pub prev_row: usize,
     pub mem: Vec<String>,
     //update_scroll_pos: bool,
+    /// Number of refreshes a changed byte stays highlighted for.
+    pub fade_refreshes: u8,
+    last_refresh: Option<u64>,
+    /// Show a heat map of memory accesses per 4KB page.
+    pub show_heatmap: bool,
+    page_stats: Vec<(u32, u32)>,
+    /// Allow edits to bypass the read-only mapping of ROM.
+    pub force_rom_writes: bool,
+    break_on_edit: bool,
+    paste_text: String,
+    undo_len: usize,
+    layout: MemoryDumpLayout,
     tlv: TokenListView,
 }
 
@@ -64,6 +88,15 @@ impl MemoryViewerControl {
             prev_row: 0,
             mem: Vec::new(),
             //update_scroll_pos: false,
+            fade_refreshes: DEFAULT_FADE_REFRESHES,
+            last_refresh: None,
+            show_heatmap: false,
+            page_stats: Vec::new(),
+            force_rom_writes: false,
+            break_on_edit: false,
+            paste_text: String::new(),
+            undo_len: 0,
+            layout: MemoryDumpLayout::default(),
             tlv: TokenListView::new(),
         }
     }
@@ -77,6 +110,11 @@ impl MemoryViewerControl {
             }
             ui.label("Rows:");
             ui.add(egui::Slider::new(&mut self.visible_rows, 16..=64).text(""));
+            ui.label("Fade:")
+                .on_hover_text("Number of refreshes a changed byte remains highlighted.");
+            ui.add(egui::Slider::new(&mut self.fade_refreshes, 1..=128).text(""));
+            ui.checkbox(&mut self.show_heatmap, "Heat map")
+                .on_hover_text("Show memory accesses per 4KB page since the last CPU reset.");
 
             // if ui.text_edit_singleline(&mut self.address_input).lost_focus() {
             //     log::debug!("text edit changed to {}", self.address_input);
@@ -95,9 +133,76 @@ impl MemoryViewerControl {
             //     }
             // }
         });
+        ui.horizontal(|ui| {
+            let mut layout = self.layout;
+            egui::ComboBox::from_id_salt("memory_viewer_row_len")
+                .selected_text(format!("{} bytes", layout.row_len))
+                .show_ui(ui, |ui| {
+                    for row_len in ROW_LENGTHS {
+                        ui.selectable_value(&mut layout.row_len, row_len, format!("{} bytes", row_len));
+                    }
+                });
+            egui::ComboBox::from_id_salt("memory_viewer_words")
+                .selected_text(Self::words_label(layout.words))
+                .show_ui(ui, |ui| {
+                    for words in [None, Some(WordOrder::LittleEndian), Some(WordOrder::BigEndian)] {
+                        ui.selectable_value(&mut layout.words, words, Self::words_label(words));
+                    }
+                });
+            ui.checkbox(&mut layout.ascii, "ASCII");
+            if layout != self.layout {
+                self.set_layout(layout);
+                events.send(GuiEvent::MemoryUpdate);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.force_rom_writes, "Force ROM writes")
+                .on_hover_text("Allow edits to ROM, bypassing its read-only mapping.");
+            if ui
+                .checkbox(&mut self.break_on_edit, "Break on edit")
+                .on_hover_text("Trigger memory access breakpoints on bytes edited here.")
+                .changed()
+            {
+                events.send(GuiEvent::VariableChanged(
+                    GuiVariableContext::Global,
+                    GuiVariable::Bool(GuiBoolean::BreakOnMemoryEdit, self.break_on_edit),
+                ));
+            }
+            ui.add_enabled_ui(self.undo_len > 0, |ui| {
+                if ui.button(format!("Undo ({})", self.undo_len)).clicked() {
+                    events.send(GuiEvent::UndoMemoryEdit);
+                }
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Paste hex:");
+            ui.add(egui::TextEdit::singleline(&mut self.paste_text).hint_text("90 90 CD 21"));
+            let bytes = Self::parse_hex_bytes(&self.paste_text);
+            let selection = self.tlv.selection();
+            let label = match selection {
+                Some(address) => format!("Write at {:05X}", address),
+                None => "Write".to_string(),
+            };
+            ui.add_enabled_ui(selection.is_some() && bytes.is_some(), |ui| {
+                if ui
+                    .button(label)
+                    .on_hover_text("Write the bytes starting at the last byte clicked.")
+                    .clicked()
+                {
+                    if let (Some(address), Some(bytes)) = (selection, bytes) {
+                        events.send(GuiEvent::MemoryEdit(address, bytes));
+                    }
+                }
+            });
+        });
         ui.separator();
 
-        self.tlv.set_capacity(0x10000);
+        if self.show_heatmap {
+            self.draw_heatmap(ui);
+            ui.separator();
+        }
+
+        self.tlv.set_capacity(0x100000 / self.row_span);
         self.tlv.set_visible(self.visible_rows);
 
         let mut new_row = self.row;
@@ -127,6 +232,68 @@ impl MemoryViewerControl {
         self.prev_row = self.row;
     }
 
+    /// Draw a grid of pages colored from blue (cold) to red (hot) in proportion to their number of
+    /// accesses. Clicking a page jumps to its address.
+    fn draw_heatmap(&mut self, ui: &mut egui::Ui) {
+        let rows = self.page_stats.len().div_ceil(HEATMAP_COLUMNS);
+        let size = egui::vec2(
+            HEATMAP_COLUMNS as f32 * HEATMAP_CELL_SIZE,
+            rows as f32 * HEATMAP_CELL_SIZE,
+        );
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+
+        let max_accesses = self
+            .page_stats
+            .iter()
+            .map(|(reads, writes)| reads.saturating_add(*writes))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let cell_rect = |page: usize| {
+            let min = rect.min
+                + egui::vec2(
+                    (page % HEATMAP_COLUMNS) as f32 * HEATMAP_CELL_SIZE,
+                    (page / HEATMAP_COLUMNS) as f32 * HEATMAP_CELL_SIZE,
+                );
+            egui::Rect::from_min_size(min, egui::vec2(HEATMAP_CELL_SIZE, HEATMAP_CELL_SIZE))
+        };
+
+        if ui.is_rect_visible(rect) {
+            for (page, (reads, writes)) in self.page_stats.iter().enumerate() {
+                let heat = reads.saturating_add(*writes) as f32 / max_accesses as f32;
+                let color = egui::Color32::from_rgb(
+                    egui::lerp(HEATMAP_COLD.r() as f32..=HEATMAP_HOT.r() as f32, heat) as u8,
+                    0,
+                    egui::lerp(HEATMAP_COLD.b() as f32..=HEATMAP_HOT.b() as f32, heat) as u8,
+                );
+                ui.painter().rect_filled(cell_rect(page).shrink(0.5), 0.0, color);
+            }
+        }
+
+        let hovered_page = response.hover_pos().and_then(|pos| {
+            let cell = (pos - rect.min) / HEATMAP_CELL_SIZE;
+            let page = cell.y as usize * HEATMAP_COLUMNS + cell.x as usize;
+            (page < self.page_stats.len()).then_some(page)
+        });
+
+        if let Some(page) = hovered_page {
+            if response.clicked() {
+                self.address = format!("{:05X}", page * HEATMAP_PAGE_SIZE);
+                self.address_input = self.address.clone();
+                self.address_source = InputFieldChangeSource::UserInput;
+            }
+
+            let (reads, writes) = self.page_stats[page];
+            response.on_hover_text(format!(
+                "Page {:05X}: {} reads, {} writes",
+                page * HEATMAP_PAGE_SIZE,
+                reads,
+                writes
+            ));
+        }
+    }
+
     #[allow(dead_code)]
     fn update_addr_from_row(&mut self) {
         self.address_input = format!("{:05X}", self.row * self.row_span);
@@ -161,11 +328,87 @@ impl MemoryViewerControl {
         self.visible_rows * self.row_span
     }
 
-    pub fn set_memory(&mut self, mem: Vec<Vec<SyntaxToken>>) {
-        self.tlv.set_contents(mem, false);
+    /// Set the memory contents to display. `refresh` identifies the state of emulated memory, such
+    /// as the CPU cycle count. Changed bytes are highlighted, and their highlight only fades when
+    /// `refresh` changes, so a single step stays visible while the machine is paused.
+    pub fn set_memory(&mut self, mem: Vec<Vec<SyntaxToken>>, refresh: u64) {
+        let age_step = if self.last_refresh == Some(refresh) {
+            0
+        }
+        else {
+            TOKEN_MAX_AGE.div_ceil(self.fade_refreshes.max(1))
+        };
+        self.last_refresh = Some(refresh);
+        self.tlv.set_contents_aged(mem, false, age_step);
+    }
+
+    /// Set the (reads, writes) access counts for each 4KB page, displayed by the heat map.
+    pub fn set_page_stats(&mut self, page_stats: &[(u32, u32)]) {
+        self.page_stats.clear();
+        self.page_stats.extend_from_slice(page_stats);
+    }
+
+    /// Set which bytes of the viewport, starting at `address`, have been executed. Executed bytes
+    /// are tinted.
+    pub fn set_executed(&mut self, address: usize, executed: Vec<bool>) {
+        self.tlv.set_tinted_bytes(address, executed);
+    }
+
+    /// Set which bytes of the viewport, starting at `address`, are ROM. ROM bytes are drawn in a
+    /// different color.
+    pub fn set_rom(&mut self, address: usize, rom: Vec<bool>) {
+        self.tlv.set_rom_bytes(address, rom);
+    }
+
+    /// Set the number of memory edits and fills that can be undone.
+    pub fn set_undo_len(&mut self, len: usize) {
+        self.undo_len = len;
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo_len
+    }
+
+    /// Parse a string of hex byte values, optionally separated by whitespace or commas.
+    fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+        let digits: String = text.chars().filter(|c| !c.is_whitespace() && *c != ',').collect();
+        if digits.is_empty() || digits.len() % 2 != 0 {
+            return None;
+        }
+        digits
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+            .collect()
     }
 
     pub fn set_hover_text(&mut self, text: String) {
         self.tlv.set_hover_text(text);
     }
+
+    fn words_label(words: Option<WordOrder>) -> &'static str {
+        match words {
+            None => "Bytes",
+            Some(WordOrder::LittleEndian) => "Words (LE)",
+            Some(WordOrder::BigEndian) => "Words (BE)",
+        }
+    }
+
+    pub fn layout(&self) -> MemoryDumpLayout {
+        self.layout
+    }
+
+    /// Set the row length and formatting of the dump. Unsupported row lengths are ignored.
+    pub fn set_layout(&mut self, layout: MemoryDumpLayout) {
+        if !ROW_LENGTHS.contains(&layout.row_len) {
+            return;
+        }
+        self.layout = layout;
+        if self.row_span != layout.row_len {
+            // Keep the current address in view as rows change size.
+            self.row_span = layout.row_len;
+            self.row &= !(self.row_span - 1);
+            self.tlv.set_scroll_pos(self.row / self.row_span);
+        }
+    }
 }
@@ -34,7 +34,9 @@ pub mod cpu_control;
 pub mod disassembly_viewer;
 // Bring in submodules
 pub mod about;
+pub mod access_profile_viewer;
 pub mod call_stack_viewer;
+pub mod cmos_viewer;
 pub mod cpu_state_viewer;
 pub mod cycle_trace_viewer;
 pub mod data_visualizer;
@@ -48,13 +50,21 @@ pub mod info_viewer;
 pub mod instruction_history_viewer;
 pub mod io_stats_viewer;
 pub mod ivt_viewer;
+pub mod machine_templates;
 pub mod memory_viewer;
+pub mod option_rom_viewer;
 pub mod performance_viewer;
 pub mod pic_viewer;
 pub mod pit_viewer;
+pub mod post_code_viewer;
 pub mod ppi_viewer;
+pub mod prefetch_viewer;
+pub mod rom_info_viewer;
 pub mod scaler_adjust;
+pub mod script_console;
 pub mod serial_viewer;
 pub mod text_mode_viewer;
+pub mod type_text;
 pub mod vhd_creator;
 pub mod videocard_viewer;
+pub mod watch_viewer;
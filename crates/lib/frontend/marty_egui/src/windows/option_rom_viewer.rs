@@ -0,0 +1,94 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    egui::option_rom_viewer.rs
+
+    Implements a viewer for the option ROMs mapped into the machine.
+
+*/
+
+use crate::*;
+use egui_extras::{Column, TableBuilder};
+use marty_core::option_rom::OptionRomStatus;
+
+const ROW_HEIGHT: f32 = 18.0;
+
+pub struct OptionRomViewerControl {
+    roms: Vec<OptionRomStatus>,
+}
+
+impl OptionRomViewerControl {
+    pub fn new() -> Self {
+        Self { roms: Vec::new() }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        if self.roms.is_empty() {
+            ui.label("No option ROMs are configured for this machine.");
+            return;
+        }
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(true)
+            .column(Column::exact(64.0))
+            .column(Column::exact(64.0))
+            .column(Column::exact(48.0))
+            .column(Column::remainder().clip(true))
+            .header(ROW_HEIGHT, |mut header| {
+                for title in ["Segment", "Size", "Init", "Image"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, self.roms.len(), |mut row| {
+                    let rom = &self.roms[row.index()];
+                    row.col(|ui| {
+                        ui.monospace(format!("{:04X}", rom.segment));
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{}K", rom.size / 1024));
+                    });
+                    row.col(|ui| {
+                        ui.label(if rom.init_called { "Yes" } else { "No" })
+                            .on_hover_text("Whether the BIOS has called the ROM's init entry point");
+                    });
+                    row.col(|ui| {
+                        let label = ui.label(&rom.name);
+                        if rom.checksum_fixed {
+                            label.on_hover_text("Checksum was corrected on load");
+                        }
+                    });
+                });
+            });
+    }
+
+    pub fn set_content(&mut self, roms: &[OptionRomStatus]) {
+        self.roms = roms.to_vec();
+    }
+}
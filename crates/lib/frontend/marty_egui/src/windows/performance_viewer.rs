@@ -37,14 +37,20 @@
 use crate::*;
 
 use marty_common::util::format_duration;
+use marty_core::bus::ProfiledDevice;
 use marty_frontend_common::{
-    timestep_manager::{FrameEntry, PerfSnapshot},
+    timestep_manager::{
+        frame_profiler::{speed_ratio, FrameProfile},
+        FrameEntry,
+        PerfSnapshot,
+    },
     types::sound::SoundSourceInfo,
 };
 use marty_videocard_renderer::VideoParams;
+use web_time::Duration;
 
 use egui::CollapsingHeader;
-use egui_plot::{GridMark, Line, Plot, PlotPoints};
+use egui_plot::{Bar, BarChart, GridMark, Legend, Line, Plot, PlotPoints};
 
 pub struct PerformanceViewerControl {
     dti: Vec<DisplayTargetInfo>,
@@ -52,6 +58,9 @@ pub struct PerformanceViewerControl {
     perf: PerfSnapshot,
     video_data: VideoParams,
     frame_history: Vec<FrameEntry>,
+    frame_profile: Vec<FrameProfile>,
+    // Set once the frontend feeds us a frame profile. Frontends without a profiler never do.
+    frame_profile_enabled: bool,
 }
 
 // struct DisplayOption<T>(Option<T>);
@@ -90,6 +99,8 @@ impl PerformanceViewerControl {
             perf: Default::default(),
             video_data: Default::default(),
             frame_history: Vec::new(),
+            frame_profile: Vec::new(),
+            frame_profile_enabled: false,
         }
     }
 
@@ -133,6 +144,21 @@ impl PerformanceViewerControl {
                                 ui.label("Sample Count: ");
                                 ui.label(egui::RichText::new(format!("{}", ss.sample_ct)));
                                 ui.end_row();
+                                ui.label("Latency: ");
+                                ui.label(egui::RichText::new(format!(
+                                    "{:.1}ms / {:.0}ms",
+                                    ss.latency_ms, ss.target_latency_ms
+                                )));
+                                ui.end_row();
+                                ui.label("Rate Adjust: ");
+                                ui.label(egui::RichText::new(format!("{:+.3}%", ss.rate_adjust * 100.0)));
+                                ui.end_row();
+                                ui.label("Underruns: ");
+                                ui.label(egui::RichText::new(format!("{}", ss.underruns)));
+                                ui.end_row();
+                                ui.label("Overruns: ");
+                                ui.label(egui::RichText::new(format!("{}", ss.overruns)));
+                                ui.end_row();
                             })
                         });
                     ui.end_row();
@@ -224,6 +250,99 @@ impl PerformanceViewerControl {
                     plot_ui.line(line);
                 });
         });
+
+        if self.frame_profile_enabled {
+            CollapsingHeader::new("Frame Profile")
+                .default_open(false)
+                .show(ui, |ui| {
+                    self.draw_frame_profile(ui);
+                });
+        }
+    }
+
+    /// Draw a stacked graph of host time spent per phase for each profiled frame, along with the average
+    /// time spent in each phase and device group over the profile window.
+    fn draw_frame_profile(&self, ui: &mut egui::Ui) {
+        if self.frame_profile.is_empty() {
+            ui.label("Collecting profile data...");
+            return;
+        }
+
+        let frame_ct = self.frame_profile.len() as u32;
+        let avg = |f: &dyn Fn(&FrameProfile) -> Duration| -> Duration {
+            self.frame_profile.iter().map(|fp| f(fp)).sum::<Duration>() / frame_ct
+        };
+
+        // Phases from the bottom of the stack to the top.
+        let phases: [(&str, &dyn Fn(&FrameProfile) -> Duration); 5] = [
+            ("CPU", &|fp| fp.cpu()),
+            ("Devices", &|fp| fp.devices.total()),
+            ("Composite", &|fp| fp.composite),
+            ("Render", &|fp| fp.render),
+            ("GUI", &|fp| fp.gui),
+        ];
+
+        egui::Grid::new("frame_profile").striped(true).show(ui, |ui| {
+            ui.label("Emulation speed: ");
+            ui.label(egui::RichText::new(format!(
+                "{:.1}%",
+                speed_ratio(&self.frame_profile) * 100.0
+            )));
+            ui.end_row();
+
+            for (name, phase) in phases.iter() {
+                ui.label(format!("{}: ", name));
+                ui.label(egui::RichText::new(format_duration(avg(*phase))));
+                ui.end_row();
+            }
+            ui.label("Total: ");
+            ui.label(egui::RichText::new(format_duration(avg(&|fp| fp.total()))));
+            ui.end_row();
+        });
+
+        CollapsingHeader::new("Device Tick Time")
+            .default_open(false)
+            .show(ui, |ui| {
+                egui::Grid::new("frame_profile_devices").striped(true).show(ui, |ui| {
+                    for device in ProfiledDevice::ALL {
+                        ui.label(format!("{}: ", device));
+                        ui.label(egui::RichText::new(format_duration(avg(&|fp| fp.devices.get(device)))));
+                        ui.end_row();
+                    }
+                });
+            });
+
+        let mut charts: Vec<BarChart> = Vec::with_capacity(phases.len());
+        for (name, phase) in phases.iter() {
+            let bars = self
+                .frame_profile
+                .iter()
+                .enumerate()
+                .map(|(i, fp)| Bar::new(i as f64, phase(fp).as_secs_f64() * 1000.0).width(1.0))
+                .collect();
+            let below: Vec<&BarChart> = charts.iter().collect();
+            let chart = BarChart::new(bars).name(name).stack_on(&below);
+            charts.push(chart);
+        }
+
+        Plot::new("frame_profile_plot")
+            .height(128.0)
+            .allow_scroll(false)
+            .allow_drag(false)
+            .allow_zoom(false)
+            .legend(Legend::default())
+            .y_axis_label("ms")
+            .show(ui, |plot_ui| {
+                for chart in charts {
+                    plot_ui.bar_chart(chart);
+                }
+            });
+    }
+
+    /// Update the frame profile history. The profile is a snapshot copied from the frontend's profiler.
+    pub fn update_frame_profile(&mut self, frame_profile: Vec<FrameProfile>) {
+        self.frame_profile = frame_profile;
+        self.frame_profile_enabled = true;
     }
 
     pub fn update_video_data(&mut self, video_data: &VideoParams) {
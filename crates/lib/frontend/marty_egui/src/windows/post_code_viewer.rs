@@ -0,0 +1,122 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    egui::post_code_viewer.rs
+
+    Implements a viewer for BIOS POST codes written to the diagnostic port.
+
+*/
+
+use crate::*;
+use egui_extras::{Column, TableBuilder};
+use marty_core::devices::post_code::POST_CODE_TABLES;
+
+const ROW_HEIGHT: f32 = 18.0;
+
+pub struct PostCodeViewerControl {
+    log: Vec<(u64, u8)>,
+    table_idx: usize,
+}
+
+impl PostCodeViewerControl {
+    pub fn new() -> Self {
+        Self {
+            log: Vec::new(),
+            table_idx: 0,
+        }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
+        ui.horizontal(|ui| {
+            ui.label("BIOS:");
+            egui::ComboBox::from_id_salt("post-code-table")
+                .selected_text(POST_CODE_TABLES[self.table_idx].name)
+                .show_ui(ui, |ui| {
+                    for (i, table) in POST_CODE_TABLES.iter().enumerate() {
+                        ui.selectable_value(&mut self.table_idx, i, table.name);
+                    }
+                });
+            if ui.button("Clear").on_hover_text("Clear the POST code log").clicked() {
+                events.send(GuiEvent::ClearPostCodes);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Last code:");
+            match self.log.last() {
+                Some((_, code)) => ui.monospace(egui::RichText::new(format!("{:02X}h", code)).strong()),
+                None => ui.monospace("--"),
+            };
+        });
+        ui.separator();
+
+        let table = &POST_CODE_TABLES[self.table_idx];
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(true)
+            .stick_to_bottom(true)
+            .max_scroll_height(400.0)
+            .column(Column::exact(120.0))
+            .column(Column::exact(48.0))
+            .column(Column::remainder().clip(true))
+            .header(ROW_HEIGHT, |mut header| {
+                for title in ["Cycle", "Code", "Meaning"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, self.log.len(), |mut row| {
+                    let (cycle, code) = self.log[row.index()];
+                    row.col(|ui| {
+                        ui.monospace(format!("{}", cycle));
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{:02X}h", code));
+                    });
+                    row.col(|ui| {
+                        ui.label(table.lookup(code).unwrap_or("Unknown"));
+                    });
+                });
+            });
+    }
+
+    pub fn set_content(&mut self, log: Vec<(u64, u8)>) {
+        self.log = log;
+    }
+
+    /// Select the POST code table, usually the one specified by the machine profile.
+    pub fn set_table(&mut self, table_idx: usize) {
+        if table_idx < POST_CODE_TABLES.len() {
+            self.table_idx = table_idx;
+        }
+    }
+
+    pub fn table(&self) -> usize {
+        self.table_idx
+    }
+}
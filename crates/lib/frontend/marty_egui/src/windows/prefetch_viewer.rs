@@ -0,0 +1,105 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    egui::prefetch_viewer.rs
+
+    Implements a viewer for the contents of the CPU's instruction prefetch
+    queue and the state of the BIU's fetch logic.
+
+*/
+
+use crate::*;
+use marty_core::cpu_common::PrefetchState;
+
+pub struct PrefetchViewerControl {
+    state: PrefetchState,
+}
+
+impl PrefetchViewerControl {
+    pub fn new() -> Self {
+        Self {
+            state: Default::default(),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        let state = &self.state;
+
+        egui::Grid::new("prefetch_view")
+            .num_columns(2)
+            .spacing([40.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Queue:");
+                ui.horizontal(|ui| {
+                    for slot in 0..state.queue_size {
+                        match state.queue.get(slot) {
+                            Some(byte) => ui.monospace(format!("{:02X}", byte)),
+                            None => ui.weak(egui::RichText::new("--").monospace()),
+                        };
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Length:");
+                ui.monospace(format!("{} / {}", state.queue.len(), state.queue_size));
+                ui.end_row();
+
+                ui.label("Preload:");
+                match state.preload {
+                    Some(byte) => ui.monospace(format!("{:02X}", byte)),
+                    None => ui.weak("None"),
+                }
+                .on_hover_text("A byte taken from the queue and latched for decode of the next instruction");
+                ui.end_row();
+
+                ui.label("Fetch state:");
+                ui.monospace(state.fetch_state.to_string());
+                ui.end_row();
+
+                ui.label("Queue op:");
+                ui.monospace(state.queue_op.to_string());
+                ui.end_row();
+
+                ui.label("CS:PC");
+                ui.monospace(format!("{:04X}:{:04X}", state.cs, state.pc));
+                ui.end_row();
+
+                ui.label("CS:IP");
+                ui.monospace(format!("{:04X}:{:04X}", state.cs, state.ip));
+                ui.end_row();
+
+                ui.label("PC - IP:");
+                ui.monospace(format!("{}", state.pc_delta()))
+                    .on_hover_text("The number of bytes fetched ahead of the current instruction");
+                ui.end_row();
+            });
+    }
+
+    pub fn set_state(&mut self, state: PrefetchState) {
+        self.state = state;
+    }
+}
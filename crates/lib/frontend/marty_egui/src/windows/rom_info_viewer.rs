@@ -0,0 +1,115 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    egui::rom_info_viewer.rs
+
+    Implements a viewer listing the checksums and identification of each ROM
+    loaded by the machine.
+
+*/
+
+use crate::*;
+use egui_extras::{Column, TableBuilder};
+use marty_core::rom_info::RomInfo;
+
+const ROW_HEIGHT: f32 = 18.0;
+
+pub struct RomInfoViewerControl {
+    roms: Vec<RomInfo>,
+}
+
+impl RomInfoViewerControl {
+    pub fn new() -> Self {
+        Self { roms: Vec::new() }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        if self.roms.is_empty() {
+            ui.label("No ROMs are loaded.");
+            return;
+        }
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .auto_shrink(true)
+            .column(Column::exact(20.0))
+            .column(Column::exact(96.0))
+            .column(Column::exact(48.0))
+            .column(Column::exact(72.0))
+            .column(Column::exact(232.0))
+            .column(Column::remainder().clip(true))
+            .header(ROW_HEIGHT, |mut header| {
+                for title in ["", "Address", "Size", "CRC32", "MD5", "Identification"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, self.roms.len(), |mut row| {
+                    let rom = &self.roms[row.index()];
+                    row.col(|ui| {
+                        if rom.verification.is_warning() {
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                                .on_hover_text(rom.verification.to_string());
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{:05X}-{:05X}", rom.address, rom.end_address()));
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{}K", rom.size / 1024));
+                    });
+                    row.col(|ui| {
+                        ui.monospace(format!("{:08X}", rom.crc32));
+                    });
+                    row.col(|ui| {
+                        ui.monospace(&rom.md5);
+                    });
+                    row.col(|ui| {
+                        let identity = rom.identity.as_deref().unwrap_or("Unknown");
+                        ui.label(identity).on_hover_ui(|ui| {
+                            ui.label(&rom.name);
+                            if let Some(path) = &rom.path {
+                                ui.label(format!("Path: {}", path.display()));
+                            }
+                            if let Some(date) = &rom.date {
+                                ui.label(format!("Date: {}", date));
+                            }
+                            if let Some(copyright) = &rom.copyright {
+                                ui.label(copyright);
+                            }
+                            ui.label(rom.verification.to_string());
+                        });
+                    });
+                });
+            });
+    }
+
+    pub fn set_content(&mut self, roms: &[RomInfo]) {
+        self.roms = roms.to_vec();
+    }
+}
@@ -144,6 +144,30 @@ impl ScalerAdjustControl {
                 }
                 ui.end_row();
 
+                ui.label(egui::RichText::new("Scanline Intensity:").text_style(egui::TextStyle::Monospace));
+                if ui
+                    .add(egui::Slider::new(
+                        &mut self.params[self.dt_idx].crt_scanline_intensity,
+                        0.0..=1.0,
+                    ))
+                    .changed()
+                {
+                    update = true;
+                }
+                ui.end_row();
+
+                ui.label(egui::RichText::new("Persistence:").text_style(egui::TextStyle::Monospace));
+                if ui
+                    .add(egui::Slider::new(
+                        &mut self.params[self.dt_idx].crt_persistence,
+                        0.0..=0.99,
+                    ))
+                    .changed()
+                {
+                    update = true;
+                }
+                ui.end_row();
+
                 ui.label(egui::RichText::new("Barrel Distortion:").text_style(egui::TextStyle::Monospace));
                 if ui
                     .add(egui::Slider::new(
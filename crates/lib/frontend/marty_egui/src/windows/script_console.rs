@@ -0,0 +1,95 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    -------------------------------------------------------------------------
+
+    egui::script_console
+
+    Implements a simple editor and output log for running automation scripts.
+
+*/
+
+use crate::*;
+
+pub const SCRIPT_CONSOLE_MAX_LINES: usize = 1000;
+
+pub struct ScriptConsole {
+    source: String,
+    output: VecDeque<String>,
+}
+
+impl ScriptConsole {
+    pub fn new() -> Self {
+        Self {
+            source: String::new(),
+            output: VecDeque::new(),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
+        egui::ScrollArea::vertical()
+            .id_salt("script_console_source")
+            .max_height(300.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.source)
+                        .code_editor()
+                        .desired_rows(16)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.source.is_empty(), egui::Button::new("▶ Run"))
+                .clicked()
+            {
+                events.send(GuiEvent::RunScript(self.source.clone()));
+            }
+            if ui.button("Clear Output").clicked() {
+                self.output.clear();
+            }
+        });
+
+        ui.separator();
+
+        egui::ScrollArea::vertical()
+            .id_salt("script_console_output")
+            .max_height(150.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in self.output.iter() {
+                    ui.label(egui::RichText::new(line).monospace());
+                }
+            });
+    }
+
+    /// Append a line to the console output.
+    pub fn push_output(&mut self, line: &str) {
+        if self.output.len() >= SCRIPT_CONSOLE_MAX_LINES {
+            self.output.pop_front();
+        }
+        self.output.push_back(line.to_string());
+    }
+}
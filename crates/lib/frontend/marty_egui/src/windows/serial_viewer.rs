@@ -38,7 +38,10 @@ use egui::*;
 use crate::{color::*, constants::*, *};
 
 use marty_core::{
-    devices::{pit::PitDisplayState, serial::SerialPortDisplayState},
+    devices::{
+        pit::PitDisplayState,
+        serial::{SerialPortDisplayState, SERIAL_CAPTURE_STATUS_KEY},
+    },
     syntax_token::*,
 };
 
@@ -54,11 +57,34 @@ impl SerialViewerControl {
         }
     }
 
-    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+    pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
         for (i, serialport) in self.serial_state.iter().enumerate() {
             egui::CollapsingHeader::new(format!("Port: {}", i))
                 .default_open(true)
                 .show(ui, |ui| {
+                    let capture_status = match serialport.get(SERIAL_CAPTURE_STATUS_KEY) {
+                        Some(SyntaxToken::StateString(text, _, _)) => text.as_str(),
+                        _ => "Idle",
+                    };
+                    ui.horizontal(|ui| {
+                        let indicator_color = match capture_status {
+                            "Recording" => Color32::RED,
+                            "Playback" => Color32::GREEN,
+                            _ => Color32::DARK_GRAY,
+                        };
+                        ui.label(egui::RichText::new("●").color(indicator_color));
+                        if capture_status == "Idle" {
+                            if ui.button("Record").clicked() {
+                                events.send(GuiEvent::StartSerialRecording(i));
+                            }
+                            if ui.button("Play Latest").clicked() {
+                                events.send(GuiEvent::PlaySerialRecording(i));
+                            }
+                        }
+                        else if ui.button("Stop").clicked() {
+                            events.send(GuiEvent::StopSerialCapture(i));
+                        }
+                    });
                     ui.horizontal(|ui| {
                         ui.set_min_width(PIT_VIEWER_WIDTH);
                         ui.group(|ui| {
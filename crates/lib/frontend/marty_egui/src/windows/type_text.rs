@@ -0,0 +1,66 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    -------------------------------------------------------------------------
+
+    egui::type_text
+
+    Implements a dialog for typing text into the emulated machine by
+    injecting keyboard scancodes.
+
+*/
+
+use crate::*;
+
+pub struct TypeTextDialog {
+    text: String,
+}
+
+impl TypeTextDialog {
+    pub fn new() -> Self {
+        Self { text: String::new() }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, events: &mut GuiEventQueue) {
+        ui.label("Text to type into the machine. Newlines are entered as the Enter key.");
+        ui.add(
+            egui::TextEdit::multiline(&mut self.text)
+                .font(egui::TextStyle::Monospace)
+                .desired_rows(6)
+                .desired_width(f32::INFINITY),
+        );
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.text.is_empty(), egui::Button::new("⌨ Type"))
+                .clicked()
+            {
+                events.send(GuiEvent::TypeText(self.text.clone()));
+            }
+            if ui.button("Clear").clicked() {
+                self.text.clear();
+            }
+        });
+    }
+}
@@ -35,6 +35,171 @@ use egui::CollapsingHeader;
 use crate::{state::GuiState, widgets::color_swatch::color_swatch};
 use marty_core::device_traits::videocard::{VideoCardState, VideoCardStateEntry};
 
+/// Number of updates a changed register stays highlighted, so that a change is visible for longer
+/// than a single frame.
+const CHANGE_HIGHLIGHT_UPDATES: u32 = 30;
+const CHANGE_COLOR: egui::Color32 = egui::Color32::from_rgb(0xFF, 0xD0, 0x40);
+
+struct RegisterEntry {
+    name:  String,
+    value: u8,
+    // Number of updates since this register's value last changed.
+    age:   u32,
+}
+
+/// A table of the raw register values of the primary video card.
+#[derive(Default)]
+pub struct VideoCardRegisterTable {
+    registers: Vec<RegisterEntry>,
+    selected:  Option<String>,
+}
+
+impl VideoCardRegisterTable {
+    pub fn update(&mut self, registers: Vec<(String, u8)>) {
+        let previous = std::mem::take(&mut self.registers);
+        self.registers = registers
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, value))| {
+                let age = match previous.get(i) {
+                    Some(prev) if prev.name == name && prev.value == value => prev.age.saturating_add(1),
+                    Some(prev) if prev.name == name => 0,
+                    // A different card or register layout; nothing to compare against.
+                    _ => CHANGE_HIGHLIGHT_UPDATES,
+                };
+                RegisterEntry { name, value, age }
+            })
+            .collect();
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui) {
+        if self.registers.is_empty() {
+            return;
+        }
+
+        CollapsingHeader::new("Register File")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("videocard_register_table")
+                    .num_columns(3)
+                    .striped(true)
+                    .min_col_width(40.0)
+                    .show(ui, |ui| {
+                        for reg in &self.registers {
+                            let changed = reg.age < CHANGE_HIGHLIGHT_UPDATES;
+                            let mut name = egui::RichText::new(&reg.name).monospace();
+                            let mut hex = egui::RichText::new(format!("{:02X}", reg.value)).monospace();
+                            let mut bin = egui::RichText::new(format!("{:08b}", reg.value)).monospace();
+                            if changed {
+                                name = name.color(CHANGE_COLOR);
+                                hex = hex.color(CHANGE_COLOR);
+                                bin = bin.color(CHANGE_COLOR);
+                            }
+
+                            let response = ui.add(egui::Label::new(name).sense(egui::Sense::click()));
+                            if response.clicked() {
+                                self.selected = match &self.selected {
+                                    Some(selected) if *selected == reg.name => None,
+                                    _ => Some(reg.name.clone()),
+                                };
+                            }
+                            if self.selected.as_ref() == Some(&reg.name) {
+                                response.show_tooltip_text(register_purpose(&reg.name));
+                            }
+                            ui.label(hex);
+                            ui.label(bin);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+/// Describe the purpose of a register, given its name as returned by `VideoCard::get_register_state`.
+fn register_purpose(name: &str) -> &'static str {
+    match name {
+        "R0 Horizontal Total" | "R00 Horizontal Total" => {
+            "Total number of character clocks per scanline, minus one. Determines the horizontal scan rate."
+        }
+        "R1 Horizontal Displayed" | "R01 Horizontal Display End" => "Number of characters displayed per row.",
+        "R02 Start Horizontal Blank" => "Character position at which horizontal blanking begins.",
+        "R03 End Horizontal Blank" => "Character position at which horizontal blanking ends, and display enable skew.",
+        "R2 H. Sync Position" | "R04 Start Horizontal Retrace" => {
+            "Character position at which horizontal sync begins. Adjusts the horizontal position of the display."
+        }
+        "R3 Sync Width" | "R05 End Horizontal Retrace" => {
+            "Width of the horizontal (and on some CRTCs, vertical) sync pulse."
+        }
+        "R4 Vertical Total" | "R06 Vertical Total" => {
+            "Total number of character rows (or scanlines on EGA/VGA) per frame, minus one."
+        }
+        "R5 V. Total Adjust" => "Additional scanlines added to the end of the frame for fine vertical timing.",
+        "R6 Vertical Displayed" | "R12 Vertical Display End" => {
+            "Number of character rows (or scanlines on EGA/VGA) displayed."
+        }
+        "R7 V. Sync Position" | "R10 Vertical Retrace Start" => {
+            "Row (or scanline on EGA/VGA) at which vertical sync begins. Adjusts the vertical position of the display."
+        }
+        "R07 Overflow" => "High bits of the vertical timing registers that do not fit in 8 bits.",
+        "R8 Interlace Mode" => "Selects interlaced or non-interlaced scanning.",
+        "R08 Preset Row Scan" => {
+            "Starting scanline within the first character row, used for smooth vertical scrolling."
+        }
+        "R9 Max Scan Line Address" | "R09 Max Scan Line" => "Number of scanlines per character row, minus one.",
+        "R10 Cursor Start" | "R0A Cursor Start" => {
+            "First scanline of the cursor within a character row. Bits 5-6 control blinking or disable the cursor."
+        }
+        "R11 Cursor End" | "R0B Cursor End" => "Last scanline of the cursor within a character row.",
+        "R12 Start Address (H)" | "R0C Start Address High" => {
+            "High byte of the video memory address displayed at the top left of the screen."
+        }
+        "R13 Start Address (L)" | "R0D Start Address Low" => {
+            "Low byte of the video memory address displayed at the top left of the screen."
+        }
+        "R14 Cursor (H)" | "R0E Cursor Location High" => "High byte of the video memory address of the cursor.",
+        "R15 Cursor (L)" | "R0F Cursor Location Low" => "Low byte of the video memory address of the cursor.",
+        "R11 Vertical Retrace End" => "Scanline at which vertical sync ends, and vertical interrupt control.",
+        "R13 Offset" => "Logical width of a row in video memory, in words or doublewords.",
+        "R14 Underline Location" => "Scanline within a character row on which the underline attribute is drawn.",
+        "R15 Start Vertical Blank" => "Scanline at which vertical blanking begins.",
+        "R16 End Vertical Blank" => "Scanline at which vertical blanking ends.",
+        "R17 Mode Control" => "Selects byte or word addressing, address wrap and CGA/Hercules compatibility modes.",
+        "R18 Line Compare" => "Scanline at which the display address is reset to zero, used for split screens.",
+        "Mode Select (3D8)" | "Mode Control (3B8)" => {
+            "Selects text or graphics mode, resolution, color burst, video enable and blinking."
+        }
+        "Color Select (3D9)" => "Selects the border or background color and the graphics mode palette.",
+        "Page Register (3DF)" => "Selects the pages of system memory used by the CPU and the CRT controller.",
+        "Palette Mask" => "Mask applied to color values before palette lookup.",
+        "Border Color" => "Color displayed in the overscan area.",
+        "Misc Output (3C2)" => "Selects the I/O address range, dot clock, memory page and sync polarities.",
+        "SR00 Reset" => {
+            "Asynchronous and synchronous reset of the sequencer. Video memory is not refreshed while reset."
+        }
+        "SR01 Clocking Mode" => "Selects 8 or 9 dot characters, the shift load rate and half-rate dot clock.",
+        "SR02 Map Mask" => "Enables CPU writes to each of the four memory planes.",
+        "SR03 Character Map Select" => "Selects the font tables in plane 2 used for text mode characters.",
+        "SR04 Memory Mode" => "Selects alphanumeric mode, extended memory, and odd/even or chained addressing.",
+        "GR00 Set/Reset" => "Value written to each plane in write mode 0 when enabled by Enable Set/Reset.",
+        "GR01 Enable Set/Reset" => "Selects the planes that are written from Set/Reset rather than CPU data.",
+        "GR02 Color Compare" => "Color compared against each pixel in read mode 1.",
+        "GR03 Data Rotate" => "Rotation count and logical function applied to CPU data before it is written.",
+        "GR04 Read Map Select" => "Selects the plane read by the CPU in read mode 0.",
+        "GR05 Mode" => "Selects the write mode, read mode, odd/even addressing and shift register mode.",
+        "GR06 Miscellaneous" => "Selects graphics or text mode, chained odd/even addressing and the memory map.",
+        "GR07 Color Don't Care" => "Selects the planes included in the read mode 1 color compare.",
+        "GR08 Bit Mask" => "Selects the bits of each plane that can be changed by a CPU write.",
+        "AR10 Mode Control" => "Selects graphics or text mode, monochrome emulation, line graphics and blink.",
+        "AR11 Overscan Color" => "Color displayed in the border area.",
+        "AR12 Color Plane Enable" => "Enables each plane's input to the palette, and selects the diagnostic video mux.",
+        "AR13 Horizontal Pel Panning" => {
+            "Number of pixels the display is shifted left, used for smooth horizontal scrolling."
+        }
+        _ if name.starts_with("Palette ") || name.starts_with("AR0") => "Maps a color value to a displayed color.",
+        _ => "No description available.",
+    }
+}
+
 // rustfmt just has no idea how to handle this
 #[rustfmt::skip]
 impl GuiState {
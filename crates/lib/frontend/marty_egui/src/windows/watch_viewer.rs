@@ -0,0 +1,140 @@
+/*
+     MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    ---------------------------------------------------------------------------
+
+    egui::watch_viewer.rs
+
+    Implements a window that displays the values of a list of watch
+    expressions, such as 'ds:si' or 'word [0040:006C]'. The expressions are
+    re-evaluated by the frontend as the machine state changes.
+
+*/
+
+use crate::*;
+use marty_core::cpu_common::{CpuAddress, EvalError, EvalResult, ValueSize};
+
+struct Watch {
+    expr:   String,
+    result: Result<EvalResult, String>,
+}
+
+pub struct WatchViewerControl {
+    watches:  Vec<Watch>,
+    new_expr: String,
+}
+
+impl WatchViewerControl {
+    pub fn new() -> Self {
+        Self {
+            watches:  Vec::new(),
+            new_expr: String::new(),
+        }
+    }
+
+    pub fn draw(&mut self, ui: &mut egui::Ui, _events: &mut GuiEventQueue) {
+        ui.horizontal(|ui| {
+            ui.label("Expression:");
+            let response = ui.text_edit_singleline(&mut self.new_expr);
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Add").clicked() || entered) && !self.new_expr.trim().is_empty() {
+                self.add_watch(self.new_expr.trim().to_string());
+                self.new_expr.clear();
+            }
+        });
+        ui.separator();
+
+        let mut remove = None;
+        egui::Grid::new("watch_viewer_grid")
+            .striped(true)
+            .num_columns(4)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new("Expression").weak());
+                ui.label(egui::RichText::new("Address").weak());
+                ui.label(egui::RichText::new("Value").weak());
+                ui.label("");
+                ui.end_row();
+
+                for (i, watch) in self.watches.iter().enumerate() {
+                    ui.monospace(&watch.expr);
+                    match &watch.result {
+                        Ok(result) => {
+                            let address = match result.address {
+                                Some(CpuAddress::Segmented(s, o)) => format!("{:04X}:{:04X}", s, o),
+                                Some(CpuAddress::Flat(a)) => format!("{:05X}", a),
+                                Some(CpuAddress::Offset(o)) => format!("{:04X}", o),
+                                None => String::new(),
+                            };
+                            ui.monospace(address);
+                            let value = match result.size {
+                                ValueSize::Byte => format!("{:02X} ({})", result.value, result.value),
+                                ValueSize::Word => format!("{:04X} ({})", result.value, result.value),
+                            };
+                            ui.monospace(value);
+                        }
+                        Err(err) => {
+                            ui.label("");
+                            ui.colored_label(egui::Color32::LIGHT_RED, "????").on_hover_text(err);
+                        }
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove watch").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(i) = remove {
+            self.watches.remove(i);
+        }
+    }
+
+    pub fn add_watch(&mut self, expr: String) {
+        self.watches.push(Watch {
+            expr,
+            result: Err("Not evaluated".to_string()),
+        });
+    }
+
+    /// Return the watch expressions, in display order.
+    pub fn expressions(&self) -> Vec<String> {
+        self.watches.iter().map(|w| w.expr.clone()).collect()
+    }
+
+    /// Replace the watch list, such as when restoring a workspace.
+    pub fn set_expressions(&mut self, exprs: Vec<String>) {
+        self.watches.clear();
+        for expr in exprs {
+            self.add_watch(expr);
+        }
+    }
+
+    /// Re-evaluate each watch expression with the provided evaluator.
+    pub fn update(&mut self, eval: impl Fn(&str) -> Result<EvalResult, EvalError>) {
+        for watch in &mut self.watches {
+            watch.result = eval(&watch.expr).map_err(|e| e.to_string());
+        }
+    }
+}
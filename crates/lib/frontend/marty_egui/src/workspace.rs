@@ -36,13 +36,28 @@
 
 use std::collections::HashMap;
 
-use crate::{state::GuiState, GuiWindow, WORKSPACE_WINDOWS};
+use crate::{
+    state::{GuiState, WorkspaceWindowState},
+    GuiWindow,
+    WORKSPACE_WINDOWS,
+};
 
 use anyhow::Error;
 use egui::{Context, Ui};
+use marty_core::bus::MemoryDumpLayout;
+use serde::{Deserialize, Serialize};
 
 pub struct GuiWorkspaceConfig {}
 
+/// The serialized form of a workspace.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct WorkspaceState {
+    windows: HashMap<GuiWindow, WorkspaceWindowState>,
+    watches: Vec<String>,
+    memory_layout: MemoryDumpLayout,
+}
+
 impl GuiState {
     pub fn workspace_window_open_button(
         &mut self,
@@ -149,6 +164,9 @@ impl GuiState {
                 GuiWindow::MemoryViewer => {
                     self.memory_viewer.draw(ui, &mut self.event_queue);
                 }
+                GuiWindow::AccessProfileViewer => {
+                    self.access_profile_viewer.draw(ui, &mut self.event_queue);
+                }
                 GuiWindow::CompositeAdjust => {
                     self.composite_adjust.draw(ui, &mut self.event_queue);
                 }
@@ -167,6 +185,18 @@ impl GuiState {
                 GuiWindow::IoStatsViewer => {
                     self.io_stats_viewer.draw(ui, &mut self.event_queue);
                 }
+                GuiWindow::PostCodeViewer => {
+                    self.post_code_viewer.draw(ui, &mut self.event_queue);
+                }
+                GuiWindow::CmosViewer => {
+                    self.cmos_viewer.draw(ui, &mut self.event_queue);
+                }
+                GuiWindow::OptionRomViewer => {
+                    self.option_rom_viewer.draw(ui, &mut self.event_queue);
+                }
+                GuiWindow::RomInfoViewer => {
+                    self.rom_info_viewer.draw(ui, &mut self.event_queue);
+                }
                 GuiWindow::DelayAdjust => {
                     self.delay_adjust.draw(ui, &mut self.event_queue);
                 }
@@ -192,6 +222,7 @@ impl GuiState {
                     self.dma_viewer.draw(ui, &mut self.event_queue);
                 }
                 GuiWindow::VideoCardViewer => {
+                    self.videocard_registers.draw(ui);
                     GuiState::draw_video_card_panel(ui, &self.videocard_state);
                 }
                 GuiWindow::DataVisualizer => {
@@ -200,6 +231,12 @@ impl GuiState {
                 GuiWindow::CallStack => {
                     self.call_stack_viewer.draw(ui, &mut self.event_queue);
                 }
+                GuiWindow::PrefetchViewer => {
+                    self.prefetch_viewer.draw(ui, &mut self.event_queue);
+                }
+                GuiWindow::WatchViewer => {
+                    self.watch_viewer.draw(ui, &mut self.event_queue);
+                }
                 GuiWindow::VHDCreator => {
                     self.vhd_creator.draw(ui, &mut self.event_queue);
                 }
@@ -215,6 +252,15 @@ impl GuiState {
                 GuiWindow::FloppyViewer => {
                     self.floppy_viewer.draw(ui, &mut self.event_queue);
                 }
+                GuiWindow::ScriptConsole => {
+                    self.script_console.draw(ui, &mut self.event_queue);
+                }
+                GuiWindow::TypeText => {
+                    self.type_text.draw(ui, &mut self.event_queue);
+                }
+                GuiWindow::MachineTemplates => {
+                    self.machine_templates.draw(ui, &mut self.event_queue);
+                }
             });
 
             match inner_response_opt {
@@ -230,12 +276,30 @@ impl GuiState {
     }
 
     pub fn get_workspace_config_string(&mut self) -> Result<String, Error> {
-        let window_state: HashMap<_, _> = self.window_state.clone().into_iter().collect();
-        let window_state_toml = toml::to_string_pretty(&window_state).unwrap_or_else(|_| {
+        let workspace = WorkspaceState {
+            windows: self.window_state.clone().into_iter().collect(),
+            watches: self.watch_viewer.expressions(),
+            memory_layout: self.memory_viewer.layout(),
+        };
+        let workspace_toml = toml::to_string_pretty(&workspace).unwrap_or_else(|_| {
             log::error!("Failed to serialize workspace state");
             return String::new();
         });
 
-        Ok(window_state_toml)
+        Ok(workspace_toml)
+    }
+
+    /// Restore a workspace saved by [GuiState::get_workspace_config_string]. Windows that no longer
+    /// exist are ignored.
+    pub fn set_workspace_config_string(&mut self, config: &str) -> Result<(), Error> {
+        let workspace: WorkspaceState = toml::from_str(config)?;
+        for (window, state) in workspace.windows {
+            if let Some(entry) = self.window_state.get_mut(&window) {
+                *entry = state;
+            }
+        }
+        self.watch_viewer.set_expressions(workspace.watches);
+        self.memory_viewer.set_layout(workspace.memory_layout);
+        Ok(())
     }
 }
@@ -34,7 +34,7 @@ use std::sync::Arc;
 
 use marty_egui::{
     state::GuiState,
-    themes::{make_theme, GuiTheme},
+    themes::{make_theme, GuiTheme, SyntaxColors},
 };
 use marty_frontend_common::{display_manager::DmGuiOptions, MartyGuiTheme};
 //use web_time::{Duration, Instant};
@@ -141,6 +141,11 @@ impl GuiRenderContext {
         &self.ctx
     }
 
+    /// Return the syntax highlighting colors of the main theme.
+    pub fn syntax_colors(&self) -> SyntaxColors {
+        self.main_theme.syntax_colors()
+    }
+
     pub fn ctx_mut(&mut self) -> &mut Context {
         &mut self.ctx
     }
@@ -160,6 +165,7 @@ impl GuiRenderContext {
             self.ctx.set_visuals(self.menu_theme.visuals());
             egui::TopBottomPanel::top("martypc_top_panel").show(&self.ctx, |ui| {
                 state.show_menu(ui);
+                state.show_config_changed_bar(ui);
             });
         }
 
@@ -0,0 +1,304 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::audio_resampler.rs
+
+    Resamples a sound source from its native sample rate to the output
+    device's rate, and keeps the amount of buffered audio near a target
+    latency by nudging the resampling ratio.
+*/
+
+//! Emulated sound sources produce samples in bursts, at a rate derived from the emulated clock,
+//! while the output device consumes them at its own steady rate. The two clocks drift apart over
+//! long sessions, so a fixed resampling ratio would eventually run the buffer dry or let it grow
+//! without bound. [AudioResampler] measures how much input is buffered and adjusts the ratio by
+//! at most [MAX_RATIO_ADJUST], which is too small to be heard as a change in pitch.
+//!
+//! When the buffer does run dry, the last output frame is held until the buffer refills to the
+//! target latency, so playback resumes without a click.
+
+use std::collections::VecDeque;
+
+pub const DEFAULT_TARGET_LATENCY_MS: f32 = 50.0;
+pub const MIN_TARGET_LATENCY_MS: f32 = 10.0;
+pub const MAX_TARGET_LATENCY_MS: f32 = 250.0;
+
+/// The largest adjustment made to the resampling ratio, as a fraction of the nominal ratio.
+pub const MAX_RATIO_ADJUST: f64 = 0.005;
+/// Ratio adjustment per unit of fill level error, relative to the target.
+const RATIO_GAIN: f64 = 0.01;
+/// How quickly the ratio moves toward the adjustment for the current fill level, per output block.
+const RATIO_SMOOTHING: f64 = 0.05;
+/// Buffered input beyond this multiple of the target latency is discarded.
+const OVERRUN_FACTOR: f64 = 4.0;
+
+/// Buffer statistics for a sound source.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ResamplerStats {
+    pub latency_ms:  f32,
+    /// The current adjustment to the resampling ratio, as a fraction of the nominal ratio.
+    pub rate_adjust: f32,
+    pub underruns:   u64,
+    pub overruns:    u64,
+}
+
+pub struct AudioResampler {
+    channels: usize,
+    in_rate: u32,
+    out_rate: u32,
+    speed: f64,
+    ratio_adjust: f64,
+    target_latency_ms: f32,
+    // Interleaved input samples. The frame at the front follows `current`.
+    input: VecDeque<f32>,
+    current: Vec<f32>,
+    // Position of the next output frame between `current` (0.0) and the front input frame (1.0).
+    phase: f64,
+    starved: bool,
+    underruns: u64,
+    overruns: u64,
+}
+
+impl AudioResampler {
+    pub fn new(in_rate: u32, out_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            in_rate: in_rate.max(1),
+            out_rate: out_rate.max(1),
+            speed: 1.0,
+            ratio_adjust: 0.0,
+            target_latency_ms: DEFAULT_TARGET_LATENCY_MS,
+            input: VecDeque::new(),
+            current: vec![0.0; channels],
+            phase: 0.0,
+            starved: true,
+            underruns: 0,
+            overruns: 0,
+        }
+    }
+
+    /// Set the emulation speed factor. Sources produce samples faster at higher speeds, so they are
+    /// consumed faster too, raising the pitch.
+    pub fn set_speed(&mut self, speed: f64) {
+        if speed > 0.0 {
+            self.speed = speed;
+        }
+    }
+
+    pub fn target_latency(&self) -> f32 {
+        self.target_latency_ms
+    }
+
+    pub fn set_target_latency(&mut self, latency_ms: f32) {
+        self.target_latency_ms = latency_ms.clamp(MIN_TARGET_LATENCY_MS, MAX_TARGET_LATENCY_MS);
+    }
+
+    pub fn stats(&self) -> ResamplerStats {
+        ResamplerStats {
+            latency_ms:  (self.buffered_frames() as f64 / self.frames_per_ms()) as f32,
+            rate_adjust: self.ratio_adjust as f32,
+            underruns:   self.underruns,
+            overruns:    self.overruns,
+        }
+    }
+
+    /// Queue interleaved input samples at the source's native rate. If more than several times the
+    /// target latency is buffered, the oldest samples are discarded.
+    pub fn push(&mut self, samples: &[f32]) {
+        let len = samples.len() - samples.len() % self.channels;
+        self.input.extend(&samples[..len]);
+
+        let target_frames = self.target_frames();
+        if self.buffered_frames() as f64 > target_frames as f64 * OVERRUN_FACTOR {
+            let excess = self.buffered_frames() - target_frames;
+            self.input.drain(..excess * self.channels);
+            self.overruns += 1;
+        }
+    }
+
+    /// Fill `out` with interleaved samples at the output rate.
+    pub fn fill(&mut self, out: &mut [f32]) {
+        if !self.starved {
+            self.update_ratio();
+        }
+        let step = self.in_rate as f64 * self.speed / self.out_rate as f64 * (1.0 + self.ratio_adjust);
+
+        for frame in out.chunks_mut(self.channels) {
+            if self.starved {
+                // Wait for the buffer to refill, so that we don't immediately run dry again.
+                if self.buffered_frames() < self.target_frames().max(1) {
+                    frame.copy_from_slice(&self.current[..frame.len()]);
+                    continue;
+                }
+                self.starved = false;
+            }
+
+            while self.phase >= 1.0 && self.buffered_frames() > 0 {
+                for sample in self.current.iter_mut() {
+                    *sample = self.input.pop_front().unwrap_or_default();
+                }
+                self.phase -= 1.0;
+            }
+            if self.buffered_frames() == 0 {
+                // Underrun. Hold the last frame to avoid a discontinuity.
+                self.starved = true;
+                self.underruns += 1;
+                frame.copy_from_slice(&self.current[..frame.len()]);
+                continue;
+            }
+
+            for (c, sample) in frame.iter_mut().enumerate() {
+                let next = self.input[c];
+                *sample = self.current[c] + (next - self.current[c]) * self.phase as f32;
+            }
+            self.phase += step;
+        }
+    }
+
+    /// Input frames per millisecond of real time at the current emulation speed.
+    fn frames_per_ms(&self) -> f64 {
+        self.in_rate as f64 * self.speed / 1000.0
+    }
+
+    fn buffered_frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    fn target_frames(&self) -> usize {
+        (self.target_latency_ms as f64 * self.frames_per_ms()) as usize
+    }
+
+    /// Move the ratio adjustment toward one proportional to the fill level error. Consuming input
+    /// slightly faster when the buffer is over its target (and slower when under) keeps emulated
+    /// time and audio time locked together.
+    fn update_ratio(&mut self) {
+        let target = self.target_frames().max(1) as f64;
+        let error = (self.buffered_frames() as f64 - target) / target;
+        let adjust = (error * RATIO_GAIN).clamp(-MAX_RATIO_ADJUST, MAX_RATIO_ADJUST);
+        self.ratio_adjust += (adjust - self.ratio_adjust) * RATIO_SMOOTHING;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const IN_RATE: u32 = 44100;
+    const OUT_RATE: u32 = 48000;
+    const TONE_HZ: f32 = 440.0;
+
+    /// Produces a continuous sine wave in blocks, with a fractional rate multiplier to simulate
+    /// clock drift.
+    struct SineSource {
+        n:    u64,
+        owed: f64,
+    }
+
+    impl SineSource {
+        fn block(&mut self, ms: f64, rate_factor: f64) -> Vec<f32> {
+            self.owed += IN_RATE as f64 * rate_factor * ms / 1000.0;
+            let len = self.owed as usize;
+            self.owed -= len as f64;
+            (0..len)
+                .map(|_| {
+                    self.n += 1;
+                    (TAU * TONE_HZ * self.n as f32 / IN_RATE as f32).sin()
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn output_is_continuous_across_underruns() {
+        let mut resampler = AudioResampler::new(IN_RATE, OUT_RATE, 1);
+        let mut source = SineSource { n: 0, owed: 0.0 };
+        let mut out = vec![0.0; OUT_RATE as usize / 100];
+        let mut output = Vec::new();
+
+        for tick in 0..400 {
+            // Stall the source for 100ms twice, longer than the buffered audio.
+            if !(100..110).contains(&tick) && !(250..260).contains(&tick) {
+                resampler.push(&source.block(10.0, 1.0));
+            }
+            resampler.fill(&mut out);
+            output.extend_from_slice(&out);
+        }
+
+        let stats = resampler.stats();
+        assert_eq!(stats.underruns, 2);
+        assert_eq!(stats.overruns, 0);
+
+        // No step between output samples may exceed the largest step between input samples.
+        let max_input_step = TAU * TONE_HZ / IN_RATE as f32;
+        let max_step = output
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .fold(0.0, f32::max);
+        assert!(max_step <= max_input_step * 1.01, "step of {} in output", max_step);
+
+        // The tone plays again after each underrun.
+        let peak = output[output.len() - 1000..]
+            .iter()
+            .fold(0.0, |peak: f32, s| peak.max(s.abs()));
+        assert!(peak > 0.99);
+    }
+
+    #[test]
+    fn ratio_compensates_for_drift() {
+        let mut resampler = AudioResampler::new(IN_RATE, OUT_RATE, 2);
+        let mut source = SineSource { n: 0, owed: 0.0 };
+        let mut out = vec![0.0; 2 * OUT_RATE as usize / 100];
+
+        // The source runs 0.2% fast for 30 seconds.
+        for _ in 0..3000 {
+            let mono = source.block(10.0, 1.002);
+            let stereo: Vec<f32> = mono.iter().flat_map(|s| [*s, *s]).collect();
+            resampler.push(&stereo);
+            resampler.fill(&mut out);
+        }
+
+        let stats = resampler.stats();
+        assert_eq!(stats.underruns, 0);
+        assert_eq!(stats.overruns, 0);
+        assert!(stats.rate_adjust > 0.0015 && stats.rate_adjust <= MAX_RATIO_ADJUST as f32);
+        assert!(stats.latency_ms < DEFAULT_TARGET_LATENCY_MS * 1.5);
+    }
+
+    #[test]
+    fn overrun_discards_oldest_input() {
+        let mut resampler = AudioResampler::new(IN_RATE, OUT_RATE, 1);
+        let mut source = SineSource { n: 0, owed: 0.0 };
+        for _ in 0..100 {
+            resampler.push(&source.block(10.0, 1.0));
+        }
+
+        let stats = resampler.stats();
+        assert!(stats.overruns > 0);
+        assert!(stats.latency_ms <= DEFAULT_TARGET_LATENCY_MS * OVERRUN_FACTOR as f32 + 1.0);
+    }
+}
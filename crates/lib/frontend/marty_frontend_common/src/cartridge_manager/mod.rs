@@ -50,8 +50,15 @@ pub const JRIPCART_SIG_STR: &str = "PCjr Cartridge image file";
 pub const JRIPCART_CREATOR_LEN: usize = 30;
 pub const JRIPCART_COMMENT_LEN: usize = 400;
 
+/// Raw cartridge dumps carry no load address. Images of 64K or less are mapped at E000, larger images at D000.
+pub const RAW_CART_SEG_SMALL: u16 = 0xE000;
+pub const RAW_CART_SEG_LARGE: u16 = 0xD000;
+pub const RAW_CART_MAX_LEN: usize = 0x20000;
+
 pub enum CartImageType {
+    /// A JRipCart image (.jrc) with a 512 byte header specifying the load address.
     JRipCart,
+    /// A raw ROM dump (.bin) beginning with a ROM module header.
     PCJrCart,
 }
 
@@ -99,7 +106,7 @@ impl CartridgeManager {
             files: Vec::new(),
             image_vec: Vec::new(),
             image_map: HashMap::new(),
-            extensions: vec![OsString::from("jrc")],
+            extensions: vec![OsString::from("jrc"), OsString::from("bin")],
         }
     }
 
@@ -246,21 +253,18 @@ impl CartridgeManager {
             }
         };
 
-        let cart_image_type = scan_cart(&cart_vec);
+        read_cart_image(&cart_vec, &self.image_vec[idx].name)
+    }
+}
 
-        match cart_image_type {
-            Some(CartImageType::JRipCart) => {
-                let cart = read_jripcart_image(&cart_vec)?;
-                Ok(cart)
-            }
-            Some(CartImageType::PCJrCart) => {
-                log::error!("PCJrCart images currently unsupported.");
-                Err(anyhow!(CartridgeError::ImageFormatError))
-            }
-            _ => {
-                log::error!("Unknown cart image format!");
-                Err(anyhow!(CartridgeError::ImageFormatError))
-            }
+/// Read a cartridge image of either supported format. `name` is used as the comment of raw images.
+pub fn read_cart_image(bytes: &[u8], name: &OsString) -> Result<CartImage, Error> {
+    match scan_cart(bytes) {
+        Some(CartImageType::JRipCart) => read_jripcart_image(bytes),
+        Some(CartImageType::PCJrCart) => read_raw_cart_image(bytes, name),
+        _ => {
+            log::error!("Unknown cart image format!");
+            Err(anyhow!(CartridgeError::ImageFormatError))
         }
     }
 }
@@ -271,19 +275,43 @@ pub fn scan_cart(bytes: &[u8]) -> Option<CartImageType> {
     let mut sig = [0u8; JRIPCART_SIG_LEN];
     match buf.read_bytes(&mut sig, JRIPCART_SIG_LEN) {
         Ok(_) => {
-            let sig_str = std::str::from_utf8(&sig[0..(JRIPCART_SIG_LEN - 2)]).unwrap();
-
-            log::debug!("scan_cart(): sig_str: {}", sig_str);
-            if sig_str == JRIPCART_SIG_STR {
+            // Compare bytes, as a raw ROM dump will not be valid UTF-8.
+            if &sig[0..(JRIPCART_SIG_LEN - 2)] == JRIPCART_SIG_STR.as_bytes() {
                 return Some(CartImageType::JRipCart);
             }
         }
-        Err(_e) => return None,
+        Err(_e) => {}
+    }
+
+    // A raw ROM dump starts with a ROM module signature.
+    if bytes.len() > 2 && bytes[0] == 0x55 && bytes[1] == 0xAA {
+        return Some(CartImageType::PCJrCart);
     }
 
     None
 }
 
+pub fn read_raw_cart_image(bytes: &[u8], name: &OsString) -> Result<CartImage, Error> {
+    if bytes.len() > RAW_CART_MAX_LEN {
+        return Err(anyhow!(CartridgeError::ImageReadError));
+    }
+
+    let address_seg = match bytes.len() {
+        0..=0x10000 => RAW_CART_SEG_SMALL,
+        _ => RAW_CART_SEG_LARGE,
+    };
+
+    Ok(CartImage {
+        creator: String::new(),
+        comment: name.to_string_lossy().to_string(),
+        version_major: 0,
+        version_minor: 0,
+        address_seg,
+        address_mask: 0,
+        image: bytes.to_vec(),
+    })
+}
+
 pub fn read_jripcart_image(bytes: &[u8]) -> Result<CartImage, Error> {
     let mut buf = ByteBuf::from_slice(bytes);
 
@@ -0,0 +1,131 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    --------------------------------------------------------------------------
+
+    frontend_common::cmos_manager.rs
+
+    Persist the CMOS RAM of a machine's real time clock to the 'cmos'
+    resource, one file per machine configuration, so that BIOS settings
+    survive restarts.
+
+    The file holds the clock's battery-backed RAM (128 bytes of CMOS for an
+    MC146818, or the RAM latches of an XT clock card), followed by the clock
+    offset set by the guest as a little-endian i64 count of seconds. Files
+    saved without the offset are still accepted.
+*/
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Error};
+use marty_core::{device_traits::realtimeclock::RealTimeClock, devices::rtc::RtcDateTime, machine::Machine};
+
+use crate::resource_manager::ResourceManager;
+
+pub const CMOS_RESOURCE: &str = "cmos";
+pub const CMOS_EXTENSION: &str = "bin";
+const OFFSET_LEN: usize = std::mem::size_of::<i64>();
+
+/// Load saved CMOS RAM and clock offset for the named machine configuration. Returns the path loaded, or
+/// None if the machine has no real time clock or there is no saved CMOS for this configuration yet.
+pub fn load_cmos(machine: &mut Machine, rm: &ResourceManager, config_name: &str) -> Result<Option<PathBuf>, Error> {
+    // The clock offset only applies to a clock that tracks the host's time. A fixed start time is
+    // used for deterministic testing and must not be moved.
+    let host_time = machine
+        .config()
+        .rtc
+        .as_ref()
+        .filter(|rtc| rtc.fixed_time.is_none())
+        .map(|rtc| rtc.start_time());
+    let Some(rtc) = machine.bus_mut().rtc_mut()
+    else {
+        return Ok(None);
+    };
+    let path = cmos_path(rm, config_name)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read(&path).map_err(|e| anyhow!("Couldn't read CMOS file {:?}: {}", path, e))?;
+    let nvram_len = rtc.nvram().len();
+    rtc.load_nvram(&data);
+
+    if let Some(offset) = data
+        .get(nvram_len..nvram_len + OFFSET_LEN)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(i64::from_le_bytes)
+    {
+        rtc.set_clock_offset(offset);
+        if let Some(host_time) = host_time {
+            rtc.set_time(RtcDateTime::from_unix(host_time + offset));
+            log::debug!("Applied saved clock offset of {} seconds", offset);
+        }
+    }
+
+    if !rtc.nvram_valid() {
+        log::warn!(
+            "CMOS file {:?} has an invalid checksum; the BIOS will likely reject it.",
+            path
+        );
+    }
+    log::info!("Loaded CMOS RAM from {:?}", path);
+    Ok(Some(path))
+}
+
+/// Save CMOS RAM and the clock offset for the named machine configuration if either has changed since
+/// it was loaded or last saved. Returns the path written, or None if there was nothing to save.
+pub fn save_cmos(machine: &mut Machine, rm: &ResourceManager, config_name: &str) -> Result<Option<PathBuf>, Error> {
+    let Some(rtc) = machine.bus_mut().rtc_mut()
+    else {
+        return Ok(None);
+    };
+    if !rtc.nvram_dirty() {
+        return Ok(None);
+    }
+    let path = cmos_path(rm, config_name)?;
+    let mut data = rtc.nvram();
+    data.extend_from_slice(&rtc.clock_offset().to_le_bytes());
+    fs::write(&path, data)?;
+    rtc.clear_nvram_dirty();
+    log::debug!("Saved CMOS RAM to {:?}", path);
+    Ok(Some(path))
+}
+
+fn cmos_path(rm: &ResourceManager, config_name: &str) -> Result<PathBuf, Error> {
+    let dir = rm
+        .resource_path(CMOS_RESOURCE)
+        .ok_or_else(|| anyhow!("Resource path not found: {}", CMOS_RESOURCE))?;
+    let stem: String = config_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            }
+            else {
+                '_'
+            }
+        })
+        .collect();
+    Ok(dir.join(format!("{}.{}", stem, CMOS_EXTENSION)))
+}
@@ -38,3 +38,9 @@ pub const DEFAULT_RESOLUTION_H: u32 = 480;
 pub const SHORT_NOTIFICATION_TIME: Duration = Duration::from_secs(1);
 pub const NORMAL_NOTIFICATION_TIME: Duration = Duration::from_secs(3);
 pub const LONG_NOTIFICATION_TIME: Duration = Duration::from_secs(5);
+
+/// Number of quick save slots available from the Machine menu and hotkeys.
+pub const QUICKSAVE_SLOTS: usize = 4;
+
+/// Sample rate used for PC speaker recordings when there is no sound output device.
+pub const SPEAKER_RECORDING_DEFAULT_RATE: u32 = 44100;
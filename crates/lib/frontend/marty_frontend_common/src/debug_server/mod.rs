@@ -0,0 +1,509 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::debug_server::mod.rs
+
+    Implements a debug server that allows MartyPC to be driven externally
+    over a local TCP socket, for automated testing and remote debugging.
+
+    The protocol is line-based text. Each command produces a single reply
+    line beginning with OK or ERR. Addresses may be given as a flat
+    address or as segment:offset. Addresses, register values and data are
+    hexadecimal; counts are decimal.
+
+        regs                       OK AX=0000 BX=0000 ... FLAGS=F002
+        setreg <reg> <value>       OK
+        read <addr> <count>        OK <hex bytes>
+        write <addr> <hex bytes>   OK
+        step [count]               OK <CS:IP>
+        continue                   OK
+        break                      OK
+        bp <addr>                  OK
+        bc <addr>                  OK
+        bl                         OK <addr> <addr> ...
+
+    Breakpoints set with bp are added alongside any set from the GUI, and
+    bc and bl only operate on breakpoints set with bp.
+
+    When the machine stops after running, whether from a breakpoint, a
+    break command, or the GUI, an unsolicited notification is sent:
+
+        STOP <breakpoint|stepover|stepout|pause|halt> <CS:IP>
+
+    The server accepts a single client at a time. The socket is serviced
+    on its own thread, while commands are forwarded over a channel to be
+    executed on the emulator thread by DebugServer::poll().
+*/
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    str::FromStr,
+};
+
+use anyhow::Error;
+use crossbeam_channel::{Receiver, Sender};
+
+use marty_core::{
+    breakpoints::BreakPointType,
+    cpu_common::{Cpu, Register16},
+    machine::{ExecutionControl, ExecutionOperation, ExecutionState, Machine},
+};
+
+pub const DEFAULT_DEBUG_SERVER_PORT: u16 = 8086;
+const MAX_READ_LEN: usize = 0x1000;
+
+/// Registers exposed by the regs and setreg commands, in display order.
+const REGISTERS: &[(&str, Register16)] = &[
+    ("AX", Register16::AX),
+    ("BX", Register16::BX),
+    ("CX", Register16::CX),
+    ("DX", Register16::DX),
+    ("SP", Register16::SP),
+    ("BP", Register16::BP),
+    ("SI", Register16::SI),
+    ("DI", Register16::DI),
+    ("CS", Register16::CS),
+    ("DS", Register16::DS),
+    ("ES", Register16::ES),
+    ("SS", Register16::SS),
+    ("IP", Register16::PC),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugCommand {
+    ReadRegisters,
+    WriteRegister(String, u16),
+    ReadMemory(u32, usize),
+    WriteMemory(u32, Vec<u8>),
+    Step(u32),
+    Continue,
+    Break,
+    SetBreakpoint(u32),
+    ClearBreakpoint(u32),
+    ListBreakpoints,
+}
+
+fn parse_hex(s: &str) -> Result<u32, String> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u32::from_str_radix(digits, 16).map_err(|_| format!("invalid hex value: {}", s))
+}
+
+/// Parse either a flat address or a segment:offset pair into a flat address.
+fn parse_address(s: &str) -> Result<u32, String> {
+    match s.split_once(':') {
+        Some((segment, offset)) => {
+            let segment = parse_hex(segment)? & 0xFFFF;
+            let offset = parse_hex(offset)? & 0xFFFF;
+            Ok(((segment << 4) + offset) & 0xFFFFF)
+        }
+        None => parse_hex(s),
+    }
+}
+
+fn parse_hex_bytes(s: &str) -> Result<Vec<u8>, String> {
+    if s.is_empty() || s.len() % 2 != 0 || !s.is_ascii() {
+        return Err(format!("invalid hex byte string: {}", s));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| format!("invalid hex byte string: {}", s)))
+        .collect()
+}
+
+fn next_arg<'a>(args: &mut impl Iterator<Item = &'a str>, name: &str) -> Result<&'a str, String> {
+    args.next().ok_or_else(|| format!("missing argument: {}", name))
+}
+
+impl FromStr for DebugCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut args = s.split_whitespace();
+        let cmd = args.next().ok_or_else(|| "empty command".to_string())?;
+
+        let command = match cmd.to_ascii_lowercase().as_str() {
+            "regs" => DebugCommand::ReadRegisters,
+            "setreg" => {
+                let reg = next_arg(&mut args, "register")?.to_ascii_uppercase();
+                let value = parse_hex(next_arg(&mut args, "value")?)?;
+                if reg != "FLAGS" && !REGISTERS.iter().any(|(name, _)| *name == reg) {
+                    return Err(format!("invalid register: {}", reg));
+                }
+                DebugCommand::WriteRegister(reg, value as u16)
+            }
+            "read" => {
+                let addr = parse_address(next_arg(&mut args, "address")?)?;
+                let count = next_arg(&mut args, "count")?;
+                let count = count
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid count: {}", count))?;
+                if count > MAX_READ_LEN {
+                    return Err(format!("count exceeds maximum of {}", MAX_READ_LEN));
+                }
+                DebugCommand::ReadMemory(addr, count)
+            }
+            "write" => {
+                let addr = parse_address(next_arg(&mut args, "address")?)?;
+                DebugCommand::WriteMemory(addr, parse_hex_bytes(next_arg(&mut args, "data")?)?)
+            }
+            "step" => {
+                let count = match args.next() {
+                    Some(count) => count.parse::<u32>().map_err(|_| format!("invalid count: {}", count))?,
+                    None => 1,
+                };
+                DebugCommand::Step(count)
+            }
+            "continue" => DebugCommand::Continue,
+            "break" => DebugCommand::Break,
+            "bp" => DebugCommand::SetBreakpoint(parse_address(next_arg(&mut args, "address")?)?),
+            "bc" => DebugCommand::ClearBreakpoint(parse_address(next_arg(&mut args, "address")?)?),
+            "bl" => DebugCommand::ListBreakpoints,
+            _ => return Err(format!("unknown command: {}", cmd)),
+        };
+        Ok(command)
+    }
+}
+
+/// Messages sent from the socket thread to the emulator thread.
+enum ServerMessage {
+    /// A client connected. Reply and notification lines should be sent to the provided Sender.
+    Connected(Sender<String>),
+    Command(DebugCommand),
+    Disconnected,
+}
+
+pub struct DebugServer {
+    local_addr: SocketAddr,
+    rx: Receiver<ServerMessage>,
+    client: Option<Sender<String>>,
+    // Execute breakpoints added by the debug server. Breakpoints set from the GUI are left alone.
+    breakpoints: Vec<u32>,
+    was_running: bool,
+}
+
+impl DebugServer {
+    /// Bind the debug server to the specified address and begin accepting clients on a new thread.
+    pub fn start(addr: impl ToSocketAddrs) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let (tx, rx) = crossbeam_channel::unbounded();
+
+        std::thread::Builder::new()
+            .name("debug_server".to_string())
+            .spawn(move || listen(listener, tx))?;
+
+        log::info!("Debug server listening on {}", local_addr);
+        Ok(Self {
+            local_addr,
+            rx,
+            client: None,
+            breakpoints: Vec::new(),
+            was_running: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Execute any pending client commands against the machine, and notify the client if the machine
+    /// has stopped since the last poll. This should be called from the emulator thread once per frame.
+    pub fn poll(&mut self, machine: &mut Machine, exec_control: &mut ExecutionControl) {
+        while let Ok(message) = self.rx.try_recv() {
+            match message {
+                ServerMessage::Connected(sender) => {
+                    self.client = Some(sender);
+                    self.was_running = matches!(exec_control.get_state(), ExecutionState::Running);
+                }
+                ServerMessage::Command(command) => {
+                    let reply = match self.execute(command, machine, exec_control) {
+                        Ok(reply) if reply.is_empty() => "OK".to_string(),
+                        Ok(reply) => format!("OK {}", reply),
+                        Err(e) => format!("ERR {}", e),
+                    };
+                    self.send(reply);
+                }
+                ServerMessage::Disconnected => {
+                    self.client = None;
+                }
+            }
+        }
+
+        let running = matches!(exec_control.get_state(), ExecutionState::Running);
+        if self.was_running && !running {
+            let reason = match exec_control.get_state() {
+                ExecutionState::BreakpointHit => "breakpoint",
+                ExecutionState::StepOverHit => "stepover",
+                ExecutionState::StepOutHit => "stepout",
+                ExecutionState::Halted => "halt",
+                _ => "pause",
+            };
+            self.send(format!("STOP {} {}", reason, DebugServer::cs_ip(machine)));
+        }
+        self.was_running = running;
+    }
+
+    fn send(&self, line: String) {
+        if let Some(client) = &self.client {
+            _ = client.send(line);
+        }
+    }
+
+    fn cs_ip(machine: &Machine) -> String {
+        format!(
+            "{:04X}:{:04X}",
+            machine.cpu().get_register16(Register16::CS),
+            machine.cpu().get_register16(Register16::PC)
+        )
+    }
+
+    fn execute(
+        &mut self,
+        command: DebugCommand,
+        machine: &mut Machine,
+        exec_control: &mut ExecutionControl,
+    ) -> Result<String, String> {
+        match command {
+            DebugCommand::ReadRegisters => {
+                let mut regs: Vec<String> = REGISTERS
+                    .iter()
+                    .map(|(name, reg)| format!("{}={:04X}", name, machine.cpu().get_register16(*reg)))
+                    .collect();
+                regs.push(format!("FLAGS={:04X}", machine.cpu().get_flags()));
+                Ok(regs.join(" "))
+            }
+            DebugCommand::WriteRegister(name, value) => {
+                match REGISTERS.iter().find(|(reg_name, _)| *reg_name == name) {
+                    Some((_, reg)) => machine.cpu_mut().set_register16(*reg, value),
+                    None => machine.cpu_mut().set_flags(value),
+                }
+                Ok(String::new())
+            }
+            DebugCommand::ReadMemory(addr, count) => {
+                let mut data = String::with_capacity(count * 2);
+                for i in 0..count {
+                    let byte = machine
+                        .bus()
+                        .peek_u8(addr as usize + i)
+                        .map_err(|e| format!("read failed at {:05X}: {}", addr as usize + i, e))?;
+                    data.push_str(&format!("{:02X}", byte));
+                }
+                Ok(data)
+            }
+            DebugCommand::WriteMemory(addr, data) => {
+                for (i, byte) in data.iter().enumerate() {
+                    machine
+                        .bus_mut()
+                        .write_u8(addr as usize + i, *byte, 0)
+                        .map_err(|e| format!("write failed at {:05X}: {}", addr as usize + i, e))?;
+                }
+                Ok(String::new())
+            }
+            DebugCommand::Step(count) => {
+                for _ in 0..count {
+                    if !exec_control.get_state().can_step() {
+                        return Err("machine is running".to_string());
+                    }
+                    exec_control.set_op(ExecutionOperation::Step);
+                    machine.run(1, exec_control);
+                }
+                Ok(DebugServer::cs_ip(machine))
+            }
+            DebugCommand::Continue => {
+                if !exec_control.get_state().can_run() {
+                    return Err("machine cannot be resumed from its current state".to_string());
+                }
+                exec_control.set_op(ExecutionOperation::Run);
+                // The state does not change to Running until the next machine update, so track it here to
+                // ensure a STOP is sent even if the machine stops immediately.
+                self.was_running = true;
+                Ok(String::new())
+            }
+            DebugCommand::Break => {
+                if !exec_control.get_state().can_pause() {
+                    return Err("machine is not running".to_string());
+                }
+                exec_control.set_op(ExecutionOperation::Pause);
+                Ok(String::new())
+            }
+            DebugCommand::SetBreakpoint(addr) => {
+                if !self.breakpoints.contains(&addr) {
+                    let mut bp_list = machine.breakpoints().to_vec();
+                    // A breakpoint already set from the GUI belongs to the GUI, so the server doesn't
+                    // track it and won't remove it.
+                    if !bp_list.contains(&BreakPointType::ExecuteFlat(addr)) {
+                        bp_list.push(BreakPointType::ExecuteFlat(addr));
+                        machine.set_breakpoints(bp_list);
+                        self.breakpoints.push(addr);
+                    }
+                }
+                Ok(String::new())
+            }
+            DebugCommand::ClearBreakpoint(addr) => {
+                if !self.breakpoints.contains(&addr) {
+                    return Err(format!("no breakpoint at {:05X}", addr));
+                }
+                self.breakpoints.retain(|bp| *bp != addr);
+                let mut bp_list = machine.breakpoints().to_vec();
+                bp_list.retain(|bp| *bp != BreakPointType::ExecuteFlat(addr));
+                machine.set_breakpoints(bp_list);
+                Ok(String::new())
+            }
+            DebugCommand::ListBreakpoints => Ok(self
+                .breakpoints
+                .iter()
+                .map(|bp| format!("{:05X}", bp))
+                .collect::<Vec<_>>()
+                .join(" ")),
+        }
+    }
+}
+
+/// Accept clients one at a time until the emulator side of the channel is dropped.
+fn listen(listener: TcpListener, tx: Sender<ServerMessage>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Debug server failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = serve_client(stream, &tx) {
+            log::warn!("Debug client error: {}", e);
+        }
+        if tx.send(ServerMessage::Disconnected).is_err() {
+            return;
+        }
+    }
+}
+
+fn serve_client(stream: TcpStream, tx: &Sender<ServerMessage>) -> Result<(), Error> {
+    log::info!("Debug client connected from {}", stream.peer_addr()?);
+
+    // Replies and notifications are written from a separate thread, so that STOP notifications can be
+    // delivered while this thread is blocked reading commands.
+    let (out_tx, out_rx) = crossbeam_channel::unbounded::<String>();
+    let mut writer = stream.try_clone()?;
+    std::thread::spawn(move || {
+        for line in out_rx.iter() {
+            if writeln!(writer, "{}", line).is_err() {
+                break;
+            }
+        }
+    });
+
+    tx.send(ServerMessage::Connected(out_tx.clone()))?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.parse::<DebugCommand>() {
+            Ok(command) => tx.send(ServerMessage::Command(command))?,
+            Err(e) => {
+                _ = out_tx.send(format!("ERR {}", e));
+            }
+        }
+    }
+
+    log::info!("Debug client disconnected");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!("regs".parse(), Ok(DebugCommand::ReadRegisters));
+        assert_eq!(
+            "setreg ax 1234".parse(),
+            Ok(DebugCommand::WriteRegister("AX".to_string(), 0x1234))
+        );
+        assert_eq!("read F000:FFF0 16".parse(), Ok(DebugCommand::ReadMemory(0xFFFF0, 16)));
+        assert_eq!(
+            "write 0x400 DEADBEEF".parse(),
+            Ok(DebugCommand::WriteMemory(0x400, vec![0xDE, 0xAD, 0xBE, 0xEF]))
+        );
+        assert_eq!("step".parse(), Ok(DebugCommand::Step(1)));
+        assert_eq!("step 10".parse(), Ok(DebugCommand::Step(10)));
+        assert_eq!("bp 0070:0100".parse(), Ok(DebugCommand::SetBreakpoint(0x800)));
+
+        assert!("setreg XX 0".parse::<DebugCommand>().is_err());
+        assert!("write 400 ABC".parse::<DebugCommand>().is_err());
+        assert!("read 0 99999".parse::<DebugCommand>().is_err());
+        assert!("bp".parse::<DebugCommand>().is_err());
+        assert!("frobnicate".parse::<DebugCommand>().is_err());
+    }
+
+    #[test]
+    fn client_round_trip() {
+        let server = DebugServer::start("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr()).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let timeout = Duration::from_secs(5);
+
+        let reply_tx = match server.rx.recv_timeout(timeout).unwrap() {
+            ServerMessage::Connected(sender) => sender,
+            _ => panic!("expected Connected"),
+        };
+
+        // Invalid commands are rejected on the socket thread without reaching the emulator.
+        writeln!(client, "bogus").unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("ERR"));
+
+        writeln!(client, "bp F000:E05B").unwrap();
+        match server.rx.recv_timeout(timeout).unwrap() {
+            ServerMessage::Command(command) => assert_eq!(command, DebugCommand::SetBreakpoint(0xFE05B)),
+            _ => panic!("expected Command"),
+        }
+
+        reply_tx.send("OK".to_string()).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), "OK");
+
+        drop(reader);
+        drop(client);
+        assert!(matches!(
+            server.rx.recv_timeout(timeout).unwrap(),
+            ServerMessage::Disconnected
+        ));
+    }
+}
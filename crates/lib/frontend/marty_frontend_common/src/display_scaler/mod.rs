@@ -84,7 +84,7 @@ pub enum ScalerOption {
     FillColor { r: u8, g: u8, b: u8, a: u8 },
     Mono { enabled: bool, r: f32, g: f32, b: f32, a: f32 },
     Geometry { h_curvature: f32, v_curvature: f32, corner_radius: f32 },
-    Scanlines { enabled: Option<bool>, lines: Option<u32>, intensity: Option<f32> },
+    Scanlines { enabled: Option<bool>, lines: Option<u32>, intensity: Option<f32>, persistence: Option<f32> },
     Effect(ScalerEffect),
 }
 
@@ -107,6 +107,10 @@ pub struct ScalerPreset {
     pub crt_barrel_distortion: f32,
     pub crt_corner_radius: f32,
     pub crt_scanlines: bool,
+    #[serde(default = "_default_scanline_intensity")]
+    pub crt_scanline_intensity: f32,
+    #[serde(default)]
+    pub crt_persistence: f32,
     pub crt_phosphor_type: PhosphorType,
     pub gamma: f32,
     // Options for associated renderer
@@ -123,6 +127,13 @@ pub struct ScalerGeometry {
     pub surface_h: u32,
 }
 
+/// Default darkening of alternate scanlines, from 0.0 (none) to 1.0 (black).
+pub const DEFAULT_SCANLINE_INTENSITY: f32 = 0.3;
+
+fn _default_scanline_intensity() -> f32 {
+    DEFAULT_SCANLINE_INTENSITY
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct ScalerParams {
     pub filter: ScalerFilter,
@@ -130,6 +141,11 @@ pub struct ScalerParams {
     pub crt_barrel_distortion: f32,
     pub crt_corner_radius: f32,
     pub crt_scanlines: bool,
+    /// Darkening applied to alternate scanlines, from 0.0 to 1.0.
+    pub crt_scanline_intensity: f32,
+    /// Fraction of the previous frame blended into each new frame to simulate phosphor persistence.
+    /// 0.0 disables the persistence pass.
+    pub crt_persistence: f32,
     pub crt_phosphor_type: PhosphorType,
     pub gamma: f32,
 }
@@ -141,6 +157,8 @@ impl From<ScalerPreset> for ScalerParams {
             crt_effect: value.crt_effect,
             crt_barrel_distortion: value.crt_barrel_distortion,
             crt_scanlines: value.crt_scanlines,
+            crt_scanline_intensity: value.crt_scanline_intensity,
+            crt_persistence: value.crt_persistence,
             crt_phosphor_type: value.crt_phosphor_type,
             crt_corner_radius: value.crt_corner_radius,
             gamma: value.gamma,
@@ -156,6 +174,8 @@ impl Default for ScalerParams {
             crt_barrel_distortion: 0.0,
             crt_corner_radius: 0.0,
             crt_scanlines: false,
+            crt_scanline_intensity: DEFAULT_SCANLINE_INTENSITY,
+            crt_persistence: 0.0,
             crt_phosphor_type: PhosphorType::Color,
             gamma: 1.0,
         }
@@ -186,6 +206,9 @@ pub trait DisplayScaler<D, Q, T>: Send + Sync {
     type NativeEncoder;
 
     fn texture_view(&self) -> &Self::NativeTextureView;
+    /// Record any passes that must run before the scaling pass, such as phosphor persistence. render() calls
+    /// this itself; callers of render_with_renderpass() must call it beforehand with their own encoder.
+    fn prepare(&self, encoder: &mut Self::NativeEncoder);
     fn render(&self, encoder: &mut Self::NativeEncoder, render_target: &Self::NativeTextureView);
 
     fn render_with_renderpass(&self, render_pass: &mut Self::NativeRenderPass);
@@ -131,9 +131,8 @@ impl Emulator {
             self.config.machine.cpu.service_interrupt.unwrap_or(false),
         ));
 
-        // TODO: Re-enable these
-        //gui.set_option(GuiBoolean::EnableSnow, config.machine.cga_snow.unwrap_or(false));
-        //machine.set_video_option(VideoOption::EnableSnow(config.machine.cga_snow.unwrap_or(false)));
+        // CGA snow is configured per video card, and applied when the card is created.
+        // TODO: Re-enable this
         //gui.set_option(GuiBoolean::CorrectAspect, config.emulator.scaler_aspect_correction);
 
         //if config.emulator.scaler_aspect_correction {
@@ -208,7 +207,14 @@ impl Emulator {
         self.machine
             .set_cpu_option(CpuOption::TraceLoggingEnabled(self.config.machine.cpu.trace_on));
 
+        if let Some(seed) = self.config.emulator.deterministic_seed {
+            log::info!("Deterministic mode enabled with seed: {}", seed);
+            self.machine.set_cpu_option(CpuOption::Deterministic(seed));
+        }
+
         self.gui.set_option(GuiBoolean::TurboButton, self.config.machine.turbo);
+        // The machine may have refused fast boot, so show its state rather than the config's.
+        self.gui.set_option(GuiBoolean::FastBoot, self.machine.get_fast_boot());
 
         self.gui.set_scaler_presets(&self.config.emulator.scaler_preset);
 
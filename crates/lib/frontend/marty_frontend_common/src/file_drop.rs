@@ -0,0 +1,204 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::file_drop.rs
+
+    Identification of files dropped onto the emulator window.
+*/
+
+//! Files dropped onto the emulator window are identified by their extension, then confirmed by
+//! sniffing their header, before being routed to a floppy drive, hard disk or cartridge slot.
+
+use std::{
+    ffi::OsString,
+    fmt::Display,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use fluxfox::DiskImage;
+
+use crate::cartridge_manager::{scan_cart, JRIPCART_SIG_LEN};
+
+const VHD_COOKIE: &[u8; 8] = b"conectix";
+const VHD_FOOTER_LEN: u64 = 512;
+const CART_EXTENSIONS: [&str; 2] = ["jrc", "bin"];
+
+/// The kind of media a dropped file was identified as.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DroppedFileKind {
+    FloppyImage,
+    Vhd,
+    Cartridge,
+}
+
+impl Display for DroppedFileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DroppedFileKind::FloppyImage => write!(f, "Floppy image"),
+            DroppedFileKind::Vhd => write!(f, "VHD"),
+            DroppedFileKind::Cartridge => write!(f, "Cartridge image"),
+        }
+    }
+}
+
+/// Where a dropped file should be mounted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DropTarget {
+    FloppyDrive(usize),
+    HardDisk(usize),
+    CartridgeSlot(usize),
+}
+
+impl Display for DropTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DropTarget::FloppyDrive(idx) => write!(f, "Floppy Drive {}", idx),
+            DropTarget::HardDisk(idx) => write!(f, "Hard Disk {}", idx),
+            DropTarget::CartridgeSlot(idx) => write!(f, "Cartridge Slot {}", idx),
+        }
+    }
+}
+
+/// The contents of a dropped file. Native platforms provide a path, while the browser provides
+/// the file's bytes directly.
+#[derive(Clone, Debug)]
+pub enum DroppedFileSource {
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+/// A file dropped onto the emulator window that has been identified as a mountable image.
+#[derive(Clone, Debug)]
+pub struct DroppedFile {
+    pub name:   OsString,
+    pub kind:   DroppedFileKind,
+    pub source: DroppedFileSource,
+}
+
+impl DroppedFile {
+    /// Identify a dropped file. Returns `Ok(None)` if the file is not of a supported type.
+    pub fn identify(name: impl Into<OsString>, source: DroppedFileSource) -> Result<Option<Self>, std::io::Error> {
+        let name = name.into();
+        let kind = match &source {
+            #[cfg(not(target_arch = "wasm32"))]
+            DroppedFileSource::Path(path) => identify_file(path, &mut std::fs::File::open(path)?),
+            #[cfg(target_arch = "wasm32")]
+            DroppedFileSource::Path(_) => None,
+            DroppedFileSource::Bytes(bytes) => identify_file(Path::new(&name), &mut Cursor::new(bytes)),
+        };
+        Ok(kind.map(|kind| DroppedFile { name, kind, source }))
+    }
+
+    /// The path of the dropped file, if the platform provided one.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            DroppedFileSource::Path(path) => Some(path),
+            DroppedFileSource::Bytes(_) => None,
+        }
+    }
+
+    /// Read the complete contents of the dropped file.
+    pub fn read(&self) -> Result<Vec<u8>, std::io::Error> {
+        match &self.source {
+            DroppedFileSource::Path(path) => std::fs::read(path),
+            DroppedFileSource::Bytes(bytes) => Ok(bytes.clone()),
+        }
+    }
+}
+
+/// Identify a file by its extension, confirming the match by inspecting its contents.
+pub fn identify_file<RS: Read + Seek>(path: &Path, io: &mut RS) -> Option<DroppedFileKind> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    if ext == "vhd" {
+        return is_vhd(io).then_some(DroppedFileKind::Vhd);
+    }
+    if CART_EXTENSIONS.contains(&ext.as_str()) {
+        return is_cart(io).then_some(DroppedFileKind::Cartridge);
+    }
+    if ext == "zip" {
+        // Zip files may be compressed images or mountable archives; leave it to the loader to decide.
+        return Some(DroppedFileKind::FloppyImage);
+    }
+    if fluxfox::supported_extensions()
+        .iter()
+        .any(|e| e.eq_ignore_ascii_case(&ext))
+    {
+        _ = io.seek(SeekFrom::Start(0));
+        return DiskImage::detect_format(io, Some(path))
+            .ok()
+            .map(|_| DroppedFileKind::FloppyImage);
+    }
+    None
+}
+
+/// A VHD is identified by the cookie at the start of its footer.
+fn is_vhd<RS: Read + Seek>(io: &mut RS) -> bool {
+    let mut cookie = [0u8; 8];
+    io.seek(SeekFrom::End(-(VHD_FOOTER_LEN as i64)))
+        .and_then(|_| io.read_exact(&mut cookie))
+        .is_ok_and(|_| &cookie == VHD_COOKIE)
+}
+
+fn is_cart<RS: Read + Seek>(io: &mut RS) -> bool {
+    let mut header = Vec::with_capacity(JRIPCART_SIG_LEN);
+    io.seek(SeekFrom::Start(0))
+        .and_then(|_| io.take(JRIPCART_SIG_LEN as u64).read_to_end(&mut header))
+        .is_ok_and(|_| scan_cart(&header).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identify_vhd_and_cart() {
+        let mut vhd = vec![0u8; 4096];
+        let footer = vhd.len() - VHD_FOOTER_LEN as usize;
+        vhd[footer..footer + 8].copy_from_slice(VHD_COOKIE);
+        let path = Path::new("disk.VHD");
+        assert_eq!(identify_file(path, &mut Cursor::new(&vhd)), Some(DroppedFileKind::Vhd));
+
+        // A VHD extension on a file without a footer is rejected.
+        assert_eq!(identify_file(path, &mut Cursor::new(vec![0u8; 4096])), None);
+        assert_eq!(identify_file(path, &mut Cursor::new(vec![0u8; 16])), None);
+
+        let mut rom = vec![0u8; 0x2000];
+        rom[0] = 0x55;
+        rom[1] = 0xAA;
+        assert_eq!(
+            identify_file(Path::new("game.bin"), &mut Cursor::new(&rom)),
+            Some(DroppedFileKind::Cartridge)
+        );
+        assert_eq!(
+            identify_file(Path::new("game.bin"), &mut Cursor::new(vec![0u8; 0x2000])),
+            None
+        );
+        assert_eq!(identify_file(Path::new("readme.txt"), &mut Cursor::new(&rom)), None);
+        assert_eq!(identify_file(Path::new("noext"), &mut Cursor::new(&rom)), None);
+    }
+}
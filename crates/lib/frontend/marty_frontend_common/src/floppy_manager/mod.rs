@@ -33,6 +33,7 @@
 use crate::{
     resource_manager::{
         tree::{NodeType, TreeNode},
+        PathManager,
         PathTreeNode,
         ResourceItem,
         ResourceItemType,
@@ -129,6 +130,8 @@ impl FloppyManager {
 
         // Retrieve all items from the floppy resource paths.
         let floppy_items = rm.enumerate_items("floppy", None, true, true, Some(self.extensions.clone()))?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let floppy_items = self.expand_image_archives(rm, floppy_items);
 
         let floppy_names = floppy_items
             .iter()
@@ -155,6 +158,51 @@ impl FloppyManager {
         Ok(true)
     }
 
+    /// Replace zip files that hold a collection of floppy images with the images they contain, registering
+    /// each such zip as an archive of the floppy resource. Other zip files are left to be loaded as before,
+    /// as a compressed image or a mountable directory.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn expand_image_archives(&self, rm: &mut ResourceManager, items: Vec<ResourceItem>) -> Vec<ResourceItem> {
+        let mut expanded = Vec::new();
+        for item in items {
+            let is_zip = item
+                .location
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+
+            let images = if is_zip {
+                PathManager::list_archive(&item.location)
+                    .map(|files| {
+                        files
+                            .into_iter()
+                            .filter(|(name, _)| {
+                                Path::new(name).extension().is_some_and(|ext| {
+                                    let ext = ext.to_ascii_lowercase();
+                                    ext != "zip" && self.extensions.contains(&ext)
+                                })
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+            }
+            else {
+                Vec::new()
+            };
+
+            // A single image is handled as a compressed image by discover_archive_type().
+            if images.len() < 2 || rm.pm.add_archive("floppy", &item.location).is_err() {
+                expanded.push(item);
+                continue;
+            }
+
+            log::debug!("Found {} floppy images in archive {:?}", images.len(), item.location);
+            for (name, size) in images {
+                expanded.push(ResourceItem::from_archive_member(&item, &name, size));
+            }
+        }
+        expanded
+    }
+
     pub fn scan_autofloppy(&mut self, rm: &mut ResourceManager) -> Result<bool, Error> {
         // Clear and rebuild autofloppy list.
         self.autofloppy_dir_vec.clear();
@@ -42,26 +42,41 @@
 //! - FloppyManager: Manages the loading and unloading of floppy disk images
 //! - VhdManager: Manages the loading and unloading of VHD disk images
 //! - CartridgeManager: Manages the loading and unloading of ROM cartridges (PCjr specific)
+//! - SaveStateManager: Writes and restores machine save states and quick save slots (native only)
+//! - LuaScriptEngine: Runs Lua automation scripts against a Machine (requires `lua_scripting`)
+//! - DebugServer: Allows a Machine to be debugged by an external client over TCP (native only)
 #![feature(trait_alias)]
 
 use serde_derive::Deserialize;
 
 pub mod cartridge_manager;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cmos_manager;
 pub mod color;
 pub mod constants;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod debug_server;
 pub mod display_manager;
 
 pub mod display_scaler;
 //mod emulator_manager;
 pub mod async_exec;
+pub mod audio_resampler;
+pub mod file_drop;
 pub mod floppy_manager;
 pub mod machine_manager;
 pub mod resource_manager;
 pub mod rom_manager;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod savestate_manager;
+#[cfg(feature = "lua_scripting")]
+pub mod script_engine;
+pub mod text_screen;
 pub mod thread_events;
 pub mod timestep_manager;
 pub mod types;
 pub mod vhd_manager;
+pub mod wav_writer;
 
 pub type FileTreeNode = resource_manager::tree::TreeNode;
 pub type MartyGuiTheme = types::gui::MartyGuiTheme;
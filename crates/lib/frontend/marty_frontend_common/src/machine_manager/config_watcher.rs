@@ -0,0 +1,123 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    machine_manager::config_watcher.rs
+
+    Watches a machine configuration file for changes, re-parsing it and
+    sending the new configuration over a channel whenever it is modified.
+
+*/
+
+use std::path::{Path, PathBuf};
+
+use crate::machine_manager::MachineConfigFile;
+use anyhow::Error;
+use crossbeam_channel::{Receiver, TryRecvError};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+pub struct ConfigWatcher {
+    path: PathBuf,
+    receiver: Receiver<Result<MachineConfigFile, String>>,
+    // The watcher stops watching when dropped.
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    /// Watch the specified configuration file. The file's directory is watched rather than the file itself,
+    /// as many editors save a file by replacing it, which would end a watch on the original file.
+    pub fn new(path: &Path) -> Result<Self, Error> {
+        let path = path.canonicalize()?;
+        let dir = path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Configuration file {:?} has no parent directory", path))?
+            .to_path_buf();
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let watch_path = path.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let event = match res {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("Configuration watcher error: {}", e);
+                    return;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            if !event.paths.iter().any(|p| *p == watch_path) {
+                return;
+            }
+            log::debug!("Configuration file changed: {:?}", watch_path);
+            let _ = sender.send(MachineConfigFile::from_file(&watch_path).map_err(|e| e.to_string()));
+        })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            path,
+            receiver,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the most recent re-parse of the configuration file, if it has changed since the last poll.
+    /// Saving a file often generates several events, so only the last result is returned.
+    pub fn poll(&self) -> Option<Result<MachineConfigFile, String>> {
+        let mut latest = None;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(result) => latest = Some(result),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        latest
+    }
+}
+
+impl MachineConfigFile {
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let toml_str = std::fs::read_to_string(path)?;
+        Ok(toml::from_str::<MachineConfigFile>(&toml_str)?)
+    }
+
+    /// Parse a configuration file and start watching it for changes.
+    pub fn from_file_watched(path: &Path) -> Result<(Self, ConfigWatcher), Error> {
+        let config = Self::from_file(path)?;
+        let watcher = ConfigWatcher::new(path)?;
+        Ok((config, watcher))
+    }
+
+    /// Return whether the file defines a machine configuration with the specified name.
+    pub fn defines_machine(&self, name: &str) -> bool {
+        self.machine
+            .as_ref()
+            .is_some_and(|machines| machines.iter().any(|machine| machine.name == name))
+    }
+}
@@ -36,6 +36,7 @@ use marty_core::{
     machine_config::{
         CpuConfig,
         EmsMemoryConfig,
+        ExtensionRomConfig,
         FloppyControllerConfig,
         GamePortConfig,
         HardDriveControllerConfig,
@@ -43,11 +44,18 @@ use marty_core::{
         MachineConfiguration,
         MediaConfig,
         MemoryConfig,
+        OptionRomConfig,
+        ProfileRegionConfig,
+        RomHookConfig,
+        RomPatchConfig,
+        RtcConfig,
         SerialControllerConfig,
         SerialMouseConfig,
         SoundDeviceConfig,
         VideoCardConfig,
+        WaitStateRegionConfig,
     },
+    machine_preset::{MachinePreset, PresetOverrides},
     machine_types::{HardDiskControllerType, MachineType},
 };
 
@@ -55,8 +63,12 @@ use serde_derive::Deserialize;
 use std::{
     collections::{BTreeMap, HashSet},
     ffi::OsString,
+    path::PathBuf,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config_watcher;
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct MachineConfigFile {
     machine: Option<Vec<MachineConfigFileEntry>>,
@@ -89,7 +101,18 @@ pub struct MachineConfigFileEntry {
     keyboard: Option<KeyboardConfig>,
     serial_mouse: Option<SerialMouseConfig>,
     game_port: Option<GamePortConfig>,
+    rtc: Option<RtcConfig>,
     media: Option<MediaConfig>,
+    post_code_table: Option<String>,
+    option_rom: Option<Vec<OptionRomConfig>>,
+    extension_rom: Option<Vec<ExtensionRomConfig>>,
+    io_open_bus_value: Option<u8>,
+    #[serde(default)]
+    io_floating_bus: bool,
+    profile_region: Option<Vec<ProfileRegionConfig>>,
+    wait_state_region: Option<Vec<WaitStateRegionConfig>>,
+    rom_patch: Option<Vec<RomPatchConfig>>,
+    rom_hook: Option<Vec<RomHookConfig>>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -106,6 +129,8 @@ pub struct MachineConfigFileOverlayEntry {
     keyboard: Option<KeyboardConfig>,
     serial_mouse: Option<SerialMouseConfig>,
     game_port: Option<GamePortConfig>,
+    rtc: Option<RtcConfig>,
+    option_rom: Option<Vec<OptionRomConfig>>,
     // TODO: Support media in overlay?
     #[allow(unused)]
     media: Option<MediaConfig>,
@@ -124,6 +149,7 @@ pub struct MachineManager {
     config_names: HashSet<String>,
     overlay_names: HashSet<String>,
     configs: BTreeMap<String, MachineConfigFileEntry>,
+    config_paths: BTreeMap<String, PathBuf>,
     overlays: BTreeMap<String, MachineConfigFileOverlayEntry>,
     features_requested: HashSet<String>,
     features_provided: HashSet<String>,
@@ -137,6 +163,7 @@ impl Default for MachineManager {
             config_names: HashSet::new(),
             overlay_names: HashSet::new(),
             configs: BTreeMap::new(),
+            config_paths: BTreeMap::new(),
             overlays: BTreeMap::new(),
             features_requested: HashSet::new(),
             features_provided: HashSet::new(),
@@ -153,7 +180,7 @@ impl MachineManager {
     }
 
     pub async fn load_configs(&mut self, rm: &mut ResourceManager) -> Result<(), Error> {
-        let mut machine_configs: Vec<MachineConfigFileEntry> = Vec::new();
+        let mut machine_configs: Vec<(MachineConfigFileEntry, PathBuf)> = Vec::new();
         let mut overlay_configs: Vec<MachineConfigFileOverlayEntry> = Vec::new();
 
         log::debug!("load_configs(): Loading machine configurations...");
@@ -186,8 +213,8 @@ impl MachineManager {
                 }
             };
 
-            if let Some(machine_vec) = loaded_config.machine.as_mut() {
-                machine_configs.append(machine_vec);
+            if let Some(machine_vec) = loaded_config.machine.take() {
+                machine_configs.extend(machine_vec.into_iter().map(|entry| (entry, config.location.clone())));
             }
             if let Some(overlay_vec) = loaded_config.overlay.as_mut() {
                 overlay_configs.append(overlay_vec);
@@ -195,10 +222,11 @@ impl MachineManager {
         }
 
         // Check for duplicate names
-        for config in machine_configs {
+        for (config, path) in machine_configs {
             if self.configs.contains_key(&config.name) {
                 return Err(anyhow::anyhow!("Duplicate machine name: {}", config.name));
             }
+            self.config_paths.insert(config.name.clone(), path);
             self.configs.insert(config.name.clone(), config);
         }
         for overlay in overlay_configs {
@@ -241,6 +269,11 @@ impl MachineManager {
         names
     }
 
+    /// Return the path of the file that defines the machine configuration with the given name, if present.
+    pub fn get_config_path(&self, config_name: &str) -> Option<&PathBuf> {
+        self.config_paths.get(config_name)
+    }
+
     /// Return the machine configuration with the given name, if present.
     pub fn get_config(&self, config_name: &str) -> Option<&MachineConfigFileEntry> {
         self.configs.get(config_name)
@@ -275,6 +308,22 @@ impl MachineManager {
         Ok(&self.active_config.as_ref().unwrap())
     }
 
+    /// Generate a configuration from a built-in machine template instead of a configuration file.
+    /// The generated configuration is named after the template's equivalent shipped configuration.
+    pub fn get_config_from_template(
+        &mut self,
+        preset: MachinePreset,
+        overrides: &PresetOverrides,
+    ) -> &MachineConfigFileEntry {
+        let config = preset.to_machine_config_with(overrides);
+        self.active_config = Some(MachineConfigFileEntry::from_machine_config(
+            preset.config_name(),
+            preset.rom_set(),
+            &config,
+        ));
+        self.active_config.as_ref().unwrap()
+    }
+
     /*
     pub fn resolve_sets(&self, config_name: &str, rom_manager: &RomManager) -> Result<MachineConfigContext, Error> {
         let config = self
@@ -292,6 +341,41 @@ impl MachineManager {
 }
 
 impl MachineConfigFileEntry {
+    /// Create a configuration entry from a complete MachineConfiguration. This is the inverse of
+    /// [MachineConfigFileEntry::to_machine_config].
+    pub fn from_machine_config(name: &str, rom_set: &str, config: &MachineConfiguration) -> Self {
+        Self {
+            name: name.to_string(),
+            machine_type: config.machine_type,
+            rom_set: rom_set.to_string(),
+            overlays: None,
+            cpu: config.cpu.clone(),
+            memory: config.memory.clone(),
+            ems: config.ems.clone(),
+            speaker: config.speaker,
+            ppi_turbo: config.ppi_turbo,
+            fdc: config.fdc.clone(),
+            hdc: config.hdc.clone(),
+            serial: Some(config.serial.clone()),
+            video: Some(config.video.clone()),
+            sound: Some(config.sound.clone()),
+            keyboard: config.keyboard.clone(),
+            serial_mouse: config.serial_mouse.clone(),
+            game_port: config.game_port.clone(),
+            rtc: config.rtc.clone(),
+            media: config.media.clone(),
+            post_code_table: config.post_code_table.clone(),
+            option_rom: Some(config.option_roms.clone()),
+            extension_rom: Some(config.extension_roms.clone()),
+            io_open_bus_value: config.io_open_bus_value,
+            io_floating_bus: config.io_floating_bus,
+            profile_region: Some(config.profile_regions.clone()),
+            wait_state_region: Some(config.wait_state_regions.clone()),
+            rom_patch: Some(config.rom_patches.clone()),
+            rom_hook: Some(config.rom_hooks.clone()),
+        }
+    }
+
     pub fn get_specified_rom_set(&self) -> Option<String> {
         if self.rom_set.contains("auto") {
             return None;
@@ -424,6 +508,14 @@ impl MachineConfigFileEntry {
             log::debug!("Applying game port overlay: {:?}", game_port);
             self.game_port = Some(game_port);
         }
+        if let Some(rtc) = overlay.rtc {
+            log::debug!("Applying RTC overlay: {:?}", rtc);
+            self.rtc = Some(rtc);
+        }
+        if let Some(option_rom) = overlay.option_rom {
+            log::debug!("Applying option ROM overlay: {:?}", option_rom);
+            self.option_rom = Some(option_rom);
+        }
     }
 
     pub fn to_machine_config(&self) -> MachineConfiguration {
@@ -442,7 +534,47 @@ impl MachineConfigFileEntry {
             keyboard: self.keyboard.clone(),
             serial_mouse: self.serial_mouse.clone(),
             game_port: self.game_port.clone(),
+            rtc: self.rtc.clone(),
             media: self.media.clone(),
+            post_code_table: self.post_code_table.clone(),
+            option_roms: self.option_rom.clone().unwrap_or_default(),
+            extension_roms: self.extension_rom.clone().unwrap_or_default(),
+            io_open_bus_value: self.io_open_bus_value,
+            io_floating_bus: self.io_floating_bus,
+            profile_regions: self.profile_region.clone().unwrap_or_default(),
+            wait_state_regions: self.wait_state_region.clone().unwrap_or_default(),
+            rom_patches: self.rom_patch.clone().unwrap_or_default(),
+            rom_hooks: self.rom_hook.clone().unwrap_or_default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn config_from_template() {
+        let mut manager = MachineManager::new();
+        for preset in MachinePreset::iter() {
+            let expected = preset.to_machine_config();
+            let entry = manager.get_config_from_template(preset, &PresetOverrides::default());
+            assert_eq!(entry.get_specified_rom_set().is_none(), preset.rom_set() == "auto");
+
+            let config = entry.to_machine_config();
+            assert_eq!(config.machine_type, expected.machine_type);
+            assert_eq!(config.memory.conventional.size, expected.memory.conventional.size);
+            assert_eq!(config.video.len(), expected.video.len());
+            assert_eq!(config.sound.len(), expected.sound.len());
+        }
+
+        let overrides = PresetOverrides {
+            conventional_memory: Some(0x40000),
+            ..Default::default()
+        };
+        let entry = manager.get_config_from_template(MachinePreset::IbmPcjr, &overrides);
+        assert_eq!(entry.get_specified_rom_set().as_deref(), Some("ibm_pcjr"));
+        assert_eq!(entry.to_machine_config().memory.conventional.size, 0x40000);
+    }
+}
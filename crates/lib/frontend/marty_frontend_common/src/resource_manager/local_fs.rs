@@ -38,6 +38,7 @@ use crate::resource_manager::{
     ResourceManager,
 };
 use anyhow::Error;
+use glob::Pattern;
 use marty_common::MartyHashMap;
 use std::{
     collections::HashSet,
@@ -189,7 +190,9 @@ impl ResourceManager {
     /// This function searches for resource items starting from the paths associated with the given `resource`.
     /// The paths are obtained from a resource manager (`self.pm`). If `multipath` is `true`, it explores all
     /// available paths; otherwise, it only explores the first path. The search avoids directories listed in
-    /// `self.ignore_dirs`.
+    /// `self.ignore_dirs`, and descends into subdirectories up to the resource's maximum scan depth.
+    /// Listings always recurse regardless of the resource's 'recurse' option, as the floppy manager builds
+    /// autofloppy images from the subdirectories of the autofloppy path.
     fn enumerate_items_recursive(
         &mut self,
         multipath: bool,
@@ -215,24 +218,24 @@ impl ResourceManager {
         }
 
         let mut items: Vec<ResourceItem> = Vec::new();
-        let mut visited = HashSet::new();
         let mut item_map = MartyHashMap::default();
 
+        // Ignored directories are matched by name, so escape them for use as glob patterns.
+        let ignore_dirs = self.ignore_dirs.iter().map(|s| Pattern::escape(s)).collect::<Vec<_>>();
+        let ignore_dirs = ignore_dirs.iter().map(|s| s.as_str()).collect::<Vec<_>>();
         for root in roots.iter() {
-            let ignore_dirs = self.ignore_dirs.iter().map(|s| s.as_str()).collect();
-            ResourceManager::visit_dirs(&root, &mut visited, &ignore_dirs, &mut |entry: &fs::DirEntry| {
-                let path = entry.path();
+            for path in self.pm.scan_directory(resource, root, true, &[], &ignore_dirs)? {
                 let resource_item = ResourceItem {
                     rtype: ResourceItemType::File(ResourceFsType::Native),
-                    location: entry.path(),
+                    location: path.clone(),
                     relative_path: None,
-                    filename_only: Some(entry.path().file_name().unwrap_or_default().to_os_string()),
+                    filename_only: Some(path.file_name().unwrap_or_default().to_os_string()),
                     flags: 0,
-                    size: Some(entry.path().metadata().unwrap().len()),
+                    size: Some(path.metadata()?.len()),
                 };
 
-                item_map.insert(path.clone(), resource_item);
-            })?
+                item_map.insert(path, resource_item);
+            }
         }
 
         for overlay in &mut self.overlays {
@@ -350,7 +353,13 @@ impl ResourceManager {
                 let path = entry.path();
 
                 // Resolve the symlink (if any) and check if it's already visited
-                let canonical_path = fs::canonicalize(&path)?;
+                let canonical_path = match fs::canonicalize(&path) {
+                    Ok(canonical_path) => canonical_path,
+                    Err(e) => {
+                        log::warn!("Skipping unresolvable path {}: {}", path.display(), e);
+                        continue;
+                    }
+                };
                 if visited.contains(&canonical_path) {
                     continue;
                 }
@@ -446,6 +455,11 @@ impl ResourceManager {
         let buffer = match std::fs::read(path) {
             Ok(buffer) => buffer,
             Err(e) => {
+                // The path may address a file within a registered archive.
+                if let Some(result) = self.pm.read_archive_path(path) {
+                    return result;
+                }
+
                 // If the file doesn't exist, try reading from the overlay.
                 for overlay in &mut self.overlays {
                     log::debug!("Attempting to read file {:?} from fs overlay...", path.display());
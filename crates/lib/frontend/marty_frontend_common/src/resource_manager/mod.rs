@@ -56,8 +56,7 @@ use crate::{resource_manager::archive_overlay::ArchiveOverlay, types::resource_l
 
 #[cfg(target_arch = "wasm32")]
 use marty_web_helpers::fetch_file;
-pub use path_manager::PathConfigItem;
-use path_manager::PathManager;
+pub use path_manager::{PathConfigItem, PathManager};
 
 use anyhow::Error;
 use regex::Regex;
@@ -105,6 +104,19 @@ impl ResourceItem {
             flags: 0,
         }
     }
+
+    /// Create an item for a file within a zip archive, addressed as if the archive were a directory.
+    pub fn from_archive_member(archive: &ResourceItem, name: &str, size: u64) -> Self {
+        let location = archive.location.join(name);
+        Self {
+            rtype: ResourceItemType::File(ResourceFsType::Native),
+            relative_path: archive.relative_path.as_ref().map(|p| p.join(name)),
+            filename_only: location.file_name().map(|s| s.to_os_string()),
+            location,
+            size: Some(size),
+            flags: RESOURCE_READONLY,
+        }
+    }
 }
 
 pub struct ResourceManager {
@@ -193,6 +205,7 @@ impl ResourceManager {
         let mut rm = Self::new(base_path);
         for item in config {
             rm.pm.add_path(&item.resource, &item.path, item.create)?;
+            rm.pm.set_scan_options(&item.resource, item.recurse, item.max_depth);
         }
         //rm.pm.create_paths()?;
         Ok(rm)
@@ -29,17 +29,33 @@
     File and path services for frontends. File operations are abstracted
     to support both local and web filesystems (for wasm compilation).
 
-    Eventually archive support will be added as well.
+    ZIP archives may be registered as containers for a resource. Their
+    members are addressed as if the archive were a directory, ie,
+    'floppy/games.zip/disk1.img'.
+
+    Resource directories may be scanned recursively, up to a maximum depth
+    that can be configured per resource.
 
 */
 
 use crate::resource_manager::ResourceManager;
 use anyhow::Error;
+use glob::{MatchOptions, Pattern};
 use serde::Deserialize;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
 
 const BASEDIR_TOKEN: &'static str = "$basedir$";
 
+/// The maximum number of directory levels scanned below a resource path if not configured.
+pub const DEFAULT_MAX_SCAN_DEPTH: usize = 8;
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct PathConfigItem {
     pub resource: String,
@@ -48,11 +64,16 @@ pub struct PathConfigItem {
     pub create: bool,
     #[serde(default)]
     pub recurse: bool,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
 }
 
 pub struct PathManager {
     base_path: PathBuf,
     paths: HashMap<String, Vec<PathBuf>>,
+    archives: HashMap<String, Vec<PathBuf>>,
+    recurse: HashMap<String, bool>,
+    max_depth: HashMap<String, usize>,
 }
 
 impl PathManager {
@@ -60,7 +81,193 @@ impl PathManager {
         Self {
             base_path,
             paths: HashMap::new(),
+            archives: HashMap::new(),
+            recurse: HashMap::new(),
+            max_depth: HashMap::new(),
+        }
+    }
+
+    /// Set whether the directories of the specified resource are scanned recursively, and how deep.
+    pub fn set_scan_options(&mut self, resource_name: &str, recurse: bool, max_depth: Option<usize>) {
+        self.recurse.insert(resource_name.to_string(), recurse);
+        if let Some(max_depth) = max_depth {
+            self.max_depth.insert(resource_name.to_string(), max_depth);
+        }
+    }
+
+    pub fn resource_recurses(&self, resource_name: &str) -> bool {
+        self.recurse.get(resource_name).copied().unwrap_or(false)
+    }
+
+    pub fn max_scan_depth(&self, resource_name: &str) -> usize {
+        self.max_depth
+            .get(resource_name)
+            .copied()
+            .unwrap_or(DEFAULT_MAX_SCAN_DEPTH)
+    }
+
+    /// Walk the directory tree below `root` and return the files whose names match any of the
+    /// `include_glob` patterns (or all files, if none are given) and none of the `exclude_glob`
+    /// patterns. Directories matching an exclude pattern are not descended into. If `recurse` is
+    /// set, subdirectories are scanned up to the maximum depth configured for the resource.
+    /// Patterns are matched case-insensitively against file and directory names.
+    pub fn scan_directory(
+        &self,
+        resource_name: &str,
+        root: &Path,
+        recurse: bool,
+        include_glob: &[&str],
+        exclude_glob: &[&str],
+    ) -> Result<Vec<PathBuf>, Error> {
+        let include = PathManager::compile_globs(include_glob)?;
+        let exclude = PathManager::compile_globs(exclude_glob)?;
+        let max_depth = if recurse { self.max_scan_depth(resource_name) } else { 0 };
+
+        let mut files = Vec::new();
+        let mut visited = HashSet::new();
+        PathManager::scan_directory_level(root, 0, max_depth, &include, &exclude, &mut visited, &mut files)?;
+        files.sort();
+
+        log::debug!(
+            "scan_directory(): Found {} files under {} for resource: {}",
+            files.len(),
+            root.display(),
+            resource_name
+        );
+        Ok(files)
+    }
+
+    fn compile_globs(patterns: &[&str]) -> Result<Vec<Pattern>, Error> {
+        patterns
+            .iter()
+            .map(|p| Pattern::new(p).map_err(|e| anyhow::anyhow!("Invalid glob pattern '{}': {}", p, e)))
+            .collect()
+    }
+
+    fn scan_directory_level(
+        dir: &Path,
+        depth: usize,
+        max_depth: usize,
+        include: &[Pattern],
+        exclude: &[Pattern],
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if exclude.iter().any(|p| p.matches_with(&name, options)) {
+                continue;
+            }
+
+            // Skip anything we have already reached through a symlink, and broken symlinks.
+            let canonical_path = match fs::canonicalize(&path) {
+                Ok(canonical_path) => canonical_path,
+                Err(e) => {
+                    log::warn!("Skipping unresolvable path {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if !visited.insert(canonical_path) {
+                continue;
+            }
+
+            if path.is_dir() {
+                if depth < max_depth {
+                    PathManager::scan_directory_level(&path, depth + 1, max_depth, include, exclude, visited, files)?;
+                }
+            }
+            else if include.is_empty() || include.iter().any(|p| p.matches_with(&name, options)) {
+                files.push(path);
+            }
         }
+        Ok(())
+    }
+
+    /// Register a ZIP archive as a container for the specified resource.
+    pub fn add_archive(&mut self, resource_name: &str, zip_path: &Path) -> Result<(), Error> {
+        // Make sure the archive is readable before registering it.
+        ZipArchive::new(File::open(zip_path)?)
+            .map_err(|e| anyhow::anyhow!("Failed to open archive {}: {}", zip_path.display(), e))?;
+
+        let archives = self.archives.entry(resource_name.to_string()).or_default();
+        if !archives.iter().any(|p| p == zip_path) {
+            archives.push(zip_path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    pub fn get_resource_archives(&self, resource_name: &str) -> Option<Vec<PathBuf>> {
+        self.archives.get(resource_name).cloned()
+    }
+
+    /// Extract the named file from the first archive registered for the specified resource that contains it.
+    pub fn read_resource(&self, resource_name: &str, file_name: &str) -> Result<Vec<u8>, Error> {
+        let archives = self.archives.get(resource_name).ok_or(anyhow::anyhow!(
+            "No archives registered for resource: {}",
+            resource_name
+        ))?;
+
+        for zip_path in archives {
+            if let Some(data) = PathManager::read_archive_file(zip_path, file_name)? {
+                return Ok(data);
+            }
+        }
+        Err(anyhow::anyhow!(
+            "File '{}' not found in archives for resource: {}",
+            file_name,
+            resource_name
+        ))
+    }
+
+    /// Read a path of the form 'archive.zip/member' from a registered archive. Returns None if the path
+    /// does not lie within a registered archive.
+    pub fn read_archive_path(&self, path: &Path) -> Option<Result<Vec<u8>, Error>> {
+        let (zip_path, member) = self.archives.values().flatten().find_map(|zip_path| {
+            path.strip_prefix(zip_path)
+                .ok()
+                .map(|member| (zip_path, member.to_string_lossy().replace('\\', "/")))
+        })?;
+
+        Some(match PathManager::read_archive_file(zip_path, &member) {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => Err(anyhow::anyhow!(
+                "File '{}' not found in archive {}",
+                member,
+                zip_path.display()
+            )),
+            Err(e) => Err(e),
+        })
+    }
+
+    /// List the names of the files contained in a ZIP archive.
+    pub fn list_archive(zip_path: &Path) -> Result<Vec<(String, u64)>, Error> {
+        let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+        let mut files = Vec::new();
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            if !file.is_dir() {
+                files.push((file.name().to_string(), file.size()));
+            }
+        }
+        Ok(files)
+    }
+
+    fn read_archive_file(zip_path: &Path, file_name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+        let mut file = match archive.by_name(file_name) {
+            Ok(file) => file,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(Some(data))
     }
 
     pub fn add_path(&mut self, resource_name: &str, path_str: &str, create: bool) -> Result<(), Error> {
@@ -149,3 +356,99 @@ impl PathManager {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    #[test]
+    fn archive_resources_are_readable_by_name_and_path() {
+        let dir = std::env::temp_dir().join(format!("martypc_archive_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let zip_path = dir.join("games.zip");
+
+        let mut writer = ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("disk1.img", options).unwrap();
+        writer.write_all(&[1, 2, 3]).unwrap();
+        writer.start_file("extra/disk2.img", options).unwrap();
+        writer.write_all(&[4, 5]).unwrap();
+        writer.finish().unwrap();
+
+        let mut pm = PathManager::new(dir.clone());
+        assert!(pm.add_archive("floppy", &dir.join("missing.zip")).is_err());
+        pm.add_archive("floppy", &zip_path).unwrap();
+        pm.add_archive("floppy", &zip_path).unwrap();
+        assert_eq!(pm.get_resource_archives("floppy").unwrap().len(), 1);
+
+        let files = PathManager::list_archive(&zip_path).unwrap();
+        assert_eq!(
+            files,
+            vec![("disk1.img".to_string(), 3), ("extra/disk2.img".to_string(), 2)]
+        );
+
+        assert_eq!(pm.read_resource("floppy", "disk1.img").unwrap(), vec![1, 2, 3]);
+        assert!(pm.read_resource("floppy", "disk3.img").is_err());
+        assert!(pm.read_resource("rom", "disk1.img").is_err());
+
+        let member = zip_path.join("extra").join("disk2.img");
+        assert_eq!(pm.read_archive_path(&member).unwrap().unwrap(), vec![4, 5]);
+        assert!(pm.read_archive_path(&dir.join("disk1.img")).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scan_directory_honors_depth_and_patterns() {
+        let dir = std::env::temp_dir().join(format!("martypc_scan_test_{}", std::process::id()));
+        for sub in ["parking", "sub/deeper"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in [
+            "a.img",
+            "b.IMG",
+            "notes.txt",
+            "parking/c.img",
+            "sub/d.img",
+            "sub/deeper/e.img",
+        ] {
+            File::create(dir.join(file)).unwrap();
+        }
+
+        let mut pm = PathManager::new(dir.clone());
+        pm.set_scan_options("floppy", true, Some(1));
+        assert!(pm.resource_recurses("floppy"));
+        assert!(!pm.resource_recurses("rom"));
+
+        let scan = |resource: &str, recurse: bool, exclude: &[&str]| {
+            pm.scan_directory(resource, &dir, recurse, &["*.img", "*.ima"], exclude)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(scan("floppy", false, &[]), vec!["a.img", "b.IMG"]);
+        assert_eq!(scan("floppy", true, &["parking"]), vec!["a.img", "b.IMG", "sub/d.img"]);
+        assert_eq!(
+            scan("rom", true, &["parking"]),
+            vec!["a.img", "b.IMG", "sub/d.img", "sub/deeper/e.img"]
+        );
+        assert_eq!(
+            pm.scan_directory("floppy", &dir, true, &[], &["*.img"]).unwrap().len(),
+            1
+        );
+        assert!(pm.scan_directory("floppy", &dir, false, &["[img"], &[]).is_err());
+
+        // A broken symlink is skipped rather than failing the scan.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("missing.img"), dir.join("broken.img")).unwrap();
+            assert_eq!(scan("floppy", false, &[]), vec!["a.img", "b.IMG"]);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
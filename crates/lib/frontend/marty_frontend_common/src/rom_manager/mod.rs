@@ -31,7 +31,12 @@
 
 use crate::resource_manager::{ResourceItemType, ResourceManager};
 use anyhow::Error;
-use marty_core::machine::{MachineCheckpoint, MachinePatch, MachineRomEntry, MachineRomManifest};
+use marty_core::{
+    machine::{MachineCheckpoint, MachinePatch, MachineRomEntry, MachineRomManifest},
+    machine_config::OptionRomConfig,
+    option_rom::{OptionRom, OptionRomError},
+    rom_info::{self, KnownRom},
+};
 use serde::Deserialize;
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
@@ -113,6 +118,7 @@ pub struct RomCheckpoint {
 #[derive(Clone, Debug, Deserialize)]
 pub struct RomSetDefinition {
     alias: String,
+    desc: Option<String>,
     priority: u32,
     provides: Vec<String>,
     #[serde(default)]
@@ -160,7 +166,9 @@ pub struct RomManager {
     checkpoints_active: HashMap<u32, RomCheckpoint>,
     patches_active: HashMap<u32, RomPatch>,
 
-    manifest: Option<MachineRomManifest>,
+    manifest:    Option<MachineRomManifest>,
+    option_roms: Vec<OptionRom>,
+    known_roms:  Vec<KnownRom>,
 }
 
 impl Default for RomManager {
@@ -186,7 +194,9 @@ impl Default for RomManager {
             checkpoints_active: HashMap::new(),
             patches_active: HashMap::new(),
 
-            manifest: None,
+            manifest:    None,
+            option_roms: Vec::new(),
+            known_roms:  Vec::new(),
         }
     }
 }
@@ -233,10 +243,43 @@ impl RomManager {
         // We haven't had any errors yet, so we can assign the rom_defs as our final list.
         self.rom_defs = rom_defs;
         self.sort_by_feature();
+        self.collect_known_roms();
         //self.print_rom_stats();
         Ok(())
     }
 
+    /// Build the list of known-good ROM dumps from the hashes in the ROM set definitions. This must be
+    /// done before the ROM sets are resolved, as resolving fills in the hash of ROMs identified only by
+    /// filename from whatever file is present.
+    fn collect_known_roms(&mut self) {
+        let mut known_roms: Vec<KnownRom> = Vec::new();
+        for def in self.rom_defs.iter() {
+            let machines = rom_info::machines_for_features(&def.provides);
+            let set_name = def.desc.as_ref().unwrap_or(&def.alias);
+            for rom in def.rom.iter() {
+                let Some(md5) = rom.md5.as_ref()
+                else {
+                    continue;
+                };
+                let name = match (&rom.chip, def.rom.len() > 1) {
+                    (Some(chip), true) => format!("{} ({})", set_name, chip),
+                    _ => set_name.clone(),
+                };
+                let known = KnownRom {
+                    md5: md5.to_ascii_lowercase(),
+                    name,
+                    machines: machines.clone(),
+                };
+                match known_roms.iter_mut().find(|rom| rom.md5 == known.md5) {
+                    Some(existing) => existing.merge(&known),
+                    None => known_roms.push(known),
+                }
+            }
+        }
+        log::debug!("collect_known_roms(): {} known-good ROM dumps.", known_roms.len());
+        self.known_roms = known_roms;
+    }
+
     async fn load_def(&mut self, toml_path: &PathBuf, rm: &mut ResourceManager) -> Result<RomDefinitionFile, Error> {
         let toml_str = rm.read_string_from_path(toml_path).await?;
         //let toml_str = std::fs::read_to_string(toml_path)?;
@@ -844,6 +887,8 @@ impl RomManager {
                 })?;
 
                 let mut rom_vec = rm.read_resource_from_path_blocking(&rom_file.path)?;
+                // Checksum the image file before it is reorganized for mapping.
+                let rom_crc32 = rom_info::crc32(&rom_vec);
 
                 // Handle rom organization
                 // TODO: Interleaved organizations... double rom size and then interleave?
@@ -870,9 +915,10 @@ impl RomManager {
                         }
 
                         new_manifest.roms.push(MachineRomEntry {
-                            md5:  rom_desc.md5.clone().unwrap(),
-                            addr: rom_desc.addr,
-                            data: rom_vec,
+                            md5:   rom_desc.md5.clone().unwrap(),
+                            crc32: rom_crc32,
+                            addr:  rom_desc.addr,
+                            data:  rom_vec,
                         });
                         new_manifest.rom_paths.push(rom_file.path.clone());
                     }
@@ -899,9 +945,10 @@ impl RomManager {
                         }
 
                         new_manifest.roms.push(MachineRomEntry {
-                            md5:  rom_desc.md5.clone().unwrap(),
-                            addr: rom_desc.addr,
-                            data: rom_vec,
+                            md5:   rom_desc.md5.clone().unwrap(),
+                            crc32: rom_crc32,
+                            addr:  rom_desc.addr,
+                            data:  rom_vec,
                         });
                         new_manifest.rom_paths.push(rom_file.path.clone());
                     }
@@ -942,6 +989,10 @@ impl RomManager {
             }
         }
 
+        // Option ROMs are not part of any rom set, so carry them over from the last load.
+        new_manifest.option_roms = self.option_roms.clone();
+        new_manifest.known_roms = self.known_roms.clone();
+
         // Save a copy of the manifest for reloading
         self.manifest = Some(new_manifest.clone());
         Ok(new_manifest)
@@ -976,6 +1027,8 @@ impl RomManager {
                 })?;
 
                 let mut rom_vec = rm.read_resource_from_path(&rom_file.path).await?;
+                // Checksum the image file before it is reorganized for mapping.
+                let rom_crc32 = rom_info::crc32(&rom_vec);
 
                 // Handle rom organization
                 // TODO: Interleaved organizations... double rom size and then interleave?
@@ -1002,9 +1055,10 @@ impl RomManager {
                         }
 
                         new_manifest.roms.push(MachineRomEntry {
-                            md5:  rom_desc.md5.clone().unwrap(),
-                            addr: rom_desc.addr,
-                            data: rom_vec,
+                            md5:   rom_desc.md5.clone().unwrap(),
+                            crc32: rom_crc32,
+                            addr:  rom_desc.addr,
+                            data:  rom_vec,
                         });
                         new_manifest.rom_paths.push(rom_file.path.clone());
                     }
@@ -1031,9 +1085,10 @@ impl RomManager {
                         }
 
                         new_manifest.roms.push(MachineRomEntry {
-                            md5:  rom_desc.md5.clone().unwrap(),
-                            addr: rom_desc.addr,
-                            data: rom_vec,
+                            md5:   rom_desc.md5.clone().unwrap(),
+                            crc32: rom_crc32,
+                            addr:  rom_desc.addr,
+                            data:  rom_vec,
                         });
                         new_manifest.rom_paths.push(rom_file.path.clone());
                     }
@@ -1074,8 +1129,80 @@ impl RomManager {
             }
         }
 
+        // Option ROMs are not part of any rom set, so carry them over from the last load.
+        new_manifest.option_roms = self.option_roms.clone();
+        new_manifest.known_roms = self.known_roms.clone();
+
         // Save a copy of the manifest for reloading
         self.manifest = Some(new_manifest.clone());
         Ok(new_manifest)
     }
+
+    /// Load the option ROMs specified by a machine configuration into the manifest. Relative paths are
+    /// resolved against the 'rom' resource directory. A ROM that is missing, fails validation, or overlaps
+    /// another ROM is skipped; an error is returned for each so the caller can report them.
+    pub async fn load_option_roms(
+        &mut self,
+        configs: &[OptionRomConfig],
+        manifest: &mut MachineRomManifest,
+        rm: &mut ResourceManager,
+    ) -> Vec<Error> {
+        let mut errors = Vec::new();
+        let rom_base = rm.resource_path("rom").unwrap_or_default();
+
+        for config in configs.iter() {
+            let path = rom_base.join(&config.path);
+            let data = match rm.read_resource_from_path(&path).await {
+                Ok(data) => data,
+                Err(e) => {
+                    errors.push(anyhow::anyhow!("Failed to read option ROM {}: {}", config.path, e));
+                    continue;
+                }
+            };
+
+            let rom = match OptionRom::new(&config.path, config.segment, data, config.fix_checksum) {
+                Ok(rom) => rom,
+                Err(e) => {
+                    errors.push(anyhow::anyhow!("Option ROM {} is invalid: {}", config.path, e));
+                    continue;
+                }
+            };
+
+            let conflict = manifest
+                .roms
+                .iter()
+                .find(|other| rom.overlaps(other.addr, other.data.len()))
+                .map(|other| other.addr)
+                .or_else(|| {
+                    manifest
+                        .option_roms
+                        .iter()
+                        .find(|other| rom.overlaps(other.address(), other.size()))
+                        .map(|other| other.address())
+                });
+            if let Some(addr) = conflict {
+                errors.push(anyhow::anyhow!(
+                    "Option ROM {} is invalid: {}",
+                    config.path,
+                    OptionRomError::Overlap(config.segment, addr)
+                ));
+                continue;
+            }
+
+            if rom.checksum_fixed {
+                log::warn!("Corrected checksum of option ROM {}", config.path);
+            }
+            log::debug!(
+                "Loaded option ROM {} ({} bytes) at segment {:04X}",
+                config.path,
+                rom.size(),
+                config.segment
+            );
+            manifest.option_roms.push(rom);
+        }
+
+        self.option_roms = manifest.option_roms.clone();
+        self.manifest = Some(manifest.clone());
+        errors
+    }
 }
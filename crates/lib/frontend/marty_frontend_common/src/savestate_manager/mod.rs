@@ -0,0 +1,94 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::savestate_manager.rs
+
+    Write and read machine save states to and from the 'savestate' resource,
+    including numbered quick save slots.
+*/
+
+use std::{fs, path::PathBuf, time::SystemTime};
+
+use anyhow::{anyhow, Error};
+use marty_core::{machine::Machine, savestate::SAVE_STATE_EXTENSION};
+
+use crate::{constants::QUICKSAVE_SLOTS, resource_manager::ResourceManager};
+
+pub const SAVESTATE_RESOURCE: &str = "savestate";
+
+/// Save the machine state. If a slot is specified, the quick save file for that slot is overwritten;
+/// otherwise a new, uniquely named save state file is created.
+pub fn save_state(machine: &mut Machine, rm: &mut ResourceManager, slot: Option<usize>) -> Result<PathBuf, Error> {
+    let path = match slot {
+        Some(slot) => slot_path(rm, slot)?,
+        None => rm.get_available_filename(SAVESTATE_RESOURCE, "state", Some(SAVE_STATE_EXTENSION))?,
+    };
+    let data = machine.save_state()?;
+    fs::write(&path, data)?;
+    log::info!("Saved machine state to {:?}", path);
+    Ok(path)
+}
+
+/// Restore the machine state. If a slot is specified, the quick save file for that slot is loaded;
+/// otherwise the most recently written save state is loaded.
+pub fn load_state(machine: &mut Machine, rm: &ResourceManager, slot: Option<usize>) -> Result<PathBuf, Error> {
+    let path = match slot {
+        Some(slot) => slot_path(rm, slot)?,
+        None => latest_state(rm)?,
+    };
+    let data = fs::read(&path).map_err(|e| anyhow!("Couldn't read save state {:?}: {}", path, e))?;
+    machine.load_state(&data)?;
+    log::info!("Restored machine state from {:?}", path);
+    Ok(path)
+}
+
+fn resource_dir(rm: &ResourceManager) -> Result<PathBuf, Error> {
+    rm.resource_path(SAVESTATE_RESOURCE)
+        .ok_or_else(|| anyhow!("Resource path not found: {}", SAVESTATE_RESOURCE))
+}
+
+fn slot_path(rm: &ResourceManager, slot: usize) -> Result<PathBuf, Error> {
+    if slot == 0 || slot > QUICKSAVE_SLOTS {
+        return Err(anyhow!("Invalid quick save slot: {}", slot));
+    }
+    Ok(resource_dir(rm)?.join(format!("quicksave{}.{}", slot, SAVE_STATE_EXTENSION)))
+}
+
+fn latest_state(rm: &ResourceManager) -> Result<PathBuf, Error> {
+    fs::read_dir(resource_dir(rm)?)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(SAVE_STATE_EXTENSION))
+        })
+        .max_by_key(|path| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        })
+        .ok_or_else(|| anyhow!("No save states found"))
+}
@@ -0,0 +1,365 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::script_engine::mod.rs
+
+    Embeds a Lua interpreter to allow the emulator to be automated with
+    scripts. Scripts are given access to the CPU, memory, and execution
+    state of the machine through a small set of Lua globals:
+
+        cpu.get_register(name)        cpu.set_register(name, val)
+        cpu.step()                    cpu.run(n)
+        mem.read_byte(addr)           mem.write_byte(addr, val)
+        machine.pause()               machine.resume()
+        gui.show_message(str)
+
+    Scripts run synchronously to completion against a borrowed Machine.
+    Requests that affect the frontend (pausing, messages) are collected
+    into a ScriptOutput for the frontend to act on afterwards.
+*/
+
+use std::cell::RefCell;
+
+use anyhow::{anyhow, Error};
+use mlua::{Lua, Table};
+
+use marty_core::{
+    cpu_common::{Cpu, Register16, Register8},
+    machine::{ExecutionControl, ExecutionOperation, ExecutionState, Machine},
+};
+
+#[derive(Copy, Clone, Debug)]
+enum ScriptRegister {
+    Reg16(Register16),
+    Reg8(Register8),
+    Flags,
+}
+
+fn parse_register(name: &str) -> Option<ScriptRegister> {
+    let reg = match name.to_ascii_uppercase().as_str() {
+        "AX" => ScriptRegister::Reg16(Register16::AX),
+        "BX" => ScriptRegister::Reg16(Register16::BX),
+        "CX" => ScriptRegister::Reg16(Register16::CX),
+        "DX" => ScriptRegister::Reg16(Register16::DX),
+        "SP" => ScriptRegister::Reg16(Register16::SP),
+        "BP" => ScriptRegister::Reg16(Register16::BP),
+        "SI" => ScriptRegister::Reg16(Register16::SI),
+        "DI" => ScriptRegister::Reg16(Register16::DI),
+        "CS" => ScriptRegister::Reg16(Register16::CS),
+        "DS" => ScriptRegister::Reg16(Register16::DS),
+        "ES" => ScriptRegister::Reg16(Register16::ES),
+        "SS" => ScriptRegister::Reg16(Register16::SS),
+        "IP" | "PC" => ScriptRegister::Reg16(Register16::PC),
+        "AL" => ScriptRegister::Reg8(Register8::AL),
+        "AH" => ScriptRegister::Reg8(Register8::AH),
+        "BL" => ScriptRegister::Reg8(Register8::BL),
+        "BH" => ScriptRegister::Reg8(Register8::BH),
+        "CL" => ScriptRegister::Reg8(Register8::CL),
+        "CH" => ScriptRegister::Reg8(Register8::CH),
+        "DL" => ScriptRegister::Reg8(Register8::DL),
+        "DH" => ScriptRegister::Reg8(Register8::DH),
+        "FLAGS" => ScriptRegister::Flags,
+        _ => return None,
+    };
+    Some(reg)
+}
+
+/// Execute a single instruction, regardless of the frontend's current execution state.
+fn step_machine(machine: &mut Machine) {
+    let mut exec_control = ExecutionControl::new();
+    exec_control.set_state(ExecutionState::Paused);
+    exec_control.set_op(ExecutionOperation::Step);
+    machine.run(1, &mut exec_control);
+}
+
+/// Results of a script run that the frontend is responsible for acting on.
+#[derive(Default)]
+pub struct ScriptOutput {
+    /// Messages passed to gui.show_message(), in order.
+    pub messages: Vec<String>,
+    /// The last execution operation requested via machine.pause() or machine.resume(), if any.
+    pub exec_op:  Option<ExecutionOperation>,
+}
+
+pub struct LuaScriptEngine {
+    lua: Lua,
+}
+
+impl Default for LuaScriptEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LuaScriptEngine {
+    pub fn new() -> Self {
+        Self { lua: Lua::new() }
+    }
+
+    /// Run the provided Lua source against the specified machine.
+    /// The Lua state persists between runs, so globals defined by one script are visible to the next.
+    pub fn run(&mut self, machine: &mut Machine, source: &str) -> Result<ScriptOutput, Error> {
+        let machine = RefCell::new(machine);
+        let output = RefCell::new(ScriptOutput::default());
+
+        self.lua
+            .scope(|scope| {
+                let globals = self.lua.globals();
+
+                // cpu table
+                let cpu: Table = self.lua.create_table()?;
+                cpu.set(
+                    "get_register",
+                    scope.create_function(|_, name: String| {
+                        let machine = machine.borrow();
+                        match parse_register(&name) {
+                            Some(ScriptRegister::Reg16(reg)) => Ok(machine.cpu().get_register16(reg)),
+                            Some(ScriptRegister::Reg8(reg)) => Ok(machine.cpu().get_register8(reg) as u16),
+                            Some(ScriptRegister::Flags) => Ok(machine.cpu().get_flags()),
+                            None => Err(mlua::Error::RuntimeError(format!("Invalid register: {}", name))),
+                        }
+                    })?,
+                )?;
+                cpu.set(
+                    "set_register",
+                    scope.create_function(|_, (name, value): (String, u16)| {
+                        let mut machine = machine.borrow_mut();
+                        match parse_register(&name) {
+                            Some(ScriptRegister::Reg16(reg)) => machine.cpu_mut().set_register16(reg, value),
+                            Some(ScriptRegister::Reg8(reg)) => machine.cpu_mut().set_register8(reg, value as u8),
+                            Some(ScriptRegister::Flags) => machine.cpu_mut().set_flags(value),
+                            None => return Err(mlua::Error::RuntimeError(format!("Invalid register: {}", name))),
+                        }
+                        Ok(())
+                    })?,
+                )?;
+                cpu.set(
+                    "step",
+                    scope.create_function(|_, ()| {
+                        step_machine(&mut machine.borrow_mut());
+                        Ok(())
+                    })?,
+                )?;
+                cpu.set(
+                    "run",
+                    scope.create_function(|_, count: u32| {
+                        let mut machine = machine.borrow_mut();
+                        for _ in 0..count {
+                            step_machine(&mut machine);
+                        }
+                        Ok(())
+                    })?,
+                )?;
+                globals.set("cpu", cpu)?;
+
+                // mem table
+                let mem: Table = self.lua.create_table()?;
+                mem.set(
+                    "read_byte",
+                    scope.create_function(|_, addr: u32| {
+                        machine
+                            .borrow()
+                            .bus()
+                            .peek_u8(addr as usize)
+                            .map_err(|e| mlua::Error::RuntimeError(format!("{}", e)))
+                    })?,
+                )?;
+                mem.set(
+                    "write_byte",
+                    scope.create_function(|_, (addr, value): (u32, u8)| {
+                        machine
+                            .borrow_mut()
+                            .bus_mut()
+                            .write_u8(addr as usize, value, 0)
+                            .map(|_| ())
+                            .map_err(|e| mlua::Error::RuntimeError(format!("{}", e)))
+                    })?,
+                )?;
+                globals.set("mem", mem)?;
+
+                // machine table
+                let machine_tbl: Table = self.lua.create_table()?;
+                machine_tbl.set(
+                    "pause",
+                    scope.create_function(|_, ()| {
+                        output.borrow_mut().exec_op = Some(ExecutionOperation::Pause);
+                        Ok(())
+                    })?,
+                )?;
+                machine_tbl.set(
+                    "resume",
+                    scope.create_function(|_, ()| {
+                        output.borrow_mut().exec_op = Some(ExecutionOperation::Run);
+                        Ok(())
+                    })?,
+                )?;
+                globals.set("machine", machine_tbl)?;
+
+                // gui table
+                let gui: Table = self.lua.create_table()?;
+                gui.set(
+                    "show_message",
+                    scope.create_function(|_, msg: String| {
+                        output.borrow_mut().messages.push(msg);
+                        Ok(())
+                    })?,
+                )?;
+                globals.set("gui", gui)?;
+
+                let result = self.lua.load(source).set_name("script").exec();
+
+                // Scoped functions are invalidated when the scope ends; remove them so that the
+                // persistent Lua state never holds dangling references.
+                for name in ["cpu", "mem", "machine", "gui"] {
+                    globals.set(name, mlua::Value::Nil)?;
+                }
+                result
+            })
+            .map_err(|e| anyhow!("Script error: {}", e))?;
+
+        Ok(output.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use marty_core::{
+        coreconfig::CoreConfig,
+        cpu_common::TraceMode,
+        cpu_validator::ValidatorType,
+        machine::{MachineBuilder, MachineRomManifest},
+        machine_preset::MachinePreset,
+        machine_types::{MachineType, OnHaltBehavior},
+    };
+
+    /// A core configuration with every optional feature turned off.
+    struct TestCoreConfig;
+
+    impl CoreConfig for TestCoreConfig {
+        fn get_base_dir(&self) -> PathBuf {
+            PathBuf::new()
+        }
+        fn get_machine_type(&self) -> MachineType {
+            MachineType::Ibm5160
+        }
+        fn get_audio_enabled(&self) -> bool {
+            false
+        }
+        fn get_machine_noroms(&self) -> bool {
+            false
+        }
+        fn get_machine_turbo(&self) -> bool {
+            false
+        }
+        fn get_machine_fast_boot(&self) -> bool {
+            false
+        }
+        fn get_keyboard_layout(&self) -> Option<String> {
+            None
+        }
+        fn get_keyboard_debug(&self) -> bool {
+            false
+        }
+        fn get_validator_type(&self) -> Option<ValidatorType> {
+            None
+        }
+        fn get_validator_trace_file(&self) -> Option<PathBuf> {
+            None
+        }
+        fn get_validator_baud(&self) -> Option<u32> {
+            None
+        }
+        fn get_cpu_trace_mode(&self) -> Option<TraceMode> {
+            None
+        }
+        fn get_cpu_trace_on(&self) -> bool {
+            false
+        }
+        fn get_cpu_trace_file(&self) -> Option<PathBuf> {
+            None
+        }
+        fn get_title_hacks(&self) -> bool {
+            false
+        }
+        fn get_patch_enabled(&self) -> bool {
+            false
+        }
+        fn get_halt_behavior(&self) -> OnHaltBehavior {
+            OnHaltBehavior::Continue
+        }
+        fn get_terminal_port(&self) -> Option<u16> {
+            None
+        }
+        fn get_post_code_ports(&self) -> Vec<u16> {
+            Vec::new()
+        }
+    }
+
+    fn test_machine() -> Machine {
+        let mut config = MachinePreset::IbmPcXt5160.to_machine_config();
+        config.video.clear();
+        let core_config = TestCoreConfig;
+        MachineBuilder::new()
+            .with_core_config(Box::new(&core_config))
+            .with_machine_config(&config)
+            .with_roms(MachineRomManifest::default())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn scripts_access_registers_and_memory() {
+        let mut machine = test_machine();
+        let mut engine = LuaScriptEngine::new();
+
+        let output = engine
+            .run(
+                &mut machine,
+                r#"
+                cpu.set_register("AX", 0x1234)
+                cpu.set_register("cl", 0x56)
+                mem.write_byte(0x500, cpu.get_register("ah"))
+                mem.write_byte(0x501, mem.read_byte(0x500) + 1)
+                gui.show_message(string.format("%04X", cpu.get_register("ax")))
+                machine.pause()
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(machine.cpu().get_register16(Register16::AX), 0x1234);
+        assert_eq!(machine.cpu().get_register8(Register8::CL), 0x56);
+        assert_eq!(machine.bus().peek_u8(0x500).unwrap(), 0x12);
+        assert_eq!(machine.bus().peek_u8(0x501).unwrap(), 0x13);
+        assert_eq!(output.messages, vec!["1234".to_string()]);
+        assert!(matches!(output.exec_op, Some(ExecutionOperation::Pause)));
+
+        // Invalid register names are reported as script errors.
+        assert!(engine.run(&mut machine, r#"cpu.get_register("XX")"#).is_err());
+    }
+}
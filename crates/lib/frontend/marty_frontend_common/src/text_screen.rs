@@ -0,0 +1,129 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::text_screen.rs
+
+    Conversion of a video card's text mode screen to host text.
+*/
+
+//! The characters of a text mode screen are converted from code page 437 to Unicode, so that
+//! box-drawing and other graphical characters survive being copied to the clipboard or a file.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use marty_core::{device_traits::videocard::TextBuffer, devices::rtc::RtcDateTime};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// The resource path that saved text screens are written to.
+pub const TEXT_SCREEN_RESOURCE: &str = "textscreen";
+
+/// Unicode equivalents of the control characters 00h-1Fh, which the video card draws as glyphs.
+/// NUL is drawn blank, so it becomes a space.
+#[rustfmt::skip]
+const CP437_LOW: [char; 32] = [
+    ' ', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+/// Unicode equivalents of the characters 80h-FFh. FFh is drawn blank, so it becomes a space.
+#[rustfmt::skip]
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', ' ',
+];
+
+/// Convert a code page 437 character to its Unicode equivalent.
+pub fn cp437_to_char(byte: u8) -> char {
+    match byte {
+        0x00..=0x1F => CP437_LOW[byte as usize],
+        0x7F => '⌂',
+        0x80..=0xFF => CP437_HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+/// Convert a text buffer to a string, one line per row with trailing spaces trimmed.
+pub fn text_buffer_to_string(buffer: &TextBuffer) -> String {
+    let mut text = String::with_capacity(buffer.cells.len() + buffer.rows);
+    for row in buffer.rows() {
+        let line: String = row.iter().map(|&(ch, _attr)| cp437_to_char(ch)).collect();
+        text.push_str(line.trim_end_matches(' '));
+        text.push('\n');
+    }
+    text
+}
+
+/// Return a filename for a saved text screen, stamped with the current UTC date and time.
+pub fn text_screen_filename() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let t = RtcDateTime::from_unix(secs);
+    format!(
+        "textscreen_{:04}{:02}{:02}_{:02}{:02}{:02}.txt",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+/// Save a text screen to a timestamped file in the specified directory, returning its path.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_text_screen(dir: &Path, text: &str) -> Result<PathBuf, Error> {
+    let path = dir.join(text_screen_filename());
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_buffer_converts_to_utf8() {
+        let row = |s: &[u8]| s.iter().map(|&ch| (ch, 0x07)).collect::<Vec<_>>();
+        let mut cells = row(b"\xC9\xCD\xBB  ");
+        cells.extend(row(b"\xBAA\xBA \x00"));
+        cells.extend(row(&[0x20; 5]));
+        let buffer = TextBuffer {
+            columns: 5,
+            rows: 3,
+            cells,
+        };
+
+        assert_eq!(text_buffer_to_string(&buffer), "╔═╗\n║A║\n\n");
+        assert_eq!(cp437_to_char(0x01), '☺');
+        assert_eq!(cp437_to_char(0x7F), '⌂');
+        assert_eq!(cp437_to_char(0xE1), 'ß');
+        assert_eq!(cp437_to_char(0xFF), ' ');
+    }
+}
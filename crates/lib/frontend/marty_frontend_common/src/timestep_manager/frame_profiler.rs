@@ -0,0 +1,230 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::timestep_manager::frame_profiler.rs
+
+    Per-frame host time profiler, used to find out which part of the emulator
+    is keeping it from running at full speed.
+
+*/
+
+use marty_common::types::history_buffer::HistoryBuffer;
+use marty_core::bus::DeviceProfile;
+use web_time::{Duration, Instant};
+
+pub const PROFILE_HISTORY_LEN: usize = 300; // Number of frames of profile history to keep
+
+/// The phases of a frame that the profiler measures. Device tick times are collected separately by
+/// the core's DeviceProfile.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProfilePhase {
+    Emulation, // Machine::run(), including CPU and device ticks
+    Composite, // Video card buffer to display buffer rendering
+    Render,    // Scaler and display backend
+    Gui,       // Drawing the egui interface
+}
+
+/// Host time spent in each phase of a single emulated frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameProfile {
+    pub emulation: Duration,
+    pub devices: DeviceProfile,
+    pub composite: Duration,
+    pub render: Duration,
+    pub gui: Duration,
+    pub host_time: Duration,     // Wall time between the end of the previous frame and this one
+    pub emulated_time: Duration, // Time elapsed from the emulated CPU's perspective
+}
+
+impl FrameProfile {
+    /// Time spent emulating the CPU. This is whatever time in Machine::run() wasn't spent ticking devices.
+    pub fn cpu(&self) -> Duration {
+        self.emulation.saturating_sub(self.devices.total())
+    }
+
+    /// Total time spent in all measured phases.
+    pub fn total(&self) -> Duration {
+        self.emulation + self.composite + self.render + self.gui
+    }
+}
+
+/// Accumulates host time per phase for the frame in progress, and keeps a rolling history of completed
+/// frames. The frame in progress and the history form a double buffer - the GUI only ever reads a
+/// snapshot of completed frames, so reading it never has to wait on the emulator.
+pub struct FrameProfiler {
+    enabled: bool,
+    current: FrameProfile,
+    history: HistoryBuffer<FrameProfile>,
+    last_frame_instant: Instant,
+    last_cpu_cycles: u64,
+}
+
+impl Default for FrameProfiler {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            current: FrameProfile::default(),
+            history: HistoryBuffer::new(PROFILE_HISTORY_LEN),
+            last_frame_instant: Instant::now(),
+            last_cpu_cycles: 0,
+        }
+    }
+}
+
+impl FrameProfiler {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable the profiler. Changing state discards any collected history.
+    pub fn set_enabled(&mut self, state: bool) {
+        if state != self.enabled {
+            self.enabled = state;
+            self.current = FrameProfile::default();
+            self.history.clear();
+            self.last_frame_instant = Instant::now();
+        }
+    }
+
+    /// Begin timing a phase. Returns None if the profiler is disabled, so that a disabled profiler
+    /// costs nothing more than a branch.
+    #[inline]
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Stop timing a phase started with start(), and add the elapsed time to the frame in progress.
+    #[inline]
+    pub fn end(&mut self, phase: ProfilePhase, start: Option<Instant>) {
+        if let Some(start) = start {
+            let elapsed = start.elapsed();
+            match phase {
+                ProfilePhase::Emulation => self.current.emulation += elapsed,
+                ProfilePhase::Composite => self.current.composite += elapsed,
+                ProfilePhase::Render => self.current.render += elapsed,
+                ProfilePhase::Gui => self.current.gui += elapsed,
+            }
+        }
+    }
+
+    /// Complete the frame in progress and add it to the history.
+    /// `cpu_cycles` is the machine's running cycle count, used to calculate how much emulated time passed.
+    pub fn end_frame(&mut self, devices: Option<DeviceProfile>, cpu_cycles: u64, cpu_mhz: f64) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let mut frame = std::mem::take(&mut self.current);
+        frame.devices = devices.unwrap_or_default();
+        frame.host_time = now - self.last_frame_instant;
+        // The cycle count is reset along with the machine, so don't trust a count that went backwards.
+        if cpu_cycles >= self.last_cpu_cycles && cpu_mhz > 0.0 {
+            frame.emulated_time =
+                Duration::from_secs_f64((cpu_cycles - self.last_cpu_cycles) as f64 / (cpu_mhz * 1_000_000.0));
+        }
+
+        self.history.push(frame);
+        self.last_frame_instant = now;
+        self.last_cpu_cycles = cpu_cycles;
+    }
+
+    /// Return a copy of the completed frame history, oldest first.
+    pub fn snapshot(&self) -> Vec<FrameProfile> {
+        self.history.as_vec()
+    }
+}
+
+/// Calculate the emulation speed as a ratio of emulated time to host time over the given frames.
+/// A ratio of 1.0 is real-time speed.
+pub fn speed_ratio(frames: &[FrameProfile]) -> f64 {
+    let host: Duration = frames.iter().map(|f| f.host_time).sum();
+    let emulated: Duration = frames.iter().map(|f| f.emulated_time).sum();
+    if host.is_zero() {
+        0.0
+    }
+    else {
+        emulated.as_secs_f64() / host.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_profiler_records_nothing() {
+        let mut profiler = FrameProfiler::new();
+        let start = profiler.start();
+        assert!(start.is_none());
+        profiler.end(ProfilePhase::Emulation, start);
+        profiler.end_frame(None, 1000, 4.77);
+        assert!(profiler.snapshot().is_empty());
+    }
+
+    #[test]
+    fn frames_accumulate_and_roll() {
+        let mut profiler = FrameProfiler::new();
+        profiler.set_enabled(true);
+
+        profiler.current.emulation = Duration::from_millis(10);
+        profiler.current.gui = Duration::from_millis(2);
+        profiler.end_frame(None, 1_000_000, 1.0);
+
+        let frames = profiler.snapshot();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].cpu(), Duration::from_millis(10));
+        assert_eq!(frames[0].total(), Duration::from_millis(12));
+        assert_eq!(frames[0].emulated_time, Duration::from_secs(1));
+
+        // The next frame starts from zero.
+        profiler.end_frame(None, 1_000_000, 1.0);
+        assert_eq!(profiler.snapshot()[1].total(), Duration::ZERO);
+
+        for _ in 0..PROFILE_HISTORY_LEN {
+            profiler.end_frame(None, 1_000_000, 1.0);
+        }
+        assert_eq!(profiler.snapshot().len(), PROFILE_HISTORY_LEN);
+
+        // Disabling the profiler discards its history.
+        profiler.set_enabled(false);
+        assert!(profiler.snapshot().is_empty());
+    }
+
+    #[test]
+    fn speed_ratio_compares_emulated_to_host_time() {
+        let frame = FrameProfile {
+            host_time: Duration::from_millis(20),
+            emulated_time: Duration::from_millis(10),
+            ..Default::default()
+        };
+        assert_eq!(speed_ratio(&[frame.clone(), frame]), 0.5);
+        assert_eq!(speed_ratio(&[]), 0.0);
+    }
+}
@@ -30,6 +30,8 @@
 
 */
 
+pub mod frame_profiler;
+
 use marty_common::types::history_buffer::HistoryBuffer;
 use std::{cell::Cell, default::Default, thread};
 use web_time::{Duration, Instant};
@@ -41,10 +41,14 @@ pub enum HotkeyEvent {
     CtrlAltDel,
     Reboot,
     Screenshot,
+    CopyTextScreen,
     ToggleGui,
     ToggleFullscreen,
     DebugStep,
     DebugStepOver,
+    QuickSave,
+    QuickLoad,
+    ToggleTurbo,
     JoyToggle,
     JoyButton1,
     JoyButton2,
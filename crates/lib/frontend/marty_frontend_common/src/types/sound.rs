@@ -37,6 +37,10 @@ pub struct SoundSourceInfo {
     pub channels: u16,
     pub sample_ct: u64,
     pub latency_ms: f32,
+    pub target_latency_ms: f32,
+    pub rate_adjust: f32,
+    pub underruns: u64,
+    pub overruns: u64,
     pub volume: f32,
     pub muted: bool,
     pub len: usize,
@@ -45,9 +45,12 @@ use std::{
     ffi::OsString,
     fmt::Display,
     fs::File,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+/// Extension appended to a VHD's filename to name its copy-on-write overlay file.
+pub const VHD_OVERLAY_EXTENSION: &str = "cow";
+
 #[derive(Debug)]
 pub enum VhdManagerError {
     DirNotFound,
@@ -319,6 +322,61 @@ impl VhdManager {
         Err(VhdManagerError::FileNotFound)
     }
 
+    /// Return the image index of the VHD loaded into the specified drive.
+    pub fn get_drive_image_idx(&self, drive: usize) -> Option<usize> {
+        self.drives_loaded
+            .get(&drive)
+            .and_then(|path| self.image_map.get(path))
+            .copied()
+    }
+
+    /// Return the path of the VHD loaded into the specified drive.
+    pub fn get_drive_path(&self, drive: usize) -> Option<&PathBuf> {
+        self.drives_loaded.get(&drive)
+    }
+
+    /// Return the path of the copy-on-write overlay file for the specified VHD path.
+    pub fn overlay_path(vhd_path: &Path) -> PathBuf {
+        let mut overlay_name = vhd_path.as_os_str().to_os_string();
+        overlay_name.push(".");
+        overlay_name.push(VHD_OVERLAY_EXTENSION);
+        PathBuf::from(overlay_name)
+    }
+
+    /// Open the copy-on-write overlay file for the VHD loaded into the specified drive, creating
+    /// it if it doesn't exist. If `truncate` is set, any existing overlay contents are discarded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_overlay_file(&self, drive: usize, truncate: bool) -> Result<File, VhdManagerError> {
+        let vhd_path = self.drives_loaded.get(&drive).ok_or(VhdManagerError::InvalidDrive)?;
+        File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(truncate)
+            .open(Self::overlay_path(vhd_path))
+            .map_err(|e| {
+                log::error!("open_overlay_file(): error opening file: {}", e);
+                VhdManagerError::FileReadError
+            })
+    }
+
+    /// Move the overlay file for the VHD loaded into the specified drive out of the way, so that
+    /// a fresh overlay can be created without losing its contents. Returns the new path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn retire_overlay_file(&self, drive: usize) -> Result<PathBuf, VhdManagerError> {
+        let vhd_path = self.drives_loaded.get(&drive).ok_or(VhdManagerError::InvalidDrive)?;
+        let overlay_path = Self::overlay_path(vhd_path);
+        let mut retired_name = overlay_path.as_os_str().to_os_string();
+        retired_name.push(".stale");
+        let retired_path = PathBuf::from(retired_name);
+
+        std::fs::rename(&overlay_path, &retired_path).map_err(|e| {
+            log::error!("retire_overlay_file(): error renaming file: {}", e);
+            VhdManagerError::FileReadError
+        })?;
+        Ok(retired_path)
+    }
+
     pub fn release_vhd(&mut self, drive: usize) {
         if let Some(image) = self.drives_loaded.remove(&drive) {
             log::debug!("Releasing VHD {:?} from drive {}", image, drive);
@@ -0,0 +1,94 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    frontend_common::wav_writer.rs
+
+    A minimal encoder for 16-bit PCM RIFF WAVE files.
+*/
+
+use std::io::{Error, ErrorKind, Write};
+
+const WAV_HEADER_LEN: u32 = 44;
+const WAV_FORMAT_PCM: u16 = 1;
+const WAV_BITS_PER_SAMPLE: u16 = 16;
+
+/// Write interleaved 16-bit PCM `samples` to `writer` as a complete WAV file.
+pub fn write_wav<W: Write>(writer: &mut W, sample_rate: u32, channels: u16, samples: &[i16]) -> Result<(), Error> {
+    if channels == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "WAV file must have at least one channel",
+        ));
+    }
+
+    let block_align = channels * (WAV_BITS_PER_SAMPLE / 8);
+    let byte_rate = sample_rate * block_align as u32;
+    let data_len = u32::try_from(samples.len() * 2)
+        .ok()
+        .filter(|len| *len <= u32::MAX - WAV_HEADER_LEN)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Too many samples for a WAV file"))?;
+
+    let mut header = Vec::with_capacity(WAV_HEADER_LEN as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(WAV_HEADER_LEN - 8 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&WAV_FORMAT_PCM.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&WAV_BITS_PER_SAMPLE.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    writer.write_all(&header)?;
+
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_and_data_layout() {
+        let mut out = Vec::new();
+        write_wav(&mut out, 44100, 1, &[0, 1, -1]).unwrap();
+
+        assert_eq!(out.len(), 44 + 6);
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(out[4..8].try_into().unwrap()), 36 + 6);
+        assert_eq!(&out[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(out[24..28].try_into().unwrap()), 44100);
+        assert_eq!(u32::from_le_bytes(out[28..32].try_into().unwrap()), 88200);
+        assert_eq!(&out[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(out[40..44].try_into().unwrap()), 6);
+        assert_eq!(&out[44..], &[0x00, 0x00, 0x01, 0x00, 0xFF, 0xFF]);
+    }
+}
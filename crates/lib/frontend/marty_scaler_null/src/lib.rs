@@ -76,6 +76,7 @@ impl DisplayScaler<(), (), Texture> for MartyScaler {
     fn texture_view(&self) -> &() {
         &()
     }
+    fn prepare(&self, _encoder: &mut ()) {}
     fn render(&self, _encoder: &mut (), _render_target: &()) {}
     fn render_with_renderpass(&self, _render_pass: &mut Self::NativeRenderPass) {}
 
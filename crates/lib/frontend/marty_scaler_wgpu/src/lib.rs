@@ -35,6 +35,8 @@
 
 */
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use bytemuck::{Pod, Zeroable};
 
 // Reexport trait items
@@ -65,9 +67,22 @@ struct CrtParamUniform {
     brightness: f32,
     contrast: f32,
     mono: u32,
+    scanline_intensity: f32,
+    pad0: f32,
+    pad1: f32,
+    pad2: f32,
     mono_color: [f32; 4],
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+struct PersistenceUniform {
+    persistence: f32,
+    pad0: f32,
+    pad1: f32,
+    pad2: f32,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
@@ -95,6 +110,10 @@ impl Default for CrtParamUniform {
             brightness: 1.0,
             contrast: 1.0,
             mono: 0,
+            scanline_intensity: 0.3,
+            pad0: 0.0,
+            pad1: 0.0,
+            pad2: 0.0,
             mono_color: [1.0, 1.0, 1.0, 1.0],
         }
     }
@@ -174,6 +193,50 @@ fn create_bind_group(
     })
 }
 
+fn create_persistence_bind_group(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    current_view: &wgpu::TextureView,
+    previous_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    param_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label:   Some("marty_scaler_persistence_bind_group"),
+        layout:  bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding:  0,
+                resource: wgpu::BindingResource::TextureView(current_view),
+            },
+            wgpu::BindGroupEntry {
+                binding:  1,
+                resource: wgpu::BindingResource::TextureView(previous_view),
+            },
+            wgpu::BindGroupEntry {
+                binding:  2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding:  3,
+                resource: param_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// A pair of textures at source resolution used to accumulate frames for the phosphor persistence pass.
+/// Each frame, the current frame is blended with the previous accumulation into the other texture, and
+/// the scaler then samples the newly written texture instead of the source.
+struct PersistenceTargets {
+    views: [wgpu::TextureView; 2],
+    /// Bind groups for the accumulation pass writing into views[i], reading views[1 - i].
+    pass_bind_groups: [wgpu::BindGroup; 2],
+    /// Bind groups for the scaling pass reading views[i].
+    nearest_bind_groups: [wgpu::BindGroup; 2],
+    bilinear_bind_groups: [wgpu::BindGroup; 2],
+}
+
 /// The default renderer that scales your frame to the screen size.
 pub struct MartyScaler {
     mode: ScalerMode,
@@ -212,6 +275,16 @@ pub struct MartyScaler {
     corner_radius: f32,
     mono: bool,
     mono_color: wgpu::Color,
+    scanline_intensity: f32,
+
+    persistence: f32,
+    persistence_format: wgpu::TextureFormat,
+    persistence_pipeline: wgpu::RenderPipeline,
+    persistence_bind_group_layout: wgpu::BindGroupLayout,
+    persistence_uniform_buffer: wgpu::Buffer,
+    persistence_targets: Option<PersistenceTargets>,
+    /// Index of the persistence target holding the most recently accumulated frame.
+    persistence_idx: AtomicUsize,
     #[allow(dead_code)]
     effect: ScalerEffect,
     #[allow(dead_code)]
@@ -441,11 +514,89 @@ impl MartyScaler {
             cache: None,
         });
 
+        // Create the phosphor persistence pipeline. It renders at source resolution into a texture of the same
+        // format as the source texture.
+        let persistence_shader = wgpu::include_wgsl!("./shaders/persistence.wgsl");
+        let persistence_module = device.create_shader_module(persistence_shader);
+        let persistence_format = texture.format();
+
+        let persistence_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label:    Some("marty_scaler_persistence_uniform_buffer"),
+            contents: bytemuck::bytes_of(&PersistenceUniform::default()),
+            usage:    wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type:    wgpu::TextureSampleType::Float { filterable: true },
+                multisampled:   false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let persistence_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label:   Some("marty_scaler_persistence_bind_group_layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<PersistenceUniform>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let persistence_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("marty_scaler_persistence_pipeline_layout"),
+            bind_group_layouts: &[&persistence_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let persistence_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("marty_scaler_persistence_pipeline"),
+            layout: Some(&persistence_pipeline_layout),
+            vertex: wgpu::VertexState {
+                compilation_options: Default::default(),
+                module: &persistence_module,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+            },
+            primitive,
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                compilation_options: Default::default(),
+                module: &persistence_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: persistence_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
         let fill_color = fill_color.to_wgpu_color();
 
         //println!(">>>>>> have fill color: {:?}", fill_color);
 
-        Self {
+        let mut scaler = Self {
             mode,
             texture_view,
             nearest_sampler,
@@ -489,7 +640,98 @@ impl MartyScaler {
                 b: 1.0,
                 a: 1.0,
             },
+            scanline_intensity: 0.3,
+
+            persistence: 0.0,
+            persistence_format,
+            persistence_pipeline,
+            persistence_bind_group_layout,
+            persistence_uniform_buffer,
+            persistence_targets: None,
+            persistence_idx: AtomicUsize::new(0),
             crt_params: Default::default(),
+        };
+
+        scaler.persistence_targets = Some(scaler.create_persistence_targets(device, texture));
+        scaler
+    }
+
+    /// Create the persistence accumulation textures and their bind groups, matching the size of the source
+    /// texture. These must be recreated whenever the source texture changes.
+    fn create_persistence_targets(&self, device: &wgpu::Device, texture: &wgpu::Texture) -> PersistenceTargets {
+        let format = self.persistence_format;
+        let make_view = || {
+            device
+                .create_texture(&TextureDescriptor {
+                    label: Some("marty_scaler_persistence_texture"),
+                    size: texture.size(),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    view_formats: &[format],
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let views = [make_view(), make_view()];
+
+        let pass_bind_group = |i: usize| {
+            create_persistence_bind_group(
+                device,
+                &self.persistence_bind_group_layout,
+                &self.texture_view,
+                &views[1 - i],
+                &self.nearest_sampler,
+                &self.persistence_uniform_buffer,
+            )
+        };
+        let scaler_bind_group = |i: usize, sampler: &wgpu::Sampler| {
+            create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &views[i],
+                sampler,
+                &self.transform_uniform_buffer,
+                &self.params_uniform_buffer,
+            )
+        };
+
+        PersistenceTargets {
+            pass_bind_groups: [pass_bind_group(0), pass_bind_group(1)],
+            nearest_bind_groups: [
+                scaler_bind_group(0, &self.nearest_sampler),
+                scaler_bind_group(1, &self.nearest_sampler),
+            ],
+            bilinear_bind_groups: [
+                scaler_bind_group(0, &self.bilinear_sampler),
+                scaler_bind_group(1, &self.bilinear_sampler),
+            ],
+            views,
+        }
+    }
+
+    /// Return the bind group the scaling pass should use, depending on filtering mode and whether the
+    /// persistence pass is active.
+    fn scaler_bind_group(&self) -> &wgpu::BindGroup {
+        match (&self.persistence_targets, self.persistence > 0.0) {
+            (Some(targets), true) => {
+                let idx = self.persistence_idx.load(Ordering::Relaxed);
+                if self.bilinear {
+                    &targets.bilinear_bind_groups[idx]
+                }
+                else {
+                    &targets.nearest_bind_groups[idx]
+                }
+            }
+            _ => {
+                if self.bilinear {
+                    &self.bilinear_bind_group
+                }
+                else {
+                    &self.nearest_bind_group
+                }
+            }
         }
     }
 
@@ -561,6 +803,10 @@ impl MartyScaler {
             brightness: self.brightness,
             contrast: self.contrast,
             mono: self.mono as u32,
+            scanline_intensity: self.scanline_intensity,
+            pad0: 0.0,
+            pad1: 0.0,
+            pad2: 0.0,
             mono_color: MartyColor::from(self.mono_color).into(),
         };
 
@@ -595,6 +841,16 @@ impl MartyScaler {
         let uniform_vec = self.get_param_uniform_bytes();
 
         queue.write_buffer(&self.params_uniform_buffer, 0, &uniform_vec);
+
+        let persistence_uniform = PersistenceUniform {
+            persistence: self.persistence,
+            ..Default::default()
+        };
+        queue.write_buffer(
+            &self.persistence_uniform_buffer,
+            0,
+            bytemuck::bytes_of(&persistence_uniform),
+        );
     }
 }
 
@@ -607,7 +863,38 @@ impl DisplayScaler<wgpu::Device, wgpu::Queue, wgpu::Texture> for MartyScaler {
         &self.texture_view
     }
 
+    fn prepare(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.persistence <= 0.0 {
+            return;
+        }
+        if let Some(targets) = &self.persistence_targets {
+            // Accumulate the current frame into the target not holding the previous frame, then flip.
+            let dst = 1 - self.persistence_idx.load(Ordering::Relaxed);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("marty_scaler persistence pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.views[dst],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load:  wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.persistence_pipeline);
+            render_pass.set_bind_group(0, &targets.pass_bind_groups[dst], &[]);
+            render_pass.draw(0..3, 0..1);
+            drop(render_pass);
+            self.persistence_idx.store(dst, Ordering::Relaxed);
+        }
+    }
+
     fn render(&self, encoder: &mut wgpu::CommandEncoder, render_target: &wgpu::TextureView) {
+        self.prepare(encoder);
+
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("marty_renderer marty_render pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -624,13 +911,7 @@ impl DisplayScaler<wgpu::Device, wgpu::Queue, wgpu::Texture> for MartyScaler {
         });
 
         render_pass.set_pipeline(&self.render_pipeline);
-
-        if self.bilinear {
-            render_pass.set_bind_group(0, &self.bilinear_bind_group, &[]);
-        }
-        else {
-            render_pass.set_bind_group(0, &self.nearest_bind_group, &[]);
-        }
+        render_pass.set_bind_group(0, self.scaler_bind_group(), &[]);
 
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
@@ -649,13 +930,7 @@ impl DisplayScaler<wgpu::Device, wgpu::Queue, wgpu::Texture> for MartyScaler {
 
     fn render_with_renderpass(&self, render_pass: &mut Self::NativeRenderPass) {
         render_pass.set_pipeline(&self.render_pipeline);
-
-        if self.bilinear {
-            render_pass.set_bind_group(0, &self.bilinear_bind_group, &[]);
-        }
-        else {
-            render_pass.set_bind_group(0, &self.nearest_bind_group, &[]);
-        }
+        render_pass.set_bind_group(0, self.scaler_bind_group(), &[]);
 
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
@@ -703,6 +978,7 @@ impl DisplayScaler<wgpu::Device, wgpu::Queue, wgpu::Texture> for MartyScaler {
             &self.transform_uniform_buffer,
             &self.params_uniform_buffer,
         );
+        self.persistence_targets = Some(self.create_persistence_targets(device, texture));
 
         //println!("screen_margin_y: {}", self.screen_margin_y);
         let matrix = ScalingMatrix::new(
@@ -751,6 +1027,7 @@ impl DisplayScaler<wgpu::Device, wgpu::Queue, wgpu::Texture> for MartyScaler {
             &self.transform_uniform_buffer,
             &self.params_uniform_buffer,
         );
+        self.persistence_targets = Some(self.create_persistence_targets(device, texture));
 
         self.screen_width = screen_width;
         self.screen_height = screen_height;
@@ -861,10 +1138,13 @@ impl DisplayScaler<wgpu::Device, wgpu::Queue, wgpu::Texture> for MartyScaler {
             ScalerOption::Scanlines {
                 enabled,
                 lines,
-                intensity: _i,
+                intensity,
+                persistence,
             } => {
                 self.scanlines = lines.unwrap_or(self.scanlines);
                 self.do_scanlines = enabled.unwrap_or(self.do_scanlines);
+                self.scanline_intensity = intensity.unwrap_or(self.scanline_intensity);
+                self.persistence = persistence.unwrap_or(self.persistence).clamp(0.0, 0.99);
                 update_uniform = true;
             }
             ScalerOption::Effect(_) => {}
@@ -53,6 +53,24 @@ pub fn color_enum_to_rgba(color: &CGAColor) -> &'static [u8; 4] {
     }
 }
 
+/// Build the MDA/Hercules color table for the specified monitor phosphor. The four intensity levels
+/// and the Hercules graphics foreground are tinted; debug colors are left unchanged.
+pub fn mda_phosphor_palette(phosphor: PhosphorType) -> [u32; 16] {
+    let tint = match phosphor {
+        PhosphorType::Color | PhosphorType::White => return *MDA_RGBA_COLORS_U32,
+        PhosphorType::Green => MDA_PHOSPHOR_GREEN,
+        PhosphorType::Amber => MDA_PHOSPHOR_AMBER,
+    };
+
+    let mut palette = *MDA_RGBA_COLORS_U32;
+    for idx in [0, 1, 2, 3, 15] {
+        let level = MDA_RGBA_COLORS[idx][0] as u32;
+        let [r, g, b] = tint.map(|c| (c as u32 * level / 0xFF) as u8);
+        palette[idx] = u32::from_le_bytes([r, g, b, 0xFF]);
+    }
+    palette
+}
+
 pub fn get_ega_gfx_color16(bits: u8) -> &'static [u8; 4] {
     #[allow(clippy::unusual_byte_groupings)]
     match bits & 0b010_111 {
@@ -35,12 +35,16 @@
     from a the composite output of the composite conversion routine. It is not
     a full NTSC simulation.
 
+    CompositeDecoder provides a faster, lookup table based artifact color
+    decoder for 1 bit per pixel, 640 pixel wide CGA graphics.
+
     See https://github.com/dbalsom/cga_artifact_color for more details on the
     implementation.
 
 */
 
 //use cgmath::{Matrix3, Vector3};
+use crate::CompositeParams;
 use glam::{Mat3, Mat3A, Vec3A};
 
 // Composite stufff
@@ -381,3 +385,192 @@ pub fn regen_sync_table(table: &mut [(f32, f32, f32)], table_len: usize) {
         table[x as usize] = (phase, phase.cos(), phase.sin());
     }
 }
+
+/// Number of hdots per color clock in 640 pixel wide CGA modes.
+const DECODER_HDOTS: usize = 4;
+/// Number of pixels in the window sampled to decode the color at each pixel.
+const DECODER_WINDOW: usize = 4;
+const DECODER_PATTERNS: usize = 1 << DECODER_WINDOW;
+
+/// A lookup table based artifact color decoder for 640 pixel wide, 1 bit per pixel CGA graphics.
+///
+/// Each output pixel is decoded from the window of four pixels spanning one color clock, starting one pixel
+/// before it. The luma of a window is its average level, and its chroma is the projection of the pixels onto
+/// the color subcarrier at the phase of each hdot. Since there are only 16 window patterns at each of 4 phases,
+/// all colors are precalculated.
+pub struct CompositeDecoder {
+    hue_offset: f32,
+    saturation: f32,
+    brightness: f32,
+    table: [[u8; 4]; DECODER_HDOTS * DECODER_PATTERNS],
+}
+
+impl Default for CompositeDecoder {
+    fn default() -> Self {
+        Self::new(0.0, 1.0, 1.0)
+    }
+}
+
+impl CompositeDecoder {
+    /// Create a new decoder. `hue_offset` is specified in degrees.
+    pub fn new(hue_offset: f32, saturation: f32, brightness: f32) -> Self {
+        let mut decoder = Self {
+            hue_offset,
+            saturation,
+            brightness,
+            table: [[0; 4]; DECODER_HDOTS * DECODER_PATTERNS],
+        };
+        decoder.regen_table();
+        decoder
+    }
+
+    /// Create a new decoder from the composite adjustment parameters set in the GUI.
+    pub fn from_params(params: &CompositeParams) -> Self {
+        Self::new(params.hue as f32, params.sat as f32, params.luma as f32)
+    }
+
+    pub fn set_params(&mut self, hue_offset: f32, saturation: f32, brightness: f32) {
+        self.hue_offset = hue_offset;
+        self.saturation = saturation;
+        self.brightness = brightness;
+        self.regen_table();
+    }
+
+    fn regen_table(&mut self) {
+        let adjust_mat = make_adjust_mat(self.hue_offset.to_radians(), self.saturation, self.brightness);
+
+        for phase in 0..DECODER_HDOTS {
+            for pattern in 0..DECODER_PATTERNS {
+                let mut yiq = Vec3A::new(0.0, 0.0, 0.0);
+                for n in 0..DECODER_WINDOW {
+                    // The most significant bit of the pattern is the leftmost pixel of the window.
+                    if pattern & (1 << (DECODER_WINDOW - 1 - n)) == 0 {
+                        continue;
+                    }
+                    let hdot = (phase + DECODER_HDOTS - 1 + n) % DECODER_HDOTS;
+                    let angle = hdot as f32 * TAU / DECODER_HDOTS as f32;
+                    yiq.x += 1.0;
+                    yiq.y += 2.0 * angle.cos();
+                    yiq.z += 2.0 * angle.sin();
+                }
+                yiq = yiq / DECODER_WINDOW as f32;
+
+                let rgb = YIQ2RGB * adjust(yiq, adjust_mat);
+                self.table[phase * DECODER_PATTERNS + pattern] = [
+                    to_u8_clamped(rgb.x * 255.0),
+                    to_u8_clamped(rgb.y * 255.0),
+                    to_u8_clamped(rgb.z * 255.0),
+                    0xFF,
+                ];
+            }
+        }
+    }
+
+    /// Decode a single scanline. `src` contains packed pixels, 8 per byte, most significant bit first, as in
+    /// CGA video memory. `dst` receives RGBA pixels and must hold at least `src.len() * 8 * 4` bytes.
+    pub fn decode_line(&self, src: &[u8], dst: &mut [u8]) {
+        self.decode_with(src.len() * 8, 0, |x| (src[x / 8] >> (7 - (x % 8))) & 1, dst);
+    }
+
+    /// Decode a single scanline of a direct mode framebuffer, which holds one color index per hdot. Any
+    /// non-zero index is treated as a lit pixel. `phase` is the color clock phase of the first hdot.
+    /// `dst` receives RGBA pixels and must hold at least `src.len() * 4` bytes.
+    pub fn decode_hdots(&self, src: &[u8], phase: usize, dst: &mut [u8]) {
+        self.decode_with(src.len(), phase, |x| (src[x] != 0) as u8, dst);
+    }
+
+    fn decode_with(&self, width: usize, phase: usize, lit: impl Fn(usize) -> u8, dst: &mut [u8]) {
+        let pixel = |x: isize| -> usize {
+            if x < 0 || x >= width as isize {
+                0
+            }
+            else {
+                lit(x as usize) as usize
+            }
+        };
+
+        for (x, out) in dst.chunks_exact_mut(4).take(width).enumerate() {
+            let mut pattern = 0;
+            for n in 0..DECODER_WINDOW {
+                pattern = (pattern << 1) | pixel(x as isize - 1 + n as isize);
+            }
+            out.copy_from_slice(&self.table[((x + phase) % DECODER_HDOTS) * DECODER_PATTERNS + pattern]);
+        }
+    }
+
+    /// Decode an image of `height` scanlines, each `src_stride` bytes of packed pixels, into RGBA.
+    pub fn decode(&self, src: &[u8], src_stride: usize, height: usize, dst: &mut [u8]) {
+        let dst_stride = src_stride * 8 * 4;
+        for (src_line, dst_line) in src
+            .chunks_exact(src_stride)
+            .zip(dst.chunks_exact_mut(dst_stride))
+            .take(height)
+        {
+            self.decode_line(src_line, dst_line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CGA_WHITE;
+
+    fn decode_pattern(decoder: &CompositeDecoder, byte: u8) -> [u8; 4] {
+        let src = [byte; 80];
+        let mut dst = vec![0; 640 * 4];
+        decoder.decode_line(&src, &mut dst);
+        // Sample away from the edges of the line.
+        let x = 320;
+        [dst[x * 4], dst[x * 4 + 1], dst[x * 4 + 2], dst[x * 4 + 3]]
+    }
+
+    #[test]
+    fn composite_decoder_artifact_colors() {
+        let decoder = CompositeDecoder::default();
+
+        // Solid black and white have no chroma.
+        assert_eq!(decode_pattern(&decoder, 0x00), [0, 0, 0, 0xFF]);
+        assert!(decode_pattern(&decoder, 0xFF)[..3].iter().all(|c| *c >= 254));
+
+        // A pattern repeating every color clock produces a saturated artifact color.
+        let color = decode_pattern(&decoder, 0x33);
+        let (min, max) = (color[..3].iter().min().unwrap(), color[..3].iter().max().unwrap());
+        assert!(max - min > 64, "expected a saturated color, got {:?}", color);
+
+        // Every pixel of the pattern decodes to the same color regardless of its phase.
+        let src = [0x33; 80];
+        let mut dst = vec![0; 640 * 4];
+        decoder.decode_line(&src, &mut dst);
+        assert!(dst[8 * 4..632 * 4].chunks_exact(4).all(|p| *p == color));
+
+        // Removing saturation produces gray, and shifting hue changes the color.
+        let gray = decode_pattern(&CompositeDecoder::new(0.0, 0.0, 1.0), 0x33);
+        assert_eq!(gray[0], gray[1]);
+        assert_eq!(gray[1], gray[2]);
+        assert_ne!(decode_pattern(&CompositeDecoder::new(90.0, 1.0, 1.0), 0x33), color);
+    }
+
+    #[test]
+    fn composite_decoder_hdots_match_packed_pixels() {
+        let decoder = CompositeDecoder::default();
+        let packed = [0x33, 0x0F, 0xA5, 0x00, 0xFF, 0x81, 0x6C, 0x3C];
+        let hdots: Vec<u8> = packed
+            .iter()
+            .flat_map(|byte| (0..8).map(move |bit| ((byte >> (7 - bit)) & 1) * CGA_WHITE))
+            .collect();
+
+        let mut from_packed = vec![0; 64 * 4];
+        let mut from_hdots = vec![0; 64 * 4];
+        decoder.decode_line(&packed, &mut from_packed);
+        decoder.decode_hdots(&hdots, 0, &mut from_hdots);
+        assert_eq!(from_packed, from_hdots);
+
+        // Starting a color clock later shifts every hdot by one full color clock, which leaves the colors
+        // unchanged; starting half a clock later does not.
+        decoder.decode_hdots(&hdots, 4, &mut from_hdots);
+        assert_eq!(from_packed, from_hdots);
+        decoder.decode_hdots(&hdots, 2, &mut from_hdots);
+        assert_ne!(from_packed, from_hdots);
+    }
+}
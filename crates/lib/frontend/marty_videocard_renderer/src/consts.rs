@@ -45,6 +45,9 @@ pub const ATTR_BRIGHT_BG: u8 = 0b1000_0000;
 
 pub const CGA_FIELD_OFFSET: u32 = 8192;
 
+// Color index of bright white in the CGA's direct mode framebuffer.
+pub const CGA_WHITE: u8 = 0x0F;
+
 pub const FONT_SPAN: u32 = 32;
 //const FONT_W: u32 = 8;
 //const FONT_H: u32 = 8;
@@ -85,6 +88,10 @@ pub const MDA_RGBA_COLORS: &[[u8; 4]; 16] = &[
     [0xFF, 0xFF, 0xFF, 0xFF], // 3 - White (for hercules gfx mode)
 ];
 
+// Full-intensity tints for monochrome phosphors, applied to the MDA/Hercules intensity levels.
+pub const MDA_PHOSPHOR_GREEN: [u8; 3] = [0x33, 0xFF, 0x33];
+pub const MDA_PHOSPHOR_AMBER: [u8; 3] = [0xFF, 0xB0, 0x00];
+
 pub const MDA_RGBA_COLORS_U32: &[u32; 16] = &[
     u32::from_le_bytes(MDA_RGBA_COLORS[0]),
     u32::from_le_bytes(MDA_RGBA_COLORS[1]),
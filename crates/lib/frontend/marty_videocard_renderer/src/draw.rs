@@ -78,10 +78,13 @@ impl VideoRenderer {
                     input_buf,
                     self.params.aperture,
                     extents,
+                    &self.mda_palette,
                 );
             }
             VideoType::CGA | VideoType::TGA => {
                 if self.composite_enabled {
+                    // The Tandy/PCjr 640 pixel modes aren't CGA compatible, so only the CGA uses the decoder.
+                    let decoder = matches!(self.video_type, VideoType::CGA).then_some(&self.composite_decoder);
                     VideoRenderer::draw_cga_direct_composite_reenigne(
                         first_pass_buf,
                         self.params.render.w,
@@ -89,6 +92,7 @@ impl VideoRenderer {
                         input_buf,
                         &mut self.composite_bufs,
                         &mut self.composite_ctx,
+                        decoder,
                         &self.composite_params,
                         self.params.aperture,
                         extents,
@@ -475,6 +479,9 @@ impl VideoRenderer {
     /// This version uses reenigne's composite color multiplexer algorithm.
     /// It is 3x faster than my sampling algorithm and produces more accurate colors;
     /// I know when I'm beat.
+    ///
+    /// If a `decoder` is provided, scanlines in 640 pixel wide graphics mode with a white foreground are
+    /// instead decoded with the lookup table based CompositeDecoder.
     pub fn draw_cga_direct_composite_reenigne(
         frame: &mut [u8],
         w: u32,
@@ -482,6 +489,7 @@ impl VideoRenderer {
         dbuf: &[u8],
         bufs: &mut ReCompositeBuffers,
         ctx: &mut ReCompositeContext,
+        decoder: Option<&CompositeDecoder>,
         params: &CompositeParams,
         aperture: DisplayApertureType,
         extents: &DisplayExtents,
@@ -497,8 +505,29 @@ impl VideoRenderer {
             0
         };
 
+        // Mode register writes made during the frame change the composite parameters from the
+        // scanline they were made on. Color control writes are already reflected in the RGBI
+        // framebuffer, so they need no handling here.
+        let mut mode = extents.mode_byte;
+        let mut changes = extents
+            .register_changes
+            .iter()
+            .filter(|change| change.register == cga::CGA_MODE_CONTROL_REGISTER)
+            .peekable();
+        let mut recalculated = false;
+
         // Convert to composite line by line
         for y in 0..(h / 2) {
+            let mut new_mode = mode;
+            while let Some(change) = changes.next_if(|change| change.scanline <= y + aperture.y) {
+                new_mode = change.value;
+            }
+            if (new_mode & cga::CGA_MODE_ENABLE_MASK) != (mode & cga::CGA_MODE_ENABLE_MASK) {
+                ctx.recalculate(new_mode);
+                recalculated = true;
+            }
+            mode = new_mode;
+
             //let s_o (= ((y * w) ) as usize;
             let s_o = ((y + aperture.y) as usize * extents.row_stride) + (aperture.x as usize) + phase_adjust;
             let d_o = ((y * 2) as usize) * ((w as usize) * size_of::<u32>());
@@ -510,12 +539,28 @@ impl VideoRenderer {
             // Create an output slice that is 2x one scanline. We copy the first part of the scanline
             // to the last part after scanline processing to double scanlines.
             let out_slice = &mut frame[d_o..d_end];
-            let out_slice32: &mut [u32] = bytemuck::cast_slice_mut(out_slice);
 
-            ctx.composite_process(0, w as usize, bufs, in_slice, out_slice32);
+            let hires_mono = (mode & cga::CGA_MODE_HIRES_COLOR_GRAPHICS_MASK) == cga::CGA_MODE_HIRES_COLOR_GRAPHICS
+                && in_slice.iter().all(|&pixel| pixel == 0 || pixel == CGA_WHITE);
+            match decoder {
+                Some(decoder) if hires_mono => {
+                    let phase = aperture.x as usize + phase_adjust;
+                    decoder.decode_hdots(in_slice, phase, &mut out_slice[..d_span]);
+                }
+                _ => {
+                    let out_slice32: &mut [u32] = bytemuck::cast_slice_mut(out_slice);
+                    ctx.composite_process(0, w as usize, bufs, in_slice, out_slice32);
+                }
+            }
 
+            let out_slice32: &mut [u32] = bytemuck::cast_slice_mut(out_slice);
             out_slice32.copy_within(0..(w as usize), w as usize);
         }
+
+        if recalculated {
+            // Leave the context set up for the mode the next frame is expected to start with.
+            ctx.recalculate(extents.mode_byte);
+        }
     }
 
     /// Inform the CGA Direct renderer of mode changes. This is only really required by
@@ -537,13 +582,15 @@ impl VideoRenderer {
     pub fn cga_direct_param_update(&mut self, composite_params: &CompositeParams) {
         self.composite_ctx.adjust(composite_params);
         self.composite_ctx.recalculate(self.last_cga_mode);
+        self.composite_decoder = CompositeDecoder::from_params(composite_params);
 
         self.composite_params = *composite_params;
     }
 
     /// Draw the MDA card in Direct Mode.
     /// The MDA in Direct mode generates its own indexed-color framebuffer, which is
-    /// converted to 32-bit RGBA for display based on the selected display aperture profile.
+    /// converted to 32-bit RGBA for display based on the selected display aperture profile,
+    /// using the palette for the selected monitor phosphor.
     ///
     /// This version uses bytemuck to convert the framebuffer 32 bits at a time, which
     /// is much faster (benchmarked)
@@ -554,6 +601,7 @@ impl VideoRenderer {
         dbuf: &[u8],
         aperture_type: DisplayApertureType,
         extents: &DisplayExtents,
+        palette: &[u32; 16],
     ) {
         let index_mask = if let DisplayApertureType::Debug = aperture_type {
             // Allow all 16 colors for debug drawing
@@ -587,7 +635,7 @@ impl VideoRenderer {
             for x in 0..max_x {
                 let fo0 = frame_row0_offset + x as usize;
                 let dbo = dbuf_row_offset + (x + horiz_adjust) as usize;
-                frame_u32[fo0] = MDA_RGBA_COLORS_U32[(dbuf[dbo] & index_mask) as usize];
+                frame_u32[fo0] = palette[(dbuf[dbo] & index_mask) as usize];
             }
         }
     }
@@ -96,6 +96,8 @@ pub struct RendererConfigParams {
     pub display_aperture: Option<DisplayApertureType>,
     #[serde(default)]
     pub composite: bool,
+    #[serde(default)]
+    pub mono_phosphor: PhosphorType,
 }
 
 #[derive(Copy, Clone)]
@@ -170,7 +172,7 @@ impl Default for CompositeParams {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
 pub enum PhosphorType {
     #[default]
     Color,
@@ -213,11 +215,18 @@ pub struct VideoRenderer {
     composite_bufs: ReCompositeBuffers,
     last_cga_mode:  u8,
 
+    // Lookup table decoder for 640 pixel wide, 1bpp graphics
+    composite_decoder: CompositeDecoder,
+
     // Composite adjustments
     composite_enabled: bool,
     composite_params:  CompositeParams,
     resample_context:  ResampleContext,
 
+    // Monochrome phosphor emulation
+    mono_phosphor: PhosphorType,
+    mda_palette:   [u32; 16],
+
     buffer_select: BufferSelect,
 
     screenshot_buf: Vec<u8>,
@@ -263,10 +272,15 @@ impl VideoRenderer {
             composite_bufs: ReCompositeBuffers::new(),
             last_cga_mode: 0,
 
+            composite_decoder: CompositeDecoder::default(),
+
             composite_enabled: false,
             composite_params: Default::default(),
             resample_context: ResampleContext::new(),
 
+            mono_phosphor: PhosphorType::default(),
+            mda_palette: *MDA_RGBA_COLORS_U32,
+
             buffer_select: BufferSelect::Front,
 
             screenshot_buf: Vec::new(),
@@ -292,6 +306,7 @@ impl VideoRenderer {
 
     pub fn set_config_params(&mut self, cfg: &RendererConfigParams) {
         self.composite_enabled = cfg.composite;
+        self.set_mono_phosphor(cfg.mono_phosphor);
 
         if cfg.aspect_correction {
             self.set_aspect_ratio(cfg.aspect_ratio, Some(AspectCorrectionMode::Hardware));
@@ -309,6 +324,7 @@ impl VideoRenderer {
             aspect_ratio: self.aspect_ratio,
             display_aperture: Some(self.params.aperture),
             composite: self.composite_enabled,
+            mono_phosphor: self.mono_phosphor,
         }
     }
     pub fn get_params(&self) -> &VideoParams {
@@ -333,6 +349,17 @@ impl VideoRenderer {
         self.composite_enabled
     }
 
+    /// Set the phosphor color used to render monochrome (MDA and Hercules) output.
+    pub fn set_mono_phosphor(&mut self, phosphor: PhosphorType) {
+        log::debug!("Setting monochrome phosphor to {:?}", phosphor);
+        self.mono_phosphor = phosphor;
+        self.mda_palette = mda_phosphor_palette(phosphor);
+    }
+
+    pub fn get_mono_phosphor(&self) -> PhosphorType {
+        self.mono_phosphor
+    }
+
     pub fn set_aperture(&mut self, aperture: DisplayApertureType) {
         log::debug!("Setting renderer aperture to {:?}", aperture);
         self.params.aperture = aperture;
@@ -30,6 +30,9 @@
 
 */
 
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CartImage {
     pub creator: String,
     pub comment: String,
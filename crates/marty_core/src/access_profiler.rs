@@ -0,0 +1,204 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    --------------------------------------------------------------------------
+
+    access_profiler.rs
+
+    Attributes memory accesses made by the CPU, and the wait states they
+    incur, to named regions of the address space. Totals are accumulated
+    over a frame and then latched, so that a frontend can show where each
+    frame's bus time went - for example, how much of it was spent waiting
+    on video memory.
+
+*/
+
+use crate::machine_config::ProfileRegionConfig;
+
+pub const PROFILE_PAGE_SHIFT: usize = 12; // Regions are resolved per 4KB page
+pub const PROFILE_PAGE_COUNT: usize = 0x100000 >> PROFILE_PAGE_SHIFT;
+
+/// Name of the region that collects accesses to pages no configured region covers.
+pub const PROFILE_OTHER_REGION: &str = "Other";
+
+/// The regions profiled when the machine configuration does not specify any: the standard PC
+/// memory map.
+pub fn default_profile_regions() -> Vec<ProfileRegionConfig> {
+    vec![
+        ProfileRegionConfig {
+            name:  "Conventional RAM".to_string(),
+            start: 0x00000,
+            end:   0x9FFFF,
+        },
+        ProfileRegionConfig {
+            name:  "MDA VRAM".to_string(),
+            start: 0xB0000,
+            end:   0xB7FFF,
+        },
+        ProfileRegionConfig {
+            name:  "CGA VRAM".to_string(),
+            start: 0xB8000,
+            end:   0xBFFFF,
+        },
+        ProfileRegionConfig {
+            name:  "ROM".to_string(),
+            start: 0xC0000,
+            end:   0xFFFFF,
+        },
+    ]
+}
+
+/// Access totals for a single region over one frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessProfileRegion {
+    pub name:  String,
+    pub start: u32,
+    pub end:   u32,
+    pub accesses:    u64,
+    pub wait_cycles: u64,
+}
+
+pub struct AccessProfiler {
+    // Index into `regions` for each 4KB page of the address space. Looking up the page is cheaper
+    // than comparing the address against each region's range on every access.
+    page_table: Box<[u8; PROFILE_PAGE_COUNT]>,
+    regions: Vec<AccessProfileRegion>,
+    last_frame: Vec<AccessProfileRegion>,
+}
+
+impl AccessProfiler {
+    /// Create a profiler for the specified regions. Region bounds are rounded out to 4KB pages.
+    /// Where regions overlap, the page belongs to the region listed first. A final "Other" region
+    /// collects accesses to pages no region covers.
+    pub fn new(region_configs: &[ProfileRegionConfig]) -> Self {
+        // The page table stores indices as u8, and "Other" takes one.
+        let region_configs = &region_configs[..region_configs.len().min(u8::MAX as usize)];
+        let other_idx = region_configs.len() as u8;
+
+        let mut page_table = Box::new([other_idx; PROFILE_PAGE_COUNT]);
+        for (idx, region) in region_configs.iter().enumerate().rev() {
+            let first_page = (region.start as usize >> PROFILE_PAGE_SHIFT).min(PROFILE_PAGE_COUNT);
+            let last_page = (region.end as usize >> PROFILE_PAGE_SHIFT).min(PROFILE_PAGE_COUNT - 1);
+            for page in first_page..=last_page {
+                page_table[page] = idx as u8;
+            }
+        }
+
+        let mut regions: Vec<AccessProfileRegion> = region_configs
+            .iter()
+            .map(|region| AccessProfileRegion {
+                name:  region.name.clone(),
+                start: region.start,
+                end:   region.end,
+                ..Default::default()
+            })
+            .collect();
+        regions.push(AccessProfileRegion {
+            name: PROFILE_OTHER_REGION.to_string(),
+            ..Default::default()
+        });
+
+        Self {
+            page_table,
+            last_frame: regions.clone(),
+            regions,
+        }
+    }
+
+    #[inline]
+    pub fn record(&mut self, address: usize, wait_cycles: u32) {
+        let idx = self.page_table[(address >> PROFILE_PAGE_SHIFT) & (PROFILE_PAGE_COUNT - 1)];
+        let region = &mut self.regions[idx as usize];
+        region.accesses += 1;
+        region.wait_cycles += wait_cycles as u64;
+    }
+
+    /// Latch the totals accumulated since the last call and start a new frame.
+    pub fn end_frame(&mut self) {
+        for (last, current) in self.last_frame.iter_mut().zip(self.regions.iter_mut()) {
+            last.accesses = std::mem::take(&mut current.accesses);
+            last.wait_cycles = std::mem::take(&mut current.wait_cycles);
+        }
+    }
+
+    /// Return the totals for the last completed frame.
+    pub fn last_frame(&self) -> &[AccessProfileRegion] {
+        &self.last_frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accesses_are_attributed_by_page() {
+        let mut profiler = AccessProfiler::new(&default_profile_regions());
+
+        profiler.record(0x00400, 0);
+        profiler.record(0xB8000, 4);
+        profiler.record(0xBFFFF, 6);
+        profiler.record(0xA0000, 0);
+
+        // Nothing is reported until the frame ends.
+        assert!(profiler.last_frame().iter().all(|region| region.accesses == 0));
+
+        profiler.end_frame();
+        let frame = profiler.last_frame();
+        assert_eq!(frame.len(), 5);
+        assert_eq!(frame[0].accesses, 1);
+        assert_eq!(frame[2].name, "CGA VRAM");
+        assert_eq!((frame[2].accesses, frame[2].wait_cycles), (2, 10));
+        assert_eq!(frame[4].name, PROFILE_OTHER_REGION);
+        assert_eq!(frame[4].accesses, 1);
+
+        // Totals restart with each frame.
+        profiler.end_frame();
+        assert!(profiler.last_frame().iter().all(|region| region.accesses == 0));
+    }
+
+    #[test]
+    fn test_overlapping_regions_prefer_first() {
+        let regions = vec![
+            ProfileRegionConfig {
+                name:  "Video".to_string(),
+                start: 0xB8000,
+                end:   0xB8FFF,
+            },
+            ProfileRegionConfig {
+                name:  "Upper".to_string(),
+                start: 0xA0000,
+                end:   0xFFFFF,
+            },
+        ];
+        let mut profiler = AccessProfiler::new(&regions);
+        profiler.record(0xB8010, 1);
+        profiler.record(0xB9000, 1);
+        profiler.end_frame();
+
+        assert_eq!(profiler.last_frame()[0].accesses, 1);
+        assert_eq!(profiler.last_frame()[1].accesses, 1);
+    }
+}
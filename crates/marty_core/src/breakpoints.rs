@@ -30,18 +30,24 @@
 
 */
 
+use crate::cpu_common::{Cpu, Register16};
+use serde_derive::Deserialize;
+
 #[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum BreakPointType {
-    StepOver(u32),       // Breakpoint on next decoded instruction
-    Execute(u16, u16),   // Breakpoint on CS:IP
-    ExecuteOffset(u16),  // Breakpoint on *::IP
-    ExecuteFlat(u32),    // Breakpoint on CS<<4+IP
-    MemAccess(u16, u16), // Breakpoint on memory access, seg::offset
-    MemAccessFlat(u32),  // Breakpoint on memory access, seg<<4+offset
-    Interrupt(u8),       // Breakpoint on interrupt #
-    IoAccess(u16),       // Breakpoint on I/O port access
-    StartWatch(u32),     // Start stopwatch at address
-    StopWatch(u32),      // Stop stopwatch at address
+    StepOver(u32),                     // Breakpoint on next decoded instruction
+    Execute(u16, u16),                 // Breakpoint on CS:IP
+    ExecuteOffset(u16),                // Breakpoint on *::IP
+    ExecuteFlat(u32),                  // Breakpoint on CS<<4+IP
+    MemAccess(u16, u16),               // Breakpoint on memory access, seg::offset
+    MemAccessFlat(u32),                // Breakpoint on memory access, seg<<4+offset
+    Interrupt(u8),                     // Breakpoint on interrupt #
+    InterruptFunc { int: u8, ah: u8 }, // Breakpoint on interrupt # when AH matches, ie, a DOS function
+    IoAccess(u16),                     // Breakpoint on I/O port access
+    StartWatch(u32),                   // Start stopwatch at address
+    StopWatch(u32),                    // Stop stopwatch at address
+    RasterLine(u16),                   // Breakpoint when the video card's raster beam reaches scanline #
 }
 
 pub enum StopWatchType {
@@ -127,3 +133,107 @@ impl CycleStopWatch {
         self.running
     }
 }
+
+/// A built-in action performed when execution reaches a ROM hook.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    Log,       // Log a message with register context and continue
+    Skip(u16), // Skip the specified number of bytes by advancing IP
+    Ret,       // Return to the caller as if a near RET had been executed
+    Retf,      // Return to the caller as if a far RETF had been executed
+}
+
+/// A named address at which the CPU performs a HookAction without stopping. Hooks share the execute breakpoint
+/// flag in memory, but are handled by the CPU itself and are never reported as a breakpoint.
+#[derive(Clone, Debug)]
+pub struct RomHook {
+    pub name:    String,
+    pub address: u32,
+    pub action:  HookAction,
+}
+
+impl RomHook {
+    /// Perform the hook's action on the specified CPU, which must be on an instruction boundary at the hook
+    /// address. Returns true if execution was redirected, in which case the instruction at the hook address should
+    /// not be executed.
+    pub fn run<C: Cpu>(&self, cpu: &mut C) -> bool {
+        let cs = cpu.get_register16(Register16::CS);
+        let ip = cpu.get_ip();
+        match self.action {
+            HookAction::Log => {
+                log::info!(
+                    "Hook '{}' at {:04X}:{:04X}: AX={:04X} BX={:04X} CX={:04X} DX={:04X} SI={:04X} DI={:04X} \
+                     BP={:04X} SP={:04X} DS={:04X} ES={:04X} SS={:04X} FLAGS={:04X}",
+                    self.name,
+                    cs,
+                    ip,
+                    cpu.get_register16(Register16::AX),
+                    cpu.get_register16(Register16::BX),
+                    cpu.get_register16(Register16::CX),
+                    cpu.get_register16(Register16::DX),
+                    cpu.get_register16(Register16::SI),
+                    cpu.get_register16(Register16::DI),
+                    cpu.get_register16(Register16::BP),
+                    cpu.get_register16(Register16::SP),
+                    cpu.get_register16(Register16::DS),
+                    cpu.get_register16(Register16::ES),
+                    cpu.get_register16(Register16::SS),
+                    cpu.get_flags(),
+                );
+                false
+            }
+            HookAction::Skip(len) => {
+                log::debug!("Hook '{}' at {:04X}:{:04X}: skipping {} bytes", self.name, cs, ip, len);
+                Self::jump(cpu, cs, ip.wrapping_add(len));
+                true
+            }
+            HookAction::Ret => {
+                let new_ip = Self::pop(cpu);
+                log::debug!(
+                    "Hook '{}' at {:04X}:{:04X}: returning to {:04X}",
+                    self.name,
+                    cs,
+                    ip,
+                    new_ip
+                );
+                Self::jump(cpu, cs, new_ip);
+                true
+            }
+            HookAction::Retf => {
+                let new_ip = Self::pop(cpu);
+                let new_cs = Self::pop(cpu);
+                log::debug!(
+                    "Hook '{}' at {:04X}:{:04X}: returning to {:04X}:{:04X}",
+                    self.name,
+                    cs,
+                    ip,
+                    new_cs,
+                    new_ip
+                );
+                Self::jump(cpu, new_cs, new_ip);
+                true
+            }
+        }
+    }
+
+    /// Pop a word from the stack without running any bus cycles.
+    fn pop<C: Cpu>(cpu: &mut C) -> u16 {
+        let ss = cpu.get_register16(Register16::SS);
+        let sp = cpu.get_register16(Register16::SP);
+        let peek = |offset: u16| {
+            let address = (((ss as u32) << 4) + offset as u32) & 0xFFFFF;
+            cpu.bus().peek_u8(address as usize).unwrap_or(0xFF) as u16
+        };
+        let word = peek(sp) | peek(sp.wrapping_add(1)) << 8;
+        cpu.set_register16(Register16::SP, sp.wrapping_add(2));
+        word
+    }
+
+    /// Redirect execution to CS:IP, discarding the contents of the instruction queue.
+    fn jump<C: Cpu>(cpu: &mut C, cs: u16, ip: u16) {
+        cpu.flush_piq();
+        cpu.set_register16(Register16::CS, cs);
+        cpu.set_register16(Register16::PC, ip);
+    }
+}
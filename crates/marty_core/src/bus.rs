@@ -36,10 +36,13 @@
 
 #![allow(dead_code)]
 
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 
-use fxhash::FxHashMap;
-use std::{collections::VecDeque, fmt, io::Write, path::Path};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use marty_common::types::history_buffer::HistoryBuffer;
+use std::{collections::VecDeque, fmt, io::Write, ops::RangeInclusive, path::Path};
+use web_time::{Duration, Instant};
 
 #[cfg(feature = "sound")]
 use crate::device_traits::sounddevice::SoundDevice;
@@ -49,16 +52,21 @@ use crate::devices::pit::SPEAKER_SAMPLE_RATE;
 use crossbeam_channel::unbounded;
 
 use crate::{
+    access_profiler::{default_profile_regions, AccessProfileRegion, AccessProfiler},
     bytequeue::*,
     cpu_common::{CpuType, LogicAnalyzer},
-    device_traits::videocard::{
-        ClockingMode,
-        VideoCard,
-        VideoCardDispatch,
-        VideoCardId,
-        VideoCardInterface,
-        VideoCardSubType,
-        VideoType,
+    device_traits::{
+        realtimeclock::{RealTimeClock, RtcDispatch},
+        videocard::{
+            ClockingMode,
+            VideoCard,
+            VideoCardDispatch,
+            VideoCardId,
+            VideoCardInterface,
+            VideoCardSubType,
+            VideoOption,
+            VideoType,
+        },
     },
     devices::{
         a0::A0Register,
@@ -68,21 +76,49 @@ use crate::{
         fdc::FloppyController,
         game_port::GamePort,
         hdc::xtide::XtIdeController,
+        isa_card::{IsaCard, IsaSlot, MAX_ISA_SLOTS},
         keyboard::{KeyboardType, *},
         lotech_ems::LotechEmsCard,
         lpt_card::ParallelController,
         mda::MDACard,
+        mm58167::Mm58167,
         mouse::*,
         pic::*,
         pit::Pit,
+        post_code::PostCodeMonitor,
         ppi::*,
+        rtc::{Rtc, RtcDateTime},
         serial::*,
         tga::TGACard,
     },
     machine::{KeybufferEntry, MachineCheckpoint, MachinePatch},
-    machine_config::{normalize_conventional_memory, MachineConfiguration, MachineDescriptor},
-    machine_types::{EmsType, FdcType, HardDiskControllerType, MachineType, SerialControllerType, SerialMouseType},
+    machine_config::{
+        contiguous_ram_size,
+        MachineConfiguration,
+        MachineDescriptor,
+        ProfileRegionConfig,
+        RamRegionConfig,
+        WaitStateRegionConfig,
+    },
+    machine_types::{
+        EmsType,
+        FdcType,
+        HardDiskControllerType,
+        MachineType,
+        RtcType,
+        SerialControllerType,
+        SerialMouseType,
+    },
     memerror::MemError,
+    option_rom::{
+        OPTION_ROM_BLOCK_SIZE,
+        OPTION_ROM_END,
+        OPTION_ROM_SCAN_END,
+        OPTION_ROM_SCAN_STEP,
+        OPTION_ROM_SIGNATURE,
+        OPTION_ROM_START,
+    },
+    savestate::{DeviceSaveState, SaveState, SaveStateError},
     syntax_token::{SyntaxFormatType, SyntaxToken},
     tracelogger::TraceLogger,
 };
@@ -92,6 +128,8 @@ use crate::devices::adlib::AdLibCard;
 #[cfg(feature = "ega")]
 use crate::devices::ega::EGACard;
 use crate::devices::hdc::xebec::{HardDiskController, DRIVE_TYPE2_DIP};
+#[cfg(feature = "sound")]
+use crate::devices::sn76489::Sn76489;
 #[cfg(feature = "vga")]
 use crate::devices::vga::VGACard;
 #[cfg(feature = "sound")]
@@ -99,7 +137,7 @@ use crate::machine_types::SoundType;
 #[cfg(feature = "sound")]
 use crate::sound::{SoundOutputConfig, SoundSourceDescriptor};
 
-pub const NO_IO_BYTE: u8 = 0xFF; // This is the byte read from an unconnected IO address.
+pub const NO_IO_BYTE: u8 = 0xFF; // This is the byte read from an unconnected IO address by default.
 pub const OPEN_BUS_BYTE: u8 = 0xFF; // This is the byte read from an unmapped memory address.
 
 const ADDRESS_SPACE: usize = 0x10_0000;
@@ -116,6 +154,18 @@ pub const MEM_BPA_BIT: u8 = 0b0001_0000; // Bit to signify that this address is
 pub const MEM_CP_BIT: u8 = 0b0000_1000; // Bit to signify that this address is a ROM checkpoint
 pub const MEM_MMIO_BIT: u8 = 0b0000_0100; // Bit to signify that this address is MMIO mapped
 pub const MEM_SW_BIT: u8 = 0b0000_0010; // Bit to signify that this address is in a stopwatch
+pub const MEM_RAM_BIT: u8 = 0b0000_0001; // Bit to signify that this address is populated RAM
+
+pub const IVT_END: usize = 0x400; // End of the interrupt vector table
+
+pub const IO_TRACE_LEN: usize = 4096; // Number of IO accesses kept in the IO trace
+
+pub const PAGE_STATS_SHIFT: usize = 12; // Memory access statistics are collected per 4KB page
+pub const PAGE_STATS_LEN: usize = ADDRESS_SPACE >> PAGE_STATS_SHIFT;
+const WAIT_PAGE_SHIFT: usize = 12; // Wait state regions are looked up only on 4KB pages they overlap
+const WAIT_PAGE_LEN: usize = ADDRESS_SPACE >> WAIT_PAGE_SHIFT;
+
+pub const COVERAGE_SEGMENT_SIZE: usize = 0x10000; // Executed bytes are summarized per 64KB segment
 
 pub const KB_UPDATE_RATE: f64 = 5000.0; // Keyboard device update rate in microseconds
 
@@ -131,10 +181,79 @@ pub struct TimingTableEntry {
     pub us: f64,
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Specifies the CPU clock relative to the main system crystal.
+///
+/// `Ratio(ticks, cycles)` expresses CPU clocks that are not an integer factor of the system crystal,
+/// such as turbo XTs with a separate 8 or 10MHz CPU crystal: `ticks` system clock ticks elapse for every
+/// `cycles` CPU cycles.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ClockFactor {
     Divisor(u8),
     Multiplier(u8),
+    Ratio(u16, u16),
+}
+
+impl ClockFactor {
+    /// Find a ClockFactor for a CPU running at `cpu_mhz` from a system crystal of `crystal_mhz`.
+    /// An integer divisor or multiplier is used if one is within 0.1% of the requested clock.
+    pub fn from_mhz(crystal_mhz: f64, cpu_mhz: f64) -> Self {
+        const TOLERANCE: f64 = 0.001;
+        let ratio = crystal_mhz / cpu_mhz;
+        if ratio >= 1.0 {
+            let n = ratio.round();
+            if n <= u8::MAX as f64 && ((ratio - n) / ratio).abs() < TOLERANCE {
+                return ClockFactor::Divisor(n as u8);
+            }
+        }
+        else {
+            let n = (1.0 / ratio).round();
+            if n <= u8::MAX as f64 && ((1.0 / ratio - n) * ratio).abs() < TOLERANCE {
+                return ClockFactor::Multiplier(n as u8);
+            }
+        }
+        // Otherwise express the ratio with the largest denominator (up to 10000) that still fits. Rounding
+        // error is then within 100ppm, comparable to the tolerance of the crystals of the period.
+        let cycles = (u16::MAX as f64 / ratio).floor().clamp(1.0, 10_000.0);
+        let ticks = (ratio * cycles).round().clamp(1.0, u16::MAX as f64);
+        ClockFactor::Ratio(ticks as u16, cycles as u16)
+    }
+
+    /// Return the ratio of system ticks to CPU cycles as (ticks, cycles).
+    #[inline]
+    pub fn ratio(&self) -> (u32, u32) {
+        match *self {
+            ClockFactor::Divisor(n) => (n as u32, 1),
+            ClockFactor::Multiplier(n) => (1, n as u32),
+            ClockFactor::Ratio(t, c) => (t as u32, c as u32),
+        }
+    }
+
+    /// Return the CPU clock in MHz given the system crystal frequency in MHz.
+    #[inline]
+    pub fn cpu_mhz(&self, crystal_mhz: f64) -> f64 {
+        let (ticks, cycles) = self.ratio();
+        crystal_mhz * cycles as f64 / ticks as f64
+    }
+
+    /// Convert a count of CPU cycles to system ticks. Any fractional tick is discarded.
+    #[inline]
+    pub fn cycles_to_ticks(&self, cycles: u32) -> u32 {
+        match *self {
+            ClockFactor::Divisor(n) => cycles * (n as u32),
+            ClockFactor::Multiplier(n) => cycles / (n as u32),
+            ClockFactor::Ratio(t, c) => ((cycles as u64 * t as u64) / c as u64) as u32,
+        }
+    }
+
+    /// Convert a count of system ticks to CPU cycles, rounding upwards.
+    #[inline]
+    pub fn ticks_to_cycles(&self, ticks: u32) -> u32 {
+        match *self {
+            ClockFactor::Divisor(n) => (ticks + (n as u32) - 1) / (n as u32),
+            ClockFactor::Multiplier(n) => ticks * (n as u32),
+            ClockFactor::Ratio(t, c) => ((ticks as u64 * c as u64).div_ceil(t as u64)) as u32,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -158,15 +277,8 @@ impl Default for DeviceRunContext {
 
 impl DeviceRunContext {
     pub fn new(cpu_ticks: u32, factor: ClockFactor, sysclock: f64) -> Self {
-        let delta_ticks = match factor {
-            ClockFactor::Divisor(n) => (cpu_ticks + (n as u32) - 1) / (n as u32),
-            ClockFactor::Multiplier(n) => cpu_ticks * (n as u32),
-        };
-        let mhz = match factor {
-            ClockFactor::Divisor(n) => sysclock / (n as f64),
-            ClockFactor::Multiplier(n) => sysclock * (n as f64),
-        };
-        let delta_us = 1.0 / mhz * cpu_ticks as f64;
+        let delta_ticks = factor.cycles_to_ticks(cpu_ticks);
+        let delta_us = 1.0 / factor.cpu_mhz(sysclock) * cpu_ticks as f64;
 
         Self {
             delta_ticks,
@@ -246,6 +358,47 @@ pub trait MemoryMappedDevice {
     fn get_mapping(&self) -> Vec<MemRangeDescriptor>;
 }
 
+/// A device that switches banks of memory in and out of a region of the address space, such as the
+/// PCjr's video RAM page register or the Tandy 1000's extra RAM/ROM banking. Addresses are absolute.
+pub trait BankController {
+    /// Return the byte at `addr` from the currently selected bank, or None if no bank is mapped at
+    /// `addr`, in which case the read falls through to system memory.
+    fn bank_read_u8(&self, addr: usize) -> Option<u8>;
+    /// Write `val` to the currently selected bank at `addr`. Return false if no bank is mapped at
+    /// `addr`, in which case the write falls through to system memory.
+    fn bank_write_u8(&mut self, addr: usize, val: u8) -> bool;
+}
+
+/// Byte order used to interpret words in a memory dump.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WordOrder {
+    #[default]
+    LittleEndian,
+    BigEndian,
+}
+
+/// Layout of a memory dump produced by [BusInterface::dump_flat_tokens_layout].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryDumpLayout {
+    /// Bytes per row. Must be a power of two.
+    pub row_len: usize,
+    /// Show an ASCII column after the hex values.
+    pub ascii: bool,
+    /// Show 16-bit words of the given byte order instead of bytes.
+    pub words: Option<WordOrder>,
+}
+
+impl Default for MemoryDumpLayout {
+    fn default() -> Self {
+        Self {
+            row_len: 16,
+            ascii:   true,
+            words:   None,
+        }
+    }
+}
+
 pub struct MemoryDebug {
     addr:  String,
     byte:  String,
@@ -264,6 +417,87 @@ impl fmt::Display for MemoryDebug {
     }
 }
 
+/// A byte written to memory by the debugger, and the value it replaced.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DebugWrite {
+    pub address: usize,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// The region of memory an interrupt vector's handler resides in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IvtTargetRegion {
+    /// The IVT, BIOS data area or DOS communication area.
+    LowMemory,
+    /// The DOS kernel, below the first memory control block.
+    Dos,
+    /// A memory block owned by a program, such as a TSR. Holds the owner's PSP segment and name, if known.
+    Program(u16, String),
+    /// Conventional memory that could not be attributed to DOS or a program.
+    Ram,
+    /// The system BIOS ROM.
+    Bios,
+    /// An adapter or option ROM.
+    OptionRom,
+    /// Memory outside of conventional RAM and ROM.
+    Unknown,
+}
+
+impl fmt::Display for IvtTargetRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IvtTargetRegion::LowMemory => write!(f, "Low Memory"),
+            IvtTargetRegion::Dos => write!(f, "DOS"),
+            IvtTargetRegion::Program(psp, name) if name.is_empty() => write!(f, "PSP {:04X}", psp),
+            IvtTargetRegion::Program(psp, name) => write!(f, "{} (PSP {:04X})", name, psp),
+            IvtTargetRegion::Ram => write!(f, "RAM"),
+            IvtTargetRegion::Bios => write!(f, "BIOS"),
+            IvtTargetRegion::OptionRom => write!(f, "Option ROM"),
+            IvtTargetRegion::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// The instruction that last wrote to an interrupt vector.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IvtWriter {
+    pub segment: u16,
+    pub offset: u16,
+    /// The CPU instruction count at the time of the write.
+    pub instruction: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IoDirection {
+    Read,
+    Write,
+}
+
+/// A single IN or OUT performed by the CPU.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IoTraceEntry {
+    pub port: u16,
+    pub value: u8,
+    pub direction: IoDirection,
+    pub segment: u16,
+    pub offset: u16,
+    /// The CPU instruction count at the time of the access.
+    pub instruction: u64,
+    /// Whether a device claimed the port.
+    pub claimed: bool,
+}
+
+/// A decoded entry of the interrupt vector table.
+#[derive(Clone, Debug)]
+pub struct IvtEntry {
+    pub vector:     u8,
+    pub segment:    u16,
+    pub offset:     u16,
+    pub region:     IvtTargetRegion,
+    pub written_by: Option<IvtWriter>,
+}
+
 #[derive(Clone, Debug)]
 pub struct MemRangeDescriptor {
     pub address: usize,
@@ -300,8 +534,12 @@ pub enum IoDeviceType {
     Mouse,
     Ems,
     GamePort,
+    Rtc,
     Video(VideoCardId),
     Sound,
+    Sn76489,
+    PostCode,
+    IsaCard(usize),
 }
 
 pub enum IoDeviceDispatch {
@@ -381,6 +619,85 @@ pub enum MmioDeviceType {
     Rom,
     Ems,
     Cart,
+    Bank(usize),
+    IsaCard(usize),
+}
+
+/// Device groups tracked by the optional device tick profiler.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProfiledDevice {
+    Keyboard,
+    Pic,
+    Ppi,
+    Pit,
+    Storage,
+    Dma,
+    Serial,
+    Sound,
+    Video,
+    IsaCards,
+}
+
+impl ProfiledDevice {
+    pub const ALL: [ProfiledDevice; 10] = [
+        ProfiledDevice::Keyboard,
+        ProfiledDevice::Pic,
+        ProfiledDevice::Ppi,
+        ProfiledDevice::Pit,
+        ProfiledDevice::Storage,
+        ProfiledDevice::Dma,
+        ProfiledDevice::Serial,
+        ProfiledDevice::Sound,
+        ProfiledDevice::Video,
+        ProfiledDevice::IsaCards,
+    ];
+}
+
+impl fmt::Display for ProfiledDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ProfiledDevice::Keyboard => "Keyboard",
+            ProfiledDevice::Pic => "PIC",
+            ProfiledDevice::Ppi => "PPI",
+            ProfiledDevice::Pit => "PIT",
+            ProfiledDevice::Storage => "Storage",
+            ProfiledDevice::Dma => "DMA",
+            ProfiledDevice::Serial => "Serial/Input",
+            ProfiledDevice::Sound => "Sound",
+            ProfiledDevice::Video => "Video",
+            ProfiledDevice::IsaCards => "ISA Cards",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Host time spent ticking each device group in run_devices(). Only collected when enabled
+/// via BusInterface::set_device_profiling(), as timing every device run is not free.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceProfile {
+    times: [Duration; ProfiledDevice::ALL.len()],
+}
+
+impl DeviceProfile {
+    #[inline]
+    fn start(profile: &Option<DeviceProfile>) -> Option<Instant> {
+        profile.as_ref().map(|_| Instant::now())
+    }
+
+    #[inline]
+    fn end(profile: &mut Option<DeviceProfile>, device: ProfiledDevice, start: Option<Instant>) {
+        if let (Some(profile), Some(start)) = (profile, start) {
+            profile.times[device as usize] += start.elapsed();
+        }
+    }
+
+    pub fn get(&self, device: ProfiledDevice) -> Duration {
+        self.times[device as usize]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.times.iter().sum()
+    }
 }
 
 // Main bus struct.
@@ -401,16 +718,32 @@ pub struct BusInterface {
     memory: Vec<u8>,
     memory_mask: Vec<u8>,
     open_bus_byte: u8,
+    io_open_bus_byte: u8,
+    io_floating_bus: bool,
+    last_bus_write: u8,
     desc_vec: Vec<MemRangeDescriptor>,
+    ivt_writers: Vec<Option<IvtWriter>>,
+    page_stats_enabled: bool,
+    page_stats: [(u32, u32); PAGE_STATS_LEN], // (reads, writes) per 4KB page
+    access_profiler: Option<AccessProfiler>,
+    profile_regions: Vec<ProfileRegionConfig>,
+    wait_regions: Vec<WaitStateRegionConfig>,
+    // Pages overlapped by at least one wait state region, so most accesses skip the region search.
+    wait_pages: [bool; WAIT_PAGE_LEN],
+    exec_coverage: Vec<u8>,                   // Bitmap of addresses executed while instruction coverage is enabled
     mmio_map: Vec<(MemRangeDescriptor, MmioDeviceType)>,
     mmio_map_fast: [MmioDeviceType; MMIO_MAP_LEN],
     mmio_data: MmioData,
+    bank_controllers: Vec<Box<dyn BankController>>,
     cursor: usize,
     intr_imminent: bool,
 
     io_map: FxHashMap<u16, IoDeviceType>,
     io_desc_map: FxHashMap<u16, String>,
     io_stats: FxHashMap<u16, (bool, IoDeviceStats)>,
+    io_trace: HistoryBuffer<IoTraceEntry>,
+    io_trace_filter: Option<RangeInclusive<u16>>,
+    io_unclaimed_logged: FxHashSet<u16>,
     ppi: Option<Ppi>,
     a0: Option<A0Register>,
     a0_data: u8,
@@ -431,8 +764,13 @@ pub struct BusInterface {
     ems: Option<LotechEmsCard>,
     cart_slot: Option<CartridgeSlot>,
     game_port: Option<GamePort>,
+    rtc: Option<RtcDispatch>,
+    post_code_monitor: Option<PostCodeMonitor>,
     #[cfg(feature = "opl")]
     adlib: Option<AdLibCard>,
+    #[cfg(feature = "sound")]
+    sn76489: Option<Sn76489>,
+    isa_slots: Vec<Option<IsaSlot>>,
 
     videocards:    FxHashMap<VideoCardId, VideoCardDispatch>,
     videocard_ids: Vec<VideoCardId>,
@@ -449,7 +787,8 @@ pub struct BusInterface {
     kb_us_accum:    f64,
     refresh_active: bool,
 
-    terminal_port: Option<u16>,
+    terminal_port:  Option<u16>,
+    device_profile: Option<DeviceProfile>,
 }
 
 #[macro_export]
@@ -487,7 +826,7 @@ impl ByteQueue for BusInterface {
 
     fn q_read_u8(&mut self, _dtype: QueueType, _reader: QueueReader) -> u8 {
         if self.cursor < self.memory.len() {
-            let (b, _) = self.read_u8(self.cursor, 0).unwrap_or((0xFF, 0));
+            let (b, _) = self.read_u8_untracked(self.cursor, 0).unwrap_or((0xFF, 0));
             self.cursor += 1;
             return b;
         }
@@ -496,7 +835,7 @@ impl ByteQueue for BusInterface {
 
     fn q_read_i8(&mut self, _dtype: QueueType, _reader: QueueReader) -> i8 {
         if self.cursor < self.memory.len() {
-            let (b, _) = self.read_u8(self.cursor, 0).unwrap_or((0xFF, 0));
+            let (b, _) = self.read_u8_untracked(self.cursor, 0).unwrap_or((0xFF, 0));
             self.cursor += 1;
             return b as i8;
         }
@@ -505,8 +844,8 @@ impl ByteQueue for BusInterface {
 
     fn q_read_u16(&mut self, _dtype: QueueType, _reader: QueueReader) -> u16 {
         if self.cursor < self.memory.len() - 1 {
-            let (b0, _) = self.read_u8(self.cursor, 0).unwrap_or((0xFF, 0));
-            let (b1, _) = self.read_u8(self.cursor + 1, 0).unwrap_or((0xFF, 0));
+            let (b0, _) = self.read_u8_untracked(self.cursor, 0).unwrap_or((0xFF, 0));
+            let (b1, _) = self.read_u8_untracked(self.cursor + 1, 0).unwrap_or((0xFF, 0));
             self.cursor += 2;
             return b0 as u16 | (b1 as u16) << 8;
         }
@@ -515,8 +854,8 @@ impl ByteQueue for BusInterface {
 
     fn q_read_i16(&mut self, _dtype: QueueType, _reader: QueueReader) -> i16 {
         if self.cursor < self.memory.len() - 1 {
-            let (b0, _) = self.read_u8(self.cursor, 0).unwrap_or((0xFF, 0));
-            let (b1, _) = self.read_u8(self.cursor + 1, 0).unwrap_or((0xFF, 0));
+            let (b0, _) = self.read_u8_untracked(self.cursor, 0).unwrap_or((0xFF, 0));
+            let (b1, _) = self.read_u8_untracked(self.cursor + 1, 0).unwrap_or((0xFF, 0));
             self.cursor += 2;
             return (b0 as u16 | (b1 as u16) << 8) as i16;
         }
@@ -579,11 +918,23 @@ impl Default for BusInterface {
             keyboard: None,
             conventional_size: ADDRESS_SPACE,
             memory: vec![0; ADDRESS_SPACE],
-            memory_mask: vec![0; ADDRESS_SPACE],
+            memory_mask: vec![MEM_RAM_BIT; ADDRESS_SPACE],
             open_bus_byte: 0xFF,
+            io_open_bus_byte: NO_IO_BYTE,
+            io_floating_bus: false,
+            last_bus_write: NO_IO_BYTE,
             desc_vec: Vec::new(),
+            ivt_writers: vec![None; 256],
+            page_stats_enabled: false,
+            page_stats: [(0, 0); PAGE_STATS_LEN],
+            access_profiler: None,
+            profile_regions: default_profile_regions(),
+            wait_regions: Vec::new(),
+            wait_pages: [false; WAIT_PAGE_LEN],
+            exec_coverage: vec![0; ADDRESS_SPACE / 8],
             mmio_map: Vec::new(),
             mmio_map_fast: [MmioDeviceType::Memory; MMIO_MAP_LEN],
+            bank_controllers: Vec::new(),
             mmio_data: MmioData::new(),
             cursor: 0,
             intr_imminent: false,
@@ -591,6 +942,9 @@ impl Default for BusInterface {
             io_map: FxHashMap::default(),
             io_desc_map: FxHashMap::default(),
             io_stats: FxHashMap::default(),
+            io_trace: HistoryBuffer::new(IO_TRACE_LEN),
+            io_trace_filter: None,
+            io_unclaimed_logged: FxHashSet::default(),
             ppi: None,
             a0: None,
             a0_data: 0,
@@ -611,8 +965,13 @@ impl Default for BusInterface {
             ems: None,
             cart_slot: None,
             game_port: None,
+            rtc: None,
+            post_code_monitor: None,
             #[cfg(feature = "opl")]
             adlib: None,
+            #[cfg(feature = "sound")]
+            sn76489: None,
+            isa_slots: (0..MAX_ISA_SLOTS).map(|_| None).collect(),
             videocards: FxHashMap::default(),
             videocard_ids: Vec::new(),
 
@@ -628,7 +987,8 @@ impl Default for BusInterface {
             kb_us_accum:    0.0,
             refresh_active: false,
 
-            terminal_port: None,
+            terminal_port:  None,
+            device_profile: None,
         }
     }
 }
@@ -651,6 +1011,21 @@ impl BusInterface {
         self.do_title_hacks = do_timing_hacks;
     }
 
+    /// Enable or disable collection of per-device tick times. Enabling resets any collected times.
+    pub fn set_device_profiling(&mut self, state: bool) {
+        self.device_profile = state.then(DeviceProfile::default);
+    }
+
+    pub fn device_profile(&self) -> Option<&DeviceProfile> {
+        self.device_profile.as_ref()
+    }
+
+    /// Return the device tick times collected since the last call, and reset them.
+    /// Returns None if device profiling is disabled.
+    pub fn take_device_profile(&mut self) -> Option<DeviceProfile> {
+        self.device_profile.as_mut().map(std::mem::take)
+    }
+
     /// Update the bus timing table.
     /// The bus keeps a timing table which is a lookup table of system ticks and microseconds for each possible CPU
     /// instruction cycle count from 0 to TIMING_TABLE_LEN. This table needs to be updated whenever the clock divisor
@@ -663,15 +1038,8 @@ impl BusInterface {
         for cycles in 0..TIMING_TABLE_LEN {
             let entry = &mut timing_table[cycles];
 
-            entry.sys_ticks = match clock_factor {
-                ClockFactor::Divisor(n) => ((cycles as u32) + (n as u32) - 1) / (n as u32),
-                ClockFactor::Multiplier(n) => (cycles as u32) * (n as u32),
-            };
-            let mhz = match clock_factor {
-                ClockFactor::Divisor(n) => cpu_crystal / (n as f64),
-                ClockFactor::Multiplier(n) => cpu_crystal * (n as f64),
-            };
-            entry.us = 1.0 / mhz * cycles as f64;
+            entry.sys_ticks = clock_factor.cycles_to_ticks(cycles as u32);
+            entry.us = 1.0 / clock_factor.cpu_mhz(cpu_crystal) * cycles as f64;
         }
     }
 
@@ -734,6 +1102,32 @@ impl BusInterface {
         self.conventional_size
     }
 
+    /// Populate RAM in the specified regions only. Unpopulated addresses that are not ROM or memory-mapped are
+    /// filled with the open bus byte and ignore writes.
+    pub fn set_ram_regions(&mut self, regions: &[RamRegionConfig]) {
+        for flags in self.memory_mask.iter_mut() {
+            *flags &= !MEM_RAM_BIT;
+        }
+        for region in regions {
+            let start = (region.address as usize).min(ADDRESS_SPACE);
+            let end = (region.address as usize + region.size as usize).min(ADDRESS_SPACE);
+            for flags in &mut self.memory_mask[start..end] {
+                *flags |= MEM_RAM_BIT;
+            }
+        }
+        for (byte_ref, flags) in self.memory.iter_mut().zip(self.memory_mask.iter()) {
+            if *flags & (MEM_RAM_BIT | MEM_ROM_BIT | MEM_MMIO_BIT) == 0 {
+                *byte_ref = self.open_bus_byte;
+            }
+        }
+    }
+
+    /// Return whether the specified address is backed by RAM, ROM or a memory-mapped device.
+    #[inline]
+    pub fn is_populated(&self, address: usize) -> bool {
+        address < self.memory_mask.len() && self.memory_mask[address] & (MEM_RAM_BIT | MEM_ROM_BIT | MEM_MMIO_BIT) != 0
+    }
+
     pub fn size(&self) -> usize {
         self.memory.len()
     }
@@ -766,6 +1160,102 @@ impl BusInterface {
         self.mmio_map.push((mem_descriptor, device));
     }
 
+    /// Register a bank controller for the address range starting at `start` of `len` bytes.
+    ///
+    /// Memory accesses in the range are offered to the controller first; accesses it declines fall
+    /// through to system memory. The range must be aligned to the MMIO map granularity.
+    pub fn register_bank_controller(&mut self, start: usize, len: usize, controller: Box<dyn BankController>) {
+        let idx = self.bank_controllers.len();
+        self.bank_controllers.push(controller);
+        self.register_map(MmioDeviceType::Bank(idx), MemRangeDescriptor::new(start, len, false));
+    }
+
+    #[inline]
+    fn bank_read_u8(&self, idx: usize, address: usize) -> u8 {
+        self.bank_controllers[idx]
+            .bank_read_u8(address)
+            .unwrap_or(self.memory[address])
+    }
+
+    /// Insert an ISA card into the specified slot.
+    ///
+    /// The card's IO ports are added to the IO map, skipping any already claimed by another device,
+    /// and its memory ranges are registered as MMIO. Memory accesses the card declines fall through
+    /// to system memory.
+    pub fn insert_isa_card(&mut self, slot: usize, card: Box<dyn IsaCard>) -> Result<(), Error> {
+        if slot >= MAX_ISA_SLOTS {
+            return Err(anyhow!("Invalid ISA slot: {}", slot));
+        }
+        if self.isa_slots[slot].is_some() {
+            return Err(anyhow!("ISA slot {} is already occupied", slot));
+        }
+
+        let mem_ranges = card.mem_ranges();
+        for range in &mem_ranges {
+            if range.address % MMIO_MAP_SIZE != 0
+                || range.size % MMIO_MAP_SIZE != 0
+                || range.address + range.size > ADDRESS_SPACE
+            {
+                return Err(anyhow!(
+                    "Invalid ISA card memory range {:05X}-{:05X}: ranges must be {:X}-byte aligned",
+                    range.address,
+                    range.address + range.size,
+                    MMIO_MAP_SIZE
+                ));
+            }
+        }
+
+        for (desc, port) in card.port_list() {
+            if self.io_map.contains_key(&port) {
+                log::warn!("ISA card port {:04X} is in use by another device, ignoring.", port);
+                continue;
+            }
+            self.io_desc_map.insert(port, desc);
+            self.io_map.insert(port, IoDeviceType::IsaCard(slot));
+        }
+        for range in mem_ranges {
+            self.register_map(MmioDeviceType::IsaCard(slot), range);
+        }
+
+        self.isa_slots[slot] = Some(IsaSlot { card, irq: None });
+        Ok(())
+    }
+
+    #[inline]
+    fn isa_card_read_u8(&self, slot: usize, address: usize) -> u8 {
+        self.isa_slots[slot]
+            .as_ref()
+            .and_then(|isa_slot| isa_slot.card.mem_read(address as u32))
+            .unwrap_or(self.memory[address])
+    }
+
+    #[inline]
+    fn isa_card_write_u8(&mut self, slot: usize, address: usize, data: u8) {
+        let claimed = match &mut self.isa_slots[slot] {
+            Some(isa_slot) => isa_slot.card.mem_write(address as u32, data),
+            None => false,
+        };
+        if !claimed && self.memory_mask[address] & MEM_RAM_BIT != 0 {
+            self.memory[address] = data;
+        }
+    }
+
+    /// Cartridges are read-only, but writes to addresses no cartridge maps fall through to system memory.
+    #[inline]
+    fn cart_write_u8(&mut self, address: usize, data: u8) {
+        let claimed = self.cart_slot.as_ref().is_some_and(|slot| slot.maps(address));
+        if !claimed && self.memory_mask[address] & MEM_RAM_BIT != 0 {
+            self.memory[address] = data;
+        }
+    }
+
+    #[inline]
+    fn bank_write_u8(&mut self, idx: usize, address: usize, data: u8) {
+        if !self.bank_controllers[idx].bank_write_u8(address, data) && self.memory_mask[address] & MEM_RAM_BIT != 0 {
+            self.memory[address] = data;
+        }
+    }
+
     pub fn copy_from(&mut self, src: &[u8], location: usize, cycle_cost: u32, read_only: bool) -> Result<(), bool> {
         let src_size = src.len();
         if location + src_size > self.memory.len() {
@@ -821,6 +1311,51 @@ impl BusInterface {
         Ok(())
     }
 
+    /// Read a ROM extension image from the filesystem and map it read-only at 'base_address'.
+    pub fn load_extension_rom(&mut self, path: &Path, base_address: u32) -> Result<(), Error> {
+        let data = std::fs::read(path).map_err(|e| anyhow!("Couldn't read extension ROM {:?}: {}", path, e))?;
+        if data.is_empty() {
+            return Err(anyhow!("Extension ROM {:?} is empty", path));
+        }
+        if base_address as usize + data.len() > OPTION_ROM_END as usize {
+            return Err(anyhow!(
+                "Extension ROM {:?} of {} bytes at {:05X} exceeds the address space",
+                path,
+                data.len(),
+                base_address
+            ));
+        }
+        self.copy_from(&data, base_address as usize, 0, true)
+            .map_err(|_| anyhow!("Couldn't map extension ROM {:?} at {:05X}", path, base_address))?;
+        log::debug!(
+            "Mapped extension ROM {:?} ({} bytes) at {:05X}",
+            path,
+            data.len(),
+            base_address
+        );
+        Ok(())
+    }
+
+    /// Scan the option ROM area in 2KB increments for the 55AAh signature, as the BIOS does during POST,
+    /// returning the address of each ROM found. A ROM's declared length is skipped so that a signature
+    /// within a ROM isn't mistaken for another ROM.
+    pub fn scan_extension_roms(&self) -> Vec<u32> {
+        let mut roms = Vec::new();
+        let mut address = OPTION_ROM_START as usize;
+        while address < OPTION_ROM_SCAN_END as usize {
+            let header = self.get_slice_at(address, 3);
+            if header.len() == 3 && header[0..2] == OPTION_ROM_SIGNATURE {
+                roms.push(address as u32);
+                let len = header[2] as usize * OPTION_ROM_BLOCK_SIZE;
+                address += len.next_multiple_of(OPTION_ROM_SCAN_STEP).max(OPTION_ROM_SCAN_STEP);
+            }
+            else {
+                address += OPTION_ROM_SCAN_STEP;
+            }
+        }
+        roms
+    }
+
     /// Return a slice of memory at the specified location and length.
     /// Does not resolve mmio addresses.
     pub fn get_slice_at(&self, start: usize, len: usize) -> &[u8] {
@@ -905,13 +1440,99 @@ impl BusInterface {
         for byte_ref in &mut self.memory {
             *byte_ref = self.open_bus_byte;
         }
-        // Then clear conventional memory
-        for byte_ref in &mut self.memory[0..self.conventional_size] {
-            *byte_ref = 0;
+        // Then clear populated RAM
+        for (byte_ref, flags) in self.memory.iter_mut().zip(self.memory_mask.iter()) {
+            if *flags & (MEM_RAM_BIT | MEM_ROM_BIT) == MEM_RAM_BIT {
+                *byte_ref = 0;
+            }
         }
 
         // Reset IO statistics
         self.io_stats.clear();
+        self.io_trace.clear();
+        self.io_unclaimed_logged.clear();
+
+        // Forget who wrote the IVT
+        self.ivt_writers.fill(None);
+    }
+
+    /// Record the CPU instruction at `segment:offset` as the last writer of the interrupt vector containing
+    /// `address`. The CPU calls this for memory writes below IVT_END.
+    pub fn record_ivt_write(&mut self, address: usize, size: usize, segment: u16, offset: u16, instruction: u64) {
+        let writer = IvtWriter {
+            segment,
+            offset,
+            instruction,
+        };
+        for vector in (address >> 2)..=((address + size - 1) >> 2).min(255) {
+            self.ivt_writers[vector] = Some(writer);
+        }
+    }
+
+    /// Record an IN or OUT issued by the CPU instruction at `segment:offset` in the IO trace. The CPU calls
+    /// this after each IO bus transfer. The first access to a port that no device claims is logged as a
+    /// warning, as it may indicate hardware the running program expects to find.
+    pub fn record_io_access(
+        &mut self,
+        port: u16,
+        value: u8,
+        direction: IoDirection,
+        segment: u16,
+        offset: u16,
+        instruction: u64,
+    ) {
+        let claimed = self.io_stats.get(&port).is_some_and(|(resolved, _)| *resolved);
+        if !claimed {
+            if self.io_unclaimed_logged.insert(port) {
+                log::warn!(
+                    "{:?} of unclaimed IO port {:04X} by {:04X}:{:04X}",
+                    direction,
+                    port,
+                    segment,
+                    offset
+                );
+            }
+            else {
+                log::trace!(
+                    "{:?} of unclaimed IO port {:04X} by {:04X}:{:04X}",
+                    direction,
+                    port,
+                    segment,
+                    offset
+                );
+            }
+        }
+
+        if self
+            .io_trace_filter
+            .as_ref()
+            .is_some_and(|range| !range.contains(&port))
+        {
+            return;
+        }
+        self.io_trace.push(IoTraceEntry {
+            port,
+            value,
+            direction,
+            segment,
+            offset,
+            instruction,
+            claimed,
+        });
+    }
+
+    /// Return the most recent IO accesses, oldest first.
+    pub fn io_trace(&self) -> &HistoryBuffer<IoTraceEntry> {
+        &self.io_trace
+    }
+
+    /// Only trace accesses to ports within `range`. Passing None traces all ports.
+    pub fn set_io_trace_filter(&mut self, range: Option<RangeInclusive<u16>>) {
+        self.io_trace_filter = range;
+    }
+
+    pub fn clear_io_trace(&mut self) {
+        self.io_trace.clear();
     }
 
     pub fn reset(&mut self) {
@@ -921,9 +1542,13 @@ impl BusInterface {
     }
 
     pub fn set_cpu_factor(&mut self, cpu_factor: ClockFactor) {
+        let changed = cpu_factor != self.cpu_factor;
         self.cpu_factor = cpu_factor;
 
         self.recalculate_cycle_lut();
+        if let (true, Some(desc)) = (changed, &self.machine_desc) {
+            Self::update_timing_table(&mut self.timing_table, cpu_factor, desc.system_crystal);
+        }
     }
 
     pub fn recalculate_cycle_lut(&mut self) {
@@ -936,23 +1561,50 @@ impl BusInterface {
     /// Convert a count of CPU cycles to system clock ticks based on the current CPU
     /// clock divisor.
     fn cpu_cycles_to_system_ticks(&self, cycles: u32) -> u32 {
-        match self.cpu_factor {
-            ClockFactor::Divisor(n) => cycles * (n as u32),
-            ClockFactor::Multiplier(n) => cycles / (n as u32),
-        }
+        self.cpu_factor.cycles_to_ticks(cycles)
     }
 
     #[inline]
     /// Convert a count of system clock ticks to CPU cycles based on the current CPU
     /// clock divisor. If a clock Divisor is set, the dividend will be rounded upwards.
     fn system_ticks_to_cpu_cycles(&self, ticks: u32) -> u32 {
-        match self.cpu_factor {
-            ClockFactor::Divisor(n) => (ticks + (n as u32) - 1) / (n as u32),
-            ClockFactor::Multiplier(n) => ticks * (n as u32),
-        }
+        self.cpu_factor.ticks_to_cycles(ticks)
     }
 
+    /// Return the number of wait states for a CPU read of `address`: those configured for the
+    /// address's wait state region plus any imposed by a device mapped there.
     pub fn get_read_wait(&mut self, address: usize, cycles: u32) -> Result<u32, MemError> {
+        Ok(self.device_read_wait(address, cycles)? + self.region_read_wait(address))
+    }
+
+    /// Return the number of wait states for a CPU write to `address`: those configured for the
+    /// address's wait state region plus any imposed by a device mapped there.
+    pub fn get_write_wait(&mut self, address: usize, cycles: u32) -> Result<u32, MemError> {
+        Ok(self.device_write_wait(address, cycles)? + self.region_write_wait(address))
+    }
+
+    #[inline]
+    fn region_read_wait(&self, address: usize) -> u32 {
+        self.wait_region(address).map(|region| region.read).unwrap_or(0)
+    }
+
+    #[inline]
+    fn region_write_wait(&self, address: usize) -> u32 {
+        self.wait_region(address).map(|region| region.write).unwrap_or(0)
+    }
+
+    #[inline]
+    fn wait_region(&self, address: usize) -> Option<&WaitStateRegionConfig> {
+        let page = address >> WAIT_PAGE_SHIFT;
+        if page >= WAIT_PAGE_LEN || !self.wait_pages[page] {
+            return None;
+        }
+        self.wait_regions
+            .iter()
+            .find(|region| (region.start as usize..=region.end as usize).contains(&address))
+    }
+
+    fn device_read_wait(&mut self, address: usize, cycles: u32) -> Result<u32, MemError> {
         if address < self.memory.len() {
             if self.memory_mask[address] & MEM_MMIO_BIT == 0 {
                 // Address is not mapped.
@@ -993,7 +1645,9 @@ impl BusInterface {
                         }
                     }
                     MmioDeviceType::Cart => {
-                        return Ok(0);
+                        if self.cart_slot.as_ref().is_some_and(|slot| slot.maps(address)) {
+                            return Ok(0);
+                        }
                     }
                     _ => {}
                 }
@@ -1004,7 +1658,7 @@ impl BusInterface {
         Err(MemError::ReadOutOfBoundsError)
     }
 
-    pub fn get_write_wait(&mut self, address: usize, cycles: u32) -> Result<u32, MemError> {
+    fn device_write_wait(&mut self, address: usize, cycles: u32) -> Result<u32, MemError> {
         if address < self.memory.len() {
             if self.memory_mask[address] & MEM_MMIO_BIT == 0 {
                 // Address is not mapped.
@@ -1046,7 +1700,9 @@ impl BusInterface {
                         }
                     }
                     MmioDeviceType::Cart => {
-                        return Ok(0);
+                        if self.cart_slot.as_ref().is_some_and(|slot| slot.maps(address)) {
+                            return Ok(0);
+                        }
                     }
                     _ => {}
                 }
@@ -1057,7 +1713,195 @@ impl BusInterface {
         Err(MemError::ReadOutOfBoundsError)
     }
 
+    fn set_wait_regions(&mut self, regions: &[WaitStateRegionConfig]) {
+        self.wait_regions = regions.to_vec();
+        self.wait_pages.fill(false);
+        for region in regions {
+            let first_page = region.start as usize >> WAIT_PAGE_SHIFT;
+            let last_page = (region.end as usize >> WAIT_PAGE_SHIFT).min(WAIT_PAGE_LEN - 1);
+            if first_page <= last_page {
+                self.wait_pages[first_page..=last_page].fill(true);
+            }
+        }
+    }
+
+    /// Enable or disable per-page access counting. Counting is off by default as it is only
+    /// needed for the memory viewer's heat map.
+    pub fn set_page_stats_enabled(&mut self, enabled: bool) {
+        self.page_stats_enabled = enabled;
+    }
+
+    pub fn page_stats_enabled(&self) -> bool {
+        self.page_stats_enabled
+    }
+
+    /// Return the (reads, writes) access counts for each 4KB page of the address space.
+    pub fn get_page_stats(&self) -> &[(u32, u32)] {
+        &self.page_stats
+    }
+
+    pub fn reset_page_stats(&mut self) {
+        self.page_stats.fill((0, 0));
+    }
+
+    /// Enable or disable the memory access profiler. Enabling the profiler starts a new profile.
+    pub fn set_access_profiler_enabled(&mut self, enabled: bool) {
+        self.access_profiler = match enabled {
+            true => Some(AccessProfiler::new(&self.profile_regions)),
+            false => None,
+        };
+    }
+
+    pub fn access_profiler_enabled(&self) -> bool {
+        self.access_profiler.is_some()
+    }
+
+    /// Return the memory access totals per profiled region for the last completed frame, if the
+    /// access profiler is enabled.
+    pub fn access_profile(&self) -> Option<&[AccessProfileRegion]> {
+        self.access_profiler.as_ref().map(|profiler| profiler.last_frame())
+    }
+
+    /// Latch the memory access profile for the current frame. Called once per frame.
+    pub fn end_access_profile_frame(&mut self) {
+        if let Some(profiler) = &mut self.access_profiler {
+            profiler.end_frame();
+        }
+    }
+
+    #[inline]
+    fn profile_access(&mut self, address: usize, wait_cycles: u32) {
+        if let Some(profiler) = &mut self.access_profiler {
+            profiler.record(address, wait_cycles);
+        }
+    }
+
+    /// Mark the bytes of an instruction of `size` bytes at `address` as executed.
+    #[inline]
+    pub fn mark_executed(&mut self, address: u32, size: u32) {
+        for a in address..address + size {
+            let a = a as usize & (ADDRESS_SPACE - 1);
+            self.exec_coverage[a >> 3] |= 1 << (a & 0x07);
+        }
+    }
+
+    pub fn is_executed(&self, address: usize) -> bool {
+        let a = address & (ADDRESS_SPACE - 1);
+        self.exec_coverage[a >> 3] & (1 << (a & 0x07)) != 0
+    }
+
+    /// Return the coverage bitmap. Bit n of byte n / 8 is set if address n has been executed.
+    pub fn exec_coverage(&self) -> &[u8] {
+        &self.exec_coverage
+    }
+
+    /// Return the number of executed bytes in each 64KB segment of the address space.
+    pub fn exec_coverage_summary(&self) -> Vec<usize> {
+        self.exec_coverage
+            .chunks(COVERAGE_SEGMENT_SIZE / 8)
+            .map(|chunk| chunk.iter().map(|byte| byte.count_ones() as usize).sum())
+            .collect()
+    }
+
+    pub fn clear_exec_coverage(&mut self) {
+        self.exec_coverage.fill(0);
+    }
+
+    /// Return whether a write to the specified address is stored, ie, the address is RAM or a memory-mapped
+    /// device, and not ROM.
+    #[inline]
+    pub fn is_writable(&self, address: usize) -> bool {
+        address < self.memory_mask.len()
+            && self.memory_mask[address] & MEM_ROM_BIT == 0
+            && self.memory_mask[address] & (MEM_RAM_BIT | MEM_MMIO_BIT) != 0
+    }
+
+    /// Return whether the specified address is ROM.
+    #[inline]
+    pub fn is_rom(&self, address: usize) -> bool {
+        address < self.memory_mask.len() && self.memory_mask[address] & MEM_ROM_BIT != 0
+    }
+
+    /// Write a byte on behalf of the debugger rather than the CPU. The write goes through the bus so that
+    /// memory-mapped devices see it, but it is not counted in page statistics or the access profile.
+    /// ROM is only modified if `force` is set, in which case the read-only mapping is bypassed.
+    /// Returns None if the address is unpopulated, or is ROM and `force` is not set.
+    pub fn debug_write_u8(&mut self, address: usize, data: u8, force: bool) -> Option<DebugWrite> {
+        let address = address & (ADDRESS_SPACE - 1);
+        let old = self.peek_u8(address).unwrap_or(OPEN_BUS_BYTE);
+        if self.is_rom(address) {
+            if !force {
+                return None;
+            }
+            self.memory[address] = data;
+        }
+        else if self.is_writable(address) {
+            _ = self.write_u8_untracked(address, data, 0);
+        }
+        else {
+            return None;
+        }
+        Some(DebugWrite {
+            address,
+            old,
+            new: data,
+        })
+    }
+
+    /// Fill `len` bytes of memory starting at `address` with `value`, wrapping at the end of the address space.
+    /// Read-only and unpopulated addresses are skipped. Returns the number of bytes written and the number skipped.
+    pub fn fill_u8(&mut self, address: usize, len: usize, value: u8) -> (usize, usize) {
+        let len = len.min(ADDRESS_SPACE);
+        let mut skipped = 0;
+        for i in 0..len {
+            let a = (address + i) & (ADDRESS_SPACE - 1);
+            if self.is_writable(a) {
+                _ = self.write_u8(a, value, 0);
+            }
+            else {
+                skipped += 1;
+            }
+        }
+        (len - skipped, skipped)
+    }
+
+    /// Write `data` to memory starting at `address`, wrapping at the end of the address space.
+    /// Read-only and unpopulated addresses are skipped. Returns the number of bytes skipped.
+    pub fn poke_slice(&mut self, address: usize, data: &[u8]) -> usize {
+        let mut skipped = 0;
+        for (i, byte) in data.iter().enumerate() {
+            let a = (address + i) & (ADDRESS_SPACE - 1);
+            if self.is_writable(a) {
+                _ = self.write_u8(a, *byte, 0);
+            }
+            else {
+                skipped += 1;
+            }
+        }
+        skipped
+    }
+
+    #[inline]
     pub fn read_u8(&mut self, address: usize, cycles: u32) -> Result<(u8, u32), MemError> {
+        if self.page_stats_enabled {
+            if let Some(stats) = self.page_stats.get_mut(address >> PAGE_STATS_SHIFT) {
+                stats.0 = stats.0.saturating_add(1);
+            }
+        }
+        let result = self
+            .read_u8_untracked(address, cycles)
+            .map(|(data, waits)| (data, waits + self.region_read_wait(address)));
+        if self.access_profiler.is_some() {
+            if let Ok((_, wait_cycles)) = result {
+                self.profile_access(address, wait_cycles);
+            }
+        }
+        result
+    }
+
+    /// Read a byte without counting the access in the page statistics. Used for reads made on
+    /// behalf of the debugger rather than the emulated machine.
+    fn read_u8_untracked(&mut self, address: usize, cycles: u32) -> Result<(u8, u32), MemError> {
         if address < self.memory.len() {
             if self.memory_mask[address] & MEM_MMIO_BIT == 0 {
                 // Address is not mapped.
@@ -1073,23 +1917,23 @@ impl BusInterface {
                         if let Some(card_dispatch) = self.videocards.get_mut(&vid) {
                             match card_dispatch {
                                 VideoCardDispatch::Mda(mda) => {
-                                    let (data, _waits) =
+                                    let (data, syswait) =
                                         MemoryMappedDevice::mmio_read_u8(mda, address, system_ticks, None);
-                                    return Ok((data, 0));
+                                    return Ok((data, self.system_ticks_to_cpu_cycles(syswait)));
                                 }
                                 VideoCardDispatch::Cga(cga) => {
-                                    let (data, _waits) =
+                                    let (data, syswait) =
                                         MemoryMappedDevice::mmio_read_u8(cga, address, system_ticks, None);
-                                    return Ok((data, 0));
+                                    return Ok((data, self.system_ticks_to_cpu_cycles(syswait)));
                                 }
                                 VideoCardDispatch::Tga(tga) => {
-                                    let (data, _waits) = MemoryMappedDevice::mmio_read_u8(
+                                    let (data, syswait) = MemoryMappedDevice::mmio_read_u8(
                                         tga,
                                         address,
                                         system_ticks,
                                         Some(&self.memory),
                                     );
-                                    return Ok((data, 0));
+                                    return Ok((data, self.system_ticks_to_cpu_cycles(syswait)));
                                 }
                                 #[cfg(feature = "ega")]
                                 VideoCardDispatch::Ega(ega) => {
@@ -1115,11 +1959,18 @@ impl BusInterface {
                     }
                     MmioDeviceType::Cart => {
                         if let Some(cart_slot) = &mut self.cart_slot {
+                            // Addresses without a cartridge fall through to system memory.
                             let (data, _waits) =
-                                MemoryMappedDevice::mmio_read_u8(cart_slot, address, system_ticks, None);
+                                MemoryMappedDevice::mmio_read_u8(cart_slot, address, system_ticks, Some(&self.memory));
                             return Ok((data, 0));
                         }
                     }
+                    MmioDeviceType::Bank(idx) => {
+                        return Ok((self.bank_read_u8(idx, address), 0));
+                    }
+                    MmioDeviceType::IsaCard(slot) => {
+                        return Ok((self.isa_card_read_u8(slot, address), 0));
+                    }
                     _ => {}
                 }
                 return Err(MemError::MmioError);
@@ -1184,10 +2035,16 @@ impl BusInterface {
                     }
                     MmioDeviceType::Cart => {
                         if let Some(cart_slot) = &self.cart_slot {
-                            let data = MemoryMappedDevice::mmio_peek_u8(cart_slot, address, None);
+                            let data = MemoryMappedDevice::mmio_peek_u8(cart_slot, address, Some(&self.memory));
                             return Ok(data);
                         }
                     }
+                    MmioDeviceType::Bank(idx) => {
+                        return Ok(self.bank_read_u8(idx, address));
+                    }
+                    MmioDeviceType::IsaCard(slot) => {
+                        return Ok(self.isa_card_read_u8(slot, address));
+                    }
                     _ => {}
                 }
                 return Err(MemError::MmioError);
@@ -1196,7 +2053,25 @@ impl BusInterface {
         Err(MemError::ReadOutOfBoundsError)
     }
 
+    #[inline]
     pub fn read_u16(&mut self, address: usize, cycles: u32) -> Result<(u16, u32), MemError> {
+        if self.page_stats_enabled {
+            if let Some(stats) = self.page_stats.get_mut(address >> PAGE_STATS_SHIFT) {
+                stats.0 = stats.0.saturating_add(1);
+            }
+        }
+        let result = self
+            .read_u16_untracked(address, cycles)
+            .map(|(data, waits)| (data, waits + self.region_read_wait(address)));
+        if self.access_profiler.is_some() {
+            if let Ok((_, wait_cycles)) = result {
+                self.profile_access(address, wait_cycles);
+            }
+        }
+        result
+    }
+
+    fn read_u16_untracked(&mut self, address: usize, cycles: u32) -> Result<(u16, u32), MemError> {
         if address < self.memory.len() - 1 {
             if self.memory_mask[address] & MEM_MMIO_BIT == 0 {
                 // Address is not mapped.
@@ -1247,6 +2122,23 @@ impl BusInterface {
                             return Ok((data, self.system_ticks_to_cpu_cycles(syswait)));
                         }
                     }
+                    MmioDeviceType::Cart => {
+                        if let Some(cart_slot) = &mut self.cart_slot {
+                            let (data, _waits) =
+                                MemoryMappedDevice::mmio_read_u16(cart_slot, address, 0, Some(&self.memory));
+                            return Ok((data, 0));
+                        }
+                    }
+                    MmioDeviceType::Bank(idx) => {
+                        let w =
+                            self.bank_read_u8(idx, address) as u16 | (self.bank_read_u8(idx, address + 1) as u16) << 8;
+                        return Ok((w, DEFAULT_WAIT_STATES));
+                    }
+                    MmioDeviceType::IsaCard(slot) => {
+                        let w = self.isa_card_read_u8(slot, address) as u16
+                            | (self.isa_card_read_u8(slot, address + 1) as u16) << 8;
+                        return Ok((w, DEFAULT_WAIT_STATES));
+                    }
                     _ => {}
                 }
                 return Ok((0xFFFF, 0));
@@ -1256,11 +2148,30 @@ impl BusInterface {
         Err(MemError::ReadOutOfBoundsError)
     }
 
+    #[inline]
     pub fn write_u8(&mut self, address: usize, data: u8, cycles: u32) -> Result<u32, MemError> {
+        self.last_bus_write = data;
+        if self.page_stats_enabled {
+            if let Some(stats) = self.page_stats.get_mut(address >> PAGE_STATS_SHIFT) {
+                stats.1 = stats.1.saturating_add(1);
+            }
+        }
+        let result = self
+            .write_u8_untracked(address, data, cycles)
+            .map(|waits| waits + self.region_write_wait(address));
+        if self.access_profiler.is_some() {
+            if let Ok(wait_cycles) = result {
+                self.profile_access(address, wait_cycles);
+            }
+        }
+        result
+    }
+
+    fn write_u8_untracked(&mut self, address: usize, data: u8, cycles: u32) -> Result<u32, MemError> {
         if address < self.memory.len() {
             if self.memory_mask[address] & (MEM_MMIO_BIT | MEM_ROM_BIT) == 0 {
-                // Address is not mapped and not ROM, write to it if it is within conventional memory.
-                if address < self.conventional_size {
+                // Address is not mapped and not ROM, write to it if it is populated RAM.
+                if self.memory_mask[address] & MEM_RAM_BIT != 0 {
                     self.memory[address] = data;
                 }
                 return Ok(DEFAULT_WAIT_STATES);
@@ -1309,6 +2220,15 @@ impl BusInterface {
                             MemoryMappedDevice::mmio_write_u8(ems, address, data, 0, None);
                         }
                     }
+                    MmioDeviceType::Bank(idx) => {
+                        self.bank_write_u8(idx, address, data);
+                    }
+                    MmioDeviceType::IsaCard(slot) => {
+                        self.isa_card_write_u8(slot, address, data);
+                    }
+                    MmioDeviceType::Cart => {
+                        self.cart_write_u8(address, data);
+                    }
                     _ => {}
                 }
                 return Ok(DEFAULT_WAIT_STATES);
@@ -1317,16 +2237,35 @@ impl BusInterface {
         Err(MemError::ReadOutOfBoundsError)
     }
 
+    #[inline]
     pub fn write_u16(&mut self, address: usize, data: u16, cycles: u32) -> Result<u32, MemError> {
+        // The high byte is transferred last.
+        self.last_bus_write = (data >> 8) as u8;
+        if self.page_stats_enabled {
+            if let Some(stats) = self.page_stats.get_mut(address >> PAGE_STATS_SHIFT) {
+                stats.1 = stats.1.saturating_add(1);
+            }
+        }
+        let result = self
+            .write_u16_untracked(address, data, cycles)
+            .map(|waits| waits + self.region_write_wait(address));
+        if self.access_profiler.is_some() {
+            if let Ok(wait_cycles) = result {
+                self.profile_access(address, wait_cycles);
+            }
+        }
+        result
+    }
+
+    fn write_u16_untracked(&mut self, address: usize, data: u16, cycles: u32) -> Result<u32, MemError> {
         if address < self.memory.len() - 1 {
             if self.memory_mask[address] & (MEM_MMIO_BIT | MEM_ROM_BIT) == 0 {
-                // Address is not mapped. Write to memory if within conventional memory size.
-                if address < self.conventional_size - 1 {
+                // Address is not mapped. Write to each byte that is populated RAM.
+                if self.memory_mask[address] & MEM_RAM_BIT != 0 {
                     self.memory[address] = (data & 0xFF) as u8;
-                    self.memory[address + 1] = (data >> 8) as u8;
                 }
-                else if address < self.conventional_size {
-                    self.memory[address] = (data & 0xFF) as u8;
+                if self.memory_mask[address + 1] & MEM_RAM_BIT != 0 {
+                    self.memory[address + 1] = (data >> 8) as u8;
                 }
                 return Ok(DEFAULT_WAIT_STATES);
             }
@@ -1411,6 +2350,21 @@ impl BusInterface {
                             MemoryMappedDevice::mmio_write_u16(ems, address, data, 0, None);
                         }
                     }
+                    MmioDeviceType::Bank(idx) => {
+                        self.bank_write_u8(idx, address, (data & 0xFF) as u8);
+                        self.bank_write_u8(idx, address + 1, (data >> 8) as u8);
+                        return Ok(DEFAULT_WAIT_STATES);
+                    }
+                    MmioDeviceType::IsaCard(slot) => {
+                        self.isa_card_write_u8(slot, address, (data & 0xFF) as u8);
+                        self.isa_card_write_u8(slot, address + 1, (data >> 8) as u8);
+                        return Ok(DEFAULT_WAIT_STATES);
+                    }
+                    MmioDeviceType::Cart => {
+                        self.cart_write_u8(address, (data & 0xFF) as u8);
+                        self.cart_write_u8(address + 1, (data >> 8) as u8);
+                        return Ok(DEFAULT_WAIT_STATES);
+                    }
                     _ => {}
                 }
                 return Ok(0);
@@ -1439,10 +2393,10 @@ impl BusInterface {
     }
 
     /// Clear the specified flags for the specified byte at address
-    /// Do not allow ROM bit to be cleared
+    /// Do not allow ROM or RAM bits to be cleared
     pub fn clear_flags(&mut self, address: usize, flags: u8) {
         if address < self.memory.len() - 1 {
-            self.memory_mask[address] &= !(flags & 0x7F);
+            self.memory_mask[address] &= !(flags & !(MEM_ROM_BIT | MEM_RAM_BIT));
         }
     }
 
@@ -1600,7 +2554,19 @@ impl BusInterface {
     /// Dump memory to a vector of vectors of SyntaxTokens.
     ///
     /// Uses bus peek functions to resolve MMIO addresses.
-    pub fn dump_flat_tokens_ex(&self, address: usize, cursor: usize, mut size: usize) -> Vec<Vec<SyntaxToken>> {
+    pub fn dump_flat_tokens_ex(&self, address: usize, cursor: usize, size: usize) -> Vec<Vec<SyntaxToken>> {
+        self.dump_flat_tokens_layout(address, cursor, size, &MemoryDumpLayout::default())
+    }
+
+    /// Dump `size` bytes of memory from `address` as rows of tokens arranged by `layout`. The byte
+    /// at `cursor` is marked.
+    pub fn dump_flat_tokens_layout(
+        &self,
+        address: usize,
+        cursor: usize,
+        mut size: usize,
+        layout: &MemoryDumpLayout,
+    ) -> Vec<Vec<SyntaxToken>> {
         let mut vec: Vec<Vec<SyntaxToken>> = Vec::new();
 
         if address >= self.memory.len() {
@@ -1620,8 +2586,9 @@ impl BusInterface {
 
         let addr_vec = Vec::from_iter(address..address + size);
         let mut display_address = address;
+        let row_len = layout.row_len.max(2);
 
-        for dump_addr_row in addr_vec.chunks_exact(16) {
+        for dump_addr_row in addr_vec.chunks_exact(row_len) {
             let mut line_vec = Vec::new();
 
             // Push memory flat address tokens
@@ -1630,58 +2597,100 @@ impl BusInterface {
                 format!("{:05X}", display_address),
             ));
 
-            // Build hex byte value tokens
-            let mut i = 0;
-            for addr in dump_addr_row {
-                let byte = self.peek_u8(*addr).unwrap();
-
-                if (display_address + i) == cursor {
-                    line_vec.push(SyntaxToken::MemoryByteHexValue(
-                        (display_address + i) as u32,
-                        byte,
-                        format!("{:02X}", byte),
-                        true, // Set cursor on this byte
+            if let Some(order) = layout.words {
+                // Build hex word value tokens
+                for word_addr in dump_addr_row.chunks_exact(2) {
+                    let (lo, hi) = (word_addr[0], word_addr[1]);
+                    if !self.is_populated(lo) && !self.is_populated(hi) {
+                        line_vec.push(SyntaxToken::MemoryByteUnpopulated(lo as u32, "----".to_string()));
+                        continue;
+                    }
+                    let bytes = [self.peek_u8(lo).unwrap(), self.peek_u8(hi).unwrap()];
+                    let word = match order {
+                        WordOrder::LittleEndian => u16::from_le_bytes(bytes),
+                        WordOrder::BigEndian => u16::from_be_bytes(bytes),
+                    };
+                    line_vec.push(SyntaxToken::MemoryWordHexValue(
+                        lo as u32,
+                        word,
+                        format!("{:04X}", word),
                         0,
                     ));
                 }
-                else {
-                    line_vec.push(SyntaxToken::MemoryByteHexValue(
-                        (display_address + i) as u32,
-                        byte,
-                        format!("{:02X}", byte),
-                        false,
-                        0,
-                    ));
+            }
+            else {
+                // Build hex byte value tokens
+                let mut i = 0;
+                for addr in dump_addr_row {
+                    let byte = self.peek_u8(*addr).unwrap();
+
+                    if !self.is_populated(*addr) {
+                        // Nothing is installed at this address; reads return open bus.
+                        line_vec.push(SyntaxToken::MemoryByteUnpopulated(*addr as u32, "--".to_string()));
+                    }
+                    else {
+                        line_vec.push(SyntaxToken::MemoryByteHexValue(
+                            (display_address + i) as u32,
+                            byte,
+                            format!("{:02X}", byte),
+                            (display_address + i) == cursor, // Set cursor on this byte
+                            0,
+                        ));
+                    }
+                    i += 1;
                 }
-                i += 1;
             }
 
             // Build ASCII representation tokens
-            let mut i = 0;
-            for addr in dump_addr_row {
-                let byte = self.peek_u8(*addr).unwrap();
+            if layout.ascii {
+                let mut i = 0;
+                for addr in dump_addr_row {
+                    let byte = self.peek_u8(*addr).unwrap();
 
-                let char_str = match byte {
-                    00..=31 => ".".to_string(),
-                    32..=127 => format!("{}", byte as char),
-                    128.. => ".".to_string(),
-                };
-                line_vec.push(SyntaxToken::MemoryByteAsciiValue(
-                    (display_address + i) as u32,
-                    byte,
-                    char_str,
-                    0,
-                ));
-                i += 1;
+                    let char_str = match byte {
+                        _ if !self.is_populated(*addr) => " ".to_string(),
+                        00..=31 => ".".to_string(),
+                        32..=127 => format!("{}", byte as char),
+                        128.. => ".".to_string(),
+                    };
+                    line_vec.push(SyntaxToken::MemoryByteAsciiValue(
+                        (display_address + i) as u32,
+                        byte,
+                        char_str,
+                        0,
+                    ));
+                    i += 1;
+                }
             }
 
             vec.push(line_vec);
-            display_address += 16;
+            display_address += row_len;
         }
 
         vec
     }
 
+    /// Return a hash of the contents of the address space. Used to compare the memory state of
+    /// deterministic runs.
+    pub fn memory_signature(&self) -> u64 {
+        fxhash::hash64(&self.memory)
+    }
+
+    /// Return a copy of the contents of the address space for a save state.
+    pub fn memory_snapshot(&self) -> Vec<u8> {
+        self.memory.clone()
+    }
+
+    /// Replace the contents of the address space from a save state. The snapshot must cover the
+    /// entire address space.
+    pub fn restore_memory(&mut self, snapshot: &[u8]) -> bool {
+        if snapshot.len() != self.memory.len() {
+            return false;
+        }
+        self.memory.copy_from_slice(snapshot);
+        true
+    }
+
     pub fn dump_mem(&self, path: &Path) {
         let filename = path.to_path_buf();
 
@@ -1721,8 +2730,8 @@ impl BusInterface {
 
         for v in 0..256 {
             let mut ivr_vec = Vec::new();
-            let (ip, _) = self.read_u16((v * 4) as usize, 0).unwrap();
-            let (cs, _) = self.read_u16(((v * 4) + 2) as usize, 0).unwrap();
+            let (ip, _) = self.read_u16_untracked((v * 4) as usize, 0).unwrap();
+            let (cs, _) = self.read_u16_untracked(((v * 4) + 2) as usize, 0).unwrap();
 
             ivr_vec.push(SyntaxToken::Text(format!("{:02X}h", v)));
             ivr_vec.push(SyntaxToken::Colon);
@@ -1757,6 +2766,114 @@ impl BusInterface {
         vec
     }
 
+    /// Decode the interrupt vector table, resolving the memory region each vector's handler resides in.
+    pub fn dump_ivt(&self) -> Vec<IvtEntry> {
+        let mcb_chain = self.find_mcb_chain();
+
+        (0..256)
+            .map(|v| {
+                let offset =
+                    self.peek_u8(v * 4).unwrap_or(0) as u16 | (self.peek_u8(v * 4 + 1).unwrap_or(0) as u16) << 8;
+                let segment =
+                    self.peek_u8(v * 4 + 2).unwrap_or(0) as u16 | (self.peek_u8(v * 4 + 3).unwrap_or(0) as u16) << 8;
+                let address = ((segment as usize) << 4) + offset as usize;
+
+                IvtEntry {
+                    vector: v as u8,
+                    segment,
+                    offset,
+                    region: self.resolve_region(address & 0xFFFFF, mcb_chain),
+                    written_by: self.ivt_writers[v],
+                }
+            })
+            .collect()
+    }
+
+    /// Resolve the region of memory the specified address belongs to. If a DOS memory control block chain is
+    /// present, RAM is attributed to DOS or the program owning the containing block.
+    fn resolve_region(&self, address: usize, mcb_chain: Option<u16>) -> IvtTargetRegion {
+        if self.memory_mask[address] & MEM_ROM_BIT != 0 {
+            return match address {
+                0xF0000.. => IvtTargetRegion::Bios,
+                _ => IvtTargetRegion::OptionRom,
+            };
+        }
+        if address < 0x600 {
+            return IvtTargetRegion::LowMemory;
+        }
+        if address >= self.conventional_size {
+            return IvtTargetRegion::Unknown;
+        }
+
+        if let Some(first_mcb) = mcb_chain {
+            if address < (first_mcb as usize) << 4 {
+                return IvtTargetRegion::Dos;
+            }
+            let mut mcb = first_mcb as usize;
+            loop {
+                let mcb_addr = mcb << 4;
+                let size = self.read_ram_u16(mcb_addr + 3) as usize;
+                let end = (mcb + size + 1) << 4;
+                if address < end {
+                    let owner = self.read_ram_u16(mcb_addr + 1);
+                    return match owner {
+                        // Blocks owned by segment 0008 belong to DOS itself.
+                        0x0008 => IvtTargetRegion::Dos,
+                        0x0000 => IvtTargetRegion::Ram,
+                        _ => IvtTargetRegion::Program(owner, self.read_mcb_name(mcb_addr)),
+                    };
+                }
+                if self.memory[mcb_addr] == b'Z' {
+                    break;
+                }
+                mcb += size + 1;
+            }
+        }
+        IvtTargetRegion::Ram
+    }
+
+    /// Scan conventional memory for a valid DOS memory control block chain, returning the segment of the first
+    /// MCB. A chain is considered valid if it can be walked to a terminating 'Z' block within conventional memory.
+    fn find_mcb_chain(&self) -> Option<u16> {
+        const MAX_CHAIN_LEN: usize = 512;
+        let top_seg = self.conventional_size >> 4;
+
+        for start in 0x0060..top_seg.min(0x4000) {
+            if self.memory[start << 4] != b'M' {
+                continue;
+            }
+            let mut mcb = start;
+            for _ in 0..MAX_CHAIN_LEN {
+                let size = self.read_ram_u16((mcb << 4) + 3) as usize;
+                let next = mcb + size + 1;
+                if next > top_seg {
+                    break;
+                }
+                match self.memory[mcb << 4] {
+                    b'Z' => return Some(start as u16),
+                    b'M' if next < top_seg => mcb = next,
+                    _ => break,
+                }
+            }
+        }
+        None
+    }
+
+    /// Read the program name from a DOS 4+ memory control block, if present.
+    fn read_mcb_name(&self, mcb_addr: usize) -> String {
+        self.memory[mcb_addr + 8..mcb_addr + 16]
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as char)
+            .filter(|c| c.is_ascii_graphic())
+            .collect()
+    }
+
+    #[inline]
+    fn read_ram_u16(&self, address: usize) -> u16 {
+        self.memory[address] as u16 | (self.memory[address + 1] as u16) << 8
+    }
+
     /// Returns a MemoryDebug struct containing information about the memory at the specified address.
     /// This is used in the Memory Viewer debug window to show a popup when hovering over a byte.
     pub fn get_memory_debug(&mut self, cpu_type: CpuType, address: usize) -> MemoryDebug {
@@ -1804,6 +2921,7 @@ impl BusInterface {
         machine_config: &MachineConfiguration,
         #[cfg(feature = "sound")] sound_config: &SoundOutputConfig,
         terminal_port: Option<u16>,
+        post_code_ports: Vec<u16>,
     ) -> Result<InstalledDevicesResult, Error> {
         #[allow(unused_mut)]
         let mut installed_devices = InstalledDevicesResult::new();
@@ -1832,10 +2950,18 @@ impl BusInterface {
             .map(|fdc| fdc.drive.len() as u32)
             .unwrap_or(0);
 
-        // Get normalized conventional memory and set it.
-        let conventional_memory = normalize_conventional_memory(machine_config)?;
+        // Populate RAM regions. The memory visible to the BIOS is the RAM contiguous from address 0.
+        let ram_regions = machine_config.ram_regions()?;
+        let conventional_memory = contiguous_ram_size(&ram_regions);
         self.set_conventional_size(conventional_memory as usize);
         self.open_bus_byte = machine_desc.open_bus_byte;
+        self.io_open_bus_byte = machine_config.io_open_bus_value.unwrap_or(NO_IO_BYTE);
+        self.io_floating_bus = machine_config.io_floating_bus;
+        if !machine_config.profile_regions.is_empty() {
+            self.profile_regions = machine_config.profile_regions.clone();
+        }
+        self.set_wait_regions(&machine_config.wait_state_regions);
+        self.set_ram_regions(&ram_regions);
 
         // Create the A0 register if specified.
         // TODO: Wrap this up in a motherboard device type?
@@ -2046,13 +3172,28 @@ impl BusInterface {
             self.game_port = Some(game_port);
         }
 
+        // Create a real time clock, seeded from the host clock unless a fixed time is configured.
+        if let Some(rtc_config) = &machine_config.rtc {
+            let start_time = RtcDateTime::from_unix(rtc_config.start_time());
+            let mut rtc: RtcDispatch = match rtc_config.rtc_type {
+                RtcType::Mc146818 => Rtc::new(rtc_config.io_base, rtc_config.irq, start_time).into(),
+                RtcType::Mm58167 => Mm58167::new(rtc_config.io_base, rtc_config.irq, start_time).into(),
+            };
+            rtc.set_host_clock(rtc_config.host_clock);
+            if let Some(fdc_config) = &machine_config.fdc {
+                let drive_types: Vec<_> = fdc_config.drive.iter().map(|drive| drive.fd_type).collect();
+                rtc.set_floppy_drives(&drive_types);
+            }
+            add_io_device!(self, rtc, IoDeviceType::Rtc);
+            self.rtc = Some(rtc);
+        }
+
         // Create sound cards
         #[cfg(feature = "sound")]
         for (_i, card) in machine_config.sound.iter().enumerate() {
-            #[cfg(feature = "opl")]
-            {
-                #[allow(irrefutable_let_patterns)]
-                if let SoundType::AdLib = card.sound_type {
+            match card.sound_type {
+                #[cfg(feature = "opl")]
+                SoundType::AdLib => {
                     // Create an AdLib card.
 
                     let (s, r) = unbounded();
@@ -2062,11 +3203,27 @@ impl BusInterface {
                         2,
                         r,
                     ));
-                    let adlib = AdLibCard::new(card.io_base, 48000, s);
-                    println!(">>> TESTING ADLIB <<<");
+                    let adlib = AdLibCard::new(card.io_base, sound_config.sample_rate, s);
                     add_io_device!(self, adlib, IoDeviceType::Sound);
                     self.adlib = Some(adlib);
                 }
+                SoundType::Sn76489 => {
+                    // Create the TI SN76489 sound chip (PCjr / Tandy 1000).
+                    let (s, r) = unbounded();
+                    installed_devices.sound_sources.push(SoundSourceDescriptor::new(
+                        "TI SN76489",
+                        sound_config.sample_rate,
+                        1,
+                        r,
+                    ));
+                    let sn76489 = Sn76489::new(card.io_base, sound_config.sample_rate, s);
+                    add_io_device!(self, sn76489, IoDeviceType::Sn76489);
+                    self.sn76489 = Some(sn76489);
+                }
+                #[allow(unreachable_patterns)]
+                _ => {
+                    log::warn!("Sound device {:?} not supported in this build.", card.sound_type);
+                }
             }
         }
 
@@ -2093,7 +3250,10 @@ impl BusInterface {
                     video_dispatch = VideoCardDispatch::Mda(mda)
                 }
                 VideoType::CGA => {
-                    let cga = CGACard::new(TraceLogger::None, clock_mode, video_frame_debug);
+                    let mut cga = CGACard::new(TraceLogger::None, clock_mode, video_frame_debug);
+                    if card.snow.unwrap_or(false) {
+                        cga.set_video_option(VideoOption::EnableSnow(true));
+                    }
                     add_io_device!(self, cga, IoDeviceType::Video(video_id));
                     add_mmio_device!(self, cga, MmioDeviceType::Video(video_id));
                     video_dispatch = VideoCardDispatch::Cga(cga)
@@ -2133,6 +3293,23 @@ impl BusInterface {
             self.videocard_ids.push(video_id);
         }
 
+        // Create the POST code monitor last, so that it only claims ports not used by another device.
+        let post_code_ports: Vec<u16> = post_code_ports
+            .into_iter()
+            .filter(|port| {
+                let free = !self.io_map.contains_key(port);
+                if !free {
+                    log::warn!("POST code port {:04X} is in use by another device, ignoring.", port);
+                }
+                free
+            })
+            .collect();
+        if !post_code_ports.is_empty() {
+            let post_code_monitor = PostCodeMonitor::new(post_code_ports);
+            add_io_device!(self, post_code_monitor, IoDeviceType::PostCode);
+            self.post_code_monitor = Some(post_code_monitor);
+        }
+
         self.machine_desc = Some(machine_desc.clone());
         Ok(installed_devices)
     }
@@ -2140,7 +3317,8 @@ impl BusInterface {
     /// Return whether NMI is enabled.
     /// On the 5150 & 5160, NMI generation can be disabled via the PPI.
     pub fn nmi_enabled(&self) -> bool {
-        match self.machine_desc.unwrap().machine_type {
+        // A bus without a machine, as built for tests, has nothing to mask NMI.
+        match self.machine_desc.map_or(MachineType::Default, |desc| desc.machine_type) {
             // TODO: Add other types?
             MachineType::Ibm5150v64K | MachineType::Ibm5150v256K | MachineType::Ibm5160 => {
                 if let Some(ppi) = &self.ppi {
@@ -2171,6 +3349,13 @@ impl BusInterface {
 
     pub fn process_keyboard_input(&mut self) {
         if let Some(keyboard) = &mut self.keyboard {
+            // The keyboard holds onto its scancodes until the PPI's shift register can accept them.
+            if let Some(ppi) = &self.ppi {
+                if !ppi.kb_ready() {
+                    return;
+                }
+            }
+
             // Read a byte from the keyboard
             if let Some(kb_byte) = keyboard.recv_scancode() {
                 //log::debug!("Received keyboard byte: {:02X}", kb_byte);
@@ -2186,6 +3371,9 @@ impl BusInterface {
                                 a0.set_nmi_latch(true);
                             }
                         }
+                        MachineType::Ibm5150v64K | MachineType::Ibm5150v256K | MachineType::Ibm5160 => {
+                            // The PPI raises IRQ1 itself once the scancode has been shifted in.
+                        }
                         _ => {
                             if ppi.kb_enabled() {
                                 if let Some(pic) = &mut self.pic1 {
@@ -2213,6 +3401,7 @@ impl BusInterface {
 
         //let analyzer_ref = logic_analyzer.as_mut();
 
+        let profile_start = DeviceProfile::start(&self.device_profile);
         let mut process_keyboard = false;
         if let Some(keyboard) = &mut self.keyboard {
             self.kb_us_accum += us;
@@ -2238,16 +3427,20 @@ impl BusInterface {
         if process_keyboard {
             self.process_keyboard_input();
         }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Keyboard, profile_start);
 
         // There will always be a PIC, so safe to unwrap.
         let pic = self.pic1.as_mut().unwrap();
 
+        let profile_start = DeviceProfile::start(&self.device_profile);
         pic.run(sys_ticks);
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Pic, profile_start);
 
         // There will always be a PIT, so safe to unwrap.
         let mut pit = self.pit.take().unwrap();
 
         // Run the A0 register. It doesn't need a time delta.
+        let profile_start = DeviceProfile::start(&self.device_profile);
         let mut ppi_nmi_latch = None;
         if let Some(a0) = &mut self.a0 {
             let new_nmi_latch = a0.run(&mut pit, 0.0);
@@ -2272,6 +3465,7 @@ impl BusInterface {
             }
             ppi.run(pic, us);
         }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Ppi, profile_start);
 
         // Run the PIT. The PIT communicates with lots of things, so we send it the entire bus.
         // The PIT may have a separate clock crystal, such as in the IBM AT. In this case, there may not
@@ -2279,6 +3473,7 @@ impl BusInterface {
         // system ticks (PC/XT) or microseconds as an update parameter.
 
         // Currently the timer can only update the logic analyzer if it is ticked via system ticks.
+        let profile_start = DeviceProfile::start(&self.device_profile);
         if let Some(_crystal) = self.machine_desc.unwrap().timer_crystal {
             pit.run(self, DeviceRunTimeUnit::Microseconds(us), None);
         }
@@ -2306,10 +3501,12 @@ impl BusInterface {
 
         // Put the PIT back.
         self.pit = Some(pit);
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Pit, profile_start);
 
         let mut dma1 = self.dma1.take().unwrap();
 
         // Run the FDC, passing it DMA controller while DMA is still unattached.
+        let profile_start = DeviceProfile::start(&self.device_profile);
         if let Some(mut fdc) = self.fdc.take() {
             fdc.run(&mut dma1, self, us);
             self.fdc = Some(fdc);
@@ -2325,16 +3522,22 @@ impl BusInterface {
             xtide.run(&mut dma1, self, us);
             self.xtide = Some(xtide);
         }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Storage, profile_start);
 
         // Run the DMA controller.
+        let profile_start = DeviceProfile::start(&self.device_profile);
         dma1.run(self);
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Dma, profile_start);
 
         // Replace the DMA controller.
         self.dma1 = Some(dma1);
 
         // Run the serial port and mouse.
+        let profile_start = DeviceProfile::start(&self.device_profile);
         if let Some(serial) = &mut self.serial {
-            serial.run(&mut self.pic1.as_mut().unwrap(), us);
+            let (ticks, cycles) = self.cpu_factor.ratio();
+            let cpu_cycles = sys_ticks as u64 * cycles as u64 / ticks as u64;
+            serial.run(&mut self.pic1.as_mut().unwrap(), us, cpu_cycles);
 
             if let Some(mouse) = &mut self.mouse {
                 mouse.run(serial, us);
@@ -2346,13 +3549,40 @@ impl BusInterface {
             game_port.run(us);
         }
 
+        // Run the real time clock. IRQs 8-15 are on the secondary PIC, if present.
+        if let Some(rtc) = &mut self.rtc {
+            let pic = match rtc.irq() {
+                Some(irq) if irq >= 8 => self.pic2.as_mut(),
+                Some(_) => self.pic1.as_mut(),
+                None => None,
+            };
+            rtc.run(pic, us);
+        }
+
+        // Advance the POST code monitor's cycle count
+        if let Some(post_code_monitor) = &mut self.post_code_monitor {
+            let (ticks, cycles) = self.cpu_factor.ratio();
+            let cpu_cycles = (sys_ticks as u64 * cycles as u64 / ticks as u64) as u32;
+            post_code_monitor.run(cpu_cycles as u64);
+        }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Serial, profile_start);
+
         // Run the adlib card {
+        let profile_start = DeviceProfile::start(&self.device_profile);
         #[cfg(feature = "opl")]
         if let Some(adlib) = &mut self.adlib {
             adlib.run(us);
         }
 
+        // Run the SN76489 sound chip
+        #[cfg(feature = "sound")]
+        if let Some(sn76489) = &mut self.sn76489 {
+            sn76489.run(us);
+        }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Sound, profile_start);
+
         // Run all video cards
+        let profile_start = DeviceProfile::start(&self.device_profile);
         for (_vid, video_dispatch) in self.videocards.iter_mut() {
             match video_dispatch {
                 VideoCardDispatch::Mda(mda) => {
@@ -2394,6 +3624,26 @@ impl BusInterface {
                 VideoCardDispatch::None => {}
             }
         }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::Video, profile_start);
+
+        // Run ISA cards and update their IRQ lines.
+        let profile_start = DeviceProfile::start(&self.device_profile);
+        if let Some(pic) = self.pic1.as_mut() {
+            for isa_slot in self.isa_slots.iter_mut().flatten() {
+                isa_slot.card.tick(sys_ticks);
+                let irq = isa_slot.card.get_irq_line().filter(|irq| *irq < 8);
+                if irq != isa_slot.irq {
+                    if let Some(old_irq) = isa_slot.irq {
+                        pic.clear_interrupt(old_irq);
+                    }
+                    if let Some(new_irq) = irq {
+                        pic.request_interrupt(new_irq);
+                    }
+                    isa_slot.irq = irq;
+                }
+            }
+        }
+        DeviceProfile::end(&mut self.device_profile, ProfiledDevice::IsaCards, profile_start);
 
         // Commit logic analyzer if present
         logic_analyzer.as_mut().map(|la| la.commit());
@@ -2540,10 +3790,7 @@ impl BusInterface {
     /// to optionally tick itself to bring itself in sync with CPU state.
     pub fn io_read_u8(&mut self, port: u16, cycles: u32) -> u8 {
         // Convert cycles to system clock ticks
-        let sys_ticks = match self.cpu_factor {
-            ClockFactor::Divisor(d) => d as u32 * cycles,
-            ClockFactor::Multiplier(m) => cycles / m as u32,
-        };
+        let sys_ticks = self.cpu_factor.cycles_to_ticks(cycles);
         let nul_delta = DeviceRunTimeUnit::Microseconds(0.0);
         let mut byte = None;
         if let Some(device_id) = self.io_map.get(&port) {
@@ -2622,6 +3869,11 @@ impl BusInterface {
                         byte = Some(game_port.read_u8(port, nul_delta));
                     }
                 }
+                IoDeviceType::Rtc => {
+                    if let Some(rtc) = &mut self.rtc {
+                        byte = Some(rtc.read_u8(port, nul_delta));
+                    }
+                }
                 IoDeviceType::Video(vid) => {
                     if let Some(video_dispatch) = self.videocards.get_mut(&vid) {
                         byte = match video_dispatch {
@@ -2649,11 +3901,28 @@ impl BusInterface {
                         byte = Some(adlib.read_u8(port, nul_delta));
                     }
                 }
+                IoDeviceType::Sn76489 =>
+                {
+                    #[cfg(feature = "sound")]
+                    if let Some(sn76489) = &mut self.sn76489 {
+                        byte = Some(sn76489.read_u8(port, nul_delta));
+                    }
+                }
+                IoDeviceType::PostCode => {
+                    if let Some(post_code_monitor) = &mut self.post_code_monitor {
+                        byte = Some(post_code_monitor.read_u8(port, nul_delta));
+                    }
+                }
+                IoDeviceType::IsaCard(slot) => {
+                    if let Some(isa_slot) = &mut self.isa_slots[*slot] {
+                        byte = isa_slot.card.io_read(port);
+                    }
+                }
                 _ => {}
             }
         }
 
-        let byte_val = byte.unwrap_or(NO_IO_BYTE);
+        let byte_val = byte.unwrap_or_else(|| self.io_open_bus_byte());
 
         self.io_stats
             .entry(port)
@@ -2667,16 +3936,26 @@ impl BusInterface {
         byte_val
     }
 
+    /// Return the byte read from an IO port no device responds to. With a floating bus, this is the
+    /// last byte written to the data bus.
+    #[inline]
+    pub fn io_open_bus_byte(&self) -> u8 {
+        if self.io_floating_bus {
+            self.last_bus_write
+        }
+        else {
+            self.io_open_bus_byte
+        }
+    }
+
     /// Write an 8-bit value to an IO port.
     ///
     /// We provide the elapsed cycle count for the current instruction. This allows a device
     /// to optionally tick itself to bring itself in sync with CPU state.
     pub fn io_write_u8(&mut self, port: u16, data: u8, cycles: u32, analyzer: Option<&mut LogicAnalyzer>) {
+        self.last_bus_write = data;
         // Convert cycles to system clock ticks
-        let sys_ticks = match self.cpu_factor {
-            ClockFactor::Divisor(n) => cycles * (n as u32),
-            ClockFactor::Multiplier(n) => cycles / (n as u32),
-        };
+        let sys_ticks = self.cpu_factor.cycles_to_ticks(cycles);
 
         // Handle terminal debug port
         if let Some(terminal_port) = self.terminal_port {
@@ -2796,6 +4075,12 @@ impl BusInterface {
                         resolved = true;
                     }
                 }
+                IoDeviceType::Rtc => {
+                    if let Some(rtc) = &mut self.rtc {
+                        rtc.write_u8(port, data, None, nul_delta, analyzer);
+                        resolved = true;
+                    }
+                }
                 IoDeviceType::Video(vid) => {
                     if let Some(video_dispatch) = self.videocards.get_mut(&vid) {
                         match video_dispatch {
@@ -2853,6 +4138,24 @@ impl BusInterface {
                         IoDevice::write_u8(adlib, port, data, None, nul_delta, analyzer);
                     }
                 }
+                IoDeviceType::Sn76489 =>
+                {
+                    #[cfg(feature = "sound")]
+                    if let Some(sn76489) = &mut self.sn76489 {
+                        IoDevice::write_u8(sn76489, port, data, None, nul_delta, analyzer);
+                    }
+                }
+                IoDeviceType::PostCode => {
+                    if let Some(post_code_monitor) = &mut self.post_code_monitor {
+                        post_code_monitor.write_code(data, cycles);
+                        resolved = true;
+                    }
+                }
+                IoDeviceType::IsaCard(slot) => {
+                    if let Some(isa_slot) = &mut self.isa_slots[*slot] {
+                        resolved = isa_slot.card.io_write(port, data);
+                    }
+                }
                 _ => {}
             }
         }
@@ -2910,10 +4213,18 @@ impl BusInterface {
         &mut self.fdc
     }
 
+    pub fn hdc(&self) -> &Option<HardDiskController> {
+        &self.hdc
+    }
+
     pub fn hdc_mut(&mut self) -> &mut Option<HardDiskController> {
         &mut self.hdc
     }
 
+    pub fn xtide(&self) -> &Option<XtIdeController> {
+        &self.xtide
+    }
+
     pub fn xtide_mut(&mut self) -> &mut Option<XtIdeController> {
         &mut self.xtide
     }
@@ -2926,10 +4237,173 @@ impl BusInterface {
         &mut self.game_port
     }
 
+    pub fn rtc(&self) -> Option<&RtcDispatch> {
+        self.rtc.as_ref()
+    }
+
+    pub fn rtc_mut(&mut self) -> Option<&mut RtcDispatch> {
+        self.rtc.as_mut()
+    }
+
+    pub fn post_code_monitor(&self) -> Option<&PostCodeMonitor> {
+        self.post_code_monitor.as_ref()
+    }
+
+    pub fn post_code_monitor_mut(&mut self) -> Option<&mut PostCodeMonitor> {
+        self.post_code_monitor.as_mut()
+    }
+
     pub fn mouse_mut(&mut self) -> &mut Option<Mouse> {
         &mut self.mouse
     }
 
+    /// Snapshot the state of all devices on the bus.
+    pub fn save_device_state(&self) -> DeviceSaveState {
+        DeviceSaveState {
+            pic1: self.pic1.as_ref().map(|pic| pic.save_state()),
+            pic2: self.pic2.as_ref().map(|pic| pic.save_state()),
+            pit: self.pit.as_ref().map(|pit| pit.save_state()),
+            dma1: self.dma1.as_ref().map(|dma| dma.save_state()),
+            dma2: self.dma2.as_ref().map(|dma| dma.save_state()),
+            fdc: self.fdc.as_ref().map(|fdc| fdc.save_state()),
+            hdc: self.hdc.as_ref().map(|hdc| hdc.save_state()),
+            xtide: self.xtide.as_ref().map(|xtide| xtide.save_state()),
+            ppi: self.ppi.as_ref().map(|ppi| ppi.save_state()),
+            a0: self.a0.as_ref().map(|a0| a0.save_state()),
+            keyboard: self.keyboard.as_ref().map(|keyboard| keyboard.save_state()),
+            serial: self.serial.as_ref().map(|serial| serial.save_state()),
+            parallel: self.parallel.as_ref().map(|parallel| parallel.save_state()),
+            mouse: self.mouse.as_ref().map(|mouse| mouse.save_state()),
+            ems: self.ems.as_ref().map(|ems| ems.save_state()),
+            cart_slot: self.cart_slot.as_ref().map(|cart_slot| cart_slot.save_state()),
+            game_port: self.game_port.as_ref().map(|game_port| game_port.save_state()),
+            rtc: self.rtc.as_ref().map(|rtc| rtc.save_state()),
+            video: (!self.videocards.is_empty()).then(|| {
+                self.videocard_ids
+                    .iter()
+                    .filter_map(|vid| self.videocards.get(vid))
+                    .map(|video| video.save_state())
+                    .collect()
+            }),
+            #[cfg(feature = "opl")]
+            adlib: self.adlib.as_ref().map(|adlib| adlib.save_state()),
+            #[cfg(feature = "sound")]
+            sn76489: self.sn76489.as_ref().map(|sn76489| sn76489.save_state()),
+        }
+    }
+
+    /// Check that a snapshot holds the state of every device installed on the bus, so that no
+    /// device is left running with its current state when the snapshot is restored.
+    pub fn verify_device_state(&self, state: &DeviceSaveState) -> Result<(), SaveStateError> {
+        let mut missing = None;
+        let mut check = |present: bool, saved: bool, name: &str| {
+            if present && !saved && missing.is_none() {
+                missing = Some(name.to_string());
+            }
+        };
+        check(self.pic1.is_some(), state.pic1.is_some(), "Primary PIC");
+        check(self.pic2.is_some(), state.pic2.is_some(), "Secondary PIC");
+        check(self.pit.is_some(), state.pit.is_some(), "PIT");
+        check(self.dma1.is_some(), state.dma1.is_some(), "Primary DMA Controller");
+        check(self.dma2.is_some(), state.dma2.is_some(), "Secondary DMA Controller");
+        check(self.fdc.is_some(), state.fdc.is_some(), "Floppy Disk Controller");
+        check(self.hdc.is_some(), state.hdc.is_some(), "Hard Disk Controller");
+        check(self.xtide.is_some(), state.xtide.is_some(), "XT-IDE Controller");
+        check(self.ppi.is_some(), state.ppi.is_some(), "PPI");
+        check(self.a0.is_some(), state.a0.is_some(), "A0 Register");
+        check(self.keyboard.is_some(), state.keyboard.is_some(), "Keyboard");
+        check(self.serial.is_some(), state.serial.is_some(), "Serial Ports");
+        check(self.parallel.is_some(), state.parallel.is_some(), "Parallel Ports");
+        check(self.mouse.is_some(), state.mouse.is_some(), "Mouse");
+        check(self.ems.is_some(), state.ems.is_some(), "EMS Card");
+        check(self.cart_slot.is_some(), state.cart_slot.is_some(), "Cartridge Slot");
+        check(self.game_port.is_some(), state.game_port.is_some(), "Game Port");
+        check(self.rtc.is_some(), state.rtc.is_some(), "Real Time Clock");
+        check(!self.videocards.is_empty(), state.video.is_some(), "Video Card");
+        #[cfg(feature = "opl")]
+        check(self.adlib.is_some(), state.adlib.is_some(), "AdLib");
+        #[cfg(feature = "sound")]
+        check(self.sn76489.is_some(), state.sn76489.is_some(), "SN76489");
+        match missing {
+            Some(name) => Err(SaveStateError::MissingDeviceState(name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Restore device state from a snapshot. Devices absent from either the snapshot or the bus
+    /// are left untouched.
+    pub fn restore_device_state(&mut self, state: DeviceSaveState) {
+        if let (Some(pic), Some(saved)) = (self.pic1.as_mut(), state.pic1) {
+            pic.restore_state(saved);
+        }
+        if let (Some(pic), Some(saved)) = (self.pic2.as_mut(), state.pic2) {
+            pic.restore_state(saved);
+        }
+        if let (Some(pit), Some(saved)) = (self.pit.as_mut(), state.pit) {
+            pit.restore_state(saved);
+        }
+        if let (Some(dma), Some(saved)) = (self.dma1.as_mut(), state.dma1) {
+            dma.restore_state(saved);
+        }
+        if let (Some(dma), Some(saved)) = (self.dma2.as_mut(), state.dma2) {
+            dma.restore_state(saved);
+        }
+        if let (Some(fdc), Some(saved)) = (self.fdc.as_mut(), state.fdc) {
+            fdc.restore_state(saved);
+        }
+        if let (Some(hdc), Some(saved)) = (self.hdc.as_mut(), state.hdc) {
+            hdc.restore_state(saved);
+        }
+        if let (Some(xtide), Some(saved)) = (self.xtide.as_mut(), state.xtide) {
+            xtide.restore_state(saved);
+        }
+        if let (Some(ppi), Some(saved)) = (self.ppi.as_mut(), state.ppi) {
+            ppi.restore_state(saved);
+        }
+        if let (Some(a0), Some(saved)) = (self.a0.as_mut(), state.a0) {
+            a0.restore_state(saved);
+        }
+        if let (Some(keyboard), Some(saved)) = (self.keyboard.as_mut(), state.keyboard) {
+            keyboard.restore_state(saved);
+        }
+        if let (Some(serial), Some(saved)) = (self.serial.as_mut(), state.serial) {
+            serial.restore_state(saved);
+        }
+        if let (Some(parallel), Some(saved)) = (self.parallel.as_mut(), state.parallel) {
+            parallel.restore_state(saved);
+        }
+        if let (Some(mouse), Some(saved)) = (self.mouse.as_mut(), state.mouse) {
+            mouse.restore_state(saved);
+        }
+        if let (Some(ems), Some(saved)) = (self.ems.as_mut(), state.ems) {
+            ems.restore_state(saved);
+        }
+        if let (Some(cart_slot), Some(saved)) = (self.cart_slot.as_mut(), state.cart_slot) {
+            cart_slot.restore_state(saved);
+        }
+        if let (Some(game_port), Some(saved)) = (self.game_port.as_mut(), state.game_port) {
+            game_port.restore_state(saved);
+        }
+        if let (Some(rtc), Some(saved)) = (self.rtc.as_mut(), state.rtc) {
+            rtc.restore_state(saved);
+        }
+        if let Some(saved) = state.video {
+            for (vid, saved) in self.videocard_ids.iter().zip(saved) {
+                if let Some(video) = self.videocards.get_mut(vid) {
+                    video.restore_state(saved);
+                }
+            }
+        }
+        #[cfg(feature = "opl")]
+        if let (Some(adlib), Some(saved)) = (self.adlib.as_mut(), state.adlib) {
+            adlib.restore_state(saved);
+        }
+        #[cfg(feature = "sound")]
+        if let (Some(sn76489), Some(saved)) = (self.sn76489.as_mut(), state.sn76489) {
+            sn76489.restore_state(saved);
+        }
+    }
+
     pub fn primary_video(&self) -> Option<Box<&dyn VideoCard>> {
         if self.videocard_ids.len() > 0 {
             self.video(&self.videocard_ids[0])
@@ -3061,6 +4535,10 @@ impl BusInterface {
         }
     }
 
+    pub fn keyboard(&self) -> Option<&Keyboard> {
+        self.keyboard.as_ref()
+    }
+
     pub fn keyboard_mut(&mut self) -> Option<&mut Keyboard> {
         self.keyboard.as_mut()
     }
@@ -3125,3 +4603,373 @@ impl BusInterface {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_stats_count_reads_and_writes() {
+        let mut bus = BusInterface::default();
+
+        // Nothing is counted until page stats are enabled.
+        bus.read_u8(0x1234, 0).unwrap();
+        assert!(bus.get_page_stats().iter().all(|stats| *stats == (0, 0)));
+
+        bus.set_page_stats_enabled(true);
+        bus.write_u8(0x1234, 0x55, 0).unwrap();
+        bus.write_u16(0x1236, 0xAA55, 0).unwrap();
+        bus.read_u8(0x1234, 0).unwrap();
+        bus.read_u16(0xF_F000, 0).unwrap();
+        assert_eq!(bus.get_page_stats().len(), PAGE_STATS_LEN);
+        assert_eq!(bus.get_page_stats()[0x01], (1, 2));
+        assert_eq!(bus.get_page_stats()[0xFF], (1, 0));
+
+        // Reads made for the debugger aren't counted.
+        bus.dump_ivt_tokens();
+        assert_eq!(bus.get_page_stats()[0x00], (0, 0));
+
+        bus.reset_page_stats();
+        assert!(bus.get_page_stats().iter().all(|stats| *stats == (0, 0)));
+    }
+
+    #[test]
+    fn wait_regions_apply_only_within_their_range() {
+        let mut bus = BusInterface::default();
+        bus.set_wait_regions(&[WaitStateRegionConfig {
+            start: 0x1800,
+            end:   0x27FF,
+            read:  3,
+            write: 5,
+        }]);
+
+        let (_, base_read) = bus.read_u8(0x0100, 0).unwrap();
+        let base_write = bus.write_u8(0x0100, 0, 0).unwrap();
+        // On a page the region overlaps, but outside the region itself.
+        assert_eq!(bus.read_u8(0x17FF, 0).unwrap().1, base_read);
+        assert_eq!(bus.read_u8(0x1800, 0).unwrap().1, base_read + 3);
+        assert_eq!(bus.write_u8(0x27FF, 0, 0).unwrap(), base_write + 5);
+        assert_eq!(bus.write_u8(0x2800, 0, 0).unwrap(), base_write);
+    }
+
+    #[test]
+    fn exec_coverage_marks_instruction_bytes() {
+        let mut bus = BusInterface::default();
+
+        bus.mark_executed(0x1FFFE, 3);
+        bus.mark_executed(0xFFFFF, 2);
+        assert!(bus.is_executed(0x1FFFE));
+        assert!(bus.is_executed(0x20000));
+        assert!(!bus.is_executed(0x20001));
+        assert!(bus.is_executed(0x00000));
+
+        let summary = bus.exec_coverage_summary();
+        assert_eq!(summary.len(), 16);
+        assert_eq!(summary[0x0], 1);
+        assert_eq!(summary[0x1], 2);
+        assert_eq!(summary[0x2], 1);
+        assert_eq!(summary[0xF], 1);
+
+        bus.clear_exec_coverage();
+        assert!(bus.exec_coverage_summary().iter().all(|&n| n == 0));
+    }
+
+    #[test]
+    fn fill_skips_rom_and_can_be_undone() {
+        let mut bus = BusInterface::default();
+        bus.set_ram_regions(&[RamRegionConfig {
+            address: 0,
+            size:    0x10000,
+        }]);
+        bus.copy_from(&[0x11, 0x22], 0x1004, 0, true).unwrap();
+        bus.write_u8(0x1000, 0x55, 0).unwrap();
+
+        let snapshot = bus.memory_snapshot();
+        assert_eq!(bus.fill_u8(0x1000, 8, 0xCC), (6, 2));
+        assert_eq!(
+            bus.peek_range(0x1000, 8).unwrap(),
+            &[0xCC, 0xCC, 0xCC, 0xCC, 0x11, 0x22, 0xCC, 0xCC]
+        );
+
+        assert!(bus.restore_memory(&snapshot));
+        assert_eq!(bus.peek_range(0x1000, 8).unwrap(), &[0x55, 0, 0, 0, 0x11, 0x22, 0, 0]);
+        assert!(!bus.restore_memory(&snapshot[..0x1000]));
+    }
+
+    #[test]
+    fn cart_slot_leaves_unpopulated_addresses_to_system_memory() {
+        use crate::devices::cartridge_slots::{cart_crc, CARTRIDGE_BLOCK_SIZE};
+        use marty_common::types::cartridge::CartImage;
+
+        let mut bus = BusInterface::default();
+        let bios: Vec<u8> = (0..0x10000).map(|i| (i >> 4) as u8).collect();
+        bus.copy_from(&bios, 0xF0000, 0, true).unwrap();
+        let cart_slot = CartridgeSlot::new();
+        add_mmio_device!(bus, cart_slot, MmioDeviceType::Cart);
+        bus.cart_slot = Some(cart_slot);
+
+        // A single 2K ROM module at E000:0000.
+        let mut image = vec![0u8; 0x800];
+        image[0..3].copy_from_slice(&[0x55, 0xAA, (0x800 / CARTRIDGE_BLOCK_SIZE) as u8]);
+        let crc = cart_crc(&image[..0x7FE]);
+        image[0x7FE..].copy_from_slice(&crc.to_be_bytes());
+        let cart = CartImage {
+            creator: String::new(),
+            comment: String::new(),
+            version_major: 1,
+            version_minor: 0,
+            address_seg: 0xE000,
+            address_mask: 0,
+            image,
+        };
+        bus.cart_slot_mut().as_mut().unwrap().insert_cart(0, cart).unwrap();
+
+        // The F segment is within the cartridge window, but no cartridge populates it.
+        assert_eq!(bus.read_u8(0xFFFF0, 0).unwrap().0, 0xFF);
+        assert_eq!(bus.read_u16(0xF1230, 0).unwrap().0, 0x2323);
+        assert_eq!(bus.peek_u8(0xF4560).unwrap(), 0x56);
+        assert_eq!(bus.get_read_wait(0xF0000, 0).unwrap(), DEFAULT_WAIT_STATES);
+        // The BIOS stays read-only.
+        bus.write_u8(0xF0000, 0xAA, 0).unwrap();
+        assert_eq!(bus.peek_u8(0xF0000).unwrap(), 0x00);
+
+        bus.write_u8(0xE0000, 0x00, 0).unwrap();
+        assert_eq!(bus.read_u16(0xE0000, 0).unwrap().0, 0xAA55);
+        assert_eq!(bus.peek_u8(0xE0800).unwrap(), bus.memory[0xE0800]);
+    }
+
+    #[test]
+    fn debug_write_rejects_rom_unless_forced() {
+        let mut bus = BusInterface::default();
+        bus.set_ram_regions(&[RamRegionConfig {
+            address: 0,
+            size:    0x10000,
+        }]);
+        bus.copy_from(&[0x11, 0x22], 0x1004, 0, true).unwrap();
+        bus.write_u8(0x1000, 0x55, 0).unwrap();
+
+        assert_eq!(
+            bus.debug_write_u8(0x1000, 0xAA, false),
+            Some(DebugWrite {
+                address: 0x1000,
+                old: 0x55,
+                new: 0xAA,
+            })
+        );
+        assert_eq!(bus.peek_u8(0x1000).unwrap(), 0xAA);
+
+        // ROM is left alone unless the write is forced.
+        assert_eq!(bus.debug_write_u8(0x1004, 0x33, false), None);
+        assert_eq!(bus.peek_u8(0x1004).unwrap(), 0x11);
+        assert_eq!(
+            bus.debug_write_u8(0x1004, 0x33, true),
+            Some(DebugWrite {
+                address: 0x1004,
+                old: 0x11,
+                new: 0x33,
+            })
+        );
+        assert_eq!(bus.peek_u8(0x1004).unwrap(), 0x33);
+        assert!(bus.is_rom(0x1004));
+
+        // Unpopulated memory can't be written even when forced.
+        assert_eq!(bus.debug_write_u8(0x20000, 0x44, true), None);
+    }
+
+    #[test]
+    fn scan_finds_extension_roms() {
+        let mut bus = BusInterface::default();
+        let mut rom = vec![0u8; 0x1000];
+        rom[0..3].copy_from_slice(&[0x55, 0xAA, 0x08]);
+        // A signature within the ROM's declared length is not another ROM.
+        rom[0x800..0x802].copy_from_slice(&OPTION_ROM_SIGNATURE);
+        bus.copy_from(&rom, 0xC8000, 0, true).unwrap();
+        bus.copy_from(&[0x55, 0xAA, 0x01], 0xD0000, 0, true).unwrap();
+        // Not on a 2KB boundary
+        bus.copy_from(&[0x55, 0xAA, 0x01], 0xD1200, 0, true).unwrap();
+        // Beyond the end of the scan
+        bus.copy_from(&[0x55, 0xAA, 0x01], 0xF4000, 0, true).unwrap();
+
+        assert_eq!(bus.scan_extension_roms(), vec![0xC8000, 0xD0000]);
+
+        let path = std::env::temp_dir().join("martypc_extension_rom_test.bin");
+        std::fs::write(&path, [0x55, 0xAA, 0x01, 0xCB]).unwrap();
+        bus.load_extension_rom(&path, 0xE0000).unwrap();
+        assert!(bus.load_extension_rom(&path, 0xFFFFE).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(bus.peek_range(0xE0000, 4).unwrap(), &[0x55, 0xAA, 0x01, 0xCB]);
+        assert_eq!(bus.scan_extension_roms(), vec![0xC8000, 0xD0000, 0xE0000]);
+    }
+
+    #[test]
+    fn io_trace_records_filtered_accesses() {
+        let mut bus = BusInterface::default();
+        bus.set_io_trace_filter(Some(0x200..=0x20F));
+
+        let byte = bus.io_read_u8(0x201, 0);
+        bus.record_io_access(0x201, byte, IoDirection::Read, 0x1234, 0x0010, 1);
+        bus.io_write_u8(0x300, 0xAA, 0, None);
+        bus.record_io_access(0x300, 0xAA, IoDirection::Write, 0x1234, 0x0012, 2);
+
+        let trace = bus.io_trace().as_vec();
+        assert_eq!(
+            trace,
+            vec![IoTraceEntry {
+                port: 0x201,
+                value: NO_IO_BYTE,
+                direction: IoDirection::Read,
+                segment: 0x1234,
+                offset: 0x0010,
+                instruction: 1,
+                claimed: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn unclaimed_io_reads_return_open_bus_value() {
+        let mut bus = BusInterface::default();
+        assert_eq!(bus.io_read_u8(0x2F0, 0), NO_IO_BYTE);
+
+        bus.io_open_bus_byte = 0x00;
+        assert_eq!(bus.io_read_u8(0x2F0, 0), 0x00);
+
+        // A floating bus returns whatever was last written to it.
+        bus.io_floating_bus = true;
+        bus.io_write_u8(0x2F1, 0x5A, 0, None);
+        assert_eq!(bus.io_read_u8(0x2F0, 0), 0x5A);
+        bus.write_u16(0x1000, 0x1234, 0).unwrap();
+        assert_eq!(bus.io_read_u8(0x2F0, 0), 0x12);
+    }
+
+    /// Maps a single 4K bank over the start of its range when enabled.
+    struct TestBank {
+        base: usize,
+        enabled: bool,
+        ram: Vec<u8>,
+    }
+
+    impl BankController for TestBank {
+        fn bank_read_u8(&self, addr: usize) -> Option<u8> {
+            let offset = addr - self.base;
+            (self.enabled && offset < self.ram.len()).then(|| self.ram[offset])
+        }
+
+        fn bank_write_u8(&mut self, addr: usize, val: u8) -> bool {
+            let offset = addr - self.base;
+            if self.enabled && offset < self.ram.len() {
+                self.ram[offset] = val;
+                return true;
+            }
+            false
+        }
+    }
+
+    #[test]
+    fn bank_controller_intercepts_mapped_range() {
+        let mut bus = BusInterface::default();
+        bus.register_bank_controller(
+            0xB8000,
+            0x2000,
+            Box::new(TestBank {
+                base: 0xB8000,
+                enabled: true,
+                ram: vec![0; 0x1000],
+            }),
+        );
+
+        bus.write_u16(0xB8010, 0xBEEF, 0).unwrap();
+        assert_eq!(bus.read_u16(0xB8010, 0).unwrap().0, 0xBEEF);
+        assert_eq!(bus.peek_u8(0xB8011).unwrap(), 0xBE);
+
+        // Addresses the controller declines fall through to system memory.
+        bus.write_u8(0xB9000, 0x12, 0).unwrap();
+        assert_eq!(bus.read_u8(0xB9000, 0).unwrap().0, 0x12);
+        assert_eq!(bus.peek_u8(0xB8FFF).unwrap(), 0x00);
+    }
+
+    /// A card with a single data latch at port 0x300 and 8K of RAM at 0xD0000.
+    struct TestIsaCard {
+        latch: u8,
+        ram:   Vec<u8>,
+    }
+
+    impl IsaCard for TestIsaCard {
+        fn io_read(&mut self, port: u16) -> Option<u8> {
+            (port == 0x300).then_some(self.latch)
+        }
+
+        fn io_write(&mut self, port: u16, val: u8) -> bool {
+            self.latch = val;
+            port == 0x300
+        }
+
+        fn mem_read(&self, addr: u32) -> Option<u8> {
+            self.ram.get(addr as usize - 0xD0000).copied()
+        }
+
+        fn mem_write(&mut self, addr: u32, val: u8) -> bool {
+            self.ram[addr as usize - 0xD0000] = val;
+            true
+        }
+
+        fn tick(&mut self, _cycles: u32) {}
+
+        fn get_irq_line(&self) -> Option<u8> {
+            None
+        }
+
+        fn port_list(&self) -> Vec<(String, u16)> {
+            vec![("Test Card Latch".to_string(), 0x300)]
+        }
+
+        fn mem_ranges(&self) -> Vec<MemRangeDescriptor> {
+            vec![MemRangeDescriptor::new(0xD0000, 0x2000, false)]
+        }
+    }
+
+    fn test_isa_card() -> Box<dyn IsaCard> {
+        Box::new(TestIsaCard {
+            latch: 0,
+            ram:   vec![0; 0x2000],
+        })
+    }
+
+    #[test]
+    fn isa_card_is_wired_to_io_and_memory() {
+        let mut bus = BusInterface::default();
+        bus.insert_isa_card(2, test_isa_card()).unwrap();
+
+        bus.io_write_u8(0x300, 0xA5, 0, None);
+        assert_eq!(bus.io_read_u8(0x300, 0), 0xA5);
+
+        bus.write_u16(0xD0100, 0xCAFE, 0).unwrap();
+        assert_eq!(bus.read_u16(0xD0100, 0).unwrap().0, 0xCAFE);
+        assert_eq!(bus.peek_u8(0xD0101).unwrap(), 0xCA);
+        assert_eq!(bus.memory[0xD0100], 0x00);
+
+        assert!(bus.insert_isa_card(2, test_isa_card()).is_err());
+        assert!(bus.insert_isa_card(MAX_ISA_SLOTS, test_isa_card()).is_err());
+    }
+
+    #[test]
+    fn access_profiler_counts_cpu_accesses_per_frame() {
+        let mut bus = BusInterface::default();
+        bus.write_u8(0x1000, 0x55, 0).unwrap();
+        assert!(bus.access_profile().is_none());
+
+        bus.set_access_profiler_enabled(true);
+        bus.write_u8(0x1000, 0x55, 0).unwrap();
+        bus.read_u16(0x1000, 0).unwrap();
+        bus.end_access_profile_frame();
+
+        let profile = bus.access_profile().unwrap();
+        assert_eq!(profile[0].name, "Conventional RAM");
+        assert_eq!(profile[0].accesses, 2);
+        assert_eq!(profile.iter().map(|region| region.accesses).sum::<u64>(), 2);
+
+        bus.set_access_profiler_enabled(false);
+        assert!(!bus.access_profiler_enabled());
+    }
+}
@@ -61,6 +61,7 @@ pub trait CoreConfig {
     fn get_audio_enabled(&self) -> bool;
     fn get_machine_noroms(&self) -> bool;
     fn get_machine_turbo(&self) -> bool;
+    fn get_machine_fast_boot(&self) -> bool;
     //fn get_keyboard_type(&self) -> Option<KeyboardType>;
     fn get_keyboard_layout(&self) -> Option<String>;
     fn get_keyboard_debug(&self) -> bool;
@@ -78,4 +79,5 @@ pub trait CoreConfig {
     fn get_patch_enabled(&self) -> bool;
     fn get_halt_behavior(&self) -> OnHaltBehavior;
     fn get_terminal_port(&self) -> Option<u16>;
+    fn get_post_code_ports(&self) -> Vec<u16>;
 }
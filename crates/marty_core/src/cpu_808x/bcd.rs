@@ -120,6 +120,8 @@ impl Intel808x {
 
     /// Ascii adjust before Division
     /// Flags: The SF, ZF, and PF flags are set according to the resulting binary value in the AL register
+    /// The immediate operand is the number base. Assemblers only emit 10, but any base works, and
+    /// software uses others, e.g. 16 to combine hex digits.
     pub fn aad(&mut self, imm8: u8) {
         cycles_mc!(self, 0x170, 0x171, MC_JUMP);
         let product_native = (self.a.h() as u16).wrapping_mul(imm8 as u16) as u8;
@@ -242,7 +244,7 @@ impl Intel808x {
     /// AAM - Ascii adjust AX After multiply
     /// Flags: The SF, ZF, and PF flags are set according to the resulting binary value in the AL register
     /// As AAM is implemented via CORD, it can throw an exception. This is indicated by a return value
-    /// of false.
+    /// of false. The immediate operand is the number base, so a base of 0 raises a divide error.
     pub fn aam(&mut self, imm8: u8) -> bool {
         cycles_mc!(self, 0x175, 0x176, MC_JUMP);
         // 176: A->tmpc   | UNC CORD
@@ -262,3 +264,58 @@ impl Intel808x {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cpu_808x::{CPU_FLAG_PARITY, CPU_FLAG_ZERO},
+        cpu_common::{
+            test_util::{cpu_with_code, step},
+            Cpu,
+            CpuType,
+            Register16,
+        },
+    };
+
+    /// Execute a single instruction with the specified AX, returning the resulting AX and flags.
+    fn run_adjust(cpu_type: CpuType, code: &[u8], ax: u16) -> (u16, u16) {
+        let mut cpu = cpu_with_code(cpu_type, code);
+        cpu.set_register16(Register16::AX, ax);
+        step(&mut cpu);
+        (cpu.get_register16(Register16::AX), cpu.get_flags())
+    }
+
+    #[test]
+    fn aam_uses_immediate_base() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // AAM 16 splits AL into hex digits.
+            let (ax, flags) = run_adjust(cpu_type, &[0xD4, 0x10], 0x003F);
+            assert_eq!(ax, 0x030F);
+            assert_eq!(flags & CPU_FLAG_ZERO, 0);
+            assert_ne!(flags & CPU_FLAG_PARITY, 0);
+
+            let (ax, flags) = run_adjust(cpu_type, &[0xD4, 0x10], 0x0030);
+            assert_eq!(ax, 0x0300);
+            assert_ne!(flags & CPU_FLAG_ZERO, 0);
+        }
+    }
+
+    #[test]
+    fn aad_uses_immediate_base() {
+        // AAD 16 combines hex digits.
+        let (ax, flags) = run_adjust(CpuType::Intel8088, &[0xD5, 0x10], 0x030F);
+        assert_eq!(ax, 0x003F);
+        assert_eq!(flags & CPU_FLAG_ZERO, 0);
+
+        // The V20 ignores the operand and always uses base 10.
+        let (ax, _) = run_adjust(CpuType::NecV20, &[0xD5, 0x10], 0x030F);
+        assert_eq!(ax, 0x002D);
+    }
+
+    #[test]
+    fn aam_zero_on_v20_does_not_fault() {
+        // The 8088 raises a divide error; see the interrupt tests. The V20 sets AH to FF instead.
+        let (ax, _) = run_adjust(CpuType::NecV20, &[0xD4, 0x00], 0x0042);
+        assert_eq!(ax, 0xFF42);
+    }
+}
@@ -783,6 +783,7 @@ impl Intel808x {
             decode_idx,
             opcode,
             prefixes: op_prefixes,
+            modrm: loaded_modrm.then(|| modrm.byte()),
             address: 0,
             size,
             mnemonic: op_lu.mnemonic,
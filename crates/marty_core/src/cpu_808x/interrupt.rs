@@ -45,6 +45,47 @@ impl Intel808x {
         self.cycle_i(0x0ca);
     }
 
+    /// Return true if a breakpoint is set on the specified interrupt vector, taking any AH filter into
+    /// account.
+    pub fn interrupt_breakpoint(&self, vector: u8) -> bool {
+        let flags = self.int_flags[vector as usize];
+        flags & INTERRUPT_BREAKPOINT != 0
+            || (flags & INTERRUPT_FUNC_BREAKPOINT != 0
+                && self.breakpoints.iter().any(
+                    |bp| matches!(bp, BreakPointType::InterruptFunc { int, ah } if *int == vector && *ah == self.a.h()),
+                ))
+    }
+
+    /// Return true if a breakpoint is set on the vector of the pending hardware interrupt. The vector is
+    /// peeked from the PIC so that the interrupt is not acknowledged.
+    pub fn hw_interrupt_breakpoint(&self) -> bool {
+        self.int_breakpoints
+            && self
+                .bus
+                .pic()
+                .as_ref()
+                .and_then(|pic| pic.peek_interrupt_vector())
+                .is_some_and(|vector| self.interrupt_breakpoint(vector))
+    }
+
+    /// Return the vector of the software interrupt the instruction at the specified address would
+    /// raise, if any. We peek the instruction from memory so that an interrupt breakpoint can stop
+    /// on the INT instruction itself, with the caller's CS:IP and registers intact.
+    pub fn peek_sw_interrupt(&self, address: u32) -> Option<u8> {
+        let peek = |offset: u32| self.bus.peek_u8(((address + offset) & 0xFFFFF) as usize).ok();
+        let mut offset = 0;
+        // Skip over any prefixes. The CPU would keep going, but a run of prefixes this long is not an INT.
+        while offset < 16 && matches!(peek(offset), Some(0x26 | 0x2E | 0x36 | 0x3E | 0xF0 | 0xF2 | 0xF3)) {
+            offset += 1;
+        }
+        match peek(offset)? {
+            0xCC => Some(3),
+            0xCD => peek(offset + 1),
+            0xCE if self.get_flag(Flag::Overflow) => Some(4),
+            _ => None,
+        }
+    }
+
     /// Perform a software interrupt
     pub fn sw_interrupt(&mut self, interrupt: u8) {
         // Interrupt FC, emulator internal services.
@@ -87,6 +128,7 @@ impl Intel808x {
         }
 
         cycles_mc!(self, 0x19d, 0x19e, 0x19f);
+        self.int_vector_counts[interrupt as usize] += 1;
 
         // Read the IVT
         let vec_addr = (interrupt as usize * INTERRUPT_VEC_LEN) as u16;
@@ -120,53 +162,6 @@ impl Intel808x {
         self.int_count += 1;
     }
 
-    /*
-        /// Handle a CPU exception
-        pub fn handle_exception(&mut self, exception: u8) {
-            self.push_flags(ReadWriteFlag::Normal);
-
-            // Push return address of next instruction onto stack
-            self.push_register16(Register16::CS, ReadWriteFlag::Normal);
-
-            // Don't push address of next instruction
-            self.push_u16(self.ip, ReadWriteFlag::Normal);
-
-            if exception == 0x0 {
-                log::trace!(
-                    "CPU Exception: {:02X} Saving return: {:04X}:{:04X}",
-                    exception,
-                    self.cs,
-                    self.ip
-                );
-            }
-            // Read the IVT
-            let ivt_addr = Cpu::calc_linear_address(0x0000, (exception as usize * INTERRUPT_VEC_LEN) as u16);
-            let (new_ip, _cost) = self.bus.read_u16(ivt_addr as usize, 0).unwrap();
-            let (new_cs, _cost) = self.bus.read_u16((ivt_addr + 2) as usize, 0).unwrap();
-
-            // Add interrupt to call stack
-            self.push_call_stack(
-                CallStackEntry::Interrupt {
-                    ret_cs: self.cs,
-                    ret_ip: self.ip,
-                    call_cs: new_cs,
-                    call_ip: new_ip,
-                    itype: InterruptType::Exception,
-                    number: exception,
-                    ah: self.ah,
-                },
-                self.cs,
-                self.ip,
-            );
-
-            self.ip = new_ip;
-            self.cs = new_cs;
-
-            // Flush queue
-            self.biu_queue_flush();
-            self.biu_update_pc();
-        }
-    */
     #[allow(dead_code)]
     pub fn log_interrupt(&self, interrupt: u8) {
         match interrupt {
@@ -219,8 +214,9 @@ impl Intel808x {
     /// skip_first is used to skip the first microcode instruction, such as when entering from
     /// INT1 or INT2.
     pub fn intr_routine(&mut self, vector: u8, itype: InterruptType, skip_first: bool) {
-        // Check for interrupt breakpoint.
-        if self.int_flags[vector as usize] & INTERRUPT_BREAKPOINT != 0 {
+        // Check for interrupt breakpoint. Software and hardware interrupts are caught before they are taken.
+        if matches!(itype, InterruptType::NMI | InterruptType::Exception) && self.interrupt_breakpoint(vector) {
+            log::debug!("Interrupt breakpoint hit: {:02X}", vector);
             self.set_breakpoint_flag();
         }
 
@@ -228,6 +224,7 @@ impl Intel808x {
             self.cycle_i(0x019d);
         }
         cycles_mc!(self, 0x19e, 0x19f);
+        self.int_vector_counts[vector as usize] += 1;
 
         // Read the IVT
         let vec_addr = (vector as usize * INTERRUPT_VEC_LEN) as u16;
@@ -277,6 +274,9 @@ impl Intel808x {
     }
 
     /// Perform INT0 (Divide By 0)
+    /// The return address pushed is that of the instruction following the faulting DIV, IDIV or AAM,
+    /// as on the 8086. Software can tell these CPUs from a 286 or later, which push the address of the
+    /// faulting instruction including its prefixes.
     pub fn int0(&mut self) {
         cycles_mc!(self, 0x1a7, MC_JUMP);
         self.intr_routine(0, InterruptType::Exception, true);
@@ -334,3 +334,404 @@ impl Intel808x {
             && self.trap_enable_delay == 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        breakpoints::BreakPointType,
+        bus::BusInterface,
+        cpu_808x::{CPU_FLAG_INT_ENABLE, CPU_FLAG_OVERFLOW, CPU_FLAG_TRAP},
+        cpu_common::{
+            test_util::{cpu_with_code, step, CODE_SEG},
+            Cpu,
+            CpuAddress,
+            CpuDispatch,
+            CpuError,
+            CpuType,
+            Register16,
+            StepResult,
+        },
+        devices::pic::Pic,
+    };
+
+    const STACK_SEG: u16 = 0x3000;
+    const STACK_TOP: u16 = 0x0100;
+
+    /// Build an 8088 with the specified code at CODE_SEG:0000 and every interrupt vector pointing
+    /// to a handler at ((vector + 1) * 0x100):0000.
+    fn setup_cpu(code: &[u8], flags: u16) -> CpuDispatch {
+        setup_cpu_type(CpuType::Intel8088, code, flags)
+    }
+
+    fn setup_cpu_type(cpu_type: CpuType, code: &[u8], flags: u16) -> CpuDispatch {
+        let mut cpu = cpu_with_code(cpu_type, code);
+
+        for vector in 0..8u16 {
+            let handler_seg = (vector + 1) * 0x100;
+            let ivt = (vector as usize) * 4;
+            for (i, byte) in [0x00, 0x00, handler_seg as u8, (handler_seg >> 8) as u8]
+                .iter()
+                .enumerate()
+            {
+                cpu.bus_mut().write_u8(ivt + i, *byte, 0).unwrap();
+            }
+        }
+        cpu.set_register16(Register16::SS, STACK_SEG);
+        cpu.set_register16(Register16::SP, STACK_TOP);
+        cpu.set_flags(flags);
+        cpu
+    }
+
+    fn peek_u16(bus: &BusInterface, address: usize) -> u16 {
+        bus.peek_u8(address).unwrap() as u16 | (bus.peek_u8(address + 1).unwrap() as u16) << 8
+    }
+
+    /// Return the (IP, CS, FLAGS) frame pushed by the last interrupt.
+    fn int_frame(cpu: &CpuDispatch) -> (u16, u16, u16) {
+        let sp = ((STACK_SEG as usize) << 4) + cpu.get_register16(Register16::SP) as usize;
+        (
+            peek_u16(cpu.bus(), sp),
+            peek_u16(cpu.bus(), sp + 2),
+            peek_u16(cpu.bus(), sp + 4),
+        )
+    }
+
+    #[test]
+    fn ivt_write_records_writer() {
+        // NOP, MOV word [0084h], 1234h
+        let mut cpu = setup_cpu(&[0x90, 0xC7, 0x06, 0x84, 0x00, 0x34, 0x12], 0);
+        cpu.set_register16(Register16::DS, 0);
+        step(&mut cpu);
+        step(&mut cpu);
+
+        let ivt = cpu.bus().dump_ivt();
+        assert_eq!(ivt[0x21].offset, 0x1234);
+        let writer = ivt[0x21].written_by.expect("INT 21h writer should be recorded");
+        assert_eq!((writer.segment, writer.offset), (CODE_SEG, 0x0001));
+        // Vectors written by other means are not attributed to an instruction.
+        assert!(ivt[0x00].written_by.is_none());
+    }
+
+    #[test]
+    fn trap_after_instruction() {
+        // NOP, NOP
+        let mut cpu = setup_cpu(&[0x90, 0x90], CPU_FLAG_TRAP);
+        step(&mut cpu);
+
+        // INT 1 is taken after the first NOP, returning to the second.
+        assert_eq!(cpu.get_register16(Register16::CS), 0x0200);
+        assert_eq!(cpu.get_register16(Register16::SP), STACK_TOP - 6);
+        let (ip, cs, flags) = int_frame(&cpu);
+        assert_eq!((ip, cs), (0x0001, CODE_SEG));
+        assert_ne!(flags & CPU_FLAG_TRAP, 0);
+        // The trap flag is cleared on entry to the handler.
+        assert_eq!(cpu.get_flags() & CPU_FLAG_TRAP, 0);
+    }
+
+    #[test]
+    fn popf_setting_trap_delays_one_instruction() {
+        // POPF, NOP, NOP
+        let mut cpu = setup_cpu(&[0x9D, 0x90, 0x90], 0);
+        let sp = STACK_TOP - 2;
+        let stack_addr = ((STACK_SEG as usize) << 4) + sp as usize;
+        cpu.bus_mut().write_u8(stack_addr, CPU_FLAG_TRAP as u8, 0).unwrap();
+        cpu.bus_mut()
+            .write_u8(stack_addr + 1, (CPU_FLAG_TRAP >> 8) as u8, 0)
+            .unwrap();
+        cpu.set_register16(Register16::SP, sp);
+
+        // The POPF that sets TF does not trap itself.
+        step(&mut cpu);
+        assert_eq!(cpu.get_register16(Register16::CS), CODE_SEG);
+        assert_eq!(cpu.get_register16(Register16::SP), STACK_TOP);
+        assert_ne!(cpu.get_flags() & CPU_FLAG_TRAP, 0);
+
+        // The following instruction does.
+        step(&mut cpu);
+        assert_eq!(cpu.get_register16(Register16::CS), 0x0200);
+        let (ip, cs, _) = int_frame(&cpu);
+        assert_eq!((ip, cs), (0x0002, CODE_SEG));
+    }
+
+    #[test]
+    fn into_with_overflow() {
+        // INTO, NOP
+        let mut cpu = setup_cpu(&[0xCE, 0x90], CPU_FLAG_OVERFLOW);
+        step(&mut cpu);
+
+        assert_eq!(cpu.get_register16(Register16::CS), 0x0500);
+        let (ip, cs, _) = int_frame(&cpu);
+        assert_eq!((ip, cs), (0x0001, CODE_SEG));
+    }
+
+    #[test]
+    fn into_without_overflow() {
+        let mut cpu = setup_cpu(&[0xCE, 0x90], 0);
+        step(&mut cpu);
+
+        assert_eq!(cpu.get_register16(Register16::CS), CODE_SEG);
+        assert_eq!(cpu.get_register16(Register16::SP), STACK_TOP);
+    }
+
+    /// Interrupt a REP CS: MOVSB during its second iteration and return from the handler.
+    /// Returns the pushed IP and the CPU after the instruction at the return address has run
+    /// to completion.
+    fn interrupt_rep_movsb(cpu_type: CpuType) -> (u16, CpuDispatch) {
+        // REP CS: MOVSB, NOP
+        let mut cpu = setup_cpu_type(cpu_type, &[0xF3, 0x2E, 0xA4, 0x90], CPU_FLAG_INT_ENABLE);
+        cpu.set_register16(Register16::CX, 4);
+        cpu.set_register16(Register16::SI, 0x0010);
+        cpu.set_register16(Register16::ES, 0x2000);
+        cpu.set_register16(Register16::DI, 0);
+        // Without a PIC, hardware interrupts use vector 7. Its handler is a lone IRET.
+        cpu.bus_mut().write_u8(0x0800 << 4, 0xCF, 0).unwrap();
+
+        // First iteration. INTR rises during it and is taken by RPTI in the next.
+        cpu.step(false).unwrap();
+        cpu.set_intr(true);
+        cpu.step_finish(None).unwrap();
+        cpu.step(false).unwrap();
+        cpu.step_finish(None).unwrap();
+        cpu.set_intr(false);
+
+        assert_eq!(cpu.get_register16(Register16::CS), 0x0800);
+        assert_eq!(cpu.get_register16(Register16::CX), 2);
+        let (ip, cs, _) = int_frame(&cpu);
+        assert_eq!(cs, CODE_SEG);
+
+        // IRET, then run the resumed instruction until it reaches the NOP.
+        step(&mut cpu);
+        assert_eq!(cpu.get_register16(Register16::CS), CODE_SEG);
+        for _ in 0..8 {
+            if !cpu.in_rep() && cpu.get_ip() == 0x0003 {
+                break;
+            }
+            step(&mut cpu);
+        }
+        assert_eq!(cpu.get_ip(), 0x0003);
+        (ip, cpu)
+    }
+
+    #[test]
+    fn rep_interrupt_drops_prefixes_on_8088() {
+        // The 8088 rewinds to the last prefix only, so the REP is lost on return and a single MOVSB
+        // executes.
+        let (ip, cpu) = interrupt_rep_movsb(CpuType::Intel8088);
+        assert_eq!(ip, 0x0001);
+        assert_eq!(cpu.get_register16(Register16::CX), 2);
+        assert_eq!(cpu.get_register16(Register16::DI), 3);
+    }
+
+    #[test]
+    fn rep_interrupt_keeps_prefixes_on_v20() {
+        // The V20 rewinds over every prefix, so the REP completes on return.
+        let (ip, cpu) = interrupt_rep_movsb(CpuType::NecV20);
+        assert_eq!(ip, 0x0000);
+        assert_eq!(cpu.get_register16(Register16::CX), 0);
+        assert_eq!(cpu.get_register16(Register16::DI), 4);
+    }
+
+    #[test]
+    fn run_until_interrupt_returns_interrupted_address() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // NOP, NOP, NOP
+            let mut cpu = setup_cpu_type(cpu_type, &[0x90, 0x90, 0x90], CPU_FLAG_INT_ENABLE);
+            cpu.set_intr(true);
+            let address = cpu.run_until_interrupt(1000).unwrap();
+
+            assert_eq!(address, CpuAddress::Segmented(CODE_SEG, 0x0001));
+            assert_eq!(cpu.get_register16(Register16::CS), 0x0800);
+            let (ip, cs, _) = int_frame(&cpu);
+            assert_eq!((ip, cs), (0x0001, CODE_SEG));
+        }
+    }
+
+    #[test]
+    fn run_until_interrupt_returns_address_after_nmi_and_trap() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // NMI returns to the instruction after the one it interrupted, not into the handler.
+            let mut cpu = setup_cpu_type(cpu_type, &[0x90, 0x90, 0x90], 0);
+            cpu.set_nmi(true);
+            let address = cpu.run_until_interrupt(1000).unwrap();
+
+            assert_eq!(address, CpuAddress::Segmented(CODE_SEG, 0x0001));
+            assert_eq!(cpu.get_register16(Register16::CS), 0x0300);
+            let (ip, cs, _) = int_frame(&cpu);
+            assert_eq!((ip, cs), (0x0001, CODE_SEG));
+
+            // So does a single-step trap.
+            let mut cpu = setup_cpu_type(cpu_type, &[0x90, 0x90, 0x90], CPU_FLAG_TRAP);
+            let address = cpu.run_until_interrupt(1000).unwrap();
+
+            assert_eq!(address, CpuAddress::Segmented(CODE_SEG, 0x0001));
+            assert_eq!(cpu.get_register16(Register16::CS), 0x0200);
+            let (ip, cs, _) = int_frame(&cpu);
+            assert_eq!((ip, cs), (0x0001, CODE_SEG));
+        }
+    }
+
+    #[test]
+    fn run_until_interrupt_times_out() {
+        // JMP $
+        let mut cpu = setup_cpu(&[0xEB, 0xFE], CPU_FLAG_INT_ENABLE);
+        assert!(matches!(
+            cpu.run_until_interrupt(1000),
+            Err(CpuError::CpuHaltedError(_))
+        ));
+        assert!(cpu.get_cycle_ct().0 >= 1000);
+
+        // HLT waits for an interrupt that never comes.
+        let mut cpu = setup_cpu(&[0xF4], CPU_FLAG_INT_ENABLE);
+        assert!(matches!(
+            cpu.run_until_interrupt(1000),
+            Err(CpuError::CpuHaltedError(_))
+        ));
+
+        // A halted CPU is woken by the interrupt.
+        cpu.set_intr(true);
+        assert_eq!(
+            cpu.run_until_interrupt(1000).unwrap(),
+            CpuAddress::Segmented(CODE_SEG, 0x0001)
+        );
+    }
+
+    /// Divide AX=1 by zero with the specified code and return the IP the INT 0 handler pops off the
+    /// stack.
+    fn divide_error_return_ip(cpu_type: CpuType, code: &[u8]) -> u16 {
+        let mut cpu = setup_cpu_type(cpu_type, code, 0);
+        cpu.set_register16(Register16::AX, 0x0001);
+        cpu.set_register16(Register16::CX, 0x0000);
+        // The INT 0 handler: POP BX
+        cpu.bus_mut().write_u8(0x0100 << 4, 0x5B, 0).unwrap();
+
+        step(&mut cpu);
+        assert_eq!(cpu.get_register16(Register16::CS), 0x0100);
+        let (_, cs, _) = int_frame(&cpu);
+        assert_eq!(cs, CODE_SEG);
+        step(&mut cpu);
+        cpu.get_register16(Register16::BX)
+    }
+
+    #[test]
+    fn divide_error_returns_to_next_instruction() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // DIV CL, NOP
+            assert_eq!(divide_error_return_ip(cpu_type, &[0xF6, 0xF1, 0x90]), 0x0002);
+            // DIV BYTE CS:[0010], NOP. The return address follows the whole instruction, not the
+            // opcode after the prefix.
+            assert_eq!(
+                divide_error_return_ip(cpu_type, &[0x2E, 0xF6, 0x36, 0x10, 0x00, 0x90]),
+                0x0005
+            );
+        }
+    }
+
+    #[test]
+    fn aam_zero_raises_divide_error() {
+        // AAM 0, NOP. Only the 8088 faults; the V20 sets AH to FF.
+        assert_eq!(divide_error_return_ip(CpuType::Intel8088, &[0xD4, 0x00, 0x90]), 0x0002);
+    }
+
+    /// Run NOP, INT 21h with the specified AH and breakpoint. Returns the IP the CPU stopped at, if a
+    /// breakpoint was hit.
+    fn int21_breakpoint(cpu_type: CpuType, ah: u8, bp: BreakPointType) -> Option<u16> {
+        let mut cpu = setup_cpu_type(cpu_type, &[0x90, 0xCD, 0x21, 0x90], 0);
+        cpu.set_register16(Register16::AX, (ah as u16) << 8);
+        cpu.set_breakpoints(vec![bp]);
+
+        step(&mut cpu);
+        let (result, _) = cpu.step(false).unwrap();
+        if !matches!(result, StepResult::BreakpointHit) {
+            cpu.step_finish(None).unwrap();
+            assert_eq!(cpu.get_register16(Register16::CS), 0x0000);
+            return None;
+        }
+        // The caller's state is intact.
+        assert_eq!(cpu.get_register16(Register16::CS), CODE_SEG);
+        assert_eq!(cpu.get_register16(Register16::SP), STACK_TOP);
+        let stopped_ip = cpu.get_ip();
+
+        // Resuming skips the breakpoint and takes the interrupt.
+        cpu.clear_breakpoint_flag();
+        cpu.step(true).unwrap();
+        cpu.step_finish(None).unwrap();
+        assert_eq!(cpu.get_register16(Register16::CS), 0x0000);
+        let (ip, cs, _) = int_frame(&cpu);
+        assert_eq!((ip, cs), (0x0003, CODE_SEG));
+        Some(stopped_ip)
+    }
+
+    #[test]
+    fn interrupt_breakpoint_stops_before_int() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            assert_eq!(
+                int21_breakpoint(cpu_type, 0x4C, BreakPointType::Interrupt(0x21)),
+                Some(0x0001)
+            );
+            assert_eq!(
+                int21_breakpoint(cpu_type, 0x4C, BreakPointType::InterruptFunc { int: 0x21, ah: 0x4C }),
+                Some(0x0001)
+            );
+            assert_eq!(
+                int21_breakpoint(cpu_type, 0x09, BreakPointType::InterruptFunc { int: 0x21, ah: 0x4C }),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn hw_interrupt_breakpoint_stops_before_vector() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // NOP, NOP
+            let mut cpu = setup_cpu_type(cpu_type, &[0x90, 0x90], CPU_FLAG_INT_ENABLE);
+            let mut pic = Pic::new();
+            // Unmask all IRQs
+            pic.handle_data_register_write(0x00);
+            pic.request_interrupt(5);
+            *cpu.bus_mut().pic_mut() = Some(pic);
+            cpu.set_intr(true);
+            cpu.set_breakpoints(vec![BreakPointType::Interrupt(5)]);
+
+            // The interrupt is pending after the first NOP. We stop with the interrupted CS:IP intact.
+            step(&mut cpu);
+            let (result, _) = cpu.step(false).unwrap();
+            assert!(matches!(result, StepResult::BreakpointHit));
+            assert_eq!(cpu.get_register16(Register16::CS), CODE_SEG);
+            assert_eq!(cpu.get_ip(), 0x0001);
+            assert_eq!(cpu.get_register16(Register16::SP), STACK_TOP);
+            // The interrupt has not been acknowledged.
+            assert!(cpu.bus().pic().as_ref().unwrap().query_interrupt_line());
+
+            // Resuming takes the interrupt before executing the interrupted instruction.
+            cpu.clear_breakpoint_flag();
+            cpu.step(true).unwrap();
+            cpu.step_finish(None).unwrap();
+            assert_eq!(cpu.get_register16(Register16::CS), 0x0600);
+            let (ip, cs, _) = int_frame(&cpu);
+            assert_eq!((ip, cs), (0x0001, CODE_SEG));
+
+            // The breakpoint does not trigger again inside the handler.
+            let (result, _) = cpu.step(false).unwrap();
+            assert!(!matches!(result, StepResult::BreakpointHit));
+        }
+    }
+
+    #[test]
+    fn add_breakpoint_filters_on_ah() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // NOP, INT 21h
+            let mut cpu = setup_cpu_type(cpu_type, &[0x90, 0xCD, 0x21], 0);
+            cpu.set_register16(Register16::AX, 0x4C00);
+            let bp = BreakPointType::InterruptFunc { int: 0x21, ah: 0x4C };
+            match &mut cpu {
+                CpuDispatch::Intel808x(cpu) => cpu.add_breakpoint(bp),
+                CpuDispatch::NecVx0(cpu) => cpu.add_breakpoint(bp),
+            }
+
+            step(&mut cpu);
+            let (result, _) = cpu.step(false).unwrap();
+            assert!(matches!(result, StepResult::BreakpointHit));
+            assert_eq!(cpu.get_ip(), 0x0001);
+        }
+    }
+}
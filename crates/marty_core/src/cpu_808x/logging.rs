@@ -49,7 +49,7 @@ use crate::{
         CPU_FLAG_TRAP,
         CPU_FLAG_ZERO,
     },
-    cpu_common::{AnalyzerEntry, QueueOp, Segment, TraceMode},
+    cpu_common::{cycle_trace::BusTransfer, AnalyzerEntry, CycleTraceRecord, QueueOp, Segment, TraceMode},
     syntax_token::SyntaxToken,
 };
 
@@ -458,6 +458,109 @@ impl Intel808x {
         cycle_str
     }
 
+    /// Push a record of the current cycle into the cycle record buffer.
+    pub fn push_cycle_record(&mut self) {
+        // Get value of timer channel #1 for DMA printout
+        let mut dma_count = 0;
+
+        if let Some(pit) = self.bus.pit_mut().as_mut() {
+            (_, dma_count, _) = pit.get_channel_count(1);
+        }
+
+        let record = self.cycle_trace_record(dma_count);
+        if let Some(records) = &mut self.cycle_records {
+            records.push(record);
+        }
+
+        // The text trace clears the trace comments for us if it is running.
+        if !(self.trace_enabled && self.trace_mode == TraceMode::CycleText) {
+            self.trace_comment.clear();
+        }
+    }
+
+    /// Produce a structured record of the current cycle. The fields mirror the columns of
+    /// cycle_state_string().
+    pub fn cycle_trace_record(&self, dma_count: u16) -> CycleTraceRecord {
+        let dma = match self.dma_state {
+            DmaState::Idle => format!("{:02} {:02}", dma_count, self.dram_refresh_cycle_num),
+            DmaState::Dreq => "DREQ".to_string(),
+            DmaState::Hrq => "HRQ ".to_string(),
+            DmaState::HoldA => "HLDA".to_string(),
+            DmaState::Operating(n) if n < 4 => format!("S{}", n + 1),
+            DmaState::Operating(_) => format!("{:02} {:02}", dma_count, self.dram_refresh_cycle_num),
+            DmaState::End => "END".to_string(),
+        };
+
+        let transfer = if self.i8288.mrdc | self.i8288.iorc {
+            BusTransfer::Read(self.data_bus as u8)
+        }
+        else if self.i8288.mwtc | self.i8288.iowc {
+            BusTransfer::Write(self.data_bus as u8)
+        }
+        else {
+            BusTransfer::None
+        };
+
+        let instruction = (self.last_queue_op == QueueOp::First).then(|| {
+            format!(
+                "[{:04X}:{:04X}] {} ({})",
+                self.cs, self.instruction_ip, self.i, self.i.size
+            )
+        });
+
+        let microcode_line = match self.trace_instr {
+            MC_JUMP => "JMP".to_string(),
+            MC_RTN => "RET".to_string(),
+            MC_CORR => "COR".to_string(),
+            MC_NONE => "   ".to_string(),
+            _ => format!("{:03X}", self.trace_instr),
+        };
+        let microcode_src = match self.trace_instr {
+            i if usize::from(i) < MICROCODE_SRC_8088.len() => MICROCODE_SRC_8088[i as usize],
+            _ => MICROCODE_NUL,
+        };
+
+        let (slot0bus, slot0t, slot1bus, slot1t) = self.get_pl_slot_strings();
+
+        CycleTraceRecord {
+            cycle: self.cycle_num,
+            instr_cycle: self.instr_cycle,
+            ale: self.i8288.ale,
+            address_latch: self.address_latch,
+            segment: if self.t_cycle != TCycle::T1 {
+                self.bus_segment
+            }
+            else {
+                Segment::None
+            },
+            ready: !self.have_wait_states(),
+            wait_states: self.bus_wait_states,
+            last_wait: self.is_last_wait(),
+            mrdc: self.i8288.mrdc,
+            amwc: self.i8288.amwc,
+            mwtc: self.i8288.mwtc,
+            iorc: self.i8288.iorc,
+            aiowc: self.i8288.aiowc,
+            iowc: self.i8288.iowc,
+            dma,
+            bus_status: self.bus_status as u8,
+            t_cycle: self.t_cycle.to_string(),
+            pl_slots: [format!("{} {}", slot0bus, slot0t), format!("{} {}", slot1bus, slot1t)],
+            transfer,
+            bus_pending: format!("{:?}", self.bus_pending),
+            fetch_state: format!("{:?}", self.fetch_state),
+            queue_op: self.last_queue_op,
+            queue_len: self.last_queue_len,
+            queue_preload: self.queue.has_preload(),
+            queue: self.queue.to_string(),
+            queue_byte: self.last_queue_byte,
+            microcode_line,
+            microcode_src,
+            instruction,
+            comments: self.trace_comment.clone(),
+        }
+    }
+
     pub fn cycle_state_tokens(&self, dma_count: u16, _short: bool) -> Vec<SyntaxToken> {
         let ale_str = match self.i8288.ale {
             true => "A",
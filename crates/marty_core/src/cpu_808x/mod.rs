@@ -34,21 +34,34 @@
 
 pub use crate::cpu_common::Cpu;
 use crate::cpu_common::{
+    expression,
     instruction::Instruction,
+    mnemonic::mnemonic_to_str,
+    symbols::{DiscoveredSymbols, SymbolTable},
+    CallStackOverflowPolicy,
+    CodeWrite,
     CpuAddress,
     CpuStringState,
     CpuSubType,
+    EvalError,
+    EvalResult,
     ExecutionResult,
+    HistoryQuery,
     LogicAnalyzer,
     Mnemonic,
     QueueOp,
     Segment,
     ServiceEvent,
+    DEFAULT_CALL_STACK_DEPTH,
+    DEFAULT_HISTORY_DEPTH,
 };
 use core::fmt::Display;
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::{collections::VecDeque, fmt, path::Path};
+use marty_common::types::history_buffer::HistoryBuffer;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    path::Path,
+};
 
 // Pull in all CPU module components
 mod addressing;
@@ -71,14 +84,15 @@ mod microcode;
 pub mod mnemonic;
 mod modrm;
 mod muldiv;
+pub mod profiling;
 mod queue;
 mod stack;
 mod step;
 mod string;
 
 use crate::{
-    breakpoints::{BreakPointType, CycleStopWatch, StopWatchData},
-    bus::{BusInterface, MEM_BPA_BIT, MEM_BPE_BIT, MEM_RET_BIT, MEM_SW_BIT},
+    breakpoints::{BreakPointType, CycleStopWatch, RomHook, StopWatchData},
+    bus::{BusInterface, IoDirection, IVT_END, MEM_BPA_BIT, MEM_BPE_BIT, MEM_RET_BIT, MEM_SW_BIT},
     bytequeue::*,
     cpu_808x::{microcode::*, queue::InstructionQueue},
     cpu_common::{CpuType, TraceMode},
@@ -131,18 +145,23 @@ macro_rules! gdr {
 
 use crate::{
     bus::ClockFactor,
-    cpu_common::{operands::OperandSize, services::CPUDebugServices, Register16, Register8},
+    cpu_common::{
+        coverage::CoverageRecorder,
+        operands::OperandSize,
+        services::CPUDebugServices,
+        CycleTraceRecord,
+        Register16,
+        Register8,
+    },
 };
 use trace_print;
 
 const QUEUE_MAX: usize = 6;
 const FETCH_DELAY: u8 = 2;
 
-const CPU_HISTORY_LEN: usize = 32;
-const CPU_CALL_STACK_LEN: usize = 48;
-
 const INTERRUPT_VEC_LEN: usize = 4;
-const INTERRUPT_BREAKPOINT: u8 = 1;
+const INTERRUPT_BREAKPOINT: u8 = 0b0000_0001;
+const INTERRUPT_FUNC_BREAKPOINT: u8 = 0b0000_0010;
 
 const IO_READ_BREAKPOINT: u8 = 0b0000_0001;
 const IO_WRITE_BREAKPOINT: u8 = 0b0000_0010;
@@ -313,18 +332,7 @@ pub enum CallStackEntry {
     },
 }
 
-/// Representation of a flag in the eFlags CPU register
-pub enum Flag {
-    Carry,
-    Parity,
-    AuxCarry,
-    Zero,
-    Sign,
-    Trap,
-    Interrupt,
-    Direction,
-    Overflow,
-}
+pub use crate::cpu_common::{FetchState, Flag};
 
 #[derive(Copy, Clone, Default, Debug)]
 pub enum DmaState {
@@ -365,16 +373,6 @@ pub enum BusPendingType {
     EuLate,
 }
 
-#[derive(Copy, Clone, Default, Debug, PartialEq)]
-pub enum FetchState {
-    #[default]
-    Normal,
-    PausedFull,
-    Delayed(u8),
-    Suspended,
-    Halted,
-}
-
 pub enum HistoryEntry {
     InstructionEntry {
         cs: u16,
@@ -479,6 +477,7 @@ pub struct Intel808x {
     intr_pending: bool, // INTR line active and not processed
     in_int: bool,
     int_count: u64,
+    int_vector_counts: Vec<u64>,
     iret_count: u64,
     interrupt_inhibit: bool,
 
@@ -551,18 +550,27 @@ pub struct Intel808x {
     jumped: bool,
     instruction_address: u32,
     instruction_history_on: bool,
+    instruction_history_depth: usize,
     instruction_history: VecDeque<HistoryEntry>,
-
-    services:    CPUDebugServices,
-    call_stack:  VecDeque<CallStackEntry>,
+    discovered_symbols: DiscoveredSymbols,
+    symbols: SymbolTable,
+    coverage: CoverageRecorder,
+
+    services: CPUDebugServices,
+    call_stack: VecDeque<CallStackEntry>,
+    call_stack_depth: usize,
+    call_stack_overflow_policy: CallStackOverflowPolicy,
+    call_stack_overflowed: bool,
     exec_result: ExecutionResult,
 
     // Breakpoints
     breakpoints: Vec<BreakPointType>,
+    rom_hooks: HashMap<u32, RomHook>,
     stopwatches: Vec<Option<CycleStopWatch>>,
     stopwatch_running: bool,
     step_over_target: Option<CpuAddress>,
     step_over_breakpoint: Option<u32>,
+    step_out_frame: Option<(u16, u16)>,
 
     reset_vector: CpuAddress,
     reset_queue:  Option<Vec<u8>>,
@@ -575,12 +583,14 @@ pub struct Intel808x {
     trace_instr: u16,
     trace_str_vec: Vec<String>,
     trace_token_vec: Vec<Vec<SyntaxToken>>,
+    cycle_records: Option<HistoryBuffer<CycleTraceRecord>>,
 
     enable_wait_states: bool,
     off_rails_detection: bool,
     opcode0_counter: u32,
 
     rng: Option<rand::rngs::StdRng>,
+    deterministic: bool,
 
     #[cfg(feature = "cpu_validator")]
     validator: Option<Box<dyn CpuValidator>>,
@@ -635,6 +645,8 @@ pub struct Intel808x {
 
     halt_resume_delay: u32,
     int_flags: Vec<u8>,
+    int_breakpoints: bool, // Whether any interrupt breakpoints are set.
+    hw_int_break: bool,    // A hardware interrupt breakpoint was hit. The interrupt is taken on resume.
     io_flags: Vec<u8>,
 
     analyzer: LogicAnalyzer,
@@ -848,7 +860,9 @@ impl Intel808x {
         cpu.cpu_subtype = cpu_subtype;
 
         //cpu.instruction_history_on = true; // Control this from config/GUI instead
+        cpu.instruction_history_depth = DEFAULT_HISTORY_DEPTH;
         cpu.instruction_history = VecDeque::with_capacity(16);
+        cpu.call_stack_depth = DEFAULT_CALL_STACK_DEPTH;
 
         cpu.reset_vector = CpuAddress::Segmented(0xFFFF, 0x0000);
 
@@ -933,7 +947,9 @@ impl Intel808x {
 
         self.instruction_count = 0;
         self.int_count = 0;
+        self.int_vector_counts = vec![0; 256];
         self.iret_count = 0;
+        self.bus.reset_page_stats();
         self.instr_cycle = 0;
         self.cycle_num = 1;
         self.halt_cycles = 0;
@@ -949,9 +965,12 @@ impl Intel808x {
         self.interrupt_inhibit = false;
         self.intr_pending = false;
         self.in_int = false;
+        self.hw_int_break = false;
         self.is_error = false;
         self.instruction_history.clear();
+        self.discovered_symbols.cancel_entry();
         self.call_stack.clear();
+        self.call_stack_overflowed = false;
         //self.int_flags = vec![0; 256];
         //self.io_flags = vec![0; 0x10000];
 
@@ -977,6 +996,7 @@ impl Intel808x {
 
         self.step_over_target = None;
         self.step_over_breakpoint = None;
+        self.step_out_frame = None;
         self.end_addr = 0xFFFFF;
         self.stopwatch_running = false;
 
@@ -1018,6 +1038,11 @@ impl Intel808x {
         self.instruction_count
     }
 
+    /// Return the number of times each interrupt vector has been invoked since reset.
+    pub fn get_int_counts(&self) -> &[u64] {
+        &self.int_vector_counts
+    }
+
     pub fn flush_piq(&mut self) {
         // Rewind PC to the start of the instruction before flushing, so we will re-fetch it
         self.pc = self.pc.wrapping_sub(self.queue.len_p() as u16);
@@ -1523,111 +1548,88 @@ impl Intel808x {
         }
     }
 
-    /// Evaluate a string expression such as 'cs:ip' to an address.
-    /// Basic forms supported are [reg:reg], [reg:offset], [seg:offset]
+    /// Evaluate an address expression such as 'cs:ip-2', 'ds:bx+4' or '0x400+si*2' to an address.
+    /// See expression::eval_address() for the supported syntax.
     pub fn eval_address(&self, expr: &str) -> Option<CpuAddress> {
-        lazy_static! {
-            static ref FLAT_REX: Regex = Regex::new(r"(?P<flat>[A-Fa-f\d]{5})$").unwrap();
-            static ref SEGMENTED_REX: Regex =
-                Regex::new(r"(?P<segment>[A-Fa-f\d]{4}):(?P<offset>[A-Fa-f\d]{4})$").unwrap();
-            static ref REGREG_REX: Regex = Regex::new(r"(?P<reg1>cs|ds|ss|es):(?P<reg2>\w{2})$").unwrap();
-            static ref REGOFFSET_REX: Regex = Regex::new(r"(?P<reg1>cs|ds|ss|es):(?P<offset>[A-Fa-f\d]{4})$").unwrap();
-        }
-
-        if FLAT_REX.is_match(expr) {
-            match u32::from_str_radix(expr, 16) {
-                Ok(address) => Some(CpuAddress::Flat(address)),
-                Err(_) => None,
-            }
-        }
-        else if let Some(caps) = SEGMENTED_REX.captures(expr) {
-            let segment_str = &caps["segment"];
-            let offset_str = &caps["offset"];
+        expression::eval_address(expr, &|name| self.register_by_name(name)).ok()
+    }
+
+    /// Return the value of the register with the given lowercase name.
+    pub fn register_by_name(&self, name: &str) -> Option<u16> {
+        let value = match name {
+            "ah" => self.a.h() as u16,
+            "al" => self.a.l() as u16,
+            "ax" => self.a.x(),
+            "bh" => self.b.h() as u16,
+            "bl" => self.b.l() as u16,
+            "bx" => self.b.x(),
+            "ch" => self.c.h() as u16,
+            "cl" => self.c.l() as u16,
+            "cx" => self.c.x(),
+            "dh" => self.d.h() as u16,
+            "dl" => self.d.l() as u16,
+            "dx" => self.d.x(),
+            "sp" => self.sp,
+            "bp" => self.bp,
+            "si" => self.si,
+            "di" => self.di,
+            "cs" => self.cs,
+            "ds" => self.ds,
+            "ss" => self.ss,
+            "es" => self.es,
+            "ip" => self.disassembly_ip(), // Use reentrant IP for disassembly
+            "flags" => self.flags,
+            _ => return None,
+        };
+        Some(value)
+    }
 
-            let segment_u16r = u16::from_str_radix(segment_str, 16);
-            let offset_u16r = u16::from_str_radix(offset_str, 16);
+    /// Evaluate a watch expression such as '[bx+4]' or 'word [0040:006C]' to an address and value.
+    pub fn eval_expression(&self, expr: &str) -> Result<EvalResult, EvalError> {
+        expression::eval_expression(expr, &|name| self.register_by_name(name), &self.bus)
+    }
 
-            match (segment_u16r, offset_u16r) {
-                (Ok(segment), Ok(offset)) => Some(CpuAddress::Segmented(segment, offset)),
-                _ => None,
+    /// Push an entry on to the call stack. This can either be a CALL or an INT.
+    /// If the call stack is full, an entry is discarded according to the overflow policy and the
+    /// call stack is flagged as having overflowed.
+    pub fn push_call_stack(&mut self, entry: CallStackEntry, cs: u16, ip: u16) {
+        if self.call_stack.len() >= self.call_stack_depth {
+            if !self.call_stack_overflowed {
+                log::warn!(
+                    "push_call_stack(): call stack exceeded maximum depth of {} entries",
+                    self.call_stack_depth
+                );
+            }
+            self.call_stack_overflowed = true;
+            match self.call_stack_overflow_policy {
+                CallStackOverflowPolicy::DropOldest => {
+                    self.trim_call_stack(self.call_stack_depth - 1);
+                }
+                CallStackOverflowPolicy::DropNewest => return,
             }
         }
-        else if let Some(caps) = REGREG_REX.captures(expr) {
-            let reg1 = &caps["reg1"];
-            let reg2 = &caps["reg2"];
-
-            let segment = match reg1 {
-                "cs" => self.cs,
-                "ds" => self.ds,
-                "ss" => self.ss,
-                "es" => self.es,
-                _ => 0,
-            };
 
-            let offset = match reg2 {
-                "ah" => self.a.h() as u16,
-                "al" => self.a.l() as u16,
-                "ax" => self.a.x(),
-                "bh" => self.b.h() as u16,
-                "bl" => self.b.l() as u16,
-                "bx" => self.b.x(),
-                "ch" => self.c.h() as u16,
-                "cl" => self.c.l() as u16,
-                "cx" => self.c.x(),
-                "dh" => self.d.h() as u16,
-                "dl" => self.d.l() as u16,
-                "dx" => self.d.x(),
-                "sp" => self.sp,
-                "bp" => self.bp,
-                "si" => self.si,
-                "di" => self.di,
-                "cs" => self.cs,
-                "ds" => self.ds,
-                "ss" => self.ss,
-                "es" => self.es,
-                "ip" => self.disassembly_ip(), // Use reentrant IP for disassembly
-                _ => 0,
-            };
-
-            Some(CpuAddress::Segmented(segment, offset))
-        }
-        else if let Some(caps) = REGOFFSET_REX.captures(expr) {
-            let reg1 = &caps["reg1"];
-            let offset_str = &caps["offset"];
-
-            let segment = match reg1 {
-                "cs" => self.cs,
-                "ds" => self.ds,
-                "ss" => self.ss,
-                "es" => self.es,
-                _ => 0,
-            };
+        self.call_stack.push_back(entry);
 
-            let offset_u16r = u16::from_str_radix(offset_str, 16);
+        // Flag the specified CS:IP as a return address
+        let return_addr = Intel808x::calc_linear_address(cs, ip);
 
-            match offset_u16r {
-                Ok(offset) => Some(CpuAddress::Segmented(segment, offset)),
-                _ => None,
-            }
-        }
-        else {
-            None
-        }
+        self.bus.set_flags(return_addr as usize, MEM_RET_BIT);
     }
 
-    /// Push an entry on to the call stack. This can either be a CALL or an INT.
-    pub fn push_call_stack(&mut self, entry: CallStackEntry, cs: u16, ip: u16) {
-        if self.call_stack.len() < CPU_CALL_STACK_LEN {
-            self.call_stack.push_back(entry);
-
-            // Flag the specified CS:IP as a return address
-            let return_addr = Intel808x::calc_linear_address(cs, ip);
-
-            self.bus.set_flags(return_addr as usize, MEM_RET_BIT);
-        }
-        else {
-            // TODO: set a flag to indicate that the call stack has overflowed?
+    /// Discard the oldest entries from the call stack until at most `depth` remain, clearing
+    /// their return flags. Returns true if any entries were discarded.
+    fn trim_call_stack(&mut self, depth: usize) -> bool {
+        let excess = self.call_stack.len().saturating_sub(depth);
+        for call in self.call_stack.drain(..excess) {
+            let return_addr = match call {
+                CallStackEntry::CallF { ret_cs, ret_ip, .. } => Intel808x::calc_linear_address(ret_cs, ret_ip),
+                CallStackEntry::Call { ret_cs, ret_ip, .. } => Intel808x::calc_linear_address(ret_cs, ret_ip),
+                CallStackEntry::Interrupt { ret_cs, ret_ip, .. } => Intel808x::calc_linear_address(ret_cs, ret_ip),
+            };
+            self.bus.clear_flags(return_addr as usize, MEM_RET_BIT);
         }
+        excess > 0
     }
 
     /// Rewind the call stack to the specified address.
@@ -1724,7 +1726,7 @@ impl Intel808x {
             BreakPointType::MemAccessFlat(addr) => {
                 self.bus.clear_flags(*addr as usize, MEM_BPA_BIT);
             }
-            BreakPointType::Interrupt(vector) => {
+            BreakPointType::Interrupt(vector) | BreakPointType::InterruptFunc { int: vector, .. } => {
                 self.int_flags[*vector as usize] = 0;
             }
             BreakPointType::StartWatch(addr) => {
@@ -1753,7 +1755,10 @@ impl Intel808x {
                 self.bus.set_flags(*addr as usize, MEM_BPA_BIT);
             }
             BreakPointType::Interrupt(vector) => {
-                self.int_flags[*vector as usize] = INTERRUPT_BREAKPOINT;
+                self.int_flags[*vector as usize] |= INTERRUPT_BREAKPOINT;
+            }
+            BreakPointType::InterruptFunc { int, .. } => {
+                self.int_flags[*int as usize] |= INTERRUPT_FUNC_BREAKPOINT;
             }
             BreakPointType::StartWatch(addr) => {
                 self.bus.set_flags(*addr as usize, MEM_SW_BIT);
@@ -1767,19 +1772,53 @@ impl Intel808x {
             }
             _ => {}
         });
+        self.int_breakpoints = self.int_flags.iter().any(|flags| *flags != 0);
+
+        // Clearing an execute breakpoint may have cleared the flag of a ROM hook at the same address.
+        for addr in self.rom_hooks.keys() {
+            self.bus.set_flags(*addr as usize, MEM_BPE_BIT);
+        }
     }
 
-    pub fn add_breakpoint(&mut self, bp: &BreakPointType) {
-        match bp {
+    /// Replace the current set of ROM hooks. Hooks are flagged like execute breakpoints, but are handled by step()
+    /// without stopping execution.
+    pub fn set_rom_hooks(&mut self, hooks: Vec<RomHook>) {
+        for addr in self.rom_hooks.keys() {
+            if !self.is_execute_breakpoint(*addr) {
+                self.bus.clear_flags(*addr as usize, MEM_BPE_BIT);
+            }
+        }
+        self.rom_hooks = hooks.into_iter().map(|hook| (hook.address, hook)).collect();
+        for (addr, hook) in self.rom_hooks.iter() {
+            log::debug!("Setting ROM hook '{}' at address: {:05X}", hook.name, *addr);
+            self.bus.set_flags(*addr as usize, MEM_BPE_BIT);
+        }
+    }
+
+    /// Return true if a user execute breakpoint is set at the specified address.
+    fn is_execute_breakpoint(&self, address: u32) -> bool {
+        self.breakpoints
+            .iter()
+            .any(|bp| matches!(bp, BreakPointType::ExecuteFlat(addr) if *addr == address))
+    }
+
+    /// Add a single breakpoint to the current breakpoint list.
+    pub fn add_breakpoint(&mut self, bp: BreakPointType) {
+        match &bp {
             BreakPointType::ExecuteFlat(addr) => {
-                log::debug!("Clearing breakpoint on execute at address: {:05X}", *addr);
+                log::debug!("Setting breakpoint on execute at address: {:05X}", *addr);
                 self.bus.set_flags(*addr as usize, MEM_BPE_BIT);
             }
             BreakPointType::MemAccessFlat(addr) => {
                 self.bus.set_flags(*addr as usize, MEM_BPA_BIT);
             }
             BreakPointType::Interrupt(vector) => {
-                self.int_flags[*vector as usize] = INTERRUPT_BREAKPOINT;
+                self.int_flags[*vector as usize] |= INTERRUPT_BREAKPOINT;
+                self.int_breakpoints = true;
+            }
+            BreakPointType::InterruptFunc { int, .. } => {
+                self.int_flags[*int as usize] |= INTERRUPT_FUNC_BREAKPOINT;
+                self.int_breakpoints = true;
             }
             BreakPointType::StartWatch(addr) => {
                 self.bus.set_flags(*addr as usize, MEM_SW_BIT);
@@ -1792,6 +1831,9 @@ impl Intel808x {
                 return;
             }
         }
+        // Keep the breakpoint so that set_breakpoints() can clear it, and so that AH-filtered interrupt
+        // breakpoints can be matched.
+        self.breakpoints.push(bp);
     }
 
     pub fn set_stopwatch(&mut self, sw_idx: usize, start: u32, stop: u32) {
@@ -1837,6 +1879,26 @@ impl Intel808x {
         }
     }
 
+    /// Record the current stack frame, so that execution stops once a return pops the stack above it.
+    pub fn step_out(&mut self) {
+        log::debug!(
+            "CPU: Stepping out from {:04X}:{:04X}, SS:SP {:04X}:{:04X}",
+            self.cs,
+            self.ip(),
+            self.ss,
+            self.sp
+        );
+        self.step_out_frame = Some((self.ss, self.sp));
+    }
+
+    pub fn cancel_step_out(&mut self) {
+        self.step_out_frame = None;
+    }
+
+    pub fn is_stepping_out(&self) -> bool {
+        self.step_out_frame.is_some()
+    }
+
     pub fn get_breakpoint_flag(&self) -> bool {
         matches!(self.state, CpuState::BreakpointHit)
     }
@@ -1879,66 +1941,108 @@ impl Intel808x {
     }
 
     pub fn dump_instruction_history_tokens(&self) -> Vec<Vec<SyntaxToken>> {
-        let mut history_vec = Vec::new();
-
-        for i in &self.instruction_history {
-            let mut i_token_vec = Vec::new();
-            match i {
-                HistoryEntry::InstructionEntry {
-                    cs,
-                    ip,
-                    cycles,
-                    interrupt,
-                    jump,
-                    i,
-                } => {
-                    if *jump {
-                        i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
-                            HighlightType::Info,
-                        )));
-                    }
-                    i_token_vec.push(SyntaxToken::MemoryAddressFlat(i.address, format!("{:05X}", i.address)));
-                    i_token_vec.push(SyntaxToken::MemoryAddressSeg16(
-                        *cs,
-                        *ip,
-                        format!("{:04X}:{:04X}{}", cs, ip, if *interrupt { '*' } else { ' ' }),
-                    ));
-                    i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", " ")));
-                    i_token_vec.extend(i.tokenize());
-                    i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::Tab));
-                    i_token_vec.push(SyntaxToken::Text(format!("{}", *cycles)));
-                }
-                HistoryEntry::InterruptEntry { cs, ip, cycles: _, iv } => {
+        self.instruction_history
+            .iter()
+            .map(|entry| Self::history_entry_tokens(entry, &self.symbols))
+            .collect()
+    }
+
+    /// Return the instruction history entries matching `query`, oldest first.
+    pub fn query_instruction_history(&self, query: &HistoryQuery) -> Vec<Vec<SyntaxToken>> {
+        self.instruction_history
+            .iter()
+            .filter(|entry| {
+                let (address, mnemonic) = match entry {
+                    HistoryEntry::InstructionEntry { i, .. } => (i.address, mnemonic_to_str(i.mnemonic)),
+                    HistoryEntry::InterruptEntry { cs, ip, .. } => (Intel808x::calc_linear_address(*cs, *ip), "INT"),
+                    HistoryEntry::NmiEntry { cs, ip } => (Intel808x::calc_linear_address(*cs, *ip), "NMI"),
+                    HistoryEntry::TrapEntry { cs, ip } => (Intel808x::calc_linear_address(*cs, *ip), "TRAP"),
+                };
+                query.matches(address, mnemonic)
+            })
+            .map(|entry| Self::history_entry_tokens(entry, &self.symbols))
+            .collect()
+    }
+
+    fn history_entry_tokens(entry: &HistoryEntry, symbols: &SymbolTable) -> Vec<SyntaxToken> {
+        let mut i_token_vec = Vec::new();
+        match entry {
+            HistoryEntry::InstructionEntry {
+                cs,
+                ip,
+                cycles,
+                interrupt,
+                jump,
+                i,
+            } => {
+                if *jump {
                     i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
-                        HighlightType::Alert,
+                        HighlightType::Info,
                     )));
-                    i_token_vec.push(SyntaxToken::MemoryAddressFlat(0, format!("{:05}", "")));
-                    i_token_vec.push(SyntaxToken::MemoryAddressSeg16(*cs, *ip, String::from("          ")));
-                    i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", "")));
-                    i_token_vec.push(SyntaxToken::Text(format!("INT {:02X}", iv)));
                 }
-                HistoryEntry::NmiEntry { cs, ip } => {
-                    i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
-                        HighlightType::Alert,
-                    )));
-                    i_token_vec.push(SyntaxToken::MemoryAddressFlat(0, format!("{:05}", "")));
-                    i_token_vec.push(SyntaxToken::MemoryAddressSeg16(*cs, *ip, String::from("          ")));
-                    i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", "")));
-                    i_token_vec.push(SyntaxToken::Text(String::from("NMI")));
+                i_token_vec.push(SyntaxToken::MemoryAddressFlat(i.address, format!("{:05X}", i.address)));
+                i_token_vec.push(SyntaxToken::MemoryAddressSeg16(
+                    *cs,
+                    *ip,
+                    format!("{:04X}:{:04X}{}", cs, ip, if *interrupt { '*' } else { ' ' }),
+                ));
+                i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", " ")));
+                i_token_vec.extend(i.tokenize_with_symbols(&|address| symbols.lookup(address)));
+                i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::Tab));
+                i_token_vec.push(SyntaxToken::Text(format!("{}", *cycles)));
+            }
+            HistoryEntry::InterruptEntry { cs, ip, cycles: _, iv } => {
+                i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
+                    HighlightType::Alert,
+                )));
+                i_token_vec.push(SyntaxToken::MemoryAddressFlat(0, format!("{:05}", "")));
+                i_token_vec.push(SyntaxToken::MemoryAddressSeg16(*cs, *ip, String::from("          ")));
+                i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", "")));
+                i_token_vec.push(SyntaxToken::Text(format!("INT {:02X}", iv)));
+            }
+            HistoryEntry::NmiEntry { cs, ip } => {
+                i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
+                    HighlightType::Alert,
+                )));
+                i_token_vec.push(SyntaxToken::MemoryAddressFlat(0, format!("{:05}", "")));
+                i_token_vec.push(SyntaxToken::MemoryAddressSeg16(*cs, *ip, String::from("          ")));
+                i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", "")));
+                i_token_vec.push(SyntaxToken::Text(String::from("NMI")));
+            }
+            HistoryEntry::TrapEntry { cs, ip } => {
+                i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
+                    HighlightType::Alert,
+                )));
+                i_token_vec.push(SyntaxToken::MemoryAddressFlat(0, format!("{:05}", "")));
+                i_token_vec.push(SyntaxToken::MemoryAddressSeg16(*cs, *ip, String::from("          ")));
+                i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", "")));
+                i_token_vec.push(SyntaxToken::Text(String::from("TRAP")));
+            }
+        }
+        i_token_vec
+    }
+
+    /// Return the routine entry points seen as CALL and interrupt targets while the instruction history was enabled,
+    /// and those in the call stack. Imported symbol names replace generated ones.
+    pub fn export_discovered_symbols(&self) -> Vec<(u32, String)> {
+        let mut discovered = self.discovered_symbols.clone();
+        for call in self.call_stack.iter() {
+            match *call {
+                CallStackEntry::Call { ret_cs, call_ip, .. } => {
+                    discovered.add_call(Intel808x::calc_linear_address(ret_cs, call_ip))
                 }
-                HistoryEntry::TrapEntry { cs, ip } => {
-                    i_token_vec.push(SyntaxToken::Formatter(SyntaxFormatType::HighlightLine(
-                        HighlightType::Alert,
-                    )));
-                    i_token_vec.push(SyntaxToken::MemoryAddressFlat(0, format!("{:05}", "")));
-                    i_token_vec.push(SyntaxToken::MemoryAddressSeg16(*cs, *ip, String::from("          ")));
-                    i_token_vec.push(SyntaxToken::InstructionBytes(format!("{:012}", "")));
-                    i_token_vec.push(SyntaxToken::Text(String::from("TRAP")));
+                CallStackEntry::CallF { call_cs, call_ip, .. } => {
+                    discovered.add_call(Intel808x::calc_linear_address(call_cs, call_ip))
                 }
+                CallStackEntry::Interrupt {
+                    call_cs,
+                    call_ip,
+                    number,
+                    ..
+                } => discovered.add_interrupt(number, Intel808x::calc_linear_address(call_cs, call_ip)),
             }
-            history_vec.push(i_token_vec);
         }
-        history_vec
+        discovered.export(&self.symbols)
     }
 
     pub fn dump_call_stack(&self) -> String {
@@ -2013,7 +2117,7 @@ impl Intel808x {
 
     #[inline]
     pub fn trace_comment(&mut self, comment: &'static str) {
-        if self.trace_enabled && (self.trace_mode == TraceMode::CycleText) {
+        if (self.trace_enabled && (self.trace_mode == TraceMode::CycleText)) || self.cycle_records.is_some() {
             self.trace_comment.push(comment);
         }
     }
@@ -2050,6 +2154,9 @@ impl Intel808x {
     pub fn get_cycle_trace_tokens(&self) -> &Vec<Vec<SyntaxToken>> {
         &self.trace_token_vec
     }
+    pub fn get_cycle_trace_records(&self) -> Option<&HistoryBuffer<CycleTraceRecord>> {
+        self.cycle_records.as_ref()
+    }
     pub fn get_cycle_ct(&self) -> (u64, u64) {
         (self.cycle_num, self.halt_cycles)
     }
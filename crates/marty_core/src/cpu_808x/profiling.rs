@@ -0,0 +1,335 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    cpu_808x::profiling.rs
+
+    Exports the instruction history as folded stacks for flame graph tools
+    such as inferno-flamegraph or Brendan Gregg's flamegraph.pl.
+
+*/
+
+//! Each line of the output is a semicolon-separated call stack followed by the number of cycles
+//! spent there. Call frames are labelled with the address of the routine, `CS:IP[symbol]` if a
+//! symbol is known for it. The leaf frame of each stack is the executed mnemonic, annotated with a
+//! suffix that classifies the instruction:
+//!
+//! - `_[j]` for branches (jumps, calls, returns, loops and interrupts)
+//! - `_[k]` for instructions that access memory
+//! - `_[i]` for arithmetic and other register-only instructions
+//!
+//! These are the annotations of the `java` palette, so render with
+//! `inferno-flamegraph --colors java` to color the stacks by instruction type.
+//!
+//! The call stack at the start of the history is reconstructed by unwinding the current call stack
+//! through the calls and returns in the history. Routines entered before the history begins are
+//! labelled with the first address seen executing in them.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{
+    cpu_808x::{CallStackEntry, HistoryEntry},
+    cpu_common::{calc_linear_address, Instruction, Mnemonic, OperandType},
+};
+
+/// Instruction category, used to color flame graph stacks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InstructionClass {
+    Branch,
+    Memory,
+    Arithmetic,
+}
+
+impl InstructionClass {
+    pub fn of(i: &Instruction) -> Self {
+        use Mnemonic::*;
+        match i.mnemonic {
+            CALL | CALLF | JMP | JMPF | RETN | RETF | IRET | INT | INT3 | INTO | JB | JBE | JCXZ | JL | JLE | JNB
+            | JNBE | JNL | JNLE | JNO | JNP | JNS | JNZ | JO | JP | JS | JZ | LOOP | LOOPNE | LOOPE => {
+                InstructionClass::Branch
+            }
+            CMPSB | CMPSW | LODSB | LODSW | MOVSB | MOVSW | SCASB | SCASW | STOSB | STOSW | INSB | INSW | OUTSB
+            | OUTSW | PUSH | POP | PUSHF | POPF | PUSHA | POPA | XLAT | LDS | LES => InstructionClass::Memory,
+            _ if Self::accesses_memory(&i.operand1_type) || Self::accesses_memory(&i.operand2_type) => {
+                // LEA computes an address without accessing it.
+                match i.mnemonic {
+                    LEA => InstructionClass::Arithmetic,
+                    _ => InstructionClass::Memory,
+                }
+            }
+            _ => InstructionClass::Arithmetic,
+        }
+    }
+
+    fn accesses_memory(operand: &OperandType) -> bool {
+        matches!(
+            operand,
+            OperandType::AddressingMode(_) | OperandType::Offset8(_) | OperandType::Offset16(_)
+        )
+    }
+
+    /// The frame name annotation that selects this class's color in the `java` palette.
+    pub fn annotation(&self) -> &'static str {
+        match self {
+            InstructionClass::Branch => "_[j]",
+            InstructionClass::Memory => "_[k]",
+            InstructionClass::Arithmetic => "_[i]",
+        }
+    }
+}
+
+/// How an executed history entry changes the call stack.
+enum StackEffect {
+    None,
+    Push,
+    Pop,
+}
+
+fn stack_effect(entry: &HistoryEntry, next_address: Option<(u16, u16)>) -> StackEffect {
+    match entry {
+        HistoryEntry::InstructionEntry { cs, ip, i, .. } => match i.mnemonic {
+            Mnemonic::CALL | Mnemonic::CALLF | Mnemonic::INT | Mnemonic::INT3 => StackEffect::Push,
+            // INTO only interrupts on overflow, which we can tell by where execution went next.
+            Mnemonic::INTO => match next_address {
+                Some((next_cs, next_ip)) if (next_cs, next_ip) != (*cs, ip.wrapping_add(i.size as u16)) => {
+                    StackEffect::Push
+                }
+                _ => StackEffect::None,
+            },
+            Mnemonic::RETN | Mnemonic::RETF | Mnemonic::IRET => StackEffect::Pop,
+            _ => StackEffect::None,
+        },
+        HistoryEntry::InterruptEntry { .. } | HistoryEntry::NmiEntry { .. } | HistoryEntry::TrapEntry { .. } => {
+            StackEffect::Push
+        }
+    }
+}
+
+fn entry_address(entry: &HistoryEntry) -> (u16, u16) {
+    match entry {
+        HistoryEntry::InstructionEntry { cs, ip, .. }
+        | HistoryEntry::InterruptEntry { cs, ip, .. }
+        | HistoryEntry::NmiEntry { cs, ip }
+        | HistoryEntry::TrapEntry { cs, ip } => (*cs, *ip),
+    }
+}
+
+/// Address of the next instruction executed after the history entry at `idx`. Interrupt, NMI and
+/// trap entries share the address of the instruction they follow, so they are skipped.
+fn next_instruction_address(history: &[HistoryEntry], idx: usize) -> Option<(u16, u16)> {
+    history[idx + 1..]
+        .iter()
+        .find(|entry| matches!(entry, HistoryEntry::InstructionEntry { .. }))
+        .map(entry_address)
+}
+
+fn frame_label(cs: u16, ip: u16, symbols: &dyn Fn(u32) -> Option<String>) -> String {
+    match symbols(calc_linear_address(cs, ip)) {
+        Some(symbol) => format!("{:04X}:{:04X}[{}]", cs, ip, symbol),
+        None => format!("{:04X}:{:04X}", cs, ip),
+    }
+}
+
+/// Convert the instruction history to folded stacks, weighted by cycles. See the module
+/// documentation for the format.
+pub fn to_flamegraph(history: &[HistoryEntry], call_stack: &VecDeque<CallStackEntry>) -> String {
+    to_flamegraph_with_symbols(history, call_stack, &|_| None)
+}
+
+/// As [to_flamegraph], labelling frames with any symbol `symbols` resolves for the flat address of
+/// the routine.
+pub fn to_flamegraph_with_symbols(
+    history: &[HistoryEntry],
+    call_stack: &VecDeque<CallStackEntry>,
+    symbols: &dyn Fn(u32) -> Option<String>,
+) -> String {
+    // The stack holds indices into `labels`. A frame's label is None until we know an address in
+    // it, so that frames entered before the history can be labelled once they execute.
+    let mut labels: Vec<Option<String>> = vec![None];
+    let mut stack: Vec<usize> = vec![0]; // The outermost frame, for code executing outside any call.
+    for call in call_stack.iter() {
+        let (cs, ip) = match *call {
+            CallStackEntry::Call { ret_cs, call_ip, .. } => (ret_cs, call_ip),
+            CallStackEntry::CallF { call_cs, call_ip, .. } => (call_cs, call_ip),
+            CallStackEntry::Interrupt { call_cs, call_ip, .. } => (call_cs, call_ip),
+        };
+        stack.push(labels.len());
+        labels.push(Some(frame_label(cs, ip, symbols)));
+    }
+
+    // Unwind to the call stack as it was at the start of the history.
+    for idx in (0..history.len()).rev() {
+        match stack_effect(&history[idx], next_instruction_address(history, idx)) {
+            StackEffect::Push => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+            }
+            StackEffect::Pop => {
+                // Before a return, the routine returning was on top of the stack.
+                stack.push(labels.len());
+                labels.push(None);
+            }
+            StackEffect::None => {}
+        }
+    }
+
+    // Replay the history, accumulating cycles per stack.
+    let mut samples: BTreeMap<(Vec<usize>, String), u64> = BTreeMap::new();
+    for (idx, entry) in history.iter().enumerate() {
+        let next_address = next_instruction_address(history, idx);
+        let (cs, ip, cycles, i) = match entry {
+            HistoryEntry::InstructionEntry { cs, ip, cycles, i, .. } => (*cs, *ip, *cycles, i),
+            _ => {
+                // Interrupt, NMI and trap entries enter the routine executed next.
+                stack.push(labels.len());
+                labels.push(next_address.map(|(cs, ip)| frame_label(cs, ip, symbols)));
+                continue;
+            }
+        };
+
+        if let Some(&top) = stack.last() {
+            labels[top].get_or_insert_with(|| frame_label(cs, ip, symbols));
+        }
+
+        let leaf = format!("{}{}", i.mnemonic, InstructionClass::of(i).annotation());
+        *samples.entry((stack.clone(), leaf)).or_insert(0) += cycles as u64;
+
+        match stack_effect(entry, next_address) {
+            StackEffect::Push => {
+                stack.push(labels.len());
+                labels.push(next_address.map(|(cs, ip)| frame_label(cs, ip, symbols)));
+            }
+            StackEffect::Pop => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+            }
+            StackEffect::None => {}
+        }
+    }
+
+    // Resolve the frame labels and merge stacks that resolve to the same labels.
+    let mut folded: BTreeMap<String, u64> = BTreeMap::new();
+    for ((frames, leaf), cycles) in samples {
+        let mut key = frames
+            .iter()
+            .filter_map(|frame| labels[*frame].as_deref())
+            .collect::<Vec<_>>()
+            .join(";");
+        if !key.is_empty() {
+            key.push(';');
+        }
+        key.push_str(&leaf);
+        *folded.entry(key).or_insert(0) += cycles;
+    }
+
+    let mut output = String::new();
+    for (stack, cycles) in folded {
+        output.push_str(&format!("{} {}\n", stack, cycles));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_common::{AddressingMode, Register16};
+
+    fn instr(cs: u16, ip: u16, cycles: u16, mnemonic: Mnemonic, size: u32) -> HistoryEntry {
+        HistoryEntry::InstructionEntry {
+            cs,
+            ip,
+            cycles,
+            interrupt: false,
+            jump: false,
+            i: Instruction {
+                address: calc_linear_address(cs, ip),
+                size,
+                mnemonic,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_folded_stacks_follow_calls_and_returns() {
+        let history = vec![
+            instr(0x1000, 0x0100, 4, Mnemonic::NOP, 1),
+            instr(0x1000, 0x0101, 19, Mnemonic::CALL, 3),
+            instr(0x1000, 0x0200, 3, Mnemonic::NOP, 1),
+            instr(0x1000, 0x0201, 20, Mnemonic::RETN, 1),
+            instr(0x1000, 0x0104, 3, Mnemonic::NOP, 1),
+        ];
+        let folded = to_flamegraph(&history, &VecDeque::new());
+        let lines: Vec<&str> = folded.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "1000:0100;1000:0200;NOP_[i] 3",
+                "1000:0100;1000:0200;RETN_[j] 20",
+                "1000:0100;CALL_[j] 19",
+                "1000:0100;NOP_[i] 7",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_history_starting_inside_a_call() {
+        // The history begins inside a routine that was called before it; the current call stack
+        // is empty because that routine has since returned.
+        let history = vec![
+            instr(0x2000, 0x0010, 3, Mnemonic::NOP, 1),
+            instr(0x2000, 0x0011, 34, Mnemonic::RETF, 1),
+            instr(0x1000, 0x0105, 3, Mnemonic::NOP, 1),
+        ];
+        let folded = to_flamegraph_with_symbols(&history, &VecDeque::new(), &|address| {
+            (address == 0x10105).then(|| "main".to_string())
+        });
+
+        assert!(folded.contains("1000:0105[main];2000:0010;RETF_[j] 34\n"));
+        assert!(folded.contains("1000:0105[main];NOP_[i] 3\n"));
+    }
+
+    #[test]
+    fn test_instruction_classes() {
+        let mut i = Instruction {
+            mnemonic: Mnemonic::ADD,
+            operand1_type: OperandType::Register16(Register16::AX),
+            ..Default::default()
+        };
+        assert_eq!(InstructionClass::of(&i), InstructionClass::Arithmetic);
+
+        i.operand2_type = OperandType::AddressingMode(AddressingMode::Bx);
+        assert_eq!(InstructionClass::of(&i), InstructionClass::Memory);
+
+        i.mnemonic = Mnemonic::LEA;
+        assert_eq!(InstructionClass::of(&i), InstructionClass::Arithmetic);
+
+        i.mnemonic = Mnemonic::JNZ;
+        assert_eq!(InstructionClass::of(&i), InstructionClass::Branch);
+    }
+}
@@ -31,7 +31,11 @@
 */
 
 use crate::cpu_common::calc_linear_address;
-use std::{fmt, fmt::Display};
+use std::{
+    fmt,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
 
 #[derive(Copy, Clone, Debug)]
 pub enum Displacement {
@@ -209,6 +213,24 @@ impl CpuAddress {
             CpuAddress::Offset(a) => *a as u32,
         }
     }
+
+    /// Return the offset of the address within its segment. A flat address is treated as an
+    /// offset from the start of its 64K block.
+    pub fn offset(&self) -> u16 {
+        match self {
+            CpuAddress::Flat(a) => *a as u16,
+            CpuAddress::Segmented(_, o) | CpuAddress::Offset(o) => *o,
+        }
+    }
+
+    /// Advance the address by `n` bytes. Segmented addresses wrap around within their segment.
+    pub fn offset_by(&self, n: u16) -> CpuAddress {
+        match self {
+            CpuAddress::Flat(a) => CpuAddress::Flat(a.wrapping_add(n as u32) & 0xFFFFF),
+            CpuAddress::Segmented(s, o) => CpuAddress::Segmented(*s, o.wrapping_add(n)),
+            CpuAddress::Offset(o) => CpuAddress::Offset(o.wrapping_add(n)),
+        }
+    }
 }
 
 impl Default for CpuAddress {
@@ -288,6 +310,14 @@ impl PartialEq for CpuAddress {
 
 impl Eq for CpuAddress {}
 
+// Flat and segmented addresses compare equal when they resolve to the same linear address, so
+// hash the linear address.
+impl Hash for CpuAddress {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_flat_u32().hash(state);
+    }
+}
+
 impl PartialOrd for CpuAddress {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
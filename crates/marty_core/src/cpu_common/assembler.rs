@@ -0,0 +1,794 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::assembler.rs
+
+    Implements a single-line 8088 assembler for patching code in the debugger.
+    Supports the common forms of MOV, the ALU instructions, jumps, calls, INT,
+    PUSH and POP, and instructions without operands. As in the debugger's
+    expressions, constants are hexadecimal, for example 'mov ax, 1234',
+    'add byte [bx+si+4], 0x10' or 'jmp f000:e05b'.
+
+*/
+
+use std::{error::Error, fmt, fmt::Display};
+
+use crate::cpu_common::{expression::parse_hex, Mnemonic, Register16, Register8, ValueSize};
+
+#[derive(Debug, PartialEq)]
+pub enum AsmError {
+    Empty,
+    UnknownMnemonic(String),
+    Unsupported(String),
+    InvalidOperand(String),
+    OperandCount(usize),
+    SizeRequired,
+    SizeMismatch,
+    ValueOutOfRange(i64),
+    JumpOutOfRange(i32),
+}
+impl Error for AsmError {}
+impl Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::Empty => write!(f, "Empty instruction"),
+            AsmError::UnknownMnemonic(m) => write!(f, "Unknown mnemonic '{}'", m),
+            AsmError::Unsupported(s) => write!(f, "Unsupported instruction form: {}", s),
+            AsmError::InvalidOperand(s) => write!(f, "Invalid operand '{}'", s),
+            AsmError::OperandCount(n) => write!(f, "Expected {} operand(s)", n),
+            AsmError::SizeRequired => write!(f, "Operand size is ambiguous; specify 'byte' or 'word'"),
+            AsmError::SizeMismatch => write!(f, "Operand sizes don't match"),
+            AsmError::ValueOutOfRange(v) if *v < 0 => write!(f, "Value -{:X} is out of range", -v),
+            AsmError::ValueOutOfRange(v) => write!(f, "Value {:X} is out of range", v),
+            AsmError::JumpOutOfRange(d) => write!(f, "Jump target is out of range ({} bytes away)", d),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct MemOperand {
+    size: Option<ValueSize>,
+    segment: Option<Register16>,
+    /// The ModR/M r/m field. A direct address is encoded as r/m 6 with no base register.
+    rm: u8,
+    direct: bool,
+    disp: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Operand {
+    Reg8(Register8),
+    Reg16(Register16),
+    SegReg(Register16),
+    Imm(i64),
+    Mem(MemOperand),
+    Far(u16, u16),
+}
+
+impl Operand {
+    fn size(&self) -> Option<ValueSize> {
+        match self {
+            Operand::Reg8(_) => Some(ValueSize::Byte),
+            Operand::Reg16(_) | Operand::SegReg(_) => Some(ValueSize::Word),
+            Operand::Mem(mem) => mem.size,
+            _ => None,
+        }
+    }
+}
+
+/// How a jump to an immediate target should be encoded.
+#[derive(Copy, Clone, PartialEq)]
+enum JumpKind {
+    Default,
+    Short,
+    Near,
+    Far,
+}
+
+fn parse_register(s: &str) -> Option<Operand> {
+    use Register16::*;
+    use Register8::*;
+    Some(match s {
+        "al" => Operand::Reg8(AL),
+        "cl" => Operand::Reg8(CL),
+        "dl" => Operand::Reg8(DL),
+        "bl" => Operand::Reg8(BL),
+        "ah" => Operand::Reg8(AH),
+        "ch" => Operand::Reg8(CH),
+        "dh" => Operand::Reg8(DH),
+        "bh" => Operand::Reg8(BH),
+        "ax" => Operand::Reg16(AX),
+        "cx" => Operand::Reg16(CX),
+        "dx" => Operand::Reg16(DX),
+        "bx" => Operand::Reg16(BX),
+        "sp" => Operand::Reg16(SP),
+        "bp" => Operand::Reg16(BP),
+        "si" => Operand::Reg16(SI),
+        "di" => Operand::Reg16(DI),
+        "es" => Operand::SegReg(ES),
+        "cs" => Operand::SegReg(CS),
+        "ss" => Operand::SegReg(SS),
+        "ds" => Operand::SegReg(DS),
+        _ => return None,
+    })
+}
+
+/// Parse a hexadecimal constant with an optional leading minus sign.
+fn parse_number(s: &str) -> Option<i64> {
+    match s.strip_prefix('-') {
+        Some(s) => parse_hex(s.trim()).map(|v| -(v as i64)),
+        None => parse_hex(s).map(|v| v as i64),
+    }
+}
+
+/// Strip a segment override such as 'es:' from the start of `s`.
+fn parse_segment_override(s: &str) -> Result<(Option<Register16>, &str), AsmError> {
+    match s.split_once(':') {
+        Some((seg, rest)) => match parse_register(seg.trim()) {
+            Some(Operand::SegReg(seg)) => Ok((Some(seg), rest.trim())),
+            _ => Err(AsmError::InvalidOperand(seg.trim().to_string())),
+        },
+        None => Ok((None, s)),
+    }
+}
+
+/// Parse the inside of a memory operand's brackets, such as 'bx+si+4' or 'bp-2'.
+fn parse_address(s: &str, size: Option<ValueSize>, segment: Option<Register16>) -> Result<MemOperand, AsmError> {
+    let (inner_segment, s) = parse_segment_override(s)?;
+    let segment = match (segment, inner_segment) {
+        (Some(_), Some(_)) => return Err(AsmError::InvalidOperand(s.to_string())),
+        (outer, inner) => outer.or(inner),
+    };
+
+    let mut base = None;
+    let mut index = None;
+    let mut disp: i64 = 0;
+    let mut negative = false;
+    let mut term = String::new();
+    for c in s.chars().chain(std::iter::once('+')) {
+        if c != '+' && c != '-' {
+            term.push(c);
+            continue;
+        }
+        let word = term.trim();
+        if word.is_empty() {
+            return Err(AsmError::InvalidOperand(s.to_string()));
+        }
+        match (word, negative) {
+            ("bx" | "bp", false) if base.is_none() => base = Some(word == "bp"),
+            ("si" | "di", false) if index.is_none() => index = Some(word == "di"),
+            _ => match parse_register(word) {
+                Some(_) => return Err(AsmError::InvalidOperand(s.to_string())),
+                None => {
+                    let value = parse_number(word).ok_or_else(|| AsmError::InvalidOperand(word.to_string()))?;
+                    disp += if negative { -value } else { value };
+                }
+            },
+        }
+        negative = c == '-';
+        term.clear();
+    }
+
+    if !(-0x8000..=0xFFFF).contains(&disp) {
+        return Err(AsmError::ValueOutOfRange(disp));
+    }
+    let rm = match (base, index) {
+        (Some(false), Some(false)) => 0,
+        (Some(false), Some(true)) => 1,
+        (Some(true), Some(false)) => 2,
+        (Some(true), Some(true)) => 3,
+        (None, Some(false)) => 4,
+        (None, Some(true)) => 5,
+        (Some(true), None) | (None, None) => 6,
+        (Some(false), None) => 7,
+    };
+    Ok(MemOperand {
+        size,
+        segment,
+        rm,
+        direct: base.is_none() && index.is_none(),
+        disp: disp as i32,
+    })
+}
+
+fn parse_operand(s: &str) -> Result<Operand, AsmError> {
+    let s = s.trim();
+    let invalid = || AsmError::InvalidOperand(s.to_string());
+
+    // An optional size prefix, which must be followed by a memory operand.
+    let (size, rest) = match s.split_once(char::is_whitespace) {
+        Some(("byte", rest)) => (Some(ValueSize::Byte), rest.trim()),
+        Some(("word", rest)) => (Some(ValueSize::Word), rest.trim()),
+        _ => (None, s),
+    };
+    let rest = rest.strip_prefix("ptr ").map(str::trim).unwrap_or(rest);
+
+    if let Some((prefix, inner)) = rest.split_once('[') {
+        let inner = inner.strip_suffix(']').ok_or_else(invalid)?;
+        let (segment, prefix) = parse_segment_override(prefix.trim())?;
+        if !prefix.is_empty() {
+            return Err(invalid());
+        }
+        return parse_address(inner, size, segment).map(Operand::Mem);
+    }
+    if size.is_some() {
+        return Err(invalid());
+    }
+
+    if let Some((seg, offset)) = s.split_once(':') {
+        return match (parse_hex(seg.trim()), parse_hex(offset.trim())) {
+            (Some(seg), Some(offset)) if seg <= 0xFFFF && offset <= 0xFFFF => {
+                Ok(Operand::Far(seg as u16, offset as u16))
+            }
+            _ => Err(invalid()),
+        };
+    }
+    parse_register(s)
+        .or_else(|| parse_number(s).map(Operand::Imm))
+        .ok_or_else(invalid)
+}
+
+fn parse_mnemonic(s: &str) -> Option<Mnemonic> {
+    // Accept common aliases for mnemonics that the disassembler spells differently.
+    let mnemonic = match s {
+        "ret" => Mnemonic::RETN,
+        "je" => Mnemonic::JZ,
+        "jne" => Mnemonic::JNZ,
+        "jc" | "jnae" => Mnemonic::JB,
+        "jnc" | "jae" => Mnemonic::JNB,
+        "ja" => Mnemonic::JNBE,
+        "jna" => Mnemonic::JBE,
+        "jnge" => Mnemonic::JL,
+        "jge" => Mnemonic::JNL,
+        "jng" => Mnemonic::JLE,
+        "jg" => Mnemonic::JNLE,
+        "jpe" => Mnemonic::JP,
+        "jpo" => Mnemonic::JNP,
+        "loopz" => Mnemonic::LOOPE,
+        "loopnz" => Mnemonic::LOOPNE,
+        _ => Mnemonic::from_str(s)?,
+    };
+    Some(mnemonic)
+}
+
+fn reg8(reg: Register8) -> u8 {
+    reg as u8
+}
+
+fn reg16(reg: Register16) -> u8 {
+    reg as u8
+}
+
+fn sreg(reg: Register16) -> u8 {
+    reg as u8 - Register16::ES as u8
+}
+
+fn imm_bytes(value: i64, size: ValueSize) -> Result<Vec<u8>, AsmError> {
+    match size {
+        ValueSize::Byte if (-0x80..=0xFF).contains(&value) => Ok(vec![value as u8]),
+        ValueSize::Word if (-0x8000..=0xFFFF).contains(&value) => Ok((value as u16).to_le_bytes().to_vec()),
+        _ => Err(AsmError::ValueOutOfRange(value)),
+    }
+}
+
+/// Encode `op` as the r/m operand of an instruction with the given opcode bytes and ModR/M reg
+/// field, followed by `imm`. A segment override prefix is emitted if the operand specifies one.
+fn encode_rm(opcode: &[u8], reg: u8, op: &Operand, imm: &[u8]) -> Result<Vec<u8>, AsmError> {
+    let mut bytes = Vec::new();
+    let modrm = |md: u8, rm: u8| (md << 6) | (reg << 3) | rm;
+    match op {
+        Operand::Reg8(r) => {
+            bytes.extend_from_slice(opcode);
+            bytes.push(modrm(0b11, reg8(*r)));
+        }
+        Operand::Reg16(r) => {
+            bytes.extend_from_slice(opcode);
+            bytes.push(modrm(0b11, reg16(*r)));
+        }
+        Operand::Mem(mem) => {
+            if let Some(seg) = mem.segment {
+                bytes.push(0x26 | (sreg(seg) << 3));
+            }
+            bytes.extend_from_slice(opcode);
+            if mem.direct {
+                bytes.push(modrm(0b00, 6));
+                bytes.extend_from_slice(&(mem.disp as u16).to_le_bytes());
+            }
+            // [bp] has no encoding without a displacement.
+            else if mem.disp == 0 && mem.rm != 6 {
+                bytes.push(modrm(0b00, mem.rm));
+            }
+            else if (-0x80..=0x7F).contains(&mem.disp) {
+                bytes.push(modrm(0b01, mem.rm));
+                bytes.push(mem.disp as u8);
+            }
+            else {
+                bytes.push(modrm(0b10, mem.rm));
+                bytes.extend_from_slice(&(mem.disp as u16).to_le_bytes());
+            }
+        }
+        _ => {
+            return Err(AsmError::Unsupported(
+                "operand must be a register or memory".to_string(),
+            ))
+        }
+    }
+    bytes.extend_from_slice(imm);
+    Ok(bytes)
+}
+
+/// Resolve the operand size of a two-operand instruction.
+fn operand_size(dst: &Operand, src: &Operand) -> Result<ValueSize, AsmError> {
+    match (dst.size(), src.size()) {
+        (Some(a), Some(b)) if a != b => Err(AsmError::SizeMismatch),
+        (Some(size), _) | (None, Some(size)) => Ok(size),
+        (None, None) => Err(AsmError::SizeRequired),
+    }
+}
+
+fn word_bit(size: ValueSize) -> u8 {
+    match size {
+        ValueSize::Byte => 0,
+        ValueSize::Word => 1,
+    }
+}
+
+fn reg_code(op: &Operand) -> Option<u8> {
+    match op {
+        Operand::Reg8(r) => Some(reg8(*r)),
+        Operand::Reg16(r) => Some(reg16(*r)),
+        _ => None,
+    }
+}
+
+fn encode_mov(dst: &Operand, src: &Operand) -> Result<Vec<u8>, AsmError> {
+    use Operand::*;
+    match (dst, src) {
+        (SegReg(seg), Reg16(_) | Mem(_)) | (Reg16(_) | Mem(_), SegReg(seg)) => {
+            let (opcode, rm) = if matches!(dst, SegReg(_)) {
+                (0x8E, src)
+            }
+            else {
+                (0x8C, dst)
+            };
+            if rm.size() == Some(ValueSize::Byte) {
+                return Err(AsmError::SizeMismatch);
+            }
+            encode_rm(&[opcode], sreg(*seg), rm, &[])
+        }
+        (Reg8(_) | Reg16(_), Imm(value)) => {
+            let size = operand_size(dst, src)?;
+            let opcode = 0xB0 | (word_bit(size) << 3) | reg_code(dst).unwrap_or(0);
+            let mut bytes = vec![opcode];
+            bytes.extend(imm_bytes(*value, size)?);
+            Ok(bytes)
+        }
+        (Mem(_), Imm(value)) => {
+            let size = operand_size(dst, src)?;
+            encode_rm(&[0xC6 | word_bit(size)], 0, dst, &imm_bytes(*value, size)?)
+        }
+        // The accumulator has a shorter encoding for direct addresses.
+        (Reg8(Register8::AL) | Reg16(Register16::AX), Mem(mem))
+        | (Mem(mem), Reg8(Register8::AL) | Reg16(Register16::AX))
+            if mem.direct =>
+        {
+            let size = operand_size(dst, src)?;
+            let to_mem = matches!(dst, Mem(_));
+            let mut bytes = Vec::new();
+            if let Some(seg) = mem.segment {
+                bytes.push(0x26 | (sreg(seg) << 3));
+            }
+            bytes.push(0xA0 | ((to_mem as u8) << 1) | word_bit(size));
+            bytes.extend_from_slice(&(mem.disp as u16).to_le_bytes());
+            Ok(bytes)
+        }
+        (Reg8(_) | Reg16(_) | Mem(_), Reg8(_) | Reg16(_)) => {
+            let size = operand_size(dst, src)?;
+            encode_rm(&[0x88 | word_bit(size)], reg_code(src).unwrap_or(0), dst, &[])
+        }
+        (Reg8(_) | Reg16(_), Mem(_)) => {
+            let size = operand_size(dst, src)?;
+            encode_rm(&[0x8A | word_bit(size)], reg_code(dst).unwrap_or(0), src, &[])
+        }
+        _ => Err(AsmError::Unsupported("mov with these operands".to_string())),
+    }
+}
+
+/// Encode one of the eight ALU instructions (ADD, OR, ADC, SBB, AND, SUB, XOR, CMP), identified
+/// by `op`, which is both the ModR/M extension for the immediate forms and the opcode row.
+fn encode_alu(name: &str, op: u8, dst: &Operand, src: &Operand) -> Result<Vec<u8>, AsmError> {
+    use Operand::*;
+    let size = operand_size(dst, src)?;
+    let w = word_bit(size);
+    match (dst, src) {
+        (Reg8(Register8::AL) | Reg16(Register16::AX), Imm(value)) => {
+            let mut bytes = vec![(op << 3) | 0x04 | w];
+            bytes.extend(imm_bytes(*value, size)?);
+            Ok(bytes)
+        }
+        (Reg8(_) | Reg16(_) | Mem(_), Imm(value)) => {
+            let imm = imm_bytes(*value, size)?;
+            // Word immediates in the range of a sign-extended byte have a shorter encoding.
+            let sign_extended = imm.len() == 2 && (-0x80..=0x7F).contains(&(*value as u16 as i16));
+            if sign_extended {
+                encode_rm(&[0x83], op, dst, &imm[..1])
+            }
+            else {
+                encode_rm(&[0x80 | w], op, dst, &imm)
+            }
+        }
+        (Reg8(_) | Reg16(_) | Mem(_), Reg8(_) | Reg16(_)) => {
+            encode_rm(&[(op << 3) | w], reg_code(src).unwrap_or(0), dst, &[])
+        }
+        (Reg8(_) | Reg16(_), Mem(_)) => encode_rm(&[(op << 3) | 0x02 | w], reg_code(dst).unwrap_or(0), src, &[]),
+        _ => Err(AsmError::Unsupported(format!("{} with these operands", name))),
+    }
+}
+
+/// Calculate the displacement from the end of an instruction of `len` bytes at `ip` to `target`.
+fn relative(target: i64, ip: u16, len: u16) -> Result<i16, AsmError> {
+    if !(0..=0xFFFF).contains(&target) {
+        return Err(AsmError::ValueOutOfRange(target));
+    }
+    Ok((target as u16).wrapping_sub(ip.wrapping_add(len)) as i16)
+}
+
+/// Encode a conditional jump or loop, which only have an 8-bit displacement.
+fn encode_short_jump(opcode: u8, target: &Operand, ip: u16) -> Result<Vec<u8>, AsmError> {
+    let Operand::Imm(target) = target
+    else {
+        return Err(AsmError::Unsupported(
+            "conditional jumps require an immediate target".to_string(),
+        ));
+    };
+    let disp = relative(*target, ip, 2)?;
+    if !(-0x80..=0x7F).contains(&disp) {
+        return Err(AsmError::JumpOutOfRange(disp as i32));
+    }
+    Ok(vec![opcode, disp as u8])
+}
+
+/// Encode a JMP or CALL. `ext` holds the ModR/M extension for the near and far indirect forms.
+fn encode_transfer(call: bool, kind: JumpKind, target: &Operand, ip: u16, ext: (u8, u8)) -> Result<Vec<u8>, AsmError> {
+    match target {
+        Operand::Imm(target) if kind != JumpKind::Far => {
+            let short = relative(*target, ip, 2)?;
+            if !call && kind != JumpKind::Near && (-0x80..=0x7F).contains(&short) {
+                return Ok(vec![0xEB, short as u8]);
+            }
+            if kind == JumpKind::Short {
+                return Err(AsmError::JumpOutOfRange(short as i32));
+            }
+            let mut bytes = vec![if call { 0xE8 } else { 0xE9 }];
+            bytes.extend_from_slice(&relative(*target, ip, 3)?.to_le_bytes());
+            Ok(bytes)
+        }
+        Operand::Far(seg, offset) => {
+            let mut bytes = vec![if call { 0x9A } else { 0xEA }];
+            bytes.extend_from_slice(&offset.to_le_bytes());
+            bytes.extend_from_slice(&seg.to_le_bytes());
+            Ok(bytes)
+        }
+        Operand::Reg16(_) if kind != JumpKind::Far => encode_rm(&[0xFF], ext.0, target, &[]),
+        Operand::Mem(mem) if mem.size != Some(ValueSize::Byte) => {
+            let ext = if kind == JumpKind::Far { ext.1 } else { ext.0 };
+            encode_rm(&[0xFF], ext, target, &[])
+        }
+        _ => Err(AsmError::Unsupported("jump or call to this operand".to_string())),
+    }
+}
+
+/// Conditional jumps, in the order of their condition codes.
+const CONDITIONAL_JUMPS: [Mnemonic; 16] = [
+    Mnemonic::JO,
+    Mnemonic::JNO,
+    Mnemonic::JB,
+    Mnemonic::JNB,
+    Mnemonic::JZ,
+    Mnemonic::JNZ,
+    Mnemonic::JBE,
+    Mnemonic::JNBE,
+    Mnemonic::JS,
+    Mnemonic::JNS,
+    Mnemonic::JP,
+    Mnemonic::JNP,
+    Mnemonic::JL,
+    Mnemonic::JNL,
+    Mnemonic::JLE,
+    Mnemonic::JNLE,
+];
+
+/// Instructions without operands, and their opcodes.
+fn implied_opcode(mnemonic: Mnemonic) -> Option<u8> {
+    use Mnemonic::*;
+    Some(match mnemonic {
+        NOP => 0x90,
+        DAA => 0x27,
+        DAS => 0x2F,
+        AAA => 0x37,
+        AAS => 0x3F,
+        CBW => 0x98,
+        CWD => 0x99,
+        WAIT => 0x9B,
+        PUSHF => 0x9C,
+        POPF => 0x9D,
+        SAHF => 0x9E,
+        LAHF => 0x9F,
+        MOVSB => 0xA4,
+        MOVSW => 0xA5,
+        CMPSB => 0xA6,
+        CMPSW => 0xA7,
+        STOSB => 0xAA,
+        STOSW => 0xAB,
+        LODSB => 0xAC,
+        LODSW => 0xAD,
+        SCASB => 0xAE,
+        SCASW => 0xAF,
+        INT3 => 0xCC,
+        INTO => 0xCE,
+        IRET => 0xCF,
+        XLAT => 0xD7,
+        HLT => 0xF4,
+        CMC => 0xF5,
+        CLC => 0xF8,
+        STC => 0xF9,
+        CLI => 0xFA,
+        STI => 0xFB,
+        CLD => 0xFC,
+        STD => 0xFD,
+        _ => return None,
+    })
+}
+
+/// Assemble a single 8088 instruction located at offset `ip` within its code segment, returning
+/// the encoded bytes. The offset is needed to encode relative jumps and calls, whose targets are
+/// given as offsets within the same segment.
+pub fn assemble(text: &str, ip: u16) -> Result<Vec<u8>, AsmError> {
+    let text = text.trim().to_ascii_lowercase();
+    if text.is_empty() {
+        return Err(AsmError::Empty);
+    }
+    let (name, rest) = text.split_once(char::is_whitespace).unwrap_or((&text, ""));
+    let mnemonic = parse_mnemonic(name).ok_or_else(|| AsmError::UnknownMnemonic(name.to_string()))?;
+
+    let mut operand_strs: Vec<&str> = match rest.trim() {
+        "" => Vec::new(),
+        rest => rest.split(',').map(str::trim).collect(),
+    };
+
+    // Jumps and calls may specify 'short', 'near' or 'far'.
+    let mut kind = JumpKind::Default;
+    if let (Mnemonic::JMP | Mnemonic::CALL, [op]) = (mnemonic, operand_strs.as_mut_slice()) {
+        for (prefix, k) in [
+            ("short ", JumpKind::Short),
+            ("near ", JumpKind::Near),
+            ("far ", JumpKind::Far),
+        ] {
+            if let Some(rest) = op.strip_prefix(prefix) {
+                kind = k;
+                *op = rest.trim();
+            }
+        }
+    }
+    let operands = operand_strs
+        .iter()
+        .map(|s| parse_operand(s))
+        .collect::<Result<Vec<_>, _>>()?;
+    let expect = |n: usize| {
+        if operand_strs.len() == n {
+            Ok(())
+        }
+        else {
+            Err(AsmError::OperandCount(n))
+        }
+    };
+
+    if let Some(opcode) = implied_opcode(mnemonic) {
+        expect(0)?;
+        return Ok(vec![opcode]);
+    }
+
+    use Mnemonic::*;
+    match mnemonic {
+        MOV => {
+            expect(2)?;
+            encode_mov(&operands[0], &operands[1])
+        }
+        ADD | OR | ADC | SBB | AND | SUB | XOR | CMP => {
+            expect(2)?;
+            let op = match mnemonic {
+                ADD => 0,
+                OR => 1,
+                ADC => 2,
+                SBB => 3,
+                AND => 4,
+                SUB => 5,
+                XOR => 6,
+                _ => 7,
+            };
+            encode_alu(name, op, &operands[0], &operands[1])
+        }
+        JMP => {
+            expect(1)?;
+            encode_transfer(false, kind, &operands[0], ip, (4, 5))
+        }
+        CALL => {
+            expect(1)?;
+            if kind == JumpKind::Short {
+                return Err(AsmError::Unsupported("call short".to_string()));
+            }
+            encode_transfer(true, kind, &operands[0], ip, (2, 3))
+        }
+        _ if CONDITIONAL_JUMPS.contains(&mnemonic) => {
+            expect(1)?;
+            let cc = CONDITIONAL_JUMPS.iter().position(|m| *m == mnemonic).unwrap_or(0) as u8;
+            encode_short_jump(0x70 | cc, &operands[0], ip)
+        }
+        LOOPNE | LOOPE | LOOP | JCXZ => {
+            expect(1)?;
+            let opcode = match mnemonic {
+                LOOPNE => 0xE0,
+                LOOPE => 0xE1,
+                LOOP => 0xE2,
+                _ => 0xE3,
+            };
+            encode_short_jump(opcode, &operands[0], ip)
+        }
+        INT => {
+            expect(1)?;
+            match operands[0] {
+                Operand::Imm(value @ 0..=0xFF) => Ok(vec![0xCD, value as u8]),
+                Operand::Imm(value) => Err(AsmError::ValueOutOfRange(value)),
+                _ => Err(AsmError::InvalidOperand(operand_strs[0].to_string())),
+            }
+        }
+        PUSH | POP => {
+            expect(1)?;
+            let push = mnemonic == PUSH;
+            match &operands[0] {
+                Operand::Reg16(r) => Ok(vec![if push { 0x50 } else { 0x58 } | reg16(*r)]),
+                Operand::SegReg(r) => Ok(vec![if push { 0x06 } else { 0x07 } | (sreg(*r) << 3)]),
+                Operand::Mem(mem) if mem.size != Some(ValueSize::Byte) => {
+                    if push {
+                        encode_rm(&[0xFF], 6, &operands[0], &[])
+                    }
+                    else {
+                        encode_rm(&[0x8F], 0, &operands[0], &[])
+                    }
+                }
+                Operand::Imm(_) => Err(AsmError::Unsupported(format!(
+                    "{} of an immediate requires an 80186",
+                    name
+                ))),
+                Operand::Reg8(_) | Operand::Mem(_) => Err(AsmError::SizeMismatch),
+                _ => Err(AsmError::InvalidOperand(operand_strs[0].to_string())),
+            }
+        }
+        RETN | RETF => {
+            let far = mnemonic == RETF;
+            match operands.as_slice() {
+                [] => Ok(vec![if far { 0xCB } else { 0xC3 }]),
+                [Operand::Imm(value)] => {
+                    let mut bytes = vec![if far { 0xCA } else { 0xC2 }];
+                    bytes.extend(imm_bytes(*value, ValueSize::Word)?);
+                    Ok(bytes)
+                }
+                _ => Err(AsmError::InvalidOperand(rest.trim().to_string())),
+            }
+        }
+        _ => Err(AsmError::Unsupported(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asm(text: &str) -> Vec<u8> {
+        assemble(text, 0x0100).unwrap_or_else(|e| panic!("{}: {}", text, e))
+    }
+
+    #[test]
+    fn assemble_mov() {
+        assert_eq!(asm("mov ax, 0x1234"), [0xB8, 0x34, 0x12]);
+        assert_eq!(asm("MOV CL, 12h"), [0xB1, 0x12]);
+        assert_eq!(asm("mov bx, -1"), [0xBB, 0xFF, 0xFF]);
+        assert_eq!(asm("mov [bx+si+4], al"), [0x88, 0x40, 0x04]);
+        assert_eq!(asm("mov word [bp-2], 5"), [0xC7, 0x46, 0xFE, 0x05, 0x00]);
+        assert_eq!(asm("mov byte ptr [bp], 1"), [0xC6, 0x46, 0x00, 0x01]);
+        assert_eq!(asm("mov dx, [bx+1000]"), [0x8B, 0x97, 0x00, 0x10]);
+        assert_eq!(asm("mov ax, [1234]"), [0xA1, 0x34, 0x12]);
+        assert_eq!(asm("mov cx, [1234]"), [0x8B, 0x0E, 0x34, 0x12]);
+        assert_eq!(asm("mov es:[di], ax"), [0x26, 0x89, 0x05]);
+        assert_eq!(asm("mov [cs:si], dl"), [0x2E, 0x88, 0x14]);
+        assert_eq!(asm("mov ds, ax"), [0x8E, 0xD8]);
+        assert_eq!(asm("mov [bx], es"), [0x8C, 0x07]);
+        assert_eq!(asm("mov si, di"), [0x89, 0xFE]);
+    }
+
+    #[test]
+    fn assemble_alu() {
+        assert_eq!(asm("add ax, bx"), [0x01, 0xD8]);
+        assert_eq!(asm("add bx, 1"), [0x83, 0xC3, 0x01]);
+        assert_eq!(asm("add bx, 1000"), [0x81, 0xC3, 0x00, 0x10]);
+        assert_eq!(asm("add al, 7"), [0x04, 0x07]);
+        assert_eq!(asm("cmp ax, ffff"), [0x3D, 0xFF, 0xFF]);
+        assert_eq!(asm("cmp byte [si], 0x80"), [0x80, 0x3C, 0x80]);
+        assert_eq!(asm("sub cl, [bx]"), [0x2A, 0x0F]);
+        assert_eq!(asm("sub [di+10], ax"), [0x29, 0x45, 0x10]);
+    }
+
+    #[test]
+    fn assemble_transfers() {
+        assert_eq!(asm("jmp 0110"), [0xEB, 0x0E]);
+        assert_eq!(asm("jmp near 0110"), [0xE9, 0x0D, 0x00]);
+        assert_eq!(asm("jmp 1000"), [0xE9, 0xFD, 0x0E]);
+        assert_eq!(asm("jmp f000:fff0"), [0xEA, 0xF0, 0xFF, 0x00, 0xF0]);
+        assert_eq!(asm("jmp bx"), [0xFF, 0xE3]);
+        assert_eq!(asm("jmp far [bx]"), [0xFF, 0x2F]);
+        assert_eq!(asm("jz 0100"), [0x74, 0xFE]);
+        assert_eq!(asm("jne 00c0"), [0x75, 0xBE]);
+        assert_eq!(asm("loop 0100"), [0xE2, 0xFE]);
+        assert_eq!(asm("call 0200"), [0xE8, 0xFD, 0x00]);
+        assert_eq!(asm("call bx"), [0xFF, 0xD3]);
+        assert_eq!(asm("call word [bx+2]"), [0xFF, 0x57, 0x02]);
+        assert_eq!(asm("call far [bx+2]"), [0xFF, 0x5F, 0x02]);
+        assert_eq!(asm("int 21"), [0xCD, 0x21]);
+        assert_eq!(asm("ret"), [0xC3]);
+        assert_eq!(asm("retf 4"), [0xCA, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn assemble_stack_and_implied() {
+        assert_eq!(asm("push ds"), [0x1E]);
+        assert_eq!(asm("pop es"), [0x07]);
+        assert_eq!(asm("push si"), [0x56]);
+        assert_eq!(asm("pop ax"), [0x58]);
+        assert_eq!(asm("push word [bx]"), [0xFF, 0x37]);
+        assert_eq!(asm("pop [bx]"), [0x8F, 0x07]);
+        assert_eq!(asm("nop"), [0x90]);
+        assert_eq!(asm("cli"), [0xFA]);
+        assert_eq!(asm("int3"), [0xCC]);
+    }
+
+    #[test]
+    fn assemble_rejects_bad_input() {
+        let err = |text: &str| assemble(text, 0x0100).unwrap_err();
+        assert_eq!(err(""), AsmError::Empty);
+        assert_eq!(err("foo ax"), AsmError::UnknownMnemonic("foo".to_string()));
+        assert_eq!(err("mov [bx], 1"), AsmError::SizeRequired);
+        assert_eq!(err("mov ax, bl"), AsmError::SizeMismatch);
+        assert_eq!(err("mov al, 100"), AsmError::ValueOutOfRange(0x100));
+        assert_eq!(err("mov ax"), AsmError::OperandCount(2));
+        assert_eq!(err("nop ax"), AsmError::OperandCount(0));
+        assert_eq!(err("mov ax, [bx+bp]"), AsmError::InvalidOperand("bx+bp".to_string()));
+        assert_eq!(err("mov ax, zz"), AsmError::InvalidOperand("zz".to_string()));
+        assert_eq!(err("jz 0200"), AsmError::JumpOutOfRange(0xFE));
+        assert_eq!(err("jmp short 0200"), AsmError::JumpOutOfRange(0xFE));
+        assert!(matches!(err("lea ax, [bx]"), AsmError::Unsupported(_)));
+        assert!(matches!(err("push 1"), AsmError::Unsupported(_)));
+    }
+}
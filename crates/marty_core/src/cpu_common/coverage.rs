@@ -0,0 +1,217 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::coverage.rs
+
+    Implements an instruction coverage recorder. The recorder tracks the
+    distinct instruction "shapes" (opcode, modrm byte and prefixes) executed
+    by the CPU, in order to identify gaps in a test corpus.
+
+*/
+
+use std::{collections::HashMap, fmt::Write};
+
+use crate::cpu_common::{mnemonic::mnemonic_to_str, Instruction, Mnemonic, OPCODE_PREFIX_0F, OPCODE_PREFIX_CT_MASK};
+
+/// A distinct decoded instruction form.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstructionShape {
+    pub opcode:   u8,
+    pub modrm:    Option<u8>,
+    pub prefixes: u32,
+}
+
+impl From<&Instruction> for InstructionShape {
+    fn from(i: &Instruction) -> Self {
+        InstructionShape {
+            opcode:   i.opcode,
+            modrm:    i.modrm,
+            // The prefix count field is bookkeeping for interrupted string operations, not part of the shape.
+            prefixes: i.prefixes & !OPCODE_PREFIX_CT_MASK,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ShapeEntry {
+    pub shape:    InstructionShape,
+    pub mnemonic: Mnemonic,
+    pub count:    u64,
+}
+
+#[derive(Default)]
+pub struct CoverageRecorder {
+    enabled: bool,
+    shapes:  HashMap<InstructionShape, (Mnemonic, u64)>,
+}
+
+impl CoverageRecorder {
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable coverage recording. Changing state resets any collected coverage.
+    pub fn set_enabled(&mut self, state: bool) {
+        if state != self.enabled {
+            self.shapes.clear();
+        }
+        self.enabled = state;
+    }
+
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+    }
+
+    #[inline]
+    pub fn record(&mut self, i: &Instruction) {
+        if !self.enabled {
+            return;
+        }
+        self.shapes
+            .entry(InstructionShape::from(i))
+            .or_insert((i.mnemonic, 0))
+            .1 += 1;
+    }
+
+    pub fn report(&self) -> CoverageReport {
+        let mut shapes: Vec<ShapeEntry> = self
+            .shapes
+            .iter()
+            .map(|(shape, (mnemonic, count))| ShapeEntry {
+                shape:    *shape,
+                mnemonic: *mnemonic,
+                count:    *count,
+            })
+            .collect();
+        shapes.sort_by_key(|e| {
+            (
+                e.shape.prefixes & OPCODE_PREFIX_0F,
+                e.shape.opcode,
+                e.shape.modrm,
+                e.shape.prefixes,
+            )
+        });
+
+        let mut touched = [false; 256];
+        for e in shapes.iter().filter(|e| e.shape.prefixes & OPCODE_PREFIX_0F == 0) {
+            touched[e.shape.opcode as usize] = true;
+        }
+        let untouched_opcodes = (0..=255u8).filter(|op| !touched[*op as usize]).collect();
+
+        CoverageReport {
+            shapes,
+            untouched_opcodes,
+        }
+    }
+}
+
+/// A summary of the instruction shapes executed while coverage recording was enabled.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+    /// Distinct instruction shapes seen, with execution counts.
+    pub shapes: Vec<ShapeEntry>,
+    /// Primary (single-byte) opcodes that were never executed.
+    pub untouched_opcodes: Vec<u8>,
+}
+
+impl CoverageReport {
+    /// Render the report as CSV. Untouched primary opcodes are listed with a count of zero.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("opcode,modrm,prefixes,mnemonic,count\n");
+        for e in &self.shapes {
+            let opcode = if e.shape.prefixes & OPCODE_PREFIX_0F != 0 {
+                format!("0F{:02X}", e.shape.opcode)
+            }
+            else {
+                format!("{:02X}", e.shape.opcode)
+            };
+            let modrm = e.shape.modrm.map(|m| format!("{:02X}", m)).unwrap_or_default();
+            _ = writeln!(
+                csv,
+                "{},{},{:04X},{},{}",
+                opcode,
+                modrm,
+                e.shape.prefixes & !OPCODE_PREFIX_0F,
+                mnemonic_to_str(e.mnemonic),
+                e.count
+            );
+        }
+        for op in &self.untouched_opcodes {
+            _ = writeln!(csv, "{:02X},,0000,,0", op);
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_common::OPCODE_PREFIX_REP1;
+
+    fn instr(opcode: u8, modrm: Option<u8>, prefixes: u32) -> Instruction {
+        Instruction {
+            opcode,
+            modrm,
+            prefixes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn coverage_shapes() {
+        let mut rec = CoverageRecorder::default();
+        rec.record(&instr(0x90, None, 0));
+        assert!(rec.report().shapes.is_empty());
+
+        rec.set_enabled(true);
+        rec.record(&instr(0x90, None, 0));
+        rec.record(&instr(0x90, None, 0));
+        rec.record(&instr(0x01, Some(0xC0), 0));
+        rec.record(&instr(0x01, Some(0x06), 0));
+        rec.record(&instr(0xA4, None, OPCODE_PREFIX_REP1));
+        // Prefix count bits do not create a new shape.
+        rec.record(&instr(0xA4, None, OPCODE_PREFIX_REP1 | 1));
+        // Extended opcodes don't count towards primary opcode coverage.
+        rec.record(&instr(0x02, Some(0xC0), OPCODE_PREFIX_0F));
+
+        let report = rec.report();
+        assert_eq!(report.shapes.len(), 5);
+        assert_eq!(report.untouched_opcodes.len(), 253);
+        assert!(!report.untouched_opcodes.contains(&0x90));
+        assert!(report.untouched_opcodes.contains(&0x02));
+
+        let nop = report.shapes.iter().find(|e| e.shape.opcode == 0x90).unwrap();
+        assert_eq!(nop.count, 2);
+        let movsb = report.shapes.iter().find(|e| e.shape.opcode == 0xA4).unwrap();
+        assert_eq!(movsb.count, 2);
+
+        let csv = report.to_csv();
+        assert_eq!(csv.lines().count(), 1 + 5 + 253);
+        assert!(csv.contains("\n0F02,C0,0000,"));
+    }
+}
@@ -0,0 +1,160 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::cycle_trace.rs
+
+    Defines a structured cycle trace record. When enabled, the CPU pushes one
+    record per cycle into a bounded ring buffer, so that a debugger can
+    display the cycle trace without parsing the text trace format.
+
+*/
+
+use crate::cpu_common::{QueueOp, Segment};
+
+/// The number of cycle trace records kept by the CPU.
+pub const CYCLE_TRACE_RECORD_LEN: usize = 4096;
+
+/// A data transfer on the bus during a cycle.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum BusTransfer {
+    #[default]
+    None,
+    Read(u8),
+    Write(u8),
+}
+
+/// The state of the CPU for a single cycle. The fields mirror the columns of the text cycle trace.
+#[derive(Clone, Debug, Default)]
+pub struct CycleTraceRecord {
+    pub cycle: u64,
+    pub instr_cycle: u32,
+    pub ale: bool,
+    pub address_latch: u32,
+    /// The segment status. Only valid in T2 and later, otherwise Segment::None.
+    pub segment: Segment,
+    pub ready: bool,
+    pub wait_states: u32,
+    pub last_wait: bool,
+    pub mrdc: bool,
+    pub amwc: bool,
+    pub mwtc: bool,
+    pub iorc: bool,
+    pub aiowc: bool,
+    pub iowc: bool,
+    pub dma: String,
+    /// The bus status as encoded on the S0-S2 status lines.
+    pub bus_status: u8,
+    pub t_cycle: String,
+    pub pl_slots: [String; 2],
+    pub transfer: BusTransfer,
+    pub bus_pending: String,
+    pub fetch_state: String,
+    pub queue_op: QueueOp,
+    pub queue_len: usize,
+    pub queue_preload: bool,
+    pub queue: String,
+    /// The byte read from the queue, if queue_op is First or Subsequent.
+    pub queue_byte: u8,
+    /// The microcode line number, or an empty string for CPUs without microcode tracing.
+    pub microcode_line: String,
+    pub microcode_src: &'static str,
+    /// The decoded instruction, set on the cycle its first byte is read from the queue.
+    pub instruction: Option<String>,
+    pub comments: Vec<&'static str>,
+}
+
+impl CycleTraceRecord {
+    /// Return whether this cycle begins a new instruction.
+    #[inline]
+    pub fn begins_instruction(&self) -> bool {
+        self.queue_op == QueueOp::First
+    }
+}
+
+/// Return the mnemonic for a bus status, as shown in the text cycle trace.
+pub fn bus_status_str(status: u8) -> &'static str {
+    match status & 0x07 {
+        0 => "INTA",
+        1 => "IOR ",
+        2 => "IOW ",
+        3 => "HALT",
+        4 => "CODE",
+        5 => "MEMR",
+        6 => "MEMW",
+        _ => "PASV",
+    }
+}
+
+/// Return the character for a queue operation, as shown in the text cycle trace.
+pub fn queue_op_char(op: QueueOp) -> char {
+    match op {
+        QueueOp::Idle => ' ',
+        QueueOp::First => 'F',
+        QueueOp::Flush => 'E',
+        QueueOp::Subsequent => 'S',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu_common::{
+        test_util::{cpu_with_code, step},
+        Cpu,
+        CpuOption,
+        CpuType,
+    };
+
+    #[test]
+    fn records_mark_instruction_boundaries() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // NOP x4
+            let mut cpu = cpu_with_code(cpu_type, &[0x90; 4]);
+            assert!(cpu.get_cycle_trace_records().is_none());
+
+            cpu.set_option(CpuOption::CycleTraceRecords(true));
+            assert!(cpu.get_option(CpuOption::CycleTraceRecords(true)));
+            for _ in 0..3 {
+                step(&mut cpu);
+            }
+
+            let records = cpu.get_cycle_trace_records().unwrap();
+            assert!(records.len() >= 3);
+            assert!(records
+                .iter()
+                .zip(records.iter().skip(1))
+                .all(|(a, b)| b.cycle > a.cycle));
+            let firsts = records.iter().filter(|r| r.begins_instruction()).count();
+            assert!(firsts >= 2);
+            assert!(records
+                .iter()
+                .filter(|r| r.begins_instruction())
+                .all(|r| r.instruction.is_some()));
+
+            cpu.set_option(CpuOption::CycleTraceRecords(false));
+            assert!(cpu.get_cycle_trace_records().is_none());
+        }
+    }
+}
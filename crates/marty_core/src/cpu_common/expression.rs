@@ -0,0 +1,442 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::expression.rs
+
+    Implements a small expression evaluator for debugger watches and addresses.
+    Expressions add, subtract and multiply registers and hexadecimal constants,
+    may specify a segment with ':', and may dereference memory with brackets and
+    an optional 'byte' or 'word' size prefix, for example 'ds:si', '[bx+4]',
+    'word [0040:006C]' or '0x400+si*2'.
+
+*/
+
+use std::{error::Error, fmt, fmt::Display};
+
+use crate::{
+    bus::BusInterface,
+    cpu_common::{calc_linear_address, CpuAddress},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ValueSize {
+    Byte,
+    Word,
+}
+
+/// The result of evaluating an expression.
+#[derive(Copy, Clone, Debug)]
+pub struct EvalResult {
+    /// The memory address the expression resolved to, if it referenced memory.
+    pub address: Option<CpuAddress>,
+    pub value: u16,
+    pub size: ValueSize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    Empty,
+    UnexpectedChar(char),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    InvalidOperand(String),
+    ReadError(u32),
+}
+impl Error for EvalError {}
+impl Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Empty => write!(f, "Empty expression"),
+            EvalError::UnexpectedChar(c) => write!(f, "Unexpected character '{}'", c),
+            EvalError::UnexpectedToken(t) => write!(f, "Unexpected '{}'", t),
+            EvalError::UnexpectedEnd => write!(f, "Unexpected end of expression"),
+            EvalError::InvalidOperand(s) => write!(f, "'{}' is not a register or hex value", s),
+            EvalError::ReadError(addr) => write!(f, "Can't read memory at {:05X}", addr),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Plus,
+    Minus,
+    Star,
+    Colon,
+    OpenBracket,
+    CloseBracket,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(s) => write!(f, "{}", s),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Star => write!(f, "*"),
+            Token::Colon => write!(f, ":"),
+            Token::OpenBracket => write!(f, "["),
+            Token::CloseBracket => write!(f, "]"),
+        }
+    }
+}
+
+fn lex(expr: &str) -> Result<Vec<Token>, EvalError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | ':' | '[' | ']' => {
+                chars.next();
+                tokens.push(match c {
+                    '+' => Token::Plus,
+                    '-' => Token::Minus,
+                    '*' => Token::Star,
+                    ':' => Token::Colon,
+                    '[' => Token::OpenBracket,
+                    _ => Token::CloseBracket,
+                });
+            }
+            c if c.is_ascii_alphanumeric() => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    word.push(c.to_ascii_lowercase());
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => return Err(EvalError::UnexpectedChar(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a hexadecimal constant, with an optional '0x' prefix or 'h' suffix.
+pub(crate) fn parse_hex(s: &str) -> Option<u32> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_suffix('h')).unwrap_or(s);
+    if digits.is_empty() || digits.len() > 5 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    registers: &'a dyn Fn(&str) -> Option<u16>,
+    /// Set when the expression refers to BP, making SS the default segment.
+    uses_bp: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), EvalError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(EvalError::UnexpectedToken(token.to_string())),
+            None => Err(EvalError::UnexpectedEnd),
+        }
+    }
+
+    fn term(&mut self) -> Result<i64, EvalError> {
+        match self.next() {
+            Some(Token::Word(word)) => {
+                if let Some(value) = (self.registers)(&word) {
+                    if word == "bp" {
+                        self.uses_bp = true;
+                    }
+                    Ok(value as i64)
+                }
+                else {
+                    parse_hex(&word)
+                        .map(|value| value as i64)
+                        .ok_or(EvalError::InvalidOperand(word))
+                }
+            }
+            Some(token) => Err(EvalError::UnexpectedToken(token.to_string())),
+            None => Err(EvalError::UnexpectedEnd),
+        }
+    }
+
+    /// Multiplication binds more tightly than addition and subtraction.
+    fn product(&mut self) -> Result<i64, EvalError> {
+        let mut value = self.term()?;
+        while let Some(Token::Star) = self.peek() {
+            self.next();
+            value = value.wrapping_mul(self.term()?);
+        }
+        Ok(value)
+    }
+
+    fn sum(&mut self) -> Result<i64, EvalError> {
+        let mut value = self.product()?;
+        loop {
+            match self.peek() {
+                Some(Token::Plus) => {
+                    self.next();
+                    value = value.wrapping_add(self.product()?);
+                }
+                Some(Token::Minus) => {
+                    self.next();
+                    value = value.wrapping_sub(self.product()?);
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    /// Parse a sum with an optional segment, returning (segment, offset).
+    fn address(&mut self) -> Result<(Option<u16>, i64), EvalError> {
+        let first = self.sum()?;
+        if let Some(Token::Colon) = self.peek() {
+            self.next();
+            let offset = self.sum()?;
+            Ok((Some(first as u16), offset))
+        }
+        else {
+            Ok((None, first))
+        }
+    }
+}
+
+/// Evaluate an address expression such as 'cs:ip-2', 'ds:bx+4' or '0x400+si*2'. `registers` resolves a
+/// lowercase register name to its value, and returns None for names that are not registers.
+///
+/// The segment and offset may both be arithmetic expressions. The offset wraps within its segment; an
+/// expression without a segment is a flat address.
+pub fn eval_address(expr: &str, registers: &dyn Fn(&str) -> Option<u16>) -> Result<CpuAddress, EvalError> {
+    let mut parser = Parser {
+        tokens: lex(expr)?,
+        pos: 0,
+        registers,
+        uses_bp: false,
+    };
+
+    if parser.peek().is_none() {
+        return Err(EvalError::Empty);
+    }
+
+    let (segment, offset) = parser.address()?;
+    if let Some(token) = parser.next() {
+        return Err(EvalError::UnexpectedToken(token.to_string()));
+    }
+
+    Ok(match segment {
+        Some(segment) => CpuAddress::Segmented(segment, offset as u16),
+        None => CpuAddress::Flat(offset as u32 & 0xFFFFF),
+    })
+}
+
+/// Evaluate an expression. `registers` resolves a lowercase register name to its value, and returns
+/// None for names that are not registers. Memory is read through `bus` without side effects.
+///
+/// A bracketed expression without a segment uses DS, or SS if it refers to BP. An expression
+/// with a segment, or a bare value larger than 16 bits, is also treated as a memory reference.
+/// Other expressions evaluate to their value, truncated to a byte if a 'byte' prefix is given.
+pub fn eval_expression(
+    expr: &str,
+    registers: &dyn Fn(&str) -> Option<u16>,
+    bus: &BusInterface,
+) -> Result<EvalResult, EvalError> {
+    let mut parser = Parser {
+        tokens: lex(expr)?,
+        pos: 0,
+        registers,
+        uses_bp: false,
+    };
+
+    if parser.peek().is_none() {
+        return Err(EvalError::Empty);
+    }
+
+    let mut size = ValueSize::Word;
+    if let Some(Token::Word(word)) = parser.peek() {
+        let prefix = match word.as_str() {
+            "byte" => Some(ValueSize::Byte),
+            "word" => Some(ValueSize::Word),
+            _ => None,
+        };
+        if let Some(prefix) = prefix {
+            size = prefix;
+            parser.next();
+            if let Some(Token::Word(word)) = parser.peek() {
+                if word == "ptr" {
+                    parser.next();
+                }
+            }
+        }
+    }
+
+    let bracketed = matches!(parser.peek(), Some(Token::OpenBracket));
+    if bracketed {
+        parser.next();
+    }
+    let (segment, offset) = parser.address()?;
+    if bracketed {
+        parser.expect(Token::CloseBracket)?;
+    }
+    if let Some(token) = parser.next() {
+        return Err(EvalError::UnexpectedToken(token.to_string()));
+    }
+
+    let address = match segment {
+        Some(segment) => CpuAddress::Segmented(segment, offset as u16),
+        None if offset > 0xFFFF => CpuAddress::Flat(offset as u32 & 0xFFFFF),
+        None if bracketed => {
+            let segment = if parser.uses_bp { "ss" } else { "ds" };
+            CpuAddress::Segmented((registers)(segment).unwrap_or(0), offset as u16)
+        }
+        None => {
+            let value = match size {
+                ValueSize::Byte => offset as u16 & 0xFF,
+                ValueSize::Word => offset as u16,
+            };
+            return Ok(EvalResult {
+                address: None,
+                value,
+                size,
+            });
+        }
+    };
+
+    // The high byte of a word wraps around within its segment.
+    let (lo_addr, hi_addr) = match address {
+        CpuAddress::Segmented(s, o) => (calc_linear_address(s, o), calc_linear_address(s, o.wrapping_add(1))),
+        CpuAddress::Flat(a) => (a, (a + 1) & 0xFFFFF),
+        CpuAddress::Offset(o) => (o as u32, o.wrapping_add(1) as u32),
+    };
+    let read = |addr: u32| bus.peek_u8(addr as usize).map_err(|_| EvalError::ReadError(addr));
+
+    let value = match size {
+        ValueSize::Byte => read(lo_addr)? as u16,
+        ValueSize::Word => u16::from_le_bytes([read(lo_addr)?, read(hi_addr)?]),
+    };
+
+    Ok(EvalResult {
+        address: Some(address),
+        value,
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registers(name: &str) -> Option<u16> {
+        match name {
+            "ax" => Some(0x1234),
+            "bx" => Some(0x0100),
+            "si" => Some(0x0010),
+            "bp" => Some(0x0200),
+            "ds" => Some(0x2000),
+            "ss" => Some(0x3000),
+            _ => None,
+        }
+    }
+
+    fn eval(bus: &BusInterface, expr: &str) -> Result<EvalResult, EvalError> {
+        eval_expression(expr, &registers, bus)
+    }
+
+    #[test]
+    fn eval_register_arithmetic() {
+        let bus = BusInterface::default();
+        let result = eval(&bus, "ax+bx-2").unwrap();
+        assert!(result.address.is_none());
+        assert_eq!(result.value, 0x1332);
+        assert_eq!(eval(&bus, "byte ax").unwrap().value, 0x34);
+        assert_eq!(eval(&bus, "bx+si*2-1").unwrap().value, 0x011F);
+    }
+
+    #[test]
+    fn eval_address_arithmetic() {
+        assert!(matches!(eval_address("ds:bx+4", &registers), Ok(CpuAddress::Segmented(0x2000, 0x0104))));
+        assert!(matches!(eval_address("0x400+si*2", &registers), Ok(CpuAddress::Flat(0x0420))));
+        assert!(matches!(eval_address("B8000", &registers), Ok(CpuAddress::Flat(0xB8000))));
+        assert!(matches!(eval_address("0040:006C", &registers), Ok(CpuAddress::Segmented(0x0040, 0x006C))));
+
+        // The offset wraps within the segment.
+        assert!(matches!(eval_address("ss:si-11", &registers), Ok(CpuAddress::Segmented(0x3000, 0xFFFF))));
+        assert!(matches!(eval_address("ds:ax*10", &registers), Ok(CpuAddress::Segmented(0x2000, 0x2340))));
+
+        assert_eq!(eval_address("[bx]", &registers).unwrap_err(), EvalError::UnexpectedToken("[".to_string()));
+        assert_eq!(eval_address("ds:", &registers).unwrap_err(), EvalError::UnexpectedEnd);
+        assert_eq!(eval_address("ds:bx*", &registers).unwrap_err(), EvalError::UnexpectedEnd);
+    }
+
+    #[test]
+    fn eval_dereferences_memory() {
+        let mut bus = BusInterface::default();
+        bus.write_u8(0x20104, 0xCD, 0).unwrap();
+        bus.write_u8(0x20105, 0xAB, 0).unwrap();
+        bus.write_u8(0x3020A, 0x55, 0).unwrap();
+        bus.write_u8(0x0046C, 0x78, 0).unwrap();
+        bus.write_u8(0x0046D, 0x56, 0).unwrap();
+        bus.write_u8(0xB8000, 0x07, 0).unwrap();
+
+        let result = eval(&bus, "[bx+4]").unwrap();
+        assert!(matches!(result.address, Some(CpuAddress::Segmented(0x2000, 0x0104))));
+        assert_eq!(result.value, 0xABCD);
+
+        assert_eq!(eval(&bus, "byte ptr [bx+4]").unwrap().value, 0xCD);
+        assert_eq!(eval(&bus, "byte [bp+0Ah]").unwrap().value, 0x55);
+        assert_eq!(eval(&bus, "word [0040:006C]").unwrap().value, 0x5678);
+        assert_eq!(eval(&bus, "0040:006Ch").unwrap().value, 0x5678);
+        assert_eq!(eval(&bus, "ds:bx+4").unwrap().value, 0xABCD);
+
+        let result = eval(&bus, "byte [B8000]").unwrap();
+        assert!(matches!(result.address, Some(CpuAddress::Flat(0xB8000))));
+        assert_eq!(result.value, 0x07);
+    }
+
+    #[test]
+    fn eval_rejects_bad_expressions() {
+        let bus = BusInterface::default();
+        assert_eq!(eval(&bus, "").unwrap_err(), EvalError::Empty);
+        assert_eq!(eval(&bus, "[bx").unwrap_err(), EvalError::UnexpectedEnd);
+        assert_eq!(eval(&bus, "bx/2").unwrap_err(), EvalError::UnexpectedChar('/'));
+        assert_eq!(eval(&bus, "zz").unwrap_err(), EvalError::InvalidOperand("zz".to_string()));
+        assert_eq!(eval(&bus, "[bx] ax").unwrap_err(), EvalError::UnexpectedToken("ax".to_string()));
+    }
+}
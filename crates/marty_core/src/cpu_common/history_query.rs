@@ -0,0 +1,124 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::history_query.rs
+
+    Implements queries for searching the instruction history by mnemonic or
+    by address range.
+
+*/
+
+use crate::cpu_common::calc_linear_address;
+
+/// A filter for entries in the instruction history.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HistoryQuery {
+    /// Match entries whose mnemonic begins with the given text, in uppercase. Interrupt, NMI and
+    /// trap entries match "INT", "NMI" and "TRAP".
+    Mnemonic(String),
+    /// Match entries whose flat address falls within the inclusive range.
+    AddressRange(u32, u32),
+}
+
+impl HistoryQuery {
+    /// Parse a query. Text containing a ':' or '-' is parsed as an address or address range, with
+    /// addresses given as hexadecimal flat addresses (FE000) or segment:offset pairs (F000:E000).
+    /// Anything else is treated as a mnemonic.
+    pub fn parse(s: &str) -> Option<HistoryQuery> {
+        let s = s.trim();
+        if s.contains(':') || s.contains('-') {
+            let (start, end) = s.split_once('-').unwrap_or((s, s));
+            let start = parse_address(start)?;
+            let end = parse_address(end)?;
+            return Some(HistoryQuery::AddressRange(start.min(end), start.max(end)));
+        }
+        if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Some(HistoryQuery::Mnemonic(s.to_ascii_uppercase()));
+        }
+        None
+    }
+
+    /// Returns true if an entry at the flat `address` with the specified `mnemonic` matches the query.
+    pub fn matches(&self, address: u32, mnemonic: &str) -> bool {
+        match self {
+            HistoryQuery::Mnemonic(prefix) => mnemonic.starts_with(prefix.as_str()),
+            HistoryQuery::AddressRange(start, end) => (*start..=*end).contains(&address),
+        }
+    }
+}
+
+fn parse_address(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.split_once(':') {
+        Some((segment, offset)) => Some(calc_linear_address(
+            u16::from_str_radix(segment.trim(), 16).ok()?,
+            u16::from_str_radix(offset.trim(), 16).ok()?,
+        )),
+        None => u32::from_str_radix(s, 16).ok().filter(|address| *address <= 0xFFFFF),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            HistoryQuery::parse(" mov "),
+            Some(HistoryQuery::Mnemonic("MOV".to_string()))
+        );
+        assert_eq!(
+            HistoryQuery::parse("F000:E000-F000:E0FF"),
+            Some(HistoryQuery::AddressRange(0xFE000, 0xFE0FF))
+        );
+        assert_eq!(
+            HistoryQuery::parse("FE0FF - FE000"),
+            Some(HistoryQuery::AddressRange(0xFE000, 0xFE0FF))
+        );
+        assert_eq!(
+            HistoryQuery::parse("0040:006C"),
+            Some(HistoryQuery::AddressRange(0x46C, 0x46C))
+        );
+        assert_eq!(HistoryQuery::parse(""), None);
+        assert_eq!(HistoryQuery::parse("MOV AX"), None);
+        assert_eq!(HistoryQuery::parse("FE000-XYZ"), None);
+        assert_eq!(HistoryQuery::parse("100000-100010"), None);
+    }
+
+    #[test]
+    fn test_match_query() {
+        let query = HistoryQuery::parse("mov").unwrap();
+        assert!(query.matches(0, "MOV"));
+        assert!(query.matches(0, "MOVSB"));
+        assert!(!query.matches(0, "CMOV"));
+
+        let query = HistoryQuery::parse("FE000-FE0FF").unwrap();
+        assert!(query.matches(0xFE000, "NOP"));
+        assert!(query.matches(0xFE0FF, "NOP"));
+        assert!(!query.matches(0xFE100, "NOP"));
+    }
+}
@@ -40,6 +40,7 @@ use crate::{
     cpu_common::{
         operands::OperandSize,
         AddressingMode,
+        CpuAddress,
         Mnemonic,
         OperandType,
         Register16,
@@ -66,6 +67,7 @@ pub struct Instruction {
     pub decode_idx: usize,
     pub opcode: u8,
     pub prefixes: u32,
+    pub modrm: Option<u8>,
     pub address: u32,
     pub size: u32,
     pub mnemonic: Mnemonic,
@@ -82,6 +84,7 @@ impl Default for Instruction {
             decode_idx: 0,
             opcode: 0,
             prefixes: 0,
+            modrm: None,
             address: 0,
             size: 1,
             mnemonic: Mnemonic::NOP,
@@ -140,6 +143,14 @@ impl Display for Instruction {
 
 impl SyntaxTokenize for Instruction {
     fn tokenize(&self) -> Vec<SyntaxToken> {
+        self.tokenize_with_symbols(&|_| None)
+    }
+}
+
+impl Instruction {
+    /// Tokenize the instruction, replacing branch targets with [SyntaxToken::Symbol] wherever `symbols`
+    /// can resolve the target's flat address to a name.
+    pub fn tokenize_with_symbols(&self, symbols: &dyn Fn(u32) -> Option<String>) -> Vec<SyntaxToken> {
         // Dont sign-extend 8-bit port addresses.
         let op_size = match self.mnemonic {
             Mnemonic::IN | Mnemonic::OUT => OperandSize::Operand8,
@@ -164,18 +175,42 @@ impl SyntaxTokenize for Instruction {
         let mnemonic = mnemonic_to_str(self.mnemonic).to_string().to_lowercase();
         i_vec.0.push(SyntaxToken::Mnemonic(mnemonic));
 
-        let op1_vec = tokenize_operand(self, OperandSelect::FirstOperand, op_size);
+        let op1_vec = tokenize_operand(self, OperandSelect::FirstOperand, op_size, symbols);
         i_vec.append(op1_vec, Some(SyntaxToken::Formatter(SyntaxFormatType::Space)), None);
 
-        let op2_vec = tokenize_operand(self, OperandSelect::SecondOperand, op_size);
+        let op2_vec = tokenize_operand(self, OperandSelect::SecondOperand, op_size, symbols);
 
         if !op2_vec.is_empty() {
             i_vec.0.push(SyntaxToken::Comma);
             i_vec.append(op2_vec, Some(SyntaxToken::Formatter(SyntaxFormatType::Space)), None);
         }
 
+        if let Some(comment) = immediate_comment(self, op_size) {
+            i_vec.push_space(SyntaxToken::Comment(comment));
+        }
+
         i_vec.0
     }
+
+    /// Resolve the target of a jump or call, if it can be determined from the instruction alone.
+    /// Relative targets resolve within the instruction's code segment when its `cs:ip` is known,
+    /// and to a flat address otherwise.
+    pub fn branch_target(&self, cs_ip: Option<(u16, u16)>) -> Option<CpuAddress> {
+        let rel = match (self.operand1_type, cs_ip) {
+            (OperandType::FarAddress(segment, offset), _) => return Some(CpuAddress::Segmented(segment, offset)),
+            (OperandType::NearAddress(offset), Some((cs, _))) => return Some(CpuAddress::Segmented(cs, offset)),
+            (OperandType::Relative8(rel8), _) => rel8 as i16,
+            (OperandType::Relative16(rel16), _) => rel16,
+            _ => return None,
+        };
+        match cs_ip {
+            Some((cs, ip)) => Some(CpuAddress::Segmented(
+                cs,
+                ip.wrapping_add(self.size as u16).wrapping_add(rel as u16),
+            )),
+            None => Some(CpuAddress::Flat(relative_target(self, rel))),
+        }
+    }
 }
 
 struct Imm8Extend(u8);
@@ -421,7 +456,12 @@ fn operand_to_string(i: &Instruction, op: OperandSelect, lvalue: OperandSize) ->
     instruction_string
 }
 
-fn tokenize_operand(i: &Instruction, op: OperandSelect, lvalue: OperandSize) -> Vec<SyntaxToken> {
+fn tokenize_operand(
+    i: &Instruction,
+    op: OperandSelect,
+    lvalue: OperandSize,
+    symbols: &dyn Fn(u32) -> Option<String>,
+) -> Vec<SyntaxToken> {
     let (op_type, op_size) = match op {
         OperandSelect::FirstOperand => (i.operand1_type, i.operand1_size),
         OperandSelect::SecondOperand => (i.operand2_type, i.operand2_size),
@@ -432,27 +472,43 @@ fn tokenize_operand(i: &Instruction, op: OperandSelect, lvalue: OperandSize) ->
     match op_type {
         OperandType::Immediate8(imm8) => {
             if let OperandSize::Operand8 = lvalue {
-                op_vec.push(SyntaxToken::HexValue(format!("{:X}h", imm8)));
+                op_vec.push(SyntaxToken::Immediate(imm8 as u32, format!("{:X}h", imm8)));
             }
             else {
-                op_vec.push(SyntaxToken::HexValue(format!("{:X}h", Imm8Extend(imm8))));
+                let value = imm8 as i8 as i16 as u16;
+                op_vec.push(SyntaxToken::Immediate(value as u32, format!("{:X}h", Imm8Extend(imm8))));
             }
         }
         OperandType::Immediate8s(imm8s) => {
-            op_vec.push(SyntaxToken::HexValue(format!("{:X}h", Imm8sExtend(imm8s))));
+            let value = imm8s as i16 as u16;
+            op_vec.push(SyntaxToken::Immediate(value as u32, format!("{:X}h", Imm8sExtend(imm8s))));
         }
         OperandType::Immediate16(imm16) => {
-            op_vec.push(SyntaxToken::HexValue(format!("{:X}h", imm16)));
+            op_vec.push(SyntaxToken::Immediate(imm16 as u32, format!("{:X}h", imm16)));
         }
         OperandType::Relative8(rel8) => {
             //op_vec.push(SyntaxToken::Text("short".to_string()));
             //op_vec.push(SyntaxToken::Formatter(SyntaxFormatType::Space));
-            op_vec.push(SyntaxToken::HexValue(format!("{:04X}h", i.size as i16 + rel8 as i16)));
+            let target = relative_target(i, rel8 as i16);
+            match symbols(target) {
+                Some(symbol) => op_vec.push(SyntaxToken::Symbol(target, symbol)),
+                None => op_vec.push(SyntaxToken::BranchTarget(
+                    CpuAddress::Flat(target),
+                    format!("{:04X}h", i.size as i16 + rel8 as i16),
+                )),
+            }
         }
         OperandType::Relative16(rel16) => {
             //op_vec.push(SyntaxToken::Text("short".to_string()));
             //op_vec.push(SyntaxToken::Formatter(SyntaxFormatType::Space));
-            op_vec.push(SyntaxToken::HexValue(format!("{:04X}h", i.size as i16 + rel16)));
+            let target = relative_target(i, rel16);
+            match symbols(target) {
+                Some(symbol) => op_vec.push(SyntaxToken::Symbol(target, symbol)),
+                None => op_vec.push(SyntaxToken::BranchTarget(
+                    CpuAddress::Flat(target),
+                    format!("{:04X}h", i.size as i16 + rel16),
+                )),
+            }
         }
         OperandType::Offset8(offset8) => {
             let segment: String = match i.segment_override {
@@ -637,9 +693,16 @@ fn tokenize_operand(i: &Instruction, op: OperandSelect, lvalue: OperandSize) ->
         }
         */
         OperandType::FarAddress(segment, offset) => {
-            op_vec.push(SyntaxToken::HexValue(format!("{:04X}h", segment)));
-            op_vec.push(SyntaxToken::Colon);
-            op_vec.push(SyntaxToken::HexValue(format!("{:04X}h", offset)));
+            let target = (((segment as u32) << 4) + offset as u32) & 0xFFFFF;
+            if let Some(symbol) = symbols(target) {
+                op_vec.push(SyntaxToken::Symbol(target, symbol));
+            }
+            else {
+                op_vec.push(SyntaxToken::BranchTarget(
+                    CpuAddress::Segmented(segment, offset),
+                    format!("{:04X}h:{:04X}h", segment, offset),
+                ));
+            }
         }
         _ => {}
     };
@@ -647,6 +710,29 @@ fn tokenize_operand(i: &Instruction, op: OperandSelect, lvalue: OperandSize) ->
     op_vec
 }
 
+/// Calculate the flat address of a relative branch target. The segment is not known here, so a
+/// branch that wraps around its code segment resolves to the wrong address.
+fn relative_target(i: &Instruction, rel: i16) -> u32 {
+    i.address.wrapping_add(i.size).wrapping_add(rel as i32 as u32) & 0xFFFFF
+}
+
+/// Annotate 8-bit immediates that are printable ASCII with their character, as these are usually
+/// character constants.
+fn immediate_comment(i: &Instruction, lvalue: OperandSize) -> Option<String> {
+    match i.mnemonic {
+        // Port numbers are not characters.
+        Mnemonic::IN | Mnemonic::OUT => return None,
+        _ => {}
+    }
+    if !matches!(lvalue, OperandSize::Operand8) {
+        return None;
+    }
+    match i.operand2_type {
+        OperandType::Immediate8(imm8) if (0x20..0x7F).contains(&imm8) => Some(format!("'{}'", imm8 as char)),
+        _ => None,
+    }
+}
+
 fn override_prefix_to_string(i: &Instruction) -> Option<String> {
     if let Some(seg_override) = i.segment_override {
         match ((i.prefixes & OPCODE_PREFIX_0F != 0), i.opcode) {
@@ -724,3 +810,73 @@ fn prefix_to_string(i: &Instruction) -> Option<String> {
         Some(prefix_str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bus::BusInterface, bytequeue::ByteQueue, cpu_common::CpuType};
+
+    fn decode_at(address: u32, code: &[u8]) -> Instruction {
+        let mut bus = BusInterface::default();
+        for (i, b) in code.iter().enumerate() {
+            bus.write_u8(address as usize + i, *b, 0).unwrap();
+        }
+        bus.seek(address as usize);
+        let mut instruction = CpuType::Intel8088.decode(&mut bus, true).unwrap();
+        instruction.address = address;
+        instruction
+    }
+
+    #[test]
+    fn tokenize_classifies_operands() {
+        // mov al, 41h
+        let tokens = decode_at(0, &[0xB0, 0x41]).tokenize();
+        assert!(tokens.contains(&SyntaxToken::Mnemonic("mov".to_string())));
+        assert!(tokens.contains(&SyntaxToken::Register("al".to_string())));
+        assert!(tokens.contains(&SyntaxToken::Immediate(0x41, "41h".to_string())));
+        assert!(tokens.last() == Some(&SyntaxToken::Comment("'A'".to_string())));
+
+        // add ax, -1
+        let tokens = decode_at(0, &[0x83, 0xC0, 0xFF]).tokenize();
+        assert!(tokens.contains(&SyntaxToken::Immediate(0xFFFF, "FFFFh".to_string())));
+        assert!(!tokens.iter().any(|t| matches!(t, SyntaxToken::Comment(_))));
+    }
+
+    #[test]
+    fn tokenize_resolves_branch_symbols() {
+        // jmp short +2
+        let instruction = decode_at(0x100, &[0xEB, 0x02]);
+        assert!(instruction
+            .tokenize()
+            .contains(&SyntaxToken::BranchTarget(CpuAddress::Flat(0x104), "0004h".to_string())));
+
+        let tokens = instruction.tokenize_with_symbols(&|addr| (addr == 0x104).then(|| "label".to_string()));
+        assert!(tokens.contains(&SyntaxToken::Symbol(0x104, "label".to_string())));
+
+        // jmp far F000:E05B
+        let instruction = decode_at(0x100, &[0xEA, 0x5B, 0xE0, 0x00, 0xF0]);
+        let tokens = instruction.tokenize_with_symbols(&|addr| (addr == 0xFE05B).then(|| "reset".to_string()));
+        assert!(tokens.contains(&SyntaxToken::Symbol(0xFE05B, "reset".to_string())));
+    }
+
+    #[test]
+    fn branch_target_resolves_in_code_segment() {
+        // jmp short -4 at the start of a segment wraps to the end of the same segment.
+        let instruction = decode_at(0x10000, &[0xEB, 0xFA]);
+        assert_eq!(
+            instruction.branch_target(Some((0x1000, 0))),
+            Some(CpuAddress::Segmented(0x1000, 0xFFFC))
+        );
+        assert_eq!(instruction.branch_target(None), Some(CpuAddress::Flat(0xFFFC)));
+
+        // call far F000:E05B
+        let instruction = decode_at(0x100, &[0x9A, 0x5B, 0xE0, 0x00, 0xF0]);
+        assert_eq!(
+            instruction.branch_target(None),
+            Some(CpuAddress::Segmented(0xF000, 0xE05B))
+        );
+
+        // mov al, 41h
+        assert_eq!(decode_at(0, &[0xB0, 0x41]).branch_target(Some((0, 0))), None);
+    }
+}
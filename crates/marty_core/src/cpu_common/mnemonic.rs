@@ -32,8 +32,11 @@
 
 use std::fmt;
 
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
 #[allow(dead_code)]
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Copy, Clone, Debug, EnumIter)]
 pub enum Mnemonic {
     Invalid,
     NoOpcode,
@@ -177,9 +180,26 @@ impl Default for Mnemonic {
     }
 }
 
+impl Mnemonic {
+    /// Parse a mnemonic string, ignoring case. This is the inverse of the Display implementation,
+    /// so `Mnemonic::from_str(&mnemonic.to_string())` always returns `Some(mnemonic)`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Mnemonic> {
+        let s = s.trim();
+        Mnemonic::iter().find(|m| mnemonic_to_str(*m).eq_ignore_ascii_case(s))
+    }
+}
+
 // TODO: Is this any faster than just using derive Debug?
 pub(crate) fn mnemonic_to_str(op: Mnemonic) -> &'static str {
     match op {
+        Mnemonic::Invalid => "INVALID",
+        // Decode table placeholders. These never appear in a decoded instruction, but each needs
+        // a distinct name to parse back.
+        Mnemonic::NoOpcode => "NOOPCODE",
+        Mnemonic::Group => "GROUP",
+        Mnemonic::Extension => "EXTENSION",
+        Mnemonic::Prefix => "PREFIX",
         Mnemonic::NOP => "NOP",
         Mnemonic::AAA => "AAA",
         Mnemonic::AAD => "AAD",
@@ -309,7 +329,6 @@ pub(crate) fn mnemonic_to_str(op: Mnemonic) -> &'static str {
         Mnemonic::BINS => "BINS",
         Mnemonic::BEXT => "BEXT",
         Mnemonic::BRKEM => "BRKEM",
-        _ => "INVALID",
     }
 }
 
@@ -318,3 +337,23 @@ impl fmt::Display for Mnemonic {
         write!(f, "{}", mnemonic_to_str(*self))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mnemonic_round_trip() {
+        for mnemonic in Mnemonic::iter() {
+            let s = mnemonic.to_string();
+            assert_eq!(Mnemonic::from_str(&s), Some(mnemonic), "round trip failed for {}", s);
+            assert_eq!(Mnemonic::from_str(&s.to_lowercase()), Some(mnemonic));
+        }
+    }
+
+    #[test]
+    fn test_mnemonic_from_str_rejects_unknown() {
+        assert_eq!(Mnemonic::from_str("MOVZX"), None);
+        assert_eq!(Mnemonic::from_str(""), None);
+    }
+}
@@ -35,20 +35,39 @@
 pub mod addressing;
 pub mod alu;
 pub mod analyzer;
+pub mod assembler;
 pub mod builder;
+pub mod coverage;
+pub mod cycle_trace;
 pub mod error;
+pub mod expression;
+pub mod history_query;
 pub mod instruction;
 pub mod mnemonic;
 pub mod operands;
 pub mod services;
+pub mod symbols;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 use enum_dispatch::enum_dispatch;
-use serde::Deserialize;
-use std::str::FromStr;
+use marty_common::types::history_buffer::HistoryBuffer;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt,
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 
 pub use addressing::{AddressingMode, CpuAddress, Displacement};
 pub use analyzer::{AnalyzerEntry, LogicAnalyzer};
+pub use assembler::{assemble, AsmError};
+pub use coverage::CoverageReport;
+pub use cycle_trace::CycleTraceRecord;
 pub use error::CpuError;
+pub use expression::{EvalError, EvalResult, ValueSize};
+pub use history_query::HistoryQuery;
 pub use instruction::Instruction;
 pub use mnemonic::Mnemonic;
 pub use operands::OperandType;
@@ -57,12 +76,14 @@ pub use operands::OperandType;
 use crate::cpu_validator::{CpuValidator, CycleState, VRegisters};
 
 use crate::{
-    breakpoints::{BreakPointType, StopWatchData},
+    breakpoints::{BreakPointType, RomHook, StopWatchData},
     bus::BusInterface,
     bytequeue::ByteQueue,
     cpu_808x::Intel808x,
+    cpu_common::symbols::SymbolTable,
     cpu_vx0::NecVx0,
-    syntax_token::{SyntaxToken, SyntaxTokenize},
+    savestate::CpuExecState,
+    syntax_token::SyntaxToken,
 };
 
 // Instruction prefixes
@@ -131,6 +152,20 @@ pub enum Register16 {
     InvalidRegister,
 }
 
+/// Representation of a flag in the eFlags CPU register
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Flag {
+    Carry,
+    Parity,
+    AuxCarry,
+    Zero,
+    Sign,
+    Trap,
+    Interrupt,
+    Direction,
+    Overflow,
+}
+
 #[derive(Copy, Clone, Default, Debug)]
 pub enum Segment {
     None,
@@ -183,7 +218,7 @@ pub struct CpuStringState {
     pub dram_refresh_cycle_num: String,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash)]
 pub enum CpuType {
     Intel8088,
     Intel8086,
@@ -214,10 +249,12 @@ impl CpuType {
             CpuType::NecV20 | CpuType::NecV30 => NecVx0::decode(bytes, peek),
         }
     }
-    pub fn tokenize_instruction(&self, instruction: &Instruction) -> Vec<SyntaxToken> {
+    /// Tokenize an instruction, naming any branch target found in `symbols`.
+    pub fn tokenize_instruction(&self, instruction: &Instruction, symbols: &SymbolTable) -> Vec<SyntaxToken> {
+        let lookup = |address| symbols.lookup(address);
         match self {
-            CpuType::Intel8088 | CpuType::Intel8086 => instruction.tokenize(),
-            CpuType::NecV20 | CpuType::NecV30 => instruction.tokenize(),
+            CpuType::Intel8088 | CpuType::Intel8086 => instruction.tokenize_with_symbols(&lookup),
+            CpuType::NecV20 | CpuType::NecV30 => instruction.tokenize_with_symbols(&lookup),
         }
     }
 }
@@ -277,6 +314,12 @@ impl Default for CpuType {
 #[derive(Debug)]
 pub enum CpuOption {
     InstructionHistory(bool),
+    /// Set the number of entries kept in the instruction history.
+    InstructionHistoryDepth(usize),
+    /// Set the maximum number of entries kept in the call stack.
+    CallStackDepth(usize),
+    /// Set which entries are discarded when the call stack exceeds its maximum depth.
+    CallStackOverflowPolicy(CallStackOverflowPolicy),
     ScheduleInterrupt(bool, u32, u32, bool),
     ScheduleDramRefresh(bool, u32, u32, bool),
     DramRefreshAdjust(u32),
@@ -285,6 +328,11 @@ pub enum CpuOption {
     EnableWaitStates(bool),
     TraceLoggingEnabled(bool),
     EnableServiceInterrupt(bool),
+    InstructionCoverage(bool),
+    /// Record a structured CycleTraceRecord for each cycle, independent of trace logging.
+    CycleTraceRecords(bool),
+    /// Install a seeded RNG so that runs are reproducible.
+    Deterministic(u64),
 }
 
 #[derive(Debug)]
@@ -298,9 +346,20 @@ pub enum StepResult {
     Rep(CpuAddress),
     BreakpointHit,
     StepOverHit,
+    StepOutHit,
     ProgramEnd,
 }
 
+/// A memory write that landed in code the CPU had already fetched - the bytes of the current
+/// instruction or the contents of the prefetch queue.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CodeWrite {
+    pub address: u32,     // Linear address written
+    pub segment: u16,     // CS of the writing instruction
+    pub offset: u16,      // IP of the writing instruction
+    pub instruction: u64, // Instruction count at the time of the write
+}
+
 // Internal Emulator interrupt service events. These are returned to the machine when
 // the internal service interrupt is called to request an emulator action that cannot
 // be handled by the CPU alone.
@@ -309,6 +368,8 @@ pub enum ServiceEvent {
     TriggerPITLogging,
     /// A request to quit the emulator immediately. Triggered by the `mquit` utility.
     QuitEmulator(u8),
+    /// Self-modifying code was detected. Raised by the CPU itself rather than the service interrupt.
+    SelfModifyingWrite(CodeWrite),
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -320,6 +381,104 @@ pub enum QueueOp {
     Subsequent,
 }
 
+impl Display for QueueOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            QueueOp::Idle => write!(f, "Idle"),
+            QueueOp::First => write!(f, "First"),
+            QueueOp::Flush => write!(f, "Flush"),
+            QueueOp::Subsequent => write!(f, "Subsequent"),
+        }
+    }
+}
+
+/// The state of the BIU's instruction prefetcher.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub enum FetchState {
+    #[default]
+    Normal,
+    PausedFull,
+    Delayed(u8),
+    Suspended,
+    Halted,
+}
+
+impl Display for FetchState {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FetchState::Normal => write!(f, "Normal"),
+            FetchState::PausedFull => write!(f, "Paused (queue full)"),
+            FetchState::Delayed(cycles) => write!(f, "Delayed ({})", cycles),
+            FetchState::Suspended => write!(f, "Suspended"),
+            FetchState::Halted => write!(f, "Halted"),
+        }
+    }
+}
+
+/// A snapshot of the prefetch queue and BIU fetch state, for display by a debugger.
+#[derive(Clone, Debug, Default)]
+pub struct PrefetchState {
+    /// The queue contents, oldest byte first, not including any preloaded byte.
+    pub queue: Vec<u8>,
+    pub queue_size: usize,
+    /// A byte taken from the queue and latched for decode of the next instruction.
+    pub preload: Option<u8>,
+    pub fetch_state: FetchState,
+    pub queue_op: QueueOp,
+    pub cs: u16,
+    pub pc: u16,
+    pub ip: u16,
+}
+
+impl PrefetchState {
+    /// The number of bytes the program counter has run ahead of IP. This should always equal the
+    /// number of bytes in the queue, including any preloaded byte.
+    pub fn pc_delta(&self) -> u16 {
+        self.pc.wrapping_sub(self.ip)
+    }
+}
+
+/// Default, minimum and maximum number of entries in the instruction history ring buffer.
+pub const DEFAULT_HISTORY_DEPTH: usize = 32;
+pub const MIN_HISTORY_DEPTH: usize = 16;
+pub const MAX_HISTORY_DEPTH: usize = 4096;
+// Each history entry holds a copy of an Instruction, so deep histories can use a lot of memory.
+const HISTORY_DEPTH_WARNING: usize = 1024;
+
+/// Resize an instruction history ring buffer to `depth` entries, discarding the oldest entries if
+/// it shrinks. Returns the new depth, clamped to the supported range.
+pub(crate) fn resize_history<T>(history: &mut VecDeque<T>, depth: usize) -> usize {
+    let depth = depth.clamp(MIN_HISTORY_DEPTH, MAX_HISTORY_DEPTH);
+    if depth > HISTORY_DEPTH_WARNING {
+        log::warn!(
+            "Instruction history depth of {} entries may use up to {} KB of memory",
+            depth,
+            depth * std::mem::size_of::<T>() / 1024
+        );
+    }
+    let excess = history.len().saturating_sub(depth);
+    history.drain(..excess);
+    history.shrink_to(depth);
+    // Reserve the full depth up front so that long captures don't reallocate while running.
+    history.reserve_exact(depth - history.len());
+    depth
+}
+
+/// Default, minimum and maximum number of entries in the call stack.
+pub const DEFAULT_CALL_STACK_DEPTH: usize = 48;
+pub const MIN_CALL_STACK_DEPTH: usize = 1;
+pub const MAX_CALL_STACK_DEPTH: usize = 4096;
+
+/// Which entries are discarded when a CALL or INT would exceed the maximum call stack depth.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum CallStackOverflowPolicy {
+    /// Discard the oldest entry, keeping the most recent calls visible.
+    #[default]
+    DropOldest,
+    /// Discard the new entry, keeping the outermost calls visible.
+    DropNewest,
+}
+
 pub fn calc_linear_address(segment: u16, offset: u16) -> u32 {
     (((segment as u32) << 4) + offset as u32) & 0xFFFFFu32
 }
@@ -393,17 +552,29 @@ pub trait Cpu {
     // General CPU control
     fn reset(&mut self);
     fn set_reset_vector(&mut self, address: CpuAddress);
+    fn get_reset_vector(&self) -> CpuAddress;
     fn set_reset_queue_contents(&mut self, contents: Vec<u8>);
     fn set_end_address(&mut self, address: CpuAddress);
     fn set_nmi(&mut self, state: bool);
     fn set_intr(&mut self, state: bool);
     fn step(&mut self, skip_breakpoint: bool) -> Result<(StepResult, u32), CpuError>;
     fn step_finish(&mut self, disassembly: Option<&mut Disassembly>) -> Result<StepResult, CpuError>;
+    /// Step the CPU until it takes an interrupt, returning the address the interrupt will return to.
+    /// Fails with CpuHaltedError if no interrupt is taken within `timeout_cycles`.
+    fn run_until_interrupt(&mut self, timeout_cycles: u64) -> Result<CpuAddress, CpuError>;
 
     fn in_rep(&self) -> bool;
     fn get_type(&self) -> CpuType;
     /// Flush the processor instruction queue. Associated registers may be updated.
     fn flush_piq(&mut self);
+    /// Return the contents of the instruction queue, oldest byte first.
+    fn queue_bytes(&self) -> Vec<u8>;
+    fn fetch_state(&self) -> FetchState;
+    /// Return the execution state that is not visible in the registers, for save states.
+    fn exec_state(&self) -> CpuExecState;
+    /// Restore execution state captured by exec_state(). Must be called between instructions.
+    fn set_exec_state(&mut self, state: CpuExecState);
+    fn prefetch_state(&self) -> PrefetchState;
     fn get_ip(&mut self) -> u16;
     fn get_register16(&self, reg: Register16) -> u16;
     fn set_register16(&mut self, reg: Register16, value: u16);
@@ -411,30 +582,55 @@ pub trait Cpu {
     fn set_register8(&mut self, reg: Register8, value: u8);
     fn get_flags(&self) -> u16;
     fn set_flags(&mut self, flags: u16);
+    fn set_flag_state(&mut self, flag: Flag, state: bool);
     fn get_cycle_ct(&self) -> (u64, u64);
     fn get_instruction_ct(&self) -> u64;
+    fn get_int_counts(&self) -> &[u64];
     fn flat_ip(&self) -> u32;
     fn flat_ip_disassembly(&self) -> u32;
     fn flat_sp(&self) -> u32;
     fn dump_instruction_history_string(&self) -> String;
     fn dump_instruction_history_tokens(&self) -> Vec<Vec<SyntaxToken>>;
+    /// Return the instruction history entries matching `query`, oldest first.
+    fn query_instruction_history(&self, query: &HistoryQuery) -> Vec<Vec<SyntaxToken>>;
     fn dump_call_stack(&self) -> String;
+    /// If the call stack has exceeded its maximum depth since the last reset, return the policy that
+    /// was used to discard entries.
+    fn call_stack_overflow(&self) -> Option<CallStackOverflowPolicy>;
+    /// Return the routine entry points discovered while the instruction history was enabled, named by any
+    /// imported symbols.
+    fn export_discovered_symbols(&self) -> Vec<(u32, String)>;
+    /// Replace the imported symbols used to name addresses.
+    fn set_symbols(&mut self, symbols: SymbolTable);
+    fn symbols(&self) -> &SymbolTable;
     fn get_service_event(&mut self) -> Option<ServiceEvent>;
     #[cfg(feature = "cpu_validator")]
     fn get_cycle_states(&self) -> &Vec<CycleState>;
     fn get_cycle_trace(&self) -> &Vec<String>;
     fn get_cycle_trace_tokens(&self) -> &Vec<Vec<SyntaxToken>>;
+    fn get_cycle_trace_records(&self) -> Option<&HistoryBuffer<CycleTraceRecord>>;
 
     fn get_string_state(&self) -> CpuStringState;
 
+    // Coverage
+    fn coverage_report(&self) -> CoverageReport;
+    fn clear_coverage(&mut self);
+
     // Eval
     fn eval_address(&self, expr: &str) -> Option<CpuAddress>;
+    fn eval_expression(&self, expr: &str) -> Result<EvalResult, EvalError>;
 
     // Breakpoints
+    fn set_breakpoint_flag(&mut self);
     fn clear_breakpoint_flag(&mut self);
     fn set_breakpoints(&mut self, bp_list: Vec<BreakPointType>);
+    fn get_breakpoints(&self) -> &[BreakPointType];
+    fn set_rom_hooks(&mut self, hooks: Vec<RomHook>);
     fn get_step_over_breakpoint(&self) -> Option<CpuAddress>;
     fn set_step_over_breakpoint(&mut self, address: CpuAddress);
+    fn step_out(&mut self);
+    fn cancel_step_out(&mut self);
+    fn is_stepping_out(&self) -> bool;
     fn get_sw_data(&self) -> Vec<StopWatchData>;
     fn set_stopwatch(&mut self, sw_idx: usize, start: u32, stop: u32);
 
@@ -0,0 +1,342 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::symbols.rs
+
+    Collects the routine entry points discovered while executing, and reads
+    and writes them as symbol files of 'address=name' lines. Discovered
+    routines are given generated names that can be edited before the file
+    is imported again.
+
+*/
+
+use std::collections::BTreeMap;
+
+use crate::cpu_common::{calc_linear_address, Instruction, Mnemonic, OperandType};
+
+/// A routine entered by the last instruction or interrupt, which begins at the next instruction executed.
+#[derive(Clone)]
+enum Entered {
+    Call,
+    Interrupt(u8),
+    // INTO only interrupts on overflow, so we keep its fall-through address to tell if it did.
+    Overflow(u32),
+}
+
+/// Routine entry points seen as CALL or interrupt targets, keyed by flat address. The CPU accumulates these as it
+/// executes, so they are not limited to what is still in the instruction history.
+#[derive(Clone, Default)]
+pub struct DiscoveredSymbols {
+    symbols: BTreeMap<u32, String>,
+    entered: Option<Entered>,
+}
+
+impl DiscoveredSymbols {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Record the target of a near or far CALL. Call names take precedence over interrupt handler names, as a
+    /// handler that is also called directly is more usefully thought of as a subroutine.
+    pub fn add_call(&mut self, address: u32) {
+        self.symbols.insert(address, format!("sub_{:05X}", address));
+    }
+
+    /// Record the handler entered for the specified interrupt vector.
+    pub fn add_interrupt(&mut self, vector: u8, address: u32) {
+        self.symbols
+            .entry(address)
+            .or_insert_with(|| format!("int{:02X}_{:05X}", vector, address));
+    }
+
+    /// Forget the routine entered by the last instruction, as when the CPU is reset before it begins.
+    pub fn cancel_entry(&mut self) {
+        self.entered = None;
+    }
+
+    /// Record an interrupt taken between instructions. `return_address` is the address the interrupt returns to,
+    /// which is the target of a call or software interrupt just made, and `handler` is the address of the handler.
+    pub fn interrupt_taken(&mut self, vector: u8, return_address: u32, handler: u32) {
+        self.entered_at(return_address);
+        self.add_interrupt(vector, handler);
+    }
+
+    /// Record an executed instruction. Instructions must be recorded in the order they were executed, as the
+    /// target of a call or interrupt is the address of the instruction executed after it.
+    pub fn instruction_executed(&mut self, cs: u16, ip: u16, i: &Instruction) {
+        self.entered_at(calc_linear_address(cs, ip));
+
+        self.entered = match i.mnemonic {
+            Mnemonic::CALL | Mnemonic::CALLF => Some(Entered::Call),
+            Mnemonic::INT => match i.operand1_type {
+                OperandType::Immediate8(vector) => Some(Entered::Interrupt(vector)),
+                _ => None,
+            },
+            Mnemonic::INT3 => Some(Entered::Interrupt(3)),
+            Mnemonic::INTO => Some(Entered::Overflow(calc_linear_address(
+                cs,
+                ip.wrapping_add(i.size as u16),
+            ))),
+            _ => None,
+        };
+    }
+
+    /// Resolve the routine entered by the last instruction, now that execution has reached `address`.
+    fn entered_at(&mut self, address: u32) {
+        match self.entered.take() {
+            Some(Entered::Call) => self.add_call(address),
+            Some(Entered::Interrupt(vector)) => self.add_interrupt(vector, address),
+            Some(Entered::Overflow(fall_through)) if fall_through != address => self.add_interrupt(4, address),
+            _ => {}
+        }
+    }
+
+    /// Return the discovered symbols. Names in `names` replace generated ones, and symbols in `names` that were
+    /// not discovered are included, so that an edited symbol file survives being imported and exported again.
+    pub fn export(mut self, names: &SymbolTable) -> Vec<(u32, String)> {
+        for (address, name) in names.iter() {
+            self.symbols.insert(address, name.to_string());
+        }
+        self.symbols.into_iter().collect()
+    }
+
+    pub fn into_vec(self) -> Vec<(u32, String)> {
+        self.symbols.into_iter().collect()
+    }
+}
+
+/// Symbol names by flat address, as imported from a symbol file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SymbolTable {
+    symbols: BTreeMap<u32, String>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn get(&self, address: u32) -> Option<&str> {
+        self.symbols.get(&address).map(|name| name.as_str())
+    }
+
+    /// Look up the name of an address, in the form `Instruction::tokenize_with_symbols` expects.
+    pub fn lookup(&self, address: u32) -> Option<String> {
+        self.get(address).map(|name| name.to_string())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.symbols.iter().map(|(address, name)| (*address, name.as_str()))
+    }
+}
+
+impl From<Vec<(u32, String)>> for SymbolTable {
+    fn from(symbols: Vec<(u32, String)>) -> Self {
+        Self {
+            symbols: symbols.into_iter().collect(),
+        }
+    }
+}
+
+/// Format symbols as a symbol file, one 'address=name' line per symbol with the address in hexadecimal.
+pub fn to_symbol_file(symbols: &[(u32, String)]) -> String {
+    let mut output = String::new();
+    for (address, name) in symbols.iter() {
+        output.push_str(&format!("{:05X}={}\n", address, name));
+    }
+    output
+}
+
+/// Parse a symbol file of 'address=name' lines. The address is a hexadecimal flat address or segment:offset pair.
+/// Blank lines and lines starting with ';' or '#' are ignored.
+pub fn parse_symbol_file(text: &str) -> Result<Vec<(u32, String)>, String> {
+    let mut symbols = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line.split_once('=').and_then(|(address, name)| {
+            let name = name.trim();
+            (!name.is_empty()).then_some(())?;
+            parse_address(address.trim()).map(|address| (address, name.to_string()))
+        });
+        match parsed {
+            Some(symbol) => symbols.push(symbol),
+            None => return Err(format!("Invalid symbol on line {}: {}", line_no + 1, line)),
+        }
+    }
+    Ok(symbols)
+}
+
+fn parse_address(address: &str) -> Option<u32> {
+    match address.split_once(':') {
+        Some((segment, offset)) => {
+            let segment = u16::from_str_radix(segment, 16).ok()?;
+            let offset = u16::from_str_radix(offset, 16).ok()?;
+            Some(calc_linear_address(segment, offset))
+        }
+        None => u32::from_str_radix(address, 16)
+            .ok()
+            .filter(|address| *address < 0x100000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu_common::{
+            test_util::{cpu_with_code, step, CODE_SEG},
+            Cpu,
+            CpuOption,
+            CpuType,
+        },
+        syntax_token::SyntaxToken,
+    };
+
+    #[test]
+    fn discovered_symbols_round_trip() {
+        let mut discovered = DiscoveredSymbols::new();
+        discovered.add_interrupt(0x10, 0xFF065);
+        discovered.add_interrupt(0x21, 0x01234);
+        discovered.add_call(0x01234);
+        discovered.add_call(0x00500);
+        let symbols = discovered.into_vec();
+        assert_eq!(
+            symbols,
+            vec![
+                (0x00500, "sub_00500".to_string()),
+                (0x01234, "sub_01234".to_string()),
+                (0xFF065, "int10_FF065".to_string()),
+            ]
+        );
+
+        let file = to_symbol_file(&symbols);
+        assert_eq!(parse_symbol_file(&file).unwrap(), symbols);
+    }
+
+    #[test]
+    fn discover_call_and_interrupt_targets() {
+        let instr = |mnemonic, size| Instruction {
+            mnemonic,
+            size,
+            ..Default::default()
+        };
+        let int21 = Instruction {
+            operand1_type: OperandType::Immediate8(0x21),
+            ..instr(Mnemonic::INT, 2)
+        };
+
+        let mut discovered = DiscoveredSymbols::new();
+        discovered.instruction_executed(0x1000, 0x0101, &instr(Mnemonic::CALL, 3));
+        discovered.instruction_executed(0x1000, 0x0200, &instr(Mnemonic::RETN, 1));
+        discovered.instruction_executed(0x1000, 0x0104, &int21);
+        discovered.instruction_executed(0x0070, 0x0010, &instr(Mnemonic::CALL, 3));
+        // A timer interrupt is taken before the called routine begins. The interrupt returns to the call target.
+        discovered.interrupt_taken(0x08, 0x00800, 0xFFEA5);
+        discovered.instruction_executed(0xF000, 0xFEA5, &instr(Mnemonic::IRET, 1));
+        discovered.instruction_executed(0x0070, 0x0100, &instr(Mnemonic::INTO, 1));
+        // INTO without overflow falls through.
+        discovered.instruction_executed(0x0070, 0x0101, &instr(Mnemonic::NOP, 1));
+
+        assert_eq!(
+            discovered.into_vec(),
+            vec![
+                (0x00710, "int21_00710".to_string()),
+                (0x00800, "sub_00800".to_string()),
+                (0x10200, "sub_10200".to_string()),
+                (0xFFEA5, "int08_FFEA5".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_edited_symbol_file() {
+        let file = "; Renamed routines\n\nF000:E05B = reset\n01234=print_string\n";
+        assert_eq!(
+            parse_symbol_file(file).unwrap(),
+            vec![(0xFE05B, "reset".to_string()), (0x01234, "print_string".to_string())]
+        );
+        assert!(parse_symbol_file("01234=\n").is_err());
+        assert!(parse_symbol_file("100000=too_far\n").is_err());
+        assert!(parse_symbol_file("sub_01234\n").is_err());
+    }
+
+    #[test]
+    fn export_edit_and_import_symbols() {
+        // CALL 0040h, then NOPs. The routine at 0040h is 40 NOPs and a RET, so the CALL has left the
+        // instruction history by the time the routine returns.
+        let mut code = vec![0x90; 0x69];
+        code[0..3].copy_from_slice(&[0xE8, 0x3D, 0x00]);
+        code[0x68] = 0xC3;
+        let routine = calc_linear_address(CODE_SEG, 0x0040);
+
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            let mut cpu = cpu_with_code(cpu_type, &code);
+            cpu.set_option(CpuOption::InstructionHistory(true));
+            for _ in 0..50 {
+                step(&mut cpu);
+            }
+            let exported = cpu.export_discovered_symbols();
+            assert_eq!(exported, vec![(routine, format!("sub_{:05X}", routine))]);
+
+            // Rename the routine and import the edited file.
+            let file = to_symbol_file(&exported).replace(&exported[0].1, "print_string");
+            cpu.set_symbols(SymbolTable::from(parse_symbol_file(&file).unwrap()));
+            assert_eq!(cpu.symbols().get(routine), Some("print_string"));
+
+            // Exporting again keeps the imported name.
+            assert_eq!(
+                cpu.export_discovered_symbols(),
+                vec![(routine, "print_string".to_string())]
+            );
+        }
+    }
+
+    #[test]
+    fn history_names_imported_symbols() {
+        // CALL 0040h
+        let routine = calc_linear_address(CODE_SEG, 0x0040);
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            let mut cpu = cpu_with_code(cpu_type, &[0xE8, 0x3D, 0x00]);
+            cpu.set_option(CpuOption::InstructionHistory(true));
+            cpu.set_symbols(SymbolTable::from(vec![(routine, "print_string".to_string())]));
+            step(&mut cpu);
+
+            let history = cpu.dump_instruction_history_tokens();
+            assert!(history[0].contains(&SyntaxToken::Symbol(routine, "print_string".to_string())));
+        }
+    }
+}
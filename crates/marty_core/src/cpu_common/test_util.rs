@@ -0,0 +1,56 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    cpu_common::test_util.rs
+
+    Shared fixture for CPU unit tests.
+
+*/
+
+use crate::cpu_common::{builder::CpuBuilder, Cpu, CpuAddress, CpuDispatch, CpuType};
+
+/// The segment test code is loaded at.
+pub const CODE_SEG: u16 = 0x1000;
+
+/// Build a CPU of the specified type with a default bus, load `code` at CODE_SEG:0000 and reset
+/// the CPU so that execution begins there.
+pub fn cpu_with_code(cpu_type: CpuType, code: &[u8]) -> CpuDispatch {
+    let mut cpu = CpuBuilder::new().with_cpu_type(cpu_type).build().unwrap();
+    for (i, byte) in code.iter().enumerate() {
+        cpu.bus_mut()
+            .write_u8(((CODE_SEG as usize) << 4) + i, *byte, 0)
+            .unwrap();
+    }
+    cpu.set_reset_vector(CpuAddress::Segmented(CODE_SEG, 0));
+    cpu.reset();
+    cpu
+}
+
+/// Execute a single instruction.
+pub fn step(cpu: &mut CpuDispatch) {
+    cpu.step(false).unwrap();
+    cpu.step_finish(None).unwrap();
+}
@@ -30,23 +30,38 @@
 
 */
 
+use marty_common::types::history_buffer::HistoryBuffer;
+
 use crate::{
-    breakpoints::{BreakPointType, StopWatchData},
+    breakpoints::{BreakPointType, RomHook, StopWatchData},
     bus::BusInterface,
     cpu_common::{
+        cycle_trace::CYCLE_TRACE_RECORD_LEN,
+        resize_history,
+        symbols::SymbolTable,
+        CallStackOverflowPolicy,
+        CoverageReport,
         Cpu,
         CpuAddress,
         CpuError,
         CpuOption,
         CpuStringState,
         CpuType,
+        CycleTraceRecord,
+        EvalError,
+        EvalResult,
         Disassembly,
+        Flag,
+        HistoryQuery,
         LogicAnalyzer,
+        PrefetchState,
         QueueOp,
         Register8,
         ServiceEvent,
         StepResult,
         TraceMode,
+        MAX_CALL_STACK_DEPTH,
+        MIN_CALL_STACK_DEPTH,
     },
     cpu_vx0::{
         trace_print,
@@ -59,6 +74,7 @@ use crate::{
         TaCycle,
         CPU_FLAGS_RESERVED_ON,
     },
+    savestate::CpuExecState,
     syntax_token::SyntaxToken,
 };
 
@@ -138,7 +154,9 @@ impl Cpu for NecVx0 {
 
         self.instruction_count = 0;
         self.int_count = 0;
+        self.int_vector_counts = vec![0; 256];
         self.iret_count = 0;
+        self.bus.reset_page_stats();
         self.instr_cycle = 0;
         self.cycle_num = 1;
         self.halt_cycles = 0;
@@ -154,9 +172,12 @@ impl Cpu for NecVx0 {
         self.interrupt_inhibit = false;
         self.intr_pending = false;
         self.in_int = false;
+        self.hw_int_break = false;
         self.is_error = false;
         self.instruction_history.clear();
+        self.discovered_symbols.cancel_entry();
         self.call_stack.clear();
+        self.call_stack_overflowed = false;
         self.int_flags = vec![0; 256];
 
         self.instruction_reentrant = false;
@@ -181,6 +202,7 @@ impl Cpu for NecVx0 {
 
         self.step_over_target = None;
         self.step_over_breakpoint = None;
+        self.step_out_frame = None;
         self.end_addr = 0xFFFFF;
         self.stopwatch_running = false;
 
@@ -221,6 +243,11 @@ impl Cpu for NecVx0 {
         self.set_reset_vector(address);
     }
 
+    #[inline]
+    fn get_reset_vector(&self) -> CpuAddress {
+        self.get_reset_vector()
+    }
+
     #[inline]
     fn set_end_address(&mut self, address: CpuAddress) {
         let end_addr;
@@ -263,6 +290,10 @@ impl Cpu for NecVx0 {
         self.step_finish(disassembly)
     }
 
+    fn run_until_interrupt(&mut self, timeout_cycles: u64) -> Result<CpuAddress, CpuError> {
+        self.run_until_interrupt(timeout_cycles)
+    }
+
     #[inline]
     fn in_rep(&self) -> bool {
         self.in_rep
@@ -279,6 +310,58 @@ impl Cpu for NecVx0 {
         self.queue.flush();
     }
 
+    fn queue_bytes(&self) -> Vec<u8> {
+        self.queue.to_vec()
+    }
+
+    fn fetch_state(&self) -> FetchState {
+        self.fetch_state
+    }
+
+    fn exec_state(&self) -> CpuExecState {
+        CpuExecState {
+            halted: self.halted,
+            interrupt_inhibit: self.interrupt_inhibit,
+            intr: self.intr,
+            intr_pending: self.intr_pending,
+            nmi: self.nmi,
+            nmi_triggered: self.nmi_triggered,
+            trap_enable_delay: self.trap_enable_delay,
+            trap_disable_delay: self.trap_disable_delay,
+            trap_suppressed: self.trap_suppressed,
+        }
+    }
+
+    fn set_exec_state(&mut self, state: CpuExecState) {
+        self.halted = state.halted;
+        self.halt_not_hold = state.halted;
+        self.reported_halt = false;
+        if state.halted {
+            self.fetch_state = FetchState::Halted;
+        }
+        self.interrupt_inhibit = state.interrupt_inhibit;
+        self.intr = state.intr;
+        self.intr_pending = state.intr_pending;
+        self.nmi = state.nmi;
+        self.nmi_triggered = state.nmi_triggered;
+        self.trap_enable_delay = state.trap_enable_delay;
+        self.trap_disable_delay = state.trap_disable_delay;
+        self.trap_suppressed = state.trap_suppressed;
+    }
+
+    fn prefetch_state(&self) -> PrefetchState {
+        PrefetchState {
+            queue: self.queue.to_vec(),
+            queue_size: self.queue.get_size(),
+            preload: self.queue.peek_preload(),
+            fetch_state: self.fetch_state,
+            queue_op: self.last_queue_op,
+            cs: self.cs,
+            pc: self.pc,
+            ip: self.ip(),
+        }
+    }
+
     #[inline]
     fn get_ip(&mut self) -> u16 {
         self.ip()
@@ -314,6 +397,11 @@ impl Cpu for NecVx0 {
         self.set_flags(flags);
     }
 
+    #[inline]
+    fn set_flag_state(&mut self, flag: Flag, state: bool) {
+        self.set_flag_state(flag, state);
+    }
+
     #[inline]
     fn get_cycle_ct(&self) -> (u64, u64) {
         self.get_cycle_ct()
@@ -324,6 +412,10 @@ impl Cpu for NecVx0 {
         self.get_instruction_ct()
     }
 
+    fn get_int_counts(&self) -> &[u64] {
+        self.get_int_counts()
+    }
+
     /// Return the resolved flat address of CS:CORR(PC)
     #[inline]
     fn flat_ip(&self) -> u32 {
@@ -351,10 +443,30 @@ impl Cpu for NecVx0 {
         self.dump_instruction_history_tokens()
     }
 
+    fn query_instruction_history(&self, query: &HistoryQuery) -> Vec<Vec<SyntaxToken>> {
+        self.query_instruction_history(query)
+    }
+
     fn dump_call_stack(&self) -> String {
         self.dump_call_stack()
     }
 
+    fn call_stack_overflow(&self) -> Option<CallStackOverflowPolicy> {
+        self.call_stack_overflowed.then_some(self.call_stack_overflow_policy)
+    }
+
+    fn export_discovered_symbols(&self) -> Vec<(u32, String)> {
+        self.export_discovered_symbols()
+    }
+
+    fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
     #[inline]
     fn get_service_event(&mut self) -> Option<ServiceEvent> {
         self.service_events.pop_front()
@@ -374,6 +486,11 @@ impl Cpu for NecVx0 {
         self.get_cycle_trace_tokens()
     }
 
+    #[inline]
+    fn get_cycle_trace_records(&self) -> Option<&HistoryBuffer<CycleTraceRecord>> {
+        self.get_cycle_trace_records()
+    }
+
     #[inline]
     #[cfg(feature = "cpu_validator")]
     fn get_vregisters(&self) -> VRegisters {
@@ -385,10 +502,28 @@ impl Cpu for NecVx0 {
         self.get_string_state()
     }
 
+    fn coverage_report(&self) -> CoverageReport {
+        self.coverage.report()
+    }
+
+    fn clear_coverage(&mut self) {
+        self.coverage.clear();
+        self.bus.clear_exec_coverage();
+    }
+
     fn eval_address(&self, expr: &str) -> Option<CpuAddress> {
         self.eval_address(expr)
     }
 
+    fn eval_expression(&self, expr: &str) -> Result<EvalResult, EvalError> {
+        self.eval_expression(expr)
+    }
+
+    #[inline]
+    fn set_breakpoint_flag(&mut self) {
+        self.set_breakpoint_flag();
+    }
+
     #[inline]
     fn clear_breakpoint_flag(&mut self) {
         self.clear_breakpoint_flag();
@@ -399,6 +534,16 @@ impl Cpu for NecVx0 {
         self.set_breakpoints(bp_list)
     }
 
+    #[inline]
+    fn get_breakpoints(&self) -> &[BreakPointType] {
+        &self.breakpoints
+    }
+
+    #[inline]
+    fn set_rom_hooks(&mut self, hooks: Vec<RomHook>) {
+        self.set_rom_hooks(hooks)
+    }
+
     #[inline]
     fn get_step_over_breakpoint(&self) -> Option<CpuAddress> {
         self.get_step_over_breakpoint()
@@ -409,6 +554,21 @@ impl Cpu for NecVx0 {
         self.set_step_over_breakpoint(address)
     }
 
+    #[inline]
+    fn step_out(&mut self) {
+        self.step_out()
+    }
+
+    #[inline]
+    fn cancel_step_out(&mut self) {
+        self.cancel_step_out()
+    }
+
+    #[inline]
+    fn is_stepping_out(&self) -> bool {
+        self.is_stepping_out()
+    }
+
     #[inline]
     fn get_sw_data(&self) -> Vec<StopWatchData> {
         self.get_sw_data()
@@ -426,6 +586,22 @@ impl Cpu for NecVx0 {
                 self.instruction_history.clear();
                 self.instruction_history_on = state;
             }
+            CpuOption::InstructionHistoryDepth(depth) => {
+                log::debug!("Setting InstructionHistoryDepth to: {}", depth);
+                self.instruction_history_depth = resize_history(&mut self.instruction_history, depth);
+            }
+            CpuOption::CallStackDepth(depth) => {
+                log::debug!("Setting CallStackDepth to: {}", depth);
+                self.call_stack_depth = depth.clamp(MIN_CALL_STACK_DEPTH, MAX_CALL_STACK_DEPTH);
+                // Shrinking the call stack always discards the oldest entries.
+                if self.trim_call_stack(self.call_stack_depth) {
+                    self.call_stack_overflowed = true;
+                }
+            }
+            CpuOption::CallStackOverflowPolicy(policy) => {
+                log::debug!("Setting CallStackOverflowPolicy to: {:?}", policy);
+                self.call_stack_overflow_policy = policy;
+            }
             CpuOption::ScheduleInterrupt(_state, cycle_target, cycles, retrigger) => {
                 log::debug!("Setting InterruptHint to: ({},{})", cycle_target, cycles);
                 self.interrupt_scheduling = true;
@@ -476,12 +652,33 @@ impl Cpu for NecVx0 {
                 log::debug!("Setting EnableServiceInterrupt to: {:?}", state);
                 self.enable_service_interrupt = state;
             }
+            CpuOption::InstructionCoverage(state) => {
+                log::debug!("Setting InstructionCoverage to: {:?}", state);
+                if state != self.coverage.enabled() {
+                    self.bus.clear_exec_coverage();
+                }
+                self.coverage.set_enabled(state);
+            }
+            CpuOption::CycleTraceRecords(state) => {
+                log::debug!("Setting CycleTraceRecords to: {:?}", state);
+                if state != self.cycle_records.is_some() {
+                    self.cycle_records = state.then(|| HistoryBuffer::new(CYCLE_TRACE_RECORD_LEN));
+                }
+            }
+            CpuOption::Deterministic(seed) => {
+                log::debug!("Setting Deterministic mode with seed: {}", seed);
+                self.randomize_seed(seed);
+                self.deterministic = true;
+            }
         }
     }
 
     fn get_option(&self, opt: CpuOption) -> bool {
         match opt {
             CpuOption::InstructionHistory(_) => self.instruction_history_on,
+            CpuOption::InstructionHistoryDepth(_) => true,
+            CpuOption::CallStackDepth(_) => true,
+            CpuOption::CallStackOverflowPolicy(_) => true,
             CpuOption::ScheduleInterrupt(..) => self.interrupt_cycle_period > 0,
             CpuOption::ScheduleDramRefresh(..) => self.dram_refresh_simulation,
             CpuOption::DramRefreshAdjust(..) => true,
@@ -490,6 +687,9 @@ impl Cpu for NecVx0 {
             CpuOption::EnableWaitStates(_) => self.enable_wait_states,
             CpuOption::TraceLoggingEnabled(_) => self.trace_enabled,
             CpuOption::EnableServiceInterrupt(_) => self.enable_service_interrupt,
+            CpuOption::InstructionCoverage(_) => self.coverage.enabled(),
+            CpuOption::CycleTraceRecords(_) => self.cycle_records.is_some(),
+            CpuOption::Deterministic(_) => self.deterministic,
         }
     }
 
@@ -194,6 +194,12 @@ impl NecVx0 {
             }
         };
 
+        // Record structured cycle state, if enabled. This must precede the text trace, which
+        // consumes the cycle's trace comments.
+        if self.cycle_records.is_some() {
+            self.push_cycle_record();
+        }
+
         // Perform cycle tracing, if enabled
         if self.trace_enabled {
             self.do_cycle_trace();
@@ -460,6 +466,28 @@ impl NecVx0 {
         }
     }
 
+    /// Check whether a memory write of `size` bytes at the address latch modifies code that has
+    /// already been fetched: the bytes of the current instruction or the contents of the prefetch
+    /// queue. The first such address is reported to the machine as a service event.
+    fn check_code_write(&mut self, size: u32) {
+        let queue_len = self.queue.len_p() as u32;
+        let queue_start = NecVx0::calc_linear_address(self.cs, self.pc.wrapping_sub(queue_len as u16));
+        let in_range = |start: u32, len: u32, address: u32| (address.wrapping_sub(start) & 0xFFFFF) < len;
+
+        for address in (0..size).map(|i| (self.address_latch + i) & 0xFFFFF) {
+            if in_range(self.instruction_address, self.i.size, address) || in_range(queue_start, queue_len, address) {
+                self.service_events
+                    .push_back(ServiceEvent::SelfModifyingWrite(CodeWrite {
+                        address,
+                        segment: self.cs,
+                        offset: self.instruction_ip,
+                        instruction: self.instruction_count,
+                    }));
+                return;
+            }
+        }
+    }
+
     pub fn do_bus_transfer(&mut self) {
         let byte;
 
@@ -519,6 +547,16 @@ impl NecVx0 {
                     )
                     .unwrap();
                 self.instr_elapsed = 0;
+                if (self.address_latch as usize) < IVT_END {
+                    self.bus.record_ivt_write(
+                        self.address_latch as usize,
+                        1,
+                        self.cs,
+                        self.instruction_ip,
+                        self.instruction_count,
+                    );
+                }
+                self.check_code_write(1);
 
                 validate_write_u8!(self, self.address_latch, (self.data_bus & 0x00FF) as u8, BusType::Mem);
             }
@@ -529,6 +567,16 @@ impl NecVx0 {
                     .write_u16(self.address_latch as usize, self.data_bus, self.instr_elapsed)
                     .unwrap();
                 self.instr_elapsed = 0;
+                if (self.address_latch as usize) < IVT_END {
+                    self.bus.record_ivt_write(
+                        self.address_latch as usize,
+                        2,
+                        self.cs,
+                        self.instruction_ip,
+                        self.instruction_count,
+                    );
+                }
+                self.check_code_write(2);
             }
             (BusStatus::IoRead, TransferSize::Byte) => {
                 self.i8288.iorc = true;
@@ -537,6 +585,14 @@ impl NecVx0 {
                     .io_read_u8((self.address_latch & 0xFFFF) as u16, self.instr_elapsed);
                 self.data_bus = byte as u16;
                 self.instr_elapsed = 0;
+                self.bus.record_io_access(
+                    (self.address_latch & 0xFFFF) as u16,
+                    byte,
+                    IoDirection::Read,
+                    self.cs,
+                    self.instruction_ip,
+                    self.instruction_count,
+                );
 
                 validate_read_u8!(
                     self,
@@ -555,6 +611,14 @@ impl NecVx0 {
                     None,
                 );
                 self.instr_elapsed = 0;
+                self.bus.record_io_access(
+                    (self.address_latch & 0xFFFF) as u16,
+                    (self.data_bus & 0x00FF) as u8,
+                    IoDirection::Write,
+                    self.cs,
+                    self.instruction_ip,
+                    self.instruction_count,
+                );
 
                 validate_write_u8!(self, self.address_latch, (self.data_bus & 0x00FF) as u8, BusType::Io);
             }
@@ -922,6 +922,7 @@ impl NecVx0 {
             decode_idx,
             opcode,
             prefixes: op_prefixes,
+            modrm: loaded_modrm.then(|| modrm.byte()),
             address: 0,
             size,
             mnemonic: op_lu.mnemonic,
@@ -44,6 +44,52 @@ impl NecVx0 {
         self.cycle_i(0x0ca);
     }
 
+    /// Return true if a breakpoint is set on the specified interrupt vector, taking any AH filter into
+    /// account.
+    pub fn interrupt_breakpoint(&self, vector: u8) -> bool {
+        let flags = self.int_flags[vector as usize];
+        flags & INTERRUPT_BREAKPOINT != 0
+            || (flags & INTERRUPT_FUNC_BREAKPOINT != 0
+                && self.breakpoints.iter().any(
+                    |bp| matches!(bp, BreakPointType::InterruptFunc { int, ah } if *int == vector && *ah == self.a.h()),
+                ))
+    }
+
+    /// Return true if a breakpoint is set on the vector of the pending hardware interrupt. The vector is
+    /// peeked from the PIC so that the interrupt is not acknowledged.
+    pub fn hw_interrupt_breakpoint(&self) -> bool {
+        self.int_breakpoints
+            && self
+                .bus
+                .pic()
+                .as_ref()
+                .and_then(|pic| pic.peek_interrupt_vector())
+                .is_some_and(|vector| self.interrupt_breakpoint(vector))
+    }
+
+    /// Return the vector of the software interrupt the instruction at the specified address would
+    /// raise, if any. We peek the instruction from memory so that an interrupt breakpoint can stop
+    /// on the INT instruction itself, with the caller's CS:IP and registers intact.
+    pub fn peek_sw_interrupt(&self, address: u32) -> Option<u8> {
+        let peek = |offset: u32| self.bus.peek_u8(((address + offset) & 0xFFFFF) as usize).ok();
+        let mut offset = 0;
+        // Skip over any prefixes. The CPU would keep going, but a run of prefixes this long is not an INT.
+        while offset < 16
+            && matches!(
+                peek(offset),
+                Some(0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0xF0 | 0xF2 | 0xF3)
+            )
+        {
+            offset += 1;
+        }
+        match peek(offset)? {
+            0xCC => Some(3),
+            0xCD => peek(offset + 1),
+            0xCE if self.get_flag(Flag::Overflow) => Some(4),
+            _ => None,
+        }
+    }
+
     /// Perform a software interrupt
     pub fn sw_interrupt(&mut self, interrupt: u8) {
         // Interrupt FC, emulator internal services.
@@ -86,6 +132,7 @@ impl NecVx0 {
         }
 
         self.cycles_i(3, &[0x19d, 0x19e, 0x19f]);
+        self.int_vector_counts[interrupt as usize] += 1;
 
         // Read the IVT
         let vec_addr = (interrupt as usize * INTERRUPT_VEC_LEN) as u16;
@@ -119,53 +166,6 @@ impl NecVx0 {
         self.int_count += 1;
     }
 
-    /*
-        /// Handle a CPU exception
-        pub fn handle_exception(&mut self, exception: u8) {
-            self.push_flags(ReadWriteFlag::Normal);
-
-            // Push return address of next instruction onto stack
-            self.push_register16(Register16::CS, ReadWriteFlag::Normal);
-
-            // Don't push address of next instruction
-            self.push_u16(self.ip, ReadWriteFlag::Normal);
-
-            if exception == 0x0 {
-                log::trace!(
-                    "CPU Exception: {:02X} Saving return: {:04X}:{:04X}",
-                    exception,
-                    self.cs,
-                    self.ip
-                );
-            }
-            // Read the IVT
-            let ivt_addr = Cpu::calc_linear_address(0x0000, (exception as usize * INTERRUPT_VEC_LEN) as u16);
-            let (new_ip, _cost) = self.bus.read_u16(ivt_addr as usize, 0).unwrap();
-            let (new_cs, _cost) = self.bus.read_u16((ivt_addr + 2) as usize, 0).unwrap();
-
-            // Add interrupt to call stack
-            self.push_call_stack(
-                CallStackEntry::Interrupt {
-                    ret_cs: self.cs,
-                    ret_ip: self.ip,
-                    call_cs: new_cs,
-                    call_ip: new_ip,
-                    itype: InterruptType::Exception,
-                    number: exception,
-                    ah: self.ah,
-                },
-                self.cs,
-                self.ip,
-            );
-
-            self.ip = new_ip;
-            self.cs = new_cs;
-
-            // Flush queue
-            self.biu_queue_flush();
-            self.biu_update_pc();
-        }
-    */
     #[allow(dead_code)]
     pub fn log_interrupt(&self, interrupt: u8) {
         match interrupt {
@@ -218,8 +218,9 @@ impl NecVx0 {
     /// skip_first is used to skip the first microcode instruction, such as when entering from
     /// INT1 or INT2.
     pub fn intr_routine(&mut self, vector: u8, itype: InterruptType, skip_first: bool) {
-        // Check for interrupt breakpoint.
-        if self.int_flags[vector as usize] & INTERRUPT_BREAKPOINT != 0 {
+        // Check for interrupt breakpoint. Software and hardware interrupts are caught before they are taken.
+        if matches!(itype, InterruptType::NMI | InterruptType::Exception) && self.interrupt_breakpoint(vector) {
+            log::debug!("Interrupt breakpoint hit: {:02X}", vector);
             self.set_breakpoint_flag();
         }
 
@@ -227,6 +228,7 @@ impl NecVx0 {
             self.cycle_i(0x019d);
         }
         self.cycles_i(2, &[0x19e, 0x19f]);
+        self.int_vector_counts[vector as usize] += 1;
 
         // Read the IVT
         let vec_addr = (vector as usize * INTERRUPT_VEC_LEN) as u16;
@@ -275,6 +277,9 @@ impl NecVx0 {
     }
 
     /// Perform INT0 (Divide By 0)
+    /// The return address pushed is that of the instruction following the faulting DIV, IDIV or AAM,
+    /// as on the 8086. Software can tell these CPUs from a 286 or later, which push the address of the
+    /// faulting instruction including its prefixes.
     pub fn int0(&mut self) {
         self.cycles_i(2, &[0x1a7, MC_JUMP]);
         self.intr_routine(0, InterruptType::Exception, true);
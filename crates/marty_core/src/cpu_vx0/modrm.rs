@@ -79,7 +79,7 @@ const MODRM_RED_DI_OR_BH:      u8 = 0b00_000_111;
 
 #[derive(Copy, Clone)]
 pub struct ModRmByte {
-    _byte: u8,
+    byte: u8,
     b_mod: u8,
     b_reg: u8,
     b_rm: u8,
@@ -93,7 +93,7 @@ pub struct ModRmByte {
 impl Default for ModRmByte {
     fn default() -> Self {
         Self {
-            _byte: 0,
+            byte: 0,
             b_mod: 0,
             b_reg: 0,
             b_rm: 0,
@@ -174,7 +174,7 @@ const EA_INSTR_TABLE_POST: [[u16; 3]; 24] = [
 
 const MODRM_TABLE: [ModRmByte; 256] = {
     let mut table: [ModRmByte; 256] = [ModRmByte {
-        _byte: 0,
+        byte: 0,
         b_mod: 0,
         b_reg: 0,
         b_rm: 0,
@@ -281,7 +281,7 @@ const MODRM_TABLE: [ModRmByte; 256] = {
         let b_rm: u8 = byte & 0x07;
 
         table[byte as usize] = ModRmByte {
-            _byte: byte,
+            byte,
             b_mod,
             b_reg,
             b_rm,
@@ -460,6 +460,10 @@ impl ModRmByte {
             _ => Register16::InvalidRegister,
         }
     }
+    // Return the raw modrm byte
+    pub fn byte(&self) -> u8 {
+        self.byte
+    }
     // Interpret the 'REG' field as a 3 bit opcode extension
     pub fn get_op_extension(&self) -> u8 {
         self.b_reg
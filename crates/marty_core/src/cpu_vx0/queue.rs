@@ -75,7 +75,7 @@ impl InstructionQueue {
         self.policy_size = size - fetch_size;
     }
 
-    pub fn get_size(&mut self) -> usize {
+    pub fn get_size(&self) -> usize {
         self.size
     }
 
@@ -112,6 +112,12 @@ impl InstructionQueue {
         preload
     }
 
+    /// Return the byte preloaded for decode, if any, without consuming it.
+    #[inline]
+    pub fn peek_preload(&self) -> Option<u8> {
+        self.preload
+    }
+
     #[inline]
     pub fn has_preload(&self) -> bool {
         self.preload.is_some()
@@ -182,6 +188,12 @@ impl InstructionQueue {
         base_str
     }
 
+    /// Return the contents of the processor instruction queue in order, not including any
+    /// preloaded byte.
+    pub fn to_vec(&self) -> Vec<u8> {
+        (0..self.len).map(|i| self.q[(self.back + i) % self.size]).collect()
+    }
+
     /// Write the contents of the processor instruction queue in order to the
     /// provided slice of u8. The slice must be the same size as the current piq
     /// length for the given cpu type.
@@ -94,6 +94,12 @@ impl NecVx0 {
                 return Ok((StepResult::BreakpointHit, 0));
             }
 
+            // Resuming from a hardware interrupt breakpoint. Don't execute anything, so that step_finish()
+            // takes the interrupt before the interrupted instruction.
+            if self.hw_int_break {
+                return Ok((StepResult::Normal, 0));
+            }
+
             // Check instruction address for breakpoint on execute flag
             let iflags = self.bus.get_flags(instruction_address as usize);
             if !skip_breakpoint && iflags & (MEM_BPE_BIT | MEM_SW_BIT) != 0 {
@@ -114,7 +120,11 @@ impl NecVx0 {
                     self.update_sw_flag();
                 }
 
-                if iflags & MEM_BPE_BIT != 0 {
+                // ROM hooks share the execute breakpoint flag, so confirm a user breakpoint is set here.
+                if iflags & MEM_BPE_BIT != 0
+                    && (!self.rom_hooks.contains_key(&instruction_address)
+                        || self.is_execute_breakpoint(instruction_address))
+                {
                     // Breakpoint hit
                     log::debug!("Breakpoint hit at {:05X}", instruction_address,);
                     self.set_breakpoint_flag();
@@ -122,6 +132,32 @@ impl NecVx0 {
                 }
             }
 
+            // Run any ROM hook at this address. Hooks are not skipped when resuming from a breakpoint.
+            if iflags & MEM_BPE_BIT != 0 && self.rom_hooks.contains_key(&instruction_address) {
+                // Take the hook table while the hook runs, so that we can borrow the hook along with the CPU.
+                let hooks = std::mem::take(&mut self.rom_hooks);
+                let redirected = hooks[&instruction_address].run(self);
+                self.rom_hooks = hooks;
+                if redirected {
+                    return Ok((StepResult::Normal, 0));
+                }
+            }
+
+            // Check for a breakpoint on the software interrupt this instruction would raise.
+            if self.int_breakpoints && !skip_breakpoint {
+                if let Some(vector) = self.peek_sw_interrupt(instruction_address) {
+                    if self.interrupt_breakpoint(vector) {
+                        log::debug!(
+                            "Interrupt breakpoint hit: INT {:02X} at {:05X}",
+                            vector,
+                            instruction_address
+                        );
+                        self.set_breakpoint_flag();
+                        return Ok((StepResult::BreakpointHit, 0));
+                    }
+                }
+            }
+
             // Check for the step over breakpoint
             if let Some(step_over_address) = self.step_over_breakpoint {
                 if instruction_address == step_over_address {
@@ -132,6 +168,19 @@ impl NecVx0 {
                 }
             }
 
+            // Check whether the last instruction returned from the routine being stepped out of. A return
+            // within a nested call only pops back to the recorded frame; leaving the routine pops above it.
+            if let Some((step_out_ss, step_out_sp)) = self.step_out_frame {
+                if matches!(self.i.mnemonic, Mnemonic::RETN | Mnemonic::RETF | Mnemonic::IRET)
+                    && self.ss == step_out_ss
+                    && self.sp > step_out_sp
+                {
+                    log::debug!("CPU: Step Out returned to {:05X}", instruction_address);
+                    self.step_out_frame = None;
+                    return Ok((StepResult::StepOutHit, 0));
+                }
+            }
+
             // Check if this address is a return from a CALL or INT
             if self.bus.get_flags(instruction_address as usize) & MEM_RET_BIT != 0 {
                 // This address is a return address, rewind the stack
@@ -180,6 +229,12 @@ impl NecVx0 {
             {
                 self.validate_begin(instruction_address);
             }
+
+            // Record the decoded instruction shape and the bytes it occupies, if coverage recording is enabled.
+            if self.coverage.enabled() {
+                self.coverage.record(&self.i);
+                self.bus.mark_executed(instruction_address, self.i.size);
+            }
         }
 
         // Since Cpu::decode doesn't know anything about the current IP, it can't set it, so we do that now.
@@ -320,6 +375,10 @@ impl NecVx0 {
         self.instr_elapsed = 0;
         self.int_elapsed = 0;
         self.device_cycles = 0;
+        let resuming_hw_int = std::mem::take(&mut self.hw_int_break);
+        // Where execution continues if no interrupt is taken. An interrupt taken now returns here.
+        let return_address = CpuAddress::Segmented(self.cs, self.ip());
+        let next_address = Self::calc_linear_address(self.cs, self.ip());
 
         if self.nmi && self.bus.nmi_enabled() && !self.nmi_triggered {
             // NMI takes priority over trap and INTR.
@@ -331,7 +390,7 @@ impl NecVx0 {
             self.nmi_triggered = true;
             self.int2();
             did_nmi = true;
-            step_result = StepResult::Call(CpuAddress::Segmented(self.cs, self.ip()));
+            step_result = StepResult::Call(return_address);
         }
         else if self.intr && self.interrupts_enabled() {
             // An interrupt needs to be processed.
@@ -343,6 +402,22 @@ impl NecVx0 {
                 // will terminate, and we can process the interrupt as normal.
                 self.intr_pending = true;
             }
+            else if !resuming_hw_int && self.hw_interrupt_breakpoint() {
+                // The interrupt's vector has a breakpoint. Stop before the interrupt is acknowledged, with the
+                // interrupted CS:IP intact, like a software interrupt breakpoint. The interrupt is taken when
+                // execution resumes.
+                log::debug!(
+                    "Interrupt breakpoint hit: hardware interrupt at {:05X}",
+                    self.instruction_address
+                );
+                if self.halted {
+                    // Resume from halt on interrupt
+                    self.resume();
+                }
+                self.hw_int_break = true;
+                self.set_breakpoint_flag();
+                self.biu_fetch_next();
+            }
             else {
                 // We are not in a REP prefixed string instruction, so we
                 // can process an interrupt normally.
@@ -366,13 +441,9 @@ impl NecVx0 {
                 }
 
                 // We will be jumping into an ISR now. Set the step result to Call and return
-                // the address of the next instruction. (Step Over skips ISRs)
-                step_result = StepResult::Call(CpuAddress::Segmented(self.cs, self.ip()));
+                // the address the ISR returns to. (Step Over skips ISRs)
+                step_result = StepResult::Call(return_address);
 
-                if self.int_flags[irq as usize] != 0 {
-                    // This interrupt has a breakpoint
-                    self.set_breakpoint_flag();
-                }
                 self.hw_interrupt(irq);
                 did_interrupt = true;
                 self.biu_fetch_next();
@@ -386,7 +457,7 @@ impl NecVx0 {
             }
             self.int1();
             did_trap = true;
-            step_result = StepResult::Call(CpuAddress::Segmented(self.cs, self.ip()));
+            step_result = StepResult::Call(return_address);
         }
         else if !self.halted {
             // We didn't have NMI, INTR, or TRAP condition. Fetch the next instruction if not halted.
@@ -414,7 +485,7 @@ impl NecVx0 {
             // Only add non-reentrant instructions to history, unless they were interrupted.
             // This prevents spamming the history with multiple rep string operations.
             if !self.instruction_reentrant || cur_intr {
-                if self.instruction_history.len() == CPU_HISTORY_LEN {
+                if self.instruction_history.len() >= self.instruction_history_depth {
                     self.instruction_history.pop_front();
                 }
 
@@ -426,10 +497,14 @@ impl NecVx0 {
                     jump: self.jumped,
                     i: self.i.clone(),
                 });
+                self.discovered_symbols
+                    .instruction_executed(self.last_cs, self.last_ip, &self.i);
             }
 
+            let handler_address = Self::calc_linear_address(self.cs, self.ip());
+
             if did_nmi {
-                if self.instruction_history.len() == CPU_HISTORY_LEN {
+                if self.instruction_history.len() >= self.instruction_history_depth {
                     self.instruction_history.pop_front();
                 }
 
@@ -437,10 +512,12 @@ impl NecVx0 {
                     cs: self.last_cs,
                     ip: self.last_ip,
                 });
+                self.discovered_symbols
+                    .interrupt_taken(2, next_address, handler_address);
             }
 
             if did_trap {
-                if self.instruction_history.len() == CPU_HISTORY_LEN {
+                if self.instruction_history.len() >= self.instruction_history_depth {
                     self.instruction_history.pop_front();
                 }
 
@@ -448,10 +525,12 @@ impl NecVx0 {
                     cs: self.last_cs,
                     ip: self.last_ip,
                 });
+                self.discovered_symbols
+                    .interrupt_taken(1, next_address, handler_address);
             }
 
             if did_interrupt {
-                if self.instruction_history.len() == CPU_HISTORY_LEN {
+                if self.instruction_history.len() >= self.instruction_history_depth {
                     self.instruction_history.pop_front();
                 }
 
@@ -461,6 +540,8 @@ impl NecVx0 {
                     cycles: self.instr_cycle as u16,
                     iv: irq,
                 });
+                self.discovered_symbols
+                    .interrupt_taken(irq, next_address, handler_address);
             }
 
             self.last_intr = cur_intr;
@@ -469,6 +550,25 @@ impl NecVx0 {
         Ok(step_result)
     }
 
+    /// Run instructions until the CPU takes an NMI, hardware interrupt or trap, returning the address
+    /// the interrupt will return to. A halted CPU keeps running, as only an interrupt can resume it.
+    ///
+    /// Returns CpuHaltedError if no interrupt is taken within `timeout_cycles`, or if execution stops
+    /// at a breakpoint or the end address first. Any other error from stepping the CPU is returned as-is.
+    pub fn run_until_interrupt(&mut self, timeout_cycles: u64) -> Result<CpuAddress, CpuError> {
+        let deadline = self.cycle_num.saturating_add(timeout_cycles);
+        while self.cycle_num < deadline {
+            if matches!(self.step(true)?.0, StepResult::BreakpointHit | StepResult::ProgramEnd) {
+                break;
+            }
+            // step_finish() only returns a Call if an interrupt was taken.
+            if let StepResult::Call(address) = self.step_finish(None)? {
+                return Ok(address);
+            }
+        }
+        Err(CpuError::CpuHaltedError(self.instruction_address))
+    }
+
     #[rustfmt::skip]
     #[allow(dead_code, unused_variables)]
     pub fn debug_fetch(&mut self, instruction_address: u32) {
@@ -30,5 +30,6 @@
 
 */
 
+pub mod realtimeclock;
 pub mod sounddevice;
 pub mod videocard;
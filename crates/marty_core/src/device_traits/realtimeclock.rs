@@ -0,0 +1,137 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    device_traits::realtimeclock.rs
+
+    Defines the RealTimeClock trait which any real time clock (the AT's
+    MC146818, or an XT clock card) must implement.
+*/
+
+use crate::{
+    bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
+    cpu_common::LogicAnalyzer,
+    devices::{
+        mm58167::{Mm58167, Mm58167SaveState},
+        pic::Pic,
+        rtc::{Rtc, RtcDateTime, RtcSaveState},
+    },
+    machine_types::FloppyDriveType,
+    savestate::SaveState,
+};
+use enum_dispatch::enum_dispatch;
+use serde::{Deserialize, Serialize};
+
+#[enum_dispatch]
+pub enum RtcDispatch {
+    Rtc,
+    Mm58167,
+}
+
+#[enum_dispatch(RtcDispatch)]
+pub trait RealTimeClock {
+    /// Return the current time and date.
+    fn time(&self) -> RtcDateTime;
+    /// Set the time and date, as if the clock had been seeded with it. The clock offset is not changed.
+    fn set_time(&mut self, time: RtcDateTime);
+    /// Advance the clock with the host's wall clock instead of emulated time.
+    fn set_host_clock(&mut self, enabled: bool);
+    fn host_clock(&self) -> bool;
+    /// Stop the host clock until the next call to run(), so that time spent paused is not counted.
+    fn freeze(&mut self);
+    /// Return the IRQ the clock's interrupt is wired to, if any. IRQs 8-15 are on the secondary PIC.
+    fn irq(&self) -> Option<u8>;
+    fn run(&mut self, pic: Option<&mut Pic>, us: f64);
+    /// Return the contents of the clock's battery-backed RAM.
+    fn nvram(&self) -> Vec<u8>;
+    /// Restore battery-backed RAM from a previously saved image.
+    fn load_nvram(&mut self, data: &[u8]);
+    /// Return true if battery-backed RAM or the time has been written by the guest since it was loaded
+    /// or this flag was cleared.
+    fn nvram_dirty(&self) -> bool;
+    fn clear_nvram_dirty(&mut self);
+    /// Return true if battery-backed RAM passes any check the BIOS will apply to it.
+    fn nvram_valid(&self) -> bool;
+    /// Return the number of seconds the guest has moved the clock from the time it was seeded with.
+    fn clock_offset(&self) -> i64;
+    fn set_clock_offset(&mut self, offset: i64);
+    /// Record the installed floppy drive types in battery-backed RAM, if the BIOS reads them from it.
+    fn set_floppy_drives(&mut self, drives: &[FloppyDriveType]);
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum RtcDispatchSaveState {
+    Rtc(RtcSaveState),
+    Mm58167(Mm58167SaveState),
+}
+
+impl SaveState for RtcDispatch {
+    type State = RtcDispatchSaveState;
+
+    fn save_state(&self) -> RtcDispatchSaveState {
+        match self {
+            RtcDispatch::Rtc(rtc) => RtcDispatchSaveState::Rtc(rtc.save_state()),
+            RtcDispatch::Mm58167(rtc) => RtcDispatchSaveState::Mm58167(rtc.save_state()),
+        }
+    }
+
+    fn restore_state(&mut self, state: RtcDispatchSaveState) {
+        match (self, state) {
+            (RtcDispatch::Rtc(rtc), RtcDispatchSaveState::Rtc(saved)) => rtc.restore_state(saved),
+            (RtcDispatch::Mm58167(rtc), RtcDispatchSaveState::Mm58167(saved)) => rtc.restore_state(saved),
+            _ => log::error!("RtcDispatch: saved clock type does not match installed clock"),
+        }
+    }
+}
+
+impl IoDevice for RtcDispatch {
+    fn read_u8(&mut self, port: u16, delta: DeviceRunTimeUnit) -> u8 {
+        match self {
+            RtcDispatch::Rtc(rtc) => rtc.read_u8(port, delta),
+            RtcDispatch::Mm58167(rtc) => rtc.read_u8(port, delta),
+        }
+    }
+
+    fn write_u8(
+        &mut self,
+        port: u16,
+        data: u8,
+        bus: Option<&mut BusInterface>,
+        delta: DeviceRunTimeUnit,
+        analyzer: Option<&mut LogicAnalyzer>,
+    ) {
+        match self {
+            RtcDispatch::Rtc(rtc) => rtc.write_u8(port, data, bus, delta, analyzer),
+            RtcDispatch::Mm58167(rtc) => rtc.write_u8(port, data, bus, delta, analyzer),
+        }
+    }
+
+    fn port_list(&self) -> Vec<(String, u16)> {
+        match self {
+            RtcDispatch::Rtc(rtc) => rtc.port_list(),
+            RtcDispatch::Mm58167(rtc) => rtc.port_list(),
+        }
+    }
+}
@@ -33,6 +33,8 @@
 #[cfg(feature = "opl")]
 use crate::devices::adlib::AdLibCard;
 use crate::devices::null_sound::NullSoundDevice;
+#[cfg(feature = "sound")]
+use crate::devices::sn76489::Sn76489;
 use enum_dispatch::enum_dispatch;
 
 pub type AudioSample = f32;
@@ -41,6 +43,8 @@ pub type AudioSample = f32;
 pub enum SoundDispatch {
     #[cfg(feature = "opl")]
     AdLibCard,
+    #[cfg(feature = "sound")]
+    Sn76489,
     NullSoundDevice,
 }
 
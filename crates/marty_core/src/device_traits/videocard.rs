@@ -65,10 +65,17 @@ use std::{collections::HashMap, path::Path, str::FromStr};
 use crate::bus::DeviceRunTimeUnit;
 
 #[cfg(feature = "ega")]
-use crate::devices::ega::EGACard;
+use crate::devices::ega::{EGACard, EgaSaveState};
 #[cfg(feature = "vga")]
-use crate::devices::vga::VGACard;
-use crate::devices::{cga::CGACard, mda::MDACard, tga::TGACard};
+use crate::devices::vga::{VGACard, VgaSaveState};
+use crate::{
+    devices::{
+        cga::{CGACard, CgaSaveState},
+        mda::{MDACard, MdaSaveState},
+        tga::{TGACard, TgaSaveState},
+    },
+    savestate::SaveState,
+};
 
 use crate::devices::pic::Pic;
 use serde::Deserialize;
@@ -168,6 +175,50 @@ pub enum VideoCardDispatch {
     Vga(VGACard),
 }
 
+/// The save state of a video card, tagged with the type of card it was taken from.
+#[derive(Serialize, Deserialize)]
+pub enum VideoCardSaveState {
+    None,
+    Mda(MdaSaveState),
+    Cga(CgaSaveState),
+    Tga(TgaSaveState),
+    #[cfg(feature = "ega")]
+    Ega(EgaSaveState),
+    #[cfg(feature = "vga")]
+    Vga(VgaSaveState),
+}
+
+impl SaveState for VideoCardDispatch {
+    type State = VideoCardSaveState;
+
+    fn save_state(&self) -> VideoCardSaveState {
+        match self {
+            VideoCardDispatch::None => VideoCardSaveState::None,
+            VideoCardDispatch::Mda(mda) => VideoCardSaveState::Mda(mda.save_state()),
+            VideoCardDispatch::Cga(cga) => VideoCardSaveState::Cga(cga.save_state()),
+            VideoCardDispatch::Tga(tga) => VideoCardSaveState::Tga(tga.save_state()),
+            #[cfg(feature = "ega")]
+            VideoCardDispatch::Ega(ega) => VideoCardSaveState::Ega(ega.save_state()),
+            #[cfg(feature = "vga")]
+            VideoCardDispatch::Vga(vga) => VideoCardSaveState::Vga(vga.save_state()),
+        }
+    }
+
+    fn restore_state(&mut self, state: VideoCardSaveState) {
+        match (self, state) {
+            (VideoCardDispatch::None, VideoCardSaveState::None) => {}
+            (VideoCardDispatch::Mda(mda), VideoCardSaveState::Mda(saved)) => mda.restore_state(saved),
+            (VideoCardDispatch::Cga(cga), VideoCardSaveState::Cga(saved)) => cga.restore_state(saved),
+            (VideoCardDispatch::Tga(tga), VideoCardSaveState::Tga(saved)) => tga.restore_state(saved),
+            #[cfg(feature = "ega")]
+            (VideoCardDispatch::Ega(ega), VideoCardSaveState::Ega(saved)) => ega.restore_state(saved),
+            #[cfg(feature = "vga")]
+            (VideoCardDispatch::Vga(vga), VideoCardSaveState::Vga(saved)) => vga.restore_state(saved),
+            _ => log::error!("VideoCardDispatch: saved video card type does not match installed card"),
+        }
+    }
+}
+
 // This struct provides an identifier for a VideoCard, encapsulating a unique numeric id ('idx')
 // and the card's type. Hashable to store video cards in HashMap
 #[derive(Default, Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -221,7 +272,7 @@ pub type VideoCardState = HashMap<String, Vec<(String, VideoCardStateEntry)>>;
 
 /// All valid graphics modes for CGA, EGA and VGA Cards
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum DisplayMode {
     Disabled,
     Mode0TextBw40,
@@ -256,6 +307,101 @@ pub struct CursorInfo {
     pub visible: bool,
 }
 
+/// The contents of the displayed text mode page, as (character, attribute) pairs in row-major order.
+#[derive(Clone, Debug, Default)]
+pub struct TextBuffer {
+    pub columns: usize,
+    pub rows:    usize,
+    pub cells:   Vec<(u8, u8)>,
+}
+
+impl TextBuffer {
+    /// Return an iterator over the rows of the buffer.
+    pub fn rows(&self) -> impl Iterator<Item = &[(u8, u8)]> {
+        self.cells.chunks(self.columns.max(1))
+    }
+}
+
+/// Names of the 6845 CRTC's R0-R15 registers, as given in the Motorola MC6845 datasheet.
+pub const MC6845_REGISTER_NAMES: [&str; 16] = [
+    "R0 Horizontal Total",
+    "R1 Horizontal Displayed",
+    "R2 H. Sync Position",
+    "R3 Sync Width",
+    "R4 Vertical Total",
+    "R5 V. Total Adjust",
+    "R6 Vertical Displayed",
+    "R7 V. Sync Position",
+    "R8 Interlace Mode",
+    "R9 Max Scan Line Address",
+    "R10 Cursor Start",
+    "R11 Cursor End",
+    "R12 Start Address (H)",
+    "R13 Start Address (L)",
+    "R14 Cursor (H)",
+    "R15 Cursor (L)",
+];
+
+/// Names of the EGA and VGA CRT controller's registers, as given in the IBM EGA Technical Reference.
+pub const EGA_CRTC_REGISTER_NAMES: [&str; 25] = [
+    "R00 Horizontal Total",
+    "R01 Horizontal Display End",
+    "R02 Start Horizontal Blank",
+    "R03 End Horizontal Blank",
+    "R04 Start Horizontal Retrace",
+    "R05 End Horizontal Retrace",
+    "R06 Vertical Total",
+    "R07 Overflow",
+    "R08 Preset Row Scan",
+    "R09 Max Scan Line",
+    "R0A Cursor Start",
+    "R0B Cursor End",
+    "R0C Start Address High",
+    "R0D Start Address Low",
+    "R0E Cursor Location High",
+    "R0F Cursor Location Low",
+    "R10 Vertical Retrace Start",
+    "R11 Vertical Retrace End",
+    "R12 Vertical Display End",
+    "R13 Offset",
+    "R14 Underline Location",
+    "R15 Start Vertical Blank",
+    "R16 End Vertical Blank",
+    "R17 Mode Control",
+    "R18 Line Compare",
+];
+
+/// Names of the EGA and VGA sequencer's registers, as given in the IBM EGA Technical Reference.
+pub const EGA_SEQUENCER_REGISTER_NAMES: [&str; 5] = [
+    "SR00 Reset",
+    "SR01 Clocking Mode",
+    "SR02 Map Mask",
+    "SR03 Character Map Select",
+    "SR04 Memory Mode",
+];
+
+/// Names of the EGA and VGA graphics controller's registers, as given in the IBM EGA Technical Reference.
+pub const EGA_GRAPHICS_REGISTER_NAMES: [&str; 9] = [
+    "GR00 Set/Reset",
+    "GR01 Enable Set/Reset",
+    "GR02 Color Compare",
+    "GR03 Data Rotate",
+    "GR04 Read Map Select",
+    "GR05 Mode",
+    "GR06 Miscellaneous",
+    "GR07 Color Don't Care",
+    "GR08 Bit Mask",
+];
+
+/// Names of the EGA and VGA attribute controller's registers following the 16 palette registers
+/// (AR00-AR0F), as given in the IBM EGA Technical Reference.
+pub const EGA_ATTRIBUTE_REGISTER_NAMES: [&str; 4] = [
+    "AR10 Mode Control",
+    "AR11 Overscan Color",
+    "AR12 Color Plane Enable",
+    "AR13 Horizontal Pel Panning",
+];
+
 pub struct FontInfo {
     pub w: u32,
     pub h: u32,
@@ -332,6 +478,24 @@ pub struct DisplayAperture {
     pub debug: bool,
 }
 
+/// A register write latched at the raster position where it occurred, so that a renderer can
+/// apply register state that was changed mid-frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RasterRegisterChange {
+    pub scanline: u32, // Framebuffer row being drawn at the time of the write
+    pub hdot: u32,     // Horizontal position in hdots at the time of the write
+    pub register: u16, // IO port written to
+    pub value: u8,
+}
+
+/// The position of the raster beam within the frame being drawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RasterPosition {
+    pub frame: u64,    // Number of frames rendered
+    pub scanline: u32, // Framebuffer row being drawn
+    pub dot: u32,      // Horizontal position in hdots
+}
+
 #[derive(Clone)]
 pub struct DisplayExtents {
     pub apertures: Vec<DisplayAperture>, // List of display aperture definitions.
@@ -339,7 +503,8 @@ pub struct DisplayExtents {
     pub field_h: u32,                    // The total height of the video field
     pub row_stride: usize,               // Number of bytes in frame buffer to skip to reach next row
     pub double_scan: bool,               // Whether the display should be double-scanned when RGBA converted
-    pub mode_byte: u8,                   // Mode byte. Used by CGA modes only.
+    pub mode_byte: u8,                   // Mode byte at the start of the frame. Used by CGA modes only.
+    pub register_changes: Vec<RasterRegisterChange>, // Mode and color register writes made during the frame.
 }
 
 pub trait VideoCard {
@@ -393,6 +558,9 @@ pub trait VideoCard {
     /// Get the position of the CRT beam (Direct rendering only)
     fn get_beam_pos(&self) -> Option<(u32, u32)>;
 
+    /// Get the current frame, scanline and dot position of the raster beam.
+    fn get_raster_position(&self) -> RasterPosition;
+
     /// Get the current scanline being rendered.
     fn get_scanline(&self) -> u32;
 
@@ -465,4 +633,12 @@ pub trait VideoCard {
     /// Return a vector of Strings representing the current text on screen. If the adapter is not in
     /// text mode, an empty vector should be returned.
     fn get_text_mode_strings(&self) -> Vec<String>;
+
+    /// Return the characters and attributes of the currently displayed text mode page, or None if
+    /// the adapter is not in a text mode.
+    fn get_text_buffer(&self) -> Option<TextBuffer>;
+
+    /// Return the raw values of the adapter's registers, paired with the register's name as given
+    /// in the adapter's hardware documentation.
+    fn get_register_state(&self) -> Vec<(String, u8)>;
 }
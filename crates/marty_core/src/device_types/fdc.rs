@@ -32,7 +32,11 @@
 use crate::machine_types::FloppyDriveType;
 use fluxfox::prelude::*;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
 
 pub struct DiskFormat {
     pub chs: DiskChs,
@@ -65,6 +69,192 @@ impl TryFrom<FloppyImageType> for StandardFormat {
     }
 }
 
+/// The data rate of the controller's data separator, as selected by the Configuration Control Register.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataRate {
+    Rate250Kbps,
+    Rate300Kbps,
+    Rate500Kbps,
+    Rate1Mbps,
+}
+
+impl DataRate {
+    /// Decode the data rate select bits of a value written to the Configuration Control Register.
+    pub fn from_ccr(byte: u8) -> Self {
+        match byte & 0x03 {
+            0b00 => DataRate::Rate500Kbps,
+            0b01 => DataRate::Rate300Kbps,
+            0b10 => DataRate::Rate250Kbps,
+            _ => DataRate::Rate1Mbps,
+        }
+    }
+
+    /// Return the time in microseconds to transfer one byte at this data rate. FM encoding needs two
+    /// flux transitions per bit where MFM needs one, so it transfers data at half the rate.
+    pub fn byte_period_us(&self, mfm: bool) -> f64 {
+        let period = 8.0 * 1000.0 / self.kbps();
+        if mfm {
+            period
+        }
+        else {
+            period * 2.0
+        }
+    }
+
+    /// Return the data rate in kilobits per second.
+    pub fn kbps(&self) -> f64 {
+        match self {
+            DataRate::Rate250Kbps => 250.0,
+            DataRate::Rate300Kbps => 300.0,
+            DataRate::Rate500Kbps => 500.0,
+            DataRate::Rate1Mbps => 1000.0,
+        }
+    }
+}
+
+impl Display for DataRate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataRate::Rate250Kbps => write!(f, "250Kbps"),
+            DataRate::Rate300Kbps => write!(f, "300Kbps"),
+            DataRate::Rate500Kbps => write!(f, "500Kbps"),
+            DataRate::Rate1Mbps => write!(f, "1Mbps"),
+        }
+    }
+}
+
+/// The reason a drive is unable to access the inserted media.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MediaMismatch {
+    /// The media has more cylinders than the drive can seek to.
+    Cylinders,
+    /// The media was recorded at a density the drive's heads can't read.
+    Density,
+    /// The controller is programmed for a different data rate than the media requires.
+    DataRate(DataRate),
+    /// The command's MFM flag does not match the encoding of the media.
+    Encoding,
+}
+
+impl Display for MediaMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaMismatch::Cylinders => write!(f, "media has more cylinders than the drive supports"),
+            MediaMismatch::Density => write!(f, "media density is not supported by the drive"),
+            MediaMismatch::DataRate(rate) => write!(f, "media requires a data rate of {}", rate),
+            MediaMismatch::Encoding => write!(f, "media encoding does not match the command's MFM flag"),
+        }
+    }
+}
+
+/// The density a track was recorded at.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MediaDensity {
+    /// FM encoded at 125Kbps.
+    Single,
+    #[default]
+    Double,
+    High,
+    Extended,
+}
+
+impl MediaDensity {
+    /// Classify a track by the data rate it was recorded at, in bits per second. fluxfox reports data
+    /// rates as seen by a 300RPM drive, so double density media read in a 1.2M drive is still 250Kbps.
+    pub fn from_bps(bps: u32) -> Self {
+        match bps {
+            0..=187_499 => MediaDensity::Single,
+            187_500..=399_999 => MediaDensity::Double,
+            400_000..=749_999 => MediaDensity::High,
+            _ => MediaDensity::Extended,
+        }
+    }
+}
+
+impl From<TrackDataRate> for MediaDensity {
+    fn from(rate: TrackDataRate) -> Self {
+        MediaDensity::from_bps(u32::from(rate))
+    }
+}
+
+/// Return the data rate a drive of the specified type must use to access a track of the specified
+/// density on media with the specified number of cylinders, or the reason the drive can't access
+/// the media at all.
+///
+/// High density media is always recorded at 500Kbps. A 1.2M drive spins at 360RPM, so it reads
+/// single and double density media at 300Kbps instead of the 250Kbps used by every other drive type.
+pub fn media_data_rate(
+    drive_type: FloppyDriveType,
+    media_cylinders: u16,
+    density: MediaDensity,
+) -> Result<DataRate, MediaMismatch> {
+    // Should be safe to unwrap as we are limited by valid drive type enums.
+    let drive_geom = DRIVE_CAPABILITIES.get(&drive_type).unwrap().chs;
+
+    if media_cylinders > drive_geom.c() {
+        return Err(MediaMismatch::Cylinders);
+    }
+
+    match density {
+        MediaDensity::High if drive_type.is_high_density() => Ok(DataRate::Rate500Kbps),
+        MediaDensity::High | MediaDensity::Extended => Err(MediaMismatch::Density),
+        _ if matches!(drive_type, FloppyDriveType::Floppy12M) => Ok(DataRate::Rate300Kbps),
+        _ => Ok(DataRate::Rate250Kbps),
+    }
+}
+
+/// The mechanical timing characteristics of a floppy drive.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DriveTiming {
+    /// Spindle speed in revolutions per minute.
+    pub rpm: f64,
+    /// The shortest time the drive can step its heads from one cylinder to the next, in milliseconds.
+    pub step_ms: f64,
+    /// Time for the heads to settle after the last step of a seek, in milliseconds.
+    pub head_settle_ms: f64,
+    /// Time for the heads to load onto the media before data can be read, in milliseconds.
+    pub head_load_ms: f64,
+    /// Time for the spindle to come up to speed after the motor is turned on, in milliseconds.
+    pub spin_up_ms: f64,
+}
+
+impl DriveTiming {
+    /// Return the time for one revolution of the media in microseconds.
+    pub fn revolution_us(&self) -> f64 {
+        60_000_000.0 / self.rpm
+    }
+}
+
+/// Return the timing of a drive of the specified type. A 1.2M drive spins at 360RPM, which is why it
+/// reads double density media at 300Kbps; every other type spins at 300RPM. 3.5" drives
+/// load their heads as soon as a disk is inserted, so have no head load delay.
+/// TODO: Values are typical of drive datasheets, not measured.
+pub fn drive_timing(drive_type: FloppyDriveType) -> DriveTiming {
+    match drive_type {
+        FloppyDriveType::Floppy360K => DriveTiming {
+            rpm: 300.0,
+            step_ms: 6.0,
+            head_settle_ms: 15.0,
+            head_load_ms: 35.0,
+            spin_up_ms: 500.0,
+        },
+        FloppyDriveType::Floppy12M => DriveTiming {
+            rpm: 360.0,
+            step_ms: 3.0,
+            head_settle_ms: 15.0,
+            head_load_ms: 35.0,
+            spin_up_ms: 400.0,
+        },
+        FloppyDriveType::Floppy720K | FloppyDriveType::Floppy144M => DriveTiming {
+            rpm: 300.0,
+            step_ms: 3.0,
+            head_settle_ms: 15.0,
+            head_load_ms: 0.0,
+            spin_up_ms: 500.0,
+        },
+    }
+}
+
 lazy_static! {
     /// Define the drive capabilities for each floppy drive type.
     /// Drives can seek a bit beyond the end of the traditional media sizes.
@@ -148,3 +338,104 @@ lazy_static! {
         map
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn media_density_from_data_rate() {
+        assert_eq!(MediaDensity::from_bps(125_000), MediaDensity::Single);
+        assert_eq!(MediaDensity::from_bps(250_000), MediaDensity::Double);
+        assert_eq!(MediaDensity::from_bps(300_000), MediaDensity::Double);
+        assert_eq!(MediaDensity::from_bps(500_000), MediaDensity::High);
+        assert_eq!(MediaDensity::from_bps(1_000_000), MediaDensity::Extended);
+    }
+
+    #[test]
+    fn media_data_rate_depends_on_drive_type() {
+        use MediaDensity::*;
+
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy360K, 40, Double),
+            Ok(DataRate::Rate250Kbps)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy360K, 40, Single),
+            Ok(DataRate::Rate250Kbps)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy360K, 80, Double),
+            Err(MediaMismatch::Cylinders)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy360K, 40, High),
+            Err(MediaMismatch::Density)
+        );
+
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy12M, 40, Double),
+            Ok(DataRate::Rate300Kbps)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy12M, 80, High),
+            Ok(DataRate::Rate500Kbps)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy12M, 80, Extended),
+            Err(MediaMismatch::Density)
+        );
+
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy720K, 80, High),
+            Err(MediaMismatch::Density)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy144M, 80, Double),
+            Ok(DataRate::Rate250Kbps)
+        );
+        assert_eq!(
+            media_data_rate(FloppyDriveType::Floppy144M, 80, High),
+            Ok(DataRate::Rate500Kbps)
+        );
+    }
+
+    #[test]
+    fn hd_media_rejects_dd_data_rate() {
+        let rate = media_data_rate(FloppyDriveType::Floppy144M, 80, MediaDensity::High).unwrap();
+        assert_ne!(rate, DataRate::from_ccr(0x02));
+        assert_eq!(rate, DataRate::from_ccr(0x00));
+    }
+
+    #[test]
+    fn drive_timing_depends_on_drive_type() {
+        assert_eq!(drive_timing(FloppyDriveType::Floppy360K).revolution_us(), 200_000.0);
+        assert!((drive_timing(FloppyDriveType::Floppy12M).revolution_us() - 166_666.67).abs() < 0.01);
+        assert_eq!(drive_timing(FloppyDriveType::Floppy144M).head_load_ms, 0.0);
+
+        // Double density media read at 300Kbps in a 1.2M drive holds as many bytes per track as it
+        // does read at 250Kbps in a 360K drive.
+        let bytes_per_rev =
+            |drive_type, rate: DataRate| drive_timing(drive_type).revolution_us() / rate.byte_period_us(true);
+        assert_eq!(
+            bytes_per_rev(FloppyDriveType::Floppy12M, DataRate::Rate300Kbps).round(),
+            bytes_per_rev(FloppyDriveType::Floppy360K, DataRate::Rate250Kbps).round()
+        );
+    }
+
+    #[test]
+    fn ccr_selects_data_rate() {
+        assert_eq!(DataRate::from_ccr(0x00), DataRate::Rate500Kbps);
+        assert_eq!(DataRate::from_ccr(0x01), DataRate::Rate300Kbps);
+        assert_eq!(DataRate::from_ccr(0x02), DataRate::Rate250Kbps);
+        assert_eq!(DataRate::from_ccr(0xFF), DataRate::Rate1Mbps);
+    }
+
+    #[test]
+    fn fm_halves_data_rate() {
+        assert_eq!(DataRate::Rate250Kbps.byte_period_us(true), 32.0);
+        assert_eq!(DataRate::Rate250Kbps.byte_period_us(false), 64.0);
+        assert_eq!(DataRate::Rate500Kbps.byte_period_us(true), 16.0);
+        assert_eq!(DataRate::Rate500Kbps.byte_period_us(false), 32.0);
+    }
+}
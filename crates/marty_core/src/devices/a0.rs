@@ -38,10 +38,13 @@ pub enum A0Type {
     Tandy1000,
 }
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
     devices::pit::Pit,
+    savestate::SaveState,
 };
 
 pub struct A0Register {
@@ -101,6 +104,44 @@ impl IoDevice for A0Register {
     }
 }
 
+/// Snapshot of the A0 register and the PCjr NMI latch.
+#[derive(Serialize, Deserialize)]
+pub struct A0SaveState {
+    a0_byte: u8,
+    nmi_latch: bool,
+    nmi_enabled: bool,
+    ir_test_ena: bool,
+    clock_1_select: bool,
+    hrq_disable: bool,
+    clear_nmi_latch: bool,
+}
+
+impl SaveState for A0Register {
+    type State = A0SaveState;
+
+    fn save_state(&self) -> A0SaveState {
+        A0SaveState {
+            a0_byte: self.a0_byte,
+            nmi_latch: self.nmi_latch,
+            nmi_enabled: self.nmi_enabled,
+            ir_test_ena: self.ir_test_ena,
+            clock_1_select: self.clock_1_select,
+            hrq_disable: self.hrq_disable,
+            clear_nmi_latch: self.clear_nmi_latch,
+        }
+    }
+
+    fn restore_state(&mut self, state: A0SaveState) {
+        self.a0_byte = state.a0_byte;
+        self.nmi_latch = state.nmi_latch;
+        self.nmi_enabled = state.nmi_enabled;
+        self.ir_test_ena = state.ir_test_ena;
+        self.clock_1_select = state.clock_1_select;
+        self.hrq_disable = state.hrq_disable;
+        self.clear_nmi_latch = state.clear_nmi_latch;
+    }
+}
+
 impl A0Register {
     pub fn new(a0type: A0Type) -> A0Register {
         A0Register {
@@ -37,9 +37,11 @@ use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
     device_traits::sounddevice::{AudioSample, SoundDevice},
+    savestate::SaveState,
 };
 use crossbeam_channel::Sender;
 use opl3_rs::{Opl3Device, OplRegisterFile};
+use serde::{Deserialize, Serialize};
 
 pub struct AdLibCard {
     pub io_base: u16,
@@ -53,6 +55,17 @@ pub struct AdLibCard {
     pub usec_accum_second: f64,
     pub usec_accum: f64,
     pub addr: u8,
+    pub sample_rate: u32,
+    /// The last value written to each OPL register, as the OPL core itself can't be saved.
+    pub regs: [u8; 256],
+}
+
+/// The AdLib register file. Restoring replays the registers into a freshly reset OPL, so notes
+/// that were playing restart their envelopes and running timers restart from their preset.
+#[derive(Serialize, Deserialize)]
+pub struct AdLibSaveState {
+    addr: u8,
+    regs: Vec<u8>,
 }
 
 impl AdLibCard {
@@ -71,6 +84,8 @@ impl AdLibCard {
             usec_accum_second: 0.0,
             usec_accum: 0.0,
             addr: 0,
+            sample_rate,
+            regs: [0; 256],
         }
     }
 }
@@ -101,13 +116,36 @@ impl SoundDevice for AdLibCard {
             for s in self.out_buf.chunks_exact(2) {
                 let samp0 = if s[0] == -1 { 0 } else { s[0] };
                 let samp1 = if s[1] == -1 { 0 } else { s[1] };
-                self.sender.send(samp0 as f32 / i16::MAX as f32).unwrap();
-                self.sender.send(samp1 as f32 / i16::MAX as f32).unwrap();
+                _ = self.sender.send(samp0 as f32 / i16::MAX as f32);
+                _ = self.sender.send(samp1 as f32 / i16::MAX as f32);
             }
         }
     }
 }
 
+impl SaveState for AdLibCard {
+    type State = AdLibSaveState;
+
+    fn save_state(&self) -> AdLibSaveState {
+        AdLibSaveState {
+            addr: self.addr,
+            regs: self.regs.to_vec(),
+        }
+    }
+
+    fn restore_state(&mut self, state: AdLibSaveState) {
+        self.opl3 = Opl3Device::new(self.sample_rate);
+        self.regs = [0; 256];
+        for (addr, data) in state.regs.iter().enumerate().take(self.regs.len()) {
+            _ = self.opl3.write_address(addr as u8, OplRegisterFile::Primary);
+            _ = self.opl3.write_data(*data, OplRegisterFile::Primary, false);
+            self.regs[addr] = *data;
+        }
+        _ = self.opl3.write_address(state.addr, OplRegisterFile::Primary);
+        self.addr = state.addr;
+    }
+}
+
 impl IoDevice for AdLibCard {
     fn read_u8(&mut self, port: u16, _delta: DeviceRunTimeUnit) -> u8 {
         //log::debug!("Read from Adlib port {:04X}", port - self.io_base);
@@ -134,6 +172,7 @@ impl IoDevice for AdLibCard {
             }
             1 => {
                 _ = self.opl3.write_data(data, OplRegisterFile::Primary, false);
+                self.regs[self.addr as usize] = data;
             }
             _ => {}
         }
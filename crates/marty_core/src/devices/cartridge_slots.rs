@@ -28,32 +28,191 @@
 
     Implement the IBM PCJr's cartridge slots.
 
+    The cartridge slots decode the D0000-FFFFF region. Each cartridge image is
+    mapped at the segment given in its image header; addresses not claimed by
+    a cartridge fall through to system memory, so a cartridge at F0000 (such
+    as Cartridge BASIC) overrides the system ROM only where it is populated.
+    A cartridge may contain several ROM chips, each beginning with a ROM module
+    header (55AAh signature followed by a length in 512-byte blocks). The
+    PCjr BIOS only calls a module's init vector if the module passes a CRC
+    check, so we perform the same validation when a cartridge is inserted
+    rather than mapping an image the BIOS would reject.
+
 */
 
+use std::fmt::Display;
+
 use anyhow::{anyhow, Error};
 
-use crate::bus::{MemRangeDescriptor, MemoryMappedDevice};
+use crate::{
+    bus::{MemRangeDescriptor, MemoryMappedDevice},
+    savestate::SaveState,
+};
 
 use marty_common::types::cartridge::CartImage;
+use serde::{Deserialize, Serialize};
 
 pub const CARTRIDGE_SLOT_ADDRESS: usize = 0xD0000;
-pub const CARTRIDGE_SLOT_SIZE: usize = 0x20000;
+pub const CARTRIDGE_SLOT_SIZE: usize = 0x30000;
+
+/// ROM modules are located on 2K boundaries.
+pub const CARTRIDGE_MODULE_ALIGN: usize = 0x800;
+pub const CARTRIDGE_BLOCK_SIZE: usize = 512;
+
+#[derive(Debug, PartialEq)]
+pub enum CartridgeError {
+    InvalidSlot(usize),
+    OutOfRange(usize, usize),
+    NoSignature,
+    BadModuleLength(usize),
+    CrcMismatch(usize, u16),
+}
+impl std::error::Error for CartridgeError {}
+impl Display for CartridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CartridgeError::InvalidSlot(slot) => write!(f, "Invalid cartridge slot: {}", slot),
+            CartridgeError::OutOfRange(address, size) => write!(
+                f,
+                "Cartridge at {:05X}h of size {:X}h lies outside of the cartridge address space",
+                address, size
+            ),
+            CartridgeError::NoSignature => write!(f, "Cartridge image has no ROM module signature"),
+            CartridgeError::BadModuleLength(address) => {
+                write!(f, "ROM module at {:05X}h has an invalid length", address)
+            }
+            CartridgeError::CrcMismatch(address, crc) => {
+                write!(
+                    f,
+                    "ROM module at {:05X}h failed CRC check (residue {:04X})",
+                    address, crc
+                )
+            }
+        }
+    }
+}
+
+/// A validated ROM module within a cartridge image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CartModule {
+    /// Physical address of the module.
+    pub address: usize,
+    /// Length of the module in bytes.
+    pub len: usize,
+    /// Address of the module's init vector, called by the BIOS after the module passes its CRC check.
+    pub init_vector: usize,
+}
+
+/// Calculate the CRC-CCITT of `data` as performed by the PCjr BIOS. A module with its CRC stored in its last two
+/// bytes (high byte first) has a residue of 0.
+pub fn cart_crc(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            }
+            else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Validate a cartridge image, returning the ROM modules it contains. Areas of the image without a module signature
+/// are assumed to be data chips and are mapped but not checked.
+pub fn validate_cart(cart: &CartImage) -> Result<Vec<CartModule>, CartridgeError> {
+    let base = (cart.address_seg as usize) << 4;
+    let image = &cart.image;
+
+    if base < CARTRIDGE_SLOT_ADDRESS || base + image.len() > CARTRIDGE_SLOT_ADDRESS + CARTRIDGE_SLOT_SIZE {
+        return Err(CartridgeError::OutOfRange(base, image.len()));
+    }
+
+    let mut modules = Vec::new();
+    let mut offset = 0;
+    while offset + 3 <= image.len() {
+        if image[offset] != 0x55 || image[offset + 1] != 0xAA {
+            offset += CARTRIDGE_MODULE_ALIGN;
+            continue;
+        }
+
+        let len = image[offset + 2] as usize * CARTRIDGE_BLOCK_SIZE;
+        if len == 0 || offset + len > image.len() {
+            return Err(CartridgeError::BadModuleLength(base + offset));
+        }
+        let crc = cart_crc(&image[offset..offset + len]);
+        if crc != 0 {
+            return Err(CartridgeError::CrcMismatch(base + offset, crc));
+        }
+
+        modules.push(CartModule {
+            address: base + offset,
+            len,
+            init_vector: base + offset + 3,
+        });
+        offset += len.next_multiple_of(CARTRIDGE_MODULE_ALIGN);
+    }
+
+    if modules.is_empty() {
+        return Err(CartridgeError::NoSignature);
+    }
+    Ok(modules)
+}
 
 pub struct CartridgeSlot {
     pub carts: [Option<CartImage>; 2],
+    modules:   [Vec<CartModule>; 2],
+}
+
+/// The cartridges inserted when the state was saved. Cartridges are small, so their images are stored
+/// in the save state and reinserted on restore.
+#[derive(Serialize, Deserialize)]
+pub struct CartridgeSlotSaveState {
+    carts: [Option<CartImage>; 2],
+}
+
+impl SaveState for CartridgeSlot {
+    type State = CartridgeSlotSaveState;
+
+    fn save_state(&self) -> CartridgeSlotSaveState {
+        CartridgeSlotSaveState {
+            carts: self.carts.clone(),
+        }
+    }
+
+    fn restore_state(&mut self, state: CartridgeSlotSaveState) {
+        for (slot, cart) in state.carts.into_iter().enumerate() {
+            self.remove_cart(slot);
+            if let Some(cart) = cart {
+                if let Err(e) = self.insert_cart(slot, cart) {
+                    log::error!("CartridgeSlot: failed to restore cartridge in slot {}: {}", slot, e);
+                }
+            }
+        }
+    }
 }
 
 impl CartridgeSlot {
     pub fn new() -> Self {
-        CartridgeSlot { carts: [None, None] }
+        CartridgeSlot {
+            carts:   [None, None],
+            modules: [Vec::new(), Vec::new()],
+        }
     }
 
+    /// Insert a cartridge into the specified slot. The image is validated first, and is not mapped if validation
+    /// fails.
     pub fn insert_cart(&mut self, slot: usize, cart: CartImage) -> Result<(), Error> {
         if slot > 1 {
-            return Err(anyhow!("Invalid cartridge slot"));
+            return Err(anyhow!(CartridgeError::InvalidSlot(slot)));
         }
 
-        log::error!(
+        let modules = validate_cart(&cart).map_err(|e| anyhow!(e))?;
+
+        log::debug!(
             "Loaded cartridge into slot {}. Segment: {:04X} Mask: {:04X} Size: {} Comment: {}",
             slot,
             cart.address_seg,
@@ -61,13 +220,55 @@ impl CartridgeSlot {
             cart.image.len(),
             cart.comment
         );
+        for module in modules.iter() {
+            log::debug!(
+                "Cartridge ROM module at {:05X}h, length {:X}h, init vector {:05X}h",
+                module.address,
+                module.len,
+                module.init_vector
+            );
+        }
 
         self.carts[slot] = Some(cart);
+        self.modules[slot] = modules;
         Ok(())
     }
 
     pub fn remove_cart(&mut self, slot: usize) {
         self.carts[slot] = None;
+        self.modules[slot].clear();
+    }
+
+    /// Return the validated ROM modules of the cartridge in the specified slot.
+    pub fn modules(&self, slot: usize) -> &[CartModule] {
+        &self.modules[slot]
+    }
+
+    /// Return the byte of whichever cartridge maps the specified address, if any.
+    #[inline]
+    fn cart_byte(&self, address: usize) -> Option<u8> {
+        self.carts.iter().flatten().find_map(|cart| {
+            let cart_address = (cart.address_seg as usize) << 4;
+            address
+                .checked_sub(cart_address)
+                .and_then(|offset| cart.image.get(offset).copied())
+        })
+    }
+
+    /// Return whether an inserted cartridge maps the specified address. Accesses to other addresses in the
+    /// cartridge window are left to system memory.
+    #[inline]
+    pub fn maps(&self, address: usize) -> bool {
+        self.cart_byte(address).is_some()
+    }
+
+    /// Read a byte from whichever cartridge maps the specified address, or from system memory if no cartridge
+    /// does.
+    #[inline]
+    fn read_byte(&self, address: usize, cpumem: Option<&[u8]>) -> u8 {
+        self.cart_byte(address)
+            .or_else(|| cpumem.and_then(|mem| mem.get(address).copied()))
+            .unwrap_or(0xFF)
     }
 }
 
@@ -76,41 +277,20 @@ impl MemoryMappedDevice for CartridgeSlot {
         0
     }
 
-    fn mmio_read_u8(&mut self, address: usize, _cycles: u32, _cpumem: Option<&[u8]>) -> (u8, u32) {
-        //log::debug!("Cartridge slot address space read at {:X}", address);
-
-        for cart in self.carts.iter() {
-            if let Some(cart) = cart {
-                let cart_address = (cart.address_seg as usize) << 4;
-
-                let _masked_address = address & !(cart.address_mask as usize);
-                if (address >= cart_address) && (address < (cart_address + cart.image.len())) {
-                    //log::debug!("Cartridge read at {:X}", address);
-                    return (cart.image[address - cart_address], 0);
-                }
-            }
-        }
-        (0xFF, 0)
+    fn mmio_read_u8(&mut self, address: usize, _cycles: u32, cpumem: Option<&[u8]>) -> (u8, u32) {
+        (self.read_byte(address, cpumem), 0)
     }
 
-    fn mmio_read_u16(&mut self, _address: usize, _cycles: u32, _cpumem: Option<&[u8]>) -> (u16, u32) {
-        (0xFFFF, 0)
+    fn mmio_read_u16(&mut self, address: usize, _cycles: u32, cpumem: Option<&[u8]>) -> (u16, u32) {
+        (self.mmio_peek_u16(address, cpumem), 0)
     }
 
-    fn mmio_peek_u8(&self, address: usize, _cpumem: Option<&[u8]>) -> u8 {
-        for cart in self.carts.iter() {
-            if let Some(cart) = cart {
-                let cart_address = (cart.address_seg as usize) << 4;
-                if address >= cart_address && address < (cart_address + cart.image.len()) {
-                    return cart.image[address - cart_address];
-                }
-            }
-        }
-        0xFF
+    fn mmio_peek_u8(&self, address: usize, cpumem: Option<&[u8]>) -> u8 {
+        self.read_byte(address, cpumem)
     }
 
-    fn mmio_peek_u16(&self, _address: usize, _cpumem: Option<&[u8]>) -> u16 {
-        0xFFFF
+    fn mmio_peek_u16(&self, address: usize, cpumem: Option<&[u8]>) -> u16 {
+        self.read_byte(address, cpumem) as u16 | (self.read_byte(address + 1, cpumem) as u16) << 8
     }
 
     fn get_write_wait(&mut self, _address: usize, _cycles: u32) -> u32 {
@@ -139,3 +319,103 @@ impl MemoryMappedDevice for CartridgeSlot {
         mapping
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a cartridge image with a single 8K ROM module with a valid CRC, at segment E000.
+    fn make_cart() -> CartImage {
+        let len = 0x2000;
+        let mut image = vec![0u8; len];
+        image[0] = 0x55;
+        image[1] = 0xAA;
+        image[2] = (len / CARTRIDGE_BLOCK_SIZE) as u8;
+        // Init vector: RETF
+        image[3] = 0xCB;
+        for (i, byte) in image[4..len - 2].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let crc = cart_crc(&image[0..len - 2]);
+        image[len - 2] = (crc >> 8) as u8;
+        image[len - 1] = (crc & 0xFF) as u8;
+
+        CartImage {
+            creator: String::new(),
+            comment: String::new(),
+            version_major: 1,
+            version_minor: 0,
+            address_seg: 0xE000,
+            address_mask: 0,
+            image,
+        }
+    }
+
+    #[test]
+    fn cart_validation() {
+        let cart = make_cart();
+        assert_eq!(
+            validate_cart(&cart),
+            Ok(vec![CartModule {
+                address: 0xE0000,
+                len: 0x2000,
+                init_vector: 0xE0003,
+            }])
+        );
+
+        let mut bad_crc = make_cart();
+        bad_crc.image[0x100] ^= 0x01;
+        assert!(matches!(
+            validate_cart(&bad_crc),
+            Err(CartridgeError::CrcMismatch(0xE0000, _))
+        ));
+
+        let mut no_sig = make_cart();
+        no_sig.image[0] = 0;
+        assert_eq!(validate_cart(&no_sig), Err(CartridgeError::NoSignature));
+
+        let mut out_of_range = make_cart();
+        out_of_range.address_seg = 0xC000;
+        assert!(matches!(
+            validate_cart(&out_of_range),
+            Err(CartridgeError::OutOfRange(0xC0000, _))
+        ));
+
+        // A cartridge may extend to the top of the address space, but not past it.
+        let mut top = make_cart();
+        top.address_seg = 0xFE00;
+        assert!(validate_cart(&top).is_ok());
+        top.address_seg = 0xFF00;
+        assert!(matches!(
+            validate_cart(&top),
+            Err(CartridgeError::OutOfRange(0xFF000, _))
+        ));
+    }
+
+    #[test]
+    fn cart_mapping() {
+        let mut slot = CartridgeSlot::new();
+        let mut bad_cart = make_cart();
+        bad_cart.image[2] = 0;
+        assert!(slot.insert_cart(0, bad_cart).is_err());
+        assert_eq!(slot.mmio_peek_u8(0xE0000, None), 0xFF);
+
+        slot.insert_cart(1, make_cart()).unwrap();
+        assert_eq!(slot.modules(1).len(), 1);
+        assert_eq!(slot.mmio_peek_u16(0xE0000, None), 0xAA55);
+        assert_eq!(slot.mmio_read_u8(0xE0003, 0, None).0, 0xCB);
+        assert_eq!(slot.mmio_peek_u8(0xE2000, None), 0xFF);
+
+        slot.remove_cart(1);
+        assert_eq!(slot.mmio_peek_u8(0xE0000, None), 0xFF);
+
+        // Unmapped addresses read from system memory, if provided.
+        let mut mem = vec![0u8; 0x100000];
+        mem[0xF0000] = 0x12;
+        slot.insert_cart(0, make_cart()).unwrap();
+        assert!(slot.maps(0xE1FFF));
+        assert!(!slot.maps(0xF0000));
+        assert_eq!(slot.mmio_peek_u8(0xF0000, Some(&mem)), 0x12);
+        assert_eq!(slot.mmio_peek_u8(0xE0000, Some(&mem)), 0x55);
+    }
+}
@@ -113,9 +113,11 @@ impl IoDevice for CGACard {
         else {
             match port {
                 CGA_MODE_CONTROL_REGISTER => {
+                    self.latch_register_change(port, data);
                     self.handle_mode_register(data);
                 }
                 CGA_COLOR_CONTROL_REGISTER => {
+                    self.latch_register_change(port, data);
                     self.handle_cc_register_write(data);
                 }
                 CGA_LIGHTPEN_LATCH_RESET => self.clear_lp_latch(),
@@ -33,6 +33,29 @@
 use super::*;
 use crate::bus::{MemRangeDescriptor, MemoryMappedDevice};
 
+impl CGACard {
+    /// Return whether a CPU access to VRAM right now would produce snow. The CRTC only fetches
+    /// from VRAM during active display, so accesses during retrace or the border are clean.
+    #[inline]
+    fn snow_access(&self) -> bool {
+        self.enable_snow && self.in_display_area
+    }
+
+    /// Return the additional wait states incurred by a CPU access to VRAM contending with the
+    /// CRTC during active display. Contention is a timing effect of the hardware and applies
+    /// whether or not snow emulation is enabled.
+    #[inline]
+    fn contention_waits(&mut self) -> u32 {
+        if self.in_display_area {
+            self.contended_accesses += 1;
+            CONTENTION_WAITS
+        }
+        else {
+            0
+        }
+    }
+}
+
 /// Unlike the EGA or VGA the CGA doesn't do any operations on video memory on read/write,
 /// but we handle the mirroring of VRAM this way, and for consistency with other devices
 impl MemoryMappedDevice for CGACard {
@@ -40,7 +63,7 @@ impl MemoryMappedDevice for CGACard {
         // Look up wait states given the last ticked clock cycle + elapsed cycles
         // passed in.
         let phase = (self.cycles + cycles as u64 + 1) as usize & (0x0F as usize);
-        let waits = WAIT_TABLE[phase];
+        let waits = WAIT_TABLE[phase] + self.contention_waits();
 
         trace!(self, "READ_U8 (T2): PHASE: {:02X}, WAITS: {}", phase, waits);
         waits
@@ -50,34 +73,23 @@ impl MemoryMappedDevice for CGACard {
         // Look up wait states given the last ticked clock cycle + elapsed cycles
         // passed in.
         let phase = (self.cycles + cycles as u64 + 1) as usize & (0x0F as usize);
-        let waits = WAIT_TABLE[phase];
+        let waits = WAIT_TABLE[phase] + self.contention_waits();
 
         trace!(self, "WRITE_U8 (T2): PHASE: {:02X}, WAITS: {}", phase, waits);
         waits
     }
 
-    fn mmio_read_u8(&mut self, address: usize, _cycles: u32, _cpumem: Option<&[u8]>) -> (u8, u32) {
-        /*
-        if self.enable_snow {
-            // Catch up to CPU state.
-
-            // TODO: We need to pass converted ticks from the CPU here, in case the frequency is different
-            self.catch_up(DeviceRunTimeUnit::SystemTicks(cycles * 3));
-        }*/
-
+    fn mmio_read_u8(&mut self, address: usize, cycles: u32, _cpumem: Option<&[u8]>) -> (u8, u32) {
         let a_offset = (address & CGA_MEM_MASK) - CGA_MEM_ADDRESS;
         if a_offset < CGA_MEM_SIZE {
-            // Do snow every other hchar
-            if self.cycles & 0b1000 == 0 {
-                // Save bus parameters for snow emulation
+            if self.snow_access() {
+                // Save bus parameters for snow emulation. The access happens 'cycles' system ticks
+                // ahead of our last ticked cycle, so record when it lands.
                 self.last_bus_addr = a_offset;
                 self.last_bus_value = self.mem[a_offset] ^ 0xAA; // this becomes the char attribute
+                self.snow_char = self.mem[a_offset]; // this becomes the character glyph
+                self.snow_tick = self.cycles + cycles as u64;
                 self.dirty_snow = true;
-                self.snow_char = self.mem[a_offset]; // 0xDD; // this becomes the character glyph
-
-            //log::debug!("snow attr: {:08b}", self.mem[a_offset]);
-            }
-            else {
             }
 
             trace!(self, "READ_U8: {:04X}:{:02X}", a_offset, self.mem[a_offset],);
@@ -101,14 +113,17 @@ impl MemoryMappedDevice for CGACard {
         (self.mem[a_offset] as u16) << 8 | self.mem[a_offset + 1] as u16
     }
 
-    fn mmio_write_u8(&mut self, address: usize, byte: u8, _cycles: u32, _cpumem: Option<&mut [u8]>) -> u32 {
+    fn mmio_write_u8(&mut self, address: usize, byte: u8, cycles: u32, _cpumem: Option<&mut [u8]>) -> u32 {
         let a_offset = (address & CGA_MEM_MASK) - CGA_MEM_ADDRESS;
         if a_offset < CGA_MEM_SIZE {
-            // Save bus parameters for snow emulation
-            self.last_bus_addr = a_offset;
-            self.last_bus_value = byte;
-            self.dirty_snow = true;
-            self.snow_char = self.mem[a_offset];
+            if self.snow_access() {
+                // Save bus parameters for snow emulation
+                self.last_bus_addr = a_offset;
+                self.last_bus_value = byte;
+                self.snow_char = self.mem[a_offset];
+                self.snow_tick = self.cycles + cycles as u64;
+                self.dirty_snow = true;
+            }
 
             self.mem[a_offset] = byte;
 
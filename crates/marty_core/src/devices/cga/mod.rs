@@ -47,13 +47,17 @@ mod mmio;
 mod tablegen;
 mod videocard;
 
+pub use io::{CGA_COLOR_CONTROL_REGISTER, CGA_MODE_CONTROL_REGISTER};
+
 use super::*;
 
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit},
     device_traits::videocard::*,
+    savestate::SaveState,
     tracelogger::TraceLogger,
 };
+use serde::{Deserialize, Serialize};
 
 #[derive(Copy, Clone)]
 enum RwSlotType {
@@ -87,6 +91,11 @@ static DUMMY_PIXEL: [u8; 4] = [0, 0, 0, 0];
 static WAIT_TABLE: [u32; 16] = [14, 13, 12, 11, 10, 9, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15];
 // in cpu cycles: 5,5,4,4,4,3,8,8,8,7,7,7,6,6,6,5
 
+// Additional wait in system ticks for a CPU access during active display. The CGA must let the
+// CRTC's character fetch for the current cell complete before it can service the CPU, delaying it
+// by one hires character clock.
+const CONTENTION_WAITS: u32 = CGA_HCHAR_CLOCK as u32;
+
 pub const CGA_MEM_ADDRESS: usize = 0xB8000;
 // CGA memory is repeated twice due to incomplete address decoding.
 pub const CGA_MEM_APERTURE: usize = 0x8000;
@@ -94,6 +103,13 @@ pub const CGA_MEM_SIZE: usize = 0x4000; // 16384 bytes
 pub const CGA_MEM_MASK: usize = !0x4000; // Applying this mask will implement memory mirror.
 
 pub const CGA_MODE_ENABLE_MASK: u8 = 0b1_0111;
+// Mode register bits selecting 640 pixel wide, 1 bit per pixel graphics with the color burst enabled,
+// which produces artifact colors on a composite monitor.
+pub const CGA_MODE_HIRES_COLOR_GRAPHICS_MASK: u8 = 0b1_0110;
+pub const CGA_MODE_HIRES_COLOR_GRAPHICS: u8 = 0b1_0010;
+
+// Upper bound on latched register writes per frame, in case a program never lets the CRTC vsync.
+const CGA_MAX_REGISTER_CHANGES: usize = 8192;
 
 // Sensible defaults for CRTC registers. A real CRTC is probably uninitialized.
 // 4/5/2023: Changed these values to 40 column mode.
@@ -151,6 +167,8 @@ const CGA_MONITOR_HSYNC_WIDTH: u32 = 80;
 const CGA_MONITOR_VSYNC_POS: u32 = 246;
 // Minimum scanline value after which we can perform a vsync. A vsync before this scanline will be ignored.
 const CGA_MONITOR_VSYNC_MIN: u32 = 127;
+// Frames longer than this are unlikely to be displayed by a real monitor.
+const CGA_MAX_FRAME_CLOCKS: u64 = 300000;
 
 // For derivation of CGA timings, see https://www.vogons.org/viewtopic.php?t=47052
 // We run the CGA card independent of the CPU frequency.
@@ -422,6 +440,8 @@ pub struct CGACard {
     last_vsync_cycles: u64,
     cur_screen_cycles: u64,
     cycles_per_vsync: u64,
+    last_frame_cycles: u64,
+    cycles_per_frame: u64,
     sink_cycles: u32,
     catching_up: bool,
 
@@ -432,13 +452,17 @@ pub struct CGACard {
     enable_snow: bool,
     dirty_snow: bool,
     snow_char: u8,
+    snow_tick: u64,
     last_bus_value: u8,
     last_bus_addr: usize,
     snow_count: u64,
+    contended_accesses: u64,
 
     mode_pending: bool,
     clock_pending: bool,
     mode_byte: u8,
+    frame_mode_byte: u8, // Mode byte in effect at the start of the current frame
+    register_changes: Vec<RasterRegisterChange>, // Mode and color register writes made this frame
     display_mode: DisplayMode,
     mode_enable: bool,
     mode_graphics: bool,
@@ -562,7 +586,130 @@ pub struct CGACard {
     out_of_sync: bool,
 }
 
-#[derive(Debug)]
+/// Serializable state of the CGA card, including video memory and the raster position. The frame
+/// buffers are not included and are redrawn from the next frame.
+#[derive(Serialize, Deserialize)]
+pub struct CgaSaveState {
+    cycles: u64,
+    last_vsync_cycles: u64,
+    cur_screen_cycles: u64,
+    cycles_per_vsync: u64,
+    last_frame_cycles: u64,
+    cycles_per_frame: u64,
+    sink_cycles: u32,
+    catching_up: bool,
+    last_rw_tick: u32,
+    slot_idx: usize,
+    dirty_snow: bool,
+    snow_char: u8,
+    snow_tick: u64,
+    last_bus_value: u8,
+    last_bus_addr: usize,
+    snow_count: u64,
+    contended_accesses: u64,
+    mode_pending: bool,
+    clock_pending: bool,
+    mode_byte: u8,
+    frame_mode_byte: u8,
+    register_changes: Vec<RasterRegisterChange>,
+    display_mode: DisplayMode,
+    mode_enable: bool,
+    mode_graphics: bool,
+    mode_bw: bool,
+    mode_hires_gfx: bool,
+    mode_hires_txt: bool,
+    mode_blinking: bool,
+    cc_palette: usize,
+    cc_altcolor: u8,
+    cc_overscan_color: u8,
+    scanline_us: f64,
+    frame_us: f64,
+    cursor_frames: u32,
+    frame_count: u64,
+    status_reads: u64,
+    cursor_status: bool,
+    cursor_slowblink: bool,
+    cursor_blink_rate: f64,
+    cursor_data: [bool; CGA_CURSOR_MAX],
+    cursor_attr: u8,
+    crtc_register_select_byte: u8,
+    crtc_register_selected: CRTCRegister,
+    crtc_horizontal_total: u8,
+    crtc_horizontal_displayed: u8,
+    crtc_horizontal_sync_pos: u8,
+    crtc_sync_width: u8,
+    crtc_vertical_total: u8,
+    crtc_vertical_total_adjust: u8,
+    crtc_vertical_displayed: u8,
+    crtc_vertical_sync_pos: u8,
+    crtc_interlace_mode: u8,
+    crtc_maximum_scanline_address: u8,
+    crtc_cursor_start_line: u8,
+    crtc_cursor_end_line: u8,
+    crtc_start_address: usize,
+    crtc_start_address_ho: u8,
+    crtc_start_address_lo: u8,
+    crtc_cursor_address_lo: u8,
+    crtc_cursor_address_ho: u8,
+    crtc_cursor_address: usize,
+    crtc_frame_address: usize,
+    in_crtc_hblank: bool,
+    in_crtc_vblank: bool,
+    in_crtc_vsync: bool,
+    in_last_vblank_line: bool,
+    hborder: bool,
+    vborder: bool,
+    cc_register: u8,
+    clock_divisor: u8,
+    char_clock: u32,
+    char_clock_mask: u64,
+    char_clock_odd_mask: u64,
+    beam_x: u32,
+    beam_y: u32,
+    in_monitor_hsync: bool,
+    in_monitor_vblank: bool,
+    monitor_hsc: u32,
+    scanline: u32,
+    missed_hsyncs: u32,
+    overscan_left: u32,
+    overscan_right_start: u32,
+    overscan_right: u32,
+    vsync_len: u32,
+    in_display_area: bool,
+    cur_char: u8,
+    cur_attr: u8,
+    cur_fg: u8,
+    cur_bg: u8,
+    cur_blink: bool,
+    char_col: u8,
+    hcc_c0: u8,
+    vlc_c9: u8,
+    vcc_c4: u8,
+    last_row: bool,
+    last_line: bool,
+    vsc_c3h: u8,
+    hsc_c3l: u8,
+    vtac_c5: u8,
+    in_vta: bool,
+    effective_vta: u8,
+    vma: usize,
+    vma_t: usize,
+    rba: usize,
+    blink_state: bool,
+    blink_accum_us: f64,
+    blink_accum_clocks: u32,
+    accumulated_us: f64,
+    ticks_advanced: u32,
+    pixel_clocks_owed: u32,
+    ticks_accum: u32,
+    clocks_accum: u32,
+    mem: Vec<u8>,
+    lightpen_latch: bool,
+    lightpen_addr: usize,
+    out_of_sync: bool,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum CRTCRegister {
     HorizontalTotal,
     HorizontalDisplayed,
@@ -599,6 +746,7 @@ impl CgaDefault for DisplayExtents {
             row_stride: CGA_XRES_MAX as usize,
             double_scan: true,
             mode_byte: 0,
+            register_changes: Vec::new(),
         }
     }
 }
@@ -612,6 +760,8 @@ impl Default for CGACard {
             last_vsync_cycles: 0,
             cur_screen_cycles: 0,
             cycles_per_vsync: 0,
+            last_frame_cycles: 0,
+            cycles_per_frame: 0,
             sink_cycles: 0,
             catching_up: false,
 
@@ -620,13 +770,17 @@ impl Default for CGACard {
             slot_idx: 0,
 
             enable_snow: false,
-            dirty_snow: true,
+            dirty_snow: false,
             snow_char: 0,
+            snow_tick: 0,
             last_bus_value: 0,
             last_bus_addr: 0,
             snow_count: 0,
+            contended_accesses: 0,
 
             mode_byte: 0,
+            frame_mode_byte: 0,
+            register_changes: Vec::new(),
             mode_pending: false,
             clock_pending: false,
             display_mode: DisplayMode::Mode0TextBw40,
@@ -762,6 +916,257 @@ impl Default for CGACard {
     }
 }
 
+impl SaveState for CGACard {
+    type State = CgaSaveState;
+
+    fn save_state(&self) -> CgaSaveState {
+        CgaSaveState {
+            cycles: self.cycles,
+            last_vsync_cycles: self.last_vsync_cycles,
+            cur_screen_cycles: self.cur_screen_cycles,
+            cycles_per_vsync: self.cycles_per_vsync,
+            last_frame_cycles: self.last_frame_cycles,
+            cycles_per_frame: self.cycles_per_frame,
+            sink_cycles: self.sink_cycles,
+            catching_up: self.catching_up,
+            last_rw_tick: self.last_rw_tick,
+            slot_idx: self.slot_idx,
+            dirty_snow: self.dirty_snow,
+            snow_char: self.snow_char,
+            snow_tick: self.snow_tick,
+            last_bus_value: self.last_bus_value,
+            last_bus_addr: self.last_bus_addr,
+            snow_count: self.snow_count,
+            contended_accesses: self.contended_accesses,
+            mode_pending: self.mode_pending,
+            clock_pending: self.clock_pending,
+            mode_byte: self.mode_byte,
+            frame_mode_byte: self.frame_mode_byte,
+            register_changes: self.register_changes.clone(),
+            display_mode: self.display_mode,
+            mode_enable: self.mode_enable,
+            mode_graphics: self.mode_graphics,
+            mode_bw: self.mode_bw,
+            mode_hires_gfx: self.mode_hires_gfx,
+            mode_hires_txt: self.mode_hires_txt,
+            mode_blinking: self.mode_blinking,
+            cc_palette: self.cc_palette,
+            cc_altcolor: self.cc_altcolor,
+            cc_overscan_color: self.cc_overscan_color,
+            scanline_us: self.scanline_us,
+            frame_us: self.frame_us,
+            cursor_frames: self.cursor_frames,
+            frame_count: self.frame_count,
+            status_reads: self.status_reads,
+            cursor_status: self.cursor_status,
+            cursor_slowblink: self.cursor_slowblink,
+            cursor_blink_rate: self.cursor_blink_rate,
+            cursor_data: self.cursor_data,
+            cursor_attr: self.cursor_attr,
+            crtc_register_select_byte: self.crtc_register_select_byte,
+            crtc_register_selected: self.crtc_register_selected,
+            crtc_horizontal_total: self.crtc_horizontal_total,
+            crtc_horizontal_displayed: self.crtc_horizontal_displayed,
+            crtc_horizontal_sync_pos: self.crtc_horizontal_sync_pos,
+            crtc_sync_width: self.crtc_sync_width,
+            crtc_vertical_total: self.crtc_vertical_total,
+            crtc_vertical_total_adjust: self.crtc_vertical_total_adjust,
+            crtc_vertical_displayed: self.crtc_vertical_displayed,
+            crtc_vertical_sync_pos: self.crtc_vertical_sync_pos,
+            crtc_interlace_mode: self.crtc_interlace_mode,
+            crtc_maximum_scanline_address: self.crtc_maximum_scanline_address,
+            crtc_cursor_start_line: self.crtc_cursor_start_line,
+            crtc_cursor_end_line: self.crtc_cursor_end_line,
+            crtc_start_address: self.crtc_start_address,
+            crtc_start_address_ho: self.crtc_start_address_ho,
+            crtc_start_address_lo: self.crtc_start_address_lo,
+            crtc_cursor_address_lo: self.crtc_cursor_address_lo,
+            crtc_cursor_address_ho: self.crtc_cursor_address_ho,
+            crtc_cursor_address: self.crtc_cursor_address,
+            crtc_frame_address: self.crtc_frame_address,
+            in_crtc_hblank: self.in_crtc_hblank,
+            in_crtc_vblank: self.in_crtc_vblank,
+            in_crtc_vsync: self.in_crtc_vsync,
+            in_last_vblank_line: self.in_last_vblank_line,
+            hborder: self.hborder,
+            vborder: self.vborder,
+            cc_register: self.cc_register,
+            clock_divisor: self.clock_divisor,
+            char_clock: self.char_clock,
+            char_clock_mask: self.char_clock_mask,
+            char_clock_odd_mask: self.char_clock_odd_mask,
+            beam_x: self.beam_x,
+            beam_y: self.beam_y,
+            in_monitor_hsync: self.in_monitor_hsync,
+            in_monitor_vblank: self.in_monitor_vblank,
+            monitor_hsc: self.monitor_hsc,
+            scanline: self.scanline,
+            missed_hsyncs: self.missed_hsyncs,
+            overscan_left: self.overscan_left,
+            overscan_right_start: self.overscan_right_start,
+            overscan_right: self.overscan_right,
+            vsync_len: self.vsync_len,
+            in_display_area: self.in_display_area,
+            cur_char: self.cur_char,
+            cur_attr: self.cur_attr,
+            cur_fg: self.cur_fg,
+            cur_bg: self.cur_bg,
+            cur_blink: self.cur_blink,
+            char_col: self.char_col,
+            hcc_c0: self.hcc_c0,
+            vlc_c9: self.vlc_c9,
+            vcc_c4: self.vcc_c4,
+            last_row: self.last_row,
+            last_line: self.last_line,
+            vsc_c3h: self.vsc_c3h,
+            hsc_c3l: self.hsc_c3l,
+            vtac_c5: self.vtac_c5,
+            in_vta: self.in_vta,
+            effective_vta: self.effective_vta,
+            vma: self.vma,
+            vma_t: self.vma_t,
+            rba: self.rba,
+            blink_state: self.blink_state,
+            blink_accum_us: self.blink_accum_us,
+            blink_accum_clocks: self.blink_accum_clocks,
+            accumulated_us: self.accumulated_us,
+            ticks_advanced: self.ticks_advanced,
+            pixel_clocks_owed: self.pixel_clocks_owed,
+            ticks_accum: self.ticks_accum,
+            clocks_accum: self.clocks_accum,
+            mem: self.mem.to_vec(),
+            lightpen_latch: self.lightpen_latch,
+            lightpen_addr: self.lightpen_addr,
+            out_of_sync: self.out_of_sync,
+        }
+    }
+
+    fn restore_state(&mut self, state: CgaSaveState) {
+        self.cycles = state.cycles;
+        self.last_vsync_cycles = state.last_vsync_cycles;
+        self.cur_screen_cycles = state.cur_screen_cycles;
+        self.cycles_per_vsync = state.cycles_per_vsync;
+        self.last_frame_cycles = state.last_frame_cycles;
+        self.cycles_per_frame = state.cycles_per_frame;
+        self.sink_cycles = state.sink_cycles;
+        self.catching_up = state.catching_up;
+        self.last_rw_tick = state.last_rw_tick;
+        self.slot_idx = state.slot_idx;
+        self.dirty_snow = state.dirty_snow;
+        self.snow_char = state.snow_char;
+        self.snow_tick = state.snow_tick;
+        self.last_bus_value = state.last_bus_value;
+        self.last_bus_addr = state.last_bus_addr;
+        self.snow_count = state.snow_count;
+        self.contended_accesses = state.contended_accesses;
+        self.mode_pending = state.mode_pending;
+        self.clock_pending = state.clock_pending;
+        self.mode_byte = state.mode_byte;
+        self.frame_mode_byte = state.frame_mode_byte;
+        self.register_changes = state.register_changes;
+        self.display_mode = state.display_mode;
+        self.mode_enable = state.mode_enable;
+        self.mode_graphics = state.mode_graphics;
+        self.mode_bw = state.mode_bw;
+        self.mode_hires_gfx = state.mode_hires_gfx;
+        self.mode_hires_txt = state.mode_hires_txt;
+        self.mode_blinking = state.mode_blinking;
+        self.cc_palette = state.cc_palette;
+        self.cc_altcolor = state.cc_altcolor;
+        self.cc_overscan_color = state.cc_overscan_color;
+        self.scanline_us = state.scanline_us;
+        self.frame_us = state.frame_us;
+        self.cursor_frames = state.cursor_frames;
+        self.frame_count = state.frame_count;
+        self.status_reads = state.status_reads;
+        self.cursor_status = state.cursor_status;
+        self.cursor_slowblink = state.cursor_slowblink;
+        self.cursor_blink_rate = state.cursor_blink_rate;
+        self.cursor_data = state.cursor_data;
+        self.cursor_attr = state.cursor_attr;
+        self.crtc_register_select_byte = state.crtc_register_select_byte;
+        self.crtc_register_selected = state.crtc_register_selected;
+        self.crtc_horizontal_total = state.crtc_horizontal_total;
+        self.crtc_horizontal_displayed = state.crtc_horizontal_displayed;
+        self.crtc_horizontal_sync_pos = state.crtc_horizontal_sync_pos;
+        self.crtc_sync_width = state.crtc_sync_width;
+        self.crtc_vertical_total = state.crtc_vertical_total;
+        self.crtc_vertical_total_adjust = state.crtc_vertical_total_adjust;
+        self.crtc_vertical_displayed = state.crtc_vertical_displayed;
+        self.crtc_vertical_sync_pos = state.crtc_vertical_sync_pos;
+        self.crtc_interlace_mode = state.crtc_interlace_mode;
+        self.crtc_maximum_scanline_address = state.crtc_maximum_scanline_address;
+        self.crtc_cursor_start_line = state.crtc_cursor_start_line;
+        self.crtc_cursor_end_line = state.crtc_cursor_end_line;
+        self.crtc_start_address = state.crtc_start_address;
+        self.crtc_start_address_ho = state.crtc_start_address_ho;
+        self.crtc_start_address_lo = state.crtc_start_address_lo;
+        self.crtc_cursor_address_lo = state.crtc_cursor_address_lo;
+        self.crtc_cursor_address_ho = state.crtc_cursor_address_ho;
+        self.crtc_cursor_address = state.crtc_cursor_address;
+        self.crtc_frame_address = state.crtc_frame_address;
+        self.in_crtc_hblank = state.in_crtc_hblank;
+        self.in_crtc_vblank = state.in_crtc_vblank;
+        self.in_crtc_vsync = state.in_crtc_vsync;
+        self.in_last_vblank_line = state.in_last_vblank_line;
+        self.hborder = state.hborder;
+        self.vborder = state.vborder;
+        self.cc_register = state.cc_register;
+        self.clock_divisor = state.clock_divisor;
+        self.char_clock = state.char_clock;
+        self.char_clock_mask = state.char_clock_mask;
+        self.char_clock_odd_mask = state.char_clock_odd_mask;
+        self.beam_x = state.beam_x;
+        self.beam_y = state.beam_y;
+        self.in_monitor_hsync = state.in_monitor_hsync;
+        self.in_monitor_vblank = state.in_monitor_vblank;
+        self.monitor_hsc = state.monitor_hsc;
+        self.scanline = state.scanline;
+        self.missed_hsyncs = state.missed_hsyncs;
+        self.overscan_left = state.overscan_left;
+        self.overscan_right_start = state.overscan_right_start;
+        self.overscan_right = state.overscan_right;
+        self.vsync_len = state.vsync_len;
+        self.in_display_area = state.in_display_area;
+        self.cur_char = state.cur_char;
+        self.cur_attr = state.cur_attr;
+        self.cur_fg = state.cur_fg;
+        self.cur_bg = state.cur_bg;
+        self.cur_blink = state.cur_blink;
+        self.char_col = state.char_col;
+        self.hcc_c0 = state.hcc_c0;
+        self.vlc_c9 = state.vlc_c9;
+        self.vcc_c4 = state.vcc_c4;
+        self.last_row = state.last_row;
+        self.last_line = state.last_line;
+        self.vsc_c3h = state.vsc_c3h;
+        self.hsc_c3l = state.hsc_c3l;
+        self.vtac_c5 = state.vtac_c5;
+        self.in_vta = state.in_vta;
+        self.effective_vta = state.effective_vta;
+        self.vma = state.vma;
+        self.vma_t = state.vma_t;
+        self.rba = state.rba;
+        self.blink_state = state.blink_state;
+        self.blink_accum_us = state.blink_accum_us;
+        self.blink_accum_clocks = state.blink_accum_clocks;
+        self.accumulated_us = state.accumulated_us;
+        self.ticks_advanced = state.ticks_advanced;
+        self.pixel_clocks_owed = state.pixel_clocks_owed;
+        self.ticks_accum = state.ticks_accum;
+        self.clocks_accum = state.clocks_accum;
+        if state.mem.len() == self.mem.len() {
+            self.mem.copy_from_slice(&state.mem);
+        }
+        else {
+            log::error!("CgaSaveState: video memory size mismatch, memory not restored");
+        }
+        self.lightpen_latch = state.lightpen_latch;
+        self.lightpen_addr = state.lightpen_addr;
+        self.out_of_sync = state.out_of_sync;
+    }
+}
+
 impl CGACard {
     pub fn new(trace_logger: TraceLogger, clock_mode: ClockingMode, _video_frame_debug: bool) -> Self {
         let mut cga = Self::default();
@@ -1367,6 +1772,20 @@ impl CGACard {
         byte
     }
 
+    /// Latch a write to the mode or color control register along with the current raster position.
+    /// The list is handed to the renderer and cleared on each vsync.
+    fn latch_register_change(&mut self, register: u16, value: u8) {
+        if self.register_changes.len() >= CGA_MAX_REGISTER_CHANGES {
+            return;
+        }
+        self.register_changes.push(RasterRegisterChange {
+            scanline: self.beam_y,
+            hdot: self.beam_x,
+            register,
+            value,
+        });
+    }
+
     /// Handle write to the Color Control register. This register controls the palette selection
     /// and background/overscan color (foreground color in high-res graphics mode)
     fn handle_cc_register_write(&mut self, data: u8) {
@@ -1433,11 +1852,13 @@ impl CGACard {
         // Address from CRTC is masked by 0x1FFF by the CGA card (bit 13 ignored) and doubled.
         let addr = (self.vma & CGA_TEXT_MODE_WRAP) << 1;
 
+        // A CPU access is only visible once we have caught up to the tick it occurred on.
+        let bus_active = self.dirty_snow && self.cycles >= self.snow_tick;
+
         // Generate snow if we are in hires mode, have a dirty bus, and HCLOCK is odd
-        if self.enable_snow && self.mode_hires_txt && self.dirty_snow && (self.cycles & 0b1000 != 0) {
+        if self.enable_snow && self.mode_hires_txt && bus_active && (self.cycles & 0b1000 != 0) {
             self.cur_char = self.snow_char;
             self.cur_attr = self.last_bus_value;
-            self.dirty_snow = false;
             self.snow_count += 1;
         }
         else {
@@ -1460,7 +1881,9 @@ impl CGACard {
             self.cur_blink = false;
         }
 
-        self.dirty_snow = false;
+        if bus_active {
+            self.dirty_snow = false;
+        }
 
         //(self.cur_fg, self.cur_bg) = ATTRIBUTE_TABLE[self.cur_attr as usize];
     }
@@ -2241,7 +2664,7 @@ impl CGACard {
         self.cur_screen_cycles = 0;
         self.last_vsync_cycles = self.cycles;
 
-        if self.cycles_per_vsync > 300000 {
+        if self.cycles_per_vsync > CGA_MAX_FRAME_CLOCKS {
             log::trace!(
                 "do_vsync(): Excessively long frame. char_clock: {} cycles: {} beam_y: {}",
                 self.char_clock,
@@ -2272,6 +2695,11 @@ impl CGACard {
                 //log::trace!("sink_cycles: {}", self.sink_cycles);
             }
 
+            // Measure the frame the monitor actually displayed. CRTC tweaks to the horizontal or
+            // vertical totals change this from the nominal 238,944 clocks.
+            self.cycles_per_frame = self.cycles - self.last_frame_cycles;
+            self.last_frame_cycles = self.cycles;
+
             self.beam_x = 0;
             self.beam_y = 0;
             self.rba = 0;
@@ -2283,10 +2711,11 @@ impl CGACard {
             self.scanline = 0;
             self.frame_count += 1;
 
-            // Save the current mode byte, used for composite rendering.
-            // The mode could have changed several times per frame, but I am not sure how the composite rendering should
-            // really handle that...
-            self.extents.mode_byte = self.mode_byte;
+            // Hand the mode byte the frame started with and the register writes made during it to the
+            // renderer, so that composite rendering can follow mode changes made mid-frame.
+            self.extents.mode_byte = self.frame_mode_byte;
+            self.extents.register_changes = std::mem::take(&mut self.register_changes);
+            self.frame_mode_byte = self.mode_byte;
 
             // Swap the display buffers
             self.swap();
@@ -2307,3 +2736,218 @@ impl CGACard {
         println!("{}", self.vtac_c5);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{IoDevice, MemoryMappedDevice};
+
+    fn write_crtc(cga: &mut CGACard, register: u8, byte: u8) {
+        cga.handle_crtc_register_select(register);
+        cga.handle_crtc_register_write(byte);
+    }
+
+    fn run_frames(cga: &mut CGACard, frames: usize) {
+        for _ in 0..frames {
+            cga.run(DeviceRunTimeUnit::SystemTicks(CGA_MAX_CLOCK as u32), &mut None, None);
+        }
+    }
+
+    /// Program the 160x100 16-color tweak: 80 column text mode with two scanlines per character
+    /// row and 100 displayed rows.
+    fn set_lowres_tweak(cga: &mut CGACard) {
+        cga.handle_mode_register(0x09);
+        for (register, byte) in [
+            (0x00, 0x71),
+            (0x01, 0x50),
+            (0x02, 0x5A),
+            (0x03, 0x0A),
+            (0x04, 0x7F),
+            (0x05, 0x06),
+            (0x06, 0x64),
+            (0x07, 0x70),
+            (0x09, 0x01),
+            // Move the cursor past the end of the displayed area.
+            (0x0E, 0x1F),
+            (0x0F, 0xFF),
+        ] {
+            write_crtc(cga, register, byte);
+        }
+    }
+
+    #[test]
+    fn test_lowres_tweak_renders_160x100() {
+        let mut cga = CGACard::new(TraceLogger::None, ClockingMode::Cycle, false);
+        set_lowres_tweak(&mut cga);
+
+        // Fill the screen with right half-block characters, blue on yellow. Each character is
+        // then two 'pixels' wide.
+        for i in 0..(80 * 100) {
+            cga.mem[i * 2] = 0xDE;
+            cga.mem[i * 2 + 1] = 0x1E;
+        }
+        run_frames(&mut cga, 3);
+
+        let pattern = [1u8, 1, 1, 1, 14, 14, 14, 14];
+        let buf = cga.get_buf(BufferSelect::Front);
+        let rows = buf
+            .chunks_exact(CGA_XRES_MAX as usize)
+            .filter(|row| {
+                row.windows(8).position(|w| w == pattern).is_some_and(|start| {
+                    (0..80).all(|c| row.get(start + c * 8..start + c * 8 + 8) == Some(&pattern[..]))
+                })
+            })
+            .count();
+        assert_eq!(rows, 200);
+
+        // 114 characters * 262 scanlines is the standard frame, which should produce the
+        // nominal 59.92Hz refresh rate.
+        assert_eq!(cga.cycles_per_frame, FRAME_TIME_CLOCKS as u64);
+        assert!((cga.get_refresh_rate() - 59.92).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_unusual_crtc_values_do_not_panic() {
+        let mut cga = CGACard::new(TraceLogger::None, ClockingMode::Cycle, false);
+        set_lowres_tweak(&mut cga);
+
+        // Displayed values larger than totals, maximum character height, and a vsync position
+        // that is never reached.
+        for (register, byte) in [(0x00, 0x20), (0x01, 0x60), (0x06, 0x7F), (0x07, 0x7F), (0x09, 0x1F)] {
+            write_crtc(&mut cga, register, byte);
+        }
+        run_frames(&mut cga, 4);
+
+        // Switch to 40 columns mid-frame with a short character height.
+        cga.run(
+            DeviceRunTimeUnit::SystemTicks(CGA_MAX_CLOCK as u32 / 2),
+            &mut None,
+            None,
+        );
+        cga.handle_mode_register(0x08);
+        write_crtc(&mut cga, 0x09, 0x00);
+        run_frames(&mut cga, 2);
+
+        let rate = cga.get_refresh_rate();
+        assert!(rate.is_finite() && rate > 0.0);
+    }
+
+    #[test]
+    fn test_mid_frame_palette_change_renders_bands() {
+        let mut cga = CGACard::new(TraceLogger::None, ClockingMode::Cycle, false);
+
+        // 320x200 4-color graphics, every pixel set to color 1.
+        cga.handle_mode_register(0x0A);
+        cga.handle_cc_register_write(0x00);
+        cga.mem.fill(0x55);
+        run_frames(&mut cga, 2);
+
+        // Run scanline by scanline into a new frame, then switch to the cyan palette halfway down.
+        let run_scanline = |cga: &mut CGACard| {
+            cga.run(DeviceRunTimeUnit::SystemTicks(CGA_XRES_MAX), &mut None, None);
+        };
+        let frame = cga.frame_count;
+        while cga.frame_count == frame {
+            run_scanline(&mut cga);
+        }
+        while cga.beam_y < 130 {
+            run_scanline(&mut cga);
+        }
+        let split_line = cga.beam_y;
+        IoDevice::write_u8(
+            &mut cga,
+            CGA_COLOR_CONTROL_REGISTER,
+            0x20,
+            None,
+            DeviceRunTimeUnit::SystemTicks(0),
+            None,
+        );
+        let frame = cga.frame_count;
+        while cga.frame_count == frame {
+            run_scanline(&mut cga);
+        }
+
+        let changes = &cga.extents.register_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].register, CGA_COLOR_CONTROL_REGISTER);
+        assert_eq!(changes[0].value, 0x20);
+        assert_eq!(changes[0].scanline, split_line);
+        assert!(cga.register_changes.is_empty());
+
+        // Rows above the split are green (palette 0), rows below it cyan (palette 1).
+        let buf = cga.get_buf(BufferSelect::Front);
+        let row_color = |y: u32| {
+            let row = &buf[(y * CGA_XRES_MAX) as usize..((y + 1) * CGA_XRES_MAX) as usize];
+            row.iter().copied().find(|&c| c != 0)
+        };
+        let green_rows = (0..split_line).filter(|&y| row_color(y) == Some(2)).count();
+        let cyan_rows = (split_line + 1..CGA_YRES_MAX)
+            .filter(|&y| row_color(y) == Some(3))
+            .count();
+        assert!(green_rows >= 80, "green rows: {}", green_rows);
+        assert!(cyan_rows >= 50, "cyan rows: {}", cyan_rows);
+    }
+
+    #[test]
+    fn test_snow_only_during_active_display() {
+        let mut cga = CGACard::new(TraceLogger::None, ClockingMode::Cycle, false);
+        cga.set_video_option(VideoOption::EnableSnow(true));
+
+        // Standard 80x25 text mode.
+        cga.handle_mode_register(0x09);
+        for (register, byte) in [
+            (0x00, 0x71),
+            (0x01, 0x50),
+            (0x02, 0x5A),
+            (0x03, 0x0A),
+            (0x04, 0x1F),
+            (0x05, 0x06),
+            (0x06, 0x19),
+            (0x07, 0x1C),
+            (0x09, 0x07),
+        ] {
+            write_crtc(&mut cga, register, byte);
+        }
+        run_frames(&mut cga, 2);
+
+        // Busy-write VRAM one character clock at a time, as a program hammering B8000 would.
+        // Returns the wait states incurred beyond the CGA's normal clock synchronization.
+        let busy_write = |cga: &mut CGACard| {
+            let sync_waits = WAIT_TABLE[(cga.cycles + 1) as usize & 0x0F];
+            let waits = cga.get_write_wait(CGA_MEM_ADDRESS, 0) - sync_waits;
+            cga.mmio_write_u8(CGA_MEM_ADDRESS, 0x41, 0, None);
+            cga.run(DeviceRunTimeUnit::SystemTicks(CGA_HCHAR_CLOCK as u32), &mut None, None);
+            waits
+        };
+
+        // Writes during vertical retrace are not contended and produce no snow.
+        while !cga.in_crtc_vblank {
+            cga.run(DeviceRunTimeUnit::SystemTicks(1), &mut None, None);
+        }
+        let snow_count = cga.snow_count;
+        while cga.in_crtc_vblank {
+            assert_eq!(busy_write(&mut cga), 0);
+        }
+        assert_eq!(cga.snow_count, snow_count);
+        assert_eq!(cga.contended_accesses, 0);
+
+        // Writes during active display are delayed and snow appears on screen.
+        while !cga.in_display_area {
+            cga.run(DeviceRunTimeUnit::SystemTicks(1), &mut None, None);
+        }
+        while cga.in_display_area {
+            assert_eq!(busy_write(&mut cga), CONTENTION_WAITS);
+        }
+        assert!(cga.contended_accesses > 0);
+        assert!(cga.snow_count > snow_count);
+
+        // Disabling snow removes the snow, but not the contention penalty.
+        cga.set_video_option(VideoOption::EnableSnow(false));
+        while !cga.in_display_area {
+            cga.run(DeviceRunTimeUnit::SystemTicks(1), &mut None, None);
+        }
+        let snow_count = cga.snow_count;
+        assert_eq!(busy_write(&mut cga), CONTENTION_WAITS);
+        assert_eq!(cga.snow_count, snow_count);
+    }
+}
@@ -73,6 +73,7 @@ impl VideoCard for CGACard {
             VideoOption::EnableSnow(state) => {
                 log::debug!("VideoOption::EnableSnow set to: {}", state);
                 self.enable_snow = state;
+                self.dirty_snow = false;
             }
             VideoOption::DebugDraw(state) => {
                 log::debug!("VideoOption::DebugDraw set to: {}", state);
@@ -136,6 +137,14 @@ impl VideoCard for CGACard {
         Some((self.beam_x, self.beam_y))
     }
 
+    fn get_raster_position(&self) -> RasterPosition {
+        RasterPosition {
+            frame: self.frame_count,
+            scanline: self.beam_y,
+            dot: self.beam_x,
+        }
+    }
+
     /// Tick the CGA the specified number of video clock cycles.
     fn debug_tick(&mut self, ticks: u32, _cpumem: Option<&[u8]>) {
         match self.clock_mode {
@@ -207,11 +216,19 @@ impl VideoCard for CGACard {
         &self.buf[self.front_buf][..]
     }
 
-    /// Get the current display refresh rate of the device. For CGA, this is always the same value.
-    /// On real hardware, this is something slightly less than 60Hz, we set to 60Hz here for 
-    /// simplicity.
+    /// Get the current display refresh rate of the device. On real hardware, this is something
+    /// slightly less than 60Hz with standard CRTC values, but is derived from the measured frame
+    /// length to account for tweaked modes.
     fn get_refresh_rate(&self) -> f32 {
-        60.0
+        // CRTC tweaks can change the frame length, so use the last displayed frame if the monitor
+        // could plausibly sync to it.
+        let min_frame_clocks = (CGA_XRES_MAX * CGA_MONITOR_VSYNC_MIN) as u64;
+        if (min_frame_clocks..=CGA_MAX_FRAME_CLOCKS).contains(&self.cycles_per_frame) {
+            (CGA_CLOCK * 1_000_000.0 / self.cycles_per_frame as f64) as f32
+        }
+        else {
+            60.0
+        }
     }
 
     fn is_40_columns(&self) -> bool {
@@ -352,6 +369,7 @@ impl VideoCard for CGACard {
         internal_vec.push((String::from("phase:"), VideoCardStateEntry::String(format!("{}", self.cycles & 0x0F))));
         internal_vec.push((String::from("cursor attr:"), VideoCardStateEntry::String(format!("{:02b}", self.cursor_attr))));
         internal_vec.push((String::from("snowflakes:"), VideoCardStateEntry::String(format!("{}", self.snow_count))));
+        internal_vec.push((String::from("contended:"), VideoCardStateEntry::String(format!("{}", self.contended_accesses))));
         map.insert("Internal".to_string(), internal_vec);
 
         let mut external_vec = Vec::new();
@@ -586,4 +604,52 @@ impl VideoCard for CGACard {
 
         strings
     }
+
+    fn get_text_buffer(&self) -> Option<TextBuffer> {
+        if self.mode_graphics {
+            return None;
+        }
+        // The start address is latched at the beginning of each frame, so use the latched value to
+        // read the page that is actually being displayed.
+        let columns = self.crtc_horizontal_displayed as usize;
+        let rows = self.crtc_vertical_displayed as usize;
+        let cells = (0..columns * rows)
+            .map(|i| {
+                let addr = ((self.crtc_frame_address + i) & CGA_TEXT_MODE_WRAP) << 1;
+                (self.mem[addr], self.mem[addr + 1])
+            })
+            .collect();
+
+        Some(TextBuffer { columns, rows, cells })
+    }
+
+    fn get_register_state(&self) -> Vec<(String, u8)> {
+        let crtc = [
+            self.crtc_horizontal_total,
+            self.crtc_horizontal_displayed,
+            self.crtc_horizontal_sync_pos,
+            self.crtc_sync_width,
+            self.crtc_vertical_total,
+            self.crtc_vertical_total_adjust,
+            self.crtc_vertical_displayed,
+            self.crtc_vertical_sync_pos,
+            self.crtc_interlace_mode,
+            self.crtc_maximum_scanline_address,
+            self.crtc_cursor_start_line,
+            self.crtc_cursor_end_line,
+            self.crtc_start_address_ho,
+            self.crtc_start_address_lo,
+            self.crtc_cursor_address_ho,
+            self.crtc_cursor_address_lo,
+        ];
+        let mut regs: Vec<(String, u8)> = MC6845_REGISTER_NAMES
+            .iter()
+            .zip(crtc)
+            .map(|(name, val)| (name.to_string(), val))
+            .collect();
+
+        regs.push(("Mode Select (3D8)".to_string(), self.mode_byte));
+        regs.push(("Color Select (3D9)".to_string(), self.cc_register));
+        regs
+    }
 }
@@ -28,11 +28,20 @@
 
     Implements the Intel 8237 DMA Controller
 
+    A short history of completed transfers is kept for the debugger. DRAM
+    refresh on channel 0 is not recorded, as it would quickly crowd out
+    the transfers of interest.
+
 */
 
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
+    savestate::SaveState,
 };
 
 pub const DMA_CHANNEL_0_ADDR_PORT: u16 = 0x00; // R/W
@@ -72,18 +81,25 @@ pub const DMA_COMMAND_TIMING: u8 = 0x08;
 pub const DMA_COMMAND_PRIORITY: u8 = 0x10;
 
 pub const DMA_CHANNEL_COUNT: usize = 4;
+pub const DMA_REFRESH_CHANNEL: usize = 0;
+pub const DMA_HISTORY_LEN: usize = 32;
 
+/// The devices that request service on each channel of the PC and XT.
+const DMA_CHANNEL_DEVICES: [&str; DMA_CHANNEL_COUNT] = ["DRAM Refresh", "Expansion", "Floppy", "Hard Disk"];
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum TimingMode {
     NormalTiming,
     CompressedTiming,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub enum PriorityMode {
     Fixed,
     Rotating,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ServiceMode {
     Demand,
     Single,
@@ -95,7 +111,7 @@ impl Default for ServiceMode {
         ServiceMode::Demand
     }
 }
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum AddressMode {
     Increment,
     Decrement,
@@ -106,7 +122,7 @@ impl Default for AddressMode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum TransferType {
     Verify,
     Write,
@@ -120,7 +136,7 @@ impl Default for TransferType {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct DMAChannel {
     current_address_reg: u16,
     current_word_count_reg: u16,
@@ -153,16 +169,30 @@ pub struct DMAChannelStringState {
     pub terminal_count: String,
     pub terminal_count_reached: String,
     pub masked: String,
+    pub request: String,
     pub page: String,
 }
 
+/// A record of a completed DMA transfer.
+#[derive(Clone, Debug)]
+pub struct DMATransferRecord {
+    pub channel: usize,
+    pub transfer_type: TransferType,
+    pub address: u32,
+    pub length: u32,
+    pub device: &'static str,
+}
+
 #[derive(Default)]
 pub struct DMAControllerStringState {
     pub enabled: String,
     pub flipflop: String,
     pub dreq: String,
     pub dma_channel_state: Vec<DMAChannelStringState>,
+    pub transfer_history: Vec<DMATransferRecord>,
 }
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DMAController {
     enabled: bool,
     mem_to_mem_enabled: bool,
@@ -179,6 +209,9 @@ pub struct DMAController {
     temp_reg: u8,
 
     dreq: bool,
+
+    #[serde(skip)]
+    history: VecDeque<DMATransferRecord>,
 }
 
 impl IoDevice for DMAController {
@@ -308,6 +341,18 @@ impl IoDevice for DMAController {
     }
 }
 
+impl SaveState for DMAController {
+    type State = DMAController;
+
+    fn save_state(&self) -> DMAController {
+        self.clone()
+    }
+
+    fn restore_state(&mut self, state: DMAController) {
+        *self = state;
+    }
+}
+
 impl DMAController {
     pub fn new() -> Self {
         Self {
@@ -330,6 +375,8 @@ impl DMAController {
             temp_reg: 0,
 
             dreq: false,
+
+            history: VecDeque::new(),
         }
     }
 
@@ -578,7 +625,7 @@ impl DMAController {
 
     pub fn get_string_state(&self) -> DMAControllerStringState {
         let mut chan_vec = Vec::new();
-        for chan in self.channels.iter() {
+        for (i, chan) in self.channels.iter().enumerate() {
             chan_vec.push(DMAChannelStringState {
                 current_address_reg: format!("{:04X}", chan.current_address_reg),
                 current_word_count_reg: format!("{}", chan.current_word_count_reg),
@@ -592,6 +639,7 @@ impl DMAController {
                 terminal_count: format!("{:?}", chan.terminal_count),
                 terminal_count_reached: format!("{:?}", chan.terminal_count_reached),
                 masked: format!("{:?}", chan.masked),
+                request: format!("{:?}", self.request_reg & (0x01 << i) != 0),
                 page: format!("{:02X}", chan.page),
             });
         }
@@ -601,9 +649,28 @@ impl DMAController {
             flipflop: format!("{:?}", self.flipflop),
             dreq: format!("{:?}", self.dreq),
             dma_channel_state: chan_vec,
+            transfer_history: self.history.iter().cloned().collect(),
         }
     }
 
+    /// Record the completion of the transfer programmed on the specified channel.
+    fn record_transfer(&mut self, channel: usize) {
+        if channel == DMA_REFRESH_CHANNEL {
+            return;
+        }
+        let chan = &self.channels[channel];
+        if self.history.len() == DMA_HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(DMATransferRecord {
+            channel,
+            transfer_type: chan.transfer_type.clone(),
+            address: ((chan.page as u32) << 16) + chan.base_address_reg as u32,
+            length: chan.base_word_count_reg as u32 + 1,
+            device: DMA_CHANNEL_DEVICES[channel],
+        });
+    }
+
     pub fn get_dma_transfer_size(&self, channel: usize) -> usize {
         if channel >= DMA_CHANNEL_COUNT {
             panic!("Invalid DMA Channel");
@@ -706,6 +773,7 @@ impl DMAController {
                     }
                     // Set the tc status bit regardless of auto-init
                     self.channels[channel].terminal_count_reached = true;
+                    self.record_transfer(channel);
                 }
                 else {
                     // Trying to transfer on a terminal count
@@ -758,6 +826,7 @@ impl DMAController {
 
                     // Set the tc status bit regardless of auto-init
                     self.channels[channel].terminal_count_reached = true;
+                    self.record_transfer(channel);
                 }
                 else {
                     // Trying to transfer on a terminal count
@@ -801,3 +870,39 @@ impl DMAController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed_transfer_is_recorded() {
+        let mut bus = BusInterface::default();
+        let mut dma = DMAController::new();
+        let nul_delta = DeviceRunTimeUnit::SystemTicks(0);
+
+        // Program channel 2 for a 4 byte single mode write to 1:2000.
+        dma.write_u8(DMA_CLEAR_FLIPFLOP, 0, None, nul_delta, None);
+        dma.write_u8(DMA_CHANNEL_MODE_REGISTER, 0x46, None, nul_delta, None);
+        dma.write_u8(DMA_CHANNEL_2_ADDR_PORT, 0x00, None, nul_delta, None);
+        dma.write_u8(DMA_CHANNEL_2_ADDR_PORT, 0x20, None, nul_delta, None);
+        dma.write_u8(DMA_CHANNEL_2_WC_PORT, 0x03, None, nul_delta, None);
+        dma.write_u8(DMA_CHANNEL_2_WC_PORT, 0x00, None, nul_delta, None);
+        dma.write_u8(DMA_CHANNEL_2_PAGE_REGISTER, 0x01, None, nul_delta, None);
+
+        for byte in 0..3 {
+            dma.do_dma_write_u8(&mut bus, 2, byte);
+        }
+        assert!(dma.get_string_state().transfer_history.is_empty());
+        dma.do_dma_write_u8(&mut bus, 2, 3);
+        assert!(dma.check_terminal_count(2));
+        assert_eq!(bus.peek_u8(0x12003).unwrap(), 3);
+
+        let history = dma.get_string_state().transfer_history;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].channel, 2);
+        assert!(matches!(history[0].transfer_type, TransferType::Write));
+        assert_eq!((history[0].address, history[0].length), (0x12000, 4));
+        assert_eq!(history[0].device, "Floppy");
+    }
+}
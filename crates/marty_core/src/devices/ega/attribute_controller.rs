@@ -32,7 +32,7 @@
 
 use super::*;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum AttributeRegister {
     Palette0,
     Palette1,
@@ -56,7 +56,7 @@ pub enum AttributeRegister {
     HorizontalPelPanning,
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum AttributeRegisterFlipFlop {
     Address,
     Data,
@@ -102,6 +102,7 @@ pub struct APaletteRegister {
 }
 
 #[bitfield]
+#[derive(Copy, Clone)]
 pub struct AModeControl {
     #[bits = 1]
     pub mode: AttributeMode,
@@ -128,6 +129,7 @@ pub struct AOverscanColor {
 }
 
 #[bitfield]
+#[derive(Copy, Clone)]
 pub struct AColorPlaneEnable {
     pub enable_plane: B4,
     pub video_status_mux: B2,
@@ -147,7 +149,7 @@ pub enum AttributeInput<'a> {
     Parallel64(u64, u8, bool),
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct AttributePaletteEntry {
     pub six: u8,
     pub four: u8,
@@ -204,6 +206,68 @@ impl Default for AttributeController {
     }
 }
 
+/// The attribute controller registers and pixel shifter.
+#[derive(Serialize, Deserialize)]
+pub struct AttributeControllerSaveState {
+    register_flipflop: AttributeRegisterFlipFlop,
+    register_select_byte: u8,
+    register_selected: AttributeRegister,
+    palette_registers: [AttributePaletteEntry; 16],
+    palette_index: usize,
+    mode_control: u8,
+    overscan_color: AttributePaletteEntry,
+    overscan_color64: u64,
+    color_plane_enable: u8,
+    color_plane_enable64: u64,
+    pel_panning: u8,
+    blink_state: bool,
+    last_den: bool,
+    shift_reg: u128,
+    shift_buf: [u8; 8],
+}
+
+impl SaveState for AttributeController {
+    type State = AttributeControllerSaveState;
+
+    fn save_state(&self) -> AttributeControllerSaveState {
+        AttributeControllerSaveState {
+            register_flipflop: self.register_flipflop,
+            register_select_byte: self.register_select_byte,
+            register_selected: self.register_selected,
+            palette_registers: self.palette_registers,
+            palette_index: self.palette_index,
+            mode_control: self.mode_control.into_bytes()[0],
+            overscan_color: self.overscan_color,
+            overscan_color64: self.overscan_color64,
+            color_plane_enable: self.color_plane_enable.into_bytes()[0],
+            color_plane_enable64: self.color_plane_enable64,
+            pel_panning: self.pel_panning,
+            blink_state: self.blink_state,
+            last_den: self.last_den,
+            shift_reg: self.shift_reg,
+            shift_buf: self.shift_buf,
+        }
+    }
+
+    fn restore_state(&mut self, state: AttributeControllerSaveState) {
+        self.register_flipflop = state.register_flipflop;
+        self.register_select_byte = state.register_select_byte;
+        self.register_selected = state.register_selected;
+        self.palette_registers = state.palette_registers;
+        self.palette_index = state.palette_index;
+        self.mode_control = AModeControl::from_bytes([state.mode_control]);
+        self.overscan_color = state.overscan_color;
+        self.overscan_color64 = state.overscan_color64;
+        self.color_plane_enable = AColorPlaneEnable::from_bytes([state.color_plane_enable]);
+        self.color_plane_enable64 = state.color_plane_enable64;
+        self.pel_panning = state.pel_panning;
+        self.blink_state = state.blink_state;
+        self.last_den = state.last_den;
+        self.shift_reg = state.shift_reg;
+        self.shift_buf = state.shift_buf;
+    }
+}
+
 impl AttributeController {
     pub fn new() -> Self {
         Self::default()
@@ -498,6 +562,29 @@ impl AttributeController {
         self.palette_registers[(pel & 0x0F) as usize].four_to_six
     }
 
+    /// Return the raw values of the attribute controller registers, starting with the 16 palette registers.
+    pub fn get_register_state(&self) -> Vec<(String, u8)> {
+        let mut regs: Vec<(String, u8)> = self
+            .palette_registers
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (format!("AR{:02X} Palette {:X}", i, i), entry.six))
+            .collect();
+        let control = [
+            self.mode_control.into_bytes()[0],
+            self.overscan_color.six,
+            self.color_plane_enable.into_bytes()[0],
+            self.pel_panning,
+        ];
+        regs.extend(
+            EGA_ATTRIBUTE_REGISTER_NAMES
+                .iter()
+                .zip(control)
+                .map(|(name, val)| (name.to_string(), val)),
+        );
+        regs
+    }
+
     #[rustfmt::skip]
     pub fn get_state(&self) -> Vec<(String, VideoCardStateEntry)> {
         let mut attribute_vec = Vec::new();
@@ -65,7 +65,7 @@ macro_rules! push_reg_str {
     };
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum CRTCRegister {
     HorizontalTotal,
     HorizontalDisplayEnd,
@@ -155,7 +155,7 @@ pub struct CModeControl {
     pub hardware_reset: B1,
 }
 
-#[derive(Copy, Clone, Default, Debug)]
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct CrtcStatus {
     pub begin_hsync: bool,
     pub begin_vsync: bool,
@@ -312,6 +312,206 @@ impl Default for EgaCrtc {
     }
 }
 
+/// The CRTC register file and counters.
+#[derive(Serialize, Deserialize)]
+pub struct EgaCrtcSaveState {
+    register_select_byte: u8,
+    register_selected: CRTCRegister,
+    crtc_horizontal_total: u8,
+    crtc_horizontal_display_end: u8,
+    crtc_start_horizontal_blank: u8,
+    crtc_end_horizontal_blank: u8,
+    crtc_end_horizontal_blank_norm: u8,
+    crtc_start_horizontal_retrace: u8,
+    crtc_end_horizontal_retrace: u8,
+    crtc_end_horizontal_retrace_norm: u8,
+    crtc_retrace_width: u8,
+    crtc_vertical_total: u16,
+    crtc_overflow: u8,
+    crtc_preset_row_scan: u8,
+    crtc_maximum_scanline: u8,
+    crtc_cursor_start: u8,
+    crtc_cursor_enabled: bool,
+    crtc_cursor_end: u8,
+    crtc_cursor_skew: u8,
+    crtc_start_address_ho: u8,
+    crtc_start_address_lo: u8,
+    crtc_start_address: u16,
+    start_address_latch: u16,
+    crtc_cursor_address_lo: u8,
+    crtc_cursor_address_ho: u8,
+    crtc_cursor_address: u16,
+    crtc_vertical_retrace_start: u16,
+    crtc_vertical_retrace_end: u8,
+    crtc_vertical_retrace_end_norm: u16,
+    crtc_vertical_display_end: u16,
+    crtc_offset: u8,
+    crtc_underline_location: u8,
+    crtc_start_vertical_blank: u16,
+    crtc_end_vertical_blank: u16,
+    crtc_mode_control: u8,
+    crtc_line_compare: u16,
+    hcc: u8,
+    vlc: u8,
+    vcc: u8,
+    slc: u16,
+    hsc: u8,
+    vsc: u8,
+    vtac_c5: u8,
+    in_vta: bool,
+    in_hrd: bool,
+    hrdc: u8,
+    effective_vta: u8,
+    vma: u16,
+    vma_sl: u16,
+    vma_t: u16,
+    vmws: usize,
+    den_skew_front: bool,
+    den_skew_back: bool,
+    dsc: u8,
+    status: CrtcStatus,
+    blink_state: bool,
+    monitor_hsync: bool,
+    monitor_vsync: bool,
+    in_last_vblank_line: bool,
+    cursor_data: [bool; EGA_CURSOR_MAX],
+    frame: u64,
+}
+
+impl SaveState for EgaCrtc {
+    type State = EgaCrtcSaveState;
+
+    fn save_state(&self) -> EgaCrtcSaveState {
+        EgaCrtcSaveState {
+            register_select_byte: self.register_select_byte,
+            register_selected: self.register_selected,
+            crtc_horizontal_total: self.crtc_horizontal_total,
+            crtc_horizontal_display_end: self.crtc_horizontal_display_end,
+            crtc_start_horizontal_blank: self.crtc_start_horizontal_blank,
+            crtc_end_horizontal_blank: self.crtc_end_horizontal_blank.into_bytes()[0],
+            crtc_end_horizontal_blank_norm: self.crtc_end_horizontal_blank_norm,
+            crtc_start_horizontal_retrace: self.crtc_start_horizontal_retrace,
+            crtc_end_horizontal_retrace: self.crtc_end_horizontal_retrace.into_bytes()[0],
+            crtc_end_horizontal_retrace_norm: self.crtc_end_horizontal_retrace_norm,
+            crtc_retrace_width: self.crtc_retrace_width,
+            crtc_vertical_total: self.crtc_vertical_total,
+            crtc_overflow: self.crtc_overflow,
+            crtc_preset_row_scan: self.crtc_preset_row_scan,
+            crtc_maximum_scanline: self.crtc_maximum_scanline,
+            crtc_cursor_start: self.crtc_cursor_start,
+            crtc_cursor_enabled: self.crtc_cursor_enabled,
+            crtc_cursor_end: self.crtc_cursor_end.into_bytes()[0],
+            crtc_cursor_skew: self.crtc_cursor_skew,
+            crtc_start_address_ho: self.crtc_start_address_ho,
+            crtc_start_address_lo: self.crtc_start_address_lo,
+            crtc_start_address: self.crtc_start_address,
+            start_address_latch: self.start_address_latch,
+            crtc_cursor_address_lo: self.crtc_cursor_address_lo,
+            crtc_cursor_address_ho: self.crtc_cursor_address_ho,
+            crtc_cursor_address: self.crtc_cursor_address,
+            crtc_vertical_retrace_start: self.crtc_vertical_retrace_start,
+            crtc_vertical_retrace_end: self.crtc_vertical_retrace_end.into_bytes()[0],
+            crtc_vertical_retrace_end_norm: self.crtc_vertical_retrace_end_norm,
+            crtc_vertical_display_end: self.crtc_vertical_display_end,
+            crtc_offset: self.crtc_offset,
+            crtc_underline_location: self.crtc_underline_location,
+            crtc_start_vertical_blank: self.crtc_start_vertical_blank,
+            crtc_end_vertical_blank: self.crtc_end_vertical_blank,
+            crtc_mode_control: self.crtc_mode_control.into_bytes()[0],
+            crtc_line_compare: self.crtc_line_compare,
+            hcc: self.hcc,
+            vlc: self.vlc,
+            vcc: self.vcc,
+            slc: self.slc,
+            hsc: self.hsc,
+            vsc: self.vsc,
+            vtac_c5: self.vtac_c5,
+            in_vta: self.in_vta,
+            in_hrd: self.in_hrd,
+            hrdc: self.hrdc,
+            effective_vta: self.effective_vta,
+            vma: self.vma,
+            vma_sl: self.vma_sl,
+            vma_t: self.vma_t,
+            vmws: self.vmws,
+            den_skew_front: self.den_skew_front,
+            den_skew_back: self.den_skew_back,
+            dsc: self.dsc,
+            status: self.status,
+            blink_state: self.blink_state,
+            monitor_hsync: self.monitor_hsync,
+            monitor_vsync: self.monitor_vsync,
+            in_last_vblank_line: self.in_last_vblank_line,
+            cursor_data: self.cursor_data,
+            frame: self.frame,
+        }
+    }
+
+    fn restore_state(&mut self, state: EgaCrtcSaveState) {
+        self.register_select_byte = state.register_select_byte;
+        self.register_selected = state.register_selected;
+        self.crtc_horizontal_total = state.crtc_horizontal_total;
+        self.crtc_horizontal_display_end = state.crtc_horizontal_display_end;
+        self.crtc_start_horizontal_blank = state.crtc_start_horizontal_blank;
+        self.crtc_end_horizontal_blank = CEndHorizontalBlank::from_bytes([state.crtc_end_horizontal_blank]);
+        self.crtc_end_horizontal_blank_norm = state.crtc_end_horizontal_blank_norm;
+        self.crtc_start_horizontal_retrace = state.crtc_start_horizontal_retrace;
+        self.crtc_end_horizontal_retrace = CEndHorizontalRetrace::from_bytes([state.crtc_end_horizontal_retrace]);
+        self.crtc_end_horizontal_retrace_norm = state.crtc_end_horizontal_retrace_norm;
+        self.crtc_retrace_width = state.crtc_retrace_width;
+        self.crtc_vertical_total = state.crtc_vertical_total;
+        self.crtc_overflow = state.crtc_overflow;
+        self.crtc_preset_row_scan = state.crtc_preset_row_scan;
+        self.crtc_maximum_scanline = state.crtc_maximum_scanline;
+        self.crtc_cursor_start = state.crtc_cursor_start;
+        self.crtc_cursor_enabled = state.crtc_cursor_enabled;
+        self.crtc_cursor_end = CCursorEnd::from_bytes([state.crtc_cursor_end]);
+        self.crtc_cursor_skew = state.crtc_cursor_skew;
+        self.crtc_start_address_ho = state.crtc_start_address_ho;
+        self.crtc_start_address_lo = state.crtc_start_address_lo;
+        self.crtc_start_address = state.crtc_start_address;
+        self.start_address_latch = state.start_address_latch;
+        self.crtc_cursor_address_lo = state.crtc_cursor_address_lo;
+        self.crtc_cursor_address_ho = state.crtc_cursor_address_ho;
+        self.crtc_cursor_address = state.crtc_cursor_address;
+        self.crtc_vertical_retrace_start = state.crtc_vertical_retrace_start;
+        self.crtc_vertical_retrace_end = CVerticalRetraceEnd::from_bytes([state.crtc_vertical_retrace_end]);
+        self.crtc_vertical_retrace_end_norm = state.crtc_vertical_retrace_end_norm;
+        self.crtc_vertical_display_end = state.crtc_vertical_display_end;
+        self.crtc_offset = state.crtc_offset;
+        self.crtc_underline_location = state.crtc_underline_location;
+        self.crtc_start_vertical_blank = state.crtc_start_vertical_blank;
+        self.crtc_end_vertical_blank = state.crtc_end_vertical_blank;
+        self.crtc_mode_control = CModeControl::from_bytes([state.crtc_mode_control]);
+        self.crtc_line_compare = state.crtc_line_compare;
+        self.hcc = state.hcc;
+        self.vlc = state.vlc;
+        self.vcc = state.vcc;
+        self.slc = state.slc;
+        self.hsc = state.hsc;
+        self.vsc = state.vsc;
+        self.vtac_c5 = state.vtac_c5;
+        self.in_vta = state.in_vta;
+        self.in_hrd = state.in_hrd;
+        self.hrdc = state.hrdc;
+        self.effective_vta = state.effective_vta;
+        self.vma = state.vma;
+        self.vma_sl = state.vma_sl;
+        self.vma_t = state.vma_t;
+        self.vmws = state.vmws;
+        self.den_skew_front = state.den_skew_front;
+        self.den_skew_back = state.den_skew_back;
+        self.dsc = state.dsc;
+        self.status = state.status;
+        self.blink_state = state.blink_state;
+        self.monitor_hsync = state.monitor_hsync;
+        self.monitor_vsync = state.monitor_vsync;
+        self.in_last_vblank_line = state.in_last_vblank_line;
+        self.cursor_data = state.cursor_data;
+        self.frame = state.frame;
+    }
+}
+
 impl EgaCrtc {
     pub fn new() -> Self {
         Self::default()
@@ -1091,6 +1291,43 @@ impl EgaCrtc {
         self.crtc_mode_control.word_or_byte_mode()
     }
 
+    /// Return the raw values of the CRTC registers. Registers wider than 8 bits return their low
+    /// byte, with the remaining bits held in the Overflow register.
+    pub fn get_register_state(&self) -> Vec<(String, u8)> {
+        let regs = [
+            self.crtc_horizontal_total,
+            self.crtc_horizontal_display_end,
+            self.crtc_start_horizontal_blank,
+            self.crtc_end_horizontal_blank.into_bytes()[0],
+            self.crtc_start_horizontal_retrace,
+            self.crtc_end_horizontal_retrace.into_bytes()[0],
+            self.crtc_vertical_total as u8,
+            self.crtc_overflow,
+            self.crtc_preset_row_scan,
+            self.crtc_maximum_scanline,
+            self.crtc_cursor_start,
+            self.crtc_cursor_end.into_bytes()[0],
+            self.crtc_start_address_ho,
+            self.crtc_start_address_lo,
+            self.crtc_cursor_address_ho,
+            self.crtc_cursor_address_lo,
+            self.crtc_vertical_retrace_start as u8,
+            self.crtc_vertical_retrace_end.into_bytes()[0],
+            self.crtc_vertical_display_end as u8,
+            self.crtc_offset,
+            self.crtc_underline_location,
+            self.crtc_start_vertical_blank as u8,
+            self.crtc_end_vertical_blank as u8,
+            self.crtc_mode_control.into_bytes()[0],
+            self.crtc_line_compare as u8,
+        ];
+        EGA_CRTC_REGISTER_NAMES
+            .iter()
+            .zip(regs)
+            .map(|(name, val)| (name.to_string(), val))
+            .collect()
+    }
+
     #[rustfmt::skip]
     pub fn get_state(&self) -> Vec<(String, VideoCardStateEntry)> {
         let mut crtc_vec = Vec::new();
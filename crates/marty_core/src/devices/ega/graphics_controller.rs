@@ -33,7 +33,7 @@
 
 use super::*;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum GraphicsRegister {
     SetReset,
     EnableSetReset,
@@ -48,6 +48,7 @@ pub enum GraphicsRegister {
 
 #[allow(dead_code)]
 #[bitfield]
+#[derive(Copy, Clone)]
 pub struct GDataRotateRegister {
     pub count: B3,
     #[bits = 2]
@@ -95,7 +96,7 @@ pub enum MemoryMap {
     B8000_32K,
 }
 
-#[derive(Copy, Clone, Debug, BitfieldSpecifier)]
+#[derive(Copy, Clone, Debug, BitfieldSpecifier, Serialize, Deserialize)]
 pub enum LogicFunction {
     Unmodified,
     And,
@@ -171,6 +172,71 @@ impl Default for GraphicsController {
     }
 }
 
+/// The graphics controller registers, latches and pixel pipeline.
+#[derive(Serialize, Deserialize)]
+pub struct GraphicsControllerSaveState {
+    graphics_register_select_byte: u8,
+    graphics_register_selected: GraphicsRegister,
+    graphics_set_reset: u8,
+    graphics_enable_set_reset: u8,
+    graphics_color_compare: u8,
+    graphics_data_rotate: u8,
+    graphics_data_rotate_function: LogicFunction,
+    graphics_read_map_select: u8,
+    graphics_mode: u8,
+    graphics_micellaneous: u8,
+    graphics_color_dont_care: u8,
+    graphics_bitmask: u8,
+    latches: [u8; 4],
+    pixel_buf: [u8; 8],
+    pipeline_buf: [u8; 4],
+    serialize_buf: [u8; 8],
+}
+
+impl SaveState for GraphicsController {
+    type State = GraphicsControllerSaveState;
+
+    fn save_state(&self) -> GraphicsControllerSaveState {
+        GraphicsControllerSaveState {
+            graphics_register_select_byte: self.graphics_register_select_byte,
+            graphics_register_selected: self.graphics_register_selected,
+            graphics_set_reset: self.graphics_set_reset,
+            graphics_enable_set_reset: self.graphics_enable_set_reset,
+            graphics_color_compare: self.graphics_color_compare,
+            graphics_data_rotate: self.graphics_data_rotate.into_bytes()[0],
+            graphics_data_rotate_function: self.graphics_data_rotate_function,
+            graphics_read_map_select: self.graphics_read_map_select,
+            graphics_mode: self.graphics_mode.into_bytes()[0],
+            graphics_micellaneous: self.graphics_micellaneous.into_bytes()[0],
+            graphics_color_dont_care: self.graphics_color_dont_care,
+            graphics_bitmask: self.graphics_bitmask,
+            latches: self.latches,
+            pixel_buf: self.pixel_buf,
+            pipeline_buf: self.pipeline_buf,
+            serialize_buf: self.serialize_buf,
+        }
+    }
+
+    fn restore_state(&mut self, state: GraphicsControllerSaveState) {
+        self.graphics_register_select_byte = state.graphics_register_select_byte;
+        self.graphics_register_selected = state.graphics_register_selected;
+        self.graphics_set_reset = state.graphics_set_reset;
+        self.graphics_enable_set_reset = state.graphics_enable_set_reset;
+        self.graphics_color_compare = state.graphics_color_compare;
+        self.graphics_data_rotate = GDataRotateRegister::from_bytes([state.graphics_data_rotate]);
+        self.graphics_data_rotate_function = state.graphics_data_rotate_function;
+        self.graphics_read_map_select = state.graphics_read_map_select;
+        self.graphics_mode = GModeRegister::from_bytes([state.graphics_mode]);
+        self.graphics_micellaneous = GMiscellaneousRegister::from_bytes([state.graphics_micellaneous]);
+        self.graphics_color_dont_care = state.graphics_color_dont_care;
+        self.graphics_bitmask = state.graphics_bitmask;
+        self.latches = state.latches;
+        self.pixel_buf = state.pixel_buf;
+        self.pipeline_buf = state.pipeline_buf;
+        self.serialize_buf = state.serialize_buf;
+    }
+}
+
 impl GraphicsController {
     pub fn new() -> Self {
         GraphicsController::default()
@@ -582,6 +648,26 @@ impl GraphicsController {
         self.graphics_mode.shift_mode()
     }
 
+    /// Return the raw values of the graphics controller registers.
+    pub fn get_register_state(&self) -> Vec<(String, u8)> {
+        let regs = [
+            self.graphics_set_reset,
+            self.graphics_enable_set_reset,
+            self.graphics_color_compare,
+            self.graphics_data_rotate.into_bytes()[0],
+            self.graphics_read_map_select,
+            self.graphics_mode.into_bytes()[0],
+            self.graphics_micellaneous.into_bytes()[0],
+            self.graphics_color_dont_care,
+            self.graphics_bitmask,
+        ];
+        EGA_GRAPHICS_REGISTER_NAMES
+            .iter()
+            .zip(regs)
+            .map(|(name, val)| (name.to_string(), val))
+            .collect()
+    }
+
     #[rustfmt::skip]
     pub fn get_state(&self) -> Vec<(String, VideoCardStateEntry)> {
         let mut graphics_vec = Vec::new();
@@ -44,7 +44,8 @@ use modular_bitfield::prelude::*;
 //#![allow(dead_code)]
 use log;
 
-use crate::tracelogger::TraceLogger;
+use crate::{savestate::SaveState, tracelogger::TraceLogger};
+use serde::{Deserialize, Serialize};
 
 use crate::device_traits::videocard::*;
 
@@ -62,7 +63,7 @@ mod vram;
 
 use attribute_controller::*;
 
-use crate::devices::ega::crtc::{EgaCrtc, WordOrByteMode};
+use crate::devices::ega::crtc::{EgaCrtc, EgaCrtcSaveState, WordOrByteMode};
 
 use crate::devices::{
     dipswitch::{DipSwitch, DipSwitchSize},
@@ -631,6 +632,57 @@ pub struct EGACard {
     feature_bits: u8,
 }
 
+/// Serializable state of the EGA card, including video memory and the raster position. The frame
+/// buffers are not included and are redrawn from the next frame.
+#[derive(Serialize, Deserialize)]
+pub struct EgaSaveState {
+    ticks_accum: f64,
+    cycles: u64,
+    io_adjust: u16,
+    mode_byte: u8,
+    display_mode: DisplayMode,
+    mode_enable: bool,
+    mode_graphics: bool,
+    mode_bw: bool,
+    mode_line_gfx: bool,
+    mode_hires_gfx: bool,
+    mode_hires_txt: bool,
+    mode_blinking: bool,
+    scanline: u32,
+    frame: u64,
+    scanline_cycles: f32,
+    frame_cycles: f32,
+    cursor_frames: u32,
+    raster_x: u32,
+    raster_y: u32,
+    cur_char: u8,
+    next_char: u8,
+    cur_attr: u8,
+    next_attr: u8,
+    cur_fg: u8,
+    cur_bg: u8,
+    cur_blink: bool,
+    blink_state: bool,
+    cursor_status: bool,
+    cursor_slowblink: bool,
+    cursor_blink_rate: u32,
+    cursor_attr: u8,
+    crtc: EgaCrtcSaveState,
+    vma: usize,
+    sequencer: SequencerSaveState,
+    gc: GraphicsControllerSaveState,
+    ac: AttributeControllerSaveState,
+    pel_pan_latch: u8,
+    current_font: u8,
+    misc_output_register: u8,
+    rba: usize,
+    hsync_ct: u64,
+    vsync_ct: u64,
+    intr: bool,
+    last_intr: bool,
+    feature_bits: u8,
+}
+
 #[bitfield]
 #[derive(Copy, Clone)]
 struct EMiscellaneousOutputRegister {
@@ -782,10 +834,113 @@ impl Default for DisplayExtents {
             row_stride: EGA16_MAX_RASTER_X as usize,
             double_scan: false,
             mode_byte: 0,
+            register_changes: Vec::new(),
         }
     }
 }*/
 
+impl SaveState for EGACard {
+    type State = EgaSaveState;
+
+    fn save_state(&self) -> EgaSaveState {
+        EgaSaveState {
+            ticks_accum: self.ticks_accum,
+            cycles: self.cycles,
+            io_adjust: self.io_adjust,
+            mode_byte: self.mode_byte,
+            display_mode: self.display_mode,
+            mode_enable: self.mode_enable,
+            mode_graphics: self.mode_graphics,
+            mode_bw: self.mode_bw,
+            mode_line_gfx: self.mode_line_gfx,
+            mode_hires_gfx: self.mode_hires_gfx,
+            mode_hires_txt: self.mode_hires_txt,
+            mode_blinking: self.mode_blinking,
+            scanline: self.scanline,
+            frame: self.frame,
+            scanline_cycles: self.scanline_cycles,
+            frame_cycles: self.frame_cycles,
+            cursor_frames: self.cursor_frames,
+            raster_x: self.raster_x,
+            raster_y: self.raster_y,
+            cur_char: self.cur_char,
+            next_char: self.next_char,
+            cur_attr: self.cur_attr,
+            next_attr: self.next_attr,
+            cur_fg: self.cur_fg,
+            cur_bg: self.cur_bg,
+            cur_blink: self.cur_blink,
+            blink_state: self.blink_state,
+            cursor_status: self.cursor_status,
+            cursor_slowblink: self.cursor_slowblink,
+            cursor_blink_rate: self.cursor_blink_rate,
+            cursor_attr: self.cursor_attr,
+            crtc: self.crtc.save_state(),
+            vma: self.vma,
+            sequencer: self.sequencer.save_state(),
+            gc: self.gc.save_state(),
+            ac: self.ac.save_state(),
+            pel_pan_latch: self.pel_pan_latch,
+            current_font: self.current_font,
+            misc_output_register: self.misc_output_register.into_bytes()[0],
+            rba: self.rba,
+            hsync_ct: self.hsync_ct,
+            vsync_ct: self.vsync_ct,
+            intr: self.intr,
+            last_intr: self.last_intr,
+            feature_bits: self.feature_bits,
+        }
+    }
+
+    fn restore_state(&mut self, state: EgaSaveState) {
+        self.ticks_accum = state.ticks_accum;
+        self.cycles = state.cycles;
+        self.io_adjust = state.io_adjust;
+        self.mode_byte = state.mode_byte;
+        self.display_mode = state.display_mode;
+        self.mode_enable = state.mode_enable;
+        self.mode_graphics = state.mode_graphics;
+        self.mode_bw = state.mode_bw;
+        self.mode_line_gfx = state.mode_line_gfx;
+        self.mode_hires_gfx = state.mode_hires_gfx;
+        self.mode_hires_txt = state.mode_hires_txt;
+        self.mode_blinking = state.mode_blinking;
+        self.scanline = state.scanline;
+        self.frame = state.frame;
+        self.scanline_cycles = state.scanline_cycles;
+        self.frame_cycles = state.frame_cycles;
+        self.cursor_frames = state.cursor_frames;
+        self.raster_x = state.raster_x;
+        self.raster_y = state.raster_y;
+        self.cur_char = state.cur_char;
+        self.next_char = state.next_char;
+        self.cur_attr = state.cur_attr;
+        self.next_attr = state.next_attr;
+        self.cur_fg = state.cur_fg;
+        self.cur_bg = state.cur_bg;
+        self.cur_blink = state.cur_blink;
+        self.blink_state = state.blink_state;
+        self.cursor_status = state.cursor_status;
+        self.cursor_slowblink = state.cursor_slowblink;
+        self.cursor_blink_rate = state.cursor_blink_rate;
+        self.cursor_attr = state.cursor_attr;
+        self.crtc.restore_state(state.crtc);
+        self.vma = state.vma;
+        self.sequencer.restore_state(state.sequencer);
+        self.gc.restore_state(state.gc);
+        self.ac.restore_state(state.ac);
+        self.pel_pan_latch = state.pel_pan_latch;
+        self.current_font = state.current_font;
+        self.misc_output_register = EMiscellaneousOutputRegister::from_bytes([state.misc_output_register]);
+        self.rba = state.rba;
+        self.hsync_ct = state.hsync_ct;
+        self.vsync_ct = state.vsync_ct;
+        self.intr = state.intr;
+        self.last_intr = state.last_intr;
+        self.feature_bits = state.feature_bits;
+    }
+}
+
 impl EGACard {
     pub fn new(trace_logger: TraceLogger, clock_mode: ClockingMode, video_frame_debug: bool, dip: Option<u8>) -> Self {
         let mut ega = Self::default();
@@ -817,6 +972,7 @@ impl EGACard {
             row_stride: EGA16_MAX_RASTER_X as usize,
             double_scan: false,
             mode_byte: 0,
+            register_changes: Vec::new(),
         }
     }
 
@@ -1417,7 +1573,35 @@ impl EGACard {
 
 #[cfg(test)]
 mod tests {
-    //use super::*;
+    use super::*;
+
+    #[test]
+    fn test_register_state() {
+        let mut sequencer = Sequencer::new();
+        sequencer.write_address(0x02);
+        sequencer.write_data(0x0F);
+        let regs = sequencer.get_register_state();
+        assert_eq!(regs.len(), 5);
+        assert_eq!(regs[2], ("SR02 Map Mask".to_string(), 0x0F));
+
+        let mut gc = GraphicsController::new();
+        gc.write_graphics_address(0x08);
+        gc.write_graphics_data(0x81);
+        let regs = gc.get_register_state();
+        assert_eq!(regs.len(), 9);
+        assert_eq!(regs[8], ("GR08 Bit Mask".to_string(), 0x81));
+
+        // The attribute controller alternates between address and data on each write.
+        let mut ac = AttributeController::new();
+        ac.write_attribute_register(0x03);
+        ac.write_attribute_register(0x3F);
+        ac.write_attribute_register(0x13);
+        ac.write_attribute_register(0x07);
+        let regs = ac.get_register_state();
+        assert_eq!(regs.len(), 20);
+        assert_eq!(regs[3], ("AR03 Palette 3".to_string(), 0x3F));
+        assert_eq!(regs[19], ("AR13 Horizontal Pel Panning".to_string(), 0x07));
+    }
 
     #[test]
     fn test_color_compare() {
@@ -31,12 +31,18 @@
 */
 
 use crate::{
-    device_traits::videocard::VideoCardStateEntry,
-    devices::ega::{tablegen::BIT_EXTEND_TABLE64, vram::Vram, EGA_CHARACTER_HEIGHT},
+    device_traits::videocard::{VideoCardStateEntry, EGA_SEQUENCER_REGISTER_NAMES},
+    devices::ega::{
+        tablegen::BIT_EXTEND_TABLE64,
+        vram::{Vram, VramSaveState},
+        EGA_CHARACTER_HEIGHT,
+    },
+    savestate::SaveState,
 };
 use modular_bitfield::{bitfield, prelude::*, BitfieldSpecifier};
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum SequencerRegister {
     Reset,
     ClockingMode,
@@ -138,6 +144,65 @@ impl Default for Sequencer {
     }
 }
 
+/// The sequencer registers and video memory.
+#[derive(Serialize, Deserialize)]
+pub struct SequencerSaveState {
+    address_byte: u8,
+    register_selected: SequencerRegister,
+    reset: u8,
+    clocking_mode: u8,
+    map_mask: u8,
+    character_map_select: u8,
+    memory_mode: u8,
+    clock_change_pending: bool,
+    clock_divisor: u32,
+    char_clock: u32,
+    font_select_enabled: bool,
+    font_offset_a: usize,
+    font_offset_b: usize,
+    vram: VramSaveState,
+}
+
+impl SaveState for Sequencer {
+    type State = SequencerSaveState;
+
+    fn save_state(&self) -> SequencerSaveState {
+        SequencerSaveState {
+            address_byte: self.address_byte,
+            register_selected: self.register_selected,
+            reset: self.reset,
+            clocking_mode: self.clocking_mode.into_bytes()[0],
+            map_mask: self.map_mask,
+            character_map_select: self.character_map_select.into_bytes()[0],
+            memory_mode: self.memory_mode.into_bytes()[0],
+            clock_change_pending: self.clock_change_pending,
+            clock_divisor: self.clock_divisor,
+            char_clock: self.char_clock,
+            font_select_enabled: self.font_select_enabled,
+            font_offset_a: self.font_offset_a,
+            font_offset_b: self.font_offset_b,
+            vram: self.vram.save_state(),
+        }
+    }
+
+    fn restore_state(&mut self, state: SequencerSaveState) {
+        self.address_byte = state.address_byte;
+        self.register_selected = state.register_selected;
+        self.reset = state.reset;
+        self.clocking_mode = SClockingModeRegister::from_bytes([state.clocking_mode]);
+        self.map_mask = state.map_mask;
+        self.character_map_select = SCharacterMapSelect::from_bytes([state.character_map_select]);
+        self.memory_mode = SMemoryModeRegister::from_bytes([state.memory_mode]);
+        self.clock_change_pending = state.clock_change_pending;
+        self.clock_divisor = state.clock_divisor;
+        self.char_clock = state.char_clock;
+        self.font_select_enabled = state.font_select_enabled;
+        self.font_offset_a = state.font_offset_a;
+        self.font_offset_b = state.font_offset_b;
+        self.vram.restore_state(state.vram);
+    }
+}
+
 impl Sequencer {
     pub fn new() -> Self {
         Sequencer::default()
@@ -363,6 +428,22 @@ impl Sequencer {
         offset + ((glyph as usize) * EGA_CHARACTER_HEIGHT) + row as usize
     }
 
+    /// Return the raw values of the sequencer registers.
+    pub fn get_register_state(&self) -> Vec<(String, u8)> {
+        let regs = [
+            self.reset,
+            self.clocking_mode.into_bytes()[0],
+            self.map_mask,
+            self.character_map_select.into_bytes()[0],
+            self.memory_mode.into_bytes()[0],
+        ];
+        EGA_SEQUENCER_REGISTER_NAMES
+            .iter()
+            .zip(regs)
+            .map(|(name, val)| (name.to_string(), val))
+            .collect()
+    }
+
     #[rustfmt::skip]
     pub fn get_state(&self) -> Vec<(String, VideoCardStateEntry)> {
         let mut sequencer_vec = Vec::new();
@@ -36,7 +36,8 @@
 
 */
 
-use crate::devices::ega::EGA_GFX_PLANE_SIZE;
+use crate::{devices::ega::EGA_GFX_PLANE_SIZE, savestate::SaveState};
+use serde::{Deserialize, Serialize};
 
 pub struct Vram {
     // Display Planes
@@ -45,6 +46,37 @@ pub struct Vram {
     linear_cga_buf: Box<[u8; EGA_GFX_PLANE_SIZE * 4]>,
 }
 
+/// The contents of the four display planes, stored one after another.
+#[derive(Serialize, Deserialize)]
+pub struct VramSaveState {
+    planes: Vec<u8>,
+}
+
+impl SaveState for Vram {
+    type State = VramSaveState;
+
+    fn save_state(&self) -> VramSaveState {
+        VramSaveState {
+            planes: self.planes.iter().flatten().copied().collect(),
+        }
+    }
+
+    fn restore_state(&mut self, state: VramSaveState) {
+        if state.planes.len() != self.planes.len() * EGA_GFX_PLANE_SIZE {
+            log::error!("VramSaveState: video memory size mismatch, memory not restored");
+            return;
+        }
+        let saved_planes = state.planes.chunks_exact(EGA_GFX_PLANE_SIZE);
+        for (plane, saved) in self.planes.iter_mut().zip(saved_planes) {
+            plane.copy_from_slice(saved);
+        }
+        // Rebuild the linear buffer from the restored planes.
+        for offset in 0..EGA_GFX_PLANE_SIZE {
+            self.deplane(offset);
+        }
+    }
+}
+
 impl Vram {
     pub fn new() -> Self {
         Self {
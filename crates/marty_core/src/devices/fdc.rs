@@ -41,13 +41,14 @@ use std::{
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
-    device_types::fdc::FloppyImageType,
+    device_types::fdc::{DataRate, FloppyImageType},
     devices::{
         dma,
-        floppy_drive::{FloppyDiskDrive, FloppyImageState},
+        floppy_drive::{BadSector, FloppyDiskDrive, FloppyDriveSaveState, FloppyImageState, SectorError},
     },
     machine_config::FloppyDriveConfig,
     machine_types::FdcType,
+    savestate::SaveState,
 };
 
 use marty_common::types::history_buffer::HistoryBuffer;
@@ -55,6 +56,7 @@ use marty_common::types::history_buffer::HistoryBuffer;
 use anyhow::{anyhow, Error};
 use fluxfox::prelude::*;
 use modular_bitfield::{bitfield, prelude::*};
+use serde::{Deserialize, Serialize};
 
 pub const FDC_LOG_LEN: usize = 1000;
 
@@ -70,6 +72,9 @@ pub const PCJR_IO_BASE: u16 = 0x00F0;
 pub const FDC_DIGITAL_OUTPUT_REGISTER: u16 = 0x02;
 pub const FDC_STATUS_REGISTER: u16 = 0x04;
 pub const FDC_DATA_REGISTER: u16 = 0x05;
+/// The Configuration Control Register selects the data rate. It is only present on AT-class controllers,
+/// so XT software never programs it.
+pub const FDC_CONFIG_CONTROL_REGISTER: u16 = 0x07;
 
 // Main Status Register Bit Definitions
 // --------------------------------------------------------------------------------
@@ -113,6 +118,13 @@ pub const DOR_JRFDC_WATCHDOG_ENABLE: u8 = 0b0010_0000;
 pub const DOR_JRFDC_WATCHDOG_TRIGGER: u8 = 0b0100_0000;
 
 pub const WATCHDOG_TIMEOUT: f64 = 3_000_000.0; // 3 seconds in microseconds
+/// Bytes between the index pulse and the first sector ID field of a standard MFM track
+/// (Gap 4a, sync, index address mark and Gap 1).
+pub const MFM_INDEX_TO_SECTOR_BYTES: usize = 146;
+/// Bytes between the index pulse and the first sector ID field of a standard FM track.
+pub const FM_INDEX_TO_SECTOR_BYTES: usize = 73;
+/// The controller gives up looking for a sector ID after this many index pulses.
+pub const NO_ID_INDEX_PULSES: f64 = 2.0;
 
 pub const COMMAND_MASK: u8 = 0b0001_1111;
 pub const COMMAND_SKIP_BIT: u8 = 0b0010_0000;
@@ -158,13 +170,14 @@ pub const ST3_DOUBLESIDED: u8 = 0b0000_1000;
 pub const ST3_HEAD: u8 = 0b0000_0100;
 
 /// Represent the state of the DIO bit of the Main Status Register in a readable way.
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum IoMode {
     ToCpu,
     FromCpu,
 }
 
 /// Represent the various commands that the NEC FDC knows how to handle.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
 pub enum Command {
     #[default]
     NoCommand,
@@ -207,7 +220,7 @@ pub enum InterruptCode {
 /// Attempt to classify every general error condition a virtual disk drive may experience.
 /// These states are used to build the status bytes presented after a command has been
 /// executed. The exact mapping between error conditions and status flags is uncertain...
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum DriveError {
     NoError,
     NoMedia,
@@ -216,6 +229,7 @@ pub enum DriveError {
     BadWrite,
     WriteProtect,
     DMAError,
+    MediaMismatch,
 }
 
 pub struct OperationSpecifier {
@@ -230,13 +244,17 @@ pub struct OperationSpecifier {
 /// terminate, and is called on a repeated basis by the run() method until complete.
 ///
 /// Operations usually involve DMA transfers.
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Operation {
     NoOperation,
-    ReadData(u8, DiskChs, u8, u8, u8, u8), // Physical head, id CHS, sector_size, track_len, gap3_len, data_len
-    ReadTrack(u8, DiskChs, u8, u8, u8, u8), // Physical head, CHS, sector_size, track_len, gap3_len, data_len
-    WriteData(u8, DiskChs, u8, u8, u8, u8, bool), // Physical head, id CHS, sector_size, track_len, gap3_len, data_len, deleted_data
-    FormatTrack(u8, u8, u8, u8, u8),              // head_select, sector_size, track_len, gap3_len, fill_byte
+    /// Physical head, id CHS, sector_size, track_len, gap3_len, data_len
+    ReadData(u8, #[serde(with = "disk_chs_serde")] DiskChs, u8, u8, u8, u8),
+    /// Physical head, CHS, sector_size, track_len, gap3_len, data_len
+    ReadTrack(u8, #[serde(with = "disk_chs_serde")] DiskChs, u8, u8, u8, u8),
+    /// Physical head, id CHS, sector_size, track_len, gap3_len, data_len, deleted_data
+    WriteData(u8, #[serde(with = "disk_chs_serde")] DiskChs, u8, u8, u8, u8, bool),
+    /// head_select, sector_size, track_len, gap3_len, fill_byte
+    FormatTrack(u8, u8, u8, u8, u8),
 }
 
 type CommandDispatchFn = fn(&mut FloppyController) -> Continuation;
@@ -263,6 +281,21 @@ pub struct DriveHeadSelect {
     unused:    B5,
 }
 
+/// Serialize a DiskChs as a (cylinder, head, sector) tuple.
+mod disk_chs_serde {
+    use fluxfox::prelude::DiskChs;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(chs: &DiskChs, serializer: S) -> Result<S::Ok, S::Error> {
+        (chs.c(), chs.h(), chs.s()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DiskChs, D::Error> {
+        let (c, h, s) = <(u16, u8, u8)>::deserialize(deserializer)?;
+        Ok(DiskChs::new(c, h, s))
+    }
+}
+
 #[derive(Default)]
 pub struct FdcDebugState {
     pub last_cmd: Command,
@@ -286,6 +319,7 @@ pub struct FloppyController {
     busy: bool,
     dio: IoMode,
     mt: bool,
+    mfm: bool,
     reading_command: bool,
     command: Command,
     command_fn: Option<CommandDispatchFn>,
@@ -298,6 +332,11 @@ pub struct FloppyController {
     operation_init: bool,
     operation_final_chs: DiskChs,
     send_interrupt: bool,
+    /// Time in microseconds until each drive sends a delayed interrupt, such as at the end of a seek.
+    /// Drives time their interrupts separately so that overlapped seeks each complete with an interrupt.
+    interrupt_delay_us: [Option<f64>; FDC_MAX_DRIVES],
+    /// The drive that raised the last interrupt, reported by Sense Interrupt at the end of a seek.
+    interrupt_drive: usize,
     pending_interrupt: bool,
     end_interrupt: bool,
     watchdog_enabled: bool,     // IBM PCJr only.  Watchdog timer enabled.
@@ -305,6 +344,13 @@ pub struct FloppyController {
     watchdog_triggered: bool,   // IBM PCJr only.  Watchdog timer triggered.
 
     last_error: DriveError,
+    /// The data rate programmed through the Configuration Control Register, or None if it has never
+    /// been written.
+    data_rate: Option<DataRate>,
+    /// The Step Rate Time field of the last Specify command, if any.
+    step_rate: Option<u8>,
+    /// The Head Load Time field of the last Specify command, if any.
+    head_load: Option<u8>,
     last_status_bytes: Vec<u8>,
 
     data_register_out: VecDeque<u8>,
@@ -344,6 +390,10 @@ impl IoDevice for FloppyController {
             }
             FDC_STATUS_REGISTER => self.handle_status_register_read(),
             FDC_DATA_REGISTER => self.handle_data_register_read(),
+            FDC_CONFIG_CONTROL_REGISTER => {
+                log::warn!("Read from Write-only CCR register");
+                0
+            }
             _ => unreachable!("FLOPPY: Bad port #"),
         }
     }
@@ -372,6 +422,9 @@ impl IoDevice for FloppyController {
             FDC_DATA_REGISTER => {
                 self.handle_data_register_write(data);
             }
+            FDC_CONFIG_CONTROL_REGISTER => {
+                self.handle_ccr_write(data);
+            }
             _ => unreachable!("FLOPPY: Bad port #"),
         }
     }
@@ -382,17 +435,85 @@ impl IoDevice for FloppyController {
             FdcType::IbmPCJrNec => PCJR_IO_BASE,
         };
 
-        vec![
+        let mut ports = vec![
             (
                 String::from("FDC Digital Output Register"),
                 base + FDC_DIGITAL_OUTPUT_REGISTER,
             ),
             (String::from("FDC Status Register"), base + FDC_STATUS_REGISTER),
             (String::from("FDC Data Register"), base + FDC_DATA_REGISTER),
-        ]
+        ];
+
+        // The PCjr has no room for a CCR in its port range.
+        if matches!(self.fdc_type, FdcType::IbmNec) {
+            ports.push((
+                String::from("FDC Configuration Control Register"),
+                base + FDC_CONFIG_CONTROL_REGISTER,
+            ));
+        }
+        ports
     }
 }
 
+/// Serializable state of the floppy disk controller and its drives, including any command or
+/// operation in progress. Inserted disks are not included, so the same images must be present when
+/// the state is restored.
+#[derive(Serialize, Deserialize)]
+pub struct FdcSaveState {
+    us_accumulator: f64,
+    watchdog_accumulator: f64,
+    status_byte: u8,
+    reset_flag: bool,
+    reset_sense_count: u8,
+    mrq: bool,
+    data_register: u8,
+    dma: bool,
+    dor: u8,
+    busy: bool,
+    dio: IoMode,
+    mt: bool,
+    mfm: bool,
+    reading_command: bool,
+    command: Command,
+    last_command: Command,
+    receiving_command: bool,
+    command_byte_n: u32,
+    command_skip: bool,
+    command_deleted: bool,
+    operation: Operation,
+    operation_init: bool,
+    #[serde(with = "disk_chs_serde")]
+    operation_final_chs: DiskChs,
+    send_interrupt: bool,
+    interrupt_delay_us: [Option<f64>; FDC_MAX_DRIVES],
+    interrupt_drive: usize,
+    pending_interrupt: bool,
+    end_interrupt: bool,
+    watchdog_enabled: bool,
+    watchdog_trigger_bit: bool,
+    watchdog_triggered: bool,
+    last_error: DriveError,
+    data_rate: Option<DataRate>,
+    step_rate: Option<u8>,
+    head_load: Option<u8>,
+    last_status_bytes: Vec<u8>,
+    data_register_out: VecDeque<u8>,
+    data_register_in: VecDeque<u8>,
+    format_buffer: VecDeque<u8>,
+    drives: Vec<FloppyDriveSaveState>,
+    drive_select: usize,
+    in_dma: bool,
+    dma_byte_count: usize,
+    dma_bytes_left: usize,
+    pio_byte_count: usize,
+    pio_sector_byte_count: usize,
+    pio_bytes_left: usize,
+    xfer_size_sectors: usize,
+    xfer_size_bytes: usize,
+    xfer_completed_sectors: usize,
+    xfer_buffer: Vec<u8>,
+}
+
 impl Default for FloppyController {
     fn default() -> Self {
         Self {
@@ -409,6 +530,7 @@ impl Default for FloppyController {
             busy: false,
             dio: IoMode::FromCpu,
             mt: false,
+            mfm: true,
             reading_command: false,
             command: Command::NoCommand,
             command_fn: None,
@@ -422,9 +544,14 @@ impl Default for FloppyController {
             operation_final_chs: DiskChs::default(),
 
             last_error: DriveError::NoError,
+            data_rate: None,
+            step_rate: None,
+            head_load: None,
             last_status_bytes: vec![0; 3],
 
             send_interrupt: false,
+            interrupt_delay_us: [None; FDC_MAX_DRIVES],
+            interrupt_drive: 0,
             pending_interrupt: false,
             end_interrupt: false,
             watchdog_enabled: false,
@@ -515,6 +642,8 @@ impl FloppyController {
         self.command_byte_n = 0;
 
         self.send_interrupt = false;
+        self.interrupt_delay_us = [None; FDC_MAX_DRIVES];
+        self.interrupt_drive = 0;
         self.pending_interrupt = false;
         self.end_interrupt = false;
 
@@ -549,7 +678,7 @@ impl FloppyController {
         if idx >= self.drive_ct {
             panic!("Invalid drive index");
         }
-        &self.drives[self.drive_select]
+        &self.drives[idx]
     }
 
     /// Load a disk into the specified drive
@@ -657,13 +786,43 @@ impl FloppyController {
     }
 
     pub fn motor_off(&mut self, drive_select: usize) {
-        if self.drives[drive_select].motor_on {
-            log::trace!("Drive {}: turning motor off.", drive_select)
-        }
-        self.drives[drive_select].motor_on = false;
+        self.drives[drive_select].motor_off();
         //self.drives[drive_select].ready = false;    // Breaks booting(?)
     }
 
+    /// Return true if the image in the specified drive has been modified since it was loaded or last saved.
+    pub fn is_dirty(&self, drive_select: usize) -> bool {
+        self.drives[drive_select].is_dirty()
+    }
+
+    pub fn clear_dirty(&mut self, drive_select: usize) {
+        self.drives[drive_select].clear_dirty();
+    }
+
+    /// Simulate an error when reading the sector with the specified ID from the specified drive.
+    pub fn set_sector_error(&mut self, drive_select: usize, cylinder: u16, head: u8, sector: u8, error: SectorError) {
+        self.drives[drive_select].set_sector_error(cylinder, head, sector, error);
+    }
+
+    pub fn clear_sector_errors(&mut self, drive_select: usize) {
+        self.drives[drive_select].clear_sector_errors();
+    }
+
+    /// Verify the sector CRCs of the image in the specified drive.
+    pub fn verify_image(&self, drive_select: usize) -> Option<Vec<BadSector>> {
+        self.drives[drive_select].verify_image()
+    }
+
+    /// Repair damaged sectors in the specified drive's image from a reference image.
+    pub fn repair_image(
+        &mut self,
+        drive_select: usize,
+        reference: &mut DiskImage,
+        bad_sectors: &[BadSector],
+    ) -> Result<usize, Error> {
+        self.drives[drive_select].repair_image(reference, bad_sectors)
+    }
+
     pub fn write_protect(&mut self, drive_select: usize, write_protected: bool) {
         self.drives[drive_select].write_protected = write_protected;
     }
@@ -777,6 +936,146 @@ impl FloppyController {
         self.dor = data;
     }
 
+    /// Handle a write to the Configuration Control Register, selecting the data rate.
+    pub fn handle_ccr_write(&mut self, data: u8) {
+        let data_rate = DataRate::from_ccr(data);
+        log::trace!("FDC data rate set to {}", data_rate);
+        self.data_rate = Some(data_rate);
+    }
+
+    /// Return the data rate programmed through the Configuration Control Register, if any.
+    pub fn data_rate(&self) -> Option<DataRate> {
+        self.data_rate
+    }
+
+    /// Return the physical head the current operation is using.
+    fn operation_head(&self) -> u8 {
+        match self.operation {
+            Operation::ReadData(h, ..)
+            | Operation::ReadTrack(h, ..)
+            | Operation::WriteData(h, ..)
+            | Operation::FormatTrack(h, ..) => h,
+            Operation::NoOperation => 0,
+        }
+    }
+
+    /// Return the data rate the controller is running at for the selected drive. If the data rate
+    /// has not been programmed, we assume the rate the track under the head requires.
+    fn effective_data_rate(&self) -> DataRate {
+        self.data_rate
+            .or_else(|| self.drives[self.drive_select].required_data_rate(self.operation_head()))
+            .unwrap_or(DataRate::Rate250Kbps)
+    }
+
+    /// Return the time in microseconds for the selected drive to deliver one byte.
+    fn byte_period_us(&self) -> f64 {
+        self.effective_data_rate().byte_period_us(self.mfm)
+    }
+
+    /// Return the factor the delays programmed by the Specify command are scaled by. The command's
+    /// units are defined at 500Kbps, and the controller's timers run slower at lower data rates.
+    fn specify_scale(&self) -> f64 {
+        500.0 / self.effective_data_rate().kbps()
+    }
+
+    /// Return the time in milliseconds between step pulses for the specified drive. A drive can't
+    /// step faster than its mechanism allows, whatever the controller has been programmed with.
+    fn step_time_ms(&self, drive_select: usize) -> f64 {
+        let drive_step_ms = self.drives[drive_select].timing().step_ms;
+        self.step_rate.map_or(drive_step_ms, |srt| {
+            ((16 - srt) as f64 * self.specify_scale()).max(drive_step_ms)
+        })
+    }
+
+    /// Return the time in microseconds for the specified drive to step its heads from its current
+    /// cylinder to the specified cylinder and let them settle.
+    fn seek_time_us(&self, drive_select: usize, cylinder: u16) -> f64 {
+        let drive = &self.drives[drive_select];
+        let steps = drive.chsn.c().abs_diff(cylinder);
+        if steps == 0 {
+            return 0.0;
+        }
+        (steps as f64 * self.step_time_ms(drive_select) + drive.timing().head_settle_ms) * 1000.0
+    }
+
+    /// Return the time in microseconds to load the heads of the selected drive, or 0 if they are
+    /// already loaded. A Head Load Time of 0 is the longest delay the controller can be programmed with.
+    fn head_load_time_us(&self) -> f64 {
+        let drive = &self.drives[self.drive_select];
+        if drive.head_loaded {
+            return 0.0;
+        }
+        let hlt_ms = self.head_load.map_or(0.0, |hlt| match hlt {
+            0 => 256.0 * self.specify_scale(),
+            _ => hlt as f64 * 2.0 * self.specify_scale(),
+        });
+        hlt_ms.max(drive.timing().head_load_ms) * 1000.0
+    }
+
+    /// Send an interrupt for the specified drive once the specified number of microseconds have elapsed.
+    fn delay_interrupt(&mut self, drive_select: usize, us: f64) {
+        if us > 0.0 {
+            self.interrupt_delay_us[drive_select] = Some(us);
+        }
+        else {
+            self.interrupt_drive = drive_select;
+            self.send_interrupt = true;
+        }
+    }
+
+    /// Start timing a data transfer from the index pulse, so the first byte is delivered once the
+    /// head reaches the first sector. If the spindle is still coming up to speed or the heads need
+    /// to be loaded, the transfer is delayed until the drive is ready.
+    fn start_transfer_timing(&mut self) {
+        let index_bytes = match self.mfm {
+            true => MFM_INDEX_TO_SECTOR_BYTES,
+            false => FM_INDEX_TO_SECTOR_BYTES,
+        };
+        let drive_delay_us = self.drives[self.drive_select].spin_up_remaining_us() + self.head_load_time_us();
+        self.drives[self.drive_select].head_loaded = true;
+        self.us_accumulator = -(drive_delay_us + index_bytes as f64 * self.byte_period_us());
+    }
+
+    /// Return true if enough time has elapsed for the next byte of a data transfer to be available.
+    fn transfer_byte_ready(&mut self) -> bool {
+        let period = self.byte_period_us();
+        if self.us_accumulator >= period {
+            self.us_accumulator -= period;
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Check that the selected drive can access the track under the specified head at the current data
+    /// rate. If it can't, the
+    /// controller would never find a sector ID, so we terminate the command with a missing address
+    /// mark error and return true. The controller only gives up after it has seen the index pulse
+    /// twice, so the interrupt is delayed by two revolutions of the media.
+    fn reject_incompatible_media(
+        &mut self,
+        func: &str,
+        drive_select: usize,
+        head: u8,
+        chs: DiskChs,
+        sector_size: u8,
+    ) -> bool {
+        match self.drives[drive_select].check_media(self.data_rate, self.mfm, head) {
+            Ok(()) => false,
+            Err(mismatch) => {
+                log::warn!("{}(): drive {} can't access media: {}", func, drive_select, mismatch);
+                self.last_error = DriveError::MediaMismatch;
+                self.send_results_phase(InterruptCode::AbnormalTermination, drive_select, chs, sector_size);
+                self.delay_interrupt(
+                    drive_select,
+                    NO_ID_INDEX_PULSES * self.drives[drive_select].timing().revolution_us(),
+                );
+                true
+            }
+        }
+    }
+
     /// Create the ST0 status register bitfield with the given parameters.
     ///
     /// Note: returning an Interrupt Code of Abnormal Termination will result in a "General failure reading drive"
@@ -830,6 +1129,7 @@ impl FloppyController {
         st1_byte |= match self.last_error {
             DriveError::BadRead | DriveError::BadWrite | DriveError::BadSeek => ST1_NODATA,
             DriveError::WriteProtect => ST1_WRITE_PROTECT | ST1_NO_ID,
+            DriveError::MediaMismatch => ST1_NO_ID,
             _ => 0,
         };
 
@@ -935,6 +1235,21 @@ impl FloppyController {
         out_byte
     }
 
+    fn command_dispatch_fn(command: Command) -> Option<CommandDispatchFn> {
+        match command {
+            Command::ReadTrack => Some(FloppyController::command_read_track),
+            Command::WriteData => Some(FloppyController::command_write_data),
+            Command::ReadData => Some(FloppyController::command_read_data),
+            Command::FormatTrack => Some(FloppyController::command_format_track),
+            Command::FixDriveData => Some(FloppyController::command_fix_drive_data),
+            Command::CheckDriveStatus => Some(FloppyController::command_check_drive_status),
+            Command::CalibrateDrive => Some(FloppyController::command_calibrate_drive),
+            Command::ReadSectorID => Some(FloppyController::command_read_sector_id),
+            Command::SeekParkHead => Some(FloppyController::command_seek_head),
+            _ => None,
+        }
+    }
+
     pub fn set_command(&mut self, command: Command, n_bytes: u32, command_fn: CommandDispatchFn) {
         // Since we are entering a new command, clear the previous error status
         self.last_error = DriveError::NoError;
@@ -989,6 +1304,7 @@ impl FloppyController {
             let command_byte = CommandByte::from_bytes([data]);
             let command = command_byte.command();
             self.mt = command_byte.mt();
+            self.mfm = command_byte.mfm();
             self.command_deleted = false;
             match command {
                 COMMAND_READ_TRACK => {
@@ -1106,6 +1422,7 @@ impl FloppyController {
         */
 
         let mut st0_byte = ST0_INVALID_OPCODE;
+        let mut sense_drive = self.drive_select;
 
         if self.reset_flag {
             // FDC was just reset, answer with an ST0 for the first drive, but prepare to send up
@@ -1114,21 +1431,14 @@ impl FloppyController {
             self.reset_sense_count = 1;
             self.reset_flag = false;
         }
-        else if let Command::SenseIntStatus = self.last_command {
-            // This Sense Interrupt command was preceded by another.
+        else if self.reset_sense_count > 0 && matches!(self.last_command, Command::SenseIntStatus) {
+            // This Sense Interrupt command continues the sequence that follows a reset.
             // Advance the reset sense count to clear all drives assuming the calling code is doing
-            // a four sense-interrupt sequence.
-            if self.reset_sense_count < 4 {
-                st0_byte |= ST0_RESET;
-                st0_byte |= self.reset_sense_count & 0x03;
-                self.reset_sense_count += 1;
-            }
-            else {
-                // More than four sense interrupts in a row shouldn't happen
-                st0_byte = ST0_INVALID_OPCODE;
-                self.reset_flag = false;
-                self.reset_sense_count = 0;
-            }
+            // a four sense-interrupt sequence. Once all four drives have been reported, Sense Interrupt
+            // is handled normally again, so that overlapped seeks can each be sensed in turn.
+            st0_byte |= ST0_RESET;
+            st0_byte |= self.reset_sense_count & 0x03;
+            self.reset_sense_count = (self.reset_sense_count + 1) % 4;
         }
         else {
             // Sense interrupt in response to some other command
@@ -1146,7 +1456,12 @@ impl FloppyController {
                     _ => InterruptCode::NormalTermination,
                 };
 
-                st0_byte = self.make_st0_byte(code, self.drive_select, seek_flag);
+                // A seek reports the drive that completed it, which may not be the selected drive if
+                // seeks were overlapped.
+                if seek_flag {
+                    sense_drive = self.interrupt_drive;
+                }
+                st0_byte = self.make_st0_byte(code, sense_drive, seek_flag);
             }
             else {
                 // Sense Interrupt without pending interrupt is invalid
@@ -1159,7 +1474,7 @@ impl FloppyController {
         self.data_register_out.push_back(cb0);
 
         // Send Current Cylinder to FIFO
-        let cb1 = self.drives[self.drive_select].chsn.c();
+        let cb1 = self.drives[sense_drive].chsn.c();
         self.data_register_out.push_back(cb1 as u8);
 
         // We have data for CPU to read
@@ -1173,10 +1488,13 @@ impl FloppyController {
     }
 
     /// Perform the Fix Drive Data command.
-    /// We don't do anything currently with the provided values which are only useful for real drive timings.
+    /// We keep the step rate and head load times to time seeks and transfers. The head unload time and
+    /// non-DMA flag are not used.
     pub fn command_fix_drive_data(&mut self) -> Continuation {
         let steprate_unload = self.data_register_in.pop_front().unwrap();
         let headload_ndm = self.data_register_in.pop_front().unwrap();
+        self.step_rate = Some(steprate_unload >> 4);
+        self.head_load = Some(headload_ndm >> 1);
 
         let log_str = format!(
             "steprate_unload: {:08b}, headload_ndm: {:08b}",
@@ -1216,13 +1534,15 @@ impl FloppyController {
 
         // Set drive select and seek to cylinder 0
         self.drive_select = drive_select;
+        let seek_time_us = self.seek_time_us(drive_select, 0);
         self.drives[drive_select].seek(0);
 
         let log_str = format!("drive_select: {}", drive_select);
         self.log_cmd(Command::CalibrateDrive, "command_calibrate_drive", &log_str);
 
-        // Calibrate command sends interrupt when complete
-        self.send_interrupt = true;
+        // Calibrate command sends interrupt when the heads reach cylinder 0
+        self.drives[drive_select].positioning = seek_time_us > 0.0;
+        self.delay_interrupt(drive_select, seek_time_us);
         Continuation::CommandComplete
     }
 
@@ -1230,9 +1550,8 @@ impl FloppyController {
     ///
     /// This command has no result phase. The status of the command is checked via Sense Interrupt.
     pub fn command_seek_head(&mut self) -> Continuation {
-        // A real floppy drive would take some time to seek
-        // Not sure how to go about determining proper timings. For now, seek instantly
-
+        // The seek completes immediately, but the interrupt is delayed until the drive would have
+        // stepped its heads to the new cylinder.
         let dhs = DriveHeadSelect::from_bytes([self.data_register_in.pop_front().unwrap()]);
         let cylinder = self.data_register_in.pop_front().unwrap();
         let drive = self.select_drive(dhs.drive() as usize);
@@ -1251,6 +1570,7 @@ impl FloppyController {
         }
 
         // Seek to cylinder given in command
+        let seek_time_us = self.seek_time_us(self.drive_select, cylinder as u16);
         self.drives[self.drive_select].seek(cylinder as u16);
 
         let log_str = format!(
@@ -1263,7 +1583,8 @@ impl FloppyController {
         self.log_cmd(Command::SeekParkHead, "command_seek_head", &log_str);
 
         self.last_error = DriveError::NoError;
-        self.send_interrupt = true;
+        self.drives[self.drive_select].positioning = seek_time_us > 0.0;
+        self.delay_interrupt(self.drive_select, seek_time_us);
         Continuation::CommandComplete
     }
 
@@ -1298,6 +1619,10 @@ impl FloppyController {
                 return Continuation::CommandComplete;
             }
 
+            if self.reject_incompatible_media(func, dhs.drive() as usize, dhs.head(), chs, sector_size) {
+                return Continuation::CommandComplete;
+            }
+
             // Start read operation
             self.operation = Operation::ReadTrack(dhs.head(), chs, sector_size, track_len, gap3_len, data_len);
 
@@ -1387,6 +1712,10 @@ impl FloppyController {
                 return Continuation::CommandComplete;
             }
 
+            if self.reject_incompatible_media(func, dhs.drive() as usize, dhs.head(), chs, sector_size) {
+                return Continuation::CommandComplete;
+            }
+
             // Start read operation
             self.operation = Operation::ReadData(dhs.head(), chs, sector_size, eot, gap3_len, data_len);
 
@@ -1457,6 +1786,10 @@ impl FloppyController {
         }
 
         if self.select_drive(drive_select).is_some() {
+            if self.reject_incompatible_media("command_write_data", drive_select, head_select, chs, sector_size) {
+                return Continuation::CommandComplete;
+            }
+
             // Start write operation
             self.operation = Operation::WriteData(
                 head_select,
@@ -1516,9 +1849,14 @@ impl FloppyController {
         let gap3_len = self.data_register_in.pop_front().unwrap();
         let fill_byte = self.data_register_in.pop_front().unwrap();
 
-        let _drive_select = (drive_head_select & 0x03) as usize;
+        let drive_select = (drive_head_select & 0x03) as usize;
         let head_select = (drive_head_select >> 2) & 0x01;
 
+        let chs = self.drives[drive_select].chsn.into();
+        if self.reject_incompatible_media("command_format_track", drive_select, head_select, chs, sector_size) {
+            return Continuation::CommandComplete;
+        }
+
         // Start format operation
         self.operation_init = false;
         self.operation = Operation::FormatTrack(head_select, sector_size, track_len, gap3_len, fill_byte);
@@ -1551,13 +1889,23 @@ impl FloppyController {
         let drive_head_select = self.data_register_in.pop_front().unwrap();
 
         let drive_select = (drive_head_select & 0x03) as usize;
-        let _head_select = (drive_head_select >> 2) & 0x01;
+        let head_select = (drive_head_select >> 2) & 0x01;
 
         let chsn = self.selected_drive().chsn;
 
         let log_str = format!("drive_select: {} chsn: {}", drive_head_select, chsn);
         self.log_cmd(Command::ReadSectorID, "command_read_sector_id", &log_str);
 
+        if self.reject_incompatible_media(
+            "command_read_sector_id",
+            drive_select,
+            head_select,
+            chsn.into(),
+            chsn.n(),
+        ) {
+            return Continuation::CommandComplete;
+        }
+
         self.send_results_phase(InterruptCode::NormalTermination, drive_select, chsn.into(), chsn.n());
 
         self.drives[drive_select].advance_sector();
@@ -1625,29 +1973,28 @@ impl FloppyController {
             self.pio_byte_count = 0;
             self.pio_sector_byte_count = 0;
             self.operation_init = true;
+            self.start_transfer_timing();
         }
 
         if self.pio_bytes_left > 0 {
-            // Calculate how many sectors we've done
-            // TODO: fix me for sector size
-            if (self.pio_bytes_left < self.xfer_size_bytes)
-                && (self.pio_bytes_left % 512 == 0)
-                && self.data_register_out.is_empty()
-            {
-                // Completed one sector
-                self.xfer_completed_sectors += 1;
-                self.pio_sector_byte_count = 0;
-                log::trace!(
-                    "operation_read_sector_pio: Transferred {}/{} sectors, {}/{} bytes ({} left)",
-                    self.xfer_completed_sectors,
-                    self.xfer_size_sectors,
-                    self.pio_byte_count,
-                    self.xfer_size_bytes,
-                    self.pio_bytes_left
-                );
-            }
+            // Deliver the next byte once the CPU has taken the last one and it has arrived from the disk
+            if self.data_register_out.is_empty() && self.transfer_byte_ready() {
+                // Calculate how many sectors we've done
+                // TODO: fix me for sector size
+                if (self.pio_bytes_left < self.xfer_size_bytes) && (self.pio_bytes_left % 512 == 0) {
+                    // Completed one sector
+                    self.xfer_completed_sectors += 1;
+                    self.pio_sector_byte_count = 0;
+                    log::trace!(
+                        "operation_read_sector_pio: Transferred {}/{} sectors, {}/{} bytes ({} left)",
+                        self.xfer_completed_sectors,
+                        self.xfer_size_sectors,
+                        self.pio_byte_count,
+                        self.xfer_size_bytes,
+                        self.pio_bytes_left
+                    );
+                }
 
-            if self.data_register_out.is_empty() {
                 let byte = self.drives[self.drive_select].read_operation_buf();
                 log::trace!(
                     "Read byte: {:02X}, bytes remaining: {} DR: {}",
@@ -1751,6 +2098,14 @@ impl FloppyController {
                     log::trace!("Read sector command accepted, new chs: {}", read_result.new_chs);
                     self.operation_final_chs = read_result.new_chs;
 
+                    if read_result.timeout {
+                        // Simulate a hung controller. We end the operation but never enter the result phase
+                        // or raise an interrupt, so the FDC stays busy until it is reset.
+                        log::warn!("Read sector command timed out (simulated)");
+                        self.operation = Operation::NoOperation;
+                        return;
+                    }
+
                     if read_result.not_found {
                         self.send_results_phase(InterruptCode::AbnormalTermination, self.drive_select, chs, n);
                         self.operation = Operation::NoOperation;
@@ -1777,24 +2132,25 @@ impl FloppyController {
             self.xfer_size_bytes = xfer_sectors * sector_size_decoded;
             self.dma_bytes_left = xfer_sectors * sector_size_decoded;
             self.operation_init = true;
+            self.start_transfer_timing();
         }
 
         if self.dma_bytes_left > 0 {
             // Bytes left to transfer
 
-            // Calculate how many sectors we've done
-            if (self.dma_bytes_left < self.xfer_size_bytes) && (self.dma_bytes_left % sector_size_decoded == 0) {
-                // Completed one sector
-
-                self.xfer_completed_sectors += 1;
-                // log::trace!(
-                //     "operation_read_sector: Transferred {} sectors.",
-                //     self.xfer_completed_sectors
-                // );
-            }
+            // Check if DMA is ready and the next byte has arrived from the disk
+            if dma.check_dma_ready(FDC_DMA) && self.transfer_byte_ready() {
+                // Calculate how many sectors we've done
+                if (self.dma_bytes_left < self.xfer_size_bytes) && (self.dma_bytes_left % sector_size_decoded == 0) {
+                    // Completed one sector
+
+                    self.xfer_completed_sectors += 1;
+                    // log::trace!(
+                    //     "operation_read_sector: Transferred {} sectors.",
+                    //     self.xfer_completed_sectors
+                    // );
+                }
 
-            // Check if DMA is ready
-            if dma.check_dma_ready(FDC_DMA) {
                 let byte = self.drives[self.drive_select].read_operation_buf();
 
                 dma.do_dma_write_u8(bus, FDC_DMA, byte);
@@ -1891,6 +2247,7 @@ impl FloppyController {
             self.xfer_size_bytes = self.xfer_size_sectors * sector_size_bytes;
             self.dma_bytes_left = self.xfer_size_bytes;
             self.operation_init = true;
+            self.start_transfer_timing();
         }
 
         if self.dma_bytes_left == sector_size_bytes {
@@ -1901,8 +2258,8 @@ impl FloppyController {
         if self.dma_bytes_left > 0 {
             // Bytes left to transfer
 
-            // Check if DMA is ready
-            if dma.check_dma_ready(FDC_DMA) {
+            // Check if DMA is ready and the disk is ready for the next byte
+            if dma.check_dma_ready(FDC_DMA) && self.transfer_byte_ready() {
                 let byte = dma.do_dma_read_u8(bus, FDC_DMA);
 
                 self.xfer_buffer.push(byte);
@@ -2073,24 +2430,25 @@ impl FloppyController {
             self.xfer_size_bytes = xfer_sectors * sector_size_decoded;
             self.dma_bytes_left = xfer_sectors * sector_size_decoded;
             self.operation_init = true;
+            self.start_transfer_timing();
         }
 
         if self.dma_bytes_left > 0 {
             // Bytes left to transfer
 
-            // Calculate how many sectors we've done
-            if (self.dma_bytes_left < self.xfer_size_bytes) && (self.dma_bytes_left % sector_size_decoded == 0) {
-                // Completed one sector
+            // Check if DMA is ready and the next byte has arrived from the disk
+            if dma.check_dma_ready(FDC_DMA) && self.transfer_byte_ready() {
+                // Calculate how many sectors we've done
+                if (self.dma_bytes_left < self.xfer_size_bytes) && (self.dma_bytes_left % sector_size_decoded == 0) {
+                    // Completed one sector
 
-                self.xfer_completed_sectors += 1;
-                log::trace!(
-                    "operation_read_track():  Transferred {} sectors.",
-                    self.xfer_completed_sectors
-                );
-            }
+                    self.xfer_completed_sectors += 1;
+                    log::trace!(
+                        "operation_read_track():  Transferred {} sectors.",
+                        self.xfer_completed_sectors
+                    );
+                }
 
-            // Check if DMA is ready
-            if dma.check_dma_ready(FDC_DMA) {
                 let byte = self.drives[self.drive_select].read_operation_buf();
 
                 dma.do_dma_write_u8(bus, FDC_DMA, byte);
@@ -2334,25 +2692,44 @@ impl FloppyController {
             }
         }
 
-        // Send an interrupt if one is queued
-        if self.send_interrupt {
-            bus.pic_mut().as_mut().unwrap().request_interrupt(FDC_IRQ);
-            self.pending_interrupt = true;
-            self.send_interrupt = false;
+        for drive in self.drives.iter_mut() {
+            drive.run(us);
         }
 
-        // End an interrupt if one was handled
+        // End an interrupt if one was handled. This is done before any new interrupt is sent, so that
+        // an interrupt raised right after a Sense Interrupt is not lost.
         if self.end_interrupt {
             bus.pic_mut().as_mut().unwrap().clear_interrupt(FDC_IRQ);
             self.pending_interrupt = false;
             self.end_interrupt = false;
         }
 
+        // Count down to each drive's delayed interrupt, such as the end of a seek
+        for drive_select in 0..FDC_MAX_DRIVES {
+            if let Some(delay_us) = self.interrupt_delay_us[drive_select].as_mut() {
+                *delay_us -= us;
+                if *delay_us <= 0.0 {
+                    self.interrupt_delay_us[drive_select] = None;
+                    self.drives[drive_select].positioning = false;
+                    self.interrupt_drive = drive_select;
+                    self.send_interrupt = true;
+                }
+            }
+        }
+
+        // Send an interrupt if one is queued
+        if self.send_interrupt {
+            bus.pic_mut().as_mut().unwrap().request_interrupt(FDC_IRQ);
+            self.pending_interrupt = true;
+            self.send_interrupt = false;
+        }
+
         // Run operation
         #[allow(unreachable_patterns)]
         match self.operation {
             Operation::NoOperation => {
-                // Do nothing
+                // Nothing to time while idle
+                self.us_accumulator = 0.0;
             }
             Operation::ReadData(h, chs, sector_size, track_len, _gap3_len, _data_len) => match self.dma {
                 true => self.operation_read_data(dma, bus, h, chs, sector_size, track_len),
@@ -2373,3 +2750,339 @@ impl FloppyController {
         }
     }
 }
+
+impl SaveState for FloppyController {
+    type State = FdcSaveState;
+
+    fn save_state(&self) -> FdcSaveState {
+        FdcSaveState {
+            us_accumulator: self.us_accumulator,
+            watchdog_accumulator: self.watchdog_accumulator,
+            status_byte: self.status_byte,
+            reset_flag: self.reset_flag,
+            reset_sense_count: self.reset_sense_count,
+            mrq: self.mrq,
+            data_register: self.data_register,
+            dma: self.dma,
+            dor: self.dor,
+            busy: self.busy,
+            dio: self.dio,
+            mt: self.mt,
+            mfm: self.mfm,
+            reading_command: self.reading_command,
+            command: self.command,
+            last_command: self.last_command,
+            receiving_command: self.receiving_command,
+            command_byte_n: self.command_byte_n,
+            command_skip: self.command_skip,
+            command_deleted: self.command_deleted,
+            operation: self.operation.clone(),
+            operation_init: self.operation_init,
+            operation_final_chs: self.operation_final_chs,
+            send_interrupt: self.send_interrupt,
+            interrupt_delay_us: self.interrupt_delay_us,
+            interrupt_drive: self.interrupt_drive,
+            pending_interrupt: self.pending_interrupt,
+            end_interrupt: self.end_interrupt,
+            watchdog_enabled: self.watchdog_enabled,
+            watchdog_trigger_bit: self.watchdog_trigger_bit,
+            watchdog_triggered: self.watchdog_triggered,
+            last_error: self.last_error,
+            data_rate: self.data_rate,
+            step_rate: self.step_rate,
+            head_load: self.head_load,
+            last_status_bytes: self.last_status_bytes.clone(),
+            data_register_out: self.data_register_out.clone(),
+            data_register_in: self.data_register_in.clone(),
+            format_buffer: self.format_buffer.clone(),
+            drives: self.drives.iter().map(|drive| drive.save_state()).collect(),
+            drive_select: self.drive_select,
+            in_dma: self.in_dma,
+            dma_byte_count: self.dma_byte_count,
+            dma_bytes_left: self.dma_bytes_left,
+            pio_byte_count: self.pio_byte_count,
+            pio_sector_byte_count: self.pio_sector_byte_count,
+            pio_bytes_left: self.pio_bytes_left,
+            xfer_size_sectors: self.xfer_size_sectors,
+            xfer_size_bytes: self.xfer_size_bytes,
+            xfer_completed_sectors: self.xfer_completed_sectors,
+            xfer_buffer: self.xfer_buffer.clone(),
+        }
+    }
+
+    fn restore_state(&mut self, state: FdcSaveState) {
+        for (drive, saved) in self.drives.iter_mut().zip(state.drives) {
+            drive.restore_state(saved);
+        }
+        self.us_accumulator = state.us_accumulator;
+        self.watchdog_accumulator = state.watchdog_accumulator;
+        self.status_byte = state.status_byte;
+        self.reset_flag = state.reset_flag;
+        self.reset_sense_count = state.reset_sense_count;
+        self.mrq = state.mrq;
+        self.data_register = state.data_register;
+        self.dma = state.dma;
+        self.dor = state.dor;
+        self.busy = state.busy;
+        self.dio = state.dio;
+        self.mt = state.mt;
+        self.mfm = state.mfm;
+        self.reading_command = state.reading_command;
+        self.command = state.command;
+        self.command_fn = FloppyController::command_dispatch_fn(self.command);
+        self.last_command = state.last_command;
+        self.receiving_command = state.receiving_command;
+        self.command_byte_n = state.command_byte_n;
+        self.command_skip = state.command_skip;
+        self.command_deleted = state.command_deleted;
+        self.operation = state.operation;
+        self.operation_init = state.operation_init;
+        self.operation_final_chs = state.operation_final_chs;
+        self.send_interrupt = state.send_interrupt;
+        self.interrupt_delay_us = state.interrupt_delay_us;
+        self.interrupt_drive = state.interrupt_drive;
+        self.pending_interrupt = state.pending_interrupt;
+        self.end_interrupt = state.end_interrupt;
+        self.watchdog_enabled = state.watchdog_enabled;
+        self.watchdog_trigger_bit = state.watchdog_trigger_bit;
+        self.watchdog_triggered = state.watchdog_triggered;
+        self.last_error = state.last_error;
+        self.data_rate = state.data_rate;
+        self.step_rate = state.step_rate;
+        self.head_load = state.head_load;
+        self.last_status_bytes = state.last_status_bytes;
+        self.data_register_out = state.data_register_out;
+        self.data_register_in = state.data_register_in;
+        self.format_buffer = state.format_buffer;
+        self.drive_select = state.drive_select;
+        self.in_dma = state.in_dma;
+        self.dma_byte_count = state.dma_byte_count;
+        self.dma_bytes_left = state.dma_bytes_left;
+        self.pio_byte_count = state.pio_byte_count;
+        self.pio_sector_byte_count = state.pio_sector_byte_count;
+        self.pio_bytes_left = state.pio_bytes_left;
+        self.xfer_size_sectors = state.xfer_size_sectors;
+        self.xfer_size_bytes = state.xfer_size_bytes;
+        self.xfer_completed_sectors = state.xfer_completed_sectors;
+        self.xfer_buffer = state.xfer_buffer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{devices::pic::Pic, machine_types::FloppyDriveType};
+
+    /// Build a controller with two drives of the specified type, and a bus with a PIC to receive its
+    /// interrupts.
+    fn setup_fdc(fd_type: FloppyDriveType) -> (FloppyController, dma::DMAController, BusInterface) {
+        let drive = FloppyDriveConfig { fd_type, image: None };
+        let fdc = FloppyController::new(FdcType::IbmNec, vec![drive.clone(), drive]);
+        let mut bus = BusInterface::default();
+        *bus.pic_mut() = Some(Pic::new());
+        (fdc, dma::DMAController::new(), bus)
+    }
+
+    fn send_command(fdc: &mut FloppyController, bytes: &[u8]) {
+        for byte in bytes {
+            fdc.handle_data_register_write(*byte);
+        }
+    }
+
+    /// Read the bytes of a command's result phase.
+    fn read_results(fdc: &mut FloppyController) -> Vec<u8> {
+        let mut results = Vec::new();
+        while fdc.handle_status_register_read() & FDC_STATUS_DIO != 0 {
+            results.push(fdc.handle_data_register_read());
+        }
+        results
+    }
+
+    /// Run the controller until it raises an interrupt. Returns false if it doesn't within the timeout.
+    fn run_until_interrupt(
+        fdc: &mut FloppyController,
+        dma: &mut dma::DMAController,
+        bus: &mut BusInterface,
+        timeout_us: f64,
+    ) -> bool {
+        // Reading a result phase doesn't acknowledge the interrupt, so forget any previous one.
+        fdc.pending_interrupt = false;
+        let mut elapsed_us = 0.0;
+        while elapsed_us < timeout_us {
+            fdc.run(dma, bus, 100.0);
+            elapsed_us += 100.0;
+            if fdc.pending_interrupt {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Insert a formatted 360K disk into drive 0 and turn its motor on.
+    fn insert_disk(fdc: &mut FloppyController) {
+        fdc.create_new_image(0, StandardFormat::PcFloppy360, true).unwrap();
+        fdc.handle_dor_write(DOR_FDC_RESET | DOR_DMA_ENABLED | DOR_MOTOR_FDD_A);
+    }
+
+    /// Program DMA channel 2 to read a single sector into memory at 0x1000.
+    fn program_dma(dma: &mut dma::DMAController) {
+        dma.handle_channel_mode_register_write(0x46); // Single mode, write to memory, channel 2
+        dma.handle_clear_flopflop();
+        dma.handle_addr_port_write(FDC_DMA, 0x00);
+        dma.handle_addr_port_write(FDC_DMA, 0x10);
+        dma.handle_page_register_write(FDC_DMA, 0x00);
+        dma.handle_wc_port_write(FDC_DMA, 0xFF);
+        dma.handle_wc_port_write(FDC_DMA, 0x01);
+        dma.handle_channel_mask_register_write(0x02); // Unmask channel 2
+    }
+
+    /// Issue an MFM Read Data command for sector `s` of cylinder 0, head 0 on drive 0.
+    fn read_sector(fdc: &mut FloppyController, dma: &mut dma::DMAController, bus: &mut BusInterface, s: u8) -> Vec<u8> {
+        send_command(fdc, &[0x40 | COMMAND_READ_DATA, 0x00, 0, 0, s, 2, 9, 0x2A, 0xFF]);
+        assert!(run_until_interrupt(fdc, dma, bus, 1_000_000.0));
+        read_results(fdc)
+    }
+
+    #[test]
+    fn injected_sector_errors_set_status_bits() {
+        let (mut fdc, mut dma, mut bus) = setup_fdc(FloppyDriveType::Floppy360K);
+        insert_disk(&mut fdc);
+        fdc.set_sector_error(0, 0, 0, 1, SectorError::CrcError);
+        fdc.set_sector_error(0, 0, 0, 2, SectorError::NotFound);
+
+        // A data CRC error still transfers the sector, but is flagged in both ST1 and ST2.
+        program_dma(&mut dma);
+        let results = read_sector(&mut fdc, &mut dma, &mut bus, 1);
+        assert_eq!(results.len(), 7);
+        assert_ne!(results[0] & ST0_ABNORMAL_TERMINATION, 0);
+        assert_eq!(results[1] & ST1_CRC_ERROR, ST1_CRC_ERROR);
+        assert_eq!(results[2] & ST2_DATA_CRC_ERROR, ST2_DATA_CRC_ERROR);
+
+        // A missing sector terminates abnormally with No Data, and no data CRC error.
+        program_dma(&mut dma);
+        let results = read_sector(&mut fdc, &mut dma, &mut bus, 2);
+        assert_eq!(results.len(), 7);
+        assert_ne!(results[0] & ST0_ABNORMAL_TERMINATION, 0);
+        assert_eq!(results[1] & (ST1_NODATA | ST1_CRC_ERROR), ST1_NODATA);
+        assert_eq!(results[2] & ST2_DATA_CRC_ERROR, 0);
+
+        // Sectors without an injected error read cleanly.
+        program_dma(&mut dma);
+        let results = read_sector(&mut fdc, &mut dma, &mut bus, 3);
+        assert_eq!(&results[0..3], &[0x00, 0x00, 0x00]);
+        assert_eq!(results[5], 4);
+    }
+
+    #[test]
+    fn read_id_requires_track_data_rate() {
+        let (mut fdc, mut dma, mut bus) = setup_fdc(FloppyDriveType::Floppy144M);
+        fdc.create_new_image(0, StandardFormat::PcFloppy1440, true).unwrap();
+        fdc.handle_dor_write(DOR_FDC_RESET | DOR_DMA_ENABLED | DOR_MOTOR_FDD_A);
+
+        // High density tracks can't be read at the double density data rate. The controller never
+        // finds an ID, so the command fails with a missing address mark.
+        fdc.handle_ccr_write(0x02);
+        send_command(&mut fdc, &[0x40 | COMMAND_READ_SECTOR_ID, 0x00]);
+        assert!(run_until_interrupt(&mut fdc, &mut dma, &mut bus, 1_000_000.0));
+        let results = read_results(&mut fdc);
+        assert_ne!(results[0] & ST0_ABNORMAL_TERMINATION, 0);
+        assert_eq!(results[1] & ST1_NO_ID, ST1_NO_ID);
+
+        // Nor can they be read with FM encoding.
+        fdc.handle_ccr_write(0x00);
+        send_command(&mut fdc, &[COMMAND_READ_SECTOR_ID, 0x00]);
+        assert!(run_until_interrupt(&mut fdc, &mut dma, &mut bus, 1_000_000.0));
+        let results = read_results(&mut fdc);
+        assert_eq!(results[1] & ST1_NO_ID, ST1_NO_ID);
+
+        // At 500Kbps with MFM encoding, the ID is read normally.
+        send_command(&mut fdc, &[0x40 | COMMAND_READ_SECTOR_ID, 0x00]);
+        assert!(run_until_interrupt(&mut fdc, &mut dma, &mut bus, 1_000_000.0));
+        let results = read_results(&mut fdc);
+        assert_eq!(&results[0..3], &[0x00, 0x00, 0x00]);
+    }
+
+    /// A 40 track, single sided FM image with ten 256 byte sectors per track. Each sector is filled
+    /// with its sector number.
+    const FM_IMAGE: &[u8] = include_bytes!("../../assets/test/fm_ss_40t.imd");
+
+    /// Read sector 1 of cylinder 0, head 0 on drive 0 the way the BIOS INT 13h read routine does, with
+    /// the MT, MFM and SK flags set. Returns the result phase.
+    fn bios_read_sector(fdc: &mut FloppyController, dma: &mut dma::DMAController, bus: &mut BusInterface) -> Vec<u8> {
+        program_dma(dma);
+        send_command(fdc, &[0xE6, 0x00, 0, 0, 1, 2, 9, 0x2A, 0xFF]);
+        assert!(run_until_interrupt(fdc, dma, bus, 1_000_000.0));
+        read_results(fdc)
+    }
+
+    #[test]
+    fn bios_read_requires_matching_density() {
+        let (mut fdc, mut dma, mut bus) = setup_fdc(FloppyDriveType::Floppy360K);
+
+        // The BIOS reads MFM media.
+        insert_disk(&mut fdc);
+        let results = bios_read_sector(&mut fdc, &mut dma, &mut bus);
+        assert_eq!(results[0] & ST0_ABNORMAL_TERMINATION, 0);
+
+        // It can't find an address mark on FM media, and INT 13h reports error 02h.
+        fdc.load_image_from(0, FM_IMAGE.to_vec(), None, true).unwrap();
+        let results = bios_read_sector(&mut fdc, &mut dma, &mut bus);
+        assert_ne!(results[0] & ST0_ABNORMAL_TERMINATION, 0);
+        assert_eq!(results[1] & ST1_NO_ID, ST1_NO_ID);
+
+        // An FM read of the same media succeeds. The DMA transfer covers two 256 byte sectors.
+        program_dma(&mut dma);
+        send_command(&mut fdc, &[COMMAND_READ_DATA, 0x00, 0, 0, 1, 1, 10, 0x0E, 0xFF]);
+        assert!(run_until_interrupt(&mut fdc, &mut dma, &mut bus, 1_000_000.0));
+        let results = read_results(&mut fdc);
+        assert_eq!(&results[0..3], &[0x00, 0x00, 0x00]);
+        assert_eq!(bus.peek_u8(0x1000).unwrap(), 0x01);
+        assert_eq!(bus.peek_u8(0x10FF).unwrap(), 0x01);
+        assert_eq!(bus.peek_u8(0x1100).unwrap(), 0x02);
+    }
+
+    #[test]
+    fn overlapped_seeks_each_interrupt() {
+        let (mut fdc, mut dma, mut bus) = setup_fdc(FloppyDriveType::Floppy360K);
+        let positioning = |fdc: &mut FloppyController| fdc.handle_status_register_read() & 0x0F;
+
+        // Seek drive 0 to cylinder 10 and drive 1 to cylinder 30 without waiting in between.
+        send_command(&mut fdc, &[COMMAND_SEEK_HEAD, 0x00, 10]);
+        send_command(&mut fdc, &[COMMAND_SEEK_HEAD, 0x01, 30]);
+        assert_eq!(positioning(&mut fdc), FDC_STATUS_FDD_A_BUSY | FDC_STATUS_FDD_B_BUSY);
+
+        // Drive 0 has the shorter seek, so finishes first. Drive 1 is still positioning.
+        assert!(run_until_interrupt(&mut fdc, &mut dma, &mut bus, 1_000_000.0));
+        assert_eq!(positioning(&mut fdc), FDC_STATUS_FDD_B_BUSY);
+        send_command(&mut fdc, &[COMMAND_SENSE_INT_STATUS]);
+        let results = read_results(&mut fdc);
+        assert_eq!(results[0] & (ST0_SEEK_END | 0x03), ST0_SEEK_END);
+        assert_eq!(results[1], 10);
+
+        // Drive 1 raises its own interrupt when its seek completes.
+        assert!(run_until_interrupt(&mut fdc, &mut dma, &mut bus, 1_000_000.0));
+        assert_eq!(positioning(&mut fdc), 0);
+        send_command(&mut fdc, &[COMMAND_SENSE_INT_STATUS]);
+        let results = read_results(&mut fdc);
+        assert_eq!(results[0] & (ST0_SEEK_END | 0x03), ST0_SEEK_END | 0x01);
+        assert_eq!(results[1], 30);
+    }
+
+    #[test]
+    fn save_state_resumes_command() {
+        let (mut fdc, mut dma, mut bus) = setup_fdc(FloppyDriveType::Floppy360K);
+
+        // Save in the middle of receiving a Seek command.
+        send_command(&mut fdc, &[COMMAND_SEEK_HEAD, 0x00]);
+        let state = serde_json::to_string(&fdc.save_state()).unwrap();
+
+        let (mut restored, _, _) = setup_fdc(FloppyDriveType::Floppy360K);
+        restored.restore_state(serde_json::from_str(&state).unwrap());
+        send_command(&mut restored, &[10]);
+        assert!(run_until_interrupt(&mut restored, &mut dma, &mut bus, 1_000_000.0));
+        send_command(&mut restored, &[COMMAND_SENSE_INT_STATUS]);
+        let results = read_results(&mut restored);
+        assert_eq!(results[0] & (ST0_SEEK_END | 0x03), ST0_SEEK_END);
+        assert_eq!(results[1], 10);
+    }
+}
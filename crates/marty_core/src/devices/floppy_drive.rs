@@ -30,12 +30,24 @@
 */
 
 use crate::{
-    device_types::fdc::{FloppyImageType, DRIVE_CAPABILITIES},
+    device_types::fdc::{
+        drive_timing,
+        media_data_rate,
+        DataRate,
+        DriveTiming,
+        FloppyImageType,
+        MediaDensity,
+        MediaMismatch,
+        DRIVE_CAPABILITIES,
+    },
     machine_types::FloppyDriveType,
+    savestate::{MediaReference, SaveState},
 };
 use anyhow::{anyhow, Error};
 use fluxfox::{prelude::*, DiskSectorMap};
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     io::{Cursor, Read, Seek},
     path::{Path, PathBuf},
     sync::{Arc, RwLock},
@@ -69,7 +81,7 @@ macro_rules! write_lock {
     }};
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum FloppyDriveOperation {
     #[default]
     NoOperation,
@@ -85,7 +97,7 @@ pub enum FloppyDriveMechanicalState {
     HeadSeeking,
 }
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Serialize, Deserialize)]
 pub struct OperationStatus {
     pub(crate) op_type: FloppyDriveOperation,
     pub(crate) sector_not_found: bool,
@@ -111,6 +123,45 @@ pub struct DriveReadResult {
     pub(crate) sectors_read: u16,
     pub(crate) new_chs: DiskChs,
     pub(crate) deleted_mark: bool,
+    pub(crate) timeout: bool,
+}
+
+/// An error to simulate when reading a specific sector, regardless of the sector's contents in the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SectorError {
+    /// The sector's data fails its CRC check.
+    CrcError,
+    /// No sector with a matching ID can be found.
+    NotFound,
+    /// The sector has a deleted data address mark.
+    DeletionMark,
+    /// The controller never completes the operation. The host must time out and reset the controller.
+    Timeout,
+}
+
+impl SectorError {
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            SectorError::CrcError => "CRC",
+            SectorError::NotFound => "NF",
+            SectorError::DeletionMark => "DEL",
+            SectorError::Timeout => "T/O",
+        }
+    }
+}
+
+/// Map of simulated sector errors, keyed by sector ID (cylinder, head, sector).
+pub type SectorErrorMap = HashMap<(u16, u8, u8), SectorError>;
+
+/// A sector found to be damaged when verifying an image.
+#[derive(Clone, Debug)]
+pub struct BadSector {
+    /// The physical cylinder and head of the track containing the sector.
+    pub phys_ch: DiskCh,
+    /// The sector's ID.
+    pub chsn: DiskChsn,
+    pub address_crc_error: bool,
+    pub data_crc_error: bool,
 }
 
 pub struct DriveWriteResult {
@@ -128,6 +179,7 @@ pub struct FloppyImageState {
     pub format: Option<StandardFormat>,
     pub heads: u8,
     pub sector_map: DiskSectorMap,
+    pub sector_errors: SectorErrorMap,
 }
 
 impl FloppyImageState {
@@ -145,9 +197,19 @@ impl FloppyImageState {
     }
 }
 
+/// Return the number of sectors on the first track of the image, for display.
+fn media_sectors_per_track(image: &DiskImage) -> u8 {
+    image
+        .sector_map()
+        .first()
+        .and_then(|tracks| tracks.first())
+        .map_or(0, |sectors| sectors.len() as u8)
+}
+
 pub struct FloppyDiskDrive {
     drive_type: FloppyDriveType,
     drive_n: usize,
+    timing: DriveTiming,
     pub(crate) error_signal: bool,
 
     cylinder: u16,
@@ -157,10 +219,22 @@ pub struct FloppyDiskDrive {
 
     pub(crate) ready: bool,
     pub(crate) motor_on: bool,
+    /// Time remaining in microseconds until the spindle is up to speed.
+    spin_up_us: f64,
+    pub(crate) head_loaded: bool,
     pub(crate) positioning: bool,
     pub(crate) disk_present: bool,
     pub(crate) write_protected: bool,
     pub(crate) disk_image: Option<Arc<RwLock<DiskImage>>>,
+    /// Hash of the source file the current image was loaded from, used to identify media in save states.
+    image_hash: Option<u64>,
+    /// Set when the image has been written to or formatted since it was loaded or last saved.
+    dirty: bool,
+    /// Simulated read errors for the current image.
+    sector_errors: SectorErrorMap,
+    /// Set when a 1.2M drive has written to double density media. The narrower 1.2M head leaves the
+    /// edges of the original wide track intact, so the disk may not read reliably in a 360K drive.
+    narrow_tracks_written: bool,
 
     operation_status: OperationStatus,
     operation_buf: Cursor<Vec<u8>>,
@@ -172,11 +246,29 @@ pub struct FloppyDiskDrive {
     ref_write: u64,
 }
 
+/// The mechanical state of a floppy drive and its pending sector buffer. The inserted disk is not
+/// included, so the same image must be present when the state is restored.
+#[derive(Serialize, Deserialize)]
+pub struct FloppyDriveSaveState {
+    error_signal: bool,
+    cylinder: u16,
+    chsn: (u16, u8, u8, u8),
+    ready: bool,
+    motor_on: bool,
+    spin_up_us: f64,
+    head_loaded: bool,
+    positioning: bool,
+    operation_status: OperationStatus,
+    operation_buf: Vec<u8>,
+    operation_buf_pos: u64,
+}
+
 impl Default for FloppyDiskDrive {
     fn default() -> Self {
         Self {
             drive_type: Default::default(),
             drive_n: 0,
+            timing: drive_timing(Default::default()),
             error_signal: false,
             cylinder: 0,
             chsn: Default::default(),
@@ -184,10 +276,16 @@ impl Default for FloppyDiskDrive {
             media_geom: Default::default(),
             ready: false,
             motor_on: false,
+            spin_up_us: 0.0,
+            head_loaded: false,
             positioning: false,
             disk_present: false,
             write_protected: true,
             disk_image: None,
+            image_hash: None,
+            dirty: false,
+            sector_errors: SectorErrorMap::new(),
+            narrow_tracks_written: false,
 
             operation_status: Default::default(),
             operation_buf:    Cursor::new(Vec::with_capacity(512 * 2)),
@@ -213,6 +311,8 @@ impl FloppyDiskDrive {
         FloppyDiskDrive {
             drive_type,
             drive_n,
+            timing: drive_timing(drive_type),
+            ready: drive_type.is_3_5_inch(),
             drive_geom,
             supported_formats,
             ..Default::default()
@@ -228,7 +328,8 @@ impl FloppyDiskDrive {
         *self = Self {
             drive_type: self.drive_type,
             drive_n: self.drive_n,
-            ready: self.disk_present,
+            timing: self.timing,
+            ready: self.disk_present || self.drive_type.is_3_5_inch(),
             disk_present: self.disk_present,
             write_protected: self.write_protected,
             media_geom: self.media_geom,
@@ -236,6 +337,10 @@ impl FloppyDiskDrive {
             motor_on: false,
             positioning: false,
             disk_image: image,
+            image_hash: self.image_hash,
+            dirty: self.dirty,
+            sector_errors: std::mem::take(&mut self.sector_errors),
+            narrow_tracks_written: self.narrow_tracks_written,
             supported_formats: self.supported_formats.clone(),
             ..Default::default()
         };
@@ -249,6 +354,16 @@ impl FloppyDiskDrive {
         self.drive_type
     }
 
+    /// Return the mechanical timing of this drive.
+    pub fn timing(&self) -> DriveTiming {
+        self.timing
+    }
+
+    /// Return the time in microseconds until the spindle is up to speed and data can be read.
+    pub fn spin_up_remaining_us(&self) -> f64 {
+        self.spin_up_us
+    }
+
     /// Load a disk into the specified drive
     pub fn load_image_from(
         &mut self,
@@ -256,13 +371,18 @@ impl FloppyDiskDrive {
         path: Option<&Path>,
         write_protect: bool,
     ) -> Result<Arc<RwLock<DiskImage>>, Error> {
+        let image_hash = fxhash::hash64(&src_vec);
         let mut image_buffer = Cursor::new(src_vec);
         let image = DiskImage::load(&mut image_buffer, path, None, None)?;
+        self.image_hash = Some(image_hash);
+        self.dirty = false;
+        self.sector_errors.clear();
+        self.narrow_tracks_written = false;
 
         self.media_geom = DiskChs::from((
             image.image_format().geometry.c(),
             image.image_format().geometry.h(),
-            0u8,
+            media_sectors_per_track(&image),
         ));
 
         log::debug!("Loaded floppy image, CHS: {}", self.media_geom,);
@@ -284,10 +404,14 @@ impl FloppyDiskDrive {
         self.media_geom = DiskChs::from((
             image.image_format().geometry.c(),
             image.image_format().geometry.h(),
-            0u8,
+            media_sectors_per_track(&image),
         ));
 
         log::debug!("Attached floppy image, CHS: {}", self.media_geom);
+        self.image_hash = None;
+        self.dirty = false;
+        self.sector_errors.clear();
+        self.narrow_tracks_written = false;
         self.disk_present = true;
         self.write_protected = write_protect;
         let image_arc = image.into_arc();
@@ -310,7 +434,102 @@ impl FloppyDiskDrive {
         self.chsn = Default::default();
         self.media_geom = DiskChs::default();
         self.disk_present = false;
+        // Opening the door of a 5.25" drive drops its READY signal.
+        self.ready = self.drive_type.is_3_5_inch();
+        self.head_loaded = false;
         self.disk_image = None;
+        self.image_hash = None;
+        self.dirty = false;
+        self.sector_errors.clear();
+        self.narrow_tracks_written = false;
+    }
+
+    /// Return true if the image has been modified since it was loaded or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Clear the dirty flag, typically after the image has been saved.
+    pub fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Simulate an error when reading the sector with the specified ID. Errors are cleared when the image
+    /// is ejected or replaced.
+    pub fn set_sector_error(&mut self, cylinder: u16, head: u8, sector: u8, error: SectorError) {
+        self.sector_errors.insert((cylinder, head, sector), error);
+    }
+
+    pub fn clear_sector_error(&mut self, cylinder: u16, head: u8, sector: u8) {
+        self.sector_errors.remove(&(cylinder, head, sector));
+    }
+
+    pub fn clear_sector_errors(&mut self) {
+        self.sector_errors.clear();
+    }
+
+    pub fn sector_errors(&self) -> &SectorErrorMap {
+        &self.sector_errors
+    }
+
+    /// Return the density and encoding fluxfox reports for the track under the specified head, or None
+    /// if there is no such track on the media.
+    fn track_media(&self, head: u8) -> Option<(MediaDensity, TrackDataEncoding)> {
+        let image = read_lock_opt!(self.disk_image.as_ref()?);
+        let info = image.track(DiskCh::new(self.cylinder, head))?.info();
+        Some((MediaDensity::from(info.data_rate), info.encoding))
+    }
+
+    /// Check whether this drive can access the track under the specified head with a command using
+    /// the specified encoding. If the controller has been programmed with a data rate, it must also
+    /// match the rate the track requires in this drive.
+    pub fn check_media(&self, data_rate: Option<DataRate>, mfm: bool, head: u8) -> Result<(), MediaMismatch> {
+        if !self.disk_present {
+            return Ok(());
+        }
+        // If there's no track here, the command will fail to find a sector ID on its own.
+        let Some((density, encoding)) = self.track_media(head)
+        else {
+            return Ok(());
+        };
+        let required_rate = media_data_rate(self.drive_type, self.media_geom.c(), density)?;
+        if mfm == matches!(encoding, TrackDataEncoding::Fm) {
+            return Err(MediaMismatch::Encoding);
+        }
+        match data_rate {
+            Some(rate) if rate != required_rate => Err(MediaMismatch::DataRate(required_rate)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the data rate the track under the specified head requires in this drive, if it can be
+    /// read at all.
+    pub fn required_data_rate(&self, head: u8) -> Option<DataRate> {
+        let (density, _) = self.track_media(head)?;
+        media_data_rate(self.drive_type, self.media_geom.c(), density).ok()
+    }
+
+    /// Return true if a 1.2M drive has written narrow tracks over the wide tracks of double density
+    /// media since it was inserted.
+    pub fn narrow_tracks_written(&self) -> bool {
+        self.narrow_tracks_written
+    }
+
+    /// Note a write to the track under the specified head, flagging double density media written in
+    /// a 1.2M drive.
+    fn mark_tracks_written(&mut self, head: u8) {
+        if matches!(self.drive_type, FloppyDriveType::Floppy12M)
+            && !self.narrow_tracks_written
+            && self
+                .track_media(head)
+                .is_some_and(|(density, _)| density != MediaDensity::High)
+        {
+            log::warn!(
+                "Drive {}: writing double density media in a 1.2M drive, disk may be unreadable in a 360K drive",
+                self.drive_n
+            );
+            self.narrow_tracks_written = true;
+        }
     }
 
     pub fn create_new_image(
@@ -328,12 +547,14 @@ impl FloppyDiskDrive {
 
         let image = builder.build()?;
         self.chsn = Default::default();
+        self.narrow_tracks_written = false;
         self.media_geom = format.chs();
         self.disk_present = true;
 
         let image_arc = image.into_arc();
         let image_clone = image_arc.clone();
         self.disk_image = Some(image_arc);
+        self.image_hash = None;
 
         Ok(image_clone)
     }
@@ -374,6 +595,7 @@ impl FloppyDiskDrive {
         if self.disk_image.is_none() {
             return Err(anyhow!("No media in drive"));
         }
+        self.mark_tracks_written(h);
 
         let image_lock = self.disk_image.as_ref().unwrap();
         let mut image = write_lock!(image_lock);
@@ -439,6 +661,7 @@ impl FloppyDiskDrive {
             write_buf_idx += sector_data_size;
             sid += 1;
             sectors_written += 1;
+            self.dirty = true;
         }
 
         Ok(DriveWriteResult {
@@ -494,7 +717,7 @@ impl FloppyDiskDrive {
         let mut not_found_count = 0;
 
         while sectors_read < ct {
-            let read_sector_result = match image.read_sector(
+            let mut read_sector_result = match image.read_sector(
                 DiskCh::new(self.cylinder, h),
                 DiskChsnQuery::new(op_chs.c(), op_chs.h(), op_chs.s(), n),
                 None,
@@ -512,11 +735,40 @@ impl FloppyDiskDrive {
                         sectors_read: 0,
                         new_chs: op_chs,
                         deleted_mark: false,
+                        timeout: false,
                     });
                 }
                 Err(e) => return Err(e.into()),
             };
 
+            // Apply any simulated error for this sector over what the image actually contains.
+            if let Some(error) = self.sector_errors.get(&(op_chs.c(), op_chs.h(), op_chs.s())) {
+                log::debug!("command_read_data(): simulating {:?} for sector id: {}", error, op_chs);
+                match error {
+                    SectorError::CrcError => read_sector_result.data_crc_error = true,
+                    SectorError::DeletionMark => read_sector_result.deleted_mark = true,
+                    SectorError::NotFound => {
+                        self.operation_status.sector_not_found = true;
+                        return Ok(DriveReadResult {
+                            not_found: true,
+                            sectors_read: 0,
+                            new_chs: op_chs,
+                            deleted_mark: false,
+                            timeout: false,
+                        });
+                    }
+                    SectorError::Timeout => {
+                        return Ok(DriveReadResult {
+                            not_found: false,
+                            sectors_read: 0,
+                            new_chs: op_chs,
+                            deleted_mark: false,
+                            timeout: true,
+                        });
+                    }
+                }
+            }
+
             if read_sector_result.no_dam {
                 self.operation_status.no_dam = true;
                 return Ok(DriveReadResult {
@@ -524,6 +776,7 @@ impl FloppyDiskDrive {
                     sectors_read: 0,
                     new_chs: op_chs,
                     deleted_mark: false,
+                    timeout: false,
                 });
             }
 
@@ -552,6 +805,7 @@ impl FloppyDiskDrive {
                     sectors_read: 0,
                     new_chs: op_chs,
                     deleted_mark: false,
+                    timeout: false,
                 });
             }
 
@@ -620,6 +874,7 @@ impl FloppyDiskDrive {
             sectors_read: sectors_read as u16,
             new_chs: op_chs,
             deleted_mark: self.operation_status.deleted_mark,
+            timeout: false,
         })
     }
 
@@ -654,6 +909,7 @@ impl FloppyDiskDrive {
                 sectors_read: 0,
                 new_chs: DiskChs::from((id_ch, 1)),
                 deleted_mark: false,
+                timeout: false,
             });
         }
         else {
@@ -673,6 +929,7 @@ impl FloppyDiskDrive {
             sectors_read: read_track_result.sectors_read,
             new_chs: DiskChs::from((id_ch, (read_track_result.sectors_read + 1) as u8)),
             deleted_mark: self.operation_status.deleted_mark,
+            timeout: false,
         })
     }
 
@@ -686,6 +943,7 @@ impl FloppyDiskDrive {
         if self.disk_image.is_none() {
             return Err(anyhow!("No media in drive"));
         }
+        self.mark_tracks_written(ch.h());
 
         let image_lock = self.disk_image.as_ref().unwrap();
         let mut image = write_lock!(image_lock);
@@ -709,10 +967,13 @@ impl FloppyDiskDrive {
             sector_ct
         );
         match image.format_track(ch, fox_format_buffer, &[fill_byte], gap3_len as usize) {
-            Ok(_) => Ok(DriveFormatResult {
-                sectors_formatted: sector_ct as u8,
-                new_sid: (sector_ct + 1) as u8,
-            }),
+            Ok(_) => {
+                self.dirty = true;
+                Ok(DriveFormatResult {
+                    sectors_formatted: sector_ct as u8,
+                    new_sid: (sector_ct + 1) as u8,
+                })
+            }
             Err(e) => Err(e.into()),
         }
     }
@@ -740,6 +1001,9 @@ impl FloppyDiskDrive {
 
     pub fn motor_on(&mut self) {
         if self.disk_present {
+            if !self.motor_on {
+                self.spin_up_us = self.timing.spin_up_ms * 1000.0;
+            }
             self.motor_on = true;
             self.ready = true;
         }
@@ -750,6 +1014,14 @@ impl FloppyDiskDrive {
             log::trace!("Drive {}: turning motor off.", self.drive_n);
         }
         self.motor_on = false;
+        self.head_loaded = false;
+    }
+
+    /// Run the drive for the specified number of microseconds, bringing the spindle up to speed.
+    pub fn run(&mut self, us: f64) {
+        if self.motor_on && self.spin_up_us > 0.0 {
+            self.spin_up_us = (self.spin_up_us - us).max(0.0);
+        }
     }
 
     /// Return whether the specified chs is valid for the disk in the drive.
@@ -826,6 +1098,98 @@ impl FloppyDiskDrive {
         self.disk_present
     }
 
+    /// Return a reference identifying the inserted disk for a save state, if a disk is present.
+    pub fn media_reference(&self) -> Option<MediaReference> {
+        let image_lock = self.disk_image.as_ref()?;
+        let dirty = image_lock.try_read().is_ok_and(|image| image.write_ct() > 0);
+        Some(MediaReference {
+            drive: self.drive_n,
+            hash: self.image_hash,
+            dirty,
+        })
+    }
+
+    /// Check the address and data CRCs of every sector in the current image. Returns the damaged
+    /// sectors, or None if no image is loaded.
+    pub fn verify_image(&self) -> Option<Vec<BadSector>> {
+        let image_lock = self.disk_image.as_ref()?;
+        let image = read_lock_opt!(image_lock);
+
+        let mut bad_sectors = Vec::new();
+        for (head, tracks) in image.sector_map().iter().enumerate() {
+            for (cylinder, sectors) in tracks.iter().enumerate() {
+                for entry in sectors {
+                    if entry.attributes.address_error || entry.attributes.data_error {
+                        bad_sectors.push(BadSector {
+                            phys_ch: DiskCh::new(cylinder as u16, head as u8),
+                            chsn: entry.chsn,
+                            address_crc_error: entry.attributes.address_error,
+                            data_crc_error: entry.attributes.data_error,
+                        });
+                    }
+                }
+            }
+        }
+        Some(bad_sectors)
+    }
+
+    /// Replace the data of the specified damaged sectors with the same sectors from a reference
+    /// image. Sectors with a damaged ID can't be located reliably and are skipped, as are sectors
+    /// that are missing or also damaged in the reference image. Returns the number of sectors
+    /// repaired.
+    pub fn repair_image(&mut self, reference: &mut DiskImage, bad_sectors: &[BadSector]) -> Result<usize, Error> {
+        let Some(image_lock) = self.disk_image.as_ref()
+        else {
+            return Err(anyhow!("No media in drive"));
+        };
+        let mut image = write_lock!(image_lock);
+
+        let mut repaired = 0;
+        for bad_sector in bad_sectors.iter().filter(|bad_sector| !bad_sector.address_crc_error) {
+            let chsn = bad_sector.chsn;
+            let reference_result = match reference.read_sector(
+                bad_sector.phys_ch,
+                DiskChsnQuery::new(chsn.c(), chsn.h(), chsn.s(), chsn.n()),
+                None,
+                None,
+                RwScope::DataOnly,
+                false,
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    log::warn!("repair_image(): failed to read sector {} from reference: {}", chsn, e);
+                    continue;
+                }
+            };
+
+            if reference_result.not_found
+                || reference_result.no_dam
+                || reference_result.address_crc_error
+                || reference_result.data_crc_error
+            {
+                log::warn!("repair_image(): sector {} is missing or damaged in reference", chsn);
+                continue;
+            }
+
+            let write_result = image.write_sector(
+                bad_sector.phys_ch,
+                DiskChsnQuery::new(chsn.c(), chsn.h(), chsn.s(), chsn.n()),
+                None,
+                &reference_result.read_buf[reference_result.data_range],
+                RwScope::DataOnly,
+                reference_result.deleted_mark,
+                false,
+            )?;
+
+            if !write_result.not_found {
+                log::debug!("repair_image(): repaired sector {}", chsn);
+                repaired += 1;
+                self.dirty = true;
+            }
+        }
+        Ok(repaired)
+    }
+
     pub fn image_state(&self) -> Option<FloppyImageState> {
         if let Some(image_lock) = &self.disk_image {
             let image = read_lock_opt!(image_lock);
@@ -835,6 +1199,7 @@ impl FloppyDiskDrive {
                 format: None,
                 heads: image.heads(),
                 sector_map,
+                sector_errors: self.sector_errors.clone(),
             })
         }
         else {
@@ -842,3 +1207,104 @@ impl FloppyDiskDrive {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive_with_media(drive_type: FloppyDriveType, format: StandardFormat) -> FloppyDiskDrive {
+        let mut drive = FloppyDiskDrive::new(0, drive_type);
+        drive.create_new_image(format, true).unwrap();
+        drive
+    }
+
+    #[test]
+    fn check_media_requires_matching_data_rate() {
+        let drive = drive_with_media(FloppyDriveType::Floppy144M, StandardFormat::PcFloppy1440);
+        assert_eq!(
+            drive.check_media(Some(DataRate::Rate250Kbps), true, 0),
+            Err(MediaMismatch::DataRate(DataRate::Rate500Kbps))
+        );
+        assert_eq!(drive.check_media(Some(DataRate::Rate500Kbps), true, 0), Ok(()));
+        assert_eq!(drive.check_media(None, true, 1), Ok(()));
+        assert_eq!(drive.check_media(None, false, 0), Err(MediaMismatch::Encoding));
+
+        let drive = drive_with_media(FloppyDriveType::Floppy12M, StandardFormat::PcFloppy360);
+        assert_eq!(
+            drive.check_media(Some(DataRate::Rate250Kbps), true, 0),
+            Err(MediaMismatch::DataRate(DataRate::Rate300Kbps))
+        );
+        assert_eq!(drive.check_media(Some(DataRate::Rate300Kbps), true, 0), Ok(()));
+        assert_eq!(drive.required_data_rate(0), Some(DataRate::Rate300Kbps));
+
+        let drive = drive_with_media(FloppyDriveType::Floppy720K, StandardFormat::PcFloppy1440);
+        assert_eq!(drive.check_media(None, true, 0), Err(MediaMismatch::Density));
+    }
+
+    #[test]
+    fn ready_depends_on_form_factor() {
+        let mut drive = FloppyDiskDrive::new(0, FloppyDriveType::Floppy360K);
+        assert!(!drive.ready);
+        drive.disk_present = true;
+        drive.motor_on();
+        assert!(drive.ready);
+        drive.unload_image();
+        assert!(!drive.ready);
+
+        let mut drive = FloppyDiskDrive::new(0, FloppyDriveType::Floppy144M);
+        assert!(drive.ready);
+        drive.unload_image();
+        assert!(drive.ready);
+    }
+
+    #[test]
+    fn motor_spins_up() {
+        let mut drive = FloppyDiskDrive::new(0, FloppyDriveType::Floppy12M);
+        drive.disk_present = true;
+        drive.motor_on();
+        assert_eq!(drive.spin_up_remaining_us(), 400_000.0);
+        drive.run(150_000.0);
+        assert_eq!(drive.spin_up_remaining_us(), 250_000.0);
+        drive.run(300_000.0);
+        assert_eq!(drive.spin_up_remaining_us(), 0.0);
+
+        // Turning the motor on again while it is running doesn't restart spin-up.
+        drive.motor_on();
+        assert_eq!(drive.spin_up_remaining_us(), 0.0);
+    }
+}
+
+impl SaveState for FloppyDiskDrive {
+    type State = FloppyDriveSaveState;
+
+    fn save_state(&self) -> FloppyDriveSaveState {
+        FloppyDriveSaveState {
+            error_signal: self.error_signal,
+            cylinder: self.cylinder,
+            chsn: (self.chsn.c(), self.chsn.h(), self.chsn.s(), self.chsn.n()),
+            ready: self.ready,
+            motor_on: self.motor_on,
+            spin_up_us: self.spin_up_us,
+            head_loaded: self.head_loaded,
+            positioning: self.positioning,
+            operation_status: self.operation_status,
+            operation_buf: self.operation_buf.get_ref().clone(),
+            operation_buf_pos: self.operation_buf.position(),
+        }
+    }
+
+    fn restore_state(&mut self, state: FloppyDriveSaveState) {
+        let (c, h, s, n) = state.chsn;
+        self.error_signal = state.error_signal;
+        self.cylinder = state.cylinder;
+        self.chsn = DiskChsn::new(c, h, s, n);
+        self.ready = state.ready;
+        self.motor_on = state.motor_on;
+        self.spin_up_us = state.spin_up_us;
+        self.head_loaded = state.head_loaded;
+        self.positioning = state.positioning;
+        self.operation_status = state.operation_status;
+        self.operation_buf = Cursor::new(state.operation_buf);
+        self.operation_buf.set_position(state.operation_buf_pos);
+    }
+}
@@ -45,9 +45,12 @@
 
 */
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice, NO_IO_BYTE},
     cpu_common::LogicAnalyzer,
+    savestate::SaveState,
 };
 
 pub const GAMEPORT_DEFAULT_PORT: u16 = 0x201;
@@ -106,6 +109,36 @@ pub struct GamePort {
     buttons:   [bool; 4],
 }
 
+/// Snapshot of the game port's axis timers. Stick positions and buttons follow the host
+/// controllers and are not included.
+#[derive(Serialize, Deserialize)]
+pub struct GamePortSaveState {
+    // Elapsed charge time and timing flag of each axis: stick 1 X and Y, then stick 2 X and Y.
+    axes: Vec<(f64, bool)>,
+}
+
+impl SaveState for GamePort {
+    type State = GamePortSaveState;
+
+    fn save_state(&self) -> GamePortSaveState {
+        GamePortSaveState {
+            axes: self
+                .sticks
+                .iter()
+                .flat_map(|stick| [&stick.x, &stick.y])
+                .map(|axis| (axis.time, axis.timing))
+                .collect(),
+        }
+    }
+
+    fn restore_state(&mut self, state: GamePortSaveState) {
+        for (stick, saved) in self.sticks.iter_mut().zip(state.axes.chunks_exact(2)) {
+            (stick.x.time, stick.x.timing) = saved[0];
+            (stick.y.time, stick.y.timing) = saved[1];
+        }
+    }
+}
+
 impl GamePort {
     pub fn new(port_base: Option<u16>) -> Self {
         GamePort {
@@ -36,9 +36,12 @@ use std::{collections::VecDeque, error::Error};
 
 use core::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit},
     devices::dma,
+    savestate::SaveState,
 };
 //use crate::fdc::Operation;
 use crate::{
@@ -88,12 +91,13 @@ const ERR_ILLEGAL_ACCESS: u8 = 0b10_0001;
 const RESET_DELAY_US: f64 = 200_000.0; // 200ms
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum OperationError {
     NoError,
     NoReadySignal,
     InvalidCommand,
     IllegalAccess,
+    WriteFault,
 }
 
 #[allow(dead_code)]
@@ -119,7 +123,7 @@ impl Display for ControllerError {
 }
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum State {
     Reset,
     WaitingForCommand,
@@ -131,7 +135,7 @@ pub enum State {
 }
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Command {
     None,
     TestDriveReady,
@@ -259,7 +263,7 @@ impl HardDisk {
 }
 
 #[allow(dead_code)]
-#[derive(Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct OperationStatus {
     drive_select: usize,
     buffer_idx: usize,
@@ -274,6 +278,44 @@ pub enum Continuation {
     ContinueAsOperation,
 }
 
+/// The head position and sector buffer of a hard disk.
+#[derive(Serialize, Deserialize)]
+pub struct HardDiskSaveState {
+    cylinder: u16,
+    head: u8,
+    sector: u8,
+    sector_buf: Vec<u8>,
+}
+
+/// Serializable state of the hard disk controller. Attached VHDs and the drive geometry read from
+/// them are not included, so the same disks must be attached when the state is restored.
+#[derive(Serialize, Deserialize)]
+pub struct HdcSaveState {
+    drives: Vec<HardDiskSaveState>,
+    drive_select: usize,
+    state: State,
+    last_error: OperationError,
+    last_error_drive: usize,
+    error_flag: bool,
+    receiving_dcb: bool,
+    command: Command,
+    last_command: Command,
+    command_byte_n: u32,
+    command_result_pending: bool,
+    data_register_in: VecDeque<u8>,
+    data_register_out: VecDeque<u8>,
+    operation_status: OperationStatus,
+    dma_enabled: bool,
+    irq_enabled: bool,
+    send_interrupt: bool,
+    clear_interrupt: bool,
+    interrupt_active: bool,
+    send_dreq: bool,
+    clear_dreq: bool,
+    dreq_active: bool,
+    state_accumulator: f64,
+}
+
 #[allow(dead_code)]
 pub struct DeviceControlBlock {
     drive_select: usize,
@@ -363,6 +405,83 @@ impl Default for HardDiskController {
     }
 }
 
+impl SaveState for HardDiskController {
+    type State = HdcSaveState;
+
+    fn save_state(&self) -> HdcSaveState {
+        HdcSaveState {
+            drives: self
+                .drives
+                .iter()
+                .map(|drive| HardDiskSaveState {
+                    cylinder: drive.cylinder,
+                    head: drive.head,
+                    sector: drive.sector,
+                    sector_buf: drive.sector_buf.clone(),
+                })
+                .collect(),
+            drive_select: self.drive_select,
+            state: self.state,
+            last_error: self.last_error,
+            last_error_drive: self.last_error_drive,
+            error_flag: self.error_flag,
+            receiving_dcb: self.receiving_dcb,
+            command: self.command,
+            last_command: self.last_command,
+            command_byte_n: self.command_byte_n,
+            command_result_pending: self.command_result_pending,
+            data_register_in: self.data_register_in.clone(),
+            data_register_out: self.data_register_out.clone(),
+            operation_status: self.operation_status.clone(),
+            dma_enabled: self.dma_enabled,
+            irq_enabled: self.irq_enabled,
+            send_interrupt: self.send_interrupt,
+            clear_interrupt: self.clear_interrupt,
+            interrupt_active: self.interrupt_active,
+            send_dreq: self.send_dreq,
+            clear_dreq: self.clear_dreq,
+            dreq_active: self.dreq_active,
+            state_accumulator: self.state_accumulator,
+        }
+    }
+
+    fn restore_state(&mut self, state: HdcSaveState) {
+        for (drive, saved) in self.drives.iter_mut().zip(state.drives) {
+            drive.cylinder = saved.cylinder;
+            drive.head = saved.head;
+            drive.sector = saved.sector;
+            drive.sector_buf = saved.sector_buf;
+        }
+        self.drive_select = state.drive_select;
+        self.state = state.state;
+        self.last_error = state.last_error;
+        self.last_error_drive = state.last_error_drive;
+        self.error_flag = state.error_flag;
+        self.receiving_dcb = state.receiving_dcb;
+        self.command = state.command;
+        // A command still receiving its DCB is dispatched once the last byte arrives.
+        self.command_fn = match self.state {
+            State::ReceivingCommand => HardDiskController::command_dispatch_fn(self.command),
+            _ => None,
+        };
+        self.last_command = state.last_command;
+        self.command_byte_n = state.command_byte_n;
+        self.command_result_pending = state.command_result_pending;
+        self.data_register_in = state.data_register_in;
+        self.data_register_out = state.data_register_out;
+        self.operation_status = state.operation_status;
+        self.dma_enabled = state.dma_enabled;
+        self.irq_enabled = state.irq_enabled;
+        self.send_interrupt = state.send_interrupt;
+        self.clear_interrupt = state.clear_interrupt;
+        self.interrupt_active = state.interrupt_active;
+        self.send_dreq = state.send_dreq;
+        self.clear_dreq = state.clear_dreq;
+        self.dreq_active = state.dreq_active;
+        self.state_accumulator = state.state_accumulator;
+    }
+}
+
 impl HardDiskController {
     pub fn new(drive_ct: usize, drive_type_dip: u8) -> Self {
         Self {
@@ -431,6 +550,34 @@ impl HardDiskController {
         Ok(())
     }
 
+    pub fn vhd(&self, device_id: usize) -> Option<&VirtualHardDisk> {
+        self.drives.get(device_id).and_then(|drive| drive.vhd.as_ref())
+    }
+
+    pub fn vhd_mut(&mut self, device_id: usize) -> Option<&mut VirtualHardDisk> {
+        self.drives.get_mut(device_id).and_then(|drive| drive.vhd.as_mut())
+    }
+
+    /// Return the handler that executes the specified command once its DCB has been received.
+    fn command_dispatch_fn(command: Command) -> Option<CommandDispatchFn> {
+        match command {
+            Command::TestDriveReady => Some(HardDiskController::command_test_drive_ready),
+            Command::Recalibrate => Some(HardDiskController::command_recalibrate),
+            Command::RequestSense => Some(HardDiskController::command_sense_status),
+            Command::ReadyVerify => Some(HardDiskController::command_ready_verify),
+            Command::Read => Some(HardDiskController::command_read),
+            Command::Write => Some(HardDiskController::command_write),
+            Command::Seek => Some(HardDiskController::command_seek),
+            Command::Initialize => Some(HardDiskController::command_initialize_dc),
+            Command::ReadSectorBuffer => Some(HardDiskController::command_read_sector_buffer),
+            Command::WriteSectorBuffer => Some(HardDiskController::command_write_sector_buffer),
+            Command::RamDiagnostic => Some(HardDiskController::command_ram_diagnostic),
+            Command::DriveDiagnostic => Some(HardDiskController::command_drive_diagnostic),
+            Command::ControllerDiagnostic => Some(HardDiskController::command_controller_diagnostic),
+            _ => None,
+        }
+    }
+
     pub fn set_command(&mut self, command: Command, n_bytes: u32, command_fn: CommandDispatchFn) {
         self.state = State::ReceivingCommand;
         self.receiving_dcb = true;
@@ -811,6 +958,7 @@ impl HardDiskController {
             OperationError::NoReadySignal => ERR_NO_READY_SIGNAL,
             OperationError::InvalidCommand => ERR_INVALID_COMMAND,
             OperationError::IllegalAccess => ERR_ILLEGAL_ACCESS,
+            OperationError::WriteFault => ERR_WRITE_FAULT,
         };
 
         /* The controller BIOS source listing provides the following table for sense byte format
@@ -1375,6 +1523,7 @@ impl HardDiskController {
 
                 // Filled the sector buffer, write it to disk
                 if self.operation_status.buffer_idx == SECTOR_SIZE {
+                    let mut write_failed = false;
                     match &mut self.drives[self.drive_select].vhd {
                         Some(vhd) => {
                             match vhd.write_sector(
@@ -1394,6 +1543,7 @@ impl HardDiskController {
                                 }
                                 Err(err) => {
                                     log::error!("Sector write failed: {}", err);
+                                    write_failed = true;
                                 }
                             };
                         }
@@ -1402,6 +1552,14 @@ impl HardDiskController {
                         }
                     }
 
+                    if write_failed {
+                        // Abort the command. A read-only VHD reports a write fault, like a drive
+                        // with its write protect jumper set.
+                        self.set_error(OperationError::WriteFault, self.drive_select);
+                        self.end_dma_command(0, true);
+                        return;
+                    }
+
                     // Advance to next sector
                     log::trace!("Command Write: Advancing to next sector...");
                     let (new_c, new_h, new_s) = self.drives[self.drive_select].get_next_sector(
@@ -40,12 +40,14 @@ use crate::{
         dma,
         hdc::{at_formats::AtFormats, DEFAULT_SECTOR_SIZE},
     },
+    savestate::SaveState,
     vhd::{VHDGeometry, VirtualHardDisk},
 };
 use binrw::{binrw, BinWrite};
 use core::{fmt, fmt::Display};
 use fluxfox::io::ReadBytesExt;
 use modular_bitfield::bitfield;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::VecDeque,
     error::Error,
@@ -143,7 +145,7 @@ impl<const N: usize> AtaString<N> {
 }
 
 /// An implementation of a 16-bit register.
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct AtaRegister16 {
     pub bytes: [Option<u8>; 2],
 }
@@ -199,7 +201,7 @@ impl AtaRegister16 {
 }
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum OperationError {
     NoError,
     NoReadySignal,
@@ -230,7 +232,7 @@ impl Display for ControllerError {
 }
 
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum State {
     Reset,
     WaitingForCommand,
@@ -243,7 +245,7 @@ pub enum State {
 
 #[repr(u8)]
 #[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Command {
     None,
     ReadSectorRetry = 0x20,
@@ -509,7 +511,7 @@ impl HardDisk {
 }
 
 #[allow(dead_code)]
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct OperationStatus {
     sectors_complete: u8,
     sectors_left: u8,
@@ -524,6 +526,53 @@ pub enum Continuation {
     ContinueAsOperation,
 }
 
+/// The head position and sector buffer of a hard disk.
+#[derive(Serialize, Deserialize)]
+pub struct HardDiskSaveState {
+    position:   (u16, u8, u8),
+    sector_buf: Vec<u8>,
+}
+
+/// Serializable state of the XT-IDE controller. Attached VHDs and the drive geometry read from
+/// them are not included, so the same disks must be attached when the state is restored.
+#[derive(Serialize, Deserialize)]
+pub struct XtIdeSaveState {
+    drives: Vec<HardDiskSaveState>,
+    drive_select: usize,
+    state: State,
+    last_error: OperationError,
+    last_error_drive: usize,
+    error_flag: bool,
+    receiving_dcb: bool,
+    command: Command,
+    command_chs: (u16, u8, u8),
+    last_command: Command,
+    command_byte_n: u32,
+    command_queue: VecDeque<u8>,
+    command_result_pending: bool,
+    sector_buffer_idx: usize,
+    sector_buffer: Vec<u8>,
+    sector_buffer_pos: u64,
+    status_register: u8,
+    error_register: u8,
+    sector_count_register: u8,
+    sector_number_register: u8,
+    cylinder_low_register: u8,
+    cylinder_high_register: u8,
+    drive_head_register: u8,
+    data_register: AtaRegister16,
+    operation_status: Vec<OperationStatus>,
+    dma_enabled: bool,
+    irq_enabled: bool,
+    send_interrupt: bool,
+    clear_interrupt: bool,
+    interrupt_active: bool,
+    send_dreq: bool,
+    clear_dreq: bool,
+    dreq_active: bool,
+    state_accumulator: f64,
+}
+
 #[allow(dead_code)]
 pub struct DeviceControlBlock {
     drive_select: usize,
@@ -653,6 +702,101 @@ impl Default for XtIdeController {
     }
 }
 
+impl SaveState for XtIdeController {
+    type State = XtIdeSaveState;
+
+    fn save_state(&self) -> XtIdeSaveState {
+        XtIdeSaveState {
+            drives: self
+                .drives
+                .iter()
+                .map(|drive| HardDiskSaveState {
+                    position:   drive.position.into(),
+                    sector_buf: drive.sector_buf.clone(),
+                })
+                .collect(),
+            drive_select: self.drive_select,
+            state: self.state,
+            last_error: self.last_error,
+            last_error_drive: self.last_error_drive,
+            error_flag: self.error_flag,
+            receiving_dcb: self.receiving_dcb,
+            command: self.command,
+            command_chs: self.command_chs.into(),
+            last_command: self.last_command,
+            command_byte_n: self.command_byte_n,
+            command_queue: self.command_queue.clone(),
+            command_result_pending: self.command_result_pending,
+            sector_buffer_idx: self.sector_buffer_idx,
+            sector_buffer: self.sector_buffer.get_ref().clone(),
+            sector_buffer_pos: self.sector_buffer.position(),
+            status_register: self.status_register.into_bytes()[0],
+            error_register: self.error_register.into_bytes()[0],
+            sector_count_register: self.sector_count_register,
+            sector_number_register: self.sector_number_register,
+            cylinder_low_register: self.cylinder_low_register,
+            cylinder_high_register: self.cylinder_high_register,
+            drive_head_register: self.drive_head_register,
+            data_register: self.data_register.clone(),
+            operation_status: self.operation_status.to_vec(),
+            dma_enabled: self.dma_enabled,
+            irq_enabled: self.irq_enabled,
+            send_interrupt: self.send_interrupt,
+            clear_interrupt: self.clear_interrupt,
+            interrupt_active: self.interrupt_active,
+            send_dreq: self.send_dreq,
+            clear_dreq: self.clear_dreq,
+            dreq_active: self.dreq_active,
+            state_accumulator: self.state_accumulator,
+        }
+    }
+
+    fn restore_state(&mut self, state: XtIdeSaveState) {
+        for (drive, saved) in self.drives.iter_mut().zip(state.drives) {
+            drive.position = saved.position.into();
+            drive.sector_buf = saved.sector_buf;
+        }
+        self.drive_select = state.drive_select;
+        self.state = state.state;
+        self.last_error = state.last_error;
+        self.last_error_drive = state.last_error_drive;
+        self.error_flag = state.error_flag;
+        self.receiving_dcb = state.receiving_dcb;
+        self.command = state.command;
+        self.command_chs = state.command_chs.into();
+        // Commands take no parameter bytes and are dispatched as soon as they are written, so a
+        // dispatch function is never pending between emulation steps.
+        self.command_fn = None;
+        self.last_command = state.last_command;
+        self.command_byte_n = state.command_byte_n;
+        self.command_queue = state.command_queue;
+        self.command_result_pending = state.command_result_pending;
+        self.sector_buffer_idx = state.sector_buffer_idx;
+        self.sector_buffer = Cursor::new(state.sector_buffer);
+        self.sector_buffer.set_position(state.sector_buffer_pos);
+        self.status_register = StatusRegister::from_bytes([state.status_register]);
+        self.error_register = ErrorRegister::from_bytes([state.error_register]);
+        self.sector_count_register = state.sector_count_register;
+        self.sector_number_register = state.sector_number_register;
+        self.cylinder_low_register = state.cylinder_low_register;
+        self.cylinder_high_register = state.cylinder_high_register;
+        self.drive_head_register = state.drive_head_register;
+        self.data_register = state.data_register;
+        for (status, saved) in self.operation_status.iter_mut().zip(state.operation_status) {
+            *status = saved;
+        }
+        self.dma_enabled = state.dma_enabled;
+        self.irq_enabled = state.irq_enabled;
+        self.send_interrupt = state.send_interrupt;
+        self.clear_interrupt = state.clear_interrupt;
+        self.interrupt_active = state.interrupt_active;
+        self.send_dreq = state.send_dreq;
+        self.clear_dreq = state.clear_dreq;
+        self.dreq_active = state.dreq_active;
+        self.state_accumulator = state.state_accumulator;
+    }
+}
+
 impl XtIdeController {
     pub fn new(io_base: Option<u16>, drive_ct: usize) -> Self {
         Self {
@@ -739,6 +883,14 @@ impl XtIdeController {
         Ok(())
     }
 
+    pub fn vhd(&self, device_id: usize) -> Option<&VirtualHardDisk> {
+        self.drives.get(device_id).and_then(|drive| drive.vhd.as_ref())
+    }
+
+    pub fn vhd_mut(&mut self, device_id: usize) -> Option<&mut VirtualHardDisk> {
+        self.drives.get_mut(device_id).and_then(|drive| drive.vhd.as_mut())
+    }
+
     pub fn set_command(&mut self, command: Command, n_bytes: u32, command_fn: CommandDispatchFn) {
         self.state = State::ReceivingCommand;
         self.receiving_dcb = true;
@@ -979,6 +1131,8 @@ impl XtIdeController {
                 }
                 Some(command_fn) => {
                     self.error_register.set_abrt(false);
+                    self.status_register.set_err(false);
+                    self.status_register.set_dwf(false);
                     result = command_fn(self, bus);
                 }
             }
@@ -1526,7 +1680,8 @@ impl XtIdeController {
     }
 
     /// Write a sector to disk from the controller's sector buffer.
-    fn write_sector_from_buffer(&mut self, drive_select: usize, _retry: bool) {
+    /// Returns false if the write failed, in which case the error status has been set.
+    fn write_sector_from_buffer(&mut self, drive_select: usize, _retry: bool) -> bool {
         //self.operation_status[self.drive_select].buffer_idx = 0;
 
         let pos = self.drives[drive_select].position_vhd();
@@ -1540,14 +1695,21 @@ impl XtIdeController {
                     // Set DRQ flag to inform host we want more data.
                     self.status_register.set_drq(true);
                     self.sector_buffer.seek(SeekFrom::Start(0)).unwrap();
+                    true
                 }
                 Err(err) => {
                     log::error!("Sector write failed: {}", err);
+                    // Report a write fault and abort the command.
+                    self.status_register.set_err(true);
+                    self.status_register.set_dwf(true);
+                    self.error_register.set_abrt(true);
+                    false
                 }
-            };
+            }
         }
         else {
             log::error!("No VHD mounted for drive {}", drive_select);
+            false
         }
     }
 
@@ -1780,7 +1942,10 @@ impl XtIdeController {
                     "operation_write_sector(): Writing sector: {}",
                     self.drives[drive_select].position()
                 );
-                self.write_sector_from_buffer(drive_select, false);
+                if !self.write_sector_from_buffer(drive_select, false) {
+                    self.end_operation(drive_select, true);
+                    return;
+                }
 
                 // Advance to next sector
                 if let Some(new_chs) = self.drives[drive_select].next_sector() {
@@ -0,0 +1,69 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    devices::isa_card.rs
+
+    Define the interface for pluggable ISA expansion cards.
+
+    A card implementing IsaCard can be inserted into a slot at runtime with
+    Machine::insert_card(), without the bus needing to know its concrete
+    type. This allows device emulators to be developed in separate crates.
+
+*/
+
+use crate::bus::MemRangeDescriptor;
+
+/// The maximum number of ISA slots a machine may have. The IBM XT has eight.
+pub const MAX_ISA_SLOTS: usize = 8;
+
+pub trait IsaCard {
+    /// Read a byte from the specified IO port, or return None if the card does not respond to it.
+    fn io_read(&mut self, port: u16) -> Option<u8>;
+    /// Write a byte to the specified IO port. Return false if the card does not respond to it.
+    fn io_write(&mut self, port: u16, val: u8) -> bool;
+    /// Read a byte from the specified memory address, or return None if the card does not respond
+    /// to it, in which case the read falls through to system memory.
+    fn mem_read(&self, addr: u32) -> Option<u8>;
+    /// Write a byte to the specified memory address. Return false if the card does not respond to
+    /// it, in which case the write falls through to system memory.
+    fn mem_write(&mut self, addr: u32, val: u8) -> bool;
+    /// Advance the card by the specified number of system clock ticks.
+    fn tick(&mut self, cycles: u32);
+    /// Return the IRQ line the card is currently asserting, if any.
+    fn get_irq_line(&self) -> Option<u8>;
+    /// Return the IO ports the card decodes. Ports already claimed by another device are skipped.
+    fn port_list(&self) -> Vec<(String, u16)>;
+    /// Return the memory ranges the card decodes. Ranges must be aligned to 8K boundaries.
+    fn mem_ranges(&self) -> Vec<MemRangeDescriptor> {
+        Vec::new()
+    }
+}
+
+/// An ISA card installed in a slot, along with the IRQ line it last asserted.
+pub struct IsaSlot {
+    pub card: Box<dyn IsaCard>,
+    pub irq:  Option<u8>,
+}
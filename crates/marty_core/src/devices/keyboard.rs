@@ -38,10 +38,14 @@ use std::{
 };
 use strum::IntoEnumIterator;
 
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use toml;
 
-use crate::{keys::MartyKey, machine::KeybufferEntry};
+use crate::{keys::MartyKey, machine::KeybufferEntry, savestate::SaveState};
+
+/// Minimum delay between injected scancodes, in ms. A make and break pair every 30ms is
+/// roughly 33 characters per second.
+pub const INJECT_SCANCODE_DELAY: f64 = 15.0;
 
 // Define the various types of keyboard we can emulate.
 #[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
@@ -90,6 +94,96 @@ impl KeyboardModifiers {
     }
 }
 
+// Lock key bits of the BIOS keyboard flag byte at 0040:0017
+pub const BIOS_KB_FLAG_ADDRESS: usize = 0x417;
+pub const BIOS_KB_FLAG_SCROLL: u8 = 0x10;
+pub const BIOS_KB_FLAG_NUM: u8 = 0x20;
+pub const BIOS_KB_FLAG_CAPS: u8 = 0x40;
+
+// The BIOS type-ahead buffer at 0040:001E. The head and tail pointers are offsets from segment 0040.
+pub const BIOS_KB_BUFFER_HEAD_ADDRESS: usize = 0x41A;
+pub const BIOS_KB_BUFFER_START: u16 = 0x1E;
+pub const BIOS_KB_BUFFER_END: u16 = 0x3E;
+
+pub const SCANCODE_CAPS_LOCK: u8 = 0x3A;
+pub const SCANCODE_NUM_LOCK: u8 = 0x45;
+pub const SCANCODE_SCROLL_LOCK: u8 = 0x46;
+
+/// The state of the Caps, Num and Scroll lock toggles.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardLockState {
+    pub caps:   bool,
+    pub num:    bool,
+    pub scroll: bool,
+}
+
+impl KeyboardLockState {
+    /// Decode the lock state from the BIOS keyboard flag byte.
+    pub fn from_bios_flags(flags: u8) -> Self {
+        Self {
+            caps:   flags & BIOS_KB_FLAG_CAPS != 0,
+            num:    flags & BIOS_KB_FLAG_NUM != 0,
+            scroll: flags & BIOS_KB_FLAG_SCROLL != 0,
+        }
+    }
+
+    /// Return the make scancodes of the lock keys that must be pressed to change this state to `target`.
+    pub fn toggles_to(&self, target: &KeyboardLockState) -> Vec<u8> {
+        let mut scancodes = Vec::new();
+        if self.caps != target.caps {
+            scancodes.push(SCANCODE_CAPS_LOCK);
+        }
+        if self.num != target.num {
+            scancodes.push(SCANCODE_NUM_LOCK);
+        }
+        if self.scroll != target.scroll {
+            scancodes.push(SCANCODE_SCROLL_LOCK);
+        }
+        scancodes
+    }
+}
+
+/// A keystroke waiting in the BIOS type-ahead buffer, as INT 16h AH=00h would return it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BiosKey {
+    pub scancode: u8,
+    pub ascii:    u8,
+}
+
+impl BiosKey {
+    /// Decode the keystrokes pending in the BIOS type-ahead buffer, oldest first. `bda` is the BIOS data
+    /// area starting at the buffer head pointer (0040:001A) and must span the end of the buffer.
+    /// An empty list is returned if the head or tail pointer does not point into the buffer, such as
+    /// before the BIOS has initialized it.
+    pub fn from_bios_buffer(bda: &[u8]) -> Vec<BiosKey> {
+        let base = (BIOS_KB_BUFFER_HEAD_ADDRESS & 0xFF) as u16;
+        if bda.len() < (BIOS_KB_BUFFER_END - base) as usize {
+            return Vec::new();
+        }
+        let head = u16::from_le_bytes([bda[0], bda[1]]);
+        let tail = u16::from_le_bytes([bda[2], bda[3]]);
+        let in_buffer = |ptr: u16| (BIOS_KB_BUFFER_START..BIOS_KB_BUFFER_END).contains(&ptr) && ptr & 1 == 0;
+        if !in_buffer(head) || !in_buffer(tail) {
+            return Vec::new();
+        }
+
+        let mut keys = Vec::new();
+        let mut ptr = head;
+        while ptr != tail {
+            let offset = (ptr - base) as usize;
+            keys.push(BiosKey {
+                ascii:    bda[offset],
+                scancode: bda[offset + 1],
+            });
+            ptr += 2;
+            if ptr == BIOS_KB_BUFFER_END {
+                ptr = BIOS_KB_BUFFER_START;
+            }
+        }
+        keys
+    }
+}
+
 /// Incoming keycode-presses can be translated two possible ways.
 /// In macro mode, translation produces additional keycodes that are fed back
 /// into the emulator's keyboard buffer for later delivery and processing.
@@ -178,6 +272,9 @@ pub struct Keyboard {
     kb_buffer: Vec<u8>, // Keyboard buffer. Variable length depending on keyboard model.
     kb_buffer_overflow: bool,
     keycode_mappings: Vec<KeycodeMapping>,
+    inject_buffer: VecDeque<u8>, // Injected scancodes awaiting delivery, such as from macro playback.
+    inject_timer: f64,           // Time since the last injected scancode was delivered (ms)
+    recording: Option<Vec<u8>>,  // Scancodes received from the physical keyboard while recording.
 }
 
 impl Default for Keyboard {
@@ -194,8 +291,40 @@ impl Default for Keyboard {
             kb_buffer: Vec::new(),
             kb_buffer_overflow: false,
             keycode_mappings: Vec::new(),
+            inject_buffer: VecDeque::new(),
+            inject_timer: 0.0,
+            recording: None,
+        }
+    }
+}
+
+/// Snapshot of the scancodes the keyboard has not yet delivered to the machine. Keys held on the host
+/// are not included.
+#[derive(Serialize, Deserialize)]
+pub struct KeyboardSaveState {
+    kb_buffer: Vec<u8>,
+    kb_buffer_overflow: bool,
+}
+
+impl SaveState for Keyboard {
+    type State = KeyboardSaveState;
+
+    fn save_state(&self) -> KeyboardSaveState {
+        KeyboardSaveState {
+            kb_buffer: self.kb_buffer.clone(),
+            kb_buffer_overflow: self.kb_buffer_overflow,
         }
     }
+
+    /// Restoring releases all held keys and discards any injected scancodes awaiting delivery.
+    fn restore_state(&mut self, state: KeyboardSaveState) {
+        self.kb_buffer = state.kb_buffer;
+        self.kb_buffer_overflow = state.kb_buffer_overflow;
+        self.clear();
+        self.keys_pressed.clear();
+        self.inject_buffer.clear();
+        self.inject_timer = 0.0;
+    }
 }
 
 impl Keyboard {
@@ -673,14 +802,95 @@ impl Keyboard {
     }
 
     /// Read out a scancode from the keyboard or None if no key in buffer.
+    /// Scancodes from physical keys take priority over injected scancodes.
     pub fn recv_scancode(&mut self) -> Option<u8> {
         if self.kb_buffer_overflow {
             // Send the keyboard overflow scancode
             self.kb_buffer_overflow = false;
             Some(0xFF)
         }
+        else if let Some(scancode) = self.kb_buffer.pop() {
+            if let Some(recording) = &mut self.recording {
+                recording.push(scancode);
+            }
+            Some(scancode)
+        }
+        else if self.inject_timer >= INJECT_SCANCODE_DELAY {
+            // Pace injected scancodes like a fast typist would, so that long injected strings don't
+            // overrun the BIOS keyboard buffer of a program that only reads keys occasionally.
+            let scancode = self.inject_buffer.pop_front();
+            if scancode.is_some() {
+                self.inject_timer = 0.0;
+            }
+            scancode
+        }
         else {
-            self.kb_buffer.pop()
+            None
+        }
+    }
+
+    /// Queue a raw scancode for delivery as if it were sent by a physical key. Unlike the keyboard buffer,
+    /// injected scancodes are never dropped; they are delivered one at a time as the keyboard interface
+    /// becomes ready to receive them.
+    pub fn inject_scancode(&mut self, scancode: u8) {
+        self.inject_buffer.push_back(scancode);
+    }
+
+    /// Return the number of injected scancodes awaiting delivery.
+    pub fn injected_len(&self) -> usize {
+        self.inject_buffer.len()
+    }
+
+    /// Begin recording scancodes produced by physical key presses.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    /// Stop recording and return the recorded scancodes.
+    pub fn stop_recording(&mut self) -> Vec<u8> {
+        self.recording.take().unwrap_or_default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Translate an ASCII character into a scancode set 1 make code for a US keyboard layout.
+    /// Returns the make code and whether the shift key must be held.
+    pub fn ascii_to_scancode(c: char) -> Option<(u8, bool)> {
+        const ROW_NUMBERS: &[u8] = b"1234567890-=";
+        const ROW_NUMBERS_SHIFTED: &[u8] = b"!@#$%^&*()_+";
+        const ROW_Q: &[u8] = b"qwertyuiop[]";
+        const ROW_Q_SHIFTED: &[u8] = b"QWERTYUIOP{}";
+        const ROW_A: &[u8] = b"asdfghjkl;'`";
+        const ROW_A_SHIFTED: &[u8] = b"ASDFGHJKL:\"~";
+        const ROW_Z: &[u8] = b"\\zxcvbnm,./";
+        const ROW_Z_SHIFTED: &[u8] = b"|ZXCVBNM<>?";
+
+        if !c.is_ascii() {
+            return None;
+        }
+        let b = c as u8;
+        let find = |row: &[u8], base: u8| row.iter().position(|&r| r == b).map(|i| base + i as u8);
+
+        match c {
+            '\n' | '\r' => Some((0x1C, false)),
+            '\t' => Some((0x0F, false)),
+            ' ' => Some((0x39, false)),
+            '\x08' => Some((0x0E, false)),
+            '\x1B' => Some((0x01, false)),
+            _ => find(ROW_NUMBERS, 0x02)
+                .or_else(|| find(ROW_Q, 0x10))
+                .or_else(|| find(ROW_A, 0x1E))
+                .or_else(|| find(ROW_Z, 0x2B))
+                .map(|sc| (sc, false))
+                .or_else(|| {
+                    find(ROW_NUMBERS_SHIFTED, 0x02)
+                        .or_else(|| find(ROW_Q_SHIFTED, 0x10))
+                        .or_else(|| find(ROW_A_SHIFTED, 0x1E))
+                        .or_else(|| find(ROW_Z_SHIFTED, 0x2B))
+                        .map(|sc| (sc, true))
+                }),
         }
     }
 
@@ -790,6 +1000,8 @@ impl Keyboard {
         // Convert to milliseconds, all typematic delays are in ms.
         let ms: f64 = us / 1000.0;
 
+        self.inject_timer += ms;
+
         let mut repeating_keys = Vec::new();
 
         // Update keys pressed.
@@ -848,3 +1060,66 @@ impl Keyboard {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_state_toggles() {
+        let state = KeyboardLockState::from_bios_flags(BIOS_KB_FLAG_CAPS | 0x03);
+        assert_eq!(
+            state,
+            KeyboardLockState {
+                caps:   true,
+                num:    false,
+                scroll: false,
+            }
+        );
+
+        let target = KeyboardLockState {
+            caps:   false,
+            num:    true,
+            scroll: false,
+        };
+        assert_eq!(state.toggles_to(&target), vec![SCANCODE_CAPS_LOCK, SCANCODE_NUM_LOCK]);
+        assert!(target.toggles_to(&target).is_empty());
+    }
+
+    #[test]
+    fn bios_key_buffer_wraps() {
+        let base = (BIOS_KB_BUFFER_HEAD_ADDRESS & 0xFF) as u16;
+        let mut bda = vec![0u8; (BIOS_KB_BUFFER_END - base) as usize];
+
+        // Empty buffer: head == tail.
+        bda[0..2].copy_from_slice(&0x1Eu16.to_le_bytes());
+        bda[2..4].copy_from_slice(&0x1Eu16.to_le_bytes());
+        assert!(BiosKey::from_bios_buffer(&bda).is_empty());
+
+        // Two keys, the second stored at the start of the buffer after wrapping.
+        let last = (BIOS_KB_BUFFER_END - 2 - base) as usize;
+        bda[last] = b'a';
+        bda[last + 1] = 0x1E;
+        bda[4] = b'b';
+        bda[5] = 0x30;
+        bda[0..2].copy_from_slice(&(BIOS_KB_BUFFER_END - 2).to_le_bytes());
+        bda[2..4].copy_from_slice(&0x20u16.to_le_bytes());
+        assert_eq!(
+            BiosKey::from_bios_buffer(&bda),
+            vec![
+                BiosKey {
+                    scancode: 0x1E,
+                    ascii:    b'a',
+                },
+                BiosKey {
+                    scancode: 0x30,
+                    ascii:    b'b',
+                },
+            ]
+        );
+
+        // Uninitialized pointers.
+        bda[0..4].fill(0);
+        assert!(BiosKey::from_bios_buffer(&bda).is_empty());
+    }
+}
@@ -31,9 +31,12 @@
 
 */
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice, MemRangeDescriptor, MemoryMappedDevice, NO_IO_BYTE},
     cpu_common::LogicAnalyzer,
+    savestate::SaveState,
 };
 
 pub const LOTECH_DEFAULT_IO_BASE: u16 = 0x260;
@@ -47,7 +50,7 @@ pub const LOTECH_PAGE_MASK: usize = 0b1100_0000_0000_0000;
 pub const LOTECH_BASE_MASK: usize = 0b0011_1111_1111_1111;
 pub const LOTECH_PAGE_SHIFT: usize = 14;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct PageRegister {
     page_addr: usize,
 }
@@ -70,6 +73,34 @@ impl Default for LotechEmsCard {
     }
 }
 
+/// Snapshot of the EMS page registers and the contents of the EMS memory.
+#[derive(Serialize, Deserialize)]
+pub struct EmsSaveState {
+    pages: [PageRegister; 4],
+    mem:   Vec<u8>,
+}
+
+impl SaveState for LotechEmsCard {
+    type State = EmsSaveState;
+
+    fn save_state(&self) -> EmsSaveState {
+        EmsSaveState {
+            pages: self.pages,
+            mem:   self.mem.clone(),
+        }
+    }
+
+    fn restore_state(&mut self, state: EmsSaveState) {
+        self.pages = state.pages;
+        if state.mem.len() == self.mem.len() {
+            self.mem = state.mem;
+        }
+        else {
+            log::error!("EmsSaveState: EMS memory size mismatch, memory not restored");
+        }
+    }
+}
+
 impl LotechEmsCard {
     pub fn new(port_base: Option<u16>, window_seg: Option<usize>) -> Self {
         LotechEmsCard {
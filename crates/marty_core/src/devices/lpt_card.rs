@@ -33,7 +33,8 @@
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice, NO_IO_BYTE},
     cpu_common::LogicAnalyzer,
-    devices::lpt_port::ParallelPort,
+    devices::lpt_port::{ParallelPort, ParallelPortSaveState},
+    savestate::SaveState,
 };
 
 pub const LPT_DEFAULT_IO_BASE: u16 = 0x3BC;
@@ -62,6 +63,18 @@ impl ParallelController {
     }
 }
 
+impl SaveState for ParallelController {
+    type State = ParallelPortSaveState;
+
+    fn save_state(&self) -> ParallelPortSaveState {
+        self.lpt.save_state()
+    }
+
+    fn restore_state(&mut self, state: ParallelPortSaveState) {
+        self.lpt.restore_state(state);
+    }
+}
+
 impl IoDevice for ParallelController {
     fn read_u8(&mut self, port: u16, _delta: DeviceRunTimeUnit) -> u8 {
         // Catch up to CPU state.
@@ -32,8 +32,9 @@
 
 */
 
-use crate::tracelogger::TraceLogger;
+use crate::{savestate::SaveState, tracelogger::TraceLogger};
 use modular_bitfield::{bitfield, prelude::*};
+use serde::{Deserialize, Serialize};
 
 pub const LPT_DEFAULT_IRQ: u16 = 7;
 
@@ -82,6 +83,32 @@ impl Default for ParallelPort {
     }
 }
 
+/// Snapshot of the parallel port registers.
+#[derive(Serialize, Deserialize)]
+pub struct ParallelPortSaveState {
+    data:    u8,
+    status:  u8,
+    control: u8,
+}
+
+impl SaveState for ParallelPort {
+    type State = ParallelPortSaveState;
+
+    fn save_state(&self) -> ParallelPortSaveState {
+        ParallelPortSaveState {
+            data:    self.data,
+            status:  self.status.into_bytes()[0],
+            control: self.control.into_bytes()[0],
+        }
+    }
+
+    fn restore_state(&mut self, state: ParallelPortSaveState) {
+        self.data = state.data;
+        self.status = ParallelStatus::from_bytes([state.status]);
+        self.control = ParallelControl::from_bytes([state.control]);
+    }
+}
+
 impl ParallelPort {
     pub fn new(irq: Option<u16>, trace_logger: TraceLogger) -> Self {
         Self {
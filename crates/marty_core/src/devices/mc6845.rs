@@ -31,7 +31,8 @@
 
 */
 
-use crate::{device_traits::videocard::VideoCardStateEntry, tracelogger::TraceLogger};
+use crate::{device_traits::videocard::VideoCardStateEntry, savestate::SaveState, tracelogger::TraceLogger};
+use serde::{Deserialize, Serialize};
 
 const CURSOR_LINE_MASK: u8 = 0b0000_1111;
 const CURSOR_ATTR_MASK: u8 = 0b0011_0000;
@@ -53,7 +54,7 @@ pub enum CursorStatus {
     SlowBlink,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CrtcRegister {
     HorizontalTotal,
     HorizontalDisplayed,
@@ -113,7 +114,7 @@ macro_rules! push_reg_str {
 
 pub type HBlankCallback = dyn FnMut() -> u8;
 
-#[derive(Copy, Clone, Default, Debug)]
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct CrtcStatus {
     pub hblank: bool,
     pub vblank: bool,
@@ -160,6 +161,37 @@ pub struct Crtc6845 {
     trace_logger: TraceLogger,
 }
 
+/// The CRTC register file and counters.
+#[derive(Serialize, Deserialize)]
+pub struct Crtc6845SaveState {
+    reg: [u8; 18],
+    reg_select: CrtcRegister,
+    start_address: u16,
+    start_address_latch: u16,
+    lightpen_position: u16,
+    cursor_data: [bool; CRTC_ROW_MAX],
+    cursor_address: u16,
+    cursor_enabled: bool,
+    cursor_start_line: u8,
+    cursor_end_line: u8,
+    blink_state: bool,
+    cursor_blink_ct: u8,
+    cursor_blink_rate: Option<u8>,
+    hcc_c0: u8,
+    char_col: u8,
+    vlc_c9: u8,
+    vcc_c4: u8,
+    vsc_c3h: u8,
+    hsc_c3l: u8,
+    vtac_c5: u8,
+    in_vta: bool,
+    vma: u16,
+    vma_t: u16,
+    hsync_target: u8,
+    status: CrtcStatus,
+    in_last_vblank_line: bool,
+}
+
 impl Crtc6845 {
     pub fn new(trace_logger: TraceLogger) -> Self {
         Self {
@@ -726,6 +758,70 @@ impl Crtc6845 {
     }
 }
 
+impl SaveState for Crtc6845 {
+    type State = Crtc6845SaveState;
+
+    fn save_state(&self) -> Crtc6845SaveState {
+        Crtc6845SaveState {
+            reg: self.reg,
+            reg_select: self.reg_select,
+            start_address: self.start_address,
+            start_address_latch: self.start_address_latch,
+            lightpen_position: self.lightpen_position,
+            cursor_data: self.cursor_data,
+            cursor_address: self.cursor_address,
+            cursor_enabled: self.cursor_enabled,
+            cursor_start_line: self.cursor_start_line,
+            cursor_end_line: self.cursor_end_line,
+            blink_state: self.blink_state,
+            cursor_blink_ct: self.cursor_blink_ct,
+            cursor_blink_rate: self.cursor_blink_rate,
+            hcc_c0: self.hcc_c0,
+            char_col: self.char_col,
+            vlc_c9: self.vlc_c9,
+            vcc_c4: self.vcc_c4,
+            vsc_c3h: self.vsc_c3h,
+            hsc_c3l: self.hsc_c3l,
+            vtac_c5: self.vtac_c5,
+            in_vta: self.in_vta,
+            vma: self.vma,
+            vma_t: self.vma_t,
+            hsync_target: self.hsync_target,
+            status: self.status,
+            in_last_vblank_line: self.in_last_vblank_line,
+        }
+    }
+
+    fn restore_state(&mut self, state: Crtc6845SaveState) {
+        self.reg = state.reg;
+        self.reg_select = state.reg_select;
+        self.start_address = state.start_address;
+        self.start_address_latch = state.start_address_latch;
+        self.lightpen_position = state.lightpen_position;
+        self.cursor_data = state.cursor_data;
+        self.cursor_address = state.cursor_address;
+        self.cursor_enabled = state.cursor_enabled;
+        self.cursor_start_line = state.cursor_start_line;
+        self.cursor_end_line = state.cursor_end_line;
+        self.blink_state = state.blink_state;
+        self.cursor_blink_ct = state.cursor_blink_ct;
+        self.cursor_blink_rate = state.cursor_blink_rate;
+        self.hcc_c0 = state.hcc_c0;
+        self.char_col = state.char_col;
+        self.vlc_c9 = state.vlc_c9;
+        self.vcc_c4 = state.vcc_c4;
+        self.vsc_c3h = state.vsc_c3h;
+        self.hsc_c3l = state.hsc_c3l;
+        self.vtac_c5 = state.vtac_c5;
+        self.in_vta = state.in_vta;
+        self.vma = state.vma;
+        self.vma_t = state.vma_t;
+        self.hsync_target = state.hsync_target;
+        self.status = state.status;
+        self.in_last_vblank_line = state.in_last_vblank_line;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
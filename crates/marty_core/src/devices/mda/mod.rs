@@ -48,8 +48,10 @@ mod videocard;
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit},
     device_traits::videocard::*,
+    savestate::SaveState,
     tracelogger::TraceLogger,
 };
+use serde::{Deserialize, Serialize};
 
 /*
 #[derive(Copy, Clone)]
@@ -363,8 +365,8 @@ macro_rules! trace_regs {
 }
 
 use crate::devices::{
-    lpt_port::ParallelPort,
-    mc6845::{Crtc6845, CrtcStatus, HBlankCallback},
+    lpt_port::{ParallelPort, ParallelPortSaveState},
+    mc6845::{Crtc6845, Crtc6845SaveState, CrtcStatus, HBlankCallback},
     mda::io::LPT_DEFAULT_IO_BASE,
 };
 
@@ -511,6 +513,83 @@ pub struct MDACard {
     hgc_page_flips: u32,
 }
 
+/// Serializable state of the MDA or Hercules card, including video memory and the raster position.
+/// The frame buffers are not included and are redrawn from the next frame.
+#[derive(Serialize, Deserialize)]
+pub struct MdaSaveState {
+    cycles: u64,
+    last_vsync_cycles: u64,
+    cur_screen_cycles: u64,
+    cycles_per_vsync: u64,
+    sink_cycles: u32,
+    catching_up: bool,
+    last_rw_tick: u32,
+    slot_idx: usize,
+    mode_pending: bool,
+    clock_pending: bool,
+    mode_byte: u8,
+    mode: u8,
+    display_mode: DisplayMode,
+    mode_enable: bool,
+    mode_graphics: bool,
+    mode_bw: bool,
+    mode_hires_gfx: bool,
+    mode_hires_txt: bool,
+    mode_blinking: bool,
+    scanline_us: f64,
+    frame_us: f64,
+    cursor_frames: u32,
+    frame_count: u64,
+    status_reads: u64,
+    cursor_status: bool,
+    cursor_slowblink: bool,
+    cursor_blink_rate: f64,
+    cursor_data: [bool; MDA_CURSOR_MAX],
+    cursor_attr: u8,
+    last_bit: bool,
+    crtc: Crtc6845SaveState,
+    clock_divisor: u8,
+    char_clock: u32,
+    beam_x: u32,
+    beam_y: u32,
+    in_monitor_hsync: bool,
+    in_monitor_vblank: bool,
+    monitor_hsc: u32,
+    scanline: u32,
+    row_span: u32,
+    missed_hsyncs: u32,
+    overscan_left: u32,
+    overscan_right_start: u32,
+    overscan_right: u32,
+    vsync_len: u32,
+    cur_char: u8,
+    cur_attr: u8,
+    cur_fg: u8,
+    cur_bg: u8,
+    cur_blink: bool,
+    cur_ul: bool,
+    char_col: u8,
+    hcc_c0: u8,
+    vma: usize,
+    vmws: usize,
+    rba: usize,
+    cursor_blink_state: bool,
+    text_blink_state: bool,
+    accumulated_us: f64,
+    ticks_advanced: u32,
+    pixel_clocks_owed: u32,
+    ticks_accum: f64,
+    clocks_accum: u32,
+    mem: Vec<u8>,
+    lightpen_latch: bool,
+    lightpen_addr: usize,
+    lpt: Option<ParallelPortSaveState>,
+    tmp_color: u8,
+    hgc_config: u8,
+    hgc_page_offset: usize,
+    hgc_page_flips: u32,
+}
+
 #[derive(Debug)]
 pub enum CRTCRegister {
     HorizontalTotal,
@@ -548,6 +627,7 @@ impl MdaDefault for DisplayExtents {
             row_stride: MDA_XRES_MAX as usize,
             double_scan: false,
             mode_byte: 0,
+            register_changes: Vec::new(),
         }
     }
 }
@@ -674,6 +754,167 @@ impl Default for MDACard {
     }
 }
 
+impl SaveState for MDACard {
+    type State = MdaSaveState;
+
+    fn save_state(&self) -> MdaSaveState {
+        MdaSaveState {
+            cycles: self.cycles,
+            last_vsync_cycles: self.last_vsync_cycles,
+            cur_screen_cycles: self.cur_screen_cycles,
+            cycles_per_vsync: self.cycles_per_vsync,
+            sink_cycles: self.sink_cycles,
+            catching_up: self.catching_up,
+            last_rw_tick: self.last_rw_tick,
+            slot_idx: self.slot_idx,
+            mode_pending: self.mode_pending,
+            clock_pending: self.clock_pending,
+            mode_byte: self.mode_byte,
+            mode: self.mode.into_bytes()[0],
+            display_mode: self.display_mode,
+            mode_enable: self.mode_enable,
+            mode_graphics: self.mode_graphics,
+            mode_bw: self.mode_bw,
+            mode_hires_gfx: self.mode_hires_gfx,
+            mode_hires_txt: self.mode_hires_txt,
+            mode_blinking: self.mode_blinking,
+            scanline_us: self.scanline_us,
+            frame_us: self.frame_us,
+            cursor_frames: self.cursor_frames,
+            frame_count: self.frame_count,
+            status_reads: self.status_reads,
+            cursor_status: self.cursor_status,
+            cursor_slowblink: self.cursor_slowblink,
+            cursor_blink_rate: self.cursor_blink_rate,
+            cursor_data: self.cursor_data,
+            cursor_attr: self.cursor_attr,
+            last_bit: self.last_bit,
+            crtc: self.crtc.save_state(),
+            clock_divisor: self.clock_divisor,
+            char_clock: self.char_clock,
+            beam_x: self.beam_x,
+            beam_y: self.beam_y,
+            in_monitor_hsync: self.in_monitor_hsync,
+            in_monitor_vblank: self.in_monitor_vblank,
+            monitor_hsc: self.monitor_hsc,
+            scanline: self.scanline,
+            row_span: self.row_span,
+            missed_hsyncs: self.missed_hsyncs,
+            overscan_left: self.overscan_left,
+            overscan_right_start: self.overscan_right_start,
+            overscan_right: self.overscan_right,
+            vsync_len: self.vsync_len,
+            cur_char: self.cur_char,
+            cur_attr: self.cur_attr,
+            cur_fg: self.cur_fg,
+            cur_bg: self.cur_bg,
+            cur_blink: self.cur_blink,
+            cur_ul: self.cur_ul,
+            char_col: self.char_col,
+            hcc_c0: self.hcc_c0,
+            vma: self.vma,
+            vmws: self.vmws,
+            rba: self.rba,
+            cursor_blink_state: self.cursor_blink_state,
+            text_blink_state: self.text_blink_state,
+            accumulated_us: self.accumulated_us,
+            ticks_advanced: self.ticks_advanced,
+            pixel_clocks_owed: self.pixel_clocks_owed,
+            ticks_accum: self.ticks_accum,
+            clocks_accum: self.clocks_accum,
+            mem: self.mem.to_vec(),
+            lightpen_latch: self.lightpen_latch,
+            lightpen_addr: self.lightpen_addr,
+            lpt: self.lpt.as_ref().map(|lpt| lpt.save_state()),
+            tmp_color: self.tmp_color,
+            hgc_config: self.hgc_config.into_bytes()[0],
+            hgc_page_offset: self.hgc_page_offset,
+            hgc_page_flips: self.hgc_page_flips,
+        }
+    }
+
+    fn restore_state(&mut self, state: MdaSaveState) {
+        self.cycles = state.cycles;
+        self.last_vsync_cycles = state.last_vsync_cycles;
+        self.cur_screen_cycles = state.cur_screen_cycles;
+        self.cycles_per_vsync = state.cycles_per_vsync;
+        self.sink_cycles = state.sink_cycles;
+        self.catching_up = state.catching_up;
+        self.last_rw_tick = state.last_rw_tick;
+        self.slot_idx = state.slot_idx;
+        self.mode_pending = state.mode_pending;
+        self.clock_pending = state.clock_pending;
+        self.mode_byte = state.mode_byte;
+        self.mode = MdaModeRegister::from_bytes([state.mode]);
+        self.display_mode = state.display_mode;
+        self.mode_enable = state.mode_enable;
+        self.mode_graphics = state.mode_graphics;
+        self.mode_bw = state.mode_bw;
+        self.mode_hires_gfx = state.mode_hires_gfx;
+        self.mode_hires_txt = state.mode_hires_txt;
+        self.mode_blinking = state.mode_blinking;
+        self.scanline_us = state.scanline_us;
+        self.frame_us = state.frame_us;
+        self.cursor_frames = state.cursor_frames;
+        self.frame_count = state.frame_count;
+        self.status_reads = state.status_reads;
+        self.cursor_status = state.cursor_status;
+        self.cursor_slowblink = state.cursor_slowblink;
+        self.cursor_blink_rate = state.cursor_blink_rate;
+        self.cursor_data = state.cursor_data;
+        self.cursor_attr = state.cursor_attr;
+        self.last_bit = state.last_bit;
+        self.crtc.restore_state(state.crtc);
+        self.clock_divisor = state.clock_divisor;
+        self.char_clock = state.char_clock;
+        self.beam_x = state.beam_x;
+        self.beam_y = state.beam_y;
+        self.in_monitor_hsync = state.in_monitor_hsync;
+        self.in_monitor_vblank = state.in_monitor_vblank;
+        self.monitor_hsc = state.monitor_hsc;
+        self.scanline = state.scanline;
+        self.row_span = state.row_span;
+        self.missed_hsyncs = state.missed_hsyncs;
+        self.overscan_left = state.overscan_left;
+        self.overscan_right_start = state.overscan_right_start;
+        self.overscan_right = state.overscan_right;
+        self.vsync_len = state.vsync_len;
+        self.cur_char = state.cur_char;
+        self.cur_attr = state.cur_attr;
+        self.cur_fg = state.cur_fg;
+        self.cur_bg = state.cur_bg;
+        self.cur_blink = state.cur_blink;
+        self.cur_ul = state.cur_ul;
+        self.char_col = state.char_col;
+        self.hcc_c0 = state.hcc_c0;
+        self.vma = state.vma;
+        self.vmws = state.vmws;
+        self.rba = state.rba;
+        self.cursor_blink_state = state.cursor_blink_state;
+        self.text_blink_state = state.text_blink_state;
+        self.accumulated_us = state.accumulated_us;
+        self.ticks_advanced = state.ticks_advanced;
+        self.pixel_clocks_owed = state.pixel_clocks_owed;
+        self.ticks_accum = state.ticks_accum;
+        self.clocks_accum = state.clocks_accum;
+        if state.mem.len() == self.mem.len() {
+            self.mem.copy_from_slice(&state.mem);
+        }
+        else {
+            log::error!("MdaSaveState: video memory size mismatch, memory not restored");
+        }
+        self.lightpen_latch = state.lightpen_latch;
+        self.lightpen_addr = state.lightpen_addr;
+        if let (Some(lpt), Some(saved)) = (self.lpt.as_mut(), state.lpt) {
+            lpt.restore_state(saved);
+        }
+        self.tmp_color = state.tmp_color;
+        self.hgc_config = HercConfigSwitch::from_bytes([state.hgc_config]);
+        self.hgc_page_offset = state.hgc_page_offset;
+        self.hgc_page_flips = state.hgc_page_flips;
+    }
+}
+
 impl MDACard {
     pub fn new(
         subtype: VideoCardSubType,
@@ -105,6 +105,14 @@ impl VideoCard for MDACard {
         Some((self.beam_x, self.beam_y))
     }
 
+    fn get_raster_position(&self) -> RasterPosition {
+        RasterPosition {
+            frame: self.frame_count,
+            scanline: self.beam_y,
+            dot: self.beam_x,
+        }
+    }
+
     /// Tick the MDA the specified number of video clock cycles.
     fn debug_tick(&mut self, ticks: u32, _cpumem: Option<&[u8]>) {
         match self.clock_mode {
@@ -524,4 +532,32 @@ impl VideoCard for MDACard {
 
         strings
     }
+
+    fn get_text_buffer(&self) -> Option<TextBuffer> {
+        if self.mode_graphics {
+            return None;
+        }
+        let start_addr = self.crtc.start_address() as usize;
+        let columns = self.crtc.reg[1] as usize;
+        let rows = self.crtc.reg[6] as usize;
+        let cells = (0..columns * rows)
+            .map(|i| {
+                let addr = ((start_addr + i) & MDA_TEXT_MODE_WRAP) << 1;
+                (self.mem[addr], self.mem[addr + 1])
+            })
+            .collect();
+
+        Some(TextBuffer { columns, rows, cells })
+    }
+
+    fn get_register_state(&self) -> Vec<(String, u8)> {
+        let mut regs: Vec<(String, u8)> = MC6845_REGISTER_NAMES
+            .iter()
+            .zip(self.crtc.reg)
+            .map(|(name, val)| (name.to_string(), val))
+            .collect();
+
+        regs.push(("Mode Control (3B8)".to_string(), self.mode_byte));
+        regs
+    }
 }
@@ -0,0 +1,500 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    devices::mm58167.rs
+
+    Implementation of the National Semiconductor MM58167 real time clock, as
+    found on XT clock cards such as the AST SixPakPlus. The chip's 32
+    registers are mapped to consecutive IO ports, at 0x2C0 by default.
+
+    The MM58167 counts thousandths of seconds through months in BCD, but has
+    no year counter; DOS clock utilities keep the year in its RAM latches,
+    which are battery-backed and persisted like CMOS RAM. We still keep a
+    year internally so that the date rolls over correctly and the clock
+    offset set by the guest can be computed.
+
+    The RAM latches double as an alarm: a compare interrupt is raised when
+    they match the counters. A latch with both high bits set matches any
+    value. Periodic interrupts can be enabled for every tenth of a second
+    through every month. The interrupt is only connected if an IRQ is
+    configured, as the cards left it jumpered off by default.
+*/
+
+use crate::{
+    bus::{BusInterface, DeviceRunTimeUnit, IoDevice, NO_IO_BYTE},
+    cpu_common::LogicAnalyzer,
+    device_traits::realtimeclock::RealTimeClock,
+    devices::{
+        pic::Pic,
+        rtc::{from_bcd, to_bcd, HostClock, RtcDateTime},
+    },
+    machine_types::FloppyDriveType,
+    savestate::SaveState,
+};
+use serde::{Deserialize, Serialize};
+
+pub const MM58167_DEFAULT_IO: u16 = 0x2C0;
+pub const MM58167_PORT_CT: u16 = 0x20;
+pub const MM58167_RAM_SIZE: usize = 8;
+
+const REG_MSEC: usize = 0x00;
+const REG_CSEC: usize = 0x01;
+const REG_SECONDS: usize = 0x02;
+const REG_MINUTES: usize = 0x03;
+const REG_HOURS: usize = 0x04;
+const REG_DAY_OF_WEEK: usize = 0x05;
+const REG_DAY_OF_MONTH: usize = 0x06;
+const REG_MONTH: usize = 0x07;
+const REG_RAM: usize = 0x08;
+const REG_INT_STATUS: usize = 0x10;
+const REG_INT_CONTROL: usize = 0x11;
+const REG_COUNTER_RESET: usize = 0x12;
+const REG_RAM_RESET: usize = 0x13;
+const REG_STATUS: usize = 0x14;
+const REG_GO: usize = 0x15;
+const REG_STANDBY: usize = 0x16;
+
+const INT_COMPARE: u8 = 0x01;
+const INT_TENTH: u8 = 0x02;
+const INT_SECOND: u8 = 0x04;
+const INT_MINUTE: u8 = 0x08;
+const INT_HOUR: u8 = 0x10;
+const INT_DAY: u8 = 0x20;
+const INT_WEEK: u8 = 0x40;
+const INT_MONTH: u8 = 0x80;
+
+/// A RAM latch with both high bits set matches any counter value.
+const RAM_DONT_CARE: u8 = 0xC0;
+const TENTH_US: f64 = 100_000.0;
+const SECOND_US: f64 = 1_000_000.0;
+
+pub struct Mm58167 {
+    io_base: u16,
+    irq: Option<u8>,
+    time: RtcDateTime,
+    day_of_week: u8,
+    /// Microseconds into the current second.
+    sub_us: f64,
+    ram: [u8; MM58167_RAM_SIZE],
+    ram_dirty: bool,
+    int_status: u8,
+    int_control: u8,
+    standby: bool,
+    irq_asserted: bool,
+    host_clock: HostClock,
+    clock_offset: i64,
+}
+
+/// Snapshot of the clock's counters, RAM latches and interrupt registers.
+#[derive(Serialize, Deserialize)]
+pub struct Mm58167SaveState {
+    time: RtcDateTime,
+    day_of_week: u8,
+    sub_us: f64,
+    ram: [u8; MM58167_RAM_SIZE],
+    int_status: u8,
+    int_control: u8,
+    standby: bool,
+    irq_asserted: bool,
+    clock_offset: i64,
+}
+
+impl SaveState for Mm58167 {
+    type State = Mm58167SaveState;
+
+    fn save_state(&self) -> Mm58167SaveState {
+        Mm58167SaveState {
+            time: self.time,
+            day_of_week: self.day_of_week,
+            sub_us: self.sub_us,
+            ram: self.ram,
+            int_status: self.int_status,
+            int_control: self.int_control,
+            standby: self.standby,
+            irq_asserted: self.irq_asserted,
+            clock_offset: self.clock_offset,
+        }
+    }
+
+    /// The restored RAM is marked dirty, as it no longer matches the persisted image.
+    fn restore_state(&mut self, state: Mm58167SaveState) {
+        self.time = state.time;
+        self.day_of_week = state.day_of_week;
+        self.sub_us = state.sub_us;
+        self.ram = state.ram;
+        self.ram_dirty = true;
+        self.int_status = state.int_status;
+        self.int_control = state.int_control;
+        self.standby = state.standby;
+        self.irq_asserted = state.irq_asserted;
+        self.clock_offset = state.clock_offset;
+        self.host_clock.freeze();
+    }
+}
+
+impl Mm58167 {
+    pub fn new(io_base: Option<u16>, irq: Option<u8>, time: RtcDateTime) -> Self {
+        let mut clock = Self {
+            io_base: io_base.unwrap_or(MM58167_DEFAULT_IO),
+            irq,
+            time: RtcDateTime::default(),
+            day_of_week: 1,
+            sub_us: 0.0,
+            ram: [0; MM58167_RAM_SIZE],
+            ram_dirty: false,
+            int_status: 0,
+            int_control: 0,
+            standby: false,
+            irq_asserted: false,
+            host_clock: HostClock::default(),
+            clock_offset: 0,
+        };
+        clock.set_time(time);
+        clock
+    }
+
+    pub fn set_time(&mut self, time: RtcDateTime) {
+        self.time = time;
+        self.day_of_week = time.day_of_week();
+        self.sub_us = 0.0;
+    }
+
+    fn read_counter(&self, reg: usize) -> u8 {
+        match reg {
+            REG_MSEC => (((self.sub_us / 1000.0) as u32 % 10) as u8) << 4,
+            REG_CSEC => {
+                let tenths = (self.sub_us / TENTH_US) as u32 % 10;
+                let hundredths = (self.sub_us / 10_000.0) as u32 % 10;
+                ((tenths as u8) << 4) | hundredths as u8
+            }
+            REG_SECONDS => to_bcd(self.time.second),
+            REG_MINUTES => to_bcd(self.time.minute),
+            REG_HOURS => to_bcd(self.time.hour),
+            REG_DAY_OF_WEEK => to_bcd(self.day_of_week),
+            REG_DAY_OF_MONTH => to_bcd(self.time.day),
+            _ => to_bcd(self.time.month),
+        }
+    }
+
+    fn write_counter(&mut self, reg: usize, data: u8) {
+        let before = self.time.to_unix();
+        match reg {
+            REG_MSEC => {
+                let whole = (self.sub_us / 10_000.0).floor() * 10_000.0;
+                self.sub_us = whole + (data >> 4) as f64 * 1000.0;
+            }
+            REG_CSEC => self.sub_us = from_bcd(data) as f64 * 10_000.0,
+            REG_SECONDS => self.time.second = from_bcd(data),
+            REG_MINUTES => self.time.minute = from_bcd(data),
+            REG_HOURS => self.time.hour = from_bcd(data),
+            REG_DAY_OF_WEEK => self.day_of_week = from_bcd(data),
+            REG_DAY_OF_MONTH => self.time.day = from_bcd(data),
+            _ => self.time.month = from_bcd(data),
+        }
+        // Setting the time moves the clock offset by the same amount.
+        self.clock_offset += self.time.to_unix() - before;
+        self.ram_dirty = true;
+    }
+
+    /// Reset the counters selected by each bit of 'mask'. The day and month counters reset to 1.
+    fn reset_counters(&mut self, mask: u8) {
+        for reg in (REG_MSEC..=REG_MONTH).filter(|reg| mask & (1 << reg) != 0) {
+            let value = if reg >= REG_DAY_OF_WEEK { 1 } else { 0 };
+            self.write_counter(reg, value);
+        }
+    }
+
+    fn compare_matches(&self) -> bool {
+        (REG_MSEC..=REG_MONTH).all(|reg| {
+            let latch = self.ram[reg];
+            latch & RAM_DONT_CARE == RAM_DONT_CARE || latch == self.read_counter(reg)
+        })
+    }
+
+    /// Advance the clock by one second, returning the interrupt flags for the counters that changed.
+    fn second_elapsed(&mut self) -> u8 {
+        let before = self.time;
+        let mut flags = INT_SECOND;
+        if self.time.tick() {
+            flags |= INT_DAY;
+            self.day_of_week = self.day_of_week % 7 + 1;
+            if self.day_of_week == 1 {
+                flags |= INT_WEEK;
+            }
+            if self.time.month != before.month {
+                flags |= INT_MONTH;
+            }
+        }
+        if self.time.minute != before.minute {
+            flags |= INT_MINUTE;
+        }
+        if self.time.hour != before.hour {
+            flags |= INT_HOUR;
+        }
+        flags
+    }
+
+    /// Advance the clock by 'us' microseconds. The counters are checked against the RAM latches at
+    /// each tenth of a second, so a compare on the thousandths or hundredths will only match 0.
+    fn advance(&mut self, mut us: f64) {
+        loop {
+            let next_tenth = ((self.sub_us / TENTH_US).floor() + 1.0) * TENTH_US;
+            if self.sub_us + us < next_tenth {
+                self.sub_us += us;
+                return;
+            }
+            us -= next_tenth - self.sub_us;
+            self.sub_us = next_tenth;
+
+            let mut flags = INT_TENTH;
+            if self.sub_us >= SECOND_US {
+                self.sub_us = 0.0;
+                flags |= self.second_elapsed();
+            }
+            if self.compare_matches() {
+                flags |= INT_COMPARE;
+            }
+            self.int_status |= flags & self.int_control;
+        }
+    }
+
+    pub fn run(&mut self, pic: Option<&mut Pic>, us: f64) {
+        let elapsed = self.host_clock.elapsed_us(us);
+        self.advance(elapsed);
+
+        let irq = self.int_status != 0 && self.irq.is_some();
+        if irq != self.irq_asserted {
+            if let (Some(pic), Some(line)) = (pic, self.irq) {
+                if irq {
+                    pic.request_interrupt(line & 0x07);
+                }
+                else {
+                    pic.clear_interrupt(line & 0x07);
+                }
+            }
+            self.irq_asserted = irq;
+        }
+    }
+}
+
+impl IoDevice for Mm58167 {
+    fn read_u8(&mut self, port: u16, _delta: DeviceRunTimeUnit) -> u8 {
+        match (port - self.io_base) as usize {
+            reg @ REG_MSEC..=REG_MONTH => self.read_counter(reg),
+            reg @ REG_RAM..=0x0F => self.ram[reg - REG_RAM],
+            REG_INT_STATUS => {
+                // Reading the interrupt status register clears it, lowering the IRQ line on the next run.
+                std::mem::take(&mut self.int_status)
+            }
+            // The counters are only updated between our reads, so they never roll over during one.
+            REG_STATUS => 0,
+            REG_STANDBY => self.standby as u8,
+            _ => NO_IO_BYTE,
+        }
+    }
+
+    fn write_u8(
+        &mut self,
+        port: u16,
+        data: u8,
+        _bus: Option<&mut BusInterface>,
+        _delta: DeviceRunTimeUnit,
+        _analyzer: Option<&mut LogicAnalyzer>,
+    ) {
+        match (port - self.io_base) as usize {
+            reg @ REG_MSEC..=REG_MONTH => self.write_counter(reg, data),
+            reg @ REG_RAM..=0x0F => {
+                self.ram[reg - REG_RAM] = data;
+                self.ram_dirty = true;
+            }
+            REG_INT_CONTROL => self.int_control = data,
+            REG_COUNTER_RESET => self.reset_counters(data),
+            REG_RAM_RESET => {
+                for (i, latch) in self.ram.iter_mut().enumerate() {
+                    if data & (1 << i) != 0 {
+                        *latch = 0;
+                    }
+                }
+                self.ram_dirty = true;
+            }
+            // GO resets the thousandths through seconds counters, to start the clock on an exact second.
+            REG_GO => self.reset_counters(0x07),
+            REG_STANDBY => self.standby = data & 0x01 != 0,
+            _ => {}
+        }
+    }
+
+    fn port_list(&self) -> Vec<(String, u16)> {
+        (0..MM58167_PORT_CT)
+            .map(|i| (format!("MM58167 Register {:02X}", i), self.io_base + i))
+            .collect()
+    }
+}
+
+impl RealTimeClock for Mm58167 {
+    fn time(&self) -> RtcDateTime {
+        self.time
+    }
+
+    fn set_time(&mut self, time: RtcDateTime) {
+        self.set_time(time)
+    }
+
+    fn set_host_clock(&mut self, enabled: bool) {
+        self.host_clock.set_enabled(enabled);
+    }
+
+    fn host_clock(&self) -> bool {
+        self.host_clock.enabled()
+    }
+
+    fn freeze(&mut self) {
+        self.host_clock.freeze();
+    }
+
+    fn irq(&self) -> Option<u8> {
+        self.irq
+    }
+
+    fn run(&mut self, pic: Option<&mut Pic>, us: f64) {
+        self.run(pic, us)
+    }
+
+    fn nvram(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_nvram(&mut self, data: &[u8]) {
+        for (latch, byte) in self.ram.iter_mut().zip(data) {
+            *latch = *byte;
+        }
+        self.ram_dirty = false;
+    }
+
+    fn nvram_dirty(&self) -> bool {
+        self.ram_dirty
+    }
+
+    fn clear_nvram_dirty(&mut self) {
+        self.ram_dirty = false;
+    }
+
+    fn nvram_valid(&self) -> bool {
+        true
+    }
+
+    fn clock_offset(&self) -> i64 {
+        self.clock_offset
+    }
+
+    fn set_clock_offset(&mut self, offset: i64) {
+        self.clock_offset = offset;
+    }
+
+    /// An XT clock card's RAM isn't read by the BIOS, which finds the floppy drives from the
+    /// motherboard switches instead.
+    fn set_floppy_drives(&mut self, _drives: &[FloppyDriveType]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::rtc::RTC_DETERMINISTIC_TIME;
+
+    const US: DeviceRunTimeUnit = DeviceRunTimeUnit::Microseconds(0.0);
+
+    fn read(clock: &mut Mm58167, reg: usize) -> u8 {
+        clock.read_u8(MM58167_DEFAULT_IO + reg as u16, US)
+    }
+
+    fn write(clock: &mut Mm58167, reg: usize, data: u8) {
+        clock.write_u8(MM58167_DEFAULT_IO + reg as u16, data, None, US, None);
+    }
+
+    #[test]
+    fn counters_roll_over_and_raise_interrupts() {
+        // 1999-12-31 23:59:59, a Friday.
+        let mut clock = Mm58167::new(None, Some(2), RtcDateTime::from_unix(946_684_799));
+        assert_eq!(read(&mut clock, REG_HOURS), 0x23);
+        assert_eq!(read(&mut clock, REG_DAY_OF_WEEK), 0x06);
+
+        write(&mut clock, REG_INT_CONTROL, INT_SECOND | INT_MONTH);
+        clock.run(None, 250_000.0);
+        assert_eq!(read(&mut clock, REG_CSEC), 0x25);
+        assert!(!clock.irq_asserted);
+
+        clock.run(None, 750_000.0);
+        assert!(clock.irq_asserted);
+        assert_eq!(read(&mut clock, REG_SECONDS), 0x00);
+        assert_eq!(read(&mut clock, REG_DAY_OF_MONTH), 0x01);
+        assert_eq!(read(&mut clock, REG_MONTH), 0x01);
+        assert_eq!(read(&mut clock, REG_DAY_OF_WEEK), 0x07);
+        assert_eq!(clock.time().year, 2000);
+
+        // Only enabled interrupts are reported, and reading the status clears it.
+        assert_eq!(read(&mut clock, REG_INT_STATUS), INT_SECOND | INT_MONTH);
+        assert_eq!(read(&mut clock, REG_INT_STATUS), 0);
+        clock.run(None, 1.0);
+        assert!(!clock.irq_asserted);
+    }
+
+    #[test]
+    fn compare_interrupt_matches_ram_latches() {
+        let mut clock = Mm58167::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+        for reg in REG_MSEC..=REG_MONTH {
+            write(&mut clock, REG_RAM + reg, RAM_DONT_CARE);
+        }
+        write(&mut clock, REG_RAM + REG_SECONDS, 0x02);
+        write(&mut clock, REG_INT_CONTROL, INT_COMPARE);
+        assert!(clock.nvram_dirty());
+
+        clock.run(None, 1_000_000.0);
+        assert_eq!(read(&mut clock, REG_INT_STATUS), 0);
+        clock.run(None, 1_000_000.0);
+        assert_eq!(read(&mut clock, REG_INT_STATUS), INT_COMPARE);
+        // No IRQ is connected.
+        assert!(!clock.irq_asserted);
+    }
+
+    #[test]
+    fn setting_the_time_moves_the_offset() {
+        let mut clock = Mm58167::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+        write(&mut clock, REG_HOURS, 0x02);
+        write(&mut clock, REG_MINUTES, 0x30);
+        assert_eq!(clock.clock_offset(), 2 * 3600 + 30 * 60);
+
+        // The day of the week counter is independent of the date.
+        write(&mut clock, REG_DAY_OF_WEEK, 0x05);
+        assert_eq!(clock.clock_offset(), 2 * 3600 + 30 * 60);
+
+        // GO restarts the clock on the second.
+        clock.run(None, 2_500_000.0);
+        write(&mut clock, REG_GO, 0);
+        assert_eq!(read(&mut clock, REG_SECONDS), 0x00);
+        assert_eq!(read(&mut clock, REG_CSEC), 0x00);
+        assert_eq!(clock.clock_offset(), 2 * 3600 + 30 * 60 - 2);
+    }
+}
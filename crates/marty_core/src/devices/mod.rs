@@ -43,18 +43,25 @@ pub mod fdc;
 pub mod floppy_drive;
 pub mod game_port;
 pub mod hdc;
+pub mod isa_card;
 pub mod keyboard;
 pub mod lotech_ems;
 pub mod lpt_card;
 pub mod lpt_port;
 pub mod mc6845;
 pub mod mda;
+pub mod mm58167;
 pub mod mouse;
 pub mod null_sound;
+pub mod pc_speaker_recorder;
 pub mod pic;
 pub mod pit;
+pub mod post_code;
 pub mod ppi;
+pub mod rtc;
 pub mod serial;
+#[cfg(feature = "sound")]
+pub mod sn76489;
 pub mod tga;
 #[cfg(feature = "vga")]
 pub mod vga;
@@ -31,7 +31,9 @@
 */
 use std::collections::VecDeque;
 
-use crate::devices::serial::SerialPortController;
+use serde::{Deserialize, Serialize};
+
+use crate::{devices::serial::SerialPortController, savestate::SaveState};
 
 // Scale factor for real vs emulated mouse deltas. Need to play with
 // this value until it feels right.
@@ -63,6 +65,34 @@ pub enum MouseUpdate {
     Update(u8, u8, u8),
 }
 
+/// Snapshot of the mouse's view of the serial control lines.
+#[derive(Serialize, Deserialize)]
+pub struct MouseSaveState {
+    rts: bool,
+    rts_low_timer: f64,
+    dtr: bool,
+}
+
+impl SaveState for Mouse {
+    type State = MouseSaveState;
+
+    fn save_state(&self) -> MouseSaveState {
+        MouseSaveState {
+            rts: self.rts,
+            rts_low_timer: self.rts_low_timer,
+            dtr: self.dtr,
+        }
+    }
+
+    /// Restoring discards any host mouse movement not yet sent to the serial port.
+    fn restore_state(&mut self, state: MouseSaveState) {
+        self.rts = state.rts;
+        self.rts_low_timer = state.rts_low_timer;
+        self.dtr = state.dtr;
+        self.updates.clear();
+    }
+}
+
 impl Mouse {
     pub fn new(port: usize) -> Self {
         Self {
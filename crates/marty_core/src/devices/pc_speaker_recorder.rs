@@ -0,0 +1,190 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    devices::pc_speaker_recorder.rs
+
+    Records the PC speaker output as 16-bit PCM.
+
+    The recorder is attached to the PIT, which reports the speaker level (the
+    AND of timer channel 2's output and the PPI's speaker data bit) on each
+    PIT clock. Only changes in level are acted upon; the square wave between
+    transitions is rendered into the sample buffer with a box filter over each
+    output sample period.
+
+*/
+
+use crate::devices::pit::PIT_MHZ;
+
+/// Amplitude of the speaker's 'on' level. The 'off' level is silence.
+pub const SPEAKER_RECORD_AMPLITUDE: i16 = 0x4000;
+
+pub struct PcSpeakerRecorder {
+    sample_rate: u32,
+    pit_hz: u64,
+    level: bool,
+    last_tick: u64,
+    cur_tick: u64,
+    started: bool,
+    // Position within the current output sample period, in units of (PIT ticks * sample rate).
+    phase: u64,
+    period_ticks: u64,
+    period_high_ticks: u64,
+    samples: Vec<i16>,
+}
+
+impl PcSpeakerRecorder {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            pit_hz: (PIT_MHZ * 1_000_000.0).round() as u64,
+            level: false,
+            last_tick: 0,
+            cur_tick: 0,
+            started: false,
+            phase: 0,
+            period_ticks: 0,
+            period_high_ticks: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Report the speaker level for the specified PIT tick. The square wave is only rendered when
+    /// the level changes or samples are requested.
+    #[inline]
+    pub fn tick(&mut self, pit_tick: u64, level: bool) {
+        if !self.started {
+            // Recording starts at the first tick we see.
+            self.started = true;
+            self.last_tick = pit_tick;
+            self.level = level;
+        }
+        else if level != self.level {
+            self.render_to(pit_tick);
+            self.level = level;
+        }
+        self.cur_tick = pit_tick;
+    }
+
+    /// Render the speaker output up to the last reported tick and return all complete samples,
+    /// leaving the recorder's buffer empty.
+    pub fn get_samples(&mut self) -> Vec<i16> {
+        if self.started {
+            self.render_to(self.cur_tick);
+        }
+        std::mem::take(&mut self.samples)
+    }
+
+    /// Render the current level from the last transition up to (but not including) the specified tick.
+    fn render_to(&mut self, pit_tick: u64) {
+        let mut ticks = pit_tick.saturating_sub(self.last_tick);
+        self.last_tick = pit_tick;
+
+        let rate = self.sample_rate as u64;
+        while ticks > 0 {
+            // Number of ticks until the end of the current output sample period.
+            let to_boundary = self.pit_hz.saturating_sub(self.phase).div_ceil(rate).max(1);
+            let step = ticks.min(to_boundary);
+
+            self.period_ticks += step;
+            if self.level {
+                self.period_high_ticks += step;
+            }
+            self.phase += step * rate;
+            ticks -= step;
+
+            // A sample rate above the PIT frequency can complete more than one sample period per tick.
+            while self.phase >= self.pit_hz {
+                self.phase -= self.pit_hz;
+                self.emit_sample();
+            }
+        }
+    }
+
+    fn emit_sample(&mut self) {
+        let sample = if self.period_ticks > 0 {
+            (self.period_high_ticks * SPEAKER_RECORD_AMPLITUDE as u64 / self.period_ticks) as i16
+        }
+        else {
+            0
+        };
+        self.samples.push(sample);
+        self.period_ticks = 0;
+        self.period_high_ticks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_wave_sample_count_and_levels() {
+        let mut recorder = PcSpeakerRecorder::new(44100);
+        let pit_hz = (PIT_MHZ * 1_000_000.0).round() as u64;
+
+        // One second of a square wave with a period of 1000 PIT ticks.
+        for tick in 0..pit_hz {
+            recorder.tick(tick, (tick / 500) % 2 == 0);
+        }
+        let samples = recorder.get_samples();
+
+        // The final partial sample period is not emitted.
+        assert!((samples.len() as i64 - 44100).abs() <= 1);
+        assert_eq!(samples[0], SPEAKER_RECORD_AMPLITUDE);
+        assert!(samples.iter().any(|&s| s == 0));
+        assert!(samples.iter().all(|&s| (0..=SPEAKER_RECORD_AMPLITUDE).contains(&s)));
+
+        // Buffer is drained by get_samples.
+        assert!(recorder.get_samples().is_empty());
+    }
+
+    #[test]
+    fn silent_speaker_records_silence() {
+        let mut recorder = PcSpeakerRecorder::new(48000);
+        for tick in 0..100_000 {
+            recorder.tick(tick, false);
+        }
+        let samples = recorder.get_samples();
+        assert!(!samples.is_empty());
+        assert!(samples.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn sample_rate_above_pit_frequency() {
+        let mut recorder = PcSpeakerRecorder::new(2_000_000);
+        let pit_hz = (PIT_MHZ * 1_000_000.0).round() as u64;
+        for tick in 0..10_000 {
+            recorder.tick(tick, true);
+        }
+        let samples = recorder.get_samples();
+        let expected = 9_999 * 2_000_000 / pit_hz as i64;
+        assert!((samples.len() as i64 - expected).abs() <= 1);
+    }
+}
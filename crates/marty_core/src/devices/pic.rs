@@ -34,9 +34,12 @@
 
 //use std::io::Read;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
+    savestate::SaveState,
 };
 //pub const PIC_INTERRUPT_OFFSET: u8 = 8;
 
@@ -65,25 +68,26 @@ const OCW3_RR_COMMAND: u8 = 0b0000_0011;
 
 const SPURIOUS_INTERRUPT: u8 = 7;
 
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum InitializationState {
     Normal,        // Normal operation, can receive an ICW1 at any point
     ExpectingICW2, // In initialization sequence, expecting ICW2
     ExpectingICW4, // In initialization sequence, expecting ICW4
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TriggerMode {
     Edge,
     Level,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum ReadSelect {
     ISR,
     IRR,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct InterruptStats {
     imr_masked_count: u64,
     isr_masked_count: u64,
@@ -102,6 +106,8 @@ impl InterruptStats {
 
 pub type PicRequestFn = fn(&mut Pic, interrupt: u8);
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Pic {
     init_state: InitializationState, // Initialization state for expecting various ICWs
     int_offset: u8,                  // Interrupt Vector Offset (Always 8 on IBM PC)
@@ -208,6 +214,18 @@ impl IoDevice for Pic {
     }
 }
 
+impl SaveState for Pic {
+    type State = Pic;
+
+    fn save_state(&self) -> Pic {
+        self.clone()
+    }
+
+    fn restore_state(&mut self, state: Pic) {
+        *self = state;
+    }
+}
+
 impl Pic {
     pub fn new() -> Self {
         Default::default()
@@ -564,6 +582,22 @@ impl Pic {
         Some(SPURIOUS_INTERRUPT)
     }
 
+    /// Return the vector that get_interrupt_vector() would deliver, without acknowledging the
+    /// interrupt. Used by the CPU to check for an interrupt breakpoint before taking an interrupt.
+    pub fn peek_interrupt_vector(&self) -> Option<u8> {
+        if !self.intr {
+            return None;
+        }
+        let mut ir_bit: u8 = 0x01;
+        for irq in 0..8 {
+            if self.irr & ir_bit != 0 && self.isr & ir_bit == 0 && self.imr & ir_bit == 0 {
+                return Some(irq | self.int_offset);
+            }
+            ir_bit <<= 1;
+        }
+        Some(SPURIOUS_INTERRUPT)
+    }
+
     pub fn get_string_state(&self) -> PicStringState {
         let mut state = PicStringState {
             imr: format!("{:08b}", self.imr),
@@ -36,10 +36,13 @@ use crossbeam_channel::Sender;
 use std::collections::{BTreeMap, VecDeque};
 
 use modular_bitfield::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
+    devices::pc_speaker_recorder::PcSpeakerRecorder,
+    savestate::SaveState,
     syntax_token::*,
     updatable::*,
 };
@@ -66,12 +69,18 @@ pub const PIT_TICK_US: f64 = 1.0 / PIT_MHZ;
 // 1.1931818181818Mhz / 25 = 47.727Khz
 pub const SPEAKER_SAMPLE_RATIO: u32 = 25;
 pub const SPEAKER_SAMPLE_RATE: u32 = 47727;
+// Cutoff frequency of the low-pass filter applied to the speaker signal at the PIT clock rate, before decimation.
+// This is below the Nyquist frequency of the output sample rate to reduce aliasing of high-frequency tones.
+pub const SPEAKER_LPF_CUTOFF: f32 = 15_000.0;
+// Pole of the DC-blocking filter applied to output samples. The speaker is AC coupled, so a constant level is
+// inaudible and should decay to silence rather than leaving an offset when mixed with other sound sources.
+const SPEAKER_DC_BLOCK_POLE: f32 = 0.999;
 
 // Minimum number of system ticks that must elapse between a counter write and the falling edge
 // of the PIT input clock that would latch the value.
 pub const PIT_WRITE_LATENCY: u32 = 3;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ChannelMode {
     InterruptOnTerminalCount,
     HardwareRetriggerableOneShot,
@@ -99,13 +108,13 @@ impl From<u8> for ChannelMode {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ReloadFlag {
     Normal,
     ReloadNextCycle,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, BitfieldSpecifier)]
+#[derive(Debug, Copy, Clone, PartialEq, BitfieldSpecifier, Serialize, Deserialize)]
 pub enum PitType {
     Model8253,
     Model8254,
@@ -119,7 +128,7 @@ enum RwModeField {
     LsbMsb,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum RwMode {
     Lsb,
     Msb,
@@ -135,7 +144,7 @@ pub struct ControlByte {
     channel: B2,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ChannelState {
     WaitingForReload,
     WaitingForGate,
@@ -145,25 +154,26 @@ pub enum ChannelState {
     Counting(ReloadFlag),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum LoadState {
     WaitingForLsb,
     WaitingForMsb,
     //Loaded
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 enum LoadType {
     InitialLoad,
     SubsequentLoad,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ReadState {
     NoRead,
     ReadLsb,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Channel {
     c: usize,
     clocks: u64,
@@ -182,6 +192,9 @@ pub struct Channel {
     armed: bool,
     read_state: ReadState,
     count_is_latched: bool,
+    status_latch: Option<u8>,
+    null_count: bool,
+    ce_loaded_value: u16,
     output: Updatable<bool>,
     output_on_reload: bool,
     reload_on_trigger: bool,
@@ -199,6 +212,53 @@ pub struct PitSpeaker {
     pub sample_accum: f32,
     pub sample_ct: u32,
     pub sender: Option<Sender<f32>>,
+    lpf_alpha: f32,
+    lpf_state: [f32; 2],
+    dc_last_in: f32,
+    dc_last_out: f32,
+}
+
+impl PitSpeaker {
+    pub fn new(sender: Option<Sender<f32>>) -> Self {
+        let pit_hz = (PIT_MHZ * 1_000_000.0) as f32;
+        Self {
+            enabled: sender.is_some(),
+            sample_accum: 0.0,
+            sample_ct: 0,
+            sender,
+            lpf_alpha: 1.0 - (-2.0 * std::f32::consts::PI * SPEAKER_LPF_CUTOFF / pit_hz).exp(),
+            lpf_state: [0.0; 2],
+            dc_last_in: 0.0,
+            dc_last_out: 0.0,
+        }
+    }
+
+    /// Process the speaker level for a single PIT clock. Every SPEAKER_SAMPLE_RATIO clocks, an output sample
+    /// is sent to the sound output.
+    /// The square wave is band-limited by a two-pole low-pass filter followed by a box filter over each output
+    /// sample period. This also smooths directly toggled ("bit-banged") speaker output into usable PCM.
+    #[inline]
+    pub fn process(&mut self, level: f32) {
+        self.lpf_state[0] += self.lpf_alpha * (level - self.lpf_state[0]);
+        self.lpf_state[1] += self.lpf_alpha * (self.lpf_state[0] - self.lpf_state[1]);
+
+        self.sample_accum += self.lpf_state[1];
+        self.sample_ct += 1;
+
+        if self.sample_ct == SPEAKER_SAMPLE_RATIO {
+            let sample = self.sample_accum / SPEAKER_SAMPLE_RATIO as f32;
+            self.sample_accum = 0.0;
+            self.sample_ct = 0;
+
+            let out = sample - self.dc_last_in + SPEAKER_DC_BLOCK_POLE * self.dc_last_out;
+            self.dc_last_in = sample;
+            self.dc_last_out = out;
+
+            if let Some(sender) = &self.sender {
+                let _ = sender.send(out);
+            }
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -217,10 +277,25 @@ pub struct ProgrammableIntervalTimer {
     chan1_source: Option<usize>,
     last_output_state: [bool; 3],
     speaker: PitSpeaker,
+    recorder: Option<PcSpeakerRecorder>,
 }
 
 pub type Pit = ProgrammableIntervalTimer;
 
+/// Snapshot of the PIT's counting state. The speaker and its sample buffer are host resources
+/// and are not included.
+#[derive(Serialize, Deserialize)]
+pub struct PitSaveState {
+    pit_cycles: u64,
+    sys_tick_accumulator: u32,
+    sys_ticks_advance: u32,
+    cycle_accumulator: f64,
+    channels: Vec<Channel>,
+    defer_reload_flag: bool,
+    chan1_source: Option<usize>,
+    last_output_state: [bool; 3],
+}
+
 #[derive(Default, Clone)]
 pub struct PitStringState {
     pub c0_value: SyntaxToken,
@@ -308,6 +383,9 @@ impl Default for Channel {
             armed: false,
             read_state: ReadState::NoRead,
             count_is_latched: false,
+            status_latch: None,
+            null_count: false,
+            ce_loaded_value: 0,
             output: Updatable::Dirty(false, false),
             output_on_reload: false,
             reload_on_trigger: false,
@@ -337,11 +415,15 @@ impl Channel {
         self.counting_element.update(0);
 
         self.count_is_latched = false;
+        self.status_latch = None;
         self.armed = false;
         //self.ce_undefined = false;
 
+        // Writing a control word sets the null count flag until a count is loaded into the counting element.
+        self.null_count = true;
+
         // Default load mask
-        //self.load_mask = 0xFFFF;
+        self.load_mask = 0xFFFF;
 
         log::trace!(
             "PIT: Channel {} selected, channel_mode {:?}, rw mode {:?}, bcd: {:?}",
@@ -444,12 +526,58 @@ impl Channel {
     /// Reading from the timer always occurs directly from the output latch.
     /// In normal operation, the output latch updates synchronously with the count element.
     /// When latched, the output latch simply stops updating.
+    /// If the count is already latched and has not been read, the latch command is ignored.
     pub fn latch_count(&mut self) {
+        if self.count_is_latched {
+            return;
+        }
         self.output_latch.update(*self.counting_element);
         self.count_is_latched = true;
         self.dirty = true;
     }
 
+    /// Latch the channel status byte (8254 only, via the read-back command).
+    /// The next read from the channel returns the status byte before any latched count.
+    /// If status is already latched and has not been read, the latch command is ignored.
+    pub fn latch_status(&mut self) {
+        if self.status_latch.is_none() {
+            self.status_latch = Some(self.status_byte());
+            self.dirty = true;
+        }
+    }
+
+    /// Build the 8254 status byte for this channel:
+    /// bit 7: output pin, bit 6: null count, bits 5-4: rw mode, bits 3-1: mode, bit 0: bcd.
+    pub fn status_byte(&self) -> u8 {
+        let rw_bits = match *self.rw_mode {
+            RwMode::Lsb => 0b01,
+            RwMode::Msb => 0b10,
+            RwMode::LsbMsb => 0b11,
+        };
+        let mode_bits = match *self.mode {
+            ChannelMode::InterruptOnTerminalCount => 0,
+            ChannelMode::HardwareRetriggerableOneShot => 1,
+            ChannelMode::RateGenerator => 2,
+            ChannelMode::SquareWaveGenerator => 3,
+            ChannelMode::SoftwareTriggeredStrobe => 4,
+            ChannelMode::HardwareTriggeredStrobe => 5,
+        };
+        ((*self.output as u8) << 7)
+            | ((self.null_count as u8) << 6)
+            | (rw_bits << 4)
+            | (mode_bits << 1)
+            | self.bcd_mode as u8
+    }
+
+    /// Load the counting element from the reload value, applying the load mask.
+    /// The unmasked value is retained so that square wave mode can determine the parity of the count
+    /// actually in use, rather than a new count that has been written but not yet loaded.
+    fn load_counting_element(&mut self) {
+        self.counting_element.update(*self.reload_value & self.load_mask);
+        self.ce_loaded_value = *self.reload_value;
+        self.null_count = false;
+    }
+
     pub fn set_gate(&mut self, new_state: bool, bus: &mut BusInterface) {
         if (*self.gate == false) && (new_state == true) {
             // Rising edge of input gate.
@@ -466,6 +594,8 @@ impl Channel {
                         self.change_channel_state(ChannelState::WaitingForLoadCycle);
                     }
                     ChannelMode::SquareWaveGenerator => {
+                        // Reload restarts the cycle at the beginning of the high half-cycle.
+                        self.output_on_reload = true;
                         self.change_channel_state(ChannelState::WaitingForLoadCycle);
                     }
                     ChannelMode::SoftwareTriggeredStrobe => {
@@ -516,7 +646,12 @@ impl Channel {
     /// Reading always occurs from the value in the output latch.
     /// When the timer is not latched, the output latch updates synchronously with the
     /// counting element per tick. When latched, the output latch stops updating.
+    /// A latched status byte (8254 read-back command) is returned first, if present.
     pub fn read_byte(&mut self) -> u8 {
+        if let Some(status) = self.status_latch.take() {
+            return status;
+        }
+
         match self.read_state {
             ReadState::NoRead => {
                 // No read in progress
@@ -585,6 +720,7 @@ impl Channel {
     pub fn finalize_load(&mut self, defer_reload: bool) {
         // The count register is transferred to the counting element when a complete count is written.
         self.reload_value.update(*self.count_register);
+        self.null_count = true;
 
         let next_reload_state = match defer_reload {
             true => ChannelState::DeferLoadCycle,
@@ -718,8 +854,7 @@ impl Channel {
             || self.channel_state == ChannelState::Counting(ReloadFlag::ReloadNextCycle)
         {
             // Load the current reload value into the counting element, applying the load mask
-            //self.counting_element.update(*self.reload_value & self.load_mask);
-            self.counting_element.update(*self.reload_value);
+            self.load_counting_element();
 
             // Start counting.
             self.change_channel_state(ChannelState::Counting(ReloadFlag::Normal));
@@ -786,12 +921,14 @@ impl Channel {
                 ChannelMode::SquareWaveGenerator => {
                     // Gate controls counting.
                     if *self.gate {
-                        if (*self.count_register & 1) == 0 {
+                        // The parity of the count in the counting element determines behavior, not the
+                        // count register, as a new count does not take effect until the next half-cycle.
+                        if (self.ce_loaded_value & 1) == 0 {
                             // Even reload value. Count decrements by two and reloads on terminal count.
                             self.count2();
                             if *self.counting_element == 0 {
                                 self.change_output_state(!*self.output, bus); // Toggle output state
-                                self.counting_element.update(*self.reload_value);
+                                self.load_counting_element();
                                 // Reload counting element
                             }
                         }
@@ -810,7 +947,7 @@ impl Channel {
                                     else {
                                         // Output is low. Reload and update output immediately.
                                         self.change_output_state(!*self.output, bus); // Toggle output state
-                                        self.counting_element.update(*self.reload_value);
+                                        self.load_counting_element();
                                         // Reload counting element
                                     }
                                 }
@@ -835,7 +972,7 @@ impl Channel {
                                 if *self.counting_element == 0 {
                                     // Counting element is immediately reloaded and output toggled.
                                     self.change_output_state(!*self.output, bus); // Toggle output state
-                                    self.counting_element.update(*self.reload_value);
+                                    self.load_counting_element();
                                 }
                             }
                         }
@@ -869,6 +1006,35 @@ impl Channel {
     }
 }
 
+impl SaveState for ProgrammableIntervalTimer {
+    type State = PitSaveState;
+
+    fn save_state(&self) -> PitSaveState {
+        PitSaveState {
+            pit_cycles: self.pit_cycles,
+            sys_tick_accumulator: self.sys_tick_accumulator,
+            sys_ticks_advance: self.sys_ticks_advance,
+            cycle_accumulator: self.cycle_accumulator,
+            channels: self.channels.clone(),
+            defer_reload_flag: self.defer_reload_flag,
+            chan1_source: self.chan1_source,
+            last_output_state: self.last_output_state,
+        }
+    }
+
+    fn restore_state(&mut self, state: PitSaveState) {
+        self.pit_cycles = state.pit_cycles;
+        self.sys_tick_accumulator = state.sys_tick_accumulator;
+        self.sys_ticks_advance = state.sys_ticks_advance;
+        self.cycle_accumulator = state.cycle_accumulator;
+        self.channels = state.channels;
+        self.defer_reload_flag = state.defer_reload_flag;
+        self.chan1_source = state.chan1_source;
+        self.last_output_state = state.last_output_state;
+        self.speaker_buf.clear();
+    }
+}
+
 impl ProgrammableIntervalTimer {
     pub fn new(ptype: PitType, _crystal: f64, clock_divisor: u32, speaker_sender: Option<Sender<f32>>) -> Self {
         /*
@@ -896,12 +1062,8 @@ impl ProgrammableIntervalTimer {
             defer_reload_flag: false,
             chan1_source: None,
             last_output_state: [false; 3],
-            speaker: PitSpeaker {
-                enabled: speaker_sender.is_some(),
-                sample_accum: 0.0,
-                sample_ct: 0,
-                sender: speaker_sender,
-            },
+            speaker: PitSpeaker::new(speaker_sender),
+            recorder: None,
         }
     }
 
@@ -978,7 +1140,18 @@ impl ProgrammableIntervalTimer {
                     // Readback command not supported. Do nothing.
                 }
                 PitType::Model8254 => {
-                    // Do readback command here and return.
+                    // Bit 5 low latches count, bit 4 low latches status, bits 1-3 select channels 0-2.
+                    for (i, channel) in self.channels.iter_mut().enumerate() {
+                        if byte & (0x02 << i) == 0 {
+                            continue;
+                        }
+                        if byte & 0x20 == 0 {
+                            channel.latch_count();
+                        }
+                        if byte & 0x10 == 0 {
+                            channel.latch_status();
+                        }
+                    }
                 }
             }
             return;
@@ -1223,6 +1396,10 @@ impl ProgrammableIntervalTimer {
 
         //log::trace!("tick(): cycle: {} channel 1 count: {}", self.pit_cycles * 4 + 7, *self.channels[1].counting_element);
 
+        if let Some(recorder) = &mut self.recorder {
+            recorder.tick(self.pit_cycles, *self.channels[2].output && speaker_data);
+        }
+
         if self.speaker.sender.is_some() {
             // Process any samples that have accumulated in the buffer between calls to run().
             if let Some(s) = self.speaker_buf.pop_front() {
@@ -1266,17 +1443,19 @@ impl ProgrammableIntervalTimer {
         }*/
     }
 
+    /// Attach a PC speaker recorder, or detach the current one by passing None.
+    /// Returns the previously attached recorder, if any.
+    pub fn set_speaker_recorder(&mut self, recorder: Option<PcSpeakerRecorder>) -> Option<PcSpeakerRecorder> {
+        std::mem::replace(&mut self.recorder, recorder)
+    }
+
+    pub fn speaker_recorder_mut(&mut self) -> Option<&mut PcSpeakerRecorder> {
+        self.recorder.as_mut()
+    }
+
     #[inline]
     pub fn process_sample(&mut self, sample: f32) {
-        self.speaker.sample_accum += sample;
-        self.speaker.sample_ct += 1;
-
-        if self.speaker.sample_ct == SPEAKER_SAMPLE_RATIO {
-            let sample = self.speaker.sample_accum / SPEAKER_SAMPLE_RATIO as f32;
-            self.speaker.sample_accum = 0.0;
-            self.speaker.sample_ct = 0;
-            let _ = self.speaker.sender.as_mut().unwrap().send(sample);
-        }
+        self.speaker.process(sample);
     }
 
     // TODO: Remove this if no longer needed
@@ -1396,6 +1575,10 @@ impl ProgrammableIntervalTimer {
                     0,
                 ),
             );
+            channel_map.insert(
+                "Null Count:",
+                SyntaxToken::StateString(format!("{:?}", self.channels[i].null_count), false, 0),
+            );
             channel_map.insert(
                 "Gate Status:",
                 SyntaxToken::StateString(
@@ -1424,3 +1607,214 @@ impl ProgrammableIntervalTimer {
         state_vec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speaker_beep_frequency() {
+        let mut bus = BusInterface::default();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut pit = Pit::new(PitType::Model8253, 14.318_180, 12, Some(tx));
+        let nul_delta = DeviceRunTimeUnit::SystemTicks(0);
+
+        // A typical beep routine: channel 2, lsb/msb, mode 3, followed by a divisor for ~1000Hz.
+        let divisor: u16 = 1193;
+        pit.write_u8(PIT_COMMAND_REGISTER, 0xB6, Some(&mut bus), nul_delta, None);
+        pit.write_u8(PIT_CHANNEL_2_DATA_PORT, divisor as u8, Some(&mut bus), nul_delta, None);
+        pit.write_u8(
+            PIT_CHANNEL_2_DATA_PORT,
+            (divisor >> 8) as u8,
+            Some(&mut bus),
+            nul_delta,
+            None,
+        );
+
+        // With no PPI installed, speaker data is always enabled, so only the timer gate needs to be opened.
+        pit.channels[2].set_gate(true, &mut bus);
+
+        let ticks = (PIT_MHZ * 1_000_000.0 / 2.0) as u32;
+        for tick in 0..ticks {
+            pit.tick(&mut bus, tick, None);
+        }
+
+        let samples: Vec<f32> = rx.try_iter().collect();
+        assert_eq!(samples.len(), (ticks / SPEAKER_SAMPLE_RATIO) as usize);
+
+        // Skip the first 50ms to let the DC-blocking filter settle, then count rising zero crossings.
+        let skip = SPEAKER_SAMPLE_RATE as usize / 20;
+        let crossings = samples[skip..].windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        let seconds = (samples.len() - skip) as f64 / SPEAKER_SAMPLE_RATE as f64;
+        let measured = crossings as f64 / seconds;
+        let expected = PIT_MHZ * 1_000_000.0 / divisor as f64;
+        assert!(
+            (measured - expected).abs() / expected < 0.02,
+            "measured {:.1}Hz, expected {:.1}Hz",
+            measured,
+            expected
+        );
+
+        // The band-limited output should not exceed the input range.
+        assert!(samples.iter().all(|s| s.abs() <= 1.0));
+    }
+
+    /// Program channel 2 (which has no required device connections) in the given mode with an lsb/msb count.
+    fn load_channel(ptype: PitType, mode: ChannelMode, count: u16, bus: &mut BusInterface) -> Channel {
+        let mut channel = Channel::new(2, ptype);
+        channel.set_gate(true, bus);
+        channel.set_mode(mode, RwMode::LsbMsb, false, bus);
+        write_count(&mut channel, count, bus);
+        channel
+    }
+
+    fn write_count(channel: &mut Channel, count: u16, bus: &mut BusInterface) {
+        channel.write_byte(count as u8, false, bus);
+        channel.write_byte((count >> 8) as u8, false, bus);
+    }
+
+    /// Tick the channel and return the output state after each tick.
+    fn run(channel: &mut Channel, ticks: usize, bus: &mut BusInterface) -> Vec<bool> {
+        (0..ticks)
+            .map(|_| {
+                channel.tick(bus, None);
+                *channel.output
+            })
+            .collect()
+    }
+
+    const H: bool = true;
+    const L: bool = false;
+
+    #[test]
+    fn mode2_output_and_reload() {
+        let mut bus = BusInterface::default();
+        let mut ch = load_channel(PitType::Model8253, ChannelMode::RateGenerator, 4, &mut bus);
+
+        // Load cycle, then output goes low for one clock when the count reaches 1.
+        assert_eq!(run(&mut ch, 8, &mut bus), vec![H, H, H, L, H, H, H, L]);
+
+        // A new count written mid-cycle takes effect only at the next pulse.
+        let mut ch = load_channel(PitType::Model8253, ChannelMode::RateGenerator, 4, &mut bus);
+        run(&mut ch, 2, &mut bus);
+        write_count(&mut ch, 6, &mut bus);
+        assert_eq!(run(&mut ch, 8, &mut bus), vec![H, L, H, H, H, H, H, L]);
+    }
+
+    #[test]
+    fn mode2_gate() {
+        let mut bus = BusInterface::default();
+        let mut ch = load_channel(PitType::Model8253, ChannelMode::RateGenerator, 4, &mut bus);
+        run(&mut ch, 3, &mut bus);
+        assert_eq!(*ch.counting_element, 2);
+
+        // Gate low suspends counting and forces output high.
+        ch.set_gate(false, &mut bus);
+        assert_eq!(run(&mut ch, 4, &mut bus), vec![H, H, H, H]);
+        assert_eq!(*ch.counting_element, 2);
+
+        // Rising gate reloads the counter on the next clock.
+        ch.set_gate(true, &mut bus);
+        run(&mut ch, 1, &mut bus);
+        assert_eq!(*ch.counting_element, 4);
+        assert_eq!(run(&mut ch, 4, &mut bus), vec![H, H, L, H]);
+    }
+
+    #[test]
+    fn mode3_even_and_mid_count_reload() {
+        let mut bus = BusInterface::default();
+        let mut ch = load_channel(PitType::Model8253, ChannelMode::SquareWaveGenerator, 4, &mut bus);
+        assert_eq!(run(&mut ch, 9, &mut bus), vec![H, H, L, L, H, H, L, L, H]);
+
+        // A new count takes effect at the next half-cycle boundary.
+        let mut ch = load_channel(PitType::Model8253, ChannelMode::SquareWaveGenerator, 8, &mut bus);
+        run(&mut ch, 2, &mut bus);
+        write_count(&mut ch, 4, &mut bus);
+        assert_eq!(run(&mut ch, 7, &mut bus), vec![H, H, L, L, H, H, L]);
+    }
+
+    #[test]
+    fn mode3_8254_odd_count() {
+        let mut bus = BusInterface::default();
+
+        // Odd counts are high for (N+1)/2 clocks and low for (N-1)/2 clocks.
+        let mut ch = load_channel(PitType::Model8254, ChannelMode::SquareWaveGenerator, 5, &mut bus);
+        assert_eq!(run(&mut ch, 9, &mut bus), vec![H, H, H, L, L, H, H, H, L]);
+
+        // An odd count written during an even half-cycle does not change the current half-cycle.
+        let mut ch = load_channel(PitType::Model8254, ChannelMode::SquareWaveGenerator, 8, &mut bus);
+        run(&mut ch, 2, &mut bus);
+        write_count(&mut ch, 5, &mut bus);
+        assert_eq!(run(&mut ch, 10, &mut bus), vec![H, H, L, L, H, H, H, L, L, H]);
+    }
+
+    #[test]
+    fn mode3_gate() {
+        let mut bus = BusInterface::default();
+        let mut ch = load_channel(PitType::Model8254, ChannelMode::SquareWaveGenerator, 5, &mut bus);
+        // Stop during the low half-cycle.
+        run(&mut ch, 4, &mut bus);
+        assert!(!*ch.output);
+
+        ch.set_gate(false, &mut bus);
+        assert_eq!(run(&mut ch, 3, &mut bus), vec![H, H, H]);
+
+        // Rising gate reloads and restarts with the high half-cycle.
+        ch.set_gate(true, &mut bus);
+        assert_eq!(run(&mut ch, 6, &mut bus), vec![H, H, H, L, L, H]);
+    }
+
+    #[test]
+    fn latch_and_readback() {
+        let mut bus = BusInterface::default();
+        let mut pit = Pit::new(PitType::Model8254, 14.318_180, 12, None);
+        let nul_delta = DeviceRunTimeUnit::SystemTicks(0);
+
+        // Channel 2, lsb/msb, mode 3, binary.
+        pit.write_u8(PIT_COMMAND_REGISTER, 0xB6, Some(&mut bus), nul_delta, None);
+        pit.write_u8(PIT_CHANNEL_2_DATA_PORT, 0x34, Some(&mut bus), nul_delta, None);
+        pit.write_u8(PIT_CHANNEL_2_DATA_PORT, 0x12, Some(&mut bus), nul_delta, None);
+        pit.channels[2].set_gate(true, &mut bus);
+
+        // Count not yet loaded into the counting element: null count is set.
+        pit.write_u8(PIT_COMMAND_REGISTER, 0xE8, Some(&mut bus), nul_delta, None);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0xF6);
+
+        // Writes through the IO port defer the load by one clock.
+        pit.channels[2].tick(&mut bus, None);
+        pit.channels[2].tick(&mut bus, None);
+
+        // Read-back of count and status returns status first, then the latched count.
+        pit.write_u8(PIT_COMMAND_REGISTER, 0xC8, Some(&mut bus), nul_delta, None);
+        pit.channels[2].tick(&mut bus, None);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0xB6);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0x34);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0x12);
+
+        // A second latch command before the latched count is read is ignored.
+        pit.write_u8(PIT_COMMAND_REGISTER, 0x80, Some(&mut bus), nul_delta, None);
+        pit.channels[2].tick(&mut bus, None);
+        pit.write_u8(PIT_COMMAND_REGISTER, 0x80, Some(&mut bus), nul_delta, None);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0x32);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0x12);
+
+        // Unlatched reads follow the counting element.
+        pit.channels[2].tick(&mut bus, None);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0x2E);
+        assert_eq!(pit.read_u8(PIT_CHANNEL_2_DATA_PORT, nul_delta), 0x12);
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let mut bus = BusInterface::default();
+        let mut pit = Pit::new(PitType::Model8254, 14.318_180, 12, None);
+        pit.channels[2] = load_channel(PitType::Model8254, ChannelMode::SquareWaveGenerator, 7, &mut bus);
+        run(&mut pit.channels[2], 3, &mut bus);
+
+        let json = serde_json::to_string(&pit.save_state()).unwrap();
+        let expected = run(&mut pit.channels[2], 16, &mut bus);
+
+        pit.restore_state(serde_json::from_str(&json).unwrap());
+        assert_eq!(run(&mut pit.channels[2], 16, &mut bus), expected);
+    }
+}
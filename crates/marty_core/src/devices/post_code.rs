@@ -0,0 +1,147 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    devices::post_code.rs
+
+    Implement a monitor for BIOS POST (Power-On Self Test) progress codes.
+
+    Many BIOSes write a checkpoint byte to a diagnostic port, usually 0x80,
+    as POST progresses. A POST card on a real machine displays the last
+    code written. The monitor keeps a log of every code written along with
+    the cycle it was written on, so that the point at which a BIOS hangs
+    can be determined.
+
+*/
+
+use std::collections::VecDeque;
+
+use crate::{
+    bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
+    cpu_common::LogicAnalyzer,
+};
+
+pub const POST_CODE_DEFAULT_PORT: u16 = 0x80;
+pub const POST_CODE_LOG_MAX: usize = 4096;
+/// Index into POST_CODE_TABLES of the "none" table, used unless a machine selects another.
+pub const DEFAULT_POST_CODE_TABLE: usize = 0;
+
+/// A table of known POST code meanings for a particular BIOS family.
+pub struct PostCodeTable {
+    pub id:    &'static str,
+    pub name:  &'static str,
+    pub codes: &'static [(u8, &'static str)],
+}
+
+/// Return the index into POST_CODE_TABLES of the table with the specified id, ignoring case.
+pub fn post_code_table_index(id: &str) -> Option<usize> {
+    POST_CODE_TABLES
+        .iter()
+        .position(|table| table.id.eq_ignore_ascii_case(id))
+}
+
+impl PostCodeTable {
+    pub fn lookup(&self, code: u8) -> Option<&'static str> {
+        self.codes.iter().find(|(c, _)| *c == code).map(|(_, desc)| *desc)
+    }
+}
+
+/// Known POST code tables. A table is only added here when its descriptions can be checked against a published
+/// source, such as a BIOS listing or technical reference, which should be cited alongside it.
+pub const POST_CODE_TABLES: &[PostCodeTable] = &[
+    // The default table describes nothing, so codes are shown as written.
+    PostCodeTable {
+        id:    "none",
+        name:  "None",
+        codes: &[],
+    },
+];
+
+pub struct PostCodeMonitor {
+    ports: Vec<u16>,
+    cycle_num: u64,
+    log: VecDeque<(u64, u8)>,
+}
+
+impl PostCodeMonitor {
+    pub fn new(ports: Vec<u16>) -> Self {
+        Self {
+            ports,
+            cycle_num: 0,
+            log: VecDeque::with_capacity(POST_CODE_LOG_MAX),
+        }
+    }
+
+    /// Advance the monitor's cycle counter by the specified number of CPU cycles.
+    pub fn run(&mut self, cycles: u64) {
+        self.cycle_num += cycles;
+    }
+
+    /// Return the log of (cycle_num, code) tuples in the order they were written.
+    pub fn log(&self) -> &VecDeque<(u64, u8)> {
+        &self.log
+    }
+
+    pub fn last_code(&self) -> Option<u8> {
+        self.log.back().map(|(_, code)| *code)
+    }
+
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    /// Record a POST code written `cycles` into the current instruction.
+    pub fn write_code(&mut self, code: u8, cycles: u32) {
+        if self.log.len() >= POST_CODE_LOG_MAX {
+            self.log.pop_front();
+        }
+        self.log.push_back((self.cycle_num + cycles as u64, code));
+    }
+}
+
+impl IoDevice for PostCodeMonitor {
+    fn read_u8(&mut self, _port: u16, _delta: DeviceRunTimeUnit) -> u8 {
+        // A POST card is write-only. Return the last code written, as some diagnostic ports latch it.
+        self.last_code().unwrap_or(0xFF)
+    }
+
+    fn write_u8(
+        &mut self,
+        _port: u16,
+        data: u8,
+        _bus: Option<&mut BusInterface>,
+        _delta: DeviceRunTimeUnit,
+        _analyzer: Option<&mut LogicAnalyzer>,
+    ) {
+        self.write_code(data, 0);
+    }
+
+    fn port_list(&self) -> Vec<(String, u16)> {
+        self.ports
+            .iter()
+            .map(|port| (format!("POST Code Port {:02X}h", port), *port))
+            .collect()
+    }
+}
@@ -35,6 +35,7 @@
 #![allow(dead_code)]
 
 use modular_bitfield::{bitfield, BitfieldSpecifier};
+use serde::{Deserialize, Serialize};
 use std::{cell::Cell, collections::BTreeMap};
 
 use crate::{
@@ -43,11 +44,12 @@ use crate::{
     device_traits::videocard::VideoType,
     devices::pic,
     machine_types::MachineType,
+    savestate::SaveState,
     syntax_token::SyntaxToken,
     updatable::Updatable,
 };
 
-#[derive(Debug, Default, BitfieldSpecifier)]
+#[derive(Copy, Clone, Debug, Default, BitfieldSpecifier, Serialize, Deserialize)]
 pub enum PpiModeA {
     #[default]
     Mode0Io,
@@ -56,14 +58,14 @@ pub enum PpiModeA {
     Mode2BiDirectional2,
 }
 
-#[derive(Debug, Default, BitfieldSpecifier)]
+#[derive(Copy, Clone, Debug, Default, BitfieldSpecifier, Serialize, Deserialize)]
 pub enum PpiModeB {
     #[default]
     Mode0Io,
     Mode1StrobedIo,
 }
 
-#[derive(Debug, Default, BitfieldSpecifier)]
+#[derive(Copy, Clone, Debug, Default, BitfieldSpecifier, Serialize, Deserialize)]
 pub enum IoMode {
     #[default]
     Output,
@@ -204,12 +206,18 @@ pub const PCJR_KB_BAUD: f64 = 2272.0;
 pub const PCJR_US_PER_BIT: f64 = 1_000_000.0 / PCJR_KB_BAUD;
 pub const PCJR_US_PER_HALFBIT: f64 = PCJR_US_PER_BIT / 2.0;
 
-#[derive(Debug)]
+// The Model F keyboard clocks data at roughly 10kHz. A scancode is framed by a start bit followed
+// by eight data bits, LSB first. The 74LS322 shift register on the motherboard asserts IRQ1 once
+// the start bit has been clocked all the way through to QH.
+pub const XT_KB_US_PER_BIT: f64 = 95.0;
+pub const XT_KB_FRAME_BITS: u8 = 9;
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum PortAMode {
     SwitchBlock1,
     KeyboardByte,
 }
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum PortCMode {
     Switch2OneToFour,
     Switch2Five,
@@ -218,7 +226,7 @@ pub enum PortCMode {
     Tandy1000,
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum KbSerializeState {
     Idle,
     StartBit,
@@ -227,6 +235,7 @@ pub enum KbSerializeState {
     StopBit,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct KbSerializer {
     us_accum: f64,
     rate: f64,
@@ -335,6 +344,70 @@ impl KbSerializer {
     }
 }
 
+/// Model of the PC/XT keyboard shift register (74LS322) and the keyboard serial clock driving it.
+/// Bits are clocked in one at a time; once a full frame has been received the register is 'full',
+/// which holds IRQ1 high and inhibits the keyboard until the register is cleared via port B.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct XtKbShiftRegister {
+    us_accum: f64,
+    data: Option<u8>,
+    bits_shifted: u8,
+    sr: u8,
+    full: bool,
+}
+
+impl XtKbShiftRegister {
+    /// Begin clocking a scancode into the shift register.
+    pub fn load(&mut self, data: u8) {
+        self.us_accum = 0.0;
+        self.data = Some(data);
+        self.bits_shifted = 0;
+        self.sr = 0;
+        self.full = false;
+    }
+
+    /// Hold the shift register in reset, discarding any partially received frame.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Return true if a frame is currently being clocked in.
+    pub fn is_busy(&self) -> bool {
+        self.data.is_some()
+    }
+
+    /// Return true if a complete scancode has been received and not yet cleared.
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// Advance the keyboard clock by the specified number of microseconds. Returns the received
+    /// scancode on the tick that completes a frame.
+    pub fn tick(&mut self, us: f64) -> Option<u8> {
+        let data = self.data?;
+
+        self.us_accum += us;
+        while self.us_accum >= XT_KB_US_PER_BIT {
+            self.us_accum -= XT_KB_US_PER_BIT;
+
+            // The start bit is shifted in first and is not part of the data byte.
+            if self.bits_shifted > 0 {
+                let bit = (data >> (self.bits_shifted - 1)) & 0x01;
+                self.sr = (self.sr >> 1) | (bit << 7);
+            }
+            self.bits_shifted += 1;
+
+            if self.bits_shifted == XT_KB_FRAME_BITS {
+                self.data = None;
+                self.full = true;
+                self.us_accum = 0.0;
+                return Some(self.sr);
+            }
+        }
+        None
+    }
+}
+
 pub struct Ppi {
     machine_type: MachineType,
     control_word: PpiControlWord,
@@ -366,6 +439,7 @@ pub struct Ppi {
     jr_kb_in: bool,
     nmi_latch_in: bool,
     kb_serializer: KbSerializer,
+    kb_shift_register: XtKbShiftRegister,
     num_floppies: u32,
 }
 
@@ -402,11 +476,114 @@ impl Default for Ppi {
             jr_kb_in: false,
             nmi_latch_in: false,
             kb_serializer: KbSerializer::default(),
+            kb_shift_register: XtKbShiftRegister::default(),
             num_floppies: 0,
         }
     }
 }
 
+/// Snapshot of the PPI's port and keyboard interface state. The DIP switches are derived from the
+/// machine configuration and are not included.
+#[derive(Serialize, Deserialize)]
+pub struct PpiSaveState {
+    control_word: u8,
+    group_a_mode: PpiModeA,
+    group_b_mode: PpiModeB,
+    port_a_mode: PortAMode,
+    port_c_mode: PortCMode,
+    port_a_iomode: IoMode,
+    port_b_iomode: IoMode,
+    port_cu_iomode: IoMode,
+    port_cl_iomode: IoMode,
+    kb_clock_low: bool,
+    kb_counting_low: bool,
+    kb_low_count: f64,
+    kb_do_reset: bool,
+    kb_count_until_reset_byte: f64,
+    kb_resets_counter: Updatable<u32>,
+    port_a_byte: u8,
+    port_b_byte: u8,
+    kb_byte: Updatable<u8>,
+    kb_byte_last: Updatable<u8>,
+    keyboard_clear_scheduled: bool,
+    ksr_cleared: bool,
+    kb_enabled: bool,
+    timer_in: bool,
+    speaker_in: bool,
+    jr_kb_in: bool,
+    nmi_latch_in: bool,
+    kb_serializer: KbSerializer,
+    kb_shift_register: XtKbShiftRegister,
+}
+
+impl SaveState for Ppi {
+    type State = PpiSaveState;
+
+    fn save_state(&self) -> PpiSaveState {
+        PpiSaveState {
+            control_word: self.control_word.into_bytes()[0],
+            group_a_mode: self.group_a_mode,
+            group_b_mode: self.group_b_mode,
+            port_a_mode: self.port_a_mode,
+            port_c_mode: self.port_c_mode,
+            port_a_iomode: self.port_a_iomode,
+            port_b_iomode: self.port_b_iomode,
+            port_cu_iomode: self.port_cu_iomode,
+            port_cl_iomode: self.port_cl_iomode,
+            kb_clock_low: self.kb_clock_low,
+            kb_counting_low: self.kb_counting_low,
+            kb_low_count: self.kb_low_count,
+            kb_do_reset: self.kb_do_reset,
+            kb_count_until_reset_byte: self.kb_count_until_reset_byte,
+            kb_resets_counter: self.kb_resets_counter.clone(),
+            port_a_byte: self.port_a_byte,
+            port_b_byte: self.port_b_byte,
+            kb_byte: self.kb_byte.clone(),
+            kb_byte_last: self.kb_byte_last.clone(),
+            keyboard_clear_scheduled: self.keyboard_clear_scheduled,
+            ksr_cleared: self.ksr_cleared,
+            kb_enabled: self.kb_enabled,
+            timer_in: self.timer_in,
+            speaker_in: self.speaker_in,
+            jr_kb_in: self.jr_kb_in,
+            nmi_latch_in: self.nmi_latch_in,
+            kb_serializer: self.kb_serializer.clone(),
+            kb_shift_register: self.kb_shift_register.clone(),
+        }
+    }
+
+    fn restore_state(&mut self, state: PpiSaveState) {
+        self.control_word = PpiControlWord::from_bytes([state.control_word]);
+        self.group_a_mode = state.group_a_mode;
+        self.group_b_mode = state.group_b_mode;
+        self.port_a_mode = state.port_a_mode;
+        self.port_c_mode = state.port_c_mode;
+        self.port_a_iomode = state.port_a_iomode;
+        self.port_b_iomode = state.port_b_iomode;
+        self.port_cu_iomode = state.port_cu_iomode;
+        self.port_cl_iomode = state.port_cl_iomode;
+        self.kb_clock_low = state.kb_clock_low;
+        self.kb_counting_low = state.kb_counting_low;
+        self.kb_low_count = state.kb_low_count;
+        self.kb_do_reset = state.kb_do_reset;
+        self.kb_count_until_reset_byte = state.kb_count_until_reset_byte;
+        self.kb_resets_counter = state.kb_resets_counter;
+        self.port_a_byte = state.port_a_byte;
+        self.port_b_byte = state.port_b_byte;
+        self.kb_byte = state.kb_byte;
+        self.kb_byte_last = state.kb_byte_last;
+        self.keyboard_clear_scheduled = state.keyboard_clear_scheduled;
+        self.ksr_cleared = state.ksr_cleared;
+        self.kb_enabled = state.kb_enabled;
+        self.timer_in = state.timer_in;
+        self.speaker_in = state.speaker_in;
+        self.jr_kb_in = state.jr_kb_in;
+        self.nmi_latch_in = state.nmi_latch_in;
+        self.kb_serializer = state.kb_serializer;
+        self.kb_shift_register = state.kb_shift_register;
+    }
+}
+
 // This structure implements an interface for wires connected to the PPI from
 // other components. Components connected to the PPI will receive a reference
 // to this structure on creation, and can read or modify the wire state via
@@ -779,10 +956,12 @@ impl Ppi {
                 }
             }
             MachineType::Ibm5150v64K | MachineType::Ibm5150v256K | MachineType::Ibm5160 => {
-                // Only send a scancode if the keyboard is not actively being reset.
-                if self.kb_enabled() && self.ksr_cleared {
+                // Only send a scancode if the keyboard is not actively being reset. The byte is
+                // clocked into the shift register serially and becomes visible at port A (and
+                // raises IRQ1) once the frame completes in run().
+                if self.kb_ready() {
                     self.ksr_cleared = false;
-                    self.kb_byte.update(byte);
+                    self.kb_shift_register.load(byte);
                 }
             }
 
@@ -799,6 +978,17 @@ impl Ppi {
         }
     }
 
+    /// Return whether the keyboard is able to transmit a new scancode. On the PC/XT, the keyboard
+    /// cannot clock out data while the shift register is full, busy, or held clear by port B.
+    pub fn kb_ready(&self) -> bool {
+        match self.machine_type {
+            MachineType::Ibm5150v64K | MachineType::Ibm5150v256K | MachineType::Ibm5160 => {
+                self.kb_enabled() && self.ksr_cleared && !self.kb_shift_register.is_busy()
+            }
+            _ => self.ksr_cleared,
+        }
+    }
+
     pub fn calc_port_c_value(&self) -> u8 {
         let cassette_bit = if self.port_b_byte & PORTB_CASSETTE_MOTOR_OFF != 0 {
             // Cassette motor is off, so we are in loopback mode.
@@ -1046,12 +1236,23 @@ impl Ppi {
                 if self.keyboard_clear_scheduled {
                     self.keyboard_clear_scheduled = false;
                     self.ksr_cleared = true;
+                    self.kb_shift_register.clear();
                     self.kb_byte_last.update(*self.kb_byte);
                     self.kb_byte.update(0);
                     pic.clear_interrupt(1);
                     //log::trace!("PPI: Clearing keyboard");
                 }
 
+                // Clock the keyboard shift register. When a full frame has been received, latch the
+                // scancode and raise IRQ1. IRQ1 remains high until the BIOS acknowledges the byte by
+                // pulsing PB7.
+                if let Some(byte) = self.kb_shift_register.tick(us) {
+                    self.kb_byte.update(byte);
+                    if self.kb_enabled {
+                        pic.request_interrupt(1);
+                    }
+                }
+
                 // Keyboard should send a 'aa' byte when clock line is held low (for how long?)
                 // BIOS waits 20ms.
                 // Clock line must go high again
@@ -1069,15 +1270,47 @@ impl Ppi {
                         self.kb_count_until_reset_byte = 0.0;
                         self.kb_resets_counter.update((*self.kb_resets_counter).wrapping_add(1));
 
+                        // The keyboard responds to a reset by transmitting its self-test byte.
                         log::trace!("PPI: Sending keyboard reset byte");
-                        self.kb_byte.update(0xAA);
-
-                        if self.kb_enabled {
-                            pic.request_interrupt(1);
-                        }
+                        self.ksr_cleared = false;
+                        self.kb_shift_register.load(0xAA);
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xt_keypress_raises_irq1() {
+        let mut ppi = Ppi::new(MachineType::Ibm5160, 0xA0000, false, vec![VideoType::CGA], 1);
+        let mut pic = pic::Pic::new();
+
+        // Keyboard clock high, PB7 low: keyboard enabled.
+        ppi.handle_portb_write(PORTB_PULL_KB_LOW);
+        ppi.send_keyboard(0x1E);
+
+        // The scancode should not appear until the full frame has been clocked in.
+        ppi.run(&mut pic, XT_KB_US_PER_BIT);
+        assert_eq!(ppi.read_u8(PPI_PORT_A, DeviceRunTimeUnit::Microseconds(0.0)), 0);
+        assert!(!ppi.kb_ready());
+
+        for _ in 0..XT_KB_FRAME_BITS {
+            ppi.run(&mut pic, XT_KB_US_PER_BIT);
+        }
+        assert_eq!(ppi.read_u8(PPI_PORT_A, DeviceRunTimeUnit::Microseconds(0.0)), 0x1E);
+        assert_eq!(pic.get_string_state().irr, "00000010");
+
+        // Acknowledge the byte by pulsing PB7.
+        ppi.handle_portb_write(PORTB_PULL_KB_LOW | PORTB_KB_CLEAR);
+        ppi.run(&mut pic, 1.0);
+        ppi.handle_portb_write(PORTB_PULL_KB_LOW);
+        assert_eq!(ppi.read_u8(PPI_PORT_A, DeviceRunTimeUnit::Microseconds(0.0)), 0);
+        assert_eq!(pic.get_string_state().irr, "00000000");
+        assert!(ppi.kb_ready());
+    }
+}
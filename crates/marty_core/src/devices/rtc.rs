@@ -0,0 +1,980 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    devices::rtc.rs
+
+
+    Implementation of the Motorola MC146818 Real Time Clock, as used in the
+    IBM AT and compatibles at ports 0x70 (index) and 0x71 (data).
+
+    The MC146818 maintains the time and date, an alarm, and a programmable
+    periodic interrupt, delivered on IRQ8 unless configured otherwise. The remainder of its battery-backed
+    RAM holds the BIOS configuration ("CMOS"), protected by a checksum the
+    BIOS validates at POST. We provide 128 bytes, as the later compatible
+    parts used by most AT clones do.
+
+    The clock normally advances with emulated time. It can instead follow the
+    host's wall clock, so that it keeps correct time when the emulator runs
+    faster or slower than the real machine. Either way it stops while the
+    machine is paused.
+
+    The time is kept internally in binary and encoded to BCD or 12-hour
+    format on read as selected by Status Register B. When the guest sets the
+    time, the difference from the time the clock would otherwise show is
+    kept as the clock offset, so it can be persisted along with CMOS RAM and
+    reapplied to the host's time on the next run.
+*/
+
+use crate::{
+    bus::{BusInterface, DeviceRunTimeUnit, IoDevice, NO_IO_BYTE},
+    cpu_common::LogicAnalyzer,
+    device_traits::realtimeclock::RealTimeClock,
+    devices::pic::Pic,
+    machine_types::FloppyDriveType,
+    savestate::SaveState,
+};
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+pub const RTC_DEFAULT_IO: u16 = 0x70;
+/// IRQ8 is the first input of the secondary PIC.
+pub const RTC_DEFAULT_IRQ: u8 = 8;
+/// The time the RTC is set to in deterministic mode, if no fixed time is configured: 1990-01-01 00:00:00.
+pub const RTC_DETERMINISTIC_TIME: i64 = 631152000;
+
+pub const CMOS_SIZE: usize = 128;
+pub const CMOS_CHECKSUM_START: usize = 0x10;
+pub const CMOS_CHECKSUM_END: usize = 0x2D;
+pub const CMOS_CHECKSUM_HI: usize = 0x2E;
+pub const CMOS_CHECKSUM_LO: usize = 0x2F;
+pub const CMOS_CENTURY: usize = 0x32;
+/// Drive A's type in the high nibble, drive B's in the low nibble.
+pub const CMOS_FLOPPY_TYPES: usize = 0x10;
+pub const CMOS_EQUIPMENT: usize = 0x14;
+
+/// Equipment byte: floppy drives are installed.
+const EQUIPMENT_FLOPPY: u8 = 0x01;
+/// Equipment byte: the number of floppy drives, less one.
+const EQUIPMENT_FLOPPY_CT_MASK: u8 = 0xC0;
+const EQUIPMENT_FLOPPY_CT_SHIFT: u8 = 6;
+
+const REG_SECONDS: usize = 0x00;
+const REG_SECONDS_ALARM: usize = 0x01;
+const REG_MINUTES: usize = 0x02;
+const REG_MINUTES_ALARM: usize = 0x03;
+const REG_HOURS: usize = 0x04;
+const REG_HOURS_ALARM: usize = 0x05;
+const REG_DAY_OF_WEEK: usize = 0x06;
+const REG_DAY_OF_MONTH: usize = 0x07;
+const REG_MONTH: usize = 0x08;
+const REG_YEAR: usize = 0x09;
+const REG_A: usize = 0x0A;
+const REG_B: usize = 0x0B;
+const REG_C: usize = 0x0C;
+const REG_D: usize = 0x0D;
+
+const A_UIP: u8 = 0x80;
+const A_DV_MASK: u8 = 0x70;
+const A_DV_32K: u8 = 0x20;
+const A_RS_MASK: u8 = 0x0F;
+const A_DEFAULT: u8 = 0x26;
+
+const B_SET: u8 = 0x80;
+const B_PIE: u8 = 0x40;
+const B_AIE: u8 = 0x20;
+const B_UIE: u8 = 0x10;
+const B_BINARY: u8 = 0x04;
+const B_24H: u8 = 0x02;
+const B_INT_MASK: u8 = B_PIE | B_AIE | B_UIE;
+
+const C_IRQF: u8 = 0x80;
+const C_PF: u8 = 0x40;
+const C_AF: u8 = 0x20;
+const C_UF: u8 = 0x10;
+
+const D_VRT: u8 = 0x80;
+
+/// An alarm register value with both high bits set matches any value.
+const ALARM_DONT_CARE: u8 = 0xC0;
+/// The update-in-progress flag is raised this long before each update cycle.
+const UIP_LEAD_US: f64 = 244.0;
+const TIME_BASE_HZ: f64 = 32768.0;
+/// The most host time credited to the clock by a single run() call. Longer gaps mean the machine was
+/// stopped, in the debugger for instance, and the clock should not leap forward.
+const HOST_CLOCK_MAX_STEP_US: f64 = 250_000.0;
+
+/// Return the code the AT BIOS uses for a floppy drive type in CMOS RAM.
+pub fn cmos_floppy_type(drive_type: FloppyDriveType) -> u8 {
+    match drive_type {
+        FloppyDriveType::Floppy360K => 1,
+        FloppyDriveType::Floppy12M => 2,
+        FloppyDriveType::Floppy720K => 3,
+        FloppyDriveType::Floppy144M => 4,
+    }
+}
+
+/// A calendar date and time, in binary.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RtcDateTime {
+    pub year:   u16,
+    pub month:  u8,
+    pub day:    u8,
+    pub hour:   u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl RtcDateTime {
+    /// Convert a calendar date and time to seconds since 1970-01-01 00:00:00.
+    pub fn to_unix(&self) -> i64 {
+        // Howard Hinnant's days_from_civil algorithm.
+        let month = self.month as i64;
+        let y = self.year as i64 - if month <= 2 { 1 } else { 0 };
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+
+    /// Convert seconds since 1970-01-01 00:00:00 to a calendar date and time.
+    pub fn from_unix(secs: i64) -> Self {
+        // Howard Hinnant's civil_from_days algorithm.
+        let days = secs.div_euclid(86400);
+        let rem = secs.rem_euclid(86400);
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year:   year as u16,
+            month:  month as u8,
+            day:    day as u8,
+            hour:   (rem / 3600) as u8,
+            minute: (rem / 60 % 60) as u8,
+            second: (rem % 60) as u8,
+        }
+    }
+
+    /// Return the day of the week, 1 being Sunday, as the MC146818 counts it.
+    pub fn day_of_week(&self) -> u8 {
+        // Tomohiko Sakamoto's algorithm.
+        const T: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let month = self.month.clamp(1, 12) as usize;
+        let y = if month < 3 { self.year - 1 } else { self.year };
+        ((y + y / 4 - y / 100 + y / 400 + T[month - 1] + self.day as u16) % 7) as u8 + 1
+    }
+
+    fn days_in_month(&self) -> u8 {
+        match self.month {
+            4 | 6 | 9 | 11 => 30,
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            _ => 31,
+        }
+    }
+
+    /// Advance by one second. Returns true if the date changed.
+    pub(crate) fn tick(&mut self) -> bool {
+        self.second += 1;
+        if self.second < 60 {
+            return false;
+        }
+        self.second = 0;
+        self.minute += 1;
+        if self.minute < 60 {
+            return false;
+        }
+        self.minute = 0;
+        self.hour += 1;
+        if self.hour < 24 {
+            return false;
+        }
+        self.hour = 0;
+        self.day += 1;
+        if self.day > self.days_in_month() {
+            self.day = 1;
+            self.month += 1;
+            if self.month > 12 {
+                self.month = 1;
+                self.year += 1;
+            }
+        }
+        true
+    }
+}
+
+pub(crate) fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+pub(crate) fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Tracks the host's wall clock for a real time clock that keeps host time instead of emulated time.
+#[derive(Default)]
+pub(crate) struct HostClock {
+    enabled:   bool,
+    last_tick: Option<Instant>,
+}
+
+impl HostClock {
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.last_tick = None;
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Stop the clock until the next call to elapsed_us(), so that time spent paused is not counted.
+    pub(crate) fn freeze(&mut self) {
+        self.last_tick = None;
+    }
+
+    /// Return the microseconds elapsed since the last call: of host time if enabled, or else the
+    /// emulated time given. Host time is 0 on the first call after the clock was frozen.
+    pub(crate) fn elapsed_us(&mut self, emulated_us: f64) -> f64 {
+        if !self.enabled {
+            return emulated_us;
+        }
+        let now = Instant::now();
+        let elapsed = self
+            .last_tick
+            .map(|last| now.duration_since(last).as_secs_f64() * 1_000_000.0)
+            .unwrap_or(0.0);
+        self.last_tick = Some(now);
+        elapsed.min(HOST_CLOCK_MAX_STEP_US)
+    }
+}
+
+/// Calculate the checksum of the CMOS configuration bytes, as the AT BIOS does.
+pub fn cmos_checksum(cmos: &[u8; CMOS_SIZE]) -> u16 {
+    cmos[CMOS_CHECKSUM_START..=CMOS_CHECKSUM_END]
+        .iter()
+        .map(|b| *b as u16)
+        .sum()
+}
+
+pub struct Rtc {
+    io_base: u16,
+    irq: u8,
+    index: usize,
+    time: RtcDateTime,
+    day_of_week: u8,
+    cmos: [u8; CMOS_SIZE],
+    cmos_dirty: bool,
+    second_accum: f64,
+    periodic_accum: f64,
+    irq_asserted: bool,
+    irq_acknowledged: bool,
+    host_clock: HostClock,
+    clock_offset: i64,
+    /// The floppy drives installed in the machine, recorded in CMOS RAM.
+    floppy_drives: Vec<FloppyDriveType>,
+}
+
+/// Snapshot of the clock's time, registers and CMOS RAM.
+#[derive(Serialize, Deserialize)]
+pub struct RtcSaveState {
+    index: usize,
+    time: RtcDateTime,
+    day_of_week: u8,
+    cmos: Vec<u8>,
+    second_accum: f64,
+    periodic_accum: f64,
+    irq_asserted: bool,
+    irq_acknowledged: bool,
+    clock_offset: i64,
+}
+
+impl SaveState for Rtc {
+    type State = RtcSaveState;
+
+    fn save_state(&self) -> RtcSaveState {
+        RtcSaveState {
+            index: self.index,
+            time: self.time,
+            day_of_week: self.day_of_week,
+            cmos: self.cmos.to_vec(),
+            second_accum: self.second_accum,
+            periodic_accum: self.periodic_accum,
+            irq_asserted: self.irq_asserted,
+            irq_acknowledged: self.irq_acknowledged,
+            clock_offset: self.clock_offset,
+        }
+    }
+
+    /// The restored CMOS RAM is marked dirty, as it no longer matches the persisted image.
+    fn restore_state(&mut self, state: RtcSaveState) {
+        self.index = state.index;
+        self.time = state.time;
+        self.day_of_week = state.day_of_week;
+        if state.cmos.len() == CMOS_SIZE {
+            self.cmos.copy_from_slice(&state.cmos);
+        }
+        else {
+            log::error!("RtcSaveState: CMOS size mismatch, CMOS RAM not restored");
+        }
+        self.cmos_dirty = true;
+        self.second_accum = state.second_accum;
+        self.periodic_accum = state.periodic_accum;
+        self.irq_asserted = state.irq_asserted;
+        self.irq_acknowledged = state.irq_acknowledged;
+        self.clock_offset = state.clock_offset;
+        self.host_clock.freeze();
+    }
+}
+
+impl Rtc {
+    pub fn new(io_base: Option<u16>, irq: Option<u8>, time: RtcDateTime) -> Self {
+        let mut cmos = [0; CMOS_SIZE];
+        cmos[REG_A] = A_DEFAULT;
+        cmos[REG_B] = B_24H;
+        cmos[REG_D] = D_VRT;
+
+        let mut rtc = Self {
+            io_base: io_base.unwrap_or(RTC_DEFAULT_IO),
+            irq: irq.unwrap_or(RTC_DEFAULT_IRQ),
+            index: 0,
+            time: RtcDateTime::default(),
+            day_of_week: 1,
+            cmos,
+            cmos_dirty: false,
+            second_accum: 0.0,
+            periodic_accum: 0.0,
+            irq_asserted: false,
+            irq_acknowledged: false,
+            host_clock: HostClock::default(),
+            clock_offset: 0,
+            floppy_drives: Vec::new(),
+        };
+        rtc.update_checksum();
+        rtc.set_time(time);
+        rtc
+    }
+
+    /// Return the current time and date.
+    pub fn time(&self) -> RtcDateTime {
+        self.time
+    }
+
+    /// Set the time and date, as the BIOS setup would. The day of the week and the century byte in
+    /// CMOS RAM are set to match.
+    pub fn set_time(&mut self, time: RtcDateTime) {
+        self.time = time;
+        self.day_of_week = time.day_of_week();
+        self.cmos[CMOS_CENTURY] = to_bcd((time.year / 100) as u8);
+        self.second_accum = 0.0;
+    }
+
+    /// Advance the clock with the host's wall clock instead of emulated time. The periodic interrupt
+    /// always runs on emulated time.
+    pub fn set_host_clock(&mut self, enabled: bool) {
+        self.host_clock.set_enabled(enabled);
+    }
+
+    pub fn host_clock(&self) -> bool {
+        self.host_clock.enabled()
+    }
+
+    /// Stop the host clock until the next call to run(), so that time spent paused is not counted.
+    pub fn freeze(&mut self) {
+        self.host_clock.freeze();
+    }
+
+    /// Return the contents of CMOS RAM, including the clock registers in their current format.
+    pub fn cmos(&self) -> [u8; CMOS_SIZE] {
+        let mut cmos = self.cmos;
+        for reg in [
+            REG_SECONDS,
+            REG_MINUTES,
+            REG_HOURS,
+            REG_DAY_OF_WEEK,
+            REG_DAY_OF_MONTH,
+            REG_MONTH,
+            REG_YEAR,
+        ] {
+            cmos[reg] = self.read_register(reg);
+        }
+        cmos
+    }
+
+    /// Restore CMOS RAM from a previously saved image. The clock and the read-only status registers
+    /// are not restored; the time always comes from the host or configuration.
+    pub fn load_cmos(&mut self, data: &[u8]) {
+        for (i, byte) in data.iter().take(CMOS_SIZE).enumerate() {
+            match i {
+                REG_SECONDS_ALARM | REG_MINUTES_ALARM | REG_HOURS_ALARM => self.cmos[i] = *byte,
+                REG_A => self.cmos[i] = *byte & !A_UIP,
+                REG_B => self.cmos[i] = *byte,
+                REG_C | REG_D => {}
+                _ if i > REG_D => self.cmos[i] = *byte,
+                _ => {}
+            }
+        }
+        // The machine configuration, not a saved image, decides which drives are installed.
+        self.store_floppy_drives();
+        self.cmos_dirty = false;
+    }
+
+    /// Record the installed floppy drive types in CMOS RAM, so that the BIOS reports the drives the
+    /// machine is configured with. The first two drives are recorded in the drive type byte, and the
+    /// drive count in the equipment byte. Drive types are reapplied whenever CMOS RAM is restored.
+    pub fn set_floppy_drives(&mut self, drives: &[FloppyDriveType]) {
+        self.floppy_drives = drives.to_vec();
+        self.store_floppy_drives();
+    }
+
+    fn store_floppy_drives(&mut self) {
+        if self.floppy_drives.is_empty() {
+            return;
+        }
+        // Don't make an invalid image valid by fixing up its checksum; the BIOS should still
+        // prompt for setup.
+        let checksum_valid = self.checksum_valid();
+
+        let type_a = cmos_floppy_type(self.floppy_drives[0]);
+        let type_b = self.floppy_drives.get(1).map_or(0, |drive| cmos_floppy_type(*drive));
+        self.cmos[CMOS_FLOPPY_TYPES] = (type_a << 4) | type_b;
+
+        let drive_ct = (self.floppy_drives.len().min(4) - 1) as u8;
+        self.cmos[CMOS_EQUIPMENT] = (self.cmos[CMOS_EQUIPMENT] & !EQUIPMENT_FLOPPY_CT_MASK)
+            | (drive_ct << EQUIPMENT_FLOPPY_CT_SHIFT)
+            | EQUIPMENT_FLOPPY;
+
+        if checksum_valid {
+            self.update_checksum();
+        }
+    }
+
+    /// Return true if CMOS RAM has been written since it was last loaded or this flag was cleared.
+    pub fn cmos_dirty(&self) -> bool {
+        self.cmos_dirty
+    }
+
+    pub fn clear_cmos_dirty(&mut self) {
+        self.cmos_dirty = false;
+    }
+
+    /// Return true if the stored CMOS checksum matches the configuration bytes.
+    pub fn checksum_valid(&self) -> bool {
+        let stored = u16::from_be_bytes([self.cmos[CMOS_CHECKSUM_HI], self.cmos[CMOS_CHECKSUM_LO]]);
+        stored == cmos_checksum(&self.cmos)
+    }
+
+    /// Recalculate and store the CMOS checksum.
+    pub fn update_checksum(&mut self) {
+        let [hi, lo] = cmos_checksum(&self.cmos).to_be_bytes();
+        self.cmos[CMOS_CHECKSUM_HI] = hi;
+        self.cmos[CMOS_CHECKSUM_LO] = lo;
+    }
+
+    fn binary(&self) -> bool {
+        self.cmos[REG_B] & B_BINARY != 0
+    }
+
+    fn encode(&self, value: u8) -> u8 {
+        if self.binary() {
+            value
+        }
+        else {
+            to_bcd(value)
+        }
+    }
+
+    fn decode(&self, value: u8) -> u8 {
+        if self.binary() {
+            value
+        }
+        else {
+            from_bcd(value)
+        }
+    }
+
+    fn encode_hour(&self, hour: u8) -> u8 {
+        if self.cmos[REG_B] & B_24H != 0 {
+            self.encode(hour)
+        }
+        else {
+            let pm = if hour >= 12 { 0x80 } else { 0 };
+            let hour12 = match hour % 12 {
+                0 => 12,
+                h => h,
+            };
+            self.encode(hour12) | pm
+        }
+    }
+
+    fn decode_hour(&self, value: u8) -> u8 {
+        if self.cmos[REG_B] & B_24H != 0 {
+            self.decode(value)
+        }
+        else {
+            let pm = if value & 0x80 != 0 { 12 } else { 0 };
+            self.decode(value & 0x7F) % 12 + pm
+        }
+    }
+
+    fn read_register(&self, reg: usize) -> u8 {
+        match reg {
+            REG_SECONDS => self.encode(self.time.second),
+            REG_MINUTES => self.encode(self.time.minute),
+            REG_HOURS => self.encode_hour(self.time.hour),
+            REG_DAY_OF_WEEK => self.encode(self.day_of_week),
+            REG_DAY_OF_MONTH => self.encode(self.time.day),
+            REG_MONTH => self.encode(self.time.month),
+            REG_YEAR => self.encode((self.time.year % 100) as u8),
+            _ => self.cmos[reg],
+        }
+    }
+
+    fn write_register(&mut self, reg: usize, data: u8) {
+        match reg {
+            REG_SECONDS | REG_MINUTES | REG_HOURS | REG_DAY_OF_MONTH | REG_MONTH | REG_YEAR => {
+                // Setting the time moves the clock offset by the same amount.
+                let before = self.time.to_unix();
+                match reg {
+                    REG_SECONDS => self.time.second = self.decode(data),
+                    REG_MINUTES => self.time.minute = self.decode(data),
+                    REG_HOURS => self.time.hour = self.decode_hour(data),
+                    REG_DAY_OF_MONTH => self.time.day = self.decode(data),
+                    REG_MONTH => self.time.month = self.decode(data),
+                    _ => self.time.year = self.time.year / 100 * 100 + self.decode(data) as u16,
+                }
+                self.clock_offset += self.time.to_unix() - before;
+                self.cmos_dirty = true;
+            }
+            REG_DAY_OF_WEEK => self.day_of_week = self.decode(data),
+            REG_A => {
+                self.cmos[REG_A] = (self.cmos[REG_A] & A_UIP) | (data & !A_UIP);
+                self.cmos_dirty = true;
+            }
+            REG_B => {
+                // Setting SET aborts any update cycle and clears the update-ended interrupt enable.
+                self.cmos[REG_B] = if data & B_SET != 0 { data & !B_UIE } else { data };
+                self.cmos_dirty = true;
+            }
+            REG_C | REG_D => {
+                // Read-only.
+            }
+            _ => {
+                self.cmos[reg] = data;
+                self.cmos_dirty = true;
+                if (CMOS_CHECKSUM_START..=CMOS_CHECKSUM_END).contains(&reg) {
+                    self.update_checksum();
+                }
+            }
+        }
+    }
+
+    fn set_flag(&mut self, flag: u8) {
+        self.cmos[REG_C] |= flag;
+        if self.cmos[REG_C] & self.cmos[REG_B] & B_INT_MASK != 0 {
+            self.cmos[REG_C] |= C_IRQF;
+        }
+    }
+
+    /// Return the periodic interrupt period in microseconds, or None if disabled.
+    fn periodic_period_us(&self) -> Option<f64> {
+        let rate = self.cmos[REG_A] & A_RS_MASK;
+        let divisor = match rate {
+            0 => return None,
+            // Rates 1 and 2 repeat rates 8 and 9 with the 32.768kHz time base.
+            1 | 2 => 1u32 << (rate + 6),
+            _ => 1u32 << (rate - 1),
+        };
+        Some(divisor as f64 * 1_000_000.0 / TIME_BASE_HZ)
+    }
+
+    fn alarm_matches(&self) -> bool {
+        [
+            (REG_SECONDS_ALARM, REG_SECONDS),
+            (REG_MINUTES_ALARM, REG_MINUTES),
+            (REG_HOURS_ALARM, REG_HOURS),
+        ]
+        .iter()
+        .all(|&(alarm, reg)| {
+            let value = self.cmos[alarm];
+            value & ALARM_DONT_CARE == ALARM_DONT_CARE || value == self.read_register(reg)
+        })
+    }
+
+    fn update_cycle(&mut self) {
+        if self.time.tick() {
+            self.day_of_week = self.day_of_week % 7 + 1;
+        }
+        self.set_flag(C_UF);
+        if self.alarm_matches() {
+            self.set_flag(C_AF);
+        }
+    }
+
+    pub fn run(&mut self, pic: Option<&mut Pic>, us: f64) {
+        // The divider chain only runs with the 32.768kHz time base selected; other values of DV hold
+        // it in reset or select time bases the AT doesn't use.
+        if self.cmos[REG_A] & A_DV_MASK == A_DV_32K {
+            if let Some(period) = self.periodic_period_us() {
+                self.periodic_accum += us;
+                if self.periodic_accum >= period {
+                    self.periodic_accum %= period;
+                    self.set_flag(C_PF);
+                }
+            }
+
+            self.second_accum += self.host_clock.elapsed_us(us);
+            while self.second_accum >= 1_000_000.0 {
+                self.second_accum -= 1_000_000.0;
+                if self.cmos[REG_B] & B_SET == 0 {
+                    self.update_cycle();
+                }
+            }
+
+            let uip = self.cmos[REG_B] & B_SET == 0 && self.second_accum >= 1_000_000.0 - UIP_LEAD_US;
+            self.cmos[REG_A] = (self.cmos[REG_A] & !A_UIP) | if uip { A_UIP } else { 0 };
+        }
+        else {
+            self.cmos[REG_A] &= !A_UIP;
+        }
+
+        self.update_irq(pic);
+    }
+
+    fn update_irq(&mut self, mut pic: Option<&mut Pic>) {
+        // Reading Register C lowers the IRQ line. Drop it before raising it again for any new event.
+        if self.irq_acknowledged {
+            self.irq_acknowledged = false;
+            if self.irq_asserted {
+                if let Some(pic) = pic.as_mut() {
+                    pic.clear_interrupt(self.irq & 0x07);
+                }
+                self.irq_asserted = false;
+            }
+        }
+
+        let irq = self.cmos[REG_C] & C_IRQF != 0;
+        if irq != self.irq_asserted {
+            if let Some(pic) = pic {
+                if irq {
+                    pic.request_interrupt(self.irq & 0x07);
+                }
+                else {
+                    pic.clear_interrupt(self.irq & 0x07);
+                }
+            }
+            self.irq_asserted = irq;
+        }
+    }
+}
+
+impl IoDevice for Rtc {
+    fn read_u8(&mut self, port: u16, _delta: DeviceRunTimeUnit) -> u8 {
+        if port != self.io_base + 1 {
+            // The index register is write-only.
+            return NO_IO_BYTE;
+        }
+        let byte = self.read_register(self.index);
+        if self.index == REG_C {
+            self.cmos[REG_C] = 0;
+            self.irq_acknowledged = true;
+        }
+        byte
+    }
+
+    fn write_u8(
+        &mut self,
+        port: u16,
+        data: u8,
+        _bus: Option<&mut BusInterface>,
+        _delta: DeviceRunTimeUnit,
+        _analyzer: Option<&mut LogicAnalyzer>,
+    ) {
+        if port == self.io_base {
+            // Bit 7 of the index port gates NMI on the AT. NMI is not routed through the RTC here.
+            self.index = (data & 0x7F) as usize;
+        }
+        else {
+            self.write_register(self.index, data);
+        }
+    }
+
+    fn port_list(&self) -> Vec<(String, u16)> {
+        vec![
+            ("RTC Index".to_string(), self.io_base),
+            ("RTC Data".to_string(), self.io_base + 1),
+        ]
+    }
+}
+
+impl RealTimeClock for Rtc {
+    fn time(&self) -> RtcDateTime {
+        self.time()
+    }
+
+    fn set_time(&mut self, time: RtcDateTime) {
+        self.set_time(time)
+    }
+
+    fn set_host_clock(&mut self, enabled: bool) {
+        self.set_host_clock(enabled)
+    }
+
+    fn host_clock(&self) -> bool {
+        self.host_clock()
+    }
+
+    fn freeze(&mut self) {
+        self.freeze()
+    }
+
+    fn irq(&self) -> Option<u8> {
+        Some(self.irq)
+    }
+
+    fn run(&mut self, pic: Option<&mut Pic>, us: f64) {
+        self.run(pic, us)
+    }
+
+    fn nvram(&self) -> Vec<u8> {
+        self.cmos().to_vec()
+    }
+
+    fn load_nvram(&mut self, data: &[u8]) {
+        self.load_cmos(data)
+    }
+
+    fn nvram_dirty(&self) -> bool {
+        self.cmos_dirty()
+    }
+
+    fn clear_nvram_dirty(&mut self) {
+        self.clear_cmos_dirty()
+    }
+
+    fn nvram_valid(&self) -> bool {
+        self.checksum_valid()
+    }
+
+    fn clock_offset(&self) -> i64 {
+        self.clock_offset
+    }
+
+    fn set_clock_offset(&mut self, offset: i64) {
+        self.clock_offset = offset;
+    }
+
+    fn set_floppy_drives(&mut self, drives: &[FloppyDriveType]) {
+        self.set_floppy_drives(drives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const US: DeviceRunTimeUnit = DeviceRunTimeUnit::Microseconds(0.0);
+
+    fn read(rtc: &mut Rtc, reg: u8) -> u8 {
+        rtc.write_u8(RTC_DEFAULT_IO, reg, None, US, None);
+        rtc.read_u8(RTC_DEFAULT_IO + 1, US)
+    }
+
+    fn write(rtc: &mut Rtc, reg: u8, data: u8) {
+        rtc.write_u8(RTC_DEFAULT_IO, reg, None, US, None);
+        rtc.write_u8(RTC_DEFAULT_IO + 1, data, None, US, None);
+    }
+
+    #[test]
+    fn from_unix_converts_dates() {
+        assert_eq!(
+            RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME),
+            RtcDateTime {
+                year:   1990,
+                month:  1,
+                day:    1,
+                hour:   0,
+                minute: 0,
+                second: 0,
+            }
+        );
+        let leap = RtcDateTime::from_unix(951_825_599); // 2000-02-29 11:59:59
+        assert_eq!((leap.year, leap.month, leap.day, leap.hour), (2000, 2, 29, 11));
+        assert_eq!(leap.to_unix(), 951_825_599);
+        // 1990-01-01 was a Monday.
+        assert_eq!(RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME).day_of_week(), 2);
+    }
+
+    #[test]
+    fn time_registers_follow_format_and_roll_over() {
+        // 1999-12-31 23:59:59, a Friday.
+        let mut rtc = Rtc::new(None, None, RtcDateTime::from_unix(946_684_799));
+        assert_eq!(read(&mut rtc, REG_HOURS as u8), 0x23);
+        assert_eq!(read(&mut rtc, REG_YEAR as u8), 0x99);
+
+        rtc.run(None, 1_000_000.0);
+        assert_eq!(read(&mut rtc, REG_SECONDS as u8), 0x00);
+        assert_eq!(read(&mut rtc, REG_HOURS as u8), 0x00);
+        assert_eq!(read(&mut rtc, REG_DAY_OF_MONTH as u8), 0x01);
+        assert_eq!(read(&mut rtc, REG_MONTH as u8), 0x01);
+        assert_eq!(read(&mut rtc, REG_YEAR as u8), 0x00);
+        assert_eq!(read(&mut rtc, REG_DAY_OF_WEEK as u8), 0x07);
+        assert_eq!(rtc.time().year, 2000);
+
+        // Binary, 12 hour mode.
+        write(&mut rtc, REG_B as u8, B_BINARY);
+        write(&mut rtc, REG_HOURS as u8, 0x80 | 1);
+        assert_eq!(rtc.time().hour, 13);
+        assert_eq!(read(&mut rtc, REG_HOURS as u8), 0x81);
+
+        // SET holds the clock.
+        write(&mut rtc, REG_B as u8, B_SET | B_24H);
+        rtc.run(None, 5_000_000.0);
+        assert_eq!(rtc.time().second, 0);
+    }
+
+    #[test]
+    fn interrupts_set_flags_and_irq() {
+        let mut rtc = Rtc::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+
+        // Periodic interrupt at 1024Hz.
+        write(&mut rtc, REG_A as u8, A_DV_32K | 6);
+        write(&mut rtc, REG_B as u8, B_PIE | B_24H);
+        rtc.run(None, 500.0);
+        assert!(!rtc.irq_asserted);
+        rtc.run(None, 500.0);
+        assert!(rtc.irq_asserted);
+        assert_eq!(read(&mut rtc, REG_C as u8), C_IRQF | C_PF);
+        assert_eq!(read(&mut rtc, REG_C as u8), 0);
+        rtc.run(None, 1.0);
+        assert!(!rtc.irq_asserted);
+
+        // Alarm at 00:00:02, with flags still reported for disabled interrupts.
+        write(&mut rtc, REG_A as u8, A_DEFAULT & !A_RS_MASK);
+        write(&mut rtc, REG_B as u8, B_AIE | B_24H);
+        write(&mut rtc, REG_SECONDS_ALARM as u8, 0x02);
+        write(&mut rtc, REG_MINUTES_ALARM as u8, ALARM_DONT_CARE);
+        write(&mut rtc, REG_HOURS_ALARM as u8, ALARM_DONT_CARE);
+        rtc.run(None, 1_000_000.0);
+        assert_eq!(read(&mut rtc, REG_C as u8), C_UF);
+        rtc.run(None, 1_000_000.0);
+        assert_eq!(read(&mut rtc, REG_C as u8), C_IRQF | C_AF | C_UF);
+    }
+
+    #[test]
+    fn cmos_checksum_and_persistence() {
+        let mut rtc = Rtc::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+        assert!(rtc.checksum_valid());
+        assert!(!rtc.cmos_dirty());
+
+        // Writes to the checksummed range keep the checksum up to date.
+        write(&mut rtc, 0x10, 0x40);
+        assert!(rtc.cmos_dirty());
+        assert!(rtc.checksum_valid());
+        assert_eq!(read(&mut rtc, CMOS_CHECKSUM_LO as u8), 0x40);
+
+        // The extended bank is plain RAM outside the checksum.
+        write(&mut rtc, 0x7F, 0x5A);
+        assert_eq!(read(&mut rtc, 0x7F), 0x5A);
+        assert_eq!(read(&mut rtc, 0x80 | 0x10), 0x40);
+        write(&mut rtc, CMOS_CHECKSUM_LO as u8, 0x00);
+        assert!(!rtc.checksum_valid());
+        rtc.update_checksum();
+
+        let saved = rtc.cmos();
+        let mut restored = Rtc::new(None, None, RtcDateTime::from_unix(0));
+        restored.load_cmos(&saved);
+        assert!(restored.checksum_valid());
+        assert_eq!(read(&mut restored, 0x10), 0x40);
+        assert_eq!(restored.time().year, 1970);
+        assert_eq!(read(&mut restored, REG_D as u8), D_VRT);
+        assert_eq!(read(&mut restored, 0x7F), 0x5A);
+    }
+
+    #[test]
+    fn setting_the_time_moves_the_offset() {
+        let mut rtc = Rtc::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+        assert_eq!(rtc.time().to_unix(), RTC_DETERMINISTIC_TIME);
+        assert!(!rtc.cmos_dirty());
+
+        write(&mut rtc, REG_YEAR as u8, 0x91);
+        write(&mut rtc, REG_HOURS as u8, 0x01);
+        assert_eq!(rtc.clock_offset, 365 * 86400 + 3600);
+        assert!(rtc.cmos_dirty());
+
+        // Alarms don't move the clock.
+        write(&mut rtc, REG_HOURS_ALARM as u8, 0x05);
+        assert_eq!(rtc.clock_offset, 365 * 86400 + 3600);
+    }
+
+    #[test]
+    fn floppy_drives_survive_cmos_restore() {
+        let mut rtc = Rtc::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+        rtc.set_floppy_drives(&[FloppyDriveType::Floppy144M, FloppyDriveType::Floppy12M]);
+        assert_eq!(read(&mut rtc, CMOS_FLOPPY_TYPES as u8), 0x42);
+        assert_eq!(read(&mut rtc, CMOS_EQUIPMENT as u8), 0x41);
+        assert!(rtc.checksum_valid());
+
+        // A saved image from a machine with a single 360K drive is corrected to the configured drives.
+        let mut saved = rtc.cmos();
+        saved[CMOS_FLOPPY_TYPES] = 0x10;
+        saved[CMOS_EQUIPMENT] = 0x01;
+        [saved[CMOS_CHECKSUM_HI], saved[CMOS_CHECKSUM_LO]] = cmos_checksum(&saved).to_be_bytes();
+        rtc.load_cmos(&saved);
+        assert_eq!(read(&mut rtc, CMOS_FLOPPY_TYPES as u8), 0x42);
+        assert_eq!(read(&mut rtc, CMOS_EQUIPMENT as u8), 0x41);
+        assert!(rtc.checksum_valid());
+
+        // An image with a bad checksum is left invalid.
+        saved[CMOS_CHECKSUM_LO] ^= 0xFF;
+        rtc.load_cmos(&saved);
+        assert_eq!(read(&mut rtc, CMOS_FLOPPY_TYPES as u8), 0x42);
+        assert!(!rtc.checksum_valid());
+    }
+
+    #[test]
+    fn host_clock_ignores_emulated_time() {
+        let mut rtc = Rtc::new(None, None, RtcDateTime::from_unix(RTC_DETERMINISTIC_TIME));
+        rtc.set_host_clock(true);
+        rtc.run(None, 5_000_000.0);
+        rtc.freeze();
+        rtc.run(None, 5_000_000.0);
+        assert_eq!(rtc.time().second, 0);
+
+        rtc.set_host_clock(false);
+        rtc.run(None, 5_000_000.0);
+        assert_eq!(rtc.time().second, 5);
+    }
+}
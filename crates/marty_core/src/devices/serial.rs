@@ -34,9 +34,23 @@
     Primary Documentation:
     IBM Publication 6361501
     "IBM Asynchronous Communications Adapter"
+
+    The bytes received on a port can be recorded to a log file and played
+    back later at their original timing, to run serial-dependent software
+    reproducibly without the device attached. A log is a 4-byte magic and a
+    16-bit version, followed by 9-byte entries: a 64-bit timestamp in CPU
+    cycles since the start of the recording and the byte received, all
+    little-endian.
 */
 
-use std::collections::{BTreeMap, VecDeque};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "serial")]
 use std::io::Read;
@@ -47,6 +61,7 @@ use crate::{
     bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
     cpu_common::LogicAnalyzer,
     devices::pic,
+    savestate::SaveState,
     syntax_token::SyntaxToken,
 };
 /*  1.8Mhz Oscillator.
@@ -131,6 +146,12 @@ const MODEM_STATUS_DSR: u8 = 0b0010_0000;
 const MODEM_STATUS_RI: u8 = 0b0100_0000;
 const MODEM_STATUS_RLSD: u8 = 0b1000_0000;
 
+pub const SERIAL_LOG_MAGIC: &[u8; 4] = b"MSRL";
+pub const SERIAL_LOG_VERSION: u16 = 1;
+pub const SERIAL_LOG_EXTENSION: &str = "srl";
+const SERIAL_LOG_HEADER_LEN: usize = 6;
+const SERIAL_LOG_ENTRY_LEN: usize = 9;
+
 impl IoDevice for SerialPortController {
     fn read_u8(&mut self, port: u16, _delta: DeviceRunTimeUnit) -> u8 {
         match port {
@@ -199,14 +220,14 @@ impl IoDevice for SerialPortController {
     }
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum StopBits {
     One,
     OneAndAHalf,
     Two,
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum IntrAction {
     None,
     Raise,
@@ -229,6 +250,38 @@ pub enum IntrAction {
 
 pub type SerialPortDisplayState = BTreeMap<&'static str, SyntaxToken>;
 
+/// The display state key holding the recording/playback status: "Recording", "Playback" or "Idle".
+pub const SERIAL_CAPTURE_STATUS_KEY: &str = "Capture:";
+
+/// Recording or playback of the bytes received on a serial port.
+enum SerialCapture {
+    Recording { writer: BufWriter<File>, start: u64 },
+    Playback { entries: VecDeque<(u64, u8)>, start: u64, total: usize },
+}
+
+/// Parse a serial log into its (timestamp, byte) entries.
+pub fn parse_serial_log(data: &[u8]) -> anyhow::Result<VecDeque<(u64, u8)>> {
+    if data.len() < SERIAL_LOG_HEADER_LEN || &data[0..4] != SERIAL_LOG_MAGIC {
+        anyhow::bail!("Not a serial log file");
+    }
+    let version = u16::from_le_bytes([data[4], data[5]]);
+    if version != SERIAL_LOG_VERSION {
+        anyhow::bail!("Unsupported serial log version: {}", version);
+    }
+    let body = &data[SERIAL_LOG_HEADER_LEN..];
+    if body.len() % SERIAL_LOG_ENTRY_LEN != 0 {
+        log::warn!("Serial log is truncated; ignoring the incomplete final entry.");
+    }
+    Ok(body
+        .chunks_exact(SERIAL_LOG_ENTRY_LEN)
+        .map(|entry| {
+            let mut timestamp = [0u8; 8];
+            timestamp.copy_from_slice(&entry[0..8]);
+            (u64::from_le_bytes(timestamp), entry[8])
+        })
+        .collect())
+}
+
 #[derive(Clone, Debug)]
 pub struct SerialPortDescriptor {
     pub id: usize,
@@ -265,6 +318,11 @@ pub struct SerialPort {
     tx_queue: VecDeque<u8>,
     tx_timer: f64,
     us_per_byte: f64,
+    cycles: u64,
+
+    // Recording and playback of received bytes
+    capture: Option<SerialCapture>,
+    capture_bytes: usize,
 
     // Serial port bridge
     // Allow a None id when serial feature is not enabled
@@ -306,6 +364,10 @@ impl Default for SerialPort {
             tx_queue: VecDeque::new(),
             tx_timer: 0.0,
             us_per_byte: 833.333, // 9600 baud
+            cycles: 0,
+
+            capture: None,
+            capture_bytes: 0,
 
             bridge_port_id: None,
             #[cfg(feature = "serial")]
@@ -316,6 +378,99 @@ impl Default for SerialPort {
     }
 }
 
+/// Snapshot of a serial port's UART registers and pending data. The host bridge and any recording
+/// or playback are not included and continue across a restore.
+#[derive(Serialize, Deserialize)]
+pub struct SerialPortSaveState {
+    line_control_reg: u8,
+    word_length: u8,
+    stop_bits: StopBits,
+    parity_enable: bool,
+    divisor_latch_access: bool,
+    divisor: u16,
+    line_status_reg: u8,
+    interrupts_active: u8,
+    interrupt_enable_reg: u8,
+    intr_action: IntrAction,
+    modem_control_reg: u8,
+    loopback: bool,
+    modem_status_reg: u8,
+    rx_byte: u8,
+    rx_count: usize,
+    rx_overrun_count: usize,
+    rx_was_read: bool,
+    tx_holding_reg: u8,
+    tx_holding_empty: bool,
+    rx_queue: VecDeque<u8>,
+    rx_timer: f64,
+    tx_count: usize,
+    tx_queue: VecDeque<u8>,
+    tx_timer: f64,
+    us_per_byte: f64,
+}
+
+impl SaveState for SerialPort {
+    type State = SerialPortSaveState;
+
+    fn save_state(&self) -> SerialPortSaveState {
+        SerialPortSaveState {
+            line_control_reg: self.line_control_reg,
+            word_length: self.word_length,
+            stop_bits: self.stop_bits,
+            parity_enable: self.parity_enable,
+            divisor_latch_access: self.divisor_latch_access,
+            divisor: self.divisor,
+            line_status_reg: self.line_status_reg,
+            interrupts_active: self.interrupts_active,
+            interrupt_enable_reg: self.interrupt_enable_reg,
+            intr_action: self.intr_action,
+            modem_control_reg: self.modem_control_reg,
+            loopback: self.loopback,
+            modem_status_reg: self.modem_status_reg,
+            rx_byte: self.rx_byte,
+            rx_count: self.rx_count,
+            rx_overrun_count: self.rx_overrun_count,
+            rx_was_read: self.rx_was_read,
+            tx_holding_reg: self.tx_holding_reg,
+            tx_holding_empty: self.tx_holding_empty,
+            rx_queue: self.rx_queue.clone(),
+            rx_timer: self.rx_timer,
+            tx_count: self.tx_count,
+            tx_queue: self.tx_queue.clone(),
+            tx_timer: self.tx_timer,
+            us_per_byte: self.us_per_byte,
+        }
+    }
+
+    fn restore_state(&mut self, state: SerialPortSaveState) {
+        self.line_control_reg = state.line_control_reg;
+        self.word_length = state.word_length;
+        self.stop_bits = state.stop_bits;
+        self.parity_enable = state.parity_enable;
+        self.divisor_latch_access = state.divisor_latch_access;
+        self.divisor = state.divisor;
+        self.line_status_reg = state.line_status_reg;
+        self.interrupts_active = state.interrupts_active;
+        self.interrupt_enable_reg = state.interrupt_enable_reg;
+        self.intr_action = state.intr_action;
+        self.modem_control_reg = state.modem_control_reg;
+        self.loopback = state.loopback;
+        self.modem_status_reg = state.modem_status_reg;
+        self.rx_byte = state.rx_byte;
+        self.rx_count = state.rx_count;
+        self.rx_overrun_count = state.rx_overrun_count;
+        self.rx_was_read = state.rx_was_read;
+        self.tx_holding_reg = state.tx_holding_reg;
+        self.tx_holding_empty = state.tx_holding_empty;
+        self.rx_queue = state.rx_queue;
+        self.rx_timer = state.rx_timer;
+        self.tx_count = state.tx_count;
+        self.tx_queue = state.tx_queue;
+        self.tx_timer = state.tx_timer;
+        self.us_per_byte = state.us_per_byte;
+    }
+}
+
 impl SerialPort {
     pub fn new(name: String, irq: u8, out2_suppresses_int: bool) -> Self {
         Self {
@@ -327,14 +482,107 @@ impl SerialPort {
     }
 
     pub fn reset(&mut self) {
+        // A recording or playback continues across a reset, so that it can capture a reboot.
         *self = Self {
             name: self.name.clone(),
             irq: self.irq,
             out2_suppresses_int: self.out2_suppresses_int,
+            cycles: self.cycles,
+            capture: self.capture.take(),
+            capture_bytes: self.capture_bytes,
             ..Default::default()
         }
     }
 
+    /// Record all bytes subsequently received on this port to a serial log at `path`.
+    /// Any recording or playback in progress is stopped.
+    pub fn start_recording(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.stop_capture()?;
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(SERIAL_LOG_MAGIC)?;
+        writer.write_all(&SERIAL_LOG_VERSION.to_le_bytes())?;
+        self.capture = Some(SerialCapture::Recording {
+            writer,
+            start: self.cycles,
+        });
+        self.capture_bytes = 0;
+        log::debug!("{}: Recording received bytes to {:?}", self.name, path);
+        Ok(())
+    }
+
+    /// Play back the serial log at `path`, receiving each byte at its recorded time relative to now.
+    /// Any recording or playback in progress is stopped.
+    pub fn start_playback(&mut self, path: &Path) -> anyhow::Result<()> {
+        let entries = parse_serial_log(&std::fs::read(path)?)?;
+        self.stop_capture()?;
+        log::debug!("{}: Playing back {} bytes from {:?}", self.name, entries.len(), path);
+        self.capture = Some(SerialCapture::Playback {
+            total: entries.len(),
+            entries,
+            start: self.cycles,
+        });
+        self.capture_bytes = 0;
+        Ok(())
+    }
+
+    /// Stop any recording or playback in progress, flushing a recording to disk.
+    pub fn stop_capture(&mut self) -> anyhow::Result<()> {
+        if let Some(SerialCapture::Recording { mut writer, .. }) = self.capture.take() {
+            writer.flush()?;
+            log::debug!("{}: Recorded {} bytes", self.name, self.capture_bytes);
+        }
+        Ok(())
+    }
+
+    /// Append a received byte to the recording, if one is in progress.
+    fn record(&mut self, byte: u8) {
+        let cycles = self.cycles;
+        if let Some(SerialCapture::Recording { writer, start }) = &mut self.capture {
+            let mut entry = [0u8; SERIAL_LOG_ENTRY_LEN];
+            entry[0..8].copy_from_slice(&(cycles - *start).to_le_bytes());
+            entry[8] = byte;
+            if let Err(e) = writer.write_all(&entry) {
+                log::error!("{}: Error writing serial log, recording stopped: {}", self.name, e);
+                self.capture = None;
+                return;
+            }
+            self.capture_bytes += 1;
+        }
+    }
+
+    /// Receive any played back bytes that are due.
+    fn run_playback(&mut self) {
+        let mut due = Vec::new();
+        let mut finished = false;
+        if let Some(SerialCapture::Playback { entries, start, .. }) = &mut self.capture {
+            while let Some(&(timestamp, byte)) = entries.front() {
+                if *start + timestamp > self.cycles {
+                    break;
+                }
+                due.push(byte);
+                entries.pop_front();
+            }
+            finished = entries.is_empty();
+        }
+        for byte in due {
+            self.capture_bytes += 1;
+            self.receive(byte);
+        }
+        if finished {
+            log::debug!("{}: Playback complete", self.name);
+            self.capture = None;
+        }
+    }
+
+    /// Return a description of the recording or playback status and its byte counter.
+    fn capture_status(&self) -> (&'static str, String) {
+        match &self.capture {
+            Some(SerialCapture::Recording { .. }) => ("Recording", format!("{}", self.capture_bytes)),
+            Some(SerialCapture::Playback { total, .. }) => ("Playback", format!("{}/{}", self.capture_bytes, total)),
+            None => ("Idle", format!("{}", self.capture_bytes)),
+        }
+    }
+
     /// Convert the integer divisor value into baud rate
     fn divisor_to_baud(divisor: u16) -> u16 {
         return ((SERIAL_CLOCK * 1_000_000.0) / divisor as f64 / 16.0) as u16;
@@ -720,6 +968,8 @@ impl SerialPort {
 
     /// Receive a byte on this port.
     fn receive(&mut self, byte: u8) {
+        self.record(byte);
+
         if !self.rx_was_read {
             self.overrun();
         }
@@ -859,6 +1109,13 @@ impl SerialPort {
             SyntaxToken::StateString(format!("{}", self.rx_overrun_count), false, 0),
         );
 
+        let (capture_status, capture_bytes) = self.capture_status();
+        state.insert(
+            SERIAL_CAPTURE_STATUS_KEY,
+            SyntaxToken::StateString(capture_status.to_string(), false, 0),
+        );
+        state.insert("Capture Bytes:", SyntaxToken::StateString(capture_bytes, false, 0));
+
         /*        if clean {
             for i in 0..3 {
                 self.channels[i].mode.clean();
@@ -880,6 +1137,20 @@ pub struct SerialPortController {
     port: [SerialPort; 2],
 }
 
+impl SaveState for SerialPortController {
+    type State = Vec<SerialPortSaveState>;
+
+    fn save_state(&self) -> Vec<SerialPortSaveState> {
+        self.port.iter().map(|port| port.save_state()).collect()
+    }
+
+    fn restore_state(&mut self, state: Vec<SerialPortSaveState>) {
+        for (port, saved) in self.port.iter_mut().zip(state) {
+            port.restore_state(saved);
+        }
+    }
+}
+
 impl SerialPortController {
     pub fn new(out2_suppresses_int: bool) -> Self {
         Self {
@@ -936,15 +1207,39 @@ impl SerialPortController {
         self.port[port].rx_queue.push_back(byte);
     }
 
+    /// Record all bytes received on the specified serial port to a serial log at `path`.
+    pub fn start_recording(&mut self, port: usize, path: &Path) -> anyhow::Result<()> {
+        self.port_mut(port)?.start_recording(path)
+    }
+
+    /// Play back the serial log at `path` into the specified serial port.
+    pub fn start_playback(&mut self, port: usize, path: &Path) -> anyhow::Result<()> {
+        self.port_mut(port)?.start_playback(path)
+    }
+
+    /// Stop any recording or playback on the specified serial port.
+    pub fn stop_capture(&mut self, port: usize) -> anyhow::Result<()> {
+        self.port_mut(port)?.stop_capture()
+    }
+
+    fn port_mut(&mut self, port: usize) -> anyhow::Result<&mut SerialPort> {
+        self.port
+            .get_mut(port)
+            .ok_or_else(|| anyhow::anyhow!("Invalid serial port: {}", port))
+    }
+
     /// Bridge the specified serial port
     #[cfg(feature = "serial")]
     pub fn bridge_port(&mut self, port: usize, host_port_name: String, host_port_id: usize) -> anyhow::Result<bool> {
         self.port[port].bridge_port(host_port_name, host_port_id)
     }
 
-    /// Run the serial ports for the specified number of microseconds
-    pub fn run(&mut self, pic: &mut pic::Pic, us: f64) {
+    /// Run the serial ports for the specified number of microseconds, equivalent to `cycles` CPU cycles.
+    pub fn run(&mut self, pic: &mut pic::Pic, us: f64, cycles: u64) {
         for port in self.port.iter_mut() {
+            port.cycles += cycles;
+            port.run_playback();
+
             // Handle pending interrupt action
             match port.intr_action {
                 IntrAction::Raise => {
@@ -1051,3 +1346,46 @@ impl SerialPortController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_recording_plays_back_at_recorded_cycles() {
+        let path = std::env::temp_dir().join(format!("martypc_serial_test_{}.srl", std::process::id()));
+
+        let mut port = SerialPort::new("COM1".to_string(), SERIAL1_IRQ, false);
+        port.cycles = 1000;
+        port.start_recording(&path).unwrap();
+        port.cycles += 10;
+        port.receive(0x4D);
+        port.cycles += 500;
+        port.receive(0x33);
+        port.stop_capture().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len(), SERIAL_LOG_HEADER_LEN + 2 * SERIAL_LOG_ENTRY_LEN);
+        assert_eq!(
+            parse_serial_log(&data).unwrap(),
+            VecDeque::from([(10, 0x4D), (510, 0x33)])
+        );
+
+        let mut port = SerialPort::new("COM1".to_string(), SERIAL1_IRQ, false);
+        port.start_playback(&path).unwrap();
+        port.cycles = 9;
+        port.run_playback();
+        assert_eq!(port.rx_count, 0);
+        port.cycles = 10;
+        port.run_playback();
+        assert_eq!((port.rx_count, port.rx_byte), (1, 0x4D));
+        assert_eq!(port.capture_status(), ("Playback", "1/2".to_string()));
+        port.cycles = 600;
+        port.run_playback();
+        assert_eq!((port.rx_count, port.rx_byte), (2, 0x33));
+        assert_eq!(port.capture_status().0, "Idle");
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(parse_serial_log(b"MSRL\x02\x00").is_err());
+    }
+}
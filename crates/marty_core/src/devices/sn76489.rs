@@ -0,0 +1,349 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    devices::sn76489.rs
+
+    Implement the TI SN76489 programmable sound generator found in the
+    IBM PCjr and Tandy 1000 series.
+
+    The chip has three square wave tone channels and one noise channel,
+    each with a 4-bit attenuator. Registers are written with a latch/data
+    protocol: a byte with bit 7 set latches a register and writes its low
+    4 bits, and a following byte with bit 7 clear writes the upper 6 bits
+    of a tone period.
+
+*/
+
+pub const SN76489_DEFAULT_IO: u16 = 0xC0;
+pub const SN76489_CLOCK: f64 = 3_579_545.0;
+
+// The chip's internal counters are clocked at 1/16th of the input clock.
+const CLOCK_DIVISOR: f64 = 16.0;
+const TONE_CHANNELS: usize = 3;
+const NOISE_CHANNEL: usize = 3;
+const NOISE_LFSR_INIT: u16 = 0x4000;
+// White noise feedback taps bits 0 and 1 of the 15-bit LFSR.
+const NOISE_LFSR_TAPS: u16 = 0x0003;
+const ATTENUATION_OFF: u8 = 0x0F;
+
+use crate::{
+    bus::{BusInterface, DeviceRunTimeUnit, IoDevice},
+    cpu_common::LogicAnalyzer,
+    device_traits::sounddevice::{AudioSample, SoundDevice},
+    savestate::SaveState,
+};
+use crossbeam_channel::Sender;
+use serde::{Deserialize, Serialize};
+
+pub struct Sn76489 {
+    io_base: u16,
+    sender: Sender<AudioSample>,
+    latched_reg: usize,
+    tone_period: [u16; TONE_CHANNELS],
+    tone_counter: [u16; TONE_CHANNELS],
+    tone_output: [bool; TONE_CHANNELS],
+    attenuation: [u8; 4],
+    noise_ctrl: u8,
+    noise_counter: u16,
+    noise_flipflop: bool,
+    lfsr: u16,
+    volume_table: [f32; 16],
+    tick_accum: f64,
+    ticks_per_sample: f64,
+    sample_phase: f64,
+    sample_sum: f32,
+    sample_ticks: u32,
+}
+
+/// The chip's registers and counters. The host sample rate and resampling accumulators are not
+/// included.
+#[derive(Serialize, Deserialize)]
+pub struct Sn76489SaveState {
+    latched_reg: usize,
+    tone_period: [u16; TONE_CHANNELS],
+    tone_counter: [u16; TONE_CHANNELS],
+    tone_output: [bool; TONE_CHANNELS],
+    attenuation: [u8; 4],
+    noise_ctrl: u8,
+    noise_counter: u16,
+    noise_flipflop: bool,
+    lfsr: u16,
+    tick_accum: f64,
+}
+
+impl Sn76489 {
+    pub fn new(io_base: u16, sample_rate: u32, sender: Sender<AudioSample>) -> Self {
+        // Each attenuation step is 2dB. The maximum attenuation value turns the channel off.
+        let mut volume_table = [0.0; 16];
+        for (i, vol) in volume_table.iter_mut().enumerate().take(ATTENUATION_OFF as usize) {
+            *vol = 10.0f32.powf(-(i as f32) * 2.0 / 20.0);
+        }
+
+        Sn76489 {
+            io_base,
+            sender,
+            latched_reg: 0,
+            tone_period: [0; TONE_CHANNELS],
+            tone_counter: [0; TONE_CHANNELS],
+            tone_output: [false; TONE_CHANNELS],
+            attenuation: [ATTENUATION_OFF; 4],
+            noise_ctrl: 0,
+            noise_counter: 0,
+            noise_flipflop: false,
+            lfsr: NOISE_LFSR_INIT,
+            volume_table,
+            tick_accum: 0.0,
+            ticks_per_sample: SN76489_CLOCK / CLOCK_DIVISOR / sample_rate as f64,
+            sample_phase: 0.0,
+            sample_sum: 0.0,
+            sample_ticks: 0,
+        }
+    }
+
+    fn write_data(&mut self, byte: u8) {
+        let latch = byte & 0x80 != 0;
+        if latch {
+            // Bits 6-5 select the channel, bit 4 selects attenuation (1) or tone/noise (0).
+            self.latched_reg = ((byte >> 4) & 0x07) as usize;
+        }
+
+        let channel = self.latched_reg >> 1;
+        if self.latched_reg & 1 != 0 {
+            self.attenuation[channel] = byte & 0x0F;
+        }
+        else if channel < TONE_CHANNELS {
+            let period = &mut self.tone_period[channel];
+            *period = match latch {
+                true => (*period & 0x3F0) | (byte & 0x0F) as u16,
+                false => (*period & 0x00F) | ((byte & 0x3F) as u16) << 4,
+            };
+        }
+        else {
+            // Any write to the noise register resets the shift register.
+            self.noise_ctrl = byte & 0x07;
+            self.lfsr = NOISE_LFSR_INIT;
+        }
+    }
+
+    fn noise_period(&self) -> u16 {
+        match self.noise_ctrl & 0x03 {
+            0 => 0x10,
+            1 => 0x20,
+            2 => 0x40,
+            _ => self.tone_period[2],
+        }
+    }
+
+    /// Advance the chip by one internal clock tick, returning the mixed output level.
+    fn tick(&mut self) -> f32 {
+        let mut output = 0.0;
+
+        for ch in 0..TONE_CHANNELS {
+            if self.tone_counter[ch] > 0 {
+                self.tone_counter[ch] -= 1;
+            }
+            if self.tone_counter[ch] == 0 {
+                self.tone_counter[ch] = self.tone_period[ch];
+                self.tone_output[ch] = !self.tone_output[ch];
+            }
+
+            // A period of 0 or 1 holds the output high, which software uses to play samples via the attenuator.
+            let level = if self.tone_period[ch] <= 1 || self.tone_output[ch] {
+                1.0
+            }
+            else {
+                -1.0
+            };
+            output += level * self.volume_table[self.attenuation[ch] as usize];
+        }
+
+        if self.noise_counter > 0 {
+            self.noise_counter -= 1;
+        }
+        if self.noise_counter == 0 {
+            self.noise_counter = self.noise_period();
+            self.noise_flipflop = !self.noise_flipflop;
+
+            // The shift register is clocked on the rising edge of the noise flip-flop.
+            if self.noise_flipflop {
+                let feedback = match self.noise_ctrl & 0x04 {
+                    0 => self.lfsr & 0x01,
+                    _ => ((self.lfsr & NOISE_LFSR_TAPS).count_ones() & 0x01) as u16,
+                };
+                self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+            }
+        }
+
+        let level = if self.lfsr & 0x01 != 0 { 1.0 } else { -1.0 };
+        output += level * self.volume_table[self.attenuation[NOISE_CHANNEL] as usize];
+
+        output / 4.0
+    }
+}
+
+impl SoundDevice for Sn76489 {
+    fn run(&mut self, usec: f64) {
+        self.tick_accum += usec * SN76489_CLOCK / CLOCK_DIVISOR / 1_000_000.0;
+
+        while self.tick_accum >= 1.0 {
+            self.tick_accum -= 1.0;
+
+            // Average the chip output over each host sample period to resample to the host rate.
+            self.sample_sum += self.tick();
+            self.sample_ticks += 1;
+            self.sample_phase += 1.0;
+
+            if self.sample_phase >= self.ticks_per_sample {
+                self.sample_phase -= self.ticks_per_sample;
+                _ = self.sender.send(self.sample_sum / self.sample_ticks as f32);
+                self.sample_sum = 0.0;
+                self.sample_ticks = 0;
+            }
+        }
+    }
+}
+
+impl SaveState for Sn76489 {
+    type State = Sn76489SaveState;
+
+    fn save_state(&self) -> Sn76489SaveState {
+        Sn76489SaveState {
+            latched_reg: self.latched_reg,
+            tone_period: self.tone_period,
+            tone_counter: self.tone_counter,
+            tone_output: self.tone_output,
+            attenuation: self.attenuation,
+            noise_ctrl: self.noise_ctrl,
+            noise_counter: self.noise_counter,
+            noise_flipflop: self.noise_flipflop,
+            lfsr: self.lfsr,
+            tick_accum: self.tick_accum,
+        }
+    }
+
+    fn restore_state(&mut self, state: Sn76489SaveState) {
+        self.latched_reg = state.latched_reg;
+        self.tone_period = state.tone_period;
+        self.tone_counter = state.tone_counter;
+        self.tone_output = state.tone_output;
+        self.attenuation = state.attenuation;
+        self.noise_ctrl = state.noise_ctrl;
+        self.noise_counter = state.noise_counter;
+        self.noise_flipflop = state.noise_flipflop;
+        self.lfsr = state.lfsr;
+        self.tick_accum = state.tick_accum;
+    }
+}
+
+impl IoDevice for Sn76489 {
+    fn read_u8(&mut self, _port: u16, _delta: DeviceRunTimeUnit) -> u8 {
+        // The SN76489 is write-only.
+        0xFF
+    }
+
+    fn write_u8(
+        &mut self,
+        _port: u16,
+        data: u8,
+        _bus: Option<&mut BusInterface>,
+        _delta: DeviceRunTimeUnit,
+        _analyzer: Option<&mut LogicAnalyzer>,
+    ) {
+        self.write_data(data);
+    }
+
+    fn port_list(&self) -> Vec<(String, u16)> {
+        vec![(String::from("SN76489 Data"), self.io_base)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+
+    #[test]
+    fn latch_data_protocol() {
+        let (s, _r) = unbounded();
+        let mut psg = Sn76489::new(SN76489_DEFAULT_IO, 44100, s);
+
+        // Latch channel 1 tone, low bits 0xE, then data byte with upper bits 0x0F.
+        psg.write_data(0x80 | (2 << 4) | 0x0E);
+        psg.write_data(0x0F);
+        assert_eq!(psg.tone_period[1], 0x0FE);
+
+        // Latch channel 2 attenuation.
+        psg.write_data(0x80 | (5 << 4) | 0x03);
+        assert_eq!(psg.attenuation[2], 0x03);
+
+        // Noise register writes reset the shift register.
+        psg.lfsr = 0x1234;
+        psg.write_data(0x80 | (6 << 4) | 0x05);
+        assert_eq!(psg.noise_ctrl, 0x05);
+        assert_eq!(psg.lfsr, NOISE_LFSR_INIT);
+    }
+
+    #[test]
+    fn output_is_resampled_to_host_rate() {
+        let (s, r) = unbounded();
+        let mut psg = Sn76489::new(SN76489_DEFAULT_IO, 44100, s);
+
+        psg.write_data(0x80 | 0x0F);
+        psg.write_data(0x3F);
+        psg.write_data(0x90);
+        psg.run(100_000.0);
+
+        let samples: Vec<f32> = r.try_iter().collect();
+        assert!((4409..=4411).contains(&samples.len()));
+        assert!(samples.iter().any(|s| *s > 0.0) && samples.iter().any(|s| *s < 0.0));
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let (s, _r) = unbounded();
+        let mut psg = Sn76489::new(SN76489_DEFAULT_IO, 44100, s);
+        psg.write_data(0x80 | 0x0F);
+        psg.write_data(0x3F);
+        psg.write_data(0x90);
+        psg.write_data(0x80 | (6 << 4) | 0x04);
+        psg.write_data(0xF2);
+        psg.run(1234.5);
+        let state = serde_json::to_string(&psg.save_state()).unwrap();
+
+        let (s2, _r2) = unbounded();
+        let mut restored = Sn76489::new(SN76489_DEFAULT_IO, 44100, s2);
+        restored.restore_state(serde_json::from_str(&state).unwrap());
+
+        // Both chips continue from the same point.
+        psg.run(10_000.0);
+        restored.run(10_000.0);
+        assert_eq!(restored.tone_counter, psg.tone_counter);
+        assert_eq!(restored.tone_output, psg.tone_output);
+        assert_eq!(restored.noise_counter, psg.noise_counter);
+        assert_eq!(restored.lfsr, psg.lfsr);
+        assert_eq!(restored.attenuation, psg.attenuation);
+    }
+}
@@ -42,6 +42,7 @@ use super::{tga::tablegen::*, *};
 use crate::{
     bus::{BusInterface, DeviceRunTimeUnit},
     device_traits::videocard::*,
+    savestate::SaveState,
     tracelogger::TraceLogger,
 };
 use bytemuck;
@@ -50,6 +51,7 @@ use modular_bitfield::{
     bitfield,
     prelude::{B1, B2, B3, B4},
 };
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, convert::TryInto, path::Path};
 
 #[macro_use]
@@ -127,6 +129,7 @@ pub struct TPageRegister {
     pub address_mode: B2,
 }
 
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum VideoModeSize {
     Mode16k,
     Mode32k,
@@ -701,7 +704,144 @@ pub struct TGACard {
     aperture_base: usize,
 }
 
-#[derive(Debug)]
+/// Serializable state of the PCjr or Tandy video subsystem and the raster position. Video memory
+/// is part of system memory and is saved with it. The frame buffers are not included and are
+/// redrawn from the next frame.
+#[derive(Serialize, Deserialize)]
+pub struct TgaSaveState {
+    cycles: u64,
+    last_vsync_cycles: u64,
+    cur_screen_cycles: u64,
+    cycles_per_vsync: u64,
+    sink_cycles: u32,
+    catching_up: bool,
+    last_rw_tick: u32,
+    slot_idx: usize,
+    dirty_snow: bool,
+    snow_char: u8,
+    last_bus_value: u8,
+    last_bus_addr: usize,
+    snow_count: u64,
+    mode_pending: bool,
+    clock_pending: bool,
+    mode_byte: u8,
+    display_mode: DisplayMode,
+    mode_enable: bool,
+    mode_graphics: bool,
+    mode_bw: bool,
+    mode_bandwidth: bool,
+    mode_hires_gfx: bool,
+    mode_hires_txt: bool,
+    mode_blinking: bool,
+    mode_4bpp: bool,
+    cc_palette: usize,
+    cc_altcolor: u8,
+    cc_overscan_color: u8,
+    scanline_us: f64,
+    frame_us: f64,
+    cursor_frames: u32,
+    frame_count: u64,
+    status_reads: u64,
+    cursor_status: bool,
+    cursor_slowblink: bool,
+    cursor_blink_rate: f64,
+    cursor_data: [bool; CGA_CURSOR_MAX],
+    cursor_attr: u8,
+    crtc_register_select_byte: u8,
+    crtc_register_selected: CRTCRegister,
+    crtc_horizontal_total: u8,
+    crtc_horizontal_displayed: u8,
+    crtc_horizontal_sync_pos: u8,
+    crtc_sync_width: u8,
+    crtc_vertical_total: u8,
+    crtc_vertical_total_adjust: u8,
+    crtc_vertical_displayed: u8,
+    crtc_vertical_sync_pos: u8,
+    crtc_interlace_mode: u8,
+    crtc_maximum_scanline_address: u8,
+    crtc_cursor_start_line: u8,
+    crtc_cursor_end_line: u8,
+    crtc_start_address: usize,
+    crtc_start_address_ho: u8,
+    crtc_start_address_lo: u8,
+    crtc_cursor_address_lo: u8,
+    crtc_cursor_address_ho: u8,
+    crtc_cursor_address: usize,
+    crtc_frame_address: usize,
+    in_crtc_hblank: bool,
+    in_crtc_vblank: bool,
+    in_crtc_vsync: bool,
+    in_last_vblank_line: bool,
+    hborder: bool,
+    vborder: bool,
+    cc_register: u8,
+    clock_divisor: u8,
+    char_clock: u32,
+    char_clock_mask: u64,
+    char_clock_odd_mask: u64,
+    beam_x: u32,
+    beam_y: u32,
+    in_monitor_hsync: bool,
+    in_monitor_vblank: bool,
+    monitor_hsc: u32,
+    scanline: u32,
+    missed_hsyncs: u32,
+    overscan_left: u32,
+    overscan_right_start: u32,
+    overscan_right: u32,
+    vsync_len: u32,
+    in_display_area: bool,
+    cur_char: u8,
+    cur_attr: u8,
+    cur_fg: u8,
+    cur_bg: u8,
+    cur_blink: bool,
+    char_col: u8,
+    hcc_c0: u8,
+    vlc_c9: u8,
+    vcc_c4: u8,
+    last_row: bool,
+    vsc_c3h: u8,
+    hsc_c3l: u8,
+    vtac_c5: u8,
+    in_vta: bool,
+    effective_vta: u8,
+    vma: usize,
+    vma_t: usize,
+    vmws: usize,
+    rba: usize,
+    blink_state: bool,
+    blink_accum_us: f64,
+    blink_accum_clocks: u32,
+    accumulated_us: f64,
+    ticks_advanced: u32,
+    pixel_clocks_owed: u32,
+    ticks_accum: u32,
+    clocks_accum: u32,
+    lightpen_latch: bool,
+    lightpen_addr: usize,
+    do_vsync: bool,
+    intr: bool,
+    last_intr: bool,
+    intr_enabled: bool,
+    video_array_address: usize,
+    palette_mask: u8,
+    border_color: u8,
+    t_mode_control: u8,
+    jr_mode_control: u8,
+    jr_mode_control2: u8,
+    mode_size: VideoModeSize,
+    palette_registers: [u8; 16],
+    page_register: u8,
+    cpu_page_offset: usize,
+    crt_page_offset: usize,
+    page_size: usize,
+    address_flipflop: bool,
+    a0: u8,
+    aperture_base: usize,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum CRTCRegister {
     HorizontalTotal,
     HorizontalDisplayed,
@@ -738,6 +878,7 @@ impl TgaDefault for DisplayExtents {
             row_stride: CGA_XRES_MAX as usize,
             double_scan: true,
             mode_byte: 0,
+            register_changes: Vec::new(),
         }
     }
 }
@@ -922,6 +1063,278 @@ impl Default for TGACard {
     }
 }
 
+impl SaveState for TGACard {
+    type State = TgaSaveState;
+
+    fn save_state(&self) -> TgaSaveState {
+        TgaSaveState {
+            cycles: self.cycles,
+            last_vsync_cycles: self.last_vsync_cycles,
+            cur_screen_cycles: self.cur_screen_cycles,
+            cycles_per_vsync: self.cycles_per_vsync,
+            sink_cycles: self.sink_cycles,
+            catching_up: self.catching_up,
+            last_rw_tick: self.last_rw_tick,
+            slot_idx: self.slot_idx,
+            dirty_snow: self.dirty_snow,
+            snow_char: self.snow_char,
+            last_bus_value: self.last_bus_value,
+            last_bus_addr: self.last_bus_addr,
+            snow_count: self.snow_count,
+            mode_pending: self.mode_pending,
+            clock_pending: self.clock_pending,
+            mode_byte: self.mode_byte,
+            display_mode: self.display_mode,
+            mode_enable: self.mode_enable,
+            mode_graphics: self.mode_graphics,
+            mode_bw: self.mode_bw,
+            mode_bandwidth: self.mode_bandwidth,
+            mode_hires_gfx: self.mode_hires_gfx,
+            mode_hires_txt: self.mode_hires_txt,
+            mode_blinking: self.mode_blinking,
+            mode_4bpp: self.mode_4bpp,
+            cc_palette: self.cc_palette,
+            cc_altcolor: self.cc_altcolor,
+            cc_overscan_color: self.cc_overscan_color,
+            scanline_us: self.scanline_us,
+            frame_us: self.frame_us,
+            cursor_frames: self.cursor_frames,
+            frame_count: self.frame_count,
+            status_reads: self.status_reads,
+            cursor_status: self.cursor_status,
+            cursor_slowblink: self.cursor_slowblink,
+            cursor_blink_rate: self.cursor_blink_rate,
+            cursor_data: self.cursor_data,
+            cursor_attr: self.cursor_attr,
+            crtc_register_select_byte: self.crtc_register_select_byte,
+            crtc_register_selected: self.crtc_register_selected,
+            crtc_horizontal_total: self.crtc_horizontal_total,
+            crtc_horizontal_displayed: self.crtc_horizontal_displayed,
+            crtc_horizontal_sync_pos: self.crtc_horizontal_sync_pos,
+            crtc_sync_width: self.crtc_sync_width,
+            crtc_vertical_total: self.crtc_vertical_total,
+            crtc_vertical_total_adjust: self.crtc_vertical_total_adjust,
+            crtc_vertical_displayed: self.crtc_vertical_displayed,
+            crtc_vertical_sync_pos: self.crtc_vertical_sync_pos,
+            crtc_interlace_mode: self.crtc_interlace_mode,
+            crtc_maximum_scanline_address: self.crtc_maximum_scanline_address,
+            crtc_cursor_start_line: self.crtc_cursor_start_line,
+            crtc_cursor_end_line: self.crtc_cursor_end_line,
+            crtc_start_address: self.crtc_start_address,
+            crtc_start_address_ho: self.crtc_start_address_ho,
+            crtc_start_address_lo: self.crtc_start_address_lo,
+            crtc_cursor_address_lo: self.crtc_cursor_address_lo,
+            crtc_cursor_address_ho: self.crtc_cursor_address_ho,
+            crtc_cursor_address: self.crtc_cursor_address,
+            crtc_frame_address: self.crtc_frame_address,
+            in_crtc_hblank: self.in_crtc_hblank,
+            in_crtc_vblank: self.in_crtc_vblank,
+            in_crtc_vsync: self.in_crtc_vsync,
+            in_last_vblank_line: self.in_last_vblank_line,
+            hborder: self.hborder,
+            vborder: self.vborder,
+            cc_register: self.cc_register,
+            clock_divisor: self.clock_divisor,
+            char_clock: self.char_clock,
+            char_clock_mask: self.char_clock_mask,
+            char_clock_odd_mask: self.char_clock_odd_mask,
+            beam_x: self.beam_x,
+            beam_y: self.beam_y,
+            in_monitor_hsync: self.in_monitor_hsync,
+            in_monitor_vblank: self.in_monitor_vblank,
+            monitor_hsc: self.monitor_hsc,
+            scanline: self.scanline,
+            missed_hsyncs: self.missed_hsyncs,
+            overscan_left: self.overscan_left,
+            overscan_right_start: self.overscan_right_start,
+            overscan_right: self.overscan_right,
+            vsync_len: self.vsync_len,
+            in_display_area: self.in_display_area,
+            cur_char: self.cur_char,
+            cur_attr: self.cur_attr,
+            cur_fg: self.cur_fg,
+            cur_bg: self.cur_bg,
+            cur_blink: self.cur_blink,
+            char_col: self.char_col,
+            hcc_c0: self.hcc_c0,
+            vlc_c9: self.vlc_c9,
+            vcc_c4: self.vcc_c4,
+            last_row: self.last_row,
+            vsc_c3h: self.vsc_c3h,
+            hsc_c3l: self.hsc_c3l,
+            vtac_c5: self.vtac_c5,
+            in_vta: self.in_vta,
+            effective_vta: self.effective_vta,
+            vma: self.vma,
+            vma_t: self.vma_t,
+            vmws: self.vmws,
+            rba: self.rba,
+            blink_state: self.blink_state,
+            blink_accum_us: self.blink_accum_us,
+            blink_accum_clocks: self.blink_accum_clocks,
+            accumulated_us: self.accumulated_us,
+            ticks_advanced: self.ticks_advanced,
+            pixel_clocks_owed: self.pixel_clocks_owed,
+            ticks_accum: self.ticks_accum,
+            clocks_accum: self.clocks_accum,
+            lightpen_latch: self.lightpen_latch,
+            lightpen_addr: self.lightpen_addr,
+            do_vsync: self.do_vsync,
+            intr: self.intr,
+            last_intr: self.last_intr,
+            intr_enabled: self.intr_enabled,
+            video_array_address: self.video_array_address,
+            palette_mask: self.palette_mask,
+            border_color: self.border_color,
+            t_mode_control: self.t_mode_control.into_bytes()[0],
+            jr_mode_control: self.jr_mode_control.into_bytes()[0],
+            jr_mode_control2: self.jr_mode_control2.into_bytes()[0],
+            mode_size: self.mode_size,
+            palette_registers: self.palette_registers,
+            page_register: self.page_register.into_bytes()[0],
+            cpu_page_offset: self.cpu_page_offset,
+            crt_page_offset: self.crt_page_offset,
+            page_size: self.page_size,
+            address_flipflop: self.address_flipflop,
+            a0: self.a0,
+            aperture_base: self.aperture_base,
+        }
+    }
+
+    fn restore_state(&mut self, state: TgaSaveState) {
+        self.cycles = state.cycles;
+        self.last_vsync_cycles = state.last_vsync_cycles;
+        self.cur_screen_cycles = state.cur_screen_cycles;
+        self.cycles_per_vsync = state.cycles_per_vsync;
+        self.sink_cycles = state.sink_cycles;
+        self.catching_up = state.catching_up;
+        self.last_rw_tick = state.last_rw_tick;
+        self.slot_idx = state.slot_idx;
+        self.dirty_snow = state.dirty_snow;
+        self.snow_char = state.snow_char;
+        self.last_bus_value = state.last_bus_value;
+        self.last_bus_addr = state.last_bus_addr;
+        self.snow_count = state.snow_count;
+        self.mode_pending = state.mode_pending;
+        self.clock_pending = state.clock_pending;
+        self.mode_byte = state.mode_byte;
+        self.display_mode = state.display_mode;
+        self.mode_enable = state.mode_enable;
+        self.mode_graphics = state.mode_graphics;
+        self.mode_bw = state.mode_bw;
+        self.mode_bandwidth = state.mode_bandwidth;
+        self.mode_hires_gfx = state.mode_hires_gfx;
+        self.mode_hires_txt = state.mode_hires_txt;
+        self.mode_blinking = state.mode_blinking;
+        self.mode_4bpp = state.mode_4bpp;
+        self.cc_palette = state.cc_palette;
+        self.cc_altcolor = state.cc_altcolor;
+        self.cc_overscan_color = state.cc_overscan_color;
+        self.scanline_us = state.scanline_us;
+        self.frame_us = state.frame_us;
+        self.cursor_frames = state.cursor_frames;
+        self.frame_count = state.frame_count;
+        self.status_reads = state.status_reads;
+        self.cursor_status = state.cursor_status;
+        self.cursor_slowblink = state.cursor_slowblink;
+        self.cursor_blink_rate = state.cursor_blink_rate;
+        self.cursor_data = state.cursor_data;
+        self.cursor_attr = state.cursor_attr;
+        self.crtc_register_select_byte = state.crtc_register_select_byte;
+        self.crtc_register_selected = state.crtc_register_selected;
+        self.crtc_horizontal_total = state.crtc_horizontal_total;
+        self.crtc_horizontal_displayed = state.crtc_horizontal_displayed;
+        self.crtc_horizontal_sync_pos = state.crtc_horizontal_sync_pos;
+        self.crtc_sync_width = state.crtc_sync_width;
+        self.crtc_vertical_total = state.crtc_vertical_total;
+        self.crtc_vertical_total_adjust = state.crtc_vertical_total_adjust;
+        self.crtc_vertical_displayed = state.crtc_vertical_displayed;
+        self.crtc_vertical_sync_pos = state.crtc_vertical_sync_pos;
+        self.crtc_interlace_mode = state.crtc_interlace_mode;
+        self.crtc_maximum_scanline_address = state.crtc_maximum_scanline_address;
+        self.crtc_cursor_start_line = state.crtc_cursor_start_line;
+        self.crtc_cursor_end_line = state.crtc_cursor_end_line;
+        self.crtc_start_address = state.crtc_start_address;
+        self.crtc_start_address_ho = state.crtc_start_address_ho;
+        self.crtc_start_address_lo = state.crtc_start_address_lo;
+        self.crtc_cursor_address_lo = state.crtc_cursor_address_lo;
+        self.crtc_cursor_address_ho = state.crtc_cursor_address_ho;
+        self.crtc_cursor_address = state.crtc_cursor_address;
+        self.crtc_frame_address = state.crtc_frame_address;
+        self.in_crtc_hblank = state.in_crtc_hblank;
+        self.in_crtc_vblank = state.in_crtc_vblank;
+        self.in_crtc_vsync = state.in_crtc_vsync;
+        self.in_last_vblank_line = state.in_last_vblank_line;
+        self.hborder = state.hborder;
+        self.vborder = state.vborder;
+        self.cc_register = state.cc_register;
+        self.clock_divisor = state.clock_divisor;
+        self.char_clock = state.char_clock;
+        self.char_clock_mask = state.char_clock_mask;
+        self.char_clock_odd_mask = state.char_clock_odd_mask;
+        self.beam_x = state.beam_x;
+        self.beam_y = state.beam_y;
+        self.in_monitor_hsync = state.in_monitor_hsync;
+        self.in_monitor_vblank = state.in_monitor_vblank;
+        self.monitor_hsc = state.monitor_hsc;
+        self.scanline = state.scanline;
+        self.missed_hsyncs = state.missed_hsyncs;
+        self.overscan_left = state.overscan_left;
+        self.overscan_right_start = state.overscan_right_start;
+        self.overscan_right = state.overscan_right;
+        self.vsync_len = state.vsync_len;
+        self.in_display_area = state.in_display_area;
+        self.cur_char = state.cur_char;
+        self.cur_attr = state.cur_attr;
+        self.cur_fg = state.cur_fg;
+        self.cur_bg = state.cur_bg;
+        self.cur_blink = state.cur_blink;
+        self.char_col = state.char_col;
+        self.hcc_c0 = state.hcc_c0;
+        self.vlc_c9 = state.vlc_c9;
+        self.vcc_c4 = state.vcc_c4;
+        self.last_row = state.last_row;
+        self.vsc_c3h = state.vsc_c3h;
+        self.hsc_c3l = state.hsc_c3l;
+        self.vtac_c5 = state.vtac_c5;
+        self.in_vta = state.in_vta;
+        self.effective_vta = state.effective_vta;
+        self.vma = state.vma;
+        self.vma_t = state.vma_t;
+        self.vmws = state.vmws;
+        self.rba = state.rba;
+        self.blink_state = state.blink_state;
+        self.blink_accum_us = state.blink_accum_us;
+        self.blink_accum_clocks = state.blink_accum_clocks;
+        self.accumulated_us = state.accumulated_us;
+        self.ticks_advanced = state.ticks_advanced;
+        self.pixel_clocks_owed = state.pixel_clocks_owed;
+        self.ticks_accum = state.ticks_accum;
+        self.clocks_accum = state.clocks_accum;
+        self.lightpen_latch = state.lightpen_latch;
+        self.lightpen_addr = state.lightpen_addr;
+        self.do_vsync = state.do_vsync;
+        self.intr = state.intr;
+        self.last_intr = state.last_intr;
+        self.intr_enabled = state.intr_enabled;
+        self.video_array_address = state.video_array_address;
+        self.palette_mask = state.palette_mask;
+        self.border_color = state.border_color;
+        self.t_mode_control = TModeControlRegister::from_bytes([state.t_mode_control]);
+        self.jr_mode_control = JrModeControlRegister::from_bytes([state.jr_mode_control]);
+        self.jr_mode_control2 = JrModeControlRegister2::from_bytes([state.jr_mode_control2]);
+        self.mode_size = state.mode_size;
+        self.palette_registers = state.palette_registers;
+        self.page_register = TPageRegister::from_bytes([state.page_register]);
+        self.cpu_page_offset = state.cpu_page_offset;
+        self.crt_page_offset = state.crt_page_offset;
+        self.page_size = state.page_size;
+        self.address_flipflop = state.address_flipflop;
+        self.a0 = state.a0;
+        self.aperture_base = state.aperture_base;
+    }
+}
+
 impl TGACard {
     pub fn new(
         subtype: VideoCardSubType,
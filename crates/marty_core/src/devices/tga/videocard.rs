@@ -141,6 +141,14 @@ impl VideoCard for TGACard {
         Some((self.beam_x, self.beam_y))
     }
 
+    fn get_raster_position(&self) -> RasterPosition {
+        RasterPosition {
+            frame: self.frame_count,
+            scanline: self.beam_y,
+            dot: self.beam_x,
+        }
+    }
+
     /// Tick the CGA the specified number of video clock cycles.
     fn debug_tick(&mut self, ticks: u32, cpumem: Option<&[u8]>) {
         match self.clock_mode {
@@ -612,4 +620,44 @@ impl VideoCard for TGACard {
                 strings*/
         Vec::new()
     }
+
+    fn get_text_buffer(&self) -> Option<TextBuffer> {
+        None
+    }
+
+    fn get_register_state(&self) -> Vec<(String, u8)> {
+        let crtc = [
+            self.crtc_horizontal_total,
+            self.crtc_horizontal_displayed,
+            self.crtc_horizontal_sync_pos,
+            self.crtc_sync_width,
+            self.crtc_vertical_total,
+            self.crtc_vertical_total_adjust,
+            self.crtc_vertical_displayed,
+            self.crtc_vertical_sync_pos,
+            self.crtc_interlace_mode,
+            self.crtc_maximum_scanline_address,
+            self.crtc_cursor_start_line,
+            self.crtc_cursor_end_line,
+            self.crtc_start_address_ho,
+            self.crtc_start_address_lo,
+            self.crtc_cursor_address_ho,
+            self.crtc_cursor_address_lo,
+        ];
+        let mut regs: Vec<(String, u8)> = MC6845_REGISTER_NAMES
+            .iter()
+            .zip(crtc)
+            .map(|(name, val)| (name.to_string(), val))
+            .collect();
+
+        regs.push(("Mode Select (3D8)".to_string(), self.mode_byte));
+        regs.push(("Color Select (3D9)".to_string(), self.cc_register));
+        regs.push(("Page Register (3DF)".to_string(), self.page_register.into_bytes()[0]));
+        regs.push(("Palette Mask".to_string(), self.palette_mask));
+        regs.push(("Border Color".to_string(), self.border_color));
+        for (i, pal) in self.palette_registers.iter().enumerate() {
+            regs.push((format!("Palette {:X}", i), *pal));
+        }
+        regs
+    }
 }
@@ -35,7 +35,7 @@ use super::*;
 pub const DAC_STATE_READ: u8 = 0;
 pub const DAC_STATE_WRITE: u8 = 0x03;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum AttributeRegister {
     Palette0,
     Palette1,
@@ -59,7 +59,7 @@ pub enum AttributeRegister {
     HorizontalPelPanning,
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum AttributeRegisterFlipFlop {
     Address,
     Data,
@@ -161,7 +161,7 @@ pub enum AttributeInput<'a> {
     Parallel64(u64, u8, bool),
 }
 
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct AttributePaletteEntry {
     pub six: u8,
     pub four: u8,
@@ -240,6 +240,99 @@ impl Default for AttributeController {
     }
 }
 
+/// The attribute controller registers, pixel shifter and DAC color registers.
+#[derive(Serialize, Deserialize)]
+pub struct AttributeControllerSaveState {
+    register_flipflop: AttributeRegisterFlipFlop,
+    register_select_byte: u8,
+    register_selected: AttributeRegister,
+    palette_registers: [AttributePaletteEntry; 16],
+    palette_index: usize,
+    mode_control: u8,
+    overscan_color: AttributePaletteEntry,
+    overscan_color64: u64,
+    color_plane_enable: u8,
+    color_plane_enable64: u64,
+    pel_panning: u8,
+    blink_state: bool,
+    last_den: bool,
+    shift_reg: u128,
+    shift_buf: [u8; 8],
+    shift_flipflop: bool,
+    color_registers: Vec<[u8; 3]>,
+    color_registers_rgba: Vec<[u8; 4]>,
+    color_pel_write_address: u8,
+    color_pel_write_address_color: u8,
+    color_pel_read_address: u8,
+    color_pel_read_address_color: u8,
+    color_pel_mask: u8,
+    color_dac_state: u8,
+}
+
+impl SaveState for AttributeController {
+    type State = AttributeControllerSaveState;
+
+    fn save_state(&self) -> AttributeControllerSaveState {
+        AttributeControllerSaveState {
+            register_flipflop: self.register_flipflop,
+            register_select_byte: self.register_select_byte,
+            register_selected: self.register_selected,
+            palette_registers: self.palette_registers,
+            palette_index: self.palette_index,
+            mode_control: self.mode_control.into_bytes()[0],
+            overscan_color: self.overscan_color,
+            overscan_color64: self.overscan_color64,
+            color_plane_enable: self.color_plane_enable.into_bytes()[0],
+            color_plane_enable64: self.color_plane_enable64,
+            pel_panning: self.pel_panning,
+            blink_state: self.blink_state,
+            last_den: self.last_den,
+            shift_reg: self.shift_reg,
+            shift_buf: self.shift_buf,
+            shift_flipflop: self.shift_flipflop,
+            color_registers: self.color_registers.to_vec(),
+            color_registers_rgba: self.color_registers_rgba.to_vec(),
+            color_pel_write_address: self.color_pel_write_address,
+            color_pel_write_address_color: self.color_pel_write_address_color,
+            color_pel_read_address: self.color_pel_read_address,
+            color_pel_read_address_color: self.color_pel_read_address_color,
+            color_pel_mask: self.color_pel_mask,
+            color_dac_state: self.color_dac_state,
+        }
+    }
+
+    fn restore_state(&mut self, state: AttributeControllerSaveState) {
+        self.register_flipflop = state.register_flipflop;
+        self.register_select_byte = state.register_select_byte;
+        self.register_selected = state.register_selected;
+        self.palette_registers = state.palette_registers;
+        self.palette_index = state.palette_index;
+        self.mode_control = AModeControl::from_bytes([state.mode_control]);
+        self.overscan_color = state.overscan_color;
+        self.overscan_color64 = state.overscan_color64;
+        self.color_plane_enable = AColorPlaneEnable::from_bytes([state.color_plane_enable]);
+        self.color_plane_enable64 = state.color_plane_enable64;
+        self.pel_panning = state.pel_panning;
+        self.blink_state = state.blink_state;
+        self.last_den = state.last_den;
+        self.shift_reg = state.shift_reg;
+        self.shift_buf = state.shift_buf;
+        self.shift_flipflop = state.shift_flipflop;
+        if state.color_registers.len() == self.color_registers.len() {
+            self.color_registers.copy_from_slice(&state.color_registers);
+        }
+        if state.color_registers_rgba.len() == self.color_registers_rgba.len() {
+            self.color_registers_rgba.copy_from_slice(&state.color_registers_rgba);
+        }
+        self.color_pel_write_address = state.color_pel_write_address;
+        self.color_pel_write_address_color = state.color_pel_write_address_color;
+        self.color_pel_read_address = state.color_pel_read_address;
+        self.color_pel_read_address_color = state.color_pel_read_address_color;
+        self.color_pel_mask = state.color_pel_mask;
+        self.color_dac_state = state.color_dac_state;
+    }
+}
+
 impl AttributeController {
     pub fn new() -> Self {
         Self::default()
@@ -682,6 +775,29 @@ impl AttributeController {
         self.color_dac_state = DAC_STATE_WRITE;
     }
 
+    /// Return the raw values of the attribute controller registers, starting with the 16 palette registers.
+    pub fn get_register_state(&self) -> Vec<(String, u8)> {
+        let mut regs: Vec<(String, u8)> = self
+            .palette_registers
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (format!("AR{:02X} Palette {:X}", i, i), entry.six))
+            .collect();
+        let control = [
+            self.mode_control.into_bytes()[0],
+            self.overscan_color.six,
+            self.color_plane_enable.into_bytes()[0],
+            self.pel_panning,
+        ];
+        regs.extend(
+            EGA_ATTRIBUTE_REGISTER_NAMES
+                .iter()
+                .zip(control)
+                .map(|(name, val)| (name.to_string(), val)),
+        );
+        regs
+    }
+
     #[rustfmt::skip]
     pub fn get_state(&self) -> Vec<(String, VideoCardStateEntry)> {
         let mut attribute_vec = Vec::new();
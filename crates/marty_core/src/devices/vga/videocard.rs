@@ -105,6 +105,14 @@ impl VideoCard for VGACard {
         Some((self.raster_x, self.raster_y))
     }
 
+    fn get_raster_position(&self) -> RasterPosition {
+        RasterPosition {
+            frame: self.frame,
+            scanline: self.raster_y,
+            dot: self.raster_x,
+        }
+    }
+
     /// Unimplemented
     fn debug_tick(&mut self, _ticks: u32, _cpumem: Option<&[u8]>) {}
 
@@ -493,4 +501,20 @@ impl VideoCard for VGACard {
     fn get_text_mode_strings(&self) -> Vec<String> {
         Vec::new()
     }
+
+    fn get_text_buffer(&self) -> Option<TextBuffer> {
+        None
+    }
+
+    fn get_register_state(&self) -> Vec<(String, u8)> {
+        let mut regs = self.crtc.get_register_state();
+        regs.extend(self.sequencer.get_register_state());
+        regs.extend(self.gc.get_register_state());
+        regs.extend(self.ac.get_register_state());
+        regs.push((
+            "Misc Output (3C2)".to_string(),
+            self.misc_output_register.into_bytes()[0],
+        ));
+        regs
+    }
 }
@@ -33,6 +33,7 @@
 #![allow(dead_code)]
 extern crate core;
 
+pub mod access_profiler;
 pub mod breakpoints;
 pub mod bus;
 pub mod bytebuf;
@@ -49,7 +50,11 @@ pub mod interrupt;
 pub mod keys;
 pub mod machine;
 pub mod machine_config;
+pub mod machine_preset;
 pub mod memerror;
+pub mod option_rom;
+pub mod rom_info;
+pub mod savestate;
 #[cfg(feature = "sound")]
 pub mod sound;
 pub mod syntax_token;
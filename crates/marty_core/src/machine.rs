@@ -39,7 +39,7 @@ use std::{
     collections::{HashMap, BTreeMap, VecDeque},
     fs::File,
     io::{BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use std::sync::{Arc, RwLock};
 use log;
@@ -51,30 +51,49 @@ use crate::sound::{SoundOutputConfig, SoundOutput, SoundSourceDescriptor};
 
 use crate::{
     breakpoints::BreakPointType,
-    bus::{BusInterface, ClockFactor, DeviceEvent, MEM_CP_BIT},
+    bus::{BusInterface, ClockFactor, DeviceEvent, MEM_BPA_BIT, MEM_CP_BIT},
     coreconfig::CoreConfig,
     cpu_808x::{Intel808x},
     cpu_common::{Cpu, CpuOption, CpuError, TraceMode},
-    device_traits::videocard::{VideoCard, VideoCardId, VideoCardInterface, VideoCardState, VideoOption},
+    device_traits::{
+        realtimeclock::RealTimeClock,
+        videocard::{VideoCard, VideoCardId, VideoCardInterface, VideoCardState, VideoOption},
+    },
     devices::{
         dma::DMAControllerStringState,
         fdc::FloppyController,
         hdc::xebec::HardDiskController,
         hdc::xtide::XtIdeController,
-        keyboard::KeyboardModifiers,
+        isa_card::IsaCard,
+        keyboard::{
+            BiosKey,
+            Keyboard,
+            KeyboardLockState,
+            KeyboardModifiers,
+            BIOS_KB_BUFFER_END,
+            BIOS_KB_BUFFER_HEAD_ADDRESS,
+            BIOS_KB_FLAG_ADDRESS,
+        },
         mouse::Mouse,
         pic::PicStringState,
+        pc_speaker_recorder::PcSpeakerRecorder,
         pit::{PitDisplayState},
+        rtc::{RtcDateTime, RTC_DETERMINISTIC_TIME},
+        post_code::{post_code_table_index, DEFAULT_POST_CODE_TABLE},
         ppi::{PpiDisplayState, PpiStringState},
         cartridge_slots::CartridgeSlot,
         serial::SerialPortDisplayState,
     },
     keys::MartyKey,
-    machine_config::{get_machine_descriptor, MachineConfiguration, MachineDescriptor},
+    machine_config::{get_machine_descriptor, normalize_conventional_memory, ExtensionRomConfig, MachineConfiguration, MachineDescriptor, RomPatchConfig},
     machine_types::{OnHaltBehavior, MachineType},
+    option_rom::{OptionRom, OptionRomStatus, OPTION_ROM_INIT_OFFSET},
+    rom_info::{self, KnownRom, RomInfo},
+    savestate::{CpuSaveState, MachineSaveState, MediaReference, SaveStateError},
     tracelogger::TraceLogger,
+    vhd::VirtualHardDisk,
 };
-use crate::cpu_common::{CpuAddress, CpuDispatch, Disassembly, format_instruction_bytes, ServiceEvent, StepResult};
+use crate::cpu_common::{CodeWrite, CpuAddress, CpuDispatch, Disassembly, format_instruction_bytes, ServiceEvent, StepResult};
 use crate::cpu_common::builder::CpuBuilder;
 use crate::devices::fdc::FdcDebugState;
 use crate::devices::floppy_drive::FloppyImageState;
@@ -82,11 +101,24 @@ use crate::devices::floppy_drive::FloppyImageState;
 use ringbuf::{Consumer};
 
 pub const STEP_OVER_TIMEOUT: u32 = 320000;
+/// Offset in segment 0 of the code that initializes ROM extensions when running without a BIOS.
+pub const EXTENSION_ROM_TRAMPOLINE: u16 = 0x0600;
+/// The number of CPU cycles to run while stepping out before giving up on the routine ever returning.
+pub const STEP_OUT_CYCLE_LIMIT: u64 = 50_000_000;
+/// The number of debugger memory changes (fills and edits) that can be undone. Each holds a snapshot
+/// of the address space.
+pub const MEMORY_UNDO_LEN: usize = 16;
 
 //pub const NUM_HDDS: u32 = 2;
 
 pub const MAX_MEMORY_ADDRESS: usize = 0xFFFFF;
 
+// The BIOS checks the reset flag word at 0040:0072 for 1234h to skip the memory test on a warm boot.
+pub const BIOS_RESET_FLAG_ADDRESS: usize = 0x472;
+pub const BIOS_RESET_FLAG_WARM: u16 = 0x1234;
+// The BIOS data area word at 0040:0013 holds the amount of conventional memory in KB.
+pub const BIOS_MEMORY_SIZE_ADDRESS: usize = 0x413;
+
 pub struct DisassemblyListingEntry {
     pub visit_count: u32,
     pub disassembly: Disassembly,
@@ -114,9 +146,18 @@ pub enum MachineState {
     Paused,
     Resuming,
     Rebooting,
+    WarmRebooting,
     Off,
 }
 
+/// The kind of reset to perform. A cold reset is a full power cycle that clears RAM, while a warm reset
+/// preserves RAM and sets the BIOS warm boot flag, like a Ctrl-Alt-Del.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResetKind {
+    Cold,
+    Warm,
+}
+
 impl MachineState {
     pub fn is_on(&self) -> bool {
         !matches!(self, MachineState::Off)
@@ -126,6 +167,8 @@ impl MachineState {
 #[derive(Copy, Clone, Debug)]
 pub enum MachineOption {
     RecordListing(bool),
+    /// Trigger access breakpoints on memory written from the debugger.
+    BreakOnDebugWrite(bool),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -133,6 +176,7 @@ pub enum ExecutionState {
     Paused,
     BreakpointHit,
     StepOverHit,
+    StepOutHit,
     Running,
     Halted,
 }
@@ -140,11 +184,23 @@ pub enum ExecutionState {
 impl ExecutionState {
     /// Can we Step from the current state?
     pub fn can_step(&self) -> bool {
-        matches!(self, ExecutionState::Paused | ExecutionState::BreakpointHit | ExecutionState::StepOverHit)
+        matches!(
+            self,
+            ExecutionState::Paused
+                | ExecutionState::BreakpointHit
+                | ExecutionState::StepOverHit
+                | ExecutionState::StepOutHit
+        )
     }
     /// Can we Run from the current state?
     pub fn can_run(&self) -> bool {
-        matches!(self, ExecutionState::Paused | ExecutionState::BreakpointHit | ExecutionState::StepOverHit)
+        matches!(
+            self,
+            ExecutionState::Paused
+                | ExecutionState::BreakpointHit
+                | ExecutionState::StepOverHit
+                | ExecutionState::StepOutHit
+        )
     }
     /// Can we Pause from the current state?
     pub fn can_pause(&self) -> bool {
@@ -159,6 +215,7 @@ pub enum ExecutionOperation {
     Pause,
     Step,
     StepOver,
+    StepOut,
     RunToNext,
     Run,
     Reset,
@@ -219,6 +276,12 @@ impl ExecutionControl {
                     self.op.set(op);
                 }
             }
+            ExecutionOperation::StepOut => {
+                // Can only Step Out if paused / breakpointhit
+                if self.state.can_step() {
+                    self.op.set(op);
+                }
+            }
             ExecutionOperation::RunToNext => {
                 // Can only RunToNext if paused / breakpointhit
                 if self.state.can_step() {
@@ -265,6 +328,8 @@ pub struct PitData {
 #[derive(Clone, Default, Debug)]
 pub struct MachineRomEntry {
     pub md5: String,
+    /// The CRC32 of the image file, calculated when it was loaded.
+    pub crc32: u32,
     pub addr: u32,
     pub data: Vec<u8>,
 }
@@ -291,11 +356,15 @@ pub struct MachineRomManifest {
     pub patches: Vec<MachinePatch>,
     pub roms: Vec<MachineRomEntry>,
     pub rom_paths: Vec<PathBuf>,
+    pub option_roms: Vec<OptionRom>,
+    /// Known-good ROM dumps to verify the loaded ROMs against.
+    pub known_roms: Vec<KnownRom>,
 }
 
 #[derive(Default, Debug)]
 pub struct MachineOptions {
     pub record_listing: bool,
+    pub break_on_debug_write: bool,
 }
 
 #[derive(Default)]
@@ -343,6 +412,70 @@ impl MachineRomManifest {
         }
         map
     }
+
+    /// Map the init entry point of each option ROM to its index in the manifest.
+    pub fn option_rom_map(&self) -> HashMap<u32, usize> {
+        let mut map = HashMap::new();
+        for (idx, rom) in self.option_roms.iter().enumerate() {
+            map.insert(rom.init_address(), idx);
+        }
+        map
+    }
+
+    pub fn option_rom_status(&self) -> Vec<OptionRomStatus> {
+        self.option_roms
+            .iter()
+            .map(|rom| OptionRomStatus {
+                name: rom.name.clone(),
+                segment: rom.segment,
+                size: rom.size(),
+                checksum_fixed: rom.checksum_fixed,
+                init_called: false,
+            })
+            .collect()
+    }
+
+    /// Gather checksums and identification for each ROM in the manifest, including option ROMs. ROMs
+    /// are verified against the manifest's known-good dumps.
+    pub fn rom_info(&self, machine_type: MachineType) -> Vec<RomInfo> {
+        let mut info: Vec<RomInfo> = self
+            .roms
+            .iter()
+            .enumerate()
+            .map(|(idx, rom)| {
+                let path = self.rom_paths.get(idx).cloned();
+                let name = path
+                    .as_ref()
+                    .and_then(|path| path.file_name())
+                    .map_or_else(|| rom.md5.clone(), |name| name.to_string_lossy().to_string());
+                RomInfo::new(
+                    name,
+                    path,
+                    rom.addr,
+                    &rom.data,
+                    rom.md5.clone(),
+                    rom.crc32,
+                    machine_type,
+                    &self.known_roms,
+                )
+            })
+            .collect();
+
+        info.extend(self.option_roms.iter().map(|rom| {
+            RomInfo::new(
+                rom.name.clone(),
+                None,
+                (rom.segment as u32) << 4,
+                &rom.data,
+                format!("{:x}", md5::compute(&rom.data)),
+                rom_info::crc32(&rom.data),
+                machine_type,
+                &self.known_roms,
+            )
+        }));
+        info.sort_by_key(|rom| rom.address);
+        info
+    }
 }
 
 #[derive(Default)]
@@ -488,17 +621,33 @@ pub struct Machine {
     turbo_button: bool,
     cpu_factor: ClockFactor,
     next_cpu_factor: ClockFactor,
+    normal_cpu_factor: ClockFactor,
+    turbo_cpu_factor: ClockFactor,
+    fast_boot: bool,
+    validating: bool,
+    sys_tick_remainder: u64,
     cpu_cycles: u64,
     cpu_instructions: u64,
     system_ticks: u64,
     checkpoint_map: HashMap<u32, usize>,
     patch_map: HashMap<u32, usize>,
+    option_rom_map: HashMap<u32, usize>,
+    option_rom_status: Vec<OptionRomStatus>,
+    rom_info: Vec<RomInfo>,
     events: Vec<MachineEvent>,
     reload_pending: bool,
+    deterministic_seed: Option<u64>,
+    cycle_overshoot: u32,
+    step_out_cycles: u64,
     halt_behavior: OnHaltBehavior,
     disassembly: Disassembly,
     disassembly_listing: BTreeMap<CpuAddress, DisassemblyListingEntry>,
     disassembly_listing_file: Option<PathBuf>,
+    code_writes: HashMap<u32, CodeWrite>,
+    memory_undo: VecDeque<Vec<u8>>,
+    debug_write_break: bool,
+    raster_breakpoints: Vec<u32>,
+    last_raster_line: Option<u32>,
 }
 
 impl Machine {
@@ -545,12 +694,11 @@ impl Machine {
         #[cfg(feature = "cpu_validator")]
         use crate::cpu_validator::ValidatorMode;
 
-        // Check the requested CPU
-        if !machine_desc.is_compatible_configuration(&machine_config) {
-            eprintln!("Specified machine configuration is incompatible with machine description.");
-            eprintln!("Have you specified incompatible hardware?");
-            std::process::exit(1);
-        };
+        // Validate the machine configuration against the machine description. This checks the requested CPU,
+        // video card compatibility, and IO and memory conflicts between installed devices.
+        if let Err(e) = machine_config.validate(&machine_desc) {
+            return Err(anyhow!("Invalid machine configuration: {}", e));
+        }
 
         // Resolve the CPU type. 
         // TODO: We should probably resolve a Machine configuration against the base machine description
@@ -647,11 +795,22 @@ impl Machine {
             if #[cfg(feature = "sound")] {
                 let install_result = cpu
                     .bus_mut()
-                    .install_devices(&machine_desc, &machine_config, &sound_config, core_config.get_terminal_port());
+                    .install_devices(
+                        &machine_desc,
+                        &machine_config,
+                        &sound_config,
+                        core_config.get_terminal_port(),
+                        core_config.get_post_code_ports(),
+                    );
             } else {
                 let install_result = cpu
                     .bus_mut()
-                    .install_devices(&machine_desc, &machine_config, core_config.get_terminal_port());
+                    .install_devices(
+                        &machine_desc,
+                        &machine_config,
+                        core_config.get_terminal_port(),
+                        core_config.get_post_code_ports(),
+                    );
             }
         }
 
@@ -699,6 +858,10 @@ impl Machine {
         if !core_config.get_machine_noroms() {
             Machine::install_roms(cpu.bus_mut(), &rom_manifest);
 
+            // Apply ROM patches from the machine configuration. A patch that doesn't match the loaded ROM is fatal,
+            // as it was likely written for a different BIOS version.
+            Machine::apply_rom_patches(cpu.bus_mut(), &machine_config.rom_patches)?;
+
             // Load checkpoint flags into memory
             cpu.bus_mut().install_checkpoints(&rom_manifest.checkpoints);
 
@@ -712,24 +875,53 @@ impl Machine {
             //cpu.set_reset_vector(CpuAddress::Segmented(rom_entry_point.0, rom_entry_point.1));
         }
 
-        // Set CPU clock divisor/multiplier
-        let cpu_factor = if core_config.get_machine_turbo() {
-            machine_desc.cpu_turbo_factor
+        // Load ROM extensions from the filesystem. These are loaded even without a BIOS, in which case
+        // we simulate the option ROM scan the BIOS would perform during POST.
+        Machine::install_extension_roms(cpu.bus_mut(), &machine_config.extension_roms);
+
+        // Install ROM hooks. These are handled by the CPU and persist across resets.
+        cpu.set_rom_hooks(machine_config.rom_hooks.iter().map(|hook| hook.to_hook()).collect());
+
+        // Set CPU clock divisor/multiplier. A machine configuration may specify its own CPU clocks,
+        // otherwise we use the clock factors of the base machine.
+        let (normal_cpu_factor, turbo_cpu_factor) = machine_config.cpu_factors(&machine_desc);
+        log::debug!(
+            "CPU clock: {:.3}MHz, turbo: {:.3}MHz",
+            normal_cpu_factor.cpu_mhz(machine_desc.system_crystal),
+            turbo_cpu_factor.cpu_mhz(machine_desc.system_crystal)
+        );
+        let turbo_button = core_config.get_machine_turbo();
+        let cpu_factor = if turbo_button {
+            turbo_cpu_factor
         } else {
-            machine_desc.cpu_factor
+            normal_cpu_factor
         };
 
         cpu.emit_header();
         cpu.reset();
 
         let checkpoint_map = rom_manifest.checkpoint_map();
+        let option_rom_map = rom_manifest.option_rom_map();
+        let option_rom_status = rom_manifest.option_rom_status();
+        let rom_info = rom_manifest.rom_info(machine_type);
 
         let mut patch_map = HashMap::new();
         if core_config.get_patch_enabled() {
             patch_map = rom_manifest.patch_map();
         }
 
-        Ok(Machine {
+        // Fast boot changes what the BIOS executes during POST, so it is never used while validating
+        // the CPU against real hardware.
+        #[cfg(feature = "cpu_validator")]
+        let validating = !matches!(
+            core_config.get_validator_type().unwrap_or_default(),
+            crate::cpu_validator::ValidatorType::None
+        );
+        #[cfg(not(feature = "cpu_validator"))]
+        let validating = false;
+        let fast_boot = core_config.get_machine_fast_boot() && !validating;
+
+        let mut machine = Machine {
             machine_type,
             machine_desc,
             machine_config,
@@ -748,21 +940,45 @@ impl Machine {
             error: false,
             error_str: None,
             turbo_bit: false,
-            turbo_button: false,
+            turbo_button,
             cpu_factor,
             next_cpu_factor: cpu_factor,
+            normal_cpu_factor,
+            turbo_cpu_factor,
+            fast_boot,
+            validating,
+            sys_tick_remainder: 0,
             cpu_cycles: 0,
             cpu_instructions: 0,
             system_ticks: 0,
             checkpoint_map,
             patch_map,
+            option_rom_map,
+            option_rom_status,
+            rom_info,
             events: Vec::new(),
             reload_pending: false,
+            deterministic_seed: None,
+            cycle_overshoot: 0,
+            step_out_cycles: 0,
             halt_behavior: core_config.get_halt_behavior(),
             disassembly: Disassembly::default(),
             disassembly_listing: BTreeMap::new(),
             disassembly_listing_file,
-        })
+            code_writes: HashMap::new(),
+            memory_undo: VecDeque::new(),
+            debug_write_break: false,
+            raster_breakpoints: Vec::new(),
+            last_raster_line: None,
+        };
+
+        if !machine.load_bios {
+            machine.simulate_extension_rom_post();
+        }
+        else if machine.fast_boot {
+            machine.prepare_fast_boot();
+        }
+        Ok(machine)
     }
 
     #[cfg(feature = "sound")]
@@ -793,12 +1009,16 @@ impl Machine {
                     }
                 }
             }
+            MachineOption::BreakOnDebugWrite(state) => {
+                self.options.break_on_debug_write = state;
+            }
         }
     }
 
     pub fn get_option(&self, opt: MachineOption) -> MachineOption {
         match opt {
             MachineOption::RecordListing(_) => MachineOption::RecordListing(self.options.record_listing),
+            MachineOption::BreakOnDebugWrite(_) => MachineOption::BreakOnDebugWrite(self.options.break_on_debug_write),
         }
     }
 
@@ -813,6 +1033,108 @@ impl Machine {
                 }
             }
         }
+        Machine::install_option_roms(bus, rom_manifest);
+    }
+
+    /// Apply the specified ROM patches, verifying that the bytes to be replaced match each patch's original bytes.
+    pub fn apply_rom_patches(bus: &mut BusInterface, patches: &[RomPatchConfig]) -> Result<(), Error> {
+        for patch in patches.iter() {
+            let address = patch.address();
+            let found = (0..patch.original.len())
+                .map(|i| bus.peek_u8(address as usize + i).unwrap_or(0xFF))
+                .collect::<Vec<u8>>();
+            if found != patch.original {
+                return Err(anyhow!(
+                    "ROM patch at {:04X}:{:04X} expected bytes {:02X?}, but found {:02X?}",
+                    patch.segment,
+                    patch.offset,
+                    patch.original,
+                    found
+                ));
+            }
+            let mut machine_patch = MachinePatch {
+                desc: format!("config patch at {:04X}:{:04X}", patch.segment, patch.offset),
+                trigger: address,
+                addr: address,
+                bytes: patch.replacement.clone(),
+                installed: false,
+            };
+            bus.install_patch(&mut machine_patch);
+            log::debug!("Applied {}", machine_patch.desc);
+        }
+        Ok(())
+    }
+
+    /// Map option ROMs read-only and flag their init entry points so we can tell when the BIOS calls them.
+    pub fn install_option_roms(bus: &mut BusInterface, rom_manifest: &MachineRomManifest) {
+        for rom in rom_manifest.option_roms.iter() {
+            match bus.copy_from(&rom.data, rom.address() as usize, 0, true) {
+                Ok(_) => {
+                    log::debug!("Mounted option rom {} at segment {:04X}", rom.name, rom.segment);
+                    bus.set_flags(rom.init_address() as usize, MEM_CP_BIT);
+                }
+                Err(e) => {
+                    log::debug!("Failed to mount option rom {} at segment {:04X}: {}", rom.name, rom.segment, e);
+                }
+            }
+        }
+    }
+
+    /// Map ROM extension images from the filesystem. A ROM that fails to load is skipped.
+    pub fn install_extension_roms(bus: &mut BusInterface, extension_roms: &[ExtensionRomConfig]) {
+        for rom in extension_roms.iter() {
+            if let Err(e) = bus.load_extension_rom(&rom.path, rom.base_address) {
+                log::error!("Failed to load extension ROM: {}", e);
+            }
+        }
+    }
+
+    /// Without a BIOS, nothing would initialize ROM extensions, so simulate the option ROM scan performed
+    /// during POST. A far call to each ROM found is assembled into a trampoline in RAM that finally jumps to
+    /// the CPU's reset vector, and the CPU is reset to run it.
+    fn simulate_extension_rom_post(&mut self) {
+        let roms = self.cpu.bus().scan_extension_roms();
+        if roms.is_empty() {
+            return;
+        }
+
+        // Set up a stack where the IBM BIOS keeps it during POST.
+        let mut trampoline = vec![
+            0xFA, // CLI
+            0xB8, 0x30, 0x00, // MOV AX, 0030h
+            0x8E, 0xD0, // MOV SS, AX
+            0xBC, 0x00, 0x01, // MOV SP, 0100h
+        ];
+        for address in roms {
+            Machine::init_extension_rom(&mut trampoline, address);
+        }
+
+        let reset_vector = self.cpu.get_reset_vector();
+        if let CpuAddress::Segmented(segment, offset) = reset_vector {
+            // JMP FAR segment:offset
+            trampoline.push(0xEA);
+            trampoline.extend_from_slice(&offset.to_le_bytes());
+            trampoline.extend_from_slice(&segment.to_le_bytes());
+        }
+
+        if self.cpu.bus_mut().patch_from(&trampoline, EXTENSION_ROM_TRAMPOLINE as usize).is_err() {
+            log::error!("Failed to install extension ROM trampoline");
+            return;
+        }
+        self.cpu.set_reset_vector(CpuAddress::Segmented(0, EXTENSION_ROM_TRAMPOLINE));
+        self.cpu.reset();
+        self.cpu.set_reset_vector(reset_vector);
+    }
+
+    /// Emit a far call to the initialization routine of the ROM extension at 'address', as the BIOS
+    /// would when it finds a ROM's signature.
+    fn init_extension_rom(trampoline: &mut Vec<u8>, address: u32) {
+        let segment = (address >> 4) as u16;
+        log::debug!("Initializing extension ROM at {:04X}:{:04X}", segment, OPTION_ROM_INIT_OFFSET);
+        // CALL FAR segment:0003
+        trampoline.push(0x9A);
+        trampoline.extend_from_slice(&(OPTION_ROM_INIT_OFFSET as u16).to_le_bytes());
+        trampoline.extend_from_slice(&segment.to_le_bytes());
     }
 
     pub fn reinstall_roms(&mut self, rom_manifest: MachineRomManifest) -> Result<(), Error> {
@@ -827,7 +1149,12 @@ impl Machine {
                 }
             }
         }
+        Machine::install_option_roms(self.cpu.bus_mut(), &rom_manifest);
+        Machine::apply_rom_patches(self.cpu.bus_mut(), &self.machine_config.rom_patches)?;
 
+        self.option_rom_map = rom_manifest.option_rom_map();
+        self.option_rom_status = rom_manifest.option_rom_status();
+        self.rom_info = rom_manifest.rom_info(self.machine_type);
         self.rom_manifest = rom_manifest;
         // Allow machine to run again
         self.reload_pending = false;
@@ -842,16 +1169,24 @@ impl Machine {
             }
             (MachineState::On, MachineState::Off) => {
                 log::debug!("Turning machine off...");
-                self.reset();
+                self.reset(ResetKind::Cold);
                 self.state = new_state;
             }
             (MachineState::On, MachineState::Rebooting) => {
                 log::debug!("Rebooting machine...");
-                self.reset();
+                self.reset(ResetKind::Cold);
+                self.state = MachineState::On;
+            }
+            (MachineState::On, MachineState::WarmRebooting) => {
+                log::debug!("Warm rebooting machine...");
+                self.reset(ResetKind::Warm);
                 self.state = MachineState::On;
             }
             (MachineState::On, MachineState::Paused) => {
                 log::debug!("Pausing machine...");
+                if let Some(rtc) = self.cpu.bus_mut().rtc_mut() {
+                    rtc.freeze();
+                }
                 self.state = new_state;
             }
             (MachineState::Paused, MachineState::Resuming) => {
@@ -883,6 +1218,10 @@ impl Machine {
             .set_reset_vector(CpuAddress::Segmented(vreset_seg, vreset_ofs));
         self.cpu.reset();
 
+        if !self.load_bios {
+            self.simulate_extension_rom_post();
+        }
+
         //self.cpu.set_end_address(((location as usize) + program.len()) & 0xFFFFF);
 
         Ok(())
@@ -896,6 +1235,11 @@ impl Machine {
         self.cpu.bus_mut()
     }
 
+    /// Insert an ISA card into the specified slot, connecting it to the bus and the primary PIC.
+    pub fn insert_card(&mut self, slot: usize, card: Box<dyn IsaCard>) -> Result<(), Error> {
+        self.cpu.bus_mut().insert_isa_card(slot, card)
+    }
+
     pub fn video_buffer_mut(&mut self, _vid: VideoCardId) -> Option<&mut u8> {
         None
     }
@@ -943,9 +1287,135 @@ impl Machine {
 
     /// Set a CPU option. Avoids needing to borrow CPU.
     pub fn set_cpu_option(&mut self, opt: CpuOption) {
+        if let CpuOption::Deterministic(seed) = opt {
+            self.deterministic_seed = Some(seed);
+            self.cycle_overshoot = 0;
+            // The host clock would make runs differ, so start the RTC at a fixed time.
+            let fixed_time = self.machine_config.rtc.as_ref().and_then(|rtc| rtc.fixed_time);
+            if let Some(rtc) = self.cpu.bus_mut().rtc_mut() {
+                rtc.set_time(RtcDateTime::from_unix(fixed_time.unwrap_or(RTC_DETERMINISTIC_TIME)));
+                rtc.set_host_clock(false);
+            }
+        }
         self.cpu.set_option(opt);
     }
 
+    /// Return the RNG seed if the machine is running in deterministic mode.
+    pub fn deterministic_seed(&self) -> Option<u64> {
+        self.deterministic_seed
+    }
+
+    /// Serialize the machine state into a compressed save state. Save states are taken between
+    /// instructions, so this should only be called between calls to run().
+    /// Saving is refused if the machine has a device that does not support save states, or if the
+    /// guest has written to a mounted disk, as disk contents are not part of the save state.
+    pub fn save_state(&mut self) -> Result<Vec<u8>, SaveStateError> {
+        self.check_save_state_support()?;
+
+        let cpu = CpuSaveState::capture(&mut self.cpu);
+        let devices = self.cpu.bus().save_device_state();
+        let media = self.floppy_media_references();
+
+        let state = MachineSaveState {
+            machine_type: self.machine_type,
+            cpu_type: self.cpu.get_type(),
+            cpu,
+            devices,
+            media,
+            memory: self.cpu.bus().memory_snapshot(),
+        };
+        state.write()
+    }
+
+    /// Restore the machine from a save state produced by save_state(). The machine and CPU type,
+    /// address space size, installed devices and inserted floppy media must match those present when
+    /// the state was saved.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), SaveStateError> {
+        let state = MachineSaveState::read(data)?;
+
+        if state.machine_type != self.machine_type {
+            return Err(SaveStateError::MachineMismatch(state.machine_type, self.machine_type));
+        }
+        if state.cpu_type != self.cpu.get_type() {
+            return Err(SaveStateError::CpuMismatch(state.cpu_type, self.cpu.get_type()));
+        }
+        let mem_size = self.cpu.bus().size();
+        if state.memory.len() != mem_size {
+            return Err(SaveStateError::MemorySizeMismatch(state.memory.len(), mem_size));
+        }
+
+        self.check_save_state_support()?;
+        self.cpu.bus().verify_device_state(&state.devices)?;
+
+        let current_media = self.floppy_media_references();
+        for saved in state.media.iter() {
+            let current = current_media.iter().find(|m| m.drive == saved.drive);
+            match (saved.hash, current) {
+                (_, None) => return Err(SaveStateError::MediaMismatch(saved.drive)),
+                (Some(hash), Some(current)) if current.hash != Some(hash) => {
+                    return Err(SaveStateError::MediaMismatch(saved.drive));
+                }
+                (None, Some(_)) => {
+                    log::warn!("load_state(): unable to verify disk in drive {}", saved.drive);
+                }
+                _ => {}
+            }
+        }
+        // The drive state is restored along with the controller, so a disk inserted since the
+        // state was saved would be left in a drive that believes it is empty.
+        if let Some(current) = current_media
+            .iter()
+            .find(|current| !state.media.iter().any(|m| m.drive == current.drive))
+        {
+            return Err(SaveStateError::MediaMismatch(current.drive));
+        }
+
+        self.cpu.bus_mut().restore_memory(&state.memory);
+        self.cpu.bus_mut().restore_device_state(state.devices);
+        self.memory_undo.clear();
+
+        state.cpu.apply(&mut self.cpu);
+
+        self.error = false;
+        self.error_str = None;
+        self.cycle_overshoot = 0;
+        Ok(())
+    }
+
+    /// Check that the machine's current state can be saved or replaced by a save state: no
+    /// mounted disk may have been written to, as disk contents are not part of the save state.
+    fn check_save_state_support(&self) -> Result<(), SaveStateError> {
+        if let Some(media_ref) = self.floppy_media_references().iter().find(|m| m.dirty) {
+            return Err(SaveStateError::FloppyModified(media_ref.drive));
+        }
+        if let Some(hdc) = self.cpu.bus().hdc() {
+            let modified = (0..hdc.drive_ct()).find(|&i| hdc.vhd(i).is_some_and(|vhd| vhd.write_ct() > 0));
+            if let Some(drive) = modified {
+                return Err(SaveStateError::HardDiskModified(drive));
+            }
+        }
+        if let Some(xtide) = self.cpu.bus().xtide() {
+            let modified = (0..xtide.drive_ct()).find(|&i| xtide.vhd(i).is_some_and(|vhd| vhd.write_ct() > 0));
+            if let Some(drive) = modified {
+                return Err(SaveStateError::HardDiskModified(drive));
+            }
+        }
+        Ok(())
+    }
+
+    fn floppy_media_references(&self) -> Vec<MediaReference> {
+        self.cpu
+            .bus()
+            .fdc()
+            .as_ref()
+            .map(|fdc| {
+                (0..fdc.drive_ct())
+                    .filter_map(|i| fdc.drive(i).media_reference())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Get a CPU option. Avoids needing to borrow CPU.
     pub fn get_cpu_option(&mut self, opt: CpuOption) -> bool {
         self.cpu.get_option(opt)
@@ -959,6 +1429,55 @@ impl Machine {
         }
     }
 
+    /// Send the specified video option to the videocard device with the specified id.
+    pub fn set_card_video_option(&mut self, vid: &VideoCardId, opt: VideoOption) {
+        if let Some(video) = self.cpu.bus_mut().video_mut(vid) {
+            video.set_video_option(opt);
+        }
+    }
+
+    /// Start recording the PC speaker at the specified sample rate, replacing any recording in
+    /// progress. Returns false if the machine has no PIT.
+    pub fn start_speaker_recording(&mut self, sample_rate: u32) -> bool {
+        if let Some(pit) = self.cpu.bus_mut().pit_mut() {
+            pit.set_speaker_recorder(Some(PcSpeakerRecorder::new(sample_rate)));
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Stop recording the PC speaker, returning any samples not yet retrieved with
+    /// get_speaker_samples(), or None if no recording was in progress.
+    pub fn stop_speaker_recording(&mut self) -> Option<Vec<i16>> {
+        self.cpu
+            .bus_mut()
+            .pit_mut()
+            .as_mut()
+            .and_then(|pit| pit.set_speaker_recorder(None))
+            .map(|mut recorder| recorder.get_samples())
+    }
+
+    pub fn is_recording_speaker(&mut self) -> bool {
+        self.cpu
+            .bus_mut()
+            .pit_mut()
+            .as_mut()
+            .is_some_and(|pit| pit.speaker_recorder_mut().is_some())
+    }
+
+    /// Retrieve the PC speaker samples recorded since the last call.
+    pub fn get_speaker_samples(&mut self) -> Vec<i16> {
+        self.cpu
+            .bus_mut()
+            .pit_mut()
+            .as_mut()
+            .and_then(|pit| pit.speaker_recorder_mut())
+            .map(|recorder| recorder.get_samples())
+            .unwrap_or_default()
+    }
+
     //noinspection ALL
     /// Flush all trace logs for devices that have one
     pub fn flush_trace_logs(&mut self) {
@@ -973,10 +1492,12 @@ impl Machine {
     /// CPU speed is always some factor of the main system crystal frequency.
     /// The CPU itself has no concept of its operational frequency.
     pub fn get_cpu_mhz(&self) -> f64 {
-        match self.cpu_factor {
-            ClockFactor::Divisor(n) => self.machine_desc.system_crystal / (n as f64),
-            ClockFactor::Multiplier(n) => self.machine_desc.system_crystal * (n as f64),
-        }
+        self.cpu_factor.cpu_mhz(self.machine_desc.system_crystal)
+    }
+
+    /// Return the state of the turbo button.
+    pub fn get_turbo_mode(&self) -> bool {
+        self.turbo_button
     }
 
     /// Set the specified state of the turbo button. True will enable turbo mode
@@ -987,9 +1508,9 @@ impl Machine {
     pub fn set_turbo_mode(&mut self, state: bool) {
         self.turbo_button = state;
         if state {
-            self.next_cpu_factor = self.machine_desc.cpu_turbo_factor;
+            self.next_cpu_factor = self.turbo_cpu_factor;
         } else {
-            self.next_cpu_factor = self.machine_desc.cpu_factor;
+            self.next_cpu_factor = self.normal_cpu_factor;
         }
         log::debug!(
             "Set turbo button to: {} New cpu factor is {:?}",
@@ -998,6 +1519,40 @@ impl Machine {
         );
     }
 
+    /// Return whether fast boot is enabled.
+    pub fn get_fast_boot(&self) -> bool {
+        self.fast_boot
+    }
+
+    /// Enable or disable fast boot. This takes effect on the next cold boot. Fast boot can't be
+    /// enabled while validating the CPU.
+    pub fn set_fast_boot(&mut self, state: bool) {
+        if state && self.validating {
+            log::warn!("Fast boot is unavailable while validating the CPU.");
+            return;
+        }
+        self.fast_boot = state;
+        log::debug!("Set fast boot to: {}", state);
+    }
+
+    /// Prepare RAM so that the BIOS skips its POST memory test. We record the memory size in the
+    /// BIOS data area and set the warm boot flag, which tells the BIOS that RAM is already known
+    /// to be good. The BIOS performs the rest of POST normally, so nothing changes once it hands
+    /// off to the boot loader.
+    fn prepare_fast_boot(&mut self) {
+        let memory_kb = normalize_conventional_memory(&self.machine_config)
+            .map(|size| (size / 1024) as u16)
+            .unwrap_or_default();
+        let bus = self.cpu.bus_mut();
+        if let Err(e) = bus
+            .write_u16(BIOS_MEMORY_SIZE_ADDRESS, memory_kb, 0)
+            .and_then(|_| bus.write_u16(BIOS_RESET_FLAG_ADDRESS, BIOS_RESET_FLAG_WARM, 0))
+        {
+            log::error!("Failed to prepare fast boot: {:?}", e);
+        }
+        log::debug!("Fast boot: skipping POST memory test for {}KB of RAM", memory_kb);
+    }
+
     pub fn fdc(&mut self) -> &mut Option<FloppyController> {
         self.cpu.bus_mut().fdc_mut()
     }
@@ -1010,6 +1565,16 @@ impl Machine {
         self.cpu.bus_mut().xtide_mut()
     }
 
+    /// Return the VHD mounted in the specified drive of whichever hard disk controller is present.
+    pub fn vhd_mut(&mut self, drive_idx: usize) -> Option<&mut VirtualHardDisk> {
+        if self.hdc_mut().is_some() {
+            self.hdc_mut().as_mut().and_then(|hdc| hdc.vhd_mut(drive_idx))
+        }
+        else {
+            self.xtide_mut().as_mut().and_then(|xtide| xtide.vhd_mut(drive_idx))
+        }
+    }
+
     pub fn cart_slot(&mut self) -> &mut Option<CartridgeSlot> { self.cpu.bus_mut().cart_slot_mut() }
 
     pub fn cpu_cycles(&self) -> u64 {
@@ -1083,6 +1648,88 @@ impl Machine {
         self.cpu.set_nmi(state);
     }
 
+    /// Fill `len` bytes of memory starting at the flat address `start` with `value`. Read-only memory is
+    /// skipped. The fill can be undone with undo_memory_change().
+    /// Returns the number of bytes written and the number skipped.
+    pub fn fill_memory(&mut self, start: u32, len: u32, value: u8) -> (usize, usize) {
+        let snapshot = self.cpu.bus().memory_snapshot();
+        let (written, skipped) = self.cpu.bus_mut().fill_u8(start as usize, len as usize, value);
+        if written > 0 {
+            self.push_memory_undo(snapshot);
+        }
+        (written, skipped)
+    }
+
+    /// Write `data` to memory starting at the flat address `start` on behalf of the debugger. ROM is
+    /// only written if `force` is set. The edit can be undone with undo_memory_change().
+    /// Returns the number of bytes written and the number rejected.
+    pub fn edit_memory(&mut self, start: u32, data: &[u8], force: bool) -> (usize, usize) {
+        let snapshot = self.cpu.bus().memory_snapshot();
+        let mut written = 0;
+        for (i, byte) in data.iter().enumerate() {
+            let address = (start as usize + i) & MAX_MEMORY_ADDRESS;
+            match self.cpu.bus_mut().debug_write_u8(address, *byte, force) {
+                Some(write) => {
+                    log::debug!(
+                        "Memory edit at {:05X}: {:02X} -> {:02X}",
+                        write.address,
+                        write.old,
+                        write.new
+                    );
+                    if self.options.break_on_debug_write && self.cpu.bus().get_flags(address) & MEM_BPA_BIT != 0 {
+                        self.debug_write_break = true;
+                    }
+                    written += 1;
+                }
+                None => log::debug!("Memory edit at {:05X} rejected: read-only or unpopulated", address),
+            }
+        }
+
+        if written > 0 {
+            self.push_memory_undo(snapshot);
+        }
+        (written, data.len() - written)
+    }
+
+    fn push_memory_undo(&mut self, snapshot: Vec<u8>) {
+        if self.memory_undo.len() == MEMORY_UNDO_LEN {
+            self.memory_undo.pop_front();
+        }
+        self.memory_undo.push_back(snapshot);
+    }
+
+    /// Undo the most recent fill_memory() or edit_memory() by restoring the memory snapshot taken
+    /// before it. Any writes made by the CPU since then are reverted as well.
+    /// Returns false if there is nothing to undo.
+    pub fn undo_memory_change(&mut self) -> bool {
+        match self.memory_undo.pop_back() {
+            Some(snapshot) => self.cpu.bus_mut().restore_memory(&snapshot),
+            None => false,
+        }
+    }
+
+    /// Return the number of debugger memory changes that can be undone.
+    pub fn memory_undo_len(&self) -> usize {
+        self.memory_undo.len()
+    }
+
+    /// Simulate a hardware interrupt request by pulsing the specified IR line of the primary PIC.
+    /// The request is latched in the IRR and will be serviced on the next instruction boundary
+    /// if unmasked. Returns false if there is no PIC or the IRQ is out of range.
+    pub fn inject_interrupt(&mut self, irq: u8) -> bool {
+        if irq > 7 {
+            log::warn!("inject_interrupt(): IRQ {} out of range", irq);
+            return false;
+        }
+        match self.cpu.bus_mut().pic_mut() {
+            Some(pic) => {
+                pic.pulse_interrupt(irq);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn dma_state(&mut self) -> DMAControllerStringState {
         // There will always be a primary DMA, so safe to unwrap.
         // TODO: Handle secondary DMA if present.
@@ -1112,6 +1759,13 @@ impl Machine {
             .map(|video_card| video_card.get_videocard_string_state())
     }
 
+    pub fn videocard_registers(&mut self) -> Option<Vec<(String, u8)>> {
+        self.cpu
+            .bus_mut()
+            .primary_video_mut()
+            .map(|video_card| video_card.get_register_state())
+    }
+
     pub fn get_error_str(&self) -> &Option<String> {
         &self.error_str
     }
@@ -1167,6 +1821,140 @@ impl Machine {
         }
     }
 
+    /// Inject a raw make scancode into the keyboard, as if a physical key was pressed.
+    pub fn inject_keydown(&mut self, scancode: u8) {
+        if let Some(keyboard) = self.cpu.bus_mut().keyboard_mut() {
+            keyboard.inject_scancode(scancode & 0x7F);
+        }
+    }
+
+    /// Inject the break scancode corresponding to the specified make scancode into the keyboard, as if a physical
+    /// key was released.
+    pub fn inject_keyup(&mut self, scancode: u8) {
+        if let Some(keyboard) = self.cpu.bus_mut().keyboard_mut() {
+            keyboard.inject_scancode(scancode | 0x80);
+        }
+    }
+
+    /// Return the state of the lock keys. PC and XT keyboards have no lock LEDs; the lock state is kept by
+    /// the BIOS in its keyboard flag byte in the BIOS data area.
+    pub fn keyboard_lock_state(&self) -> KeyboardLockState {
+        KeyboardLockState::from_bios_flags(self.cpu.bus().peek_u8(BIOS_KB_FLAG_ADDRESS).unwrap_or(0))
+    }
+
+    /// Return the keystrokes waiting in the BIOS type-ahead buffer at 0040:001E, oldest first. MartyPC runs
+    /// the real ROM BIOS, which maintains this buffer from its INT 9 handler and services INT 16h from it;
+    /// this only inspects the result.
+    pub fn bios_key_buffer(&self) -> Vec<BiosKey> {
+        let len = BIOS_KB_BUFFER_END as usize - (BIOS_KB_BUFFER_HEAD_ADDRESS & 0xFF);
+        match self.cpu.bus().peek_range(BIOS_KB_BUFFER_HEAD_ADDRESS, len) {
+            Ok(bda) => BiosKey::from_bios_buffer(bda),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Press the lock keys required to bring the guest's lock state in line with `target`, such as the
+    /// lock state of the host keyboard. Returns true if any keys were pressed.
+    pub fn sync_keyboard_lock_state(&mut self, target: KeyboardLockState) -> bool {
+        let toggles = self.keyboard_lock_state().toggles_to(&target);
+        for scancode in toggles.iter() {
+            self.inject_keydown(*scancode);
+            self.inject_keyup(*scancode);
+        }
+        !toggles.is_empty()
+    }
+
+    /// Type the specified text by injecting a key down and key up pair for each character, holding shift
+    /// where required. Returns the number of characters that could not be translated and were skipped.
+    pub fn inject_text(&mut self, text: &str) -> usize {
+        const SCANCODE_LSHIFT: u8 = 0x2A;
+        let mut skipped = 0;
+
+        for c in text.chars() {
+            match Keyboard::ascii_to_scancode(c) {
+                Some((scancode, shifted)) => {
+                    if shifted {
+                        self.inject_keydown(SCANCODE_LSHIFT);
+                    }
+                    self.inject_keydown(scancode);
+                    self.inject_keyup(scancode);
+                    if shifted {
+                        self.inject_keyup(SCANCODE_LSHIFT);
+                    }
+                }
+                None => {
+                    log::warn!("inject_text(): No scancode translation for character {:?}", c);
+                    skipped += 1;
+                }
+            }
+        }
+        skipped
+    }
+
+    /// Begin recording scancodes from physical key presses for later playback.
+    pub fn start_key_recording(&mut self) {
+        if let Some(keyboard) = self.cpu.bus_mut().keyboard_mut() {
+            keyboard.start_recording();
+        }
+    }
+
+    /// Stop recording key presses and return the recorded scancodes.
+    pub fn stop_key_recording(&mut self) -> Vec<u8> {
+        self.cpu
+            .bus_mut()
+            .keyboard_mut()
+            .map(|keyboard| keyboard.stop_recording())
+            .unwrap_or_default()
+    }
+
+    pub fn is_key_recording(&self) -> bool {
+        self.cpu.bus().keyboard().map_or(false, |keyboard| keyboard.is_recording())
+    }
+
+    /// Replay a sequence of recorded scancodes via inject_keydown() and inject_keyup().
+    pub fn play_key_recording(&mut self, scancodes: &[u8]) {
+        for &scancode in scancodes {
+            if scancode & 0x80 != 0 {
+                self.inject_keyup(scancode);
+            }
+            else {
+                self.inject_keydown(scancode);
+            }
+        }
+    }
+
+    /// Return the log of (cycle, code) POST codes written to the monitored ports.
+    pub fn post_code_log(&self) -> Vec<(u64, u8)> {
+        self.cpu
+            .bus()
+            .post_code_monitor()
+            .map_or(Vec::new(), |monitor| monitor.log().iter().copied().collect())
+    }
+
+    pub fn clear_post_codes(&mut self) {
+        if let Some(monitor) = self.cpu.bus_mut().post_code_monitor_mut() {
+            monitor.clear();
+        }
+    }
+
+    /// Return the last POST code written, if any.
+    pub fn last_post_code(&self) -> Option<u8> {
+        self.cpu.bus().post_code_monitor().and_then(|monitor| monitor.last_code())
+    }
+
+    /// Return the index into POST_CODE_TABLES of the POST code table for this machine, as specified by the
+    /// machine configuration's 'post_code_table' key. Defaults to the "none" table, which has no descriptions; an
+    /// unknown id, including one for a table that has since been removed, falls back to it.
+    pub fn post_code_table(&self) -> usize {
+        match &self.machine_config.post_code_table {
+            Some(id) => post_code_table_index(id).unwrap_or_else(|| {
+                log::warn!("Unknown POST code table '{}', using default.", id);
+                DEFAULT_POST_CODE_TABLE
+            }),
+            None => DEFAULT_POST_CODE_TABLE,
+        }
+    }
+
     pub fn mouse_mut(&mut self) -> &mut Option<Mouse> {
         self.cpu.bus_mut().mouse_mut()
     }
@@ -1185,15 +1973,87 @@ impl Machine {
         Ok(())
     }
 
+    /// Record all bytes received on the specified serial port to a serial log at `path`.
+    pub fn start_serial_recording(&mut self, port_num: usize, path: &Path) -> Result<(), Error> {
+        let spc = self
+            .cpu
+            .bus_mut()
+            .serial_mut()
+            .as_mut()
+            .ok_or_else(|| anyhow!("No serial port controller present!"))?;
+        spc.start_recording(port_num, path)
+    }
+
+    /// Play back the serial log at `path` into the specified serial port.
+    pub fn start_serial_playback(&mut self, port_num: usize, path: &Path) -> Result<(), Error> {
+        let spc = self
+            .cpu
+            .bus_mut()
+            .serial_mut()
+            .as_mut()
+            .ok_or_else(|| anyhow!("No serial port controller present!"))?;
+        spc.start_playback(port_num, path)
+    }
+
+    /// Stop any recording or playback on the specified serial port.
+    pub fn stop_serial_capture(&mut self, port_num: usize) -> Result<(), Error> {
+        let spc = self
+            .cpu
+            .bus_mut()
+            .serial_mut()
+            .as_mut()
+            .ok_or_else(|| anyhow!("No serial port controller present!"))?;
+        spc.stop_capture(port_num)
+    }
+
+    /// Return the breakpoints currently set.
+    pub fn breakpoints(&self) -> &[BreakPointType] {
+        self.cpu.get_breakpoints()
+    }
+
     pub fn set_breakpoints(&mut self, bp_list: Vec<BreakPointType>) {
+        // Raster breakpoints are evaluated against the primary video card, not the CPU.
+        self.raster_breakpoints = bp_list
+            .iter()
+            .filter_map(|bp| match bp {
+                BreakPointType::RasterLine(line) => Some(*line as u32),
+                _ => None,
+            })
+            .collect();
+        self.last_raster_line = None;
         self.cpu.set_breakpoints(bp_list)
     }
 
+    /// Return true if the primary video card's raster beam has moved onto a scanline with a raster
+    /// breakpoint since the last check. The beam stays on a scanline for many instructions, so we only
+    /// break on the transition onto the line.
+    fn check_raster_breakpoints(&mut self) -> bool {
+        let Some(position) = self.cpu.bus().primary_video().map(|video| video.get_raster_position())
+        else {
+            return false;
+        };
+
+        let entered =
+            self.last_raster_line != Some(position.scanline) && self.raster_breakpoints.contains(&position.scanline);
+        self.last_raster_line = Some(position.scanline);
+        if entered {
+            log::debug!(
+                "Raster breakpoint hit: frame: {} scanline: {} dot: {}",
+                position.frame,
+                position.scanline,
+                position.dot
+            );
+        }
+        entered
+    }
+
     pub fn set_stopwatch(&mut self, sw_idx: usize, start: u32, stop: u32) {
         self.cpu.set_stopwatch(sw_idx, start, stop)
     }
 
-    pub fn reset(&mut self) {
+    /// Reset the machine. A cold reset clears RAM, while a warm reset leaves RAM intact and sets the BIOS
+    /// reset flag so that the BIOS performs a warm boot.
+    pub fn reset(&mut self, kind: ResetKind) {
         // TODO: Reload any program specified here?
 
         // Clear any error state.
@@ -1203,22 +2063,63 @@ impl Machine {
         // Reset CPU.
         self.cpu.reset();
 
-        // Clear RAM
-        self.cpu.bus_mut().clear();
+        match kind {
+            ResetKind::Cold => {
+                // Clear RAM
+                self.cpu.bus_mut().clear();
+                if self.load_bios && self.fast_boot {
+                    self.prepare_fast_boot();
+                }
+            }
+            ResetKind::Warm => {
+                if let Err(e) = self
+                    .cpu
+                    .bus_mut()
+                    .write_u16(BIOS_RESET_FLAG_ADDRESS, BIOS_RESET_FLAG_WARM, 0)
+                {
+                    log::error!("Failed to set warm boot flag: {:?}", e);
+                }
+            }
+        }
 
         // Reload BIOS ROM images
         if self.load_bios {
             Machine::install_roms(self.cpu.bus_mut(), &self.rom_manifest);
+            if let Err(e) = Machine::apply_rom_patches(self.cpu.bus_mut(), &self.machine_config.rom_patches) {
+                log::error!("Failed to reapply ROM patches: {}", e);
+            }
             //self.rom_manager.copy_into_memory(self.cpu.bus_mut());
             // Clear patch installation status
             //self.rom_manager.reset_patches();
         }
+        Machine::install_extension_roms(self.cpu.bus_mut(), &self.machine_config.extension_roms);
+        if !self.load_bios {
+            self.simulate_extension_rom_post();
+        }
+
+        // Option ROMs will be initialized again by the BIOS.
+        for status in self.option_rom_status.iter_mut() {
+            status.init_called = false;
+        }
 
         // Reset all installed devices.
         self.cpu.bus_mut().reset_devices();
+        self.code_writes.clear();
+        self.memory_undo.clear();
+
+        // Reseed the CPU so that a deterministic run is reproducible from reset.
+        if let Some(seed) = self.deterministic_seed {
+            self.cpu.set_option(CpuOption::Deterministic(seed));
+            self.cycle_overshoot = 0;
+        }
         self.events.push(MachineEvent::Reset);
     }
 
+    /// Return the most recent self-modifying write to the specified linear address, if any.
+    pub fn code_write(&self, address: u32) -> Option<&CodeWrite> {
+        self.code_writes.get(&address)
+    }
+
     pub fn set_reload_pending(&mut self, state: bool) {
         self.reload_pending = state;
     }
@@ -1227,22 +2128,19 @@ impl Machine {
     /// Convert a count of CPU cycles to microseconds based on the current CPU clock
     /// divisor and system crystal speed.
     fn cpu_cycles_to_us(&self, cycles: u32) -> f64 {
-        let mhz = match self.cpu_factor {
-            ClockFactor::Divisor(n) => self.machine_desc.system_crystal / (n as f64),
-            ClockFactor::Multiplier(n) => self.machine_desc.system_crystal * (n as f64),
-        };
-
-        1.0 / mhz * cycles as f64
+        1.0 / self.get_cpu_mhz() * cycles as f64
     }
 
     #[inline]
     /// Convert a count of CPU cycles to system clock ticks based on the current CPU
-    /// clock divisor.
-    fn cpu_cycles_to_system_ticks(&self, cycles: u32) -> u32 {
-        match self.cpu_factor {
-            ClockFactor::Divisor(n) => cycles * (n as u32),
-            ClockFactor::Multiplier(n) => cycles / (n as u32),
-        }
+    /// clock factor. When the CPU clock is not an integer divisor of the system crystal,
+    /// the fractional tick is carried over to the next call so that devices do not drift
+    /// relative to the CPU.
+    fn cpu_cycles_to_system_ticks(&mut self, cycles: u32) -> u32 {
+        let (ticks, per_cycles) = self.cpu_factor.ratio();
+        let total = cycles as u64 * ticks as u64 + self.sys_tick_remainder;
+        self.sys_tick_remainder = total % per_cycles as u64;
+        (total / per_cycles as u64) as u32
     }
 
     #[allow(dead_code)]
@@ -1250,10 +2148,17 @@ impl Machine {
     /// Convert a count of system clock ticks to CPU cycles based on the current CPU
     /// clock divisor.
     fn system_ticks_to_cpu_cycles(&self, ticks: u32) -> u32 {
-        match self.cpu_factor {
-            ClockFactor::Divisor(n) => (ticks + (n as u32) - 1) / (n as u32),
-            ClockFactor::Multiplier(n) => ticks * (n as u32),
-        }
+        self.cpu_factor.ticks_to_cycles(ticks)
+    }
+
+    /// Return the option ROMs mapped into the machine, and whether the BIOS has called their init entry points.
+    pub fn option_roms(&self) -> &[OptionRomStatus] {
+        &self.option_rom_status
+    }
+
+    /// Return checksums and identification for each ROM loaded by the machine.
+    pub fn rom_info(&self) -> &[RomInfo] {
+        &self.rom_info
     }
 
     pub fn get_checkpoint_string(&self, idx: usize) -> Option<String> {
@@ -1271,6 +2176,10 @@ impl Machine {
 
         // Update cpu factor.
         let new_factor = self.next_cpu_factor;
+        if new_factor != self.cpu_factor {
+            // A carried fraction of a tick is meaningless under a new ratio.
+            self.sys_tick_remainder = 0;
+        }
         self.cpu_factor = new_factor;
         self.bus_mut().set_cpu_factor(new_factor);
 
@@ -1282,11 +2191,17 @@ impl Machine {
         // Was reset requested?
         if let ExecutionOperation::Reset = exec_control.peek_op() {
             _ = exec_control.get_op(); // Clear the reset operation
-            self.reset();
+            self.reset(ResetKind::Cold);
             exec_control.state = ExecutionState::Paused;
             return 0;
         }
 
+        // Did the debugger write to memory with an access breakpoint?
+        if std::mem::take(&mut self.debug_write_break) && matches!(exec_control.state, ExecutionState::Running) {
+            exec_control.state = ExecutionState::BreakpointHit;
+            return 0;
+        }
+
         let mut step_over = false;
         let cycle_target_adj = match exec_control.state {
             ExecutionState::Paused => {
@@ -1307,7 +2222,17 @@ impl Machine {
                         // then run normally.
                         1
                     }
+                    ExecutionOperation::StepOut => {
+                        // Skip current breakpoint, if any
+                        skip_breakpoint = true;
+                        // Record the current stack frame and run until the routine returns from it.
+                        self.cpu.step_out();
+                        self.step_out_cycles = 0;
+                        exec_control.state = ExecutionState::Running;
+                        cycle_target
+                    }
                     ExecutionOperation::Run => {
+                        self.cpu.cancel_step_out();
                         // Transition to ExecutionState::Running
                         exec_control.state = ExecutionState::Running;
                         cycle_target
@@ -1319,7 +2244,7 @@ impl Machine {
                 _ = exec_control.get_op(); // Clear any pending operation
                 cycle_target
             }
-            ExecutionState::BreakpointHit | ExecutionState::StepOverHit => {
+            ExecutionState::BreakpointHit | ExecutionState::StepOverHit | ExecutionState::StepOutHit => {
                 match exec_control.get_op() {
                     ExecutionOperation::Step => {
                         log::debug!("BreakpointHit -> Step");
@@ -1347,7 +2272,20 @@ impl Machine {
                         // Execute one instruction only
                         1
                     }
+                    ExecutionOperation::StepOut => {
+                        log::debug!("BreakpointHit -> StepOut");
+                        // Clear CPU's breakpoint flag
+                        self.cpu.clear_breakpoint_flag();
+                        // Skip current breakpoint, if any
+                        skip_breakpoint = true;
+                        // Record the current stack frame and run until the routine returns from it.
+                        self.cpu.step_out();
+                        self.step_out_cycles = 0;
+                        exec_control.state = ExecutionState::Running;
+                        cycle_target
+                    }
                     ExecutionOperation::Run => {
+                        self.cpu.cancel_step_out();
                         // Clear CPU's breakpoint flag
                         self.cpu.clear_breakpoint_flag();
                         // Skip current breakpoint, if any
@@ -1376,6 +2314,22 @@ impl Machine {
             return 0;
         }
 
+        // In deterministic mode the cycle budget is measured against the emulated clock. The last
+        // instruction of a run usually overshoots the budget; that overshoot is deducted from the next
+        // budget so that elapsed emulated time depends only on the sum of budgets, and not on how the
+        // host happened to partition them into frames.
+        let deterministic_budget = self.deterministic_seed.is_some()
+            && matches!(exec_control.state, ExecutionState::Running)
+            && !step_over;
+        let cycle_target_adj = if deterministic_budget {
+            let budget = cycle_target_adj.saturating_sub(self.cycle_overshoot);
+            self.cycle_overshoot = self.cycle_overshoot.saturating_sub(cycle_target_adj);
+            budget
+        }
+        else {
+            cycle_target_adj
+        };
+
         let mut cycles_elapsed = 0;
 
         while cycles_elapsed < cycle_target_adj {
@@ -1412,6 +2366,18 @@ impl Machine {
                     self.rom_manifest.patches[cp] = patch;
                 }
 
+                if let Some(&idx) = self.option_rom_map.get(&flat_address) {
+                    let status = &mut self.option_rom_status[idx];
+                    if !status.init_called {
+                        log::debug!(
+                            "Option ROM {} at segment {:04X} initialized",
+                            status.name,
+                            status.segment
+                        );
+                        status.init_called = true;
+                    }
+                }
+
                 /*
                 if let Some(cp) = self.rom_manager.get_checkpoint(flat_address) {
                     log::debug!("ROM CHECKPOINT: [{:05X}] {}", flat_address, cp);
@@ -1427,6 +2393,7 @@ impl Machine {
             }
 
             let mut step_over_target = None;
+            let mut step_out_expired = false;
 
             match self.cpu.step(skip_breakpoint) {
                 Ok((step_result, step_cycles)) => match step_result {
@@ -1442,6 +2409,8 @@ impl Machine {
                         step_over_target = Some(target);
                     }
                     StepResult::BreakpointHit => {
+                        // A breakpoint within the routine ends any step out.
+                        self.cpu.cancel_step_out();
                         exec_control.state = ExecutionState::BreakpointHit;
                         return 1;
                     }
@@ -1449,6 +2418,10 @@ impl Machine {
                         exec_control.state = ExecutionState::StepOverHit;
                         return 1;
                     }
+                    StepResult::StepOutHit => {
+                        exec_control.state = ExecutionState::StepOutHit;
+                        return 1;
+                    }
                     StepResult::ProgramEnd => {
                         log::debug!("Program ended execution.");
                         exec_control.state = ExecutionState::Halted;
@@ -1496,6 +2469,17 @@ impl Machine {
             cycles_elapsed += cpu_cycles;
             self.cpu_cycles += cpu_cycles as u64;
 
+            // Give up on a step out if the routine doesn't return in a reasonable time.
+            if self.cpu.is_stepping_out() {
+                self.step_out_cycles += cpu_cycles as u64;
+                if self.step_out_cycles > STEP_OUT_CYCLE_LIMIT {
+                    log::warn!("Step Out: routine did not return within {} cycles", STEP_OUT_CYCLE_LIMIT);
+                    self.cpu.cancel_step_out();
+                    exec_control.state = ExecutionState::Paused;
+                    step_out_expired = true;
+                }
+            }
+
             if cpu_cycles == 0 {
                 log::warn!("Instruction returned 0 cycles");
                 cpu_cycles = fake_cycles;
@@ -1522,6 +2506,12 @@ impl Machine {
                 log::error!("CPU Error: {}\n{}", err, self.cpu.dump_instruction_history_string());
             }
 
+            // Raise the CPU breakpoint flag if the video card reached a raster breakpoint. The next call to
+            // step() will return BreakpointHit before executing another instruction.
+            if !self.raster_breakpoints.is_empty() && self.check_raster_breakpoints() {
+                self.cpu.set_breakpoint_flag();
+            }
+
             if self.options.record_listing {
                 let cpu_address = CpuAddress::Segmented(self.disassembly.cs, self.disassembly.ip);
                 let listing_entry = DisassemblyListingEntry {
@@ -1553,12 +2543,29 @@ impl Machine {
                         // Forward the quit event to the frontend.
                         self.events.push(MachineEvent::Service(ServiceEvent::QuitEmulator(delay)));
                     }
+                    ServiceEvent::SelfModifyingWrite(write) => {
+                        log::debug!(
+                            "Self-modifying write to [{:05X}] by instruction at [{:04X}:{:04X}]",
+                            write.address,
+                            write.segment,
+                            write.offset
+                        );
+                        self.code_writes.insert(write.address, write);
+                    }
                 }
             }
+
+            if step_out_expired {
+                break;
+            }
         }
 
         //log::debug!("cycles_elapsed: {}", cycles_elapsed);
 
+        if deterministic_budget {
+            self.cycle_overshoot += cycles_elapsed.saturating_sub(cycle_target_adj);
+        }
+
         self.cpu_instructions += instr_count;
         instr_count
     }
@@ -1652,21 +2659,18 @@ impl Machine {
     }
 
     fn timer_ticks_to_cpu_cycles(&self, timer_ticks: u32) -> u32 {
-        let timer_multiplier = if let Some(_timer_crystal) = self.machine_desc.timer_crystal {
+        if let Some(_timer_crystal) = self.machine_desc.timer_crystal {
             // We have an alternate
             todo!("Unimplemented conversion for AT timer");
             //1
         } else {
-            match self.machine_desc.cpu_factor {
-                ClockFactor::Divisor(n) => self.machine_desc.timer_divisor / (n as u32),
-                ClockFactor::Multiplier(_n) => {
-                    todo!("unimplemented conversion for CPU multiplier");
-                    //1
-                }
-            }
-        };
-
-        timer_ticks as u32 * timer_multiplier
+            // Use the current CPU factor, not the machine's base factor - when turbo is active, more CPU
+            // cycles elapse per timer tick. Scale the whole count at once so that clocks that are not an
+            // integer multiple of the timer don't accumulate rounding error.
+            let (ticks, cycles) = self.cpu_factor.ratio();
+            let sys_ticks = timer_ticks as u64 * self.machine_desc.timer_divisor as u64;
+            ((sys_ticks * cycles as u64 + ticks as u64 / 2) / ticks as u64) as u32
+        }
     }
 
     /// Called to update machine once per frame. This can be used to update the state of devices that don't require
@@ -1675,6 +2679,9 @@ impl Machine {
     pub fn frame_update(&mut self) -> Vec<DeviceEvent> {
         let mut device_events = Vec::new();
 
+        // Latch this frame's memory access profile, if profiling.
+        self.cpu.bus_mut().end_access_profile_frame();
+
         // Update serial port, if present
         if let Some(spc) = self.cpu.bus_mut().serial_mut() {
             spc.update();
@@ -1693,11 +2700,11 @@ impl Machine {
                                 // Turbo bit has changed.
                                 match turbo_bit {
                                     true => {
-                                        self.next_cpu_factor = self.machine_desc.cpu_turbo_factor;
+                                        self.next_cpu_factor = self.turbo_cpu_factor;
                                         device_events.push(DeviceEvent::TurboToggled(true));
                                     }
                                     false => {
-                                        self.next_cpu_factor = self.machine_desc.cpu_factor;
+                                        self.next_cpu_factor = self.normal_cpu_factor;
                                         device_events.push(DeviceEvent::TurboToggled(false));
                                     }
                                 }
@@ -1845,3 +2852,384 @@ impl Machine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu_validator::ValidatorType,
+        devices::keyboard::{KeyboardType, BIOS_KB_BUFFER_START},
+        machine_config::{ConventionalMemoryConfig, KeyboardConfig, MemoryConfig},
+    };
+
+    /// A core configuration with every optional feature turned off.
+    struct TestCoreConfig;
+
+    impl CoreConfig for TestCoreConfig {
+        fn get_base_dir(&self) -> PathBuf {
+            PathBuf::new()
+        }
+        fn get_machine_type(&self) -> MachineType {
+            MachineType::Ibm5160
+        }
+        fn get_audio_enabled(&self) -> bool {
+            false
+        }
+        fn get_machine_noroms(&self) -> bool {
+            false
+        }
+        fn get_machine_turbo(&self) -> bool {
+            false
+        }
+        fn get_machine_fast_boot(&self) -> bool {
+            false
+        }
+        fn get_keyboard_layout(&self) -> Option<String> {
+            None
+        }
+        fn get_keyboard_debug(&self) -> bool {
+            false
+        }
+        fn get_validator_type(&self) -> Option<ValidatorType> {
+            None
+        }
+        fn get_validator_trace_file(&self) -> Option<PathBuf> {
+            None
+        }
+        fn get_validator_baud(&self) -> Option<u32> {
+            None
+        }
+        fn get_cpu_trace_mode(&self) -> Option<TraceMode> {
+            None
+        }
+        fn get_cpu_trace_on(&self) -> bool {
+            false
+        }
+        fn get_cpu_trace_file(&self) -> Option<PathBuf> {
+            None
+        }
+        fn get_title_hacks(&self) -> bool {
+            false
+        }
+        fn get_patch_enabled(&self) -> bool {
+            false
+        }
+        fn get_halt_behavior(&self) -> OnHaltBehavior {
+            OnHaltBehavior::Continue
+        }
+        fn get_terminal_port(&self) -> Option<u16> {
+            None
+        }
+        fn get_post_code_ports(&self) -> Vec<u16> {
+            Vec::new()
+        }
+    }
+
+    /// A ROM that programs PIT channel 0 and then stores its counter to memory in a loop, so that the
+    /// memory contents depend on exactly how far the machine has run.
+    fn pit_sampling_rom() -> MachineRomEntry {
+        let mut data = vec![0xFF; 0x2000];
+        let code = [
+            0x31, 0xC0, // XOR AX, AX
+            0x8E, 0xC0, // MOV ES, AX
+            0xBF, 0x00, 0x20, // MOV DI, 2000h
+            0xB0, 0x34, // MOV AL, 34h
+            0xE6, 0x43, // OUT 43h, AL
+            0xB0, 0x00, // MOV AL, 0
+            0xE6, 0x40, // OUT 40h, AL
+            0xE6, 0x40, // OUT 40h, AL
+            0xE4, 0x40, // IN AL, 40h
+            0xAA, // STOSB
+            0xEB, 0xFB, // JMP -5
+        ];
+        data[..code.len()].copy_from_slice(&code);
+        // Reset vector: JMP F000:E000
+        data[0x1FF0..0x1FF5].copy_from_slice(&[0xEA, 0x00, 0xE0, 0x00, 0xF0]);
+        MachineRomEntry {
+            md5: String::new(),
+            crc32: 0,
+            addr: 0xFE000,
+            data,
+        }
+    }
+
+    fn test_config() -> MachineConfiguration {
+        MachineConfiguration {
+            speaker: false,
+            ppi_turbo: None,
+            machine_type: MachineType::Ibm5160,
+            cpu: None,
+            memory: MemoryConfig {
+                conventional: ConventionalMemoryConfig {
+                    size: 0x10000,
+                    wait_states: 0,
+                },
+                regions: Vec::new(),
+            },
+            ems: None,
+            keyboard: None,
+            serial_mouse: None,
+            video: Vec::new(),
+            sound: Vec::new(),
+            serial: Vec::new(),
+            game_port: None,
+            rtc: None,
+            fdc: None,
+            hdc: None,
+            media: None,
+            post_code_table: None,
+            option_roms: Vec::new(),
+            extension_roms: Vec::new(),
+            io_open_bus_value: None,
+            io_floating_bus: false,
+            profile_regions: Vec::new(),
+            wait_state_regions: Vec::new(),
+            rom_patches: Vec::new(),
+            rom_hooks: Vec::new(),
+        }
+    }
+
+    fn build_test_machine(config: &MachineConfiguration, rom: MachineRomEntry) -> Machine {
+        let core_config = TestCoreConfig;
+        MachineBuilder::new()
+            .with_core_config(Box::new(&core_config))
+            .with_machine_config(config)
+            .with_roms(MachineRomManifest {
+                roms: vec![rom],
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+    }
+
+    fn test_machine() -> Machine {
+        build_test_machine(&test_config(), pit_sampling_rom())
+    }
+
+    #[test]
+    fn post_code_table_defaults_to_none() {
+        let mut machine = test_machine();
+        assert_eq!(machine.post_code_table(), DEFAULT_POST_CODE_TABLE);
+
+        machine.machine_config.post_code_table = Some("NONE".to_string());
+        assert_eq!(machine.post_code_table(), DEFAULT_POST_CODE_TABLE);
+
+        // Configurations naming a table that isn't provided fall back to the default.
+        machine.machine_config.post_code_table = Some("ami".to_string());
+        assert_eq!(machine.post_code_table(), DEFAULT_POST_CODE_TABLE);
+    }
+
+    /// Typed keys reach INT 16h through the real GLaBIOS INT 9 and INT 16h handlers. POST is skipped: the
+    /// reset vector jumps to a RAM stub that programs the PIC, installs the two vectors and initializes the
+    /// type-ahead buffer itself.
+    #[test]
+    fn typed_key_reaches_int16() {
+        const KEY_PEEK: usize = 0x600; // INT 16h AH=01h result
+        const KEY_FLAGS: usize = 0x602; // INT 16h AH=02h result
+        const KEY_READ: usize = 0x604; // INT 16h AH=00h result
+        const GO_READ: usize = 0x606; // Set by the test to let the stub call AH=00h
+
+        let mut data = include_bytes!("../../../install/media/roms/GLaBIOS/GLABIOS_0.2.6_8X.ROM").to_vec();
+        // Reset vector: JMP 0000:0500
+        data[0x1FF0..0x1FF5].copy_from_slice(&[0xEA, 0x00, 0x05, 0x00, 0x00]);
+        let rom = MachineRomEntry {
+            md5: String::new(),
+            crc32: 0,
+            addr: 0xFE000,
+            data,
+        };
+
+        let mut config = test_config();
+        config.keyboard = Some(KeyboardConfig {
+            kb_type: KeyboardType::ModelF,
+            layout: "US".to_string(),
+            typematic: false,
+            typematic_delay: None,
+            typematic_rate: None,
+        });
+        let mut machine = build_test_machine(&config, rom);
+
+        #[rustfmt::skip]
+        let stub = [
+            0xFA,                         // CLI
+            0x31, 0xC0,                   // XOR AX, AX
+            0x8E, 0xD0,                   // MOV SS, AX
+            0xBC, 0x00, 0x7C,             // MOV SP, 7C00h
+            0x8E, 0xD8,                   // MOV DS, AX
+            0xB0, 0x13, 0xE6, 0x20,       // ICW1: edge triggered, single, ICW4 needed
+            0xB0, 0x08, 0xE6, 0x21,       // ICW2: IRQ0 at INT 08h
+            0xB0, 0x09, 0xE6, 0x21,       // ICW4: buffered, 8086 mode
+            0xB0, 0xFD, 0xE6, 0x21,       // OCW1: unmask IRQ1 only
+            0xB0, 0x48, 0xE6, 0x61,       // Keyboard clock high, keyboard enabled
+            0xFB,                         // STI
+            0xB4, 0x01, 0xCD, 0x16,       // MOV AH, 01h; INT 16h
+            0x74, 0xFA,                   // JZ -6
+            0xA3, 0x00, 0x06,             // MOV [KEY_PEEK], AX
+            0xB4, 0x02, 0xCD, 0x16,       // MOV AH, 02h; INT 16h
+            0xA2, 0x02, 0x06,             // MOV [KEY_FLAGS], AL
+            0x80, 0x3E, 0x06, 0x06, 0x00, // CMP BYTE [GO_READ], 0
+            0x74, 0xF9,                   // JZ -7
+            0xB4, 0x00, 0xCD, 0x16,       // MOV AH, 00h; INT 16h
+            0xA3, 0x04, 0x06,             // MOV [KEY_READ], AX
+            0xEB, 0xFE,                   // JMP $
+        ];
+        let bus = machine.bus_mut();
+        for (i, byte) in stub.iter().enumerate() {
+            bus.write_u8(0x500 + i, *byte, 0).unwrap();
+        }
+        // INT 09h at F000:E987, INT 16h at F000:E82E
+        bus.write_u16(0x09 * 4, 0xE987, 0).unwrap();
+        bus.write_u16(0x09 * 4 + 2, 0xF000, 0).unwrap();
+        bus.write_u16(0x16 * 4, 0xE82E, 0).unwrap();
+        bus.write_u16(0x16 * 4 + 2, 0xF000, 0).unwrap();
+        // Empty type-ahead buffer and no shift keys down.
+        let head = BIOS_KB_BUFFER_HEAD_ADDRESS;
+        bus.write_u16(head, BIOS_KB_BUFFER_START, 0).unwrap();
+        bus.write_u16(head + 2, BIOS_KB_BUFFER_START, 0).unwrap();
+        bus.write_u8(BIOS_KB_FLAG_ADDRESS, 0, 0).unwrap();
+        bus.write_u8(GO_READ, 0, 0).unwrap();
+
+        let peek_u16 = |machine: &Machine, address: usize| {
+            let bytes = machine.bus().peek_range(address, 2).unwrap();
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        };
+        let mut exec_control = ExecutionControl::new();
+        exec_control.state = ExecutionState::Running;
+        let mut run_frames = |machine: &mut Machine, frames: usize| {
+            for _ in 0..frames {
+                machine.run(100_000, &mut exec_control);
+            }
+        };
+
+        // Nothing typed yet, so AH=01h keeps reporting an empty buffer.
+        run_frames(&mut machine, 2);
+        assert!(machine.bios_key_buffer().is_empty());
+
+        // Shift+A. Keyboard events are delivered one per frame.
+        machine.key_press(MartyKey::ShiftLeft, KeyboardModifiers::default());
+        run_frames(&mut machine, 3);
+        machine.key_press(MartyKey::KeyA, KeyboardModifiers::default());
+        run_frames(&mut machine, 3);
+
+        let shifted_a = BiosKey {
+            scancode: 0x1E,
+            ascii:    b'A',
+        };
+        assert_eq!(machine.bios_key_buffer(), vec![shifted_a]);
+        assert_eq!(peek_u16(&machine, KEY_PEEK), 0x1E41);
+        // Left shift is bit 1 of the shift flags at 0040:0017.
+        assert_eq!(machine.bus().peek_u8(BIOS_KB_FLAG_ADDRESS).unwrap(), 0x02);
+        assert_eq!(machine.bus().peek_u8(KEY_FLAGS).unwrap(), 0x02);
+
+        // AH=00h returns the same key and removes it from the buffer.
+        machine.bus_mut().write_u8(GO_READ, 1, 0).unwrap();
+        run_frames(&mut machine, 1);
+        assert_eq!(peek_u16(&machine, KEY_READ), 0x1E41);
+        assert!(machine.bios_key_buffer().is_empty());
+    }
+
+    /// Run a deterministic machine for the given frame budgets and return its instruction count,
+    /// cycle count and memory signature.
+    fn deterministic_run(seed: u64, budgets: &[u32]) -> (u64, u64, u64) {
+        let mut machine = test_machine();
+        machine.set_cpu_option(CpuOption::Deterministic(seed));
+        let mut exec_control = ExecutionControl::new();
+        exec_control.state = ExecutionState::Running;
+        for budget in budgets {
+            machine.run(*budget, &mut exec_control);
+        }
+        (
+            machine.cpu_instructions(),
+            machine.cpu_cycles(),
+            machine.bus().memory_signature(),
+        )
+    }
+
+    #[test]
+    fn deterministic_runs_are_reproducible() {
+        let budgets = [10_000, 7_500, 12_345, 10_000, 9_999];
+        let first = deterministic_run(1234, &budgets);
+        assert!(first.0 > 0);
+        assert_eq!(deterministic_run(1234, &budgets), first);
+
+        // The run samples the PIT, so running further changes the memory signature.
+        let mut longer = budgets.to_vec();
+        longer.push(10_000);
+        assert_ne!(deterministic_run(1234, &longer).2, first.2);
+    }
+
+    #[test]
+    fn memory_fills_and_edits_share_undo() {
+        let mut machine = test_machine();
+        let ram = machine.bus().peek_range(0x1000, 4).unwrap().to_vec();
+
+        assert_eq!(machine.edit_memory(0x1000, &[0x01, 0x02, 0x03], false), (3, 0));
+        // ROM is rejected without force, and a rejected edit leaves nothing to undo.
+        assert_eq!(machine.edit_memory(0xFE000, &[0x90], false), (0, 1));
+        assert_eq!(machine.memory_undo_len(), 1);
+        assert_eq!(machine.edit_memory(0xFE000, &[0x90], true), (1, 0));
+        assert_eq!(machine.bus().peek_u8(0xFE000).unwrap(), 0x90);
+        assert_eq!(machine.fill_memory(0x1000, 4, 0xCC), (4, 0));
+        assert_eq!(machine.memory_undo_len(), 3);
+
+        assert!(machine.undo_memory_change());
+        assert_eq!(
+            machine.bus().peek_range(0x1000, 4).unwrap(),
+            &[0x01, 0x02, 0x03, ram[3]]
+        );
+        assert!(machine.undo_memory_change());
+        assert_eq!(machine.bus().peek_u8(0xFE000).unwrap(), 0x31);
+        assert!(machine.undo_memory_change());
+        assert_eq!(machine.bus().peek_range(0x1000, 4).unwrap(), &ram[..]);
+        assert!(!machine.undo_memory_change());
+    }
+
+    #[test]
+    fn memory_edit_breaks_on_access_breakpoint() {
+        let mut machine = test_machine();
+        machine.bus_mut().set_flags(0x8000, MEM_BPA_BIT);
+        let mut exec_control = ExecutionControl::new();
+        exec_control.state = ExecutionState::Running;
+
+        // Edits only break when the option is enabled.
+        machine.edit_memory(0x8000, &[0x55], false);
+        machine.run(1000, &mut exec_control);
+        assert!(matches!(exec_control.state, ExecutionState::Running));
+
+        machine.set_option(MachineOption::BreakOnDebugWrite(true));
+        machine.edit_memory(0x1000, &[0x55], false);
+        machine.run(1000, &mut exec_control);
+        assert!(matches!(exec_control.state, ExecutionState::Running));
+
+        machine.edit_memory(0x8000, &[0x55], false);
+        assert_eq!(machine.run(1000, &mut exec_control), 0);
+        assert!(matches!(exec_control.state, ExecutionState::BreakpointHit));
+    }
+
+    fn rom_patch() -> RomPatchConfig {
+        RomPatchConfig {
+            segment: 0xF000,
+            offset: 0xE0AE,
+            original: vec![0xE8, 0x12, 0x34],
+            replacement: vec![0x90, 0x90, 0x90],
+        }
+    }
+
+    #[test]
+    fn rom_patch_applies_over_matching_bytes() {
+        let mut bus = BusInterface::default();
+        bus.patch_from(&vec![0xE8, 0x12, 0x34], 0xFE0AE).unwrap();
+        Machine::apply_rom_patches(&mut bus, &[rom_patch()]).unwrap();
+        assert_eq!(bus.get_vec_at(0xFE0AE, 3), vec![0x90, 0x90, 0x90]);
+    }
+
+    #[test]
+    fn rom_patch_mismatch_is_an_error() {
+        let mut bus = BusInterface::default();
+        bus.patch_from(&vec![0xE8, 0x12, 0x35], 0xFE0AE).unwrap();
+        let err = Machine::apply_rom_patches(&mut bus, &[rom_patch()]).unwrap_err();
+        assert!(err.to_string().contains("F000:E0AE"));
+        // Nothing is written when the original bytes don't match.
+        assert_eq!(bus.get_vec_at(0xFE0AE, 3), vec![0xE8, 0x12, 0x35]);
+    }
+}
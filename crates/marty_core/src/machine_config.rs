@@ -37,19 +37,28 @@ use crate::machine_types::{
     HardDiskControllerType,
     HardDriveFormat,
     MachineType,
+    RtcType,
     SerialControllerType,
     SerialMouseType,
     SoundType,
 };
 use anyhow::{anyhow, Error};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, fmt::Display, path::PathBuf};
+use web_time::{SystemTime, UNIX_EPOCH};
 
 use crate::{
+    breakpoints::{HookAction, RomHook},
     bus::ClockFactor,
     cpu_common::CpuType,
     device_traits::videocard::VideoType,
-    devices::{keyboard::KeyboardType, pit::PitType},
+    devices::{
+        keyboard::KeyboardType,
+        mm58167::{MM58167_DEFAULT_IO, MM58167_PORT_CT},
+        pit::PitType,
+        rtc::RTC_DEFAULT_IO,
+    },
+    option_rom::{OPTION_ROM_SCAN_END, OPTION_ROM_SCAN_STEP, OPTION_ROM_START},
     tracelogger::TraceLogger,
 };
 
@@ -61,6 +70,8 @@ use serde_derive::Deserialize;
 pub const IBM_PC_SYSTEM_CLOCK: f64 = 157.5 / 11.0;
 pub const PIT_DIVISOR: u32 = 12;
 pub const GAME_PORT_DEFAULT_IO: u16 = 0x201;
+pub const MAX_FLOPPY_DRIVES: usize = 4;
+pub const MAX_REGION_WAIT_STATES: u32 = 15;
 
 const fn _default_true() -> bool {
     true
@@ -121,11 +132,25 @@ pub enum BusType {
 #[derive(Clone, Debug, Deserialize)]
 pub struct CpuConfig {
     pub upgrade_type: Option<CpuType>,
+    /// CPU clock in MHz. If not specified, the base machine's CPU clock is used.
+    pub clock: Option<f64>,
+    /// CPU clock in MHz when the turbo button is active. If not specified, the base machine's turbo clock is used.
+    pub turbo_clock: Option<f64>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct MemoryConfig {
     pub conventional: ConventionalMemoryConfig,
+    /// Populated RAM regions. If empty, a single region of conventional memory starting at address 0 is populated.
+    #[serde(default)]
+    pub regions: Vec<RamRegionConfig>,
+}
+
+/// A region of populated RAM, such as that provided by the motherboard or a memory expansion card.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+pub struct RamRegionConfig {
+    pub address: u32,
+    pub size:    u32,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -165,6 +190,56 @@ pub struct GamePortConfig {
     pub io_base: u16,
 }
 
+/// A real time clock: the MC146818 of the AT, with CMOS RAM, or an MM58167 XT clock card.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RtcConfig {
+    /// The clock chip to emulate: the AT's MC146818, or the MM58167 of XT clock cards.
+    #[serde(default)]
+    pub rtc_type: RtcType,
+    /// Defaults to 0x70 for the MC146818 and 0x2C0 for the MM58167.
+    pub io_base: Option<u16>,
+    /// Defaults to IRQ8 for the MC146818. The MM58167's interrupt is not connected unless specified.
+    pub irq: Option<u8>,
+    /// Offset from UTC of the time the clock is seeded with, in minutes.
+    #[serde(default)]
+    pub utc_offset: i32,
+    /// Start the clock at a fixed time instead of the host's, in seconds since 1970-01-01.
+    /// Also used in deterministic mode.
+    pub fixed_time: Option<i64>,
+    /// Advance the clock with the host's wall clock rather than emulated time, so it keeps correct
+    /// time when the emulator runs faster or slower than the real machine. Ignored in deterministic mode.
+    #[serde(default)]
+    pub host_clock: bool,
+}
+
+impl RtcConfig {
+    /// Return the time to seed the clock with, in seconds since 1970-01-01: the fixed time if one is
+    /// configured, otherwise the host's time adjusted by utc_offset.
+    pub fn start_time(&self) -> i64 {
+        self.fixed_time.unwrap_or_else(|| {
+            let host_time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            host_time + self.utc_offset as i64 * 60
+        })
+    }
+
+    pub fn io_base(&self) -> u16 {
+        self.io_base.unwrap_or(match self.rtc_type {
+            RtcType::Mc146818 => RTC_DEFAULT_IO,
+            RtcType::Mm58167 => MM58167_DEFAULT_IO,
+        })
+    }
+
+    pub fn port_ct(&self) -> u32 {
+        match self.rtc_type {
+            RtcType::Mc146818 => 2,
+            RtcType::Mm58167 => MM58167_PORT_CT as u32,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct VideoCardConfig {
     #[serde(rename = "type")]
@@ -172,6 +247,7 @@ pub struct VideoCardConfig {
     #[serde(rename = "subtype")]
     pub video_subtype: Option<VideoCardSubType>,
     pub dip_switch:    Option<u8>,
+    pub snow:          Option<bool>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -235,6 +311,80 @@ pub struct HardDriveImage {
     pub image: String,
 }
 
+/// An option ROM image to map at the specified segment. Paths are relative to the 'rom' resource directory.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OptionRomConfig {
+    pub path: String,
+    pub segment: u16,
+    /// Correct a bad checksum instead of rejecting the ROM. Useful for ROMs under development.
+    #[serde(default)]
+    pub fix_checksum: bool,
+}
+
+/// A ROM extension image read from the filesystem and mapped at 'base_address'. Unlike option ROMs, the image is
+/// mapped as-is; it is only initialized if the BIOS (or the simulated POST, when running without a BIOS) finds a
+/// valid signature at a 2KB boundary.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExtensionRomConfig {
+    pub path: PathBuf,
+    pub base_address: u32,
+}
+
+/// A named range of the address space for the memory access profiler. 'end' is inclusive.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProfileRegionConfig {
+    pub name:  String,
+    pub start: u32,
+    pub end:   u32,
+}
+
+/// Wait states added to CPU memory accesses within a range of the address space. 'end' is inclusive.
+/// These are in addition to any wait states imposed by a device mapped in the range.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WaitStateRegionConfig {
+    pub start: u32,
+    pub end:   u32,
+    #[serde(default)]
+    pub read:  u32,
+    #[serde(default)]
+    pub write: u32,
+}
+
+/// Bytes to replace in ROM once it is loaded. The bytes at 'segment':'offset' must match 'original', so that a patch
+/// written for one BIOS version is not applied to another.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RomPatchConfig {
+    pub segment: u16,
+    pub offset: u16,
+    pub original: Vec<u8>,
+    pub replacement: Vec<u8>,
+}
+
+impl RomPatchConfig {
+    pub fn address(&self) -> u32 {
+        (((self.segment as u32) << 4) + self.offset as u32) & 0xFFFFF
+    }
+}
+
+/// A named address at which the CPU performs a built-in action instead of stopping, as it would for a breakpoint.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RomHookConfig {
+    pub name:    String,
+    pub segment: u16,
+    pub offset:  u16,
+    pub action:  HookAction,
+}
+
+impl RomHookConfig {
+    pub fn to_hook(&self) -> RomHook {
+        RomHook {
+            name:    self.name.clone(),
+            address: (((self.segment as u32) << 4) + self.offset as u32) & 0xFFFFF,
+            action:  self.action,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct MediaConfig {
     pub floppy: Option<Vec<FloppyImage>>,
@@ -255,9 +405,402 @@ pub struct MachineConfiguration {
     pub sound: Vec<SoundDeviceConfig>,
     pub serial: Vec<SerialControllerConfig>,
     pub game_port: Option<GamePortConfig>,
+    pub rtc: Option<RtcConfig>,
     pub fdc: Option<FloppyControllerConfig>,
     pub hdc: Option<HardDriveControllerConfig>,
     pub media: Option<MediaConfig>,
+    pub post_code_table: Option<String>,
+    pub option_roms: Vec<OptionRomConfig>,
+    /// ROM extension images to load from the filesystem.
+    pub extension_roms: Vec<ExtensionRomConfig>,
+    /// The byte read from IO ports no device responds to. Defaults to NO_IO_BYTE (0xFF).
+    pub io_open_bus_value: Option<u8>,
+    /// Return the last byte written to the data bus from unclaimed IO ports instead of
+    /// io_open_bus_value, emulating a floating bus.
+    pub io_floating_bus: bool,
+    /// Address regions for the memory access profiler. The standard PC memory map is used if empty.
+    pub profile_regions: Vec<ProfileRegionConfig>,
+    /// Wait states for CPU memory accesses by address region.
+    pub wait_state_regions: Vec<WaitStateRegionConfig>,
+    /// Patches applied to ROM after it is loaded.
+    pub rom_patches: Vec<RomPatchConfig>,
+    /// Addresses at which the CPU performs a built-in action, such as logging or skipping code.
+    pub rom_hooks: Vec<RomHookConfig>,
+}
+
+/// Errors produced when validating a MachineConfiguration against its MachineDescriptor.
+#[derive(Debug)]
+pub enum MachineConfigError {
+    IncompatibleCpu(CpuType, CpuType),
+    ExpansionVideoUnsupported(MachineType, VideoType),
+    ConflictingVideoCards(VideoType, VideoType),
+    IoConflict(u16, String, String),
+    MemoryConflict(u32, String, String),
+    InvalidSerialMousePort(u32),
+    TooManyFloppyDrives(usize),
+    NoRamAtZero,
+    RamRegionOutOfRange(u32, u32),
+    InvalidCpuClock(f64),
+    InvalidRomPatch(u16, u16),
+    InvalidRomHook(String),
+    InvalidWaitStateRegion(u32, u32),
+    InvalidExtensionRomAddress(u32),
+    InvalidRtcIrq(u8),
+}
+impl std::error::Error for MachineConfigError {}
+impl Display for MachineConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &*self {
+            MachineConfigError::IncompatibleCpu(upgrade, base) => write!(
+                f,
+                "CPU upgrade {:?} is not compatible with the machine's base CPU type {:?}",
+                upgrade, base
+            ),
+            MachineConfigError::ExpansionVideoUnsupported(machine, video) => write!(
+                f,
+                "Machine type {:?} does not support expansion video cards, but a {:?} card was specified",
+                machine, video
+            ),
+            MachineConfigError::ConflictingVideoCards(first, second) => write!(
+                f,
+                "Video cards {:?} and {:?} cannot be installed together",
+                first, second
+            ),
+            MachineConfigError::IoConflict(port, first, second) => {
+                write!(f, "IO port conflict at {:04X}h between {} and {}", port, first, second)
+            }
+            MachineConfigError::MemoryConflict(address, first, second) => write!(
+                f,
+                "Memory conflict at {:05X}h between {} and {}",
+                address, first, second
+            ),
+            MachineConfigError::InvalidSerialMousePort(port) => {
+                write!(
+                    f,
+                    "Serial mouse is attached to serial port {}, which is not installed",
+                    port
+                )
+            }
+            MachineConfigError::TooManyFloppyDrives(count) => write!(
+                f,
+                "{} floppy drives specified, but a maximum of {} are supported",
+                count, MAX_FLOPPY_DRIVES
+            ),
+            MachineConfigError::NoRamAtZero => write!(f, "No RAM region is populated at address 0"),
+            MachineConfigError::RamRegionOutOfRange(address, size) => write!(
+                f,
+                "RAM region at {:05X}h of size {:X}h extends beyond the 1MB address space",
+                address, size
+            ),
+            MachineConfigError::InvalidCpuClock(mhz) => write!(f, "Invalid CPU clock of {}MHz", mhz),
+            MachineConfigError::InvalidRomPatch(segment, offset) => write!(
+                f,
+                "ROM patch at {:04X}:{:04X} must have the same, non-zero number of original and replacement bytes",
+                segment, offset
+            ),
+            MachineConfigError::InvalidRomHook(name) => {
+                write!(f, "ROM hook '{}' must skip at least one byte", name)
+            }
+            MachineConfigError::InvalidWaitStateRegion(start, end) => write!(
+                f,
+                "Wait state region {:05X}-{:05X} must lie within the address space and have at most {} wait states",
+                start, end, MAX_REGION_WAIT_STATES
+            ),
+            MachineConfigError::InvalidExtensionRomAddress(address) => write!(
+                f,
+                "Extension ROM address {:05X} must be a 2KB boundary between {:05X} and {:05X}",
+                address, OPTION_ROM_START, OPTION_ROM_SCAN_END
+            ),
+            MachineConfigError::InvalidRtcIrq(irq) => write!(f, "Real time clock IRQ {} is out of range", irq),
+        }
+    }
+}
+
+/// A range of IO ports or memory addresses claimed by a device, used for conflict detection.
+struct ResourceClaim {
+    name:  String,
+    start: u32,
+    len:   u32,
+}
+
+/// Return the first address at which two claims overlap, if any.
+fn find_conflict(claims: &[ResourceClaim]) -> Option<(u32, &ResourceClaim, &ResourceClaim)> {
+    for (i, a) in claims.iter().enumerate() {
+        for b in claims.iter().skip(i + 1) {
+            let start = a.start.max(b.start);
+            if start < (a.start + a.len).min(b.start + b.len) {
+                return Some((start, a, b));
+            }
+        }
+    }
+    None
+}
+
+impl MachineConfiguration {
+    /// Return the populated RAM regions, sorted by address. If no regions are specified, a single region of the
+    /// normalized conventional memory size is returned.
+    pub fn ram_regions(&self) -> Result<Vec<RamRegionConfig>, Error> {
+        if self.memory.regions.is_empty() {
+            return Ok(vec![RamRegionConfig {
+                address: 0,
+                size:    normalize_conventional_memory(self)?,
+            }]);
+        }
+        let mut regions = self.memory.regions.clone();
+        regions.sort_by_key(|region| region.address);
+        Ok(regions)
+    }
+
+    /// Return the CPU clock factors for normal and turbo operation, in that order. CPU clocks specified in the
+    /// configuration override those of the base machine.
+    pub fn cpu_factors(&self, desc: &MachineDescriptor) -> (ClockFactor, ClockFactor) {
+        let factor_for = |clock: Option<f64>, default: ClockFactor| {
+            clock
+                .map(|mhz| ClockFactor::from_mhz(desc.system_crystal, mhz))
+                .unwrap_or(default)
+        };
+        let cpu = self.cpu.as_ref();
+        (
+            factor_for(cpu.and_then(|cpu| cpu.clock), desc.cpu_factor),
+            factor_for(cpu.and_then(|cpu| cpu.turbo_clock), desc.cpu_turbo_factor),
+        )
+    }
+
+    /// Validate this configuration against the specified machine descriptor, returning a descriptive error for
+    /// unsupported device combinations or conflicting IO and memory assignments.
+    pub fn validate(&self, desc: &MachineDescriptor) -> Result<(), MachineConfigError> {
+        // Check CPU compatibility
+        if let Some(upgrade_type) = self.cpu.as_ref().and_then(|cpu| cpu.upgrade_type) {
+            if let Some(compatible_cpus) = COMPATIBLE_CPUS.get(&desc.cpu_type) {
+                if upgrade_type != desc.cpu_type && !compatible_cpus.contains(&upgrade_type) {
+                    return Err(MachineConfigError::IncompatibleCpu(upgrade_type, desc.cpu_type));
+                }
+            }
+        }
+
+        // Check CPU clocks. Anything from a tenth of the system crystal up to its 255x multiple
+        // can be represented.
+        if let Some(cpu) = &self.cpu {
+            for mhz in [cpu.clock, cpu.turbo_clock].into_iter().flatten() {
+                if !mhz.is_finite() || mhz < desc.system_crystal / 10.0 || mhz > desc.system_crystal * 255.0 {
+                    return Err(MachineConfigError::InvalidCpuClock(mhz));
+                }
+            }
+        }
+
+        // Check RAM regions. The BIOS expects RAM at address 0 for the interrupt vector table and its data area.
+        let ram_regions = self.ram_regions().unwrap_or_else(|_| {
+            vec![RamRegionConfig {
+                address: 0,
+                size:    self.memory.conventional.size,
+            }]
+        });
+        if !ram_regions.iter().any(|region| region.address == 0 && region.size > 0) {
+            return Err(MachineConfigError::NoRamAtZero);
+        }
+        for region in ram_regions.iter() {
+            if region.address as u64 + region.size as u64 > 0x100000 {
+                return Err(MachineConfigError::RamRegionOutOfRange(region.address, region.size));
+            }
+        }
+
+        let mut io_claims = Vec::new();
+        let mut mem_claims: Vec<ResourceClaim> = ram_regions
+            .iter()
+            .map(|region| ResourceClaim {
+                name:  format!("RAM region at {:05X}h", region.address),
+                start: region.address,
+                len:   region.size,
+            })
+            .collect();
+
+        // Check video cards. Only one card of each monitor type (mono or color) can be present, as they share
+        // IO ports and memory apertures.
+        let mut mono_card = None;
+        let mut color_card = None;
+        for card in self.video.iter() {
+            let (is_mono, io, mem) = match card.video_type {
+                VideoType::MDA => (true, Some((0x3B0, 0x10)), Some((0xB0000, 0x8000))),
+                VideoType::CGA => (false, Some((0x3D0, 0x10)), Some((0xB8000, 0x8000))),
+                // The TGA's video memory is carved out of conventional memory.
+                VideoType::TGA => (false, Some((0x3D0, 0x10)), None),
+                #[cfg(feature = "ega")]
+                VideoType::EGA => {
+                    if !desc.allow_expansion_video {
+                        return Err(MachineConfigError::ExpansionVideoUnsupported(
+                            desc.machine_type,
+                            card.video_type,
+                        ));
+                    }
+                    (false, Some((0x3C0, 0x10)), Some((0xA0000, 0x10000)))
+                }
+                #[cfg(feature = "vga")]
+                VideoType::VGA => {
+                    if !desc.allow_expansion_video {
+                        return Err(MachineConfigError::ExpansionVideoUnsupported(
+                            desc.machine_type,
+                            card.video_type,
+                        ));
+                    }
+                    (false, Some((0x3C0, 0x10)), Some((0xA0000, 0x10000)))
+                }
+            };
+
+            let slot = if is_mono { &mut mono_card } else { &mut color_card };
+            if let Some(other) = *slot {
+                return Err(MachineConfigError::ConflictingVideoCards(other, card.video_type));
+            }
+            *slot = Some(card.video_type);
+
+            let name = format!("{:?} video card", card.video_type);
+            if let Some((start, len)) = io {
+                io_claims.push(ResourceClaim {
+                    name: name.clone(),
+                    start,
+                    len,
+                });
+            }
+            if let Some((start, len)) = mem {
+                mem_claims.push(ResourceClaim { name, start, len });
+            }
+        }
+
+        // Onboard devices
+        if let Some(port) = desc.onboard_serial {
+            io_claims.push(ResourceClaim {
+                name:  "onboard serial port".to_string(),
+                start: port as u32,
+                len:   8,
+            });
+        }
+        if let Some(port) = desc.onboard_parallel {
+            io_claims.push(ResourceClaim {
+                name:  "onboard parallel port".to_string(),
+                start: port as u32,
+                len:   3,
+            });
+        }
+
+        // Serial ports
+        let mut serial_port_ct = 0;
+        for controller in self.serial.iter() {
+            for (i, port) in controller.port.iter().enumerate() {
+                io_claims.push(ResourceClaim {
+                    name:  format!("serial port {}", i),
+                    start: port.io_base,
+                    len:   8,
+                });
+            }
+            serial_port_ct += controller.port.len();
+        }
+        if let Some(mouse) = &self.serial_mouse {
+            if mouse.port as usize >= serial_port_ct {
+                return Err(MachineConfigError::InvalidSerialMousePort(mouse.port));
+            }
+        }
+
+        // Game port. An onboard game port takes precedence over one specified in the configuration.
+        if let Some(port) = desc.game_port.or(self.game_port.as_ref().map(|gp| gp.io_base)) {
+            io_claims.push(ResourceClaim {
+                name:  "game port".to_string(),
+                start: port as u32,
+                len:   1,
+            });
+        }
+
+        // Real time clock
+        if let Some(rtc) = &self.rtc {
+            if rtc.irq.is_some_and(|irq| irq > 15) {
+                return Err(MachineConfigError::InvalidRtcIrq(rtc.irq.unwrap_or_default()));
+            }
+            io_claims.push(ResourceClaim {
+                name:  "real time clock".to_string(),
+                start: rtc.io_base() as u32,
+                len:   rtc.port_ct(),
+            });
+        }
+
+        // Sound devices
+        for sound in self.sound.iter() {
+            io_claims.push(ResourceClaim {
+                name:  format!("{:?} sound device", sound.sound_type),
+                start: sound.io_base as u32,
+                len:   match sound.sound_type {
+                    SoundType::AdLib => 2,
+                    SoundType::Sn76489 => 1,
+                },
+            });
+        }
+
+        // EMS board. The window is specified as a segment.
+        if let Some(ems) = &self.ems {
+            io_claims.push(ResourceClaim {
+                name:  format!("{:?} EMS board", ems.ems_type),
+                start: ems.io_base as u32,
+                len:   4,
+            });
+            mem_claims.push(ResourceClaim {
+                name:  format!("{:?} EMS window", ems.ems_type),
+                start: ems.window << 4,
+                len:   0x10000,
+            });
+        }
+
+        if let Some(fdc) = &self.fdc {
+            if fdc.drive.len() > MAX_FLOPPY_DRIVES {
+                return Err(MachineConfigError::TooManyFloppyDrives(fdc.drive.len()));
+            }
+        }
+
+        if let Some((port, a, b)) = find_conflict(&io_claims) {
+            return Err(MachineConfigError::IoConflict(
+                port as u16,
+                a.name.clone(),
+                b.name.clone(),
+            ));
+        }
+        if let Some((address, a, b)) = find_conflict(&mem_claims) {
+            return Err(MachineConfigError::MemoryConflict(
+                address,
+                a.name.clone(),
+                b.name.clone(),
+            ));
+        }
+
+        // Check wait state regions.
+        for region in self.wait_state_regions.iter() {
+            if region.start > region.end
+                || region.end > 0xFFFFF
+                || region.read > MAX_REGION_WAIT_STATES
+                || region.write > MAX_REGION_WAIT_STATES
+            {
+                return Err(MachineConfigError::InvalidWaitStateRegion(region.start, region.end));
+            }
+        }
+
+        // Extension ROMs must be mapped where the option ROM scan will find them.
+        for rom in self.extension_roms.iter() {
+            if !(OPTION_ROM_START..OPTION_ROM_SCAN_END).contains(&rom.base_address)
+                || rom.base_address as usize % OPTION_ROM_SCAN_STEP != 0
+            {
+                return Err(MachineConfigError::InvalidExtensionRomAddress(rom.base_address));
+            }
+        }
+
+        // Check ROM patches and hooks. Patch bytes are verified against the ROM when the machine is created.
+        for patch in self.rom_patches.iter() {
+            if patch.original.is_empty() || patch.original.len() != patch.replacement.len() {
+                return Err(MachineConfigError::InvalidRomPatch(patch.segment, patch.offset));
+            }
+        }
+        for hook in self.rom_hooks.iter() {
+            if hook.action == HookAction::Skip(0) {
+                return Err(MachineConfigError::InvalidRomHook(hook.name.clone()));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 lazy_static! {
@@ -363,27 +906,6 @@ impl Default for MachineDescriptor {
     }
 }
 
-impl MachineDescriptor {
-    pub fn is_compatible_configuration(&self, config: &MachineConfiguration) -> bool {
-        // Check CPU compatibility
-        if let Some(cpu_opt) = &config.cpu {
-            if let Some(upgrade_type) = cpu_opt.upgrade_type {
-                if let Some(compatible_cpus) = COMPATIBLE_CPUS.get(&self.cpu_type) {
-                    if !compatible_cpus.contains(&upgrade_type) {
-                        log::warn!(
-                            "Incompatible CPU upgrade specified: {:?} not compatible with base type {:?}",
-                            upgrade_type,
-                            self.cpu_type
-                        );
-                        return false;
-                    }
-                }
-            }
-        }
-        true
-    }
-}
-
 lazy_static! {
     /// Eventually we will want to move these machine definitions into a config file
     /// so that people can define custom architectures.
@@ -471,6 +993,19 @@ pub fn get_machine_descriptor(machine_type: MachineType) -> Option<&'static Mach
     MACHINE_DESCS.get(&machine_type)
 }
 
+/// Return the size of the block of RAM populated contiguously from address 0, given regions sorted by address.
+/// This is the amount of memory visible to the BIOS, which stops counting memory at the first hole.
+pub fn contiguous_ram_size(regions: &[RamRegionConfig]) -> u32 {
+    let mut end = 0;
+    for region in regions.iter() {
+        if region.address > end {
+            break;
+        }
+        end = end.max(region.address + region.size);
+    }
+    end
+}
+
 pub fn normalize_conventional_memory(config: &MachineConfiguration) -> Result<u32, Error> {
     let mut conventional_memory = config.memory.conventional.size;
     conventional_memory = conventional_memory & 0xfffff000; // Normalize to 4K boundary
@@ -534,3 +1069,268 @@ pub fn normalize_conventional_memory(config: &MachineConfiguration) -> Result<u3
         Ok(new_conventional_memory)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_config() -> MachineConfiguration {
+        MachineConfiguration {
+            speaker: true,
+            ppi_turbo: None,
+            machine_type: MachineType::Ibm5160,
+            cpu: None,
+            memory: MemoryConfig {
+                conventional: ConventionalMemoryConfig {
+                    size: 0xA0000,
+                    wait_states: 0,
+                },
+                regions: Vec::new(),
+            },
+            ems: None,
+            keyboard: None,
+            serial_mouse: None,
+            video: vec![VideoCardConfig {
+                video_type:    VideoType::CGA,
+                video_subtype: None,
+                dip_switch:    None,
+                snow:          None,
+            }],
+            sound: Vec::new(),
+            serial: Vec::new(),
+            game_port: None,
+            rtc: None,
+            fdc: None,
+            hdc: None,
+            media: None,
+            post_code_table: None,
+            option_roms: Vec::new(),
+            extension_roms: Vec::new(),
+            io_open_bus_value: None,
+            io_floating_bus: false,
+            profile_regions: Vec::new(),
+            wait_state_regions: Vec::new(),
+            rom_patches: Vec::new(),
+            rom_hooks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn validate_detects_conflicts() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        assert!(config.validate(desc).is_ok());
+
+        // An EMS window overlapping CGA memory
+        config.ems = Some(EmsMemoryConfig {
+            ems_type: EmsType::LoTech2MB,
+            window: 0xB000,
+            io_base: 0x260,
+            size: 0x200000,
+        });
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::MemoryConflict(0xB8000, _, _))
+        ));
+
+        // An AdLib card overlapping the EMS board's IO ports
+        config.ems.as_mut().unwrap().window = 0xE000;
+        config.sound.push(SoundDeviceConfig {
+            sound_type: SoundType::AdLib,
+            io_base:    0x262,
+        });
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::IoConflict(0x262, _, _))
+        ));
+
+        // Two color adapters
+        config.sound.clear();
+        config.video.push(config.video[0].clone());
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::ConflictingVideoCards(_, _))
+        ));
+    }
+
+    #[test]
+    fn ram_regions_with_hole() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        config.memory.regions = vec![
+            RamRegionConfig {
+                address: 0x40000,
+                size:    0x20000,
+            },
+            RamRegionConfig {
+                address: 0,
+                size:    0x20000,
+            },
+        ];
+        assert!(config.validate(desc).is_ok());
+
+        // The BIOS only sees the memory below the first hole.
+        let regions = config.ram_regions().unwrap();
+        assert_eq!(regions[0].address, 0);
+        assert_eq!(contiguous_ram_size(&regions), 0x20000);
+
+        // Closing the hole makes all memory visible.
+        config.memory.regions[1].size = 0x40000;
+        assert_eq!(contiguous_ram_size(&config.ram_regions().unwrap()), 0x60000);
+
+        // A memory map without RAM at address 0 cannot boot.
+        config.memory.regions.remove(1);
+        assert!(matches!(config.validate(desc), Err(MachineConfigError::NoRamAtZero)));
+    }
+
+    #[test]
+    fn cpu_clock_factors() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        assert_eq!(config.cpu_factors(desc), (desc.cpu_factor, desc.cpu_turbo_factor));
+
+        // Clocks derived from the system crystal resolve to integer divisors.
+        config.cpu = Some(CpuConfig {
+            upgrade_type: None,
+            clock: Some(4.77),
+            turbo_clock: Some(7.16),
+        });
+        assert_eq!(
+            config.cpu_factors(desc),
+            (ClockFactor::Divisor(3), ClockFactor::Divisor(2))
+        );
+
+        // A separate 10MHz crystal needs a ratio, which must not drift over a second of cycles.
+        config.cpu.as_mut().unwrap().turbo_clock = Some(10.0);
+        let (_, turbo) = config.cpu_factors(desc);
+        assert!(matches!(turbo, ClockFactor::Ratio(_, _)));
+        assert!((turbo.cpu_mhz(desc.system_crystal) - 10.0).abs() < 0.001);
+        let ticks = turbo.cycles_to_ticks(10_000_000) as f64;
+        assert!((ticks - desc.system_crystal * 1_000_000.0).abs() / ticks < 0.0001);
+        assert!(config.validate(desc).is_ok());
+
+        config.cpu.as_mut().unwrap().clock = Some(0.0);
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidCpuClock(_))
+        ));
+    }
+
+    #[test]
+    fn validate_rom_patches_and_hooks() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        config.rom_patches.push(RomPatchConfig {
+            segment: 0xF000,
+            offset: 0xE0AE,
+            original: vec![0xE8, 0x12, 0x34],
+            replacement: vec![0x90, 0x90, 0x90],
+        });
+        config.rom_hooks.push(RomHookConfig {
+            name:    "memory test".to_string(),
+            segment: 0xF000,
+            offset:  0xE100,
+            action:  HookAction::Skip(3),
+        });
+        assert!(config.validate(desc).is_ok());
+        assert_eq!(config.rom_patches[0].address(), 0xFE0AE);
+        assert_eq!(config.rom_hooks[0].to_hook().address, 0xFE100);
+
+        // A hook that skips nothing would run again on the same instruction forever.
+        config.rom_hooks[0].action = HookAction::Skip(0);
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidRomHook(_))
+        ));
+
+        config.rom_hooks[0].action = HookAction::Ret;
+        config.rom_patches[0].replacement.pop();
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidRomPatch(0xF000, 0xE0AE))
+        ));
+    }
+
+    #[test]
+    fn validate_wait_state_regions() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        config.wait_state_regions.push(WaitStateRegionConfig {
+            start: 0xB8000,
+            end:   0xBBFFF,
+            read:  4,
+            write: 4,
+        });
+        assert!(config.validate(desc).is_ok());
+
+        config.wait_state_regions[0].end = 0x100000;
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidWaitStateRegion(0xB8000, 0x100000))
+        ));
+
+        config.wait_state_regions[0].end = 0xBBFFF;
+        config.wait_state_regions[0].read = MAX_REGION_WAIT_STATES + 1;
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidWaitStateRegion(..))
+        ));
+    }
+
+    #[test]
+    fn validate_extension_roms() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        config.extension_roms.push(ExtensionRomConfig {
+            path: PathBuf::from("xtide.bin"),
+            base_address: 0xC8000,
+        });
+        assert!(config.validate(desc).is_ok());
+
+        // Not on a 2KB boundary
+        config.extension_roms[0].base_address = 0xC8200;
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidExtensionRomAddress(0xC8200))
+        ));
+
+        // Above the end of the option ROM scan
+        config.extension_roms[0].base_address = 0xF4000;
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidExtensionRomAddress(0xF4000))
+        ));
+    }
+
+    #[test]
+    fn validate_xt_clock_card() {
+        let desc = MACHINE_DESCS.get(&MachineType::Ibm5160).unwrap();
+        let mut config = base_config();
+        config.rtc = Some(RtcConfig {
+            rtc_type: RtcType::Mm58167,
+            io_base: None,
+            irq: Some(2),
+            utc_offset: 0,
+            fixed_time: None,
+            host_clock: false,
+        });
+        assert!(config.validate(desc).is_ok());
+
+        // The card's 32 ports overlap an AdLib card.
+        config.sound.push(SoundDeviceConfig {
+            sound_type: SoundType::AdLib,
+            io_base:    0x2D8,
+        });
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::IoConflict(0x2D8, _, _))
+        ));
+
+        config.sound.clear();
+        config.rtc.as_mut().unwrap().irq = Some(16);
+        assert!(matches!(
+            config.validate(desc),
+            Err(MachineConfigError::InvalidRtcIrq(16))
+        ));
+    }
+}
@@ -0,0 +1,390 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    machine_preset.rs
+
+    Defines built-in machine presets. A preset bundles a machine type with a
+    typical memory size, CPU, set of cards and BIOS ROM set, and can produce a
+    complete MachineConfiguration without any configuration files.
+
+*/
+
+use std::{fmt::Display, str::FromStr};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    device_traits::videocard::{VideoCardSubType, VideoType},
+    devices::keyboard::KeyboardType,
+    machine_config::{
+        ConventionalMemoryConfig,
+        CpuConfig,
+        FloppyControllerConfig,
+        FloppyDriveConfig,
+        GamePortConfig,
+        HardDriveControllerConfig,
+        KeyboardConfig,
+        MachineConfiguration,
+        MemoryConfig,
+        SerialControllerConfig,
+        SerialPortConfig,
+        SoundDeviceConfig,
+        VideoCardConfig,
+        GAME_PORT_DEFAULT_IO,
+    },
+    machine_types::{FdcType, FloppyDriveType, MachineType, SerialControllerType, SoundType},
+};
+
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Eq, Hash)]
+pub enum MachinePreset {
+    IbmPc5150,
+    IbmPcXt5160,
+    IbmPcjr,
+    Tandy1000,
+}
+
+/// Optional overrides applied on top of a preset's defaults. Any field left as None keeps the
+/// value the preset would otherwise use.
+#[derive(Clone, Debug, Default)]
+pub struct PresetOverrides {
+    pub conventional_memory: Option<u32>,
+    pub cpu: Option<CpuConfig>,
+    pub video: Option<Vec<VideoCardConfig>>,
+    pub fdc: Option<FloppyControllerConfig>,
+    pub hdc: Option<HardDriveControllerConfig>,
+}
+
+impl Display for MachinePreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for MachinePreset {
+    type Err = String;
+
+    /// Parse a preset from its configuration name, as returned by [MachinePreset::config_name].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MachinePreset::iter()
+            .find(|preset| preset.config_name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Bad value for machine template: {}", s))
+    }
+}
+
+impl MachinePreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MachinePreset::IbmPc5150 => "IBM PC 5150",
+            MachinePreset::IbmPcXt5160 => "IBM PC/XT 5160",
+            MachinePreset::IbmPcjr => "IBM PCjr",
+            MachinePreset::Tandy1000 => "Tandy 1000",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            MachinePreset::IbmPc5150 => "The original IBM PC with 640K RAM, an MDA card and two 360K floppy drives.",
+            MachinePreset::IbmPcXt5160 => "IBM PC/XT with 640K RAM, a CGA card and two 720K floppy drives.",
+            MachinePreset::IbmPcjr => "IBM PCjr with 128K RAM, onboard video, SN76489 sound and a single 360K drive.",
+            MachinePreset::Tandy1000 => "Tandy 1000 with 640K RAM, Tandy graphics, SN76489 sound and two 360K drives.",
+        }
+    }
+
+    /// The ROM set a frontend should resolve for this preset.
+    pub fn rom_set(&self) -> &'static str {
+        match self {
+            MachinePreset::IbmPcjr => "ibm_pcjr",
+            _ => "auto",
+        }
+    }
+
+    /// The name of the equivalent machine configuration shipped in the default configs directory.
+    pub fn config_name(&self) -> &'static str {
+        match self {
+            MachinePreset::IbmPc5150 => "ibm5150",
+            MachinePreset::IbmPcXt5160 => "ibm5160",
+            MachinePreset::IbmPcjr => "ibm_pcjr",
+            MachinePreset::Tandy1000 => "tandy1000",
+        }
+    }
+
+    pub fn machine_type(&self) -> MachineType {
+        match self {
+            MachinePreset::IbmPc5150 => MachineType::Ibm5150v256K,
+            MachinePreset::IbmPcXt5160 => MachineType::Ibm5160,
+            MachinePreset::IbmPcjr => MachineType::IbmPCJr,
+            MachinePreset::Tandy1000 => MachineType::Tandy1000,
+        }
+    }
+
+    pub fn to_machine_config(&self) -> MachineConfiguration {
+        self.to_machine_config_with(&PresetOverrides::default())
+    }
+
+    /// Produce a MachineConfiguration for this preset, with the specified overrides applied.
+    pub fn to_machine_config_with(&self, overrides: &PresetOverrides) -> MachineConfiguration {
+        let mut config = match self {
+            MachinePreset::IbmPc5150 => MachineConfiguration {
+                speaker: true,
+                ppi_turbo: None,
+                machine_type: self.machine_type(),
+                cpu: None,
+                memory: conventional_memory(0xA0000, 0),
+                ems: None,
+                keyboard: Some(keyboard(KeyboardType::ModelF)),
+                serial_mouse: None,
+                video: vec![video_card(VideoType::MDA, None)],
+                sound: Vec::new(),
+                serial: vec![pcxt_serial_ports()],
+                game_port: Some(GamePortConfig {
+                    io_base: GAME_PORT_DEFAULT_IO,
+                }),
+                rtc: None,
+                fdc: Some(floppy_controller(FdcType::IbmNec, FloppyDriveType::Floppy360K, 2)),
+                hdc: None,
+                media: None,
+                post_code_table: None,
+                option_roms: Vec::new(),
+                extension_roms: Vec::new(),
+                io_open_bus_value: None,
+                io_floating_bus: false,
+                profile_regions: Vec::new(),
+                wait_state_regions: Vec::new(),
+                rom_patches: Vec::new(),
+                rom_hooks: Vec::new(),
+            },
+            MachinePreset::IbmPcXt5160 => MachineConfiguration {
+                speaker: true,
+                ppi_turbo: None,
+                machine_type: self.machine_type(),
+                cpu: None,
+                memory: conventional_memory(0xA0000, 0),
+                ems: None,
+                keyboard: Some(keyboard(KeyboardType::ModelF)),
+                serial_mouse: None,
+                video: vec![video_card(VideoType::CGA, None)],
+                sound: Vec::new(),
+                serial: vec![pcxt_serial_ports()],
+                game_port: Some(GamePortConfig {
+                    io_base: GAME_PORT_DEFAULT_IO,
+                }),
+                rtc: None,
+                fdc: Some(floppy_controller(FdcType::IbmNec, FloppyDriveType::Floppy720K, 2)),
+                hdc: None,
+                media: None,
+                post_code_table: None,
+                option_roms: Vec::new(),
+                extension_roms: Vec::new(),
+                io_open_bus_value: None,
+                io_floating_bus: false,
+                profile_regions: Vec::new(),
+                wait_state_regions: Vec::new(),
+                rom_patches: Vec::new(),
+                rom_hooks: Vec::new(),
+            },
+            MachinePreset::IbmPcjr => MachineConfiguration {
+                speaker: true,
+                ppi_turbo: None,
+                machine_type: self.machine_type(),
+                cpu: None,
+                memory: conventional_memory(0x20000, 3),
+                ems: None,
+                keyboard: Some(keyboard(KeyboardType::ModelF)),
+                serial_mouse: None,
+                video: vec![video_card(VideoType::TGA, Some(VideoCardSubType::IbmPCJr))],
+                sound: vec![sn76489()],
+                serial: vec![SerialControllerConfig {
+                    sc_type: SerialControllerType::IbmAsync,
+                    port:    vec![SerialPortConfig {
+                        io_base: 0x2F8,
+                        irq: 3,
+                        out2_suppresses_int: false,
+                    }],
+                }],
+                game_port: None,
+                rtc: None,
+                fdc: Some(floppy_controller(FdcType::IbmPCJrNec, FloppyDriveType::Floppy360K, 1)),
+                hdc: None,
+                media: None,
+                post_code_table: None,
+                option_roms: Vec::new(),
+                extension_roms: Vec::new(),
+                io_open_bus_value: None,
+                io_floating_bus: false,
+                profile_regions: Vec::new(),
+                wait_state_regions: Vec::new(),
+                rom_patches: Vec::new(),
+                rom_hooks: Vec::new(),
+            },
+            MachinePreset::Tandy1000 => MachineConfiguration {
+                speaker: true,
+                ppi_turbo: None,
+                machine_type: self.machine_type(),
+                cpu: None,
+                memory: conventional_memory(0xA0000, 0),
+                ems: None,
+                keyboard: Some(keyboard(KeyboardType::Tandy1000)),
+                serial_mouse: None,
+                video: vec![video_card(VideoType::TGA, Some(VideoCardSubType::Tandy1000))],
+                sound: vec![sn76489()],
+                serial: Vec::new(),
+                game_port: None,
+                rtc: None,
+                fdc: Some(floppy_controller(FdcType::IbmNec, FloppyDriveType::Floppy360K, 2)),
+                hdc: None,
+                media: None,
+                post_code_table: None,
+                option_roms: Vec::new(),
+                extension_roms: Vec::new(),
+                io_open_bus_value: None,
+                io_floating_bus: false,
+                profile_regions: Vec::new(),
+                wait_state_regions: Vec::new(),
+                rom_patches: Vec::new(),
+                rom_hooks: Vec::new(),
+            },
+        };
+
+        if let Some(size) = overrides.conventional_memory {
+            config.memory.conventional.size = size;
+        }
+        if let Some(cpu) = &overrides.cpu {
+            config.cpu = Some(cpu.clone());
+        }
+        if let Some(video) = &overrides.video {
+            config.video = video.clone();
+        }
+        if let Some(fdc) = &overrides.fdc {
+            config.fdc = Some(fdc.clone());
+        }
+        if let Some(hdc) = &overrides.hdc {
+            config.hdc = Some(hdc.clone());
+        }
+        config
+    }
+}
+
+fn conventional_memory(size: u32, wait_states: u32) -> MemoryConfig {
+    MemoryConfig {
+        conventional: ConventionalMemoryConfig { size, wait_states },
+        regions: Vec::new(),
+    }
+}
+
+fn keyboard(kb_type: KeyboardType) -> KeyboardConfig {
+    KeyboardConfig {
+        kb_type,
+        layout: "US".to_string(),
+        typematic: true,
+        typematic_delay: Some(500.0),
+        typematic_rate: Some(50.0),
+    }
+}
+
+fn video_card(video_type: VideoType, video_subtype: Option<VideoCardSubType>) -> VideoCardConfig {
+    VideoCardConfig {
+        video_type,
+        video_subtype,
+        dip_switch: None,
+        snow: None,
+    }
+}
+
+fn sn76489() -> SoundDeviceConfig {
+    SoundDeviceConfig {
+        sound_type: SoundType::Sn76489,
+        io_base:    0xC0,
+    }
+}
+
+fn pcxt_serial_ports() -> SerialControllerConfig {
+    SerialControllerConfig {
+        sc_type: SerialControllerType::IbmAsync,
+        port:    vec![
+            SerialPortConfig {
+                io_base: 0x3F8,
+                irq: 4,
+                out2_suppresses_int: true,
+            },
+            SerialPortConfig {
+                io_base: 0x2F8,
+                irq: 3,
+                out2_suppresses_int: true,
+            },
+        ],
+    }
+}
+
+fn floppy_controller(fdc_type: FdcType, fd_type: FloppyDriveType, drives: usize) -> FloppyControllerConfig {
+    FloppyControllerConfig {
+        fdc_type,
+        drive: (0..drives)
+            .map(|_| FloppyDriveConfig { fd_type, image: None })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine_config::get_machine_descriptor;
+
+    #[test]
+    fn presets_validate() {
+        for preset in MachinePreset::iter() {
+            let config = preset.to_machine_config();
+            let desc = get_machine_descriptor(config.machine_type).unwrap();
+            if let Err(e) = config.validate(desc) {
+                panic!("Preset {} failed validation: {}", preset, e);
+            }
+        }
+    }
+
+    #[test]
+    fn preset_overrides() {
+        let overrides = PresetOverrides {
+            conventional_memory: Some(0x40000),
+            video: Some(vec![video_card(VideoType::MDA, None)]),
+            ..Default::default()
+        };
+        let config = MachinePreset::IbmPcXt5160.to_machine_config_with(&overrides);
+        assert_eq!(config.memory.conventional.size, 0x40000);
+        assert_eq!(config.video.len(), 1);
+        assert_eq!(config.video[0].video_type, VideoType::MDA);
+        // Fields without overrides keep the preset's defaults.
+        assert_eq!(config.fdc.as_ref().unwrap().drive.len(), 2);
+    }
+
+    #[test]
+    fn preset_from_config_name() {
+        for preset in MachinePreset::iter() {
+            assert_eq!(preset.config_name().parse::<MachinePreset>(), Ok(preset));
+        }
+        assert_eq!("IBM5150".parse::<MachinePreset>(), Ok(MachinePreset::IbmPc5150));
+        assert!("ibm5150_256k".parse::<MachinePreset>().is_err());
+    }
+}
@@ -33,10 +33,10 @@
 use core::fmt;
 use fluxfox::StandardFormat;
 use serde::{self, Deserializer};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 use std::{fmt::Display, str::FromStr};
 
-#[derive(Copy, Clone, Debug, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Hash, Eq, PartialEq)]
 pub enum MachineType {
     Default,
     Ibm5150v64K,
@@ -67,6 +67,7 @@ impl FromStr for MachineType {
 #[derive(Copy, Clone, Debug, Deserialize, Hash, Eq, PartialEq)]
 pub enum SoundType {
     AdLib,
+    Sn76489,
 }
 
 impl FromStr for SoundType {
@@ -77,6 +78,7 @@ impl FromStr for SoundType {
     {
         match s.to_lowercase().as_str() {
             "adlib" => Ok(SoundType::AdLib),
+            "sn76489" => Ok(SoundType::Sn76489),
             _ => Err("Bad value for SoundType".to_string()),
         }
     }
@@ -134,6 +136,27 @@ impl FloppyDriveType {
             FloppyDriveType::Floppy144M => vec![StandardFormat::PcFloppy720, StandardFormat::PcFloppy1440],
         }
     }
+
+    /// Return true if this is a 3.5" drive. PC 3.5" drives have no READY signal; pin 34 carries Disk
+    /// Change instead, so the drive appears ready whether or not its motor is running.
+    pub fn is_3_5_inch(&self) -> bool {
+        matches!(self, FloppyDriveType::Floppy720K | FloppyDriveType::Floppy144M)
+    }
+
+    /// Return true if the drive can read high density media.
+    pub fn is_high_density(&self) -> bool {
+        matches!(self, FloppyDriveType::Floppy12M | FloppyDriveType::Floppy144M)
+    }
+
+    /// Return the drive's form factor, for display.
+    pub fn form_factor(&self) -> &'static str {
+        if self.is_3_5_inch() {
+            "3.5\""
+        }
+        else {
+            "5.25\""
+        }
+    }
 }
 
 /// Convert MartyPC's FloppyDriveType to fluxfox's StandardFormat
@@ -143,7 +166,7 @@ impl Into<StandardFormat> for FloppyDriveType {
             FloppyDriveType::Floppy360K => StandardFormat::PcFloppy360,
             FloppyDriveType::Floppy720K => StandardFormat::PcFloppy720,
             FloppyDriveType::Floppy12M => StandardFormat::PcFloppy1200,
-            FloppyDriveType::Floppy144M => StandardFormat::PcFloppy2880,
+            FloppyDriveType::Floppy144M => StandardFormat::PcFloppy1440,
         }
     }
 }
@@ -253,3 +276,13 @@ pub enum SerialMouseType {
 pub enum EmsType {
     LoTech2MB,
 }
+
+/// The real time clock chip to emulate.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum RtcType {
+    /// The MC146818 of the IBM AT, with CMOS RAM.
+    #[default]
+    Mc146818,
+    /// The MM58167 found on XT clock cards such as the AST SixPakPlus.
+    Mm58167,
+}
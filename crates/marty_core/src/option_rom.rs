@@ -0,0 +1,238 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    option_rom.rs
+
+    Validation of option ROM images. An option ROM begins with the 55AAh
+    signature followed by a length byte in 512 byte blocks, and all bytes
+    within that length must sum to zero. The BIOS scans for this signature
+    during POST and makes a far call to offset 3 of each ROM it finds.
+
+*/
+
+use std::{error::Error, fmt, fmt::Display};
+
+pub const OPTION_ROM_SIGNATURE: [u8; 2] = [0x55, 0xAA];
+pub const OPTION_ROM_BLOCK_SIZE: usize = 512;
+pub const OPTION_ROM_INIT_OFFSET: u32 = 3;
+/// Lowest address an option ROM may be mapped at.
+pub const OPTION_ROM_START: u32 = 0xC0000;
+pub const OPTION_ROM_END: u32 = 0x100000;
+/// The BIOS scans for option ROMs from OPTION_ROM_START up to (but not including) this address.
+pub const OPTION_ROM_SCAN_END: u32 = 0xF4000;
+/// Option ROMs must begin on a 2KB boundary to be found by the BIOS scan.
+pub const OPTION_ROM_SCAN_STEP: usize = 0x800;
+
+#[derive(Debug)]
+pub enum OptionRomError {
+    TooShort(usize),
+    BadSignature(u8, u8),
+    BadLength(usize, usize),
+    BadChecksum(u8),
+    OutOfRange(u16, usize),
+    Overlap(u16, u32),
+}
+impl Error for OptionRomError {}
+impl Display for OptionRomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionRomError::TooShort(len) => write!(f, "Image of {} bytes is too short to be an option ROM", len),
+            OptionRomError::BadSignature(b0, b1) => {
+                write!(f, "Bad signature: expected 55AA, found {:02X}{:02X}", b0, b1)
+            }
+            OptionRomError::BadLength(declared, len) => write!(
+                f,
+                "Declared length of {} bytes exceeds image size of {} bytes",
+                declared, len
+            ),
+            OptionRomError::BadChecksum(sum) => write!(f, "Bad checksum: bytes sum to {:02X}h", sum),
+            OptionRomError::OutOfRange(segment, len) => write!(
+                f,
+                "ROM of {} bytes at segment {:04X} is outside the option ROM area",
+                len, segment
+            ),
+            OptionRomError::Overlap(segment, addr) => {
+                write!(f, "ROM at segment {:04X} overlaps another ROM at {:05X}", segment, addr)
+            }
+        }
+    }
+}
+
+/// A validated option ROM image, ready to be mapped at its target segment.
+#[derive(Clone, Debug)]
+pub struct OptionRom {
+    pub name: String,
+    pub segment: u16,
+    pub data: Vec<u8>,
+    pub checksum_fixed: bool,
+}
+
+/// The state of a mapped option ROM, for display by a debugger.
+#[derive(Clone, Debug)]
+pub struct OptionRomStatus {
+    pub name: String,
+    pub segment: u16,
+    pub size: usize,
+    pub checksum_fixed: bool,
+    pub init_called: bool,
+}
+
+/// Return the 8-bit sum of all bytes in the slice.
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+impl OptionRom {
+    /// Validate an option ROM image. The image is truncated to the length declared in its header.
+    /// If `fix_checksum` is set, a bad checksum is corrected by adjusting the last byte of the ROM
+    /// instead of being treated as an error; this is useful for ROMs under development.
+    pub fn new(name: &str, segment: u16, mut data: Vec<u8>, fix_checksum: bool) -> Result<Self, OptionRomError> {
+        if data.len() < 3 {
+            return Err(OptionRomError::TooShort(data.len()));
+        }
+        if data[0..2] != OPTION_ROM_SIGNATURE {
+            return Err(OptionRomError::BadSignature(data[0], data[1]));
+        }
+        let declared = data[2] as usize * OPTION_ROM_BLOCK_SIZE;
+        if declared == 0 || declared > data.len() {
+            return Err(OptionRomError::BadLength(declared, data.len()));
+        }
+        data.truncate(declared);
+
+        let address = (segment as u32) << 4;
+        if address < OPTION_ROM_START || address + declared as u32 > OPTION_ROM_END {
+            return Err(OptionRomError::OutOfRange(segment, declared));
+        }
+
+        let mut checksum_fixed = false;
+        let sum = checksum(&data);
+        if sum != 0 {
+            if !fix_checksum {
+                return Err(OptionRomError::BadChecksum(sum));
+            }
+            let last = data.len() - 1;
+            data[last] = data[last].wrapping_sub(sum);
+            checksum_fixed = true;
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            segment,
+            data,
+            checksum_fixed,
+        })
+    }
+
+    pub fn address(&self) -> u32 {
+        (self.segment as u32) << 4
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The address of the ROM's initialization entry point, called by the BIOS during POST.
+    pub fn init_address(&self) -> u32 {
+        self.address() + OPTION_ROM_INIT_OFFSET
+    }
+
+    /// Return true if this ROM overlaps the specified address range.
+    pub fn overlaps(&self, address: u32, len: usize) -> bool {
+        let start = self.address();
+        let end = start + self.data.len() as u32;
+        address < end && address + len as u32 > start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_rom(blocks: u8) -> Vec<u8> {
+        let mut data = vec![0u8; blocks as usize * OPTION_ROM_BLOCK_SIZE];
+        data[0] = 0x55;
+        data[1] = 0xAA;
+        data[2] = blocks;
+        // RETF at the init entry point
+        data[3] = 0xCB;
+        let last = data.len() - 1;
+        data[last] = 0u8.wrapping_sub(checksum(&data));
+        data
+    }
+
+    #[test]
+    fn valid_rom() {
+        let mut data = make_rom(4);
+        // Trailing padding beyond the declared length is dropped.
+        data.extend_from_slice(&[0xFF; 16]);
+        let rom = OptionRom::new("test", 0xC800, data, false).unwrap();
+        assert_eq!(rom.size(), 4 * OPTION_ROM_BLOCK_SIZE);
+        assert_eq!(rom.init_address(), 0xC8003);
+        assert!(!rom.checksum_fixed);
+        assert!(rom.overlaps(0xC8000, 1));
+        assert!(rom.overlaps(0xC7FFF, 2));
+        assert!(!rom.overlaps(0xC8800, 0x800));
+    }
+
+    #[test]
+    fn invalid_roms() {
+        let mut data = make_rom(1);
+        data[1] = 0x00;
+        assert!(matches!(
+            OptionRom::new("test", 0xC800, data, false),
+            Err(OptionRomError::BadSignature(0x55, 0x00))
+        ));
+
+        let mut data = make_rom(1);
+        data[2] = 2;
+        assert!(matches!(
+            OptionRom::new("test", 0xC800, data, false),
+            Err(OptionRomError::BadLength(1024, 512))
+        ));
+
+        assert!(matches!(
+            OptionRom::new("test", 0xB800, make_rom(1), false),
+            Err(OptionRomError::OutOfRange(0xB800, 512))
+        ));
+        assert!(matches!(
+            OptionRom::new("test", 0xFFF0, make_rom(1), false),
+            Err(OptionRomError::OutOfRange(0xFFF0, 512))
+        ));
+    }
+
+    #[test]
+    fn checksum_fix() {
+        let mut data = make_rom(1);
+        data[4] = 0x12;
+        assert!(matches!(
+            OptionRom::new("test", 0xC800, data.clone(), false),
+            Err(OptionRomError::BadChecksum(0x12))
+        ));
+        let rom = OptionRom::new("test", 0xC800, data, true).unwrap();
+        assert!(rom.checksum_fixed);
+        assert_eq!(checksum(&rom.data), 0);
+    }
+}
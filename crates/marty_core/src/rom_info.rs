@@ -0,0 +1,396 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    rom_info.rs
+
+    Identification of the ROM images loaded by a machine. ROMs are matched
+    by hash against the known-good dumps listed in the ROM definition files,
+    and system BIOS ROMs are identified by the date string the BIOS keeps at
+    FFFF:0005.
+
+*/
+
+use std::{fmt, fmt::Display, path::PathBuf};
+
+use crate::{machine_config::get_base_rom_features, machine_types::MachineType};
+
+/// Flat address of the BIOS date string at FFFF:0005.
+pub const BIOS_DATE_ADDRESS: u32 = 0xFFFF5;
+pub const BIOS_DATE_LEN: usize = 8;
+/// Flat address of the part number and copyright string in the IBM BIOS, at F000:E000.
+pub const BIOS_COPYRIGHT_ADDRESS: u32 = 0xFE000;
+const COPYRIGHT_MAX_LEN: usize = 80;
+const COPYRIGHT_MARKERS: [&[u8]; 3] = [b"COPR", b"COPYRIGHT", b"(C)"];
+
+/// The machine types a ROM set can be specific to.
+const ROM_MACHINE_TYPES: [MachineType; 5] = [
+    MachineType::Ibm5150v64K,
+    MachineType::Ibm5150v256K,
+    MachineType::Ibm5160,
+    MachineType::IbmPCJr,
+    MachineType::Tandy1000,
+];
+
+/// A known-good ROM dump, from a ROM definition file.
+#[derive(Clone, Debug)]
+pub struct KnownRom {
+    pub md5: String,
+    pub name: String,
+    /// The machines this ROM is valid for. An empty list means any machine.
+    pub machines: Vec<MachineType>,
+}
+
+impl KnownRom {
+    /// Merge another definition of the same dump. A ROM listed by several ROM sets is valid for all of
+    /// their machines.
+    pub fn merge(&mut self, other: &KnownRom) {
+        if self.machines.is_empty() || other.machines.is_empty() {
+            self.machines.clear();
+            return;
+        }
+        for machine in other.machines.iter() {
+            if !self.machines.contains(machine) {
+                self.machines.push(*machine);
+            }
+        }
+    }
+}
+
+/// Return the machine types whose base ROM features are among `features`, the features a ROM set
+/// provides. A ROM set that provides no machine feature, such as a video BIOS, is valid for any machine
+/// and returns an empty list.
+pub fn machines_for_features(features: &[String]) -> Vec<MachineType> {
+    ROM_MACHINE_TYPES
+        .iter()
+        .filter(|machine_type| {
+            get_base_rom_features(**machine_type)
+                .is_some_and(|base| base.iter().any(|f| features.iter().any(|feature| feature == f)))
+        })
+        .copied()
+        .collect()
+}
+
+/// A BIOS version identified by the date string at FFFF:0005.
+pub struct KnownBiosDate {
+    pub date: &'static str,
+    pub name: &'static str,
+}
+
+#[rustfmt::skip]
+pub const KNOWN_BIOS_DATES: &[KnownBiosDate] = &[
+    KnownBiosDate { date: "04/24/81", name: "IBM 5150 BIOS v1" },
+    KnownBiosDate { date: "10/19/81", name: "IBM 5150 BIOS v2" },
+    KnownBiosDate { date: "10/27/82", name: "IBM 5150 BIOS v3" },
+    KnownBiosDate { date: "11/08/82", name: "IBM 5160 BIOS v1" },
+    KnownBiosDate { date: "01/10/86", name: "IBM 5160 BIOS v2" },
+    KnownBiosDate { date: "05/09/86", name: "IBM 5160 BIOS v3" },
+    KnownBiosDate { date: "06/01/83", name: "IBM PCjr BIOS" },
+];
+
+/// The result of checking a ROM against the known-good dumps for a machine.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RomVerification {
+    /// The ROM matches a known-good dump for this machine.
+    Verified,
+    /// The ROM matches a known-good dump for a different machine.
+    WrongMachine,
+    /// The ROM's date string identifies a known BIOS version, but its hash doesn't match any known-good
+    /// dump. The image is likely a bad dump.
+    BadDump,
+    /// The ROM doesn't match any known-good dump.
+    Unknown,
+}
+
+impl RomVerification {
+    /// Whether the ROM should be flagged to the user. Any ROM that isn't a known-good dump for this
+    /// machine is flagged.
+    pub fn is_warning(&self) -> bool {
+        !matches!(self, RomVerification::Verified)
+    }
+}
+
+impl Display for RomVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomVerification::Verified => write!(f, "Matches a known-good dump"),
+            RomVerification::WrongMachine => write!(f, "Known-good dump, but for a different machine"),
+            RomVerification::BadDump => write!(f, "Identifies as a known BIOS, but doesn't match any known-good dump"),
+            RomVerification::Unknown => write!(f, "Doesn't match any known-good dump"),
+        }
+    }
+}
+
+/// Information about a ROM image loaded by the machine, computed when it is loaded.
+#[derive(Clone, Debug)]
+pub struct RomInfo {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub address: u32,
+    pub size: usize,
+    pub crc32: u32,
+    pub md5: String,
+    /// The BIOS date string at FFFF:0005, if this ROM is mapped there.
+    pub date: Option<String>,
+    pub copyright: Option<String>,
+    /// Best-effort identification of the ROM.
+    pub identity: Option<String>,
+    pub verification: RomVerification,
+}
+
+impl RomInfo {
+    /// Gather information about a ROM image mapped at `address`. The `md5` and `crc32` are those of the
+    /// image file, which may differ from `data` if the image was truncated or reordered when mapped.
+    /// The image is verified against `known_roms`.
+    pub fn new(
+        name: String,
+        path: Option<PathBuf>,
+        address: u32,
+        data: &[u8],
+        md5: String,
+        crc32: u32,
+        machine_type: MachineType,
+        known_roms: &[KnownRom],
+    ) -> Self {
+        let date = bios_date(address, data);
+        let known = known_roms.iter().find(|rom| rom.md5.eq_ignore_ascii_case(&md5));
+        let known_date = date
+            .as_ref()
+            .and_then(|date| KNOWN_BIOS_DATES.iter().find(|known| known.date == date));
+
+        let verification = match (known, known_date) {
+            (Some(rom), _) if rom.machines.is_empty() || rom.machines.contains(&machine_type) => {
+                RomVerification::Verified
+            }
+            (Some(_), _) => RomVerification::WrongMachine,
+            (None, Some(_)) => RomVerification::BadDump,
+            (None, None) => RomVerification::Unknown,
+        };
+
+        let identity = known
+            .map(|rom| rom.name.clone())
+            .or_else(|| known_date.map(|known| known.name.to_string()))
+            .or_else(|| date.as_ref().map(|date| format!("Unknown BIOS dated {}", date)));
+
+        Self {
+            name,
+            path,
+            address,
+            size: data.len(),
+            crc32,
+            md5,
+            date,
+            copyright: copyright_string(address, data),
+            identity,
+            verification,
+        }
+    }
+
+    /// The last address covered by the ROM.
+    pub fn end_address(&self) -> u32 {
+        self.address + (self.size.max(1) as u32) - 1
+    }
+}
+
+/// Calculate the CRC32 of a ROM image.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Read the BIOS date string at FFFF:0005 from a ROM mapped at `address`. Returns None if the ROM
+/// doesn't cover that address or the string isn't a valid MM/DD/YY date.
+pub fn bios_date(address: u32, data: &[u8]) -> Option<String> {
+    let offset = BIOS_DATE_ADDRESS.checked_sub(address)? as usize;
+    let date = data.get(offset..offset + BIOS_DATE_LEN)?;
+    let valid = date.iter().enumerate().all(|(i, c)| {
+        if i == 2 || i == 5 {
+            *c == b'/'
+        }
+        else {
+            c.is_ascii_digit()
+        }
+    });
+    valid.then(|| String::from_utf8_lossy(date).to_string())
+}
+
+/// Find a copyright string in a ROM mapped at `address`. The standard location in the IBM BIOS is
+/// checked first, then the rest of the image is searched.
+pub fn copyright_string(address: u32, data: &[u8]) -> Option<String> {
+    if let Some(offset) = BIOS_COPYRIGHT_ADDRESS.checked_sub(address) {
+        if let Some(s) = printable_run(data, offset as usize).filter(|s| is_copyright(s)) {
+            return Some(s);
+        }
+    }
+    let upper = data.to_ascii_uppercase();
+    let pos = COPYRIGHT_MARKERS
+        .iter()
+        .filter_map(|marker| find_marker(&upper, marker))
+        .min()?;
+    printable_run(data, pos)
+}
+
+fn is_copyright(s: &str) -> bool {
+    let upper = s.to_ascii_uppercase();
+    COPYRIGHT_MARKERS
+        .iter()
+        .any(|marker| find_marker(upper.as_bytes(), marker).is_some())
+}
+
+fn find_marker(data: &[u8], marker: &[u8]) -> Option<usize> {
+    data.windows(marker.len()).position(|w| w == marker)
+}
+
+/// Return the run of printable ASCII characters around `pos`, if any.
+fn printable_run(data: &[u8], pos: usize) -> Option<String> {
+    let printable = |c: &u8| (0x20..0x7F).contains(c);
+    if !data.get(pos).is_some_and(printable) {
+        return None;
+    }
+    let start = data[..pos].iter().rposition(|c| !printable(c)).map_or(0, |i| i + 1);
+    let end = data[pos..]
+        .iter()
+        .position(|c| !printable(c))
+        .map_or(data.len(), |i| pos + i);
+    let end = end.min(start + COPYRIGHT_MAX_LEN);
+    let run = String::from_utf8_lossy(&data[start..end]).trim().to_string();
+    (!run.is_empty()).then_some(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_bios(date: &[u8; 8], copyright: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; 0x2000];
+        data[..copyright.len()].copy_from_slice(copyright);
+        let offset = (BIOS_DATE_ADDRESS - 0xFE000) as usize;
+        data[offset..offset + 8].copy_from_slice(date);
+        data
+    }
+
+    fn known_roms() -> Vec<KnownRom> {
+        vec![KnownRom {
+            md5: "69e2bd1d08c893cbf841607c8749d5bd".to_string(),
+            name: "IBM 5160 BIOS v1 11/08/82 (u19)".to_string(),
+            machines: vec![MachineType::Ibm5160],
+        }]
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_identify_bios() {
+        let data = make_bios(b"11/08/82", b"62X0851 COPR. IBM 1982");
+        let info = RomInfo::new(
+            "bios.bin".to_string(),
+            None,
+            0xFE000,
+            &data,
+            "00000000000000000000000000000000".to_string(),
+            crc32(&data),
+            MachineType::Ibm5160,
+            &known_roms(),
+        );
+        assert_eq!(info.date.as_deref(), Some("11/08/82"));
+        assert_eq!(info.copyright.as_deref(), Some("62X0851 COPR. IBM 1982"));
+        assert_eq!(info.identity.as_deref(), Some("IBM 5160 BIOS v1"));
+        assert_eq!(info.verification, RomVerification::BadDump);
+        assert!(info.verification.is_warning());
+        assert_eq!(info.end_address(), 0xFFFFF);
+
+        // A known-good dump is verified only for the machines it belongs to.
+        let known = |machine_type| {
+            RomInfo::new(
+                "u19.bin".to_string(),
+                None,
+                0xFE000,
+                &data,
+                "69E2BD1D08C893CBF841607C8749D5BD".to_string(),
+                0,
+                machine_type,
+                &known_roms(),
+            )
+        };
+        assert_eq!(known(MachineType::Ibm5160).verification, RomVerification::Verified);
+        assert_eq!(
+            known(MachineType::Tandy1000).verification,
+            RomVerification::WrongMachine
+        );
+    }
+
+    #[test]
+    fn test_unknown_rom() {
+        // Not mapped at the BIOS date address, so no date is read.
+        let mut data = vec![0xFFu8; 0x1000];
+        data[0x100..0x110].copy_from_slice(b"(C)Copyright XYZ");
+        assert_eq!(bios_date(0xC8000, &data), None);
+        assert_eq!(copyright_string(0xC8000, &data).as_deref(), Some("(C)Copyright XYZ"));
+
+        let info = RomInfo::new(
+            "x.bin".to_string(),
+            None,
+            0xC8000,
+            &data,
+            String::new(),
+            0,
+            MachineType::Ibm5160,
+            &known_roms(),
+        );
+        assert_eq!(info.identity, None);
+        assert_eq!(info.verification, RomVerification::Unknown);
+        assert!(info.verification.is_warning());
+
+        // An invalid date string is ignored.
+        let data = make_bios(b"xx/08/82", b"");
+        assert_eq!(bios_date(0xFE000, &data), None);
+    }
+
+    #[test]
+    fn test_known_rom_machines() {
+        let features = |list: &[&str]| list.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            machines_for_features(&features(&["bios", "ibm5150v64k", "ibm5150v256k", "expansion"])),
+            vec![MachineType::Ibm5150v64K, MachineType::Ibm5150v256K]
+        );
+        assert!(machines_for_features(&features(&["ibm_ega"])).is_empty());
+
+        let mut rom = known_roms().remove(0);
+        rom.merge(&KnownRom {
+            machines: vec![MachineType::Ibm5150v256K],
+            ..rom.clone()
+        });
+        assert_eq!(rom.machines, vec![MachineType::Ibm5160, MachineType::Ibm5150v256K]);
+        rom.merge(&KnownRom {
+            machines: Vec::new(),
+            ..rom.clone()
+        });
+        assert!(rom.machines.is_empty());
+    }
+}
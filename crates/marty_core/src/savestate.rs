@@ -0,0 +1,509 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    --------------------------------------------------------------------------
+
+    savestate.rs
+
+    Defines the machine save state container and the SaveState trait
+    implemented by devices that can snapshot and restore their state.
+
+    A save state file consists of an 8-byte magic, a little-endian u32 format
+    version, and a deflate-compressed payload. The payload holds a u32 length
+    prefixed JSON header describing the CPU and device state, followed by a
+    raw copy of the address space.
+*/
+
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{Read, Write},
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    cpu_common::{Cpu, CpuDispatch, CpuType, Register16},
+    device_traits::{realtimeclock::RtcDispatchSaveState, videocard::VideoCardSaveState},
+    devices::{
+        a0::A0SaveState,
+        cartridge_slots::CartridgeSlotSaveState,
+        dma::DMAController,
+        fdc::FdcSaveState,
+        game_port::GamePortSaveState,
+        hdc::{xebec::HdcSaveState, xtide::XtIdeSaveState},
+        keyboard::KeyboardSaveState,
+        lotech_ems::EmsSaveState,
+        lpt_port::ParallelPortSaveState,
+        mouse::MouseSaveState,
+        pic::Pic,
+        pit::PitSaveState,
+        ppi::PpiSaveState,
+        serial::SerialPortSaveState,
+    },
+    machine_types::MachineType,
+};
+
+#[cfg(feature = "opl")]
+use crate::devices::adlib::AdLibSaveState;
+#[cfg(feature = "sound")]
+use crate::devices::sn76489::Sn76489SaveState;
+
+pub const SAVE_STATE_MAGIC: &[u8; 8] = b"MARTYSAV";
+/// Current save state format version. Bump this when the layout of [MachineSaveState] changes
+/// in a way that serde defaults cannot paper over.
+pub const SAVE_STATE_VERSION: u32 = 1;
+pub const SAVE_STATE_EXTENSION: &str = "msav";
+const HEADER_LEN: usize = SAVE_STATE_MAGIC.len() + 4;
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    Corrupt(String),
+    MachineMismatch(MachineType, MachineType),
+    CpuMismatch(CpuType, CpuType),
+    MemorySizeMismatch(usize, usize),
+    MediaMismatch(usize),
+    MissingDeviceState(String),
+    FloppyModified(usize),
+    HardDiskModified(usize),
+}
+impl Error for SaveStateError {}
+impl Display for SaveStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveStateError::Io(e) => write!(f, "IO error accessing save state: {}", e),
+            SaveStateError::BadMagic => write!(f, "File is not a MartyPC save state."),
+            SaveStateError::UnsupportedVersion(v) => write!(
+                f,
+                "Save state format version {} is not supported by this version of MartyPC (expected {} or older).",
+                v, SAVE_STATE_VERSION
+            ),
+            SaveStateError::Corrupt(s) => write!(f, "Save state is corrupt or incompatible: {}", s),
+            SaveStateError::MachineMismatch(saved, current) => write!(
+                f,
+                "Save state was made on a {:?} machine, but the current machine is a {:?}.",
+                saved, current
+            ),
+            SaveStateError::CpuMismatch(saved, current) => write!(
+                f,
+                "Save state was made with a {:?} CPU, but the current CPU is a {:?}.",
+                saved, current
+            ),
+            SaveStateError::MemorySizeMismatch(saved, current) => write!(
+                f,
+                "Save state address space is {} bytes, but the current machine has {} bytes.",
+                saved, current
+            ),
+            SaveStateError::MediaMismatch(drive) => write!(
+                f,
+                "The disk in floppy drive {} does not match the disk present when the state was saved.",
+                drive
+            ),
+            SaveStateError::MissingDeviceState(device) => {
+                write!(f, "Save state does not contain the state of the {}.", device)
+            }
+            SaveStateError::FloppyModified(drive) => write!(
+                f,
+                "The disk in floppy drive {} has been written to since it was inserted.",
+                drive
+            ),
+            SaveStateError::HardDiskModified(drive) => {
+                write!(f, "Hard disk {} has been written to since it was mounted.", drive)
+            }
+        }
+    }
+}
+impl From<std::io::Error> for SaveStateError {
+    fn from(e: std::io::Error) -> Self {
+        SaveStateError::Io(e)
+    }
+}
+
+/// A device that can produce a serializable snapshot of its internal state and later restore it.
+/// Host resources such as sound channels or file handles are never part of the snapshot and
+/// must be preserved by `restore_state`.
+pub trait SaveState {
+    type State: Serialize + DeserializeOwned;
+
+    fn save_state(&self) -> Self::State;
+    fn restore_state(&mut self, state: Self::State);
+}
+
+/// The programmer-visible CPU state. Save states are only taken between instructions, so the
+/// prefetch queue is flushed on restore and refilled from the restored CS:IP.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CpuSaveState {
+    pub ax:    u16,
+    pub bx:    u16,
+    pub cx:    u16,
+    pub dx:    u16,
+    pub sp:    u16,
+    pub bp:    u16,
+    pub si:    u16,
+    pub di:    u16,
+    pub cs:    u16,
+    pub ds:    u16,
+    pub ss:    u16,
+    pub es:    u16,
+    pub ip:    u16,
+    pub flags: u16,
+    #[serde(default)]
+    pub exec:  CpuExecState,
+}
+
+/// CPU execution state that is not visible in the registers, but that decides what the CPU does
+/// next - whether it is halted, whether an interrupt or trap is pending or inhibited.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuExecState {
+    pub halted: bool,
+    pub interrupt_inhibit: bool,
+    pub intr: bool,
+    pub intr_pending: bool,
+    pub nmi: bool,
+    pub nmi_triggered: bool,
+    pub trap_enable_delay: u32,
+    pub trap_disable_delay: u32,
+    pub trap_suppressed: bool,
+}
+
+impl CpuSaveState {
+    const REGISTERS: [Register16; 12] = [
+        Register16::AX,
+        Register16::BX,
+        Register16::CX,
+        Register16::DX,
+        Register16::SP,
+        Register16::BP,
+        Register16::SI,
+        Register16::DI,
+        Register16::CS,
+        Register16::DS,
+        Register16::SS,
+        Register16::ES,
+    ];
+
+    /// Capture the state of `cpu`. Must be called between instructions.
+    pub fn capture(cpu: &mut CpuDispatch) -> Self {
+        let [ax, bx, cx, dx, sp, bp, si, di, cs, ds, ss, es] = Self::REGISTERS.map(|reg| cpu.get_register16(reg));
+        CpuSaveState {
+            ax,
+            bx,
+            cx,
+            dx,
+            sp,
+            bp,
+            si,
+            di,
+            cs,
+            ds,
+            ss,
+            es,
+            ip: cpu.get_ip(),
+            flags: cpu.get_flags(),
+            exec: cpu.exec_state(),
+        }
+    }
+
+    /// Restore this state into `cpu`. The prefetch queue is flushed and will be refilled from CS:IP.
+    pub fn apply(&self, cpu: &mut CpuDispatch) {
+        cpu.flush_piq();
+        let values = [
+            self.ax, self.bx, self.cx, self.dx, self.sp, self.bp, self.si, self.di, self.cs, self.ds, self.ss, self.es,
+        ];
+        for (reg, value) in Self::REGISTERS.into_iter().zip(values) {
+            cpu.set_register16(reg, value);
+        }
+        cpu.set_register16(Register16::PC, self.ip);
+        cpu.set_flags(self.flags);
+        cpu.set_exec_state(self.exec);
+    }
+}
+
+/// The state of the devices on the bus. Each entry is None if the device is not present on the
+/// saved machine.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceSaveState {
+    pub pic1: Option<Pic>,
+    pub pic2: Option<Pic>,
+    pub pit: Option<PitSaveState>,
+    pub dma1: Option<DMAController>,
+    pub dma2: Option<DMAController>,
+    pub fdc: Option<FdcSaveState>,
+    pub hdc: Option<HdcSaveState>,
+    pub xtide: Option<XtIdeSaveState>,
+    pub ppi: Option<PpiSaveState>,
+    pub a0: Option<A0SaveState>,
+    pub keyboard: Option<KeyboardSaveState>,
+    pub serial: Option<Vec<SerialPortSaveState>>,
+    pub parallel: Option<ParallelPortSaveState>,
+    pub mouse: Option<MouseSaveState>,
+    pub ems: Option<EmsSaveState>,
+    pub cart_slot: Option<CartridgeSlotSaveState>,
+    pub game_port: Option<GamePortSaveState>,
+    pub rtc: Option<RtcDispatchSaveState>,
+    /// Video cards, in the order they were installed.
+    pub video: Option<Vec<VideoCardSaveState>>,
+    #[cfg(feature = "opl")]
+    pub adlib: Option<AdLibSaveState>,
+    #[cfg(feature = "sound")]
+    pub sn76489: Option<Sn76489SaveState>,
+}
+
+/// A reference to a floppy image that was inserted when the state was saved. Disk contents are
+/// not stored in the save state, so restoring requires the same, unmodified image to be present.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MediaReference {
+    pub drive: usize,
+    /// Hash of the image file as it was loaded, if known.
+    pub hash:  Option<u64>,
+    /// Whether the guest had written to the image before the state was saved.
+    pub dirty: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MachineSaveState {
+    pub machine_type: MachineType,
+    pub cpu_type: CpuType,
+    pub cpu: CpuSaveState,
+    #[serde(default)]
+    pub devices: DeviceSaveState,
+    #[serde(default)]
+    pub media: Vec<MediaReference>,
+    /// Raw contents of the address space. Stored outside the JSON header.
+    #[serde(skip)]
+    pub memory: Vec<u8>,
+}
+
+impl MachineSaveState {
+    /// Serialize the save state into its compressed file representation.
+    pub fn write(&self) -> Result<Vec<u8>, SaveStateError> {
+        let header = serde_json::to_vec(self).map_err(|e| SaveStateError::Corrupt(e.to_string()))?;
+
+        let mut out = Vec::with_capacity(HEADER_LEN + self.memory.len() / 2);
+        out.extend_from_slice(SAVE_STATE_MAGIC);
+        out.extend_from_slice(&SAVE_STATE_VERSION.to_le_bytes());
+
+        let mut encoder = DeflateEncoder::new(out, Compression::default());
+        encoder.write_all(&(header.len() as u32).to_le_bytes())?;
+        encoder.write_all(&header)?;
+        encoder.write_all(&self.memory)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Parse a save state from its compressed file representation.
+    pub fn read(data: &[u8]) -> Result<Self, SaveStateError> {
+        if data.len() < HEADER_LEN || &data[0..SAVE_STATE_MAGIC.len()] != SAVE_STATE_MAGIC {
+            return Err(SaveStateError::BadMagic);
+        }
+        let version = u32::from_le_bytes(data[SAVE_STATE_MAGIC.len()..HEADER_LEN].try_into().unwrap());
+        if version == 0 || version > SAVE_STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion(version));
+        }
+
+        let mut payload = Vec::new();
+        DeflateDecoder::new(&data[HEADER_LEN..])
+            .read_to_end(&mut payload)
+            .map_err(|e| SaveStateError::Corrupt(e.to_string()))?;
+
+        if payload.len() < 4 {
+            return Err(SaveStateError::Corrupt("payload truncated".to_string()));
+        }
+        let header_len = u32::from_le_bytes(payload[0..4].try_into().unwrap()) as usize;
+        if payload.len() < 4 + header_len {
+            return Err(SaveStateError::Corrupt("header truncated".to_string()));
+        }
+
+        let mut state: MachineSaveState =
+            serde_json::from_slice(&payload[4..4 + header_len]).map_err(|e| SaveStateError::Corrupt(e.to_string()))?;
+        state.memory = payload.split_off(4 + header_len);
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu_808x::CPU_FLAG_INT_ENABLE,
+        cpu_common::{
+            test_util::{cpu_with_code, step, CODE_SEG},
+            CpuAddress,
+        },
+    };
+
+    const HANDLER_SEG: u16 = 0x0800;
+
+    fn test_state() -> MachineSaveState {
+        MachineSaveState {
+            machine_type: MachineType::Ibm5160,
+            cpu_type: CpuType::Intel8088,
+            cpu: CpuSaveState {
+                ax: 0x1234,
+                cs: 0xF000,
+                ip: 0xFFF0,
+                flags: 0xF002,
+                ..Default::default()
+            },
+            devices: DeviceSaveState {
+                pic1: Some(Pic::default()),
+                ..Default::default()
+            },
+            media: vec![MediaReference {
+                drive: 0,
+                hash:  Some(0xDEAD_BEEF),
+                dirty: false,
+            }],
+            memory: (0..0x1000).map(|i| (i & 0xFF) as u8).collect(),
+        }
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let state = test_state();
+        let data = state.write().unwrap();
+        assert_eq!(&data[0..8], SAVE_STATE_MAGIC);
+
+        let restored = MachineSaveState::read(&data).unwrap();
+        assert_eq!(restored.machine_type, state.machine_type);
+        assert_eq!(restored.cpu_type, state.cpu_type);
+        assert_eq!(restored.cpu, state.cpu);
+        assert_eq!(restored.media, state.media);
+        assert_eq!(restored.memory, state.memory);
+        assert!(restored.devices.pic1.is_some());
+        assert!(restored.devices.pit.is_none());
+    }
+
+    #[test]
+    fn save_state_rejects_bad_files() {
+        assert!(matches!(
+            MachineSaveState::read(b"NOTASAVESTATE"),
+            Err(SaveStateError::BadMagic)
+        ));
+
+        let mut data = test_state().write().unwrap();
+        data[8..12].copy_from_slice(&(SAVE_STATE_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            MachineSaveState::read(&data),
+            Err(SaveStateError::UnsupportedVersion(_))
+        ));
+
+        let mut data = test_state().write().unwrap();
+        data.truncate(HEADER_LEN + 8);
+        assert!(matches!(MachineSaveState::read(&data), Err(SaveStateError::Corrupt(_))));
+    }
+
+    /// Build a CPU running `code` at CODE_SEG:0000 with interrupts enabled. INTR without a PIC
+    /// takes vector 7, which points to a handler of NOP, IRET at HANDLER_SEG:0000.
+    fn interrupt_cpu(cpu_type: CpuType, code: &[u8]) -> CpuDispatch {
+        let mut cpu = cpu_with_code(cpu_type, code);
+        for (i, byte) in [0x00, 0x00, HANDLER_SEG as u8, (HANDLER_SEG >> 8) as u8]
+            .iter()
+            .enumerate()
+        {
+            cpu.bus_mut().write_u8(7 * 4 + i, *byte, 0).unwrap();
+        }
+        for (i, byte) in [0x90, 0xCF].iter().enumerate() {
+            cpu.bus_mut()
+                .write_u8(((HANDLER_SEG as usize) << 4) + i, *byte, 0)
+                .unwrap();
+        }
+        cpu.set_register16(Register16::SS, 0x3000);
+        cpu.set_register16(Register16::SP, 0x0100);
+        cpu.set_flags(CPU_FLAG_INT_ENABLE);
+        cpu
+    }
+
+    /// Save `cpu` through the file format and restore it into a fresh CPU of the same type.
+    fn save_and_restore(cpu: &mut CpuDispatch) -> CpuDispatch {
+        let state = MachineSaveState {
+            machine_type: MachineType::Ibm5160,
+            cpu_type: cpu.get_type(),
+            cpu: CpuSaveState::capture(cpu),
+            devices: cpu.bus().save_device_state(),
+            media: Vec::new(),
+            memory: cpu.bus().memory_snapshot(),
+        };
+        let state = MachineSaveState::read(&state.write().unwrap()).unwrap();
+
+        let mut restored = cpu_with_code(state.cpu_type, &[]);
+        assert!(restored.bus_mut().restore_memory(&state.memory));
+        restored.bus_mut().restore_device_state(state.devices);
+        state.cpu.apply(&mut restored);
+        restored
+    }
+
+    #[test]
+    fn save_state_resumes_inside_interrupt_handler() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // NOP, NOP, NOP
+            let mut cpu = interrupt_cpu(cpu_type, &[0x90, 0x90, 0x90]);
+            cpu.set_intr(true);
+            cpu.run_until_interrupt(1000).unwrap();
+            cpu.set_intr(false);
+            assert_eq!(cpu.get_register16(Register16::CS), HANDLER_SEG);
+
+            let mut restored = save_and_restore(&mut cpu);
+            assert_eq!(restored.get_register16(Register16::CS), HANDLER_SEG);
+            assert_eq!(restored.exec_state(), cpu.exec_state());
+
+            // NOP, IRET returns to the interrupted instruction with interrupts enabled again.
+            step(&mut restored);
+            step(&mut restored);
+            assert_eq!(restored.get_register16(Register16::CS), CODE_SEG);
+            assert_eq!(restored.get_ip(), 0x0001);
+            assert_ne!(restored.get_flags() & CPU_FLAG_INT_ENABLE, 0);
+        }
+    }
+
+    #[test]
+    fn save_state_resumes_halted_cpu() {
+        for cpu_type in [CpuType::Intel8088, CpuType::NecV20] {
+            // HLT, NOP
+            let mut cpu = interrupt_cpu(cpu_type, &[0xF4, 0x90]);
+            step(&mut cpu);
+            assert!(cpu.exec_state().halted);
+
+            let mut restored = save_and_restore(&mut cpu);
+            assert!(restored.exec_state().halted);
+
+            // The restored CPU stays halted until INTR wakes it.
+            step(&mut restored);
+            assert!(restored.exec_state().halted);
+            assert_eq!(restored.get_register16(Register16::CS), CODE_SEG);
+
+            restored.set_intr(true);
+            assert_eq!(
+                restored.run_until_interrupt(1000).unwrap(),
+                CpuAddress::Segmented(CODE_SEG, 0x0001)
+            );
+            assert!(!restored.exec_state().halted);
+        }
+    }
+}
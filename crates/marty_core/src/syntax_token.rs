@@ -30,6 +30,7 @@
     including disassembly and memory views. A corresponding egui control
     TokenListView can use these tokens to format output with syntax coloring.
 */
+use crate::cpu_common::CpuAddress;
 use std::fmt;
 
 pub const TOKEN_MAX_AGE: u8 = 255;
@@ -67,7 +68,10 @@ pub enum SyntaxToken {
     StateMemoryAddressSeg16(u16, u16, String, u8),
     MemoryAddressFlat(u32, String),
     MemoryByteHexValue(u32, u8, String, bool, u8),
+    // Word at the address of its first byte, with its value in the chosen byte order.
+    MemoryWordHexValue(u32, u16, String, u8),
     MemoryByteAsciiValue(u32, u8, String, u8),
+    MemoryByteUnpopulated(u32, String),
 
     // Disassembly tokens
     ErrorText(String),
@@ -84,6 +88,14 @@ pub enum SyntaxToken {
     HexValue(String),
     Register(String),
     Displacement(String),
+    // Immediate operand with its (sign-extended) value.
+    Immediate(u32, String),
+    // Resolved symbol name for the given flat address.
+    Symbol(u32, String),
+    // Unnamed jump or call target.
+    BranchTarget(CpuAddress, String),
+    // Inline annotation, displayed after the instruction.
+    Comment(String),
 
     Formatter(SyntaxFormatType),
 }
@@ -104,7 +116,9 @@ impl fmt::Display for SyntaxToken {
             SyntaxToken::StateMemoryAddressSeg16(seg, off, ..) => write!(f, "{:04X}:{:04X}", seg, off),
             SyntaxToken::MemoryAddressFlat(addr, _) => write!(f, "{:05X}", addr),
             SyntaxToken::MemoryByteHexValue(_, val, ..) => write!(f, "{:02}", val),
+            SyntaxToken::MemoryWordHexValue(_, val, ..) => write!(f, "{:04X}", val),
             SyntaxToken::MemoryByteAsciiValue(_, val, ..) => write!(f, "{:02}", val),
+            SyntaxToken::MemoryByteUnpopulated(_, s) => write!(f, "{}", s),
             SyntaxToken::ErrorText(s) => write!(f, "{}", s),
             SyntaxToken::InstructionBytes(bytes) => write!(f, "{}", bytes),
             SyntaxToken::Prefix(prefix) => write!(f, "{}", prefix),
@@ -119,6 +133,10 @@ impl fmt::Display for SyntaxToken {
             SyntaxToken::HexValue(value) => write!(f, "{}", value),
             SyntaxToken::Register(register) => write!(f, "{}", register),
             SyntaxToken::Displacement(displacement) => write!(f, "{}", displacement),
+            SyntaxToken::Immediate(_, imm) => write!(f, "{}", imm),
+            SyntaxToken::Symbol(_, symbol) => write!(f, "{}", symbol),
+            SyntaxToken::BranchTarget(_, target) => write!(f, "{}", target),
+            SyntaxToken::Comment(comment) => write!(f, " ; {}", comment),
 
             SyntaxToken::Formatter(fmt_type) => match fmt_type {
                 SyntaxFormatType::HighlightLine(_) => write!(f, ">>> "),
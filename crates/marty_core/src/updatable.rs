@@ -36,6 +36,7 @@
     Updatable as a visual representation of how 'fresh' the data is.
 
 */
+use serde::{Deserialize, Serialize};
 #[rustfmt::skip]
 use std::ops::{Deref, DerefMut};
 
@@ -44,7 +45,7 @@ use std::ops::{Deref, DerefMut};
 /// DirtyAging adds a u8 frame age parameter.
 /// Aging8 has a u8 frame age parameter.
 #[allow(dead_code)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Updatable<T> {
     Dirty(T, bool),
     DirtyAging(T, bool, u8),
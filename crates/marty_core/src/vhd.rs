@@ -32,17 +32,20 @@
 
 use core::fmt::Display;
 use std::{
+    collections::BTreeMap,
     error::Error,
     ffi::OsString,
     fs,
     fs::File,
     io::{Read, Seek, SeekFrom, Write},
+    path::Path,
     str,
 };
 
 pub const SECTOR_SIZE: usize = 512;
 
 use anyhow::{bail, Result};
+use serde_derive::Deserialize;
 use uuid::Uuid;
 
 use crate::bytebuf::{ByteBuf, ByteBufWriter};
@@ -60,6 +63,18 @@ pub const VHD_DATA_OFFSET: u64 = 0xFFFFFFFFFFFFFFFF;
 pub const VHD_FEATURE_RESERVED: u32 = 0x02;
 pub const VHD_CHECKSUM_OFFSET: usize = 64;
 pub const VHD_DISK_TYPE: u32 = 0x02;
+pub const VHD_DISK_TYPE_DIFFERENCING: u32 = 0x04;
+
+pub const VHD_DYNAMIC_HEADER_LEN: usize = 1024;
+pub const VHD_DYNAMIC_HEADER_CHECKSUM_OFFSET: usize = 36;
+pub const VHD_DEFAULT_BLOCK_SIZE: u32 = 0x200000;
+pub const VHD_BAT_UNUSED: u32 = 0xFFFFFFFF;
+
+pub const VHD_OVERLAY_MAGIC: &[u8; 8] = b"MARTYCOW";
+pub const VHD_OVERLAY_VERSION: u32 = 1;
+pub const VHD_OVERLAY_HEADER_LEN: usize = 32;
+/// Each overlay record is a little-endian u64 LBA followed by one sector of data.
+pub const VHD_OVERLAY_RECORD_LEN: usize = 8 + VHD_SECTOR_SIZE;
 
 // TODO: Refactor this with thiserror
 #[derive(Debug)]
@@ -71,6 +86,12 @@ pub enum VirtualHardDiskError {
     InvalidType,
     InvalidSeek,
     WriteFailure,
+    WriteProtected,
+    InvalidOverlay,
+    StaleOverlay,
+    InvalidDynamicHeader,
+    ParentMismatch,
+    InvalidBat,
 }
 impl Error for VirtualHardDiskError {}
 impl Display for VirtualHardDiskError {
@@ -94,7 +115,359 @@ impl Display for VirtualHardDiskError {
             VirtualHardDiskError::WriteFailure => {
                 write!(f, "An error occurred while writing to the VHD file.")
             }
+            VirtualHardDiskError::WriteProtected => write!(f, "The VHD is mounted read-only."),
+            VirtualHardDiskError::InvalidOverlay => {
+                write!(
+                    f,
+                    "The copy-on-write overlay file is invalid or an unsupported version."
+                )
+            }
+            VirtualHardDiskError::StaleOverlay => write!(
+                f,
+                "The copy-on-write overlay was created for a different version of the base image."
+            ),
+            VirtualHardDiskError::InvalidDynamicHeader => {
+                write!(
+                    f,
+                    "The VHD dynamic disk header was invalid or contained an invalid value."
+                )
+            }
+            VirtualHardDiskError::InvalidBat => {
+                write!(
+                    f,
+                    "The VHD block allocation table contains invalid or overlapping entries."
+                )
+            }
+            VirtualHardDiskError::ParentMismatch => write!(
+                f,
+                "The parent VHD does not match the parent recorded in the differencing VHD."
+            ),
+        }
+    }
+}
+
+/// How a VHD is mounted.
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum VhdMountMode {
+    #[default]
+    ReadWrite,
+    /// Writes fail, and the controller reports a write fault.
+    ReadOnly,
+    /// Writes go to a [VhdOverlay], leaving the base image untouched.
+    CopyOnWrite,
+}
+
+impl Display for VhdMountMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VhdMountMode::ReadWrite => write!(f, "Read/Write"),
+            VhdMountMode::ReadOnly => write!(f, "Read Only"),
+            VhdMountMode::CopyOnWrite => write!(f, "Copy on Write"),
+        }
+    }
+}
+
+/// A sparse copy-on-write overlay for a [VirtualHardDisk].
+///
+/// The overlay file starts with a header holding the MD5 hash of the base image it was created
+/// against, followed by one record per written sector. Rewriting a sector updates its record in
+/// place, so the file only grows with the number of unique sectors written.
+pub struct VhdOverlay {
+    file: Box<dyn VhdIO>,
+    base_hash: [u8; 16],
+    // Map of LBA to the file offset of that sector's data.
+    index: BTreeMap<u64, u64>,
+}
+
+impl VhdOverlay {
+    /// Create a new, empty overlay for a base image with the specified hash.
+    pub fn create(mut file: Box<dyn VhdIO>, base_hash: [u8; 16]) -> Result<VhdOverlay, anyhow::Error> {
+        let mut header = [0u8; VHD_OVERLAY_HEADER_LEN];
+        header[0..8].copy_from_slice(VHD_OVERLAY_MAGIC);
+        header[8..12].copy_from_slice(&VHD_OVERLAY_VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(VHD_SECTOR_SIZE as u32).to_le_bytes());
+        header[16..32].copy_from_slice(&base_hash);
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)?;
+        file.flush()?;
+
+        Ok(VhdOverlay {
+            file,
+            base_hash,
+            index: BTreeMap::new(),
+        })
+    }
+
+    /// Open an existing overlay, refusing it if it was created against a base image with a
+    /// different hash.
+    pub fn open(mut file: Box<dyn VhdIO>, base_hash: [u8; 16]) -> Result<VhdOverlay, anyhow::Error> {
+        let file_len = file.seek(SeekFrom::End(0))?;
+        if file_len < VHD_OVERLAY_HEADER_LEN as u64 {
+            bail!(VirtualHardDiskError::InvalidOverlay);
+        }
+
+        let mut header = [0u8; VHD_OVERLAY_HEADER_LEN];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+
+        let version = u32::from_le_bytes(header[8..12].try_into()?);
+        let sector_size = u32::from_le_bytes(header[12..16].try_into()?);
+        if &header[0..8] != VHD_OVERLAY_MAGIC || version != VHD_OVERLAY_VERSION || sector_size != VHD_SECTOR_SIZE as u32
+        {
+            bail!(VirtualHardDiskError::InvalidOverlay);
+        }
+        if header[16..32] != base_hash {
+            bail!(VirtualHardDiskError::StaleOverlay);
+        }
+
+        // Index the sector records. A truncated trailing record is ignored.
+        let mut index = BTreeMap::new();
+        let mut record_offset = VHD_OVERLAY_HEADER_LEN as u64;
+        let mut lba_buf = [0u8; 8];
+        while record_offset + VHD_OVERLAY_RECORD_LEN as u64 <= file_len {
+            file.seek(SeekFrom::Start(record_offset))?;
+            file.read_exact(&mut lba_buf)?;
+            index.insert(u64::from_le_bytes(lba_buf), record_offset + 8);
+            record_offset += VHD_OVERLAY_RECORD_LEN as u64;
+        }
+
+        Ok(VhdOverlay { file, base_hash, index })
+    }
+
+    pub fn base_hash(&self) -> [u8; 16] {
+        self.base_hash
+    }
+
+    /// Return the number of sectors held in the overlay.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Read the specified sector into `buf`. Returns false if the overlay doesn't contain it.
+    fn read_sector(&mut self, lba: u64, buf: &mut [u8]) -> Result<bool, anyhow::Error> {
+        match self.index.get(&lba) {
+            Some(&offset) => {
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.read_exact(&mut buf[..VHD_SECTOR_SIZE])?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn write_sector(&mut self, lba: u64, buf: &[u8]) -> Result<(), anyhow::Error> {
+        match self.index.get(&lba) {
+            Some(&offset) => {
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.write_all(&buf[..VHD_SECTOR_SIZE])?;
+            }
+            None => {
+                let record_offset = self.file.seek(SeekFrom::End(0))?;
+                self.file.write_all(&lba.to_le_bytes())?;
+                self.file.write_all(&buf[..VHD_SECTOR_SIZE])?;
+                self.index.insert(lba, record_offset + 8);
+            }
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// The block allocation state of a differencing VHD.
+///
+/// The image is divided into blocks, each allocated on first write. An allocated block begins with
+/// a sector bitmap marking which of its sectors are present in the differencing disk; all other
+/// sectors are read from the parent.
+struct VhdDelta {
+    block_size: u32,
+    bitmap_len: u64,
+    bat_offset: u64,
+    bat: Vec<u32>,
+    parent: Box<VirtualHardDisk>,
+}
+
+impl VhdDelta {
+    /// Return the BAT index and the sector within the block for the specified LBA.
+    fn locate(&self, lba: u64) -> (usize, u64) {
+        let sectors_per_block = self.block_size as u64 / VHD_SECTOR_SIZE as u64;
+        ((lba / sectors_per_block) as usize, lba % sectors_per_block)
+    }
+
+    /// Return the file offset of the specified sector's data, if the differencing disk holds it.
+    fn sector_offset(&self, file: &mut dyn VhdIO, lba: u64) -> Result<Option<u64>, anyhow::Error> {
+        let (block, block_sector) = self.locate(lba);
+        let entry = match self.bat.get(block) {
+            Some(&entry) if entry != VHD_BAT_UNUSED => entry,
+            _ => return Ok(None),
+        };
+
+        let block_offset = entry as u64 * VHD_SECTOR_SIZE as u64;
+        let mut bitmap_byte = [0u8; 1];
+        file.seek(SeekFrom::Start(block_offset + block_sector / 8))?;
+        file.read_exact(&mut bitmap_byte)?;
+
+        // Bitmaps are stored most significant bit first.
+        if bitmap_byte[0] & (0x80 >> (block_sector % 8)) != 0 {
+            Ok(Some(
+                block_offset + self.bitmap_len + block_sector * VHD_SECTOR_SIZE as u64,
+            ))
+        }
+        else {
+            Ok(None)
+        }
+    }
+
+    fn read_sector(&mut self, file: &mut dyn VhdIO, lba: u64, buf: &mut [u8]) -> Result<(), anyhow::Error> {
+        match self.sector_offset(file, lba)? {
+            Some(offset) => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(buf)?;
+                Ok(())
+            }
+            None => self.parent.read_image_sector(lba, buf),
+        }
+    }
+
+    fn write_sector(&mut self, file: &mut dyn VhdIO, lba: u64, buf: &[u8]) -> Result<(), anyhow::Error> {
+        let (block, block_sector) = self.locate(lba);
+        let entry = match self.bat.get(block) {
+            Some(&VHD_BAT_UNUSED) => self.allocate_block(file, block)?,
+            Some(&entry) => entry,
+            None => bail!(VirtualHardDiskError::InvalidSeek),
+        };
+
+        let block_offset = entry as u64 * VHD_SECTOR_SIZE as u64;
+        file.seek(SeekFrom::Start(
+            block_offset + self.bitmap_len + block_sector * VHD_SECTOR_SIZE as u64,
+        ))?;
+        file.write_all(&buf[..VHD_SECTOR_SIZE])?;
+
+        let mut bitmap_byte = [0u8; 1];
+        file.seek(SeekFrom::Start(block_offset + block_sector / 8))?;
+        file.read_exact(&mut bitmap_byte)?;
+        bitmap_byte[0] |= 0x80 >> (block_sector % 8);
+        file.seek(SeekFrom::Start(block_offset + block_sector / 8))?;
+        file.write_all(&bitmap_byte)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Allocate a new, empty block where the footer currently is, and move the footer after it.
+    /// Returns the new BAT entry.
+    fn allocate_block(&mut self, file: &mut dyn VhdIO, block: usize) -> Result<u32, anyhow::Error> {
+        let mut footer_buf = vec![0u8; VHD_FOOTER_LEN];
+        let block_offset = file.seek(SeekFrom::End(-(VHD_FOOTER_LEN as i64)))?;
+        file.read_exact(&mut footer_buf)?;
+        if block_offset % VHD_SECTOR_SIZE as u64 != 0 {
+            bail!(VirtualHardDiskError::InvalidLength);
+        }
+
+        let empty_block = vec![0u8; self.bitmap_len as usize + self.block_size as usize];
+        file.seek(SeekFrom::Start(block_offset))?;
+        file.write_all(&empty_block)?;
+        file.write_all(&footer_buf)?;
+
+        let entry = (block_offset / VHD_SECTOR_SIZE as u64) as u32;
+        file.seek(SeekFrom::Start(self.bat_offset + block as u64 * 4))?;
+        file.write_all(&entry.to_be_bytes())?;
+        self.bat[block] = entry;
+        Ok(entry)
+    }
+}
+
+/// The fields of a dynamic disk header that we make use of.
+struct VHDDynamicHeader {
+    table_offset: u64,
+    max_table_entries: u32,
+    block_size: u32,
+    parent_uuid: Uuid,
+}
+
+impl VHDDynamicHeader {
+    /// Write a dynamic disk header for a differencing disk into the specified buffer, which should
+    /// be VHD_DYNAMIC_HEADER_LEN bytes long.
+    fn make_header_bytes(buf: &mut [u8], header: &VHDDynamicHeader, parent_name: &str) {
+        {
+            let mut bytebuf = ByteBufWriter::from_slice(buf);
+            bytebuf.write_bytes("cxsparse".as_bytes(), 8).unwrap();
+            bytebuf.write_u64_be(VHD_DATA_OFFSET).unwrap();
+            bytebuf.write_u64_be(header.table_offset).unwrap();
+            bytebuf.write_u32_be(VHD_VERSION).unwrap();
+            bytebuf.write_u32_be(header.max_table_entries).unwrap();
+            bytebuf.write_u32_be(header.block_size).unwrap();
+            bytebuf.write_u32_be(0).unwrap(); // Checksum calculated later
+            bytebuf.write_bytes(&header.parent_uuid.into_bytes(), 16).unwrap();
+            bytebuf.write_u32_be(0).unwrap(); // Parent timestamp
+            bytebuf.write_u32_be(0).unwrap(); // Reserved
+
+            // The parent name is stored as UTF-16BE, up to 256 characters.
+            for c in parent_name.encode_utf16().take(256) {
+                bytebuf.write_u16_be(c).unwrap();
+            }
+            // No parent locators are written - the parent path is always supplied explicitly.
+        }
+        let checksum = VHDDynamicHeader::calculate_header_checksum(buf);
+
+        let mut bytebuf = ByteBufWriter::from_slice(buf);
+        bytebuf.seek(VHD_DYNAMIC_HEADER_CHECKSUM_OFFSET).unwrap();
+        bytebuf.write_u32_be(checksum).unwrap();
+    }
+
+    fn parse_header(buf: &[u8]) -> Result<VHDDynamicHeader, anyhow::Error> {
+        let mut bytebuf = ByteBuf::from_slice(buf);
+
+        let mut cookie = [0u8; 8];
+        bytebuf.read_bytes(&mut cookie, 8)?;
+        if cookie != "cxsparse".as_bytes() {
+            bail!(VirtualHardDiskError::InvalidDynamicHeader);
+        }
+
+        let _data_offset = bytebuf.read_u64_be()?;
+        let table_offset = bytebuf.read_u64_be()?;
+
+        if bytebuf.read_u32_be()? != VHD_VERSION {
+            bail!(VirtualHardDiskError::InvalidVersion);
+        }
+
+        let max_table_entries = bytebuf.read_u32_be()?;
+        let block_size = bytebuf.read_u32_be()?;
+        if block_size == 0 || block_size % VHD_SECTOR_SIZE as u32 != 0 {
+            bail!(VirtualHardDiskError::InvalidDynamicHeader);
+        }
+
+        let checksum = bytebuf.read_u32_be()?;
+        if checksum != VHDDynamicHeader::calculate_header_checksum(buf) {
+            log::warn!("VHD dynamic header checksum incorrect");
+        }
+
+        let mut uuid_buf: [u8; 16] = [0; 16];
+        bytebuf.read_bytes(&mut uuid_buf, 16)?;
+        let parent_uuid = uuid::Builder::from_bytes(uuid_buf).into_uuid();
+        log::info!("VHD parent UUID: {}", parent_uuid);
+
+        Ok(VHDDynamicHeader {
+            table_offset,
+            max_table_entries,
+            block_size,
+            parent_uuid,
+        })
+    }
+
+    fn calculate_header_checksum(buf: &[u8]) -> u32 {
+        let mut sum: u32 = 0;
+
+        for (i, byte) in buf[..VHD_DYNAMIC_HEADER_LEN].iter().enumerate() {
+            // Skip checksum field
+            if !(VHD_DYNAMIC_HEADER_CHECKSUM_OFFSET..VHD_DYNAMIC_HEADER_CHECKSUM_OFFSET + 4).contains(&i) {
+                sum = sum.wrapping_add(*byte as u32);
+            }
         }
+        !sum
     }
 }
 
@@ -102,6 +475,8 @@ impl Display for VirtualHardDiskError {
 pub struct VirtualHardDisk {
     vhd_file:  Box<dyn VhdIO>,
     read_only: bool,
+    overlay:   Option<VhdOverlay>,
+    delta:     Option<VhdDelta>,
     footer:    VHDFileFooter,
 
     size: u64,
@@ -114,6 +489,8 @@ pub struct VirtualHardDisk {
     cur_cylinder: u32,
     cur_head: u32,
     cur_sector: u32,
+
+    write_ct: u64,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -177,6 +554,17 @@ impl VHDFileFooter {
         }
     }
 
+    /// Create the footer for a differencing disk of the specified parent. The dynamic disk header
+    /// immediately follows the copy of the footer at the start of the file.
+    pub fn new_differencing(parent: &VHDFileFooter, id: Uuid) -> Self {
+        let mut footer = VHDFileFooter::new(parent.geometry.c, parent.geometry.h, parent.geometry.s, id);
+        footer.offset = VHD_FOOTER_LEN as u64;
+        footer.original_size = parent.current_size;
+        footer.current_size = parent.current_size;
+        footer.disk_type = VHD_DISK_TYPE_DIFFERENCING;
+        footer
+    }
+
     /// Write the fields of a VHD footer into the specified buffer which should be 512 bytes long.
     fn make_vhd_footer_bytes(buf: &mut [u8], footer: VHDFileFooter) {
         {
@@ -184,7 +572,7 @@ impl VHDFileFooter {
             bytebuf.write_bytes("conectix".as_bytes(), 8).unwrap();
             bytebuf.write_u32_be(footer.features).unwrap();
             bytebuf.write_u32_be(footer.version).unwrap();
-            bytebuf.write_u64_be(VHD_DATA_OFFSET).unwrap();
+            bytebuf.write_u64_be(footer.offset).unwrap();
             bytebuf.write_u32_be(footer.timestamp).unwrap();
             bytebuf.write_bytes(&footer.creator_app, 4).unwrap();
             bytebuf.write_u32_be(footer.creator_version).unwrap();
@@ -230,10 +618,9 @@ impl VHDFileFooter {
             bail!(VirtualHardDiskError::InvalidVersion);
         }
 
+        // The data offset is unused for fixed disks, and points to the dynamic disk header otherwise.
+        // We check it against the disk type below.
         footer.offset = bytebuf.read_u64_be()?;
-        if footer.offset != 0xFFFFFFFFFFFFFFFFu64 {
-            bail!(VirtualHardDiskError::InvalidFooter);
-        }
 
         footer.timestamp = bytebuf.read_u32_be()?;
 
@@ -264,8 +651,10 @@ impl VHDFileFooter {
         );
 
         footer.disk_type = bytebuf.read_u32_be()?;
-        if footer.disk_type != 0x02 {
-            bail!(VirtualHardDiskError::InvalidType);
+        match footer.disk_type {
+            VHD_DISK_TYPE if footer.offset != VHD_DATA_OFFSET => bail!(VirtualHardDiskError::InvalidFooter),
+            VHD_DISK_TYPE | VHD_DISK_TYPE_DIFFERENCING => {}
+            _ => bail!(VirtualHardDiskError::InvalidType),
         }
 
         footer.checksum = bytebuf.read_u32_be()?;
@@ -307,25 +696,101 @@ impl VHDFileFooter {
 
 impl VirtualHardDisk {
     pub fn parse(mut vhd_file: Box<dyn VhdIO>, read_only: bool) -> Result<VirtualHardDisk, anyhow::Error> {
-        let vhd_file_size = vhd_file.seek(SeekFrom::End(0))?;
+        let (vhd_file_size, footer) = VirtualHardDisk::read_footer(vhd_file.as_mut())?;
+        if footer.disk_type != VHD_DISK_TYPE {
+            // Differencing disks must be opened with their parent.
+            bail!(VirtualHardDiskError::InvalidType);
+        }
 
-        // Check that the file is long enough to even read the footer in. Such a small file will fail
-        // for other reasons later such as not containing the proper chs
-        if vhd_file_size <= VHD_FOOTER_LEN as u64 {
-            bail!(VirtualHardDiskError::InvalidLength);
+        Ok(VirtualHardDisk {
+            vhd_file,
+            read_only,
+            overlay: None,
+            delta: None,
+
+            size: vhd_file_size,
+            checksum: 0,
+
+            max_cylinders: footer.geometry.c as u32,
+            max_heads: footer.geometry.h as u32,
+            max_sectors: footer.geometry.s as u32,
+
+            cur_cylinder: 0,
+            cur_head: 0,
+            cur_sector: 0,
+
+            write_ct: 0,
+
+            footer,
+        })
+    }
+
+    /// Open a differencing VHD on disk. Sectors not written to the differencing disk are read from
+    /// the parent, which is opened read-only.
+    pub fn open_differencing(delta_path: &Path, parent_path: &Path) -> Result<VirtualHardDisk, anyhow::Error> {
+        let parent_file = File::open(parent_path)?;
+        let parent = VirtualHardDisk::parse(Box::new(parent_file), true)?;
+        let delta_file = File::options().read(true).write(true).open(delta_path)?;
+        VirtualHardDisk::parse_differencing(Box::new(delta_file), parent, false)
+    }
+
+    /// Parse a differencing VHD. The parent must be the disk whose UUID is recorded in the
+    /// differencing disk's header. The parent may itself be a differencing disk.
+    pub fn parse_differencing(
+        mut vhd_file: Box<dyn VhdIO>,
+        parent: VirtualHardDisk,
+        read_only: bool,
+    ) -> Result<VirtualHardDisk, anyhow::Error> {
+        let (vhd_file_size, footer) = VirtualHardDisk::read_footer(vhd_file.as_mut())?;
+        if footer.disk_type != VHD_DISK_TYPE_DIFFERENCING {
+            bail!(VirtualHardDiskError::InvalidType);
         }
 
-        let mut trailer_buf = vec![0u8; VHD_FOOTER_LEN];
+        let mut header_buf = vec![0u8; VHD_DYNAMIC_HEADER_LEN];
+        vhd_file.seek(SeekFrom::Start(footer.offset))?;
+        vhd_file.read_exact(&mut header_buf)?;
+        let header = VHDDynamicHeader::parse_header(&header_buf)?;
+
+        if header.parent_uuid != parent.footer.uuid {
+            log::error!(
+                "Differencing VHD parent UUID {} does not match parent VHD UUID {}",
+                header.parent_uuid,
+                parent.footer.uuid
+            );
+            bail!(VirtualHardDiskError::ParentMismatch);
+        }
+        let (geometry, parent_geometry) = (footer.geometry(), parent.geometry());
+        if (geometry.c, geometry.h, geometry.s) != (parent_geometry.c, parent_geometry.h, parent_geometry.s) {
+            bail!(VirtualHardDiskError::ParentMismatch);
+        }
 
-        vhd_file.seek(SeekFrom::End(-(VHD_FOOTER_LEN as i64)))?;
-        // Read in the entire footer
-        vhd_file.read_exact(&mut trailer_buf)?;
+        let sectors_per_block = header.block_size as u64 / VHD_SECTOR_SIZE as u64;
+        if (header.max_table_entries as u64) * sectors_per_block * (VHD_SECTOR_SIZE as u64) < footer.current_size {
+            bail!(VirtualHardDiskError::InvalidDynamicHeader);
+        }
+
+        let mut bat_buf = vec![0u8; header.max_table_entries as usize * 4];
+        vhd_file.seek(SeekFrom::Start(header.table_offset))?;
+        vhd_file.read_exact(&mut bat_buf)?;
+        let bat = bat_buf
+            .chunks_exact(4)
+            .map(|entry| u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]))
+            .collect();
 
-        let footer = VHDFileFooter::parse_vhd_footer(&mut trailer_buf)?;
+        // The sector bitmap is padded to a sector boundary.
+        let bitmap_len = sectors_per_block.div_ceil(8).next_multiple_of(VHD_SECTOR_SIZE as u64);
 
         Ok(VirtualHardDisk {
             vhd_file,
             read_only,
+            overlay: None,
+            delta: Some(VhdDelta {
+                block_size: header.block_size,
+                bitmap_len,
+                bat_offset: header.table_offset,
+                bat,
+                parent: Box::new(parent),
+            }),
 
             size: vhd_file_size,
             checksum: 0,
@@ -338,10 +803,116 @@ impl VirtualHardDisk {
             cur_head: 0,
             cur_sector: 0,
 
+            write_ct: 0,
+
             footer,
         })
     }
 
+    /// Read and parse the footer at the end of a VHD file. Returns the file size and the footer.
+    fn read_footer(vhd_file: &mut dyn VhdIO) -> Result<(u64, VHDFileFooter), anyhow::Error> {
+        let vhd_file_size = vhd_file.seek(SeekFrom::End(0))?;
+
+        // Check that the file is long enough to even read the footer in. Such a small file will fail
+        // for other reasons later such as not containing the proper chs
+        if vhd_file_size <= VHD_FOOTER_LEN as u64 {
+            bail!(VirtualHardDiskError::InvalidLength);
+        }
+
+        let mut trailer_buf = vec![0u8; VHD_FOOTER_LEN];
+
+        vhd_file.seek(SeekFrom::End(-(VHD_FOOTER_LEN as i64)))?;
+        // Read in the entire footer
+        vhd_file.read_exact(&mut trailer_buf)?;
+
+        let footer = VHDFileFooter::parse_vhd_footer(&trailer_buf)?;
+        Ok((vhd_file_size, footer))
+    }
+
+    /// Check the disk's structure for consistency. For a fixed disk, the data area must hold the
+    /// full geometry. For a differencing disk, every allocated block in the BAT must lie between
+    /// the metadata and the footer without overlapping another block, and its parent is checked
+    /// in turn.
+    pub fn verify_bat(&mut self) -> Result<(), anyhow::Error> {
+        let geometry_len =
+            self.max_cylinders as u64 * self.max_heads as u64 * self.max_sectors as u64 * VHD_SECTOR_SIZE as u64;
+        if self.data_len()? < geometry_len {
+            bail!(VirtualHardDiskError::InvalidLength);
+        }
+
+        let file_len = self.size()?;
+        let Some(delta) = &mut self.delta
+        else {
+            return Ok(());
+        };
+
+        // Blocks must follow the dynamic disk header and the BAT.
+        let metadata_end =
+            (self.footer.offset + VHD_DYNAMIC_HEADER_LEN as u64).max(delta.bat_offset + delta.bat.len() as u64 * 4);
+        let block_len = delta.bitmap_len + delta.block_size as u64;
+        let mut block_offsets: Vec<u64> = delta
+            .bat
+            .iter()
+            .filter(|&&entry| entry != VHD_BAT_UNUSED)
+            .map(|&entry| entry as u64 * VHD_SECTOR_SIZE as u64)
+            .collect();
+        block_offsets.sort_unstable();
+
+        let mut next_free = metadata_end;
+        for offset in block_offsets {
+            if offset < next_free || offset + block_len > file_len - VHD_FOOTER_LEN as u64 {
+                log::error!("verify_bat(): invalid block at offset {:X}", offset);
+                bail!(VirtualHardDiskError::InvalidBat);
+            }
+            next_free = offset + block_len;
+        }
+
+        delta.parent.verify_bat()
+    }
+
+    /// Return the parent of a differencing disk.
+    pub fn parent(&self) -> Option<&VirtualHardDisk> {
+        self.delta.as_ref().map(|delta| delta.parent.as_ref())
+    }
+
+    pub fn is_differencing(&self) -> bool {
+        self.delta.is_some()
+    }
+
+    /// Return the size of the disk's data area in bytes.
+    fn data_len(&mut self) -> Result<u64, anyhow::Error> {
+        if self.delta.is_some() {
+            Ok(self.footer.current_size)
+        }
+        else {
+            Ok(self.size()? - VHD_FOOTER_LEN as u64)
+        }
+    }
+
+    /// Read a sector from the image itself, bypassing any overlay.
+    fn read_image_sector(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), anyhow::Error> {
+        if let Some(delta) = &mut self.delta {
+            return delta.read_sector(self.vhd_file.as_mut(), lba, buf);
+        }
+        self.vhd_file.seek(SeekFrom::Start(lba * SECTOR_SIZE as u64))?;
+        self.vhd_file.read_exact(buf)?;
+        Ok(())
+    }
+
+    /// Write a sector to the image itself, bypassing any overlay.
+    fn write_image_sector(&mut self, lba: u64, buf: &[u8]) -> Result<(), anyhow::Error> {
+        if let Some(delta) = &mut self.delta {
+            return delta.write_sector(self.vhd_file.as_mut(), lba, buf);
+        }
+        self.vhd_file.seek(SeekFrom::Start(lba * SECTOR_SIZE as u64))?;
+
+        let write_len = self.vhd_file.write(buf)?;
+        if write_len != VHD_SECTOR_SIZE {
+            bail!(VirtualHardDiskError::WriteFailure);
+        }
+        Ok(())
+    }
+
     pub fn size(&mut self) -> Result<u64, anyhow::Error> {
         // Get the size of the VHD reader, restore the stream position after
         let pos = self.vhd_file.stream_position()?;
@@ -362,12 +933,18 @@ impl VirtualHardDisk {
 
     pub fn read_sector(&mut self, buf: &mut [u8], cylinder: u16, head: u8, sector: u8) -> Result<(), anyhow::Error> {
         let read_offset = self.get_chs_offset(cylinder, head, sector);
-        if read_offset > self.size()? - VHD_FOOTER_LEN as u64 - VHD_SECTOR_SIZE as u64 {
+        if read_offset + VHD_SECTOR_SIZE as u64 > self.data_len()? {
             // Read requested past last sector in file
             bail!(VirtualHardDiskError::InvalidSeek);
         }
-        self.vhd_file.seek(SeekFrom::Start(read_offset as u64))?;
-        self.vhd_file.read_exact(buf)?;
+
+        // Sectors written to the overlay shadow the base image.
+        if let Some(overlay) = &mut self.overlay {
+            if overlay.read_sector(read_offset / SECTOR_SIZE as u64, buf)? {
+                return Ok(());
+            }
+        }
+        self.read_image_sector(read_offset / SECTOR_SIZE as u64, buf)?;
 
         //log::debug!("Read sector from VHD at offset: {} read buf: {:X?}", read_offset, buf);
         Ok(())
@@ -375,24 +952,98 @@ impl VirtualHardDisk {
 
     pub fn write_sector(&mut self, buf: &[u8], cylinder: u16, head: u8, sector: u8) -> Result<(), anyhow::Error> {
         let write_offset = self.get_chs_offset(cylinder, head, sector);
-        if write_offset > self.size()? - VHD_FOOTER_LEN as u64 - VHD_SECTOR_SIZE as u64 {
+        if write_offset + VHD_SECTOR_SIZE as u64 > self.data_len()? {
             // Write requested past last sector in file
             bail!(VirtualHardDiskError::InvalidSeek);
         }
 
-        self.vhd_file.seek(SeekFrom::Start(write_offset))?;
-
-        let write_len = self.vhd_file.write(buf)?;
-        if write_len != VHD_SECTOR_SIZE {
-            bail!(VirtualHardDiskError::WriteFailure);
+        if let Some(overlay) = &mut self.overlay {
+            overlay.write_sector(write_offset / SECTOR_SIZE as u64, buf)?;
         }
-
+        else if self.read_only {
+            bail!(VirtualHardDiskError::WriteProtected);
+        }
+        else {
+            self.write_image_sector(write_offset / SECTOR_SIZE as u64, buf)?;
+        }
+        self.write_ct += 1;
         Ok(())
     }
 
+    /// Return the number of sectors written by the guest since the disk was mounted.
+    pub fn write_ct(&self) -> u64 {
+        self.write_ct
+    }
+
     pub fn geometry(&self) -> VHDGeometry {
         self.footer.geometry()
     }
+
+    pub fn mount_mode(&self) -> VhdMountMode {
+        if self.overlay.is_some() {
+            VhdMountMode::CopyOnWrite
+        }
+        else if self.read_only {
+            VhdMountMode::ReadOnly
+        }
+        else {
+            VhdMountMode::ReadWrite
+        }
+    }
+
+    /// Calculate the MD5 hash of the base image, used to detect stale overlays.
+    pub fn content_hash(&mut self) -> Result<[u8; 16], anyhow::Error> {
+        let mut context = md5::Context::new();
+        let mut buf = vec![0u8; 64 * 1024];
+
+        self.vhd_file.seek(SeekFrom::Start(0))?;
+        loop {
+            let read_len = self.vhd_file.read(&mut buf)?;
+            if read_len == 0 {
+                break;
+            }
+            context.consume(&buf[..read_len]);
+        }
+        Ok(context.compute().0)
+    }
+
+    /// Attach a copy-on-write overlay. All subsequent writes go to the overlay instead of the
+    /// base image. Returns the previously attached overlay, if any.
+    pub fn attach_overlay(&mut self, overlay: VhdOverlay) -> Option<VhdOverlay> {
+        self.overlay.replace(overlay)
+    }
+
+    pub fn detach_overlay(&mut self) -> Option<VhdOverlay> {
+        self.overlay.take()
+    }
+
+    pub fn overlay(&self) -> Option<&VhdOverlay> {
+        self.overlay.as_ref()
+    }
+
+    /// Write all sectors held in the overlay back to the base image. The overlay remains attached,
+    /// but no longer matches the base image's hash - the caller should replace it with a new one.
+    /// Returns the number of sectors committed.
+    pub fn commit_overlay(&mut self) -> Result<usize, anyhow::Error> {
+        if self.read_only {
+            bail!(VirtualHardDiskError::WriteProtected);
+        }
+        let Some(mut overlay) = self.overlay.take()
+        else {
+            return Ok(0);
+        };
+
+        let lbas: Vec<u64> = overlay.index.keys().copied().collect();
+        let mut buf = vec![0u8; VHD_SECTOR_SIZE];
+        let result = lbas.iter().try_for_each(|lba| {
+            overlay.read_sector(*lba, &mut buf)?;
+            self.write_image_sector(*lba, &buf)
+        });
+        self.overlay = Some(overlay);
+        result?;
+        self.vhd_file.flush()?;
+        Ok(lbas.len())
+    }
 }
 
 pub fn create_vhd(filename: OsString, c: u16, h: u8, s: u8) -> Result<File, anyhow::Error> {
@@ -428,3 +1079,229 @@ pub fn create_vhd(filename: OsString, c: u16, h: u8, s: u8) -> Result<File, anyh
 
     Ok(vhd_file)
 }
+
+/// Create a new, empty differencing VHD for the parent VHD at `parent_path`. The parent is not
+/// modified, so several differencing disks may share the same parent.
+pub fn create_differencing_vhd(delta_path: &Path, parent_path: &Path) -> Result<File, anyhow::Error> {
+    // Don't overwrite an existing file
+    if fs::metadata(delta_path).is_ok() {
+        log::warn!("Requested VHD file already exists: {:?}", delta_path);
+        bail!(VirtualHardDiskError::FileExists);
+    }
+
+    let parent = VirtualHardDisk::parse(Box::new(File::open(parent_path)?), true)?;
+    let parent_name = parent_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut vhd_file = File::create(delta_path)?;
+    write_differencing_vhd(&mut vhd_file, &parent.footer, &parent_name)?;
+    Ok(vhd_file)
+}
+
+/// Write an empty differencing disk for the parent with the specified footer.
+///
+/// The file consists of a copy of the footer, the dynamic disk header, a BAT with no blocks
+/// allocated, and the footer.
+fn write_differencing_vhd(out: &mut dyn Write, parent: &VHDFileFooter, parent_name: &str) -> Result<(), anyhow::Error> {
+    let max_table_entries = parent.current_size.div_ceil(VHD_DEFAULT_BLOCK_SIZE as u64) as u32;
+    let bat_len = (max_table_entries as usize * 4).next_multiple_of(VHD_SECTOR_SIZE);
+
+    let mut footer_buf = vec![0u8; VHD_FOOTER_LEN];
+    VHDFileFooter::make_vhd_footer_bytes(&mut footer_buf, VHDFileFooter::new_differencing(parent, Uuid::new_v4()));
+
+    let mut header_buf = vec![0u8; VHD_DYNAMIC_HEADER_LEN];
+    VHDDynamicHeader::make_header_bytes(
+        &mut header_buf,
+        &VHDDynamicHeader {
+            table_offset: (VHD_FOOTER_LEN + VHD_DYNAMIC_HEADER_LEN) as u64,
+            max_table_entries,
+            block_size: VHD_DEFAULT_BLOCK_SIZE,
+            parent_uuid: parent.uuid,
+        },
+        parent_name,
+    );
+
+    out.write_all(&footer_buf)?;
+    out.write_all(&header_buf)?;
+    out.write_all(&vec![0xFF; bat_len])?;
+    out.write_all(&footer_buf)?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn make_vhd(c: u16, h: u8, s: u8) -> VirtualHardDisk {
+        make_vhd_with_id(c, h, s, Uuid::nil())
+    }
+
+    fn make_vhd_with_id(c: u16, h: u8, s: u8, id: Uuid) -> VirtualHardDisk {
+        let n_sectors = c as usize * h as usize * s as usize;
+        let mut data = vec![0u8; n_sectors * VHD_SECTOR_SIZE + VHD_FOOTER_LEN];
+        VHDFileFooter::make_vhd_footer_bytes(
+            &mut data[n_sectors * VHD_SECTOR_SIZE..],
+            VHDFileFooter::new(c, h, s, id),
+        );
+        VirtualHardDisk::parse(Box::new(Cursor::new(data)), false).unwrap()
+    }
+
+    fn file_bytes(file: &mut dyn VhdIO) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_differencing_falls_through_to_parent() {
+        let parent_id = Uuid::new_v4();
+        let mut parent = make_vhd_with_id(4, 2, 17, parent_id);
+        let parent_buf = [0x11u8; VHD_SECTOR_SIZE];
+        parent.write_sector(&parent_buf, 0, 1, 0).unwrap();
+        parent.write_sector(&parent_buf, 0, 1, 1).unwrap();
+        let parent_hash = parent.content_hash().unwrap();
+
+        let mut delta_bytes = Vec::new();
+        write_differencing_vhd(&mut delta_bytes, &parent.footer, "parent.vhd").unwrap();
+        let mut delta = VirtualHardDisk::parse_differencing(Box::new(Cursor::new(delta_bytes)), parent, false).unwrap();
+        assert!(delta.is_differencing());
+
+        let delta_buf = [0x22u8; VHD_SECTOR_SIZE];
+        delta.write_sector(&delta_buf, 0, 1, 1).unwrap();
+        delta.write_sector(&delta_buf, 3, 1, 16).unwrap();
+
+        let mut read_buf = [0u8; VHD_SECTOR_SIZE];
+        delta.read_sector(&mut read_buf, 0, 1, 0).unwrap();
+        assert_eq!(read_buf, parent_buf);
+        delta.read_sector(&mut read_buf, 0, 1, 1).unwrap();
+        assert_eq!(read_buf, delta_buf);
+        delta.read_sector(&mut read_buf, 3, 1, 16).unwrap();
+        assert_eq!(read_buf, delta_buf);
+        assert!(delta.read_sector(&mut read_buf, 4, 0, 0).is_err());
+
+        // The parent must be untouched.
+        let mut delta_parts = delta.delta.take().unwrap();
+        assert_eq!(delta_parts.parent.content_hash().unwrap(), parent_hash);
+
+        // Reopen the differencing disk from its persisted bytes.
+        let delta_bytes = file_bytes(delta.vhd_file.as_mut());
+        let mut reopened =
+            VirtualHardDisk::parse_differencing(Box::new(Cursor::new(delta_bytes)), *delta_parts.parent, false)
+                .unwrap();
+        reopened.read_sector(&mut read_buf, 0, 1, 1).unwrap();
+        assert_eq!(read_buf, delta_buf);
+        reopened.read_sector(&mut read_buf, 0, 1, 0).unwrap();
+        assert_eq!(read_buf, parent_buf);
+    }
+
+    #[test]
+    fn test_verify_bat() {
+        let mut parent = make_vhd(4, 2, 17);
+        parent.verify_bat().unwrap();
+
+        let mut delta_bytes = Vec::new();
+        write_differencing_vhd(&mut delta_bytes, &parent.footer, "parent.vhd").unwrap();
+        let mut delta = VirtualHardDisk::parse_differencing(Box::new(Cursor::new(delta_bytes)), parent, false).unwrap();
+        delta.verify_bat().unwrap();
+
+        delta.write_sector(&[0x33u8; VHD_SECTOR_SIZE], 2, 0, 5).unwrap();
+        delta.verify_bat().unwrap();
+
+        // Point the allocated block back into the BAT.
+        delta.delta.as_mut().unwrap().bat[0] = 3;
+        let err = delta.verify_bat().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VirtualHardDiskError>(),
+            Some(VirtualHardDiskError::InvalidBat)
+        ));
+    }
+
+    #[test]
+    fn test_differencing_rejects_wrong_parent() {
+        let parent = make_vhd_with_id(4, 2, 17, Uuid::new_v4());
+        let mut delta_bytes = Vec::new();
+        write_differencing_vhd(&mut delta_bytes, &parent.footer, "parent.vhd").unwrap();
+
+        let other = make_vhd_with_id(4, 2, 17, Uuid::new_v4());
+        let err = VirtualHardDisk::parse_differencing(Box::new(Cursor::new(delta_bytes.clone())), other, false)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<VirtualHardDiskError>(),
+            Some(VirtualHardDiskError::ParentMismatch)
+        ));
+
+        // A differencing disk can't be opened without its parent.
+        assert!(VirtualHardDisk::parse(Box::new(Cursor::new(delta_bytes)), false).is_err());
+    }
+
+    #[test]
+    fn test_read_only_rejects_writes() {
+        let mut vhd = make_vhd(4, 2, 17);
+        vhd.read_only = true;
+        let buf = [0xAAu8; VHD_SECTOR_SIZE];
+        let err = vhd.write_sector(&buf, 1, 0, 3).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VirtualHardDiskError>(),
+            Some(VirtualHardDiskError::WriteProtected)
+        ));
+        assert_eq!(vhd.mount_mode(), VhdMountMode::ReadOnly);
+        assert_eq!(vhd.write_ct(), 0);
+    }
+
+    #[test]
+    fn test_overlay_shadows_base_and_reopens() {
+        let mut vhd = make_vhd(4, 2, 17);
+        let base_hash = vhd.content_hash().unwrap();
+        let overlay = VhdOverlay::create(Box::new(Cursor::new(Vec::new())), base_hash).unwrap();
+        vhd.attach_overlay(overlay);
+
+        let buf = [0x55u8; VHD_SECTOR_SIZE];
+        vhd.write_sector(&buf, 1, 0, 3).unwrap();
+        vhd.write_sector(&buf, 1, 0, 3).unwrap();
+        assert_eq!(vhd.write_ct(), 2);
+
+        // The base image must be untouched.
+        assert_eq!(vhd.content_hash().unwrap(), base_hash);
+        let mut read_buf = [0u8; VHD_SECTOR_SIZE];
+        vhd.read_sector(&mut read_buf, 1, 0, 3).unwrap();
+        assert_eq!(read_buf, buf);
+        vhd.read_sector(&mut read_buf, 1, 0, 4).unwrap();
+        assert_eq!(read_buf, [0u8; VHD_SECTOR_SIZE]);
+
+        // Rewriting the same sector must not grow the overlay.
+        let mut overlay = vhd.detach_overlay().unwrap();
+        assert_eq!(overlay.len(), 1);
+        let overlay_len = overlay.file.seek(SeekFrom::End(0)).unwrap();
+        assert_eq!(overlay_len, (VHD_OVERLAY_HEADER_LEN + VHD_OVERLAY_RECORD_LEN) as u64);
+
+        // Reopen the overlay from its persisted bytes.
+        let mut overlay_bytes = Vec::new();
+        overlay.file.seek(SeekFrom::Start(0)).unwrap();
+        overlay.file.read_to_end(&mut overlay_bytes).unwrap();
+        let reopened = VhdOverlay::open(Box::new(Cursor::new(overlay_bytes.clone())), base_hash).unwrap();
+        assert_eq!(reopened.len(), 1);
+
+        // A different base hash must be refused.
+        let err = VhdOverlay::open(Box::new(Cursor::new(overlay_bytes)), [0xFF; 16])
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<VirtualHardDiskError>(),
+            Some(VirtualHardDiskError::StaleOverlay)
+        ));
+
+        // Committing writes the overlay sectors into the base image.
+        vhd.attach_overlay(reopened);
+        assert_eq!(vhd.commit_overlay().unwrap(), 1);
+        vhd.detach_overlay();
+        vhd.read_sector(&mut read_buf, 1, 0, 3).unwrap();
+        assert_eq!(read_buf, buf);
+        assert_ne!(vhd.content_hash().unwrap(), base_hash);
+    }
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use marty_trace_parser::{TraceLine, TraceParser};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = TraceLine::parse(line);
+    }
+    // Exercise the line splitting of the iterator as well, including invalid UTF-8.
+    for _ in TraceParser::new(data) {}
+});
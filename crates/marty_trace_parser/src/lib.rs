@@ -0,0 +1,517 @@
+/*
+    MartyPC
+    https://github.com/dbalsom/martypc
+
+    Copyright 2022-2025 Daniel Balsom
+
+    Permission is hereby granted, free of charge, to any person obtaining a
+    copy of this software and associated documentation files (the “Software”),
+    to deal in the Software without restriction, including without limitation
+    the rights to use, copy, modify, merge, publish, distribute, sublicense,
+    and/or sell copies of the Software, and to permit persons to whom the
+    Software is furnished to do so, subject to the following conditions:
+
+    The above copyright notice and this permission notice shall be included in
+    all copies or substantial portions of the Software.
+
+    THE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+    IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+    FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+    AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+    LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
+    FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+    DEALINGS IN THE SOFTWARE.
+
+    ---------------------------------------------------------------------------
+
+    marty_trace_parser::lib.rs
+
+    Parser for the text cycle trace written by the CPU in CycleText trace
+    mode. Each cycle line has the format produced by cycle_state_string():
+
+    00001234:0002 A:[FFFF0] CS R0. M:R.. I:... |00 01| CODE T1 | CODE T1         |
+        <-r EA | None    | Normal     | F3 [EA5BE0  ] <-q EA | 000: <microcode> | [F000:FFF0] jmp ... (5) ; comment
+
+    The microcode column is only present in traces from the 8088/8086.
+    Lines that are not cycle lines, such as instruction and interrupt
+    messages, are skipped by TraceParser.
+*/
+
+use std::io::{BufRead, Lines};
+
+/// The width of a line of 8088 microcode source, as printed in the trace.
+pub const MICROCODE_SRC_LEN: usize = 34;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    ES,
+    CS,
+    SS,
+    DS,
+}
+
+/// The bus cycle type, as advertised on the S0-S2 status lines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BusStatus {
+    InterruptAck,
+    IoRead,
+    IoWrite,
+    Halt,
+    CodeFetch,
+    MemRead,
+    MemWrite,
+    Passive,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TCycle {
+    Tinit,
+    Ti,
+    T1,
+    T2,
+    T3,
+    Tw,
+    T4,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueueOp {
+    Idle,
+    First,
+    Flush,
+    Subsequent,
+}
+
+/// A data transfer on the bus during a cycle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BusTransfer {
+    None,
+    Read(u8),
+    Write(u8),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DmaState {
+    /// Timer channel #1 count and DRAM refresh cycle number.
+    Idle(u16, u16),
+    Dreq,
+    Hrq,
+    HoldA,
+    /// DMA cycle state S1-S4, stored as 0-3.
+    Operating(u8),
+    End,
+}
+
+/// A bus cycle occupying a slot in the bus pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PipelineSlot {
+    pub status: BusStatus,
+    /// The t-cycle or address cycle of the slot, eg "T2" or "Ts". May be empty.
+    pub cycle:  String,
+}
+
+/// The microcode line executing during a cycle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Microcode {
+    /// The microcode address in hex, or one of "JMP", "RET" or "COR". Empty if no microcode executed.
+    pub line:   String,
+    pub source: String,
+}
+
+/// The instruction decoded on a cycle that read its first byte from the queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceInstruction {
+    pub cs:   u16,
+    pub ip:   u16,
+    pub text: String,
+    pub size: u32,
+}
+
+/// A single cycle of a text cycle trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceLine {
+    pub cycle: u64,
+    pub instr_cycle: u32,
+    pub ale: bool,
+    pub address_latch: u32,
+    /// The segment status. Only present in T2 and later.
+    pub segment: Option<Segment>,
+    pub ready: bool,
+    pub wait_states: u32,
+    pub last_wait: bool,
+    pub mrdc: bool,
+    pub amwc: bool,
+    pub mwtc: bool,
+    pub iorc: bool,
+    pub aiowc: bool,
+    pub iowc: bool,
+    pub dma: DmaState,
+    pub bus_status: BusStatus,
+    pub t_cycle: TCycle,
+    pub pl_slots: [Option<PipelineSlot>; 2],
+    pub transfer: BusTransfer,
+    pub bus_pending: String,
+    pub fetch_state: String,
+    pub queue_op: QueueOp,
+    pub queue_len: usize,
+    pub queue_preload: bool,
+    /// The queue contents, including the preload byte if present.
+    pub queue: Vec<u8>,
+    /// The byte read from the queue, if queue_op is First or Subsequent.
+    pub queue_byte: Option<u8>,
+    pub microcode: Option<Microcode>,
+    pub instruction: Option<TraceInstruction>,
+    pub comments: Vec<String>,
+}
+
+impl TraceLine {
+    /// Parse a single line of a cycle trace. Returns None if the line is not a cycle line.
+    pub fn parse(line: &str) -> Option<TraceLine> {
+        let mut columns = line.splitn(9, '|');
+        let mut status = Cursor(columns.next()?);
+        let dma = parse_dma(columns.next()?.trim())?;
+        let mut bus = Cursor(columns.next()?);
+        let slots = columns.next()?;
+        let transfer = parse_transfer(columns.next()?.trim())?;
+        let bus_pending = columns.next()?.trim().to_string();
+        let fetch_state = columns.next()?.trim().to_string();
+        let mut queue = Cursor(columns.next()?);
+        let rest = columns.next()?;
+
+        let cycle = status.take_until(':')?.parse().ok()?;
+        let instr_cycle = status.take_until(' ')?.parse().ok()?;
+        let ale = match status.take(2)? {
+            "A:" => true,
+            "  " => false,
+            _ => return None,
+        };
+        status.expect("[")?;
+        let address_latch = u32::from_str_radix(status.take_until(']')?, 16).ok()?;
+        status.expect(" ")?;
+        let segment = match status.take(2)? {
+            "ES" => Some(Segment::ES),
+            "CS" => Some(Segment::CS),
+            "SS" => Some(Segment::SS),
+            "DS" => Some(Segment::DS),
+            "  " => None,
+            _ => return None,
+        };
+        status.expect(" ")?;
+        let ready = status.flag('R')?;
+        let wait_states = status.take_while(|c| c.is_ascii_digit()).parse().ok()?;
+        let last_wait = status.flag('x')?;
+        status.expect(" M:")?;
+        let (mrdc, amwc, mwtc) = (status.flag('R')?, status.flag('A')?, status.flag('W')?);
+        status.expect(" I:")?;
+        let (iorc, aiowc, iowc) = (status.flag('R')?, status.flag('A')?, status.flag('W')?);
+
+        bus.expect(" ")?;
+        let bus_status = parse_bus_status(bus.take(4)?)?;
+        bus.expect(" ")?;
+        let t_cycle = parse_t_cycle(bus.take(2)?)?;
+
+        let pl_slots = [parse_slot(slots.get(1..8)?)?, parse_slot(slots.get(9..16)?)?];
+
+        queue.expect(" ")?;
+        let queue_op = match queue.take(1)? {
+            " " => QueueOp::Idle,
+            "F" => QueueOp::First,
+            "E" => QueueOp::Flush,
+            "S" => QueueOp::Subsequent,
+            _ => return None,
+        };
+        let queue_len = queue.take_while(|c| c.is_ascii_digit()).parse().ok()?;
+        let queue_preload = queue.flag('*')?;
+        queue.expect("[")?;
+        let queue_bytes = parse_hex_bytes(queue.take_until(']')?.trim_end())?;
+        queue.expect(" ")?;
+        let queue_byte = match queue.0.trim() {
+            "" => None,
+            read => Some(u8::from_str_radix(read.strip_prefix("<-q ")?, 16).ok()?),
+        };
+
+        let (microcode, rest) = parse_microcode(rest)?;
+        let (instruction, comments) = match rest.split_once("; ") {
+            Some((instruction, comments)) => (instruction, comments.split("; ").map(String::from).collect()),
+            None => (rest, Vec::new()),
+        };
+        let instruction = match instruction.trim() {
+            "" => None,
+            instruction => Some(parse_instruction(instruction)?),
+        };
+
+        Some(TraceLine {
+            cycle,
+            instr_cycle,
+            ale,
+            address_latch,
+            segment,
+            ready,
+            wait_states,
+            last_wait,
+            mrdc,
+            amwc,
+            mwtc,
+            iorc,
+            aiowc,
+            iowc,
+            dma,
+            bus_status,
+            t_cycle,
+            pl_slots,
+            transfer,
+            bus_pending,
+            fetch_state,
+            queue_op,
+            queue_len,
+            queue_preload,
+            queue: queue_bytes,
+            queue_byte,
+            microcode,
+            instruction,
+            comments,
+        })
+    }
+}
+
+/// An iterator over the cycle lines of a trace. Lines that do not parse as cycle lines are
+/// skipped. Iteration ends at the end of the input or on the first read error.
+pub struct TraceParser<R: BufRead> {
+    lines: Lines<R>,
+}
+
+impl<R: BufRead> TraceParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self { lines: reader.lines() }
+    }
+}
+
+impl<R: BufRead> Iterator for TraceParser<R> {
+    type Item = TraceLine;
+
+    fn next(&mut self) -> Option<TraceLine> {
+        loop {
+            let line = self.lines.next()?.ok()?;
+            if let Some(trace_line) = TraceLine::parse(&line) {
+                return Some(trace_line);
+            }
+        }
+    }
+}
+
+/// A simple cursor over a column of a trace line. All slicing is checked, so malformed input
+/// returns None rather than panicking.
+struct Cursor<'a>(&'a str);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a str> {
+        let taken = self.0.get(..n)?;
+        self.0 = &self.0[n..];
+        Some(taken)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let end = self.0.find(|c| !f(c)).unwrap_or(self.0.len());
+        let taken = &self.0[..end];
+        self.0 = &self.0[end..];
+        taken
+    }
+
+    /// Take everything up to `delim`, and consume the delimiter.
+    fn take_until(&mut self, delim: char) -> Option<&'a str> {
+        let (taken, rest) = self.0.split_once(delim)?;
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn expect(&mut self, s: &str) -> Option<()> {
+        self.0 = self.0.strip_prefix(s)?;
+        Some(())
+    }
+
+    /// Consume a signal character, which is either `set` or a placeholder ('.' or ' ').
+    fn flag(&mut self, set: char) -> Option<bool> {
+        match self.take(1)? {
+            c if c.starts_with(set) => Some(true),
+            "." | " " => Some(false),
+            _ => None,
+        }
+    }
+}
+
+fn parse_bus_status(s: &str) -> Option<BusStatus> {
+    match s {
+        "INTA" => Some(BusStatus::InterruptAck),
+        "IOR " => Some(BusStatus::IoRead),
+        "IOW " => Some(BusStatus::IoWrite),
+        "HALT" => Some(BusStatus::Halt),
+        "CODE" => Some(BusStatus::CodeFetch),
+        "MEMR" => Some(BusStatus::MemRead),
+        "MEMW" => Some(BusStatus::MemWrite),
+        "PASV" => Some(BusStatus::Passive),
+        _ => None,
+    }
+}
+
+fn parse_t_cycle(s: &str) -> Option<TCycle> {
+    match s {
+        "Tx" => Some(TCycle::Tinit),
+        "Ti" => Some(TCycle::Ti),
+        "T1" => Some(TCycle::T1),
+        "T2" => Some(TCycle::T2),
+        "T3" => Some(TCycle::T3),
+        "Tw" => Some(TCycle::Tw),
+        "T4" => Some(TCycle::T4),
+        _ => None,
+    }
+}
+
+fn parse_dma(s: &str) -> Option<DmaState> {
+    match s {
+        "DREQ" => Some(DmaState::Dreq),
+        "HRQ" => Some(DmaState::Hrq),
+        "HLDA" => Some(DmaState::HoldA),
+        "S1" => Some(DmaState::Operating(0)),
+        "S2" => Some(DmaState::Operating(1)),
+        "S3" => Some(DmaState::Operating(2)),
+        "S4" => Some(DmaState::Operating(3)),
+        "END" => Some(DmaState::End),
+        _ => {
+            let (count, refresh) = s.split_once(' ')?;
+            Some(DmaState::Idle(count.parse().ok()?, refresh.parse().ok()?))
+        }
+    }
+}
+
+/// Parse a pipeline slot column, eg "CODE T1". A blank column is an empty slot.
+fn parse_slot(s: &str) -> Option<Option<PipelineSlot>> {
+    if s.trim().is_empty() {
+        return Some(None);
+    }
+    let (status, cycle) = (s.get(..4)?, s.get(5..)?);
+    Some(Some(PipelineSlot {
+        status: parse_bus_status(status)?,
+        cycle:  cycle.trim().to_string(),
+    }))
+}
+
+/// Parse a data transfer column, eg "<-r EA". A blank column is no transfer.
+fn parse_transfer(s: &str) -> Option<BusTransfer> {
+    if s.is_empty() {
+        Some(BusTransfer::None)
+    }
+    else if let Some(byte) = s.strip_prefix("<-r ") {
+        Some(BusTransfer::Read(u8::from_str_radix(byte, 16).ok()?))
+    }
+    else if let Some(byte) = s.strip_prefix("w-> ") {
+        Some(BusTransfer::Write(u8::from_str_radix(byte, 16).ok()?))
+    }
+    else {
+        None
+    }
+}
+
+fn parse_hex_bytes(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Split the microcode column from the remainder of the line, if present. The microcode source
+/// itself contains a '|', so it is taken by width.
+fn parse_microcode(rest: &str) -> Option<(Option<Microcode>, &str)> {
+    let mut cursor = Cursor(rest);
+    cursor.expect(" ")?;
+    if cursor.0.starts_with('[') || cursor.0.starts_with(';') || cursor.0.trim().is_empty() {
+        return Some((None, cursor.0));
+    }
+    let line = cursor.take(3)?.trim().to_string();
+    cursor.expect(": ")?;
+    let source = cursor.take(MICROCODE_SRC_LEN)?.trim_end().to_string();
+    cursor.expect(" |")?;
+    Some((Some(Microcode { line, source }), cursor.0))
+}
+
+/// Parse an instruction, eg "[F000:FFF0] jmp far F000:E05B (5)".
+fn parse_instruction(s: &str) -> Option<TraceInstruction> {
+    let mut cursor = Cursor(s);
+    cursor.expect("[")?;
+    let cs = u16::from_str_radix(cursor.take_until(':')?, 16).ok()?;
+    let ip = u16::from_str_radix(cursor.take_until(']')?, 16).ok()?;
+    let (text, size) = cursor.0.rsplit_once(" (")?;
+    Some(TraceInstruction {
+        cs,
+        ip,
+        text: text.trim().to_string(),
+        size: size.strip_suffix(')')?.parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FETCH_LINE: &str = "00001234:0002 A:[FFFF0] CS R0. M:R.. I:... |00 01| CODE T1 | CODE T1         \
+        | <-r EA | None    | Normal     | F3 [EA5BE0  ] <-q EA | 000: XA   -> tmpb    | none  WB,NX      \
+        | [F000:FFF0] jmp far F000:E05B (5) ; reset vector; second";
+
+    #[test]
+    fn parse_cycle_line() {
+        let line = TraceLine::parse(FETCH_LINE).unwrap();
+        assert_eq!(line.cycle, 1234);
+        assert_eq!(line.instr_cycle, 2);
+        assert!(line.ale);
+        assert_eq!(line.address_latch, 0xFFFF0);
+        assert_eq!(line.segment, Some(Segment::CS));
+        assert!(line.ready && !line.last_wait);
+        assert_eq!(line.wait_states, 0);
+        assert!(line.mrdc && !line.amwc && !line.mwtc && !line.iorc);
+        assert_eq!(line.dma, DmaState::Idle(0, 1));
+        assert_eq!((line.bus_status, line.t_cycle), (BusStatus::CodeFetch, TCycle::T1));
+        assert_eq!(line.pl_slots[0].as_ref().map(|s| s.status), Some(BusStatus::CodeFetch));
+        assert_eq!(line.pl_slots[1], None);
+        assert_eq!(line.transfer, BusTransfer::Read(0xEA));
+        assert_eq!(line.fetch_state, "Normal");
+        assert_eq!(
+            (line.queue_op, line.queue_len, line.queue_preload),
+            (QueueOp::First, 3, false)
+        );
+        assert_eq!(line.queue, vec![0xEA, 0x5B, 0xE0]);
+        assert_eq!(line.queue_byte, Some(0xEA));
+        assert_eq!(line.microcode.unwrap().source, "XA   -> tmpb    | none  WB,NX");
+        let instruction = line.instruction.unwrap();
+        assert_eq!((instruction.cs, instruction.ip, instruction.size), (0xF000, 0xFFF0, 5));
+        assert_eq!(instruction.text, "jmp far F000:E05B");
+        assert_eq!(line.comments, vec!["reset vector", "second"]);
+    }
+
+    #[test]
+    fn parse_line_without_microcode() {
+        let line = "00000010:0000   [00000]    .2x M:... I:... |HRQ  | PASV Tw |                 \
+            |        | None    | Normal     |  0 [        ]        | ";
+        let line = TraceLine::parse(line).unwrap();
+        assert_eq!(line.segment, None);
+        assert!(!line.ready && line.last_wait);
+        assert_eq!(line.wait_states, 2);
+        assert_eq!(line.dma, DmaState::Hrq);
+        assert_eq!(line.queue_op, QueueOp::Idle);
+        assert!(line.queue.is_empty() && line.queue_byte.is_none());
+        assert!(line.microcode.is_none() && line.instruction.is_none());
+    }
+
+    #[test]
+    fn parser_skips_other_lines() {
+        let trace = format!("INT 10h\n{}\n\n{}", FETCH_LINE, &FETCH_LINE[..40]);
+        let lines: Vec<_> = TraceParser::new(trace.as_bytes()).collect();
+        assert_eq!(lines.len(), 1);
+
+        // Truncations of a valid line must not panic.
+        for i in 0..FETCH_LINE.len() {
+            let _ = TraceLine::parse(&FETCH_LINE[..i]);
+        }
+    }
+}